@@ -1,5 +1,5 @@
 use anyhow::{bail, Result};
-use scylla::transport::errors::QueryError;
+use scylla::transport::errors::{QueryError, TimeoutError};
 use scylla::transport::session::Session;
 use scylla::SessionBuilder;
 use std::env;
@@ -26,7 +26,10 @@ async fn main() -> Result<()> {
 
     match session.await_schema_agreement().await {
         Ok(_schema_version) => println!("Schema is in agreement in time"),
-        Err(QueryError::RequestTimeout(_)) => println!("Schema is NOT in agreement in time"),
+        Err(QueryError::TimeoutError {
+            error: TimeoutError::Request(_),
+            ..
+        }) => println!("Schema is NOT in agreement in time"),
         Err(err) => bail!(err),
     };
     session
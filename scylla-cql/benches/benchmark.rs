@@ -4,7 +4,10 @@ use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 
 use scylla_cql::frame::request::SerializableRequest;
 use scylla_cql::frame::response::result::ColumnType;
-use scylla_cql::frame::{request::query, Compression, SerializedRequest};
+use scylla_cql::frame::{
+    request::{execute, query},
+    Compression, SerializedRequest,
+};
 use scylla_cql::types::serialize::row::SerializedValues;
 
 fn make_query(contents: &str, values: SerializedValues) -> query::Query<'_> {
@@ -56,6 +59,59 @@ fn serialized_request_make_bench(c: &mut Criterion) {
                         query,
                         Some(Compression::Lz4),
                         false,
+                        None,
+                    ));
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("SerializedRequest::make_with_buffer", query_size),
+            &query,
+            |b, query| {
+                let mut buffer = Vec::new();
+                b.iter(|| {
+                    let request = SerializedRequest::make_with_buffer(
+                        std::mem::take(&mut buffer),
+                        query,
+                        Some(Compression::Lz4),
+                        0,
+                        false,
+                        None,
+                    )
+                    .unwrap();
+                    buffer = criterion::black_box(request).into_buffer();
+                })
+            },
+        );
+    }
+}
+
+fn serialized_execute_make_bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("SerializedRequest.Execute");
+    let mut values = SerializedValues::new();
+    values.add_value(&1234, &ColumnType::Int).unwrap();
+    values
+        .add_value(&vec![0xCD_u8; 1024], &ColumnType::Blob)
+        .unwrap();
+    let execute = execute::Execute {
+        id: vec![0xAB; 16].into(),
+        parameters: query::QueryParameters {
+            values: Cow::Owned(values),
+            ..make_query("", SerializedValues::new()).parameters
+        },
+    };
+
+    for (name, compression) in [("none", None), ("lz4", Some(Compression::Lz4))] {
+        group.bench_with_input(
+            BenchmarkId::new("SerializedRequest::make", name),
+            &compression,
+            |b, compression| {
+                b.iter(|| {
+                    let _ = criterion::black_box(SerializedRequest::make(
+                        &execute,
+                        *compression,
+                        false,
+                        None,
                     ));
                 })
             },
@@ -63,5 +119,9 @@ fn serialized_request_make_bench(c: &mut Criterion) {
     }
 }
 
-criterion_group!(benches, serialized_request_make_bench);
+criterion_group!(
+    benches,
+    serialized_request_make_bench,
+    serialized_execute_make_bench
+);
 criterion_main!(benches);
@@ -0,0 +1,198 @@
+//! Splitting the text of CQL statements into tokens.
+//!
+//! The lexer is only as precise as the driver needs it to be: it recognizes whitespace,
+//! comments, string literals, quoted identifiers and words, and treats any other character
+//! as a separate symbol. It is shared by the places of the driver which look into
+//! the text of statements, and is not a part of the public API.
+
+/// Kind of a [`Token`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// A run of whitespace characters.
+    Whitespace,
+    /// A `-- ...` or `// ...` comment, which ends before the newline, or a `/* ... */` comment.
+    Comment,
+    /// A `'...'` string, in which the quote is escaped by doubling it, or a `$$...$$` string.
+    String,
+    /// A `"..."` identifier, in which the quote is escaped by doubling it.
+    QuotedIdentifier,
+    /// A run of alphanumeric characters and underscores, e.g. a keyword, an unquoted
+    /// identifier or (a part of) a number.
+    Word,
+    /// Any other character.
+    Symbol,
+}
+
+/// A token at the start of a piece of CQL text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// Length of the token, in bytes.
+    pub len: usize,
+    /// Whether the string, quoted identifier or `/* ... */` comment is terminated.
+    /// An unterminated one extends to the end of the text.
+    pub terminated: bool,
+}
+
+/// Returns the token at the start of `text`, or `None` if `text` is empty.
+pub fn next_token(text: &str) -> Option<Token> {
+    let c = text.chars().next()?;
+    let token = |kind, len| Token {
+        kind,
+        len,
+        terminated: true,
+    };
+    let delimited = |kind, end: Option<usize>| Token {
+        kind,
+        len: end.unwrap_or(text.len()),
+        terminated: end.is_some(),
+    };
+
+    Some(match c {
+        '\'' => delimited(TokenKind::String, quoted_len(text, '\'')),
+        '"' => delimited(TokenKind::QuotedIdentifier, quoted_len(text, '"')),
+        '$' if text.starts_with("$$") => delimited(
+            TokenKind::String,
+            text[2..].find("$$").map(|len| 2 + len + 2),
+        ),
+        '-' | '/' if text[1..].starts_with(c) => {
+            token(TokenKind::Comment, text.find('\n').unwrap_or(text.len()))
+        }
+        '/' if text[1..].starts_with('*') => delimited(
+            TokenKind::Comment,
+            text[2..].find("*/").map(|len| 2 + len + 2),
+        ),
+        c if c.is_whitespace() => token(
+            TokenKind::Whitespace,
+            text.find(|c: char| !c.is_whitespace())
+                .unwrap_or(text.len()),
+        ),
+        c if is_word_char(c) => token(
+            TokenKind::Word,
+            text.find(|c: char| !is_word_char(c)).unwrap_or(text.len()),
+        ),
+        c => token(TokenKind::Symbol, c.len_utf8()),
+    })
+}
+
+/// Returns the content of a string or a quoted identifier token, with the quotes removed
+/// and the escaped quotes unescaped.
+pub fn unquote(token: &str) -> String {
+    if let Some(content) = token.strip_prefix("$$") {
+        return content.strip_suffix("$$").unwrap_or(content).to_owned();
+    }
+    let quote = match token.chars().next() {
+        Some(quote @ ('\'' | '"')) => quote,
+        _ => return token.to_owned(),
+    };
+    let content = &token[1..];
+    let content = content.strip_suffix(quote).unwrap_or(content);
+    content.replace(&format!("{0}{0}", quote), &quote.to_string())
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// Returns the length of the quoted token at the start of `text`, or `None` if it is not terminated.
+fn quoted_len(text: &str, quote: char) -> Option<usize> {
+    let mut pos = 1;
+    loop {
+        pos += text[pos..].find(quote)? + 1;
+        if !text[pos..].starts_with(quote) {
+            return Some(pos);
+        }
+        pos += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{next_token, unquote, Token, TokenKind};
+
+    fn tokens(mut text: &str) -> Vec<(TokenKind, &str, bool)> {
+        let mut tokens = Vec::new();
+        while let Some(Token {
+            kind,
+            len,
+            terminated,
+        }) = next_token(text)
+        {
+            tokens.push((kind, &text[..len], terminated));
+            text = &text[len..];
+        }
+        tokens
+    }
+
+    #[test]
+    fn splits_statements_into_tokens() {
+        use TokenKind::*;
+        assert_eq!(
+            tokens("SELECT \"A\"\"b\", zażółć FROM t WHERE a='it''s' -- c\n/* d */AND b = $$e$$;"),
+            vec![
+                (Word, "SELECT", true),
+                (Whitespace, " ", true),
+                (QuotedIdentifier, "\"A\"\"b\"", true),
+                (Symbol, ",", true),
+                (Whitespace, " ", true),
+                (Word, "zażółć", true),
+                (Whitespace, " ", true),
+                (Word, "FROM", true),
+                (Whitespace, " ", true),
+                (Word, "t", true),
+                (Whitespace, " ", true),
+                (Word, "WHERE", true),
+                (Whitespace, " ", true),
+                (Word, "a", true),
+                (Symbol, "=", true),
+                (String, "'it''s'", true),
+                (Whitespace, " ", true),
+                (Comment, "-- c", true),
+                (Whitespace, "\n", true),
+                (Comment, "/* d */", true),
+                (Word, "AND", true),
+                (Whitespace, " ", true),
+                (Word, "b", true),
+                (Whitespace, " ", true),
+                (Symbol, "=", true),
+                (Whitespace, " ", true),
+                (String, "$$e$$", true),
+                (Symbol, ";", true),
+            ]
+        );
+        assert_eq!(
+            tokens("a - -1 / 2 // e"),
+            vec![
+                (Word, "a", true),
+                (Whitespace, " ", true),
+                (Symbol, "-", true),
+                (Whitespace, " ", true),
+                (Symbol, "-", true),
+                (Word, "1", true),
+                (Whitespace, " ", true),
+                (Symbol, "/", true),
+                (Whitespace, " ", true),
+                (Word, "2", true),
+                (Whitespace, " ", true),
+                (Comment, "// e", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_tokens_extend_to_the_end() {
+        for text in ["'abc''", "\"abc", "$$abc$", "/* abc *"] {
+            let token = next_token(text).unwrap();
+            assert_eq!(token.len, text.len());
+            assert!(!token.terminated);
+        }
+    }
+
+    #[test]
+    fn unquotes_tokens() {
+        assert_eq!(unquote("'it''s'"), "it's");
+        assert_eq!(unquote("\"A\"\"b\""), "A\"b");
+        assert_eq!(unquote("$$it''s$$"), "it''s");
+        assert_eq!(unquote("'unterminated"), "unterminated");
+    }
+}
@@ -6,24 +6,64 @@ use crate::frame::value::SerializeValuesError;
 use crate::types::serialize::SerializationError;
 use crate::Consistency;
 use bytes::Bytes;
+use std::collections::HashMap;
 use std::io::ErrorKind;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use thiserror::Error;
 
 /// Error that occurred during query execution
+///
+/// The variants group the errors into layers, some of them holding a more specific error:
+/// - [`QueryError::DbError`] - the database responded with an error, see [`DbError`],
+/// - [`QueryError::ConnectionError`] - the connection to a node broke, could not be
+///   established or had no capacity for the request, see [`ConnectionError`],
+/// - [`QueryError::TimeoutError`] - a client-side timeout elapsed, see [`TimeoutError`],
+/// - [`QueryError::SerializationError`] - the values bound to the request could not be serialized,
+/// - [`QueryError::BadQuery`] - the driver rejected the request before sending it, see [`BadQuery`],
+/// - protocol violations, client-side limits, cancellation and load shedding.
+///
+/// Database, connection and timeout errors carry the address of the node they came from,
+/// see [`QueryError::node`]. Every variant also belongs to one of the broad categories
+/// described by [`ErrorCategory`], which can be obtained with [`QueryError::category`].
+/// Prefer matching on the layers, or using helpers like [`QueryError::is_retryable`]
+/// and [`QueryError::is_timeout`], over matching on the innermost errors - new variants
+/// may be added in the future.
 #[derive(Error, Debug, Clone)]
+#[non_exhaustive]
 pub enum QueryError {
     /// Database sent a response containing some error with a message
-    #[error("Database returned an error: {0}, Error message: {1}")]
-    DbError(DbError, String),
+    #[error("Database returned an error: {error}, Error message: {message}{}", OnNode(*.node))]
+    DbError {
+        error: DbError,
+        message: String,
+        /// The node which returned the error, if known.
+        node: Option<SocketAddr>,
+    },
 
     /// Caller passed an invalid query
     #[error(transparent)]
     BadQuery(#[from] BadQuery),
 
-    /// Input/Output error has occurred, connection broken etc.
-    #[error("IO Error: {0}")]
-    IoError(Arc<std::io::Error>),
+    /// The values bound to the request could not be serialized
+    #[error("Serializing values failed: {0}")]
+    SerializationError(#[from] SerializationError),
+
+    /// The connection to a node could not be used for the request
+    #[error("{error}{}", OnNode(*.node))]
+    ConnectionError {
+        error: ConnectionError,
+        /// The node the connection was made to, if known.
+        node: Option<SocketAddr>,
+    },
+
+    /// A client-side timeout elapsed before the operation completed
+    #[error("{error}{}", OnNode(*.node))]
+    TimeoutError {
+        error: TimeoutError,
+        /// The node the last attempt of the operation was sent to, if known.
+        node: Option<SocketAddr>,
+    },
 
     /// Unexpected message received
     #[error("Protocol Error: {0}")]
@@ -33,9 +73,33 @@ pub enum QueryError {
     #[error("Invalid message: {0}")]
     InvalidMessage(String),
 
-    /// Timeout error has occurred, function didn't complete in time.
-    #[error("Timeout Error")]
-    TimeoutError,
+    /// Response frame is larger than the limit configured in the session.
+    /// The response was discarded without being buffered.
+    #[error("Response frame too large: {size} bytes, limit: {max} bytes")]
+    ResponseTooLarge { size: usize, max: usize },
+
+    /// Page of the result contains more rows than the limit configured in the session.
+    #[error("Too many rows in a response page: {rows}, limit: {max}")]
+    TooManyRows { rows: usize, max: usize },
+
+    /// The request was cancelled by the caller before it completed.
+    #[error("Request cancelled")]
+    RequestCancelled,
+
+    /// The request was rejected by the load shedding policy of the session,
+    /// because too many requests were in flight.
+    #[error("Request shed: too many requests in flight")]
+    RequestShed,
+}
+
+/// Error of the connection to a node, because of which a request could not be sent
+/// or its response could not be received
+#[derive(Error, Debug, Clone)]
+#[non_exhaustive]
+pub enum ConnectionError {
+    /// Input/Output error has occurred, connection broken etc.
+    #[error("IO Error: {0}")]
+    IoError(Arc<std::io::Error>),
 
     #[error("Too many orphaned stream ids: {0}")]
     TooManyOrphanedStreamIds(u16),
@@ -43,18 +107,42 @@ pub enum QueryError {
     #[error("Unable to allocate stream id")]
     UnableToAllocStreamId,
 
-    /// Client timeout occurred before any response arrived
-    #[error("Request timeout: {0}")]
-    RequestTimeout(String),
-
     /// Address translation failed
     #[error("Address translation failed: {0}")]
     TranslationError(#[from] TranslationError),
 }
 
+/// Client-side timeout
+#[derive(Error, Debug, Clone)]
+#[non_exhaustive]
+pub enum TimeoutError {
+    /// Connecting to a node, or preparing a new connection for use,
+    /// didn't complete within the connect timeout.
+    #[error("Connect timeout")]
+    Connect,
+
+    /// Client timeout occurred before any response arrived,
+    /// e.g. the request timeout of the request elapsed
+    #[error("Request timeout: {0}")]
+    Request(String),
+}
+
+// Displays the address of the node an error came from, if known.
+struct OnNode(Option<SocketAddr>);
+
+impl std::fmt::Display for OnNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(node) => write!(f, " (node: {})", node),
+            None => Ok(()),
+        }
+    }
+}
+
 /// An error sent from the database in response to a query
 /// as described in the [specification](https://github.com/apache/cassandra/blob/5ed5e84613ef0e9664a774493db7d2604e3596e0/doc/native_protocol_v4.spec#L1029)\
 #[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum DbError {
     /// The submitted query has a syntax error
     #[error("The submitted query has a syntax error")]
@@ -171,6 +259,11 @@ pub enum DbError {
         required: i32,
         /// Number of nodes that experience a failure while executing the request
         numfailures: i32,
+        /// Failure code reported by each replica that failed to execute the request.
+        ///
+        /// The map is only sent in protocol v5 frames. The driver negotiates protocol v4,
+        /// so errors returned by it always have `None` here; use `numfailures` instead.
+        failure_reasons: Option<HashMap<IpAddr, u16>>,
         /// Replica that was asked for data has responded
         data_present: bool,
     },
@@ -190,6 +283,11 @@ pub enum DbError {
         required: i32,
         /// Number of nodes that experience a failure while executing the request
         numfailures: i32,
+        /// Failure code reported by each replica that failed to execute the request.
+        ///
+        /// The map is only sent in protocol v5 frames. The driver negotiates protocol v4,
+        /// so errors returned by it always have `None` here; use `numfailures` instead.
+        failure_reasons: Option<HashMap<IpAddr, u16>>,
         /// Type of write operation requested
         write_type: WriteType,
     },
@@ -260,6 +358,7 @@ impl DbError {
                 received: _,
                 required: _,
                 numfailures: _,
+                failure_reasons: _,
                 data_present: _,
             } => 0x1300,
             DbError::FunctionFailure {
@@ -272,6 +371,7 @@ impl DbError {
                 received: _,
                 required: _,
                 numfailures: _,
+                failure_reasons: _,
                 write_type: _,
             } => 0x1500,
             DbError::SyntaxError => 0x2000,
@@ -336,14 +436,8 @@ pub enum WriteType {
 /// Error caused by caller creating an invalid query
 #[derive(Error, Debug, Clone)]
 #[error("Invalid query passed to Session")]
+#[non_exhaustive]
 pub enum BadQuery {
-    /// Failed to serialize values passed to a query - values too big
-    #[error("Serializing values failed: {0} ")]
-    SerializeValuesError(#[from] SerializeValuesError),
-
-    #[error("Serializing values failed: {0} ")]
-    SerializationError(#[from] SerializationError),
-
     /// Serialized values are too long to compute partition key
     #[error("Serialized values are too long to compute partition key! Length: {0}, Max allowed length: {1}")]
     ValuesTooLongForKey(usize, usize),
@@ -352,6 +446,10 @@ pub enum BadQuery {
     #[error("Passed invalid keyspace name to use: {0}")]
     BadKeyspaceName(#[from] BadKeyspaceName),
 
+    /// Keyspace passed to `use_keyspace` does not exist according to the cluster metadata
+    #[error("Keyspace {0} does not exist")]
+    KeyspaceNotFound(String),
+
     /// Too many queries in the batch statement
     #[error("Number of Queries in Batch Statement supplied is {0} which has exceeded the max value of 65,535")]
     TooManyQueriesInBatchStatement(usize),
@@ -362,7 +460,13 @@ pub enum BadQuery {
 }
 
 /// Error that occurred during session creation
+///
+/// The configuration errors are detected before connecting; errors of the requests
+/// sent while creating the session, e.g. when connecting to the known nodes
+/// or fetching the cluster metadata, are wrapped [`QueryError`]s.
+/// Like [`QueryError`], it can be classified using [`NewSessionError::category`].
 #[derive(Error, Debug, Clone)]
+#[non_exhaustive]
 pub enum NewSessionError {
     /// Failed to resolve hostname passed in Session creation
     #[error("Couldn't resolve any hostname: {0:?}")]
@@ -373,44 +477,49 @@ pub enum NewSessionError {
     #[error("Empty known nodes list")]
     EmptyKnownNodesList,
 
-    /// Database sent a response containing some error with a message
-    #[error("Database returned an error: {0}, Error message: {1}")]
-    DbError(DbError, String),
+    /// The session configuration contains an invalid value.
+    #[error("Invalid session configuration: {0}")]
+    InvalidConfiguration(String),
 
-    /// Caller passed an invalid query
+    /// A request sent while creating the session failed
     #[error(transparent)]
-    BadQuery(#[from] BadQuery),
+    QueryError(#[from] QueryError),
+}
 
-    /// Input/Output error has occurred, connection broken etc.
-    #[error("IO Error: {0}")]
-    IoError(Arc<std::io::Error>),
+/// Broad category of a [`QueryError`] or a [`NewSessionError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCategory {
+    /// The connection to a node broke, could not be established, or had no capacity
+    /// to handle the request (e.g. no free stream ids). Address translation errors
+    /// belong here too.
+    Connection,
 
-    /// Unexpected message received
-    #[error("Protocol Error: {0}")]
-    ProtocolError(&'static str),
+    /// The database responded with an error, see [`DbError`].
+    Database,
 
-    /// Invalid message received
-    #[error("Invalid message: {0}")]
-    InvalidMessage(String),
+    /// The values bound to the request could not be serialized.
+    Serialization,
 
-    /// Timeout error has occurred, couldn't connect to node in time.
-    #[error("Timeout Error")]
-    TimeoutError,
+    /// The request was rejected by the driver before being sent, for a reason
+    /// other than serialization, e.g. an invalid keyspace name.
+    BadQuery,
 
-    #[error("Too many orphaned stream ids: {0}")]
-    TooManyOrphanedStreamIds(u16),
+    /// The operation was not completed before a client-side timeout elapsed.
+    Timeout,
 
-    #[error("Unable to allocate stream id")]
-    UnableToAllocStreamId,
+    /// The database sent a response that violates the protocol or could not be parsed.
+    Protocol,
 
-    /// Client timeout occurred before a response arrived for some query
-    /// during `Session` creation.
-    #[error("Client timeout: {0}")]
-    RequestTimeout(String),
+    /// The session configuration is invalid, e.g. no known node could be resolved.
+    Configuration,
 
-    /// Address translation failed
-    #[error("Address translation failed: {0}")]
-    TranslationError(#[from] TranslationError),
+    /// The response exceeded a client-side limit configured in the session,
+    /// e.g. the maximum response frame size.
+    LimitExceeded,
+
+    /// The request was cancelled by the caller.
+    Cancelled,
 }
 
 /// Invalid keyspace name given to `Session::use_keyspace()`
@@ -435,21 +544,33 @@ impl std::fmt::Display for WriteType {
     }
 }
 
+impl From<ConnectionError> for QueryError {
+    fn from(error: ConnectionError) -> QueryError {
+        QueryError::ConnectionError { error, node: None }
+    }
+}
+
+impl From<TimeoutError> for QueryError {
+    fn from(error: TimeoutError) -> QueryError {
+        QueryError::TimeoutError { error, node: None }
+    }
+}
+
 impl From<std::io::Error> for QueryError {
     fn from(io_error: std::io::Error) -> QueryError {
-        QueryError::IoError(Arc::new(io_error))
+        ConnectionError::IoError(Arc::new(io_error)).into()
     }
 }
 
-impl From<SerializeValuesError> for QueryError {
-    fn from(serialized_err: SerializeValuesError) -> QueryError {
-        QueryError::BadQuery(BadQuery::SerializeValuesError(serialized_err))
+impl From<TranslationError> for QueryError {
+    fn from(translation_error: TranslationError) -> QueryError {
+        ConnectionError::TranslationError(translation_error).into()
     }
 }
 
-impl From<SerializationError> for QueryError {
-    fn from(serialized_err: SerializationError) -> QueryError {
-        QueryError::BadQuery(BadQuery::SerializationError(serialized_err))
+impl From<SerializeValuesError> for QueryError {
+    fn from(serialized_err: SerializeValuesError) -> QueryError {
+        QueryError::SerializationError(SerializationError::new(serialized_err))
     }
 }
 
@@ -465,34 +586,16 @@ impl From<FrameError> for QueryError {
     }
 }
 
+#[cfg(feature = "tokio")]
 impl From<tokio::time::error::Elapsed> for QueryError {
     fn from(timer_error: tokio::time::error::Elapsed) -> QueryError {
-        QueryError::RequestTimeout(format!("{}", timer_error))
+        TimeoutError::Request(format!("{}", timer_error)).into()
     }
 }
 
 impl From<std::io::Error> for NewSessionError {
     fn from(io_error: std::io::Error) -> NewSessionError {
-        NewSessionError::IoError(Arc::new(io_error))
-    }
-}
-
-impl From<QueryError> for NewSessionError {
-    fn from(query_error: QueryError) -> NewSessionError {
-        match query_error {
-            QueryError::DbError(e, msg) => NewSessionError::DbError(e, msg),
-            QueryError::BadQuery(e) => NewSessionError::BadQuery(e),
-            QueryError::IoError(e) => NewSessionError::IoError(e),
-            QueryError::ProtocolError(m) => NewSessionError::ProtocolError(m),
-            QueryError::InvalidMessage(m) => NewSessionError::InvalidMessage(m),
-            QueryError::TimeoutError => NewSessionError::TimeoutError,
-            QueryError::TooManyOrphanedStreamIds(ids) => {
-                NewSessionError::TooManyOrphanedStreamIds(ids)
-            }
-            QueryError::UnableToAllocStreamId => NewSessionError::UnableToAllocStreamId,
-            QueryError::RequestTimeout(msg) => NewSessionError::RequestTimeout(msg),
-            QueryError::TranslationError(e) => NewSessionError::TranslationError(e),
-        }
+        NewSessionError::QueryError(io_error.into())
     }
 }
 
@@ -503,12 +606,91 @@ impl From<BadKeyspaceName> for QueryError {
 }
 
 impl QueryError {
+    /// Returns the address of the node the error came from, if it is known.
+    ///
+    /// Only database, connection and timeout errors carry the node. The session sets it
+    /// for the errors of request attempts; for request timeouts, it is the node the most
+    /// recently started attempt was sent to.
+    pub fn node(&self) -> Option<SocketAddr> {
+        match self {
+            QueryError::DbError { node, .. }
+            | QueryError::ConnectionError { node, .. }
+            | QueryError::TimeoutError { node, .. } => *node,
+            _ => None,
+        }
+    }
+
+    /// Attaches the address of the node the error came from, unless the error already has one.
+    /// Errors which don't carry the node are returned unchanged.
+    pub fn with_node(mut self, address: SocketAddr) -> Self {
+        if let QueryError::DbError { node, .. }
+        | QueryError::ConnectionError { node, .. }
+        | QueryError::TimeoutError { node, .. } = &mut self
+        {
+            node.get_or_insert(address);
+        }
+        self
+    }
+
+    /// Returns the category of this error.
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            QueryError::DbError { .. } => ErrorCategory::Database,
+            QueryError::BadQuery(_) => ErrorCategory::BadQuery,
+            QueryError::SerializationError(_) => ErrorCategory::Serialization,
+            QueryError::ConnectionError { .. } => ErrorCategory::Connection,
+            QueryError::TimeoutError { .. } => ErrorCategory::Timeout,
+            QueryError::ProtocolError(_) | QueryError::InvalidMessage(_) => ErrorCategory::Protocol,
+            QueryError::ResponseTooLarge { .. }
+            | QueryError::TooManyRows { .. }
+            | QueryError::RequestShed => ErrorCategory::LimitExceeded,
+            QueryError::RequestCancelled => ErrorCategory::Cancelled,
+        }
+    }
+
+    /// Checks if this error is transient, i.e. sending the same request again
+    /// (to the same or another node) may succeed.
+    ///
+    /// This does not take idempotence of the request into account - e.g. a write
+    /// that timed out is considered retryable, although it may have been applied.
+    /// Retry policies have to check idempotence of the request themselves.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            QueryError::DbError { error, .. } => error.is_retryable(),
+            QueryError::ConnectionError { error, .. } => {
+                !matches!(error, ConnectionError::TranslationError(_))
+            }
+            QueryError::TimeoutError { .. } => true,
+            QueryError::BadQuery(_)
+            | QueryError::SerializationError(_)
+            | QueryError::ProtocolError(_)
+            | QueryError::InvalidMessage(_)
+            | QueryError::ResponseTooLarge { .. }
+            | QueryError::TooManyRows { .. }
+            | QueryError::RequestCancelled
+            | QueryError::RequestShed => false,
+        }
+    }
+
+    /// Checks if this error is caused by a timeout, either on the client side
+    /// or reported by the database (read or write timeout).
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            QueryError::DbError { error, .. } => error.is_timeout(),
+            other => other.category() == ErrorCategory::Timeout,
+        }
+    }
+
     /// Checks if this error indicates that a chosen source port/address cannot be bound.
     /// This is caused by one of the following:
     /// - The source address is already used by another socket,
     /// - The source address is reserved and the process does not have sufficient privileges to use it.
     pub fn is_address_unavailable_for_use(&self) -> bool {
-        if let QueryError::IoError(io_error) = self {
+        if let QueryError::ConnectionError {
+            error: ConnectionError::IoError(io_error),
+            ..
+        } = self
+        {
             match io_error.kind() {
                 ErrorKind::AddrInUse | ErrorKind::PermissionDenied => return true,
                 _ => {}
@@ -519,6 +701,83 @@ impl QueryError {
     }
 }
 
+impl NewSessionError {
+    /// Returns the address of the node the error came from, if it is known,
+    /// see [`QueryError::node`].
+    pub fn node(&self) -> Option<SocketAddr> {
+        match self {
+            NewSessionError::QueryError(query_error) => query_error.node(),
+            _ => None,
+        }
+    }
+
+    /// Returns the category of this error.
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            NewSessionError::FailedToResolveAnyHostname(_)
+            | NewSessionError::EmptyKnownNodesList
+            | NewSessionError::InvalidConfiguration(_) => ErrorCategory::Configuration,
+            NewSessionError::QueryError(query_error) => query_error.category(),
+        }
+    }
+
+    /// Checks if this error is transient, i.e. creating the session again may succeed.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            NewSessionError::QueryError(query_error) => query_error.is_retryable(),
+            _ => false,
+        }
+    }
+
+    /// Checks if this error is caused by a timeout.
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            NewSessionError::QueryError(query_error) => query_error.is_timeout(),
+            _ => false,
+        }
+    }
+}
+
+impl DbError {
+    /// Checks if this error is transient, i.e. sending the same request again
+    /// (to the same or another node) may succeed.
+    /// Errors caused by the request itself, like syntax errors or missing permissions, are not.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            DbError::Unavailable { .. }
+            | DbError::Overloaded
+            | DbError::IsBootstrapping
+            | DbError::TruncateError
+            | DbError::ReadTimeout { .. }
+            | DbError::WriteTimeout { .. }
+            | DbError::ReadFailure { .. }
+            | DbError::WriteFailure { .. }
+            | DbError::Unprepared { .. }
+            | DbError::ServerError => true,
+            // Retrying, even on another node, would only add load to the partition
+            // which is already too hot.
+            DbError::RateLimitReached { .. }
+            | DbError::SyntaxError
+            | DbError::Invalid
+            | DbError::AlreadyExists { .. }
+            | DbError::FunctionFailure { .. }
+            | DbError::AuthenticationError
+            | DbError::Unauthorized
+            | DbError::ConfigError
+            | DbError::ProtocolError
+            | DbError::Other(_) => false,
+        }
+    }
+
+    /// Checks if this error is a read or write timeout reported by the database.
+    pub fn is_timeout(&self) -> bool {
+        matches!(
+            self,
+            DbError::ReadTimeout { .. } | DbError::WriteTimeout { .. }
+        )
+    }
+}
+
 impl From<u8> for OperationType {
     fn from(operation_type: u8) -> OperationType {
         match operation_type {
@@ -563,8 +822,13 @@ impl WriteType {
 
 #[cfg(test)]
 mod tests {
-    use super::{DbError, QueryError, WriteType};
+    use super::{
+        BadKeyspaceName, BadQuery, ConnectionError, DbError, ErrorCategory, NewSessionError,
+        OperationType, QueryError, TimeoutError, TranslationError, WriteType,
+    };
     use crate::frame::types::Consistency;
+    use std::net::SocketAddr;
+    use std::sync::Arc;
 
     #[test]
     fn write_type_from_str() {
@@ -609,8 +873,11 @@ mod tests {
         assert_eq!(db_error_displayed, expected_dberr_msg);
 
         // Test that QueryError::DbError::(DbError::Unavailable) is displayed correctly
-        let query_error =
-            QueryError::DbError(db_error, "a message about unavailable error".to_string());
+        let query_error = QueryError::DbError {
+            error: db_error,
+            message: "a message about unavailable error".to_string(),
+            node: None,
+        };
         let query_error_displayed: String = format!("{}", query_error);
 
         let mut expected_querr_msg = "Database returned an error: ".to_string();
@@ -618,5 +885,138 @@ mod tests {
         expected_querr_msg += ", Error message: a message about unavailable error";
 
         assert_eq!(query_error_displayed, expected_querr_msg);
+
+        // The node is displayed once it is known
+        let node: SocketAddr = "127.0.0.1:9042".parse().unwrap();
+        let query_error_displayed = format!("{}", query_error.with_node(node));
+        expected_querr_msg += " (node: 127.0.0.1:9042)";
+
+        assert_eq!(query_error_displayed, expected_querr_msg);
+    }
+
+    #[test]
+    fn error_nodes() {
+        let node: SocketAddr = "127.0.0.1:9042".parse().unwrap();
+        let other_node: SocketAddr = "127.0.0.2:9042".parse().unwrap();
+
+        let io_error = QueryError::from(std::io::Error::from(std::io::ErrorKind::ConnectionReset));
+        assert_eq!(io_error.node(), None);
+        let io_error = io_error.with_node(node);
+        assert_eq!(io_error.node(), Some(node));
+        assert_eq!(
+            io_error.to_string(),
+            "IO Error: connection reset (node: 127.0.0.1:9042)"
+        );
+
+        // The node which the error originated from is not overwritten
+        assert_eq!(io_error.with_node(other_node).node(), Some(node));
+
+        let client_timeout = QueryError::from(TimeoutError::Connect).with_node(node);
+        assert_eq!(client_timeout.node(), Some(node));
+        assert_eq!(NewSessionError::from(client_timeout).node(), Some(node));
+
+        // Errors not related to a node don't carry one
+        let cancelled = QueryError::RequestCancelled.with_node(node);
+        assert_eq!(cancelled.node(), None);
+        assert_eq!(NewSessionError::EmptyKnownNodesList.node(), None);
+    }
+
+    #[test]
+    fn error_categories() {
+        let write_timeout = QueryError::DbError {
+            error: DbError::WriteTimeout {
+                consistency: Consistency::Quorum,
+                received: 1,
+                required: 2,
+                write_type: WriteType::Simple,
+            },
+            message: String::new(),
+            node: None,
+        };
+        assert_eq!(write_timeout.category(), ErrorCategory::Database);
+        assert!(write_timeout.is_retryable());
+        assert!(write_timeout.is_timeout());
+
+        let syntax_error = QueryError::DbError {
+            error: DbError::SyntaxError,
+            message: String::new(),
+            node: None,
+        };
+        assert_eq!(syntax_error.category(), ErrorCategory::Database);
+        assert!(!syntax_error.is_retryable());
+        assert!(!syntax_error.is_timeout());
+
+        let rate_limited = QueryError::DbError {
+            error: DbError::RateLimitReached {
+                op_type: OperationType::Write,
+                rejected_by_coordinator: true,
+            },
+            message: String::new(),
+            node: None,
+        };
+        assert_eq!(rate_limited.category(), ErrorCategory::Database);
+        assert!(!rate_limited.is_retryable());
+
+        let io_error = QueryError::from(std::io::Error::from(std::io::ErrorKind::ConnectionReset));
+        assert_eq!(io_error.category(), ErrorCategory::Connection);
+        assert!(io_error.is_retryable());
+        assert!(!io_error.is_timeout());
+
+        let client_timeout = QueryError::from(TimeoutError::Request("timed out".to_owned()));
+        assert_eq!(client_timeout.category(), ErrorCategory::Timeout);
+        assert!(client_timeout.is_retryable());
+        assert!(client_timeout.is_timeout());
+
+        let too_large = QueryError::ResponseTooLarge {
+            size: 2048,
+            max: 1024,
+        };
+        assert_eq!(too_large.category(), ErrorCategory::LimitExceeded);
+        assert!(!too_large.is_retryable());
+        assert_eq!(
+            NewSessionError::from(too_large).category(),
+            ErrorCategory::LimitExceeded
+        );
+
+        let cancelled = QueryError::RequestCancelled;
+        assert_eq!(cancelled.category(), ErrorCategory::Cancelled);
+        assert!(!cancelled.is_retryable());
+        assert!(!cancelled.is_timeout());
+
+        let shed = QueryError::RequestShed;
+        assert_eq!(shed.category(), ErrorCategory::LimitExceeded);
+        assert!(!shed.is_retryable());
+        assert_eq!(
+            NewSessionError::from(shed).category(),
+            ErrorCategory::LimitExceeded
+        );
+
+        let bad_keyspace = QueryError::from(BadKeyspaceName::Empty);
+        assert_eq!(bad_keyspace.category(), ErrorCategory::BadQuery);
+        assert!(!bad_keyspace.is_retryable());
+
+        let too_long = QueryError::BadQuery(BadQuery::ValuesTooLongForKey(70000, 65535));
+        assert_eq!(too_long.category(), ErrorCategory::BadQuery);
+
+        let translation_error = QueryError::from(ConnectionError::TranslationError(
+            TranslationError::NoRuleForAddress,
+        ));
+        assert_eq!(translation_error.category(), ErrorCategory::Connection);
+        assert!(!translation_error.is_retryable());
+
+        let protocol_error = QueryError::ProtocolError("unexpected response");
+        assert_eq!(protocol_error.category(), ErrorCategory::Protocol);
+        assert!(!protocol_error.is_retryable());
+
+        // Categories are preserved when converting to NewSessionError
+        let session_error = NewSessionError::from(QueryError::from(ConnectionError::IoError(
+            Arc::new(std::io::Error::from(std::io::ErrorKind::ConnectionRefused)),
+        )));
+        assert_eq!(session_error.category(), ErrorCategory::Connection);
+        assert!(session_error.is_retryable());
+
+        let empty_nodes = NewSessionError::EmptyKnownNodesList;
+        assert_eq!(empty_nodes.category(), ErrorCategory::Configuration);
+        assert!(!empty_nodes.is_retryable());
     }
 }
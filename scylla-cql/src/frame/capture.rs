@@ -0,0 +1,479 @@
+//! Captures of the CQL frames exchanged over connections.
+//!
+//! A capture is a sequence of raw request and response frames, each recorded
+//! with the time it was sent or received and the address of the node on the other side.
+//! Captures are written with [`CaptureWriter`] and read back with [`CaptureReader`],
+//! which decompresses the frames so that they can be decoded into typed
+//! requests and responses.
+//!
+//! # Format
+//! A capture starts with the `CQLCAP` magic bytes followed by the format version (`1`).
+//! Each frame is then stored as:
+//! - direction (`u8`): `0` for requests, `1` for responses,
+//! - timestamp in microseconds since the UNIX epoch (`u64`),
+//! - node address: IP version (`u8`, `4` or `6`), IP octets and port (`u16`),
+//! - compression negotiated on the connection (`u8`): `0` for none, `1` for LZ4,
+//!   `2` for Snappy, `3` for Zstandard,
+//! - the frame, as sent on the wire: the 9 byte header followed by the body.
+//!
+//! All integers are big-endian.
+
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bytes::{BufMut, Bytes};
+use thiserror::Error;
+
+use super::frame_errors::{FrameError, ParseError};
+use super::protocol_features::ProtocolFeatures;
+use super::request::{Request, RequestOpcode};
+use super::response::{Response, ResponseOpcode};
+use super::{
+    decompress, parse_response_body_extensions, types, Compression, FrameParams, FLAG_COMPRESSION,
+    FLAG_CUSTOM_PAYLOAD, HEADER_SIZE,
+};
+
+const MAGIC: &[u8] = b"CQLCAP";
+const FORMAT_VERSION: u8 = 1;
+
+/// Whether a frame was sent to or received from the node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameDirection {
+    /// A request frame, sent by the client.
+    Request,
+    /// A response or event frame, sent by the node.
+    Response,
+}
+
+/// An error which occurred while reading a capture.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum CaptureError {
+    #[error("Failed to read the capture: {0}")]
+    Io(#[from] io::Error),
+    #[error("The data is not a frame capture")]
+    NotACapture,
+    #[error("Unsupported capture format version: {0}")]
+    UnsupportedVersion(u8),
+    #[error("Malformed captured frame: {0}")]
+    MalformedFrame(String),
+    #[error("Failed to decompress a captured frame: {0}")]
+    Decompression(FrameError),
+}
+
+/// Writes frames to a capture.
+pub struct CaptureWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> CaptureWriter<W> {
+    /// Starts a capture, which is written to the given writer.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[FORMAT_VERSION])?;
+        Ok(Self { writer })
+    }
+
+    /// Records a serialized request frame, including its header.
+    pub fn write_request(
+        &mut self,
+        timestamp: SystemTime,
+        address: SocketAddr,
+        compression: Option<Compression>,
+        frame: &[u8],
+    ) -> io::Result<()> {
+        let mut record = Vec::new();
+        write_record_prefix(
+            &mut record,
+            FrameDirection::Request,
+            timestamp,
+            address,
+            compression,
+        );
+        record.extend_from_slice(frame);
+        self.writer.write_all(&record)
+    }
+
+    /// Records a response frame, given its header fields and its body, as read from the wire.
+    pub fn write_response(
+        &mut self,
+        timestamp: SystemTime,
+        address: SocketAddr,
+        compression: Option<Compression>,
+        params: FrameParams,
+        opcode: ResponseOpcode,
+        body: &[u8],
+    ) -> io::Result<()> {
+        let mut record = Vec::new();
+        write_record_prefix(
+            &mut record,
+            FrameDirection::Response,
+            timestamp,
+            address,
+            compression,
+        );
+        record.put_u8(params.version);
+        record.put_u8(params.flags);
+        record.put_i16(params.stream);
+        record.put_u8(opcode as u8);
+        record.put_u32(body.len() as u32);
+        record.extend_from_slice(body);
+        self.writer.write_all(&record)
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+fn write_record_prefix(
+    record: &mut Vec<u8>,
+    direction: FrameDirection,
+    timestamp: SystemTime,
+    address: SocketAddr,
+    compression: Option<Compression>,
+) {
+    record.put_u8(match direction {
+        FrameDirection::Request => 0,
+        FrameDirection::Response => 1,
+    });
+    let micros = timestamp
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_micros();
+    record.put_u64(micros as u64);
+    match address.ip() {
+        IpAddr::V4(ip) => {
+            record.put_u8(4);
+            record.extend_from_slice(&ip.octets());
+        }
+        IpAddr::V6(ip) => {
+            record.put_u8(6);
+            record.extend_from_slice(&ip.octets());
+        }
+    }
+    record.put_u16(address.port());
+    record.put_u8(match compression {
+        None => 0,
+        Some(Compression::Lz4) => 1,
+        Some(Compression::Snappy) => 2,
+        Some(Compression::Zstd { .. }) => 3,
+    });
+}
+
+/// A frame read from a capture.
+#[derive(Debug, Clone)]
+pub struct CapturedFrame {
+    pub direction: FrameDirection,
+    pub timestamp: SystemTime,
+    /// Address of the node the frame was sent to or received from.
+    pub address: SocketAddr,
+    /// Header fields of the frame. The compression flag is cleared,
+    /// as the body is decompressed when the frame is read.
+    pub params: FrameParams,
+    pub opcode: u8,
+    /// The decompressed body of the frame, including its extensions
+    /// (e.g. the custom payload or the warnings).
+    pub body: Bytes,
+}
+
+impl CapturedFrame {
+    /// Decodes a request frame.
+    ///
+    /// Returns `None` for requests which can't be decoded, i.e. other than
+    /// `QUERY`, `EXECUTE` and `BATCH`, and for response frames.
+    pub fn decode_request(&self) -> Result<Option<Request<'_>>, ParseError> {
+        if self.direction != FrameDirection::Request {
+            return Ok(None);
+        }
+        let opcode = match RequestOpcode::try_from(self.opcode) {
+            Ok(opcode @ (RequestOpcode::Query | RequestOpcode::Execute | RequestOpcode::Batch)) => {
+                opcode
+            }
+            _ => return Ok(None),
+        };
+        let buf = &mut &*self.body;
+        if self.params.flags & FLAG_CUSTOM_PAYLOAD != 0 {
+            types::read_bytes_map(buf)?;
+        }
+        Request::deserialize(buf, opcode).map(Some)
+    }
+
+    /// Decodes a response frame, skipping its extensions.
+    ///
+    /// Returns `None` for request frames. Results of prepared statements
+    /// which were executed without result metadata can't be decoded,
+    /// as the metadata is not part of the capture.
+    pub fn decode_response(
+        &self,
+        features: &ProtocolFeatures,
+    ) -> Result<Option<Response>, FrameError> {
+        if self.direction != FrameDirection::Response {
+            return Ok(None);
+        }
+        let opcode = ResponseOpcode::try_from(self.opcode)?;
+        let body = parse_response_body_extensions(self.params.flags, None, self.body.clone())?.body;
+        Ok(Some(Response::deserialize(
+            features,
+            opcode,
+            &mut &*body,
+            None,
+        )?))
+    }
+}
+
+/// Reads frames from a capture.
+///
+/// The frames are returned by the [`Iterator`] implementation.
+pub struct CaptureReader<R: Read> {
+    reader: R,
+    failed: bool,
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// Opens a capture, checking its format.
+    pub fn new(mut reader: R) -> Result<Self, CaptureError> {
+        let mut header = [0u8; MAGIC.len() + 1];
+        reader.read_exact(&mut header).map_err(|err| {
+            if err.kind() == io::ErrorKind::UnexpectedEof {
+                CaptureError::NotACapture
+            } else {
+                CaptureError::Io(err)
+            }
+        })?;
+        if &header[..MAGIC.len()] != MAGIC {
+            return Err(CaptureError::NotACapture);
+        }
+        let version = header[MAGIC.len()];
+        if version != FORMAT_VERSION {
+            return Err(CaptureError::UnsupportedVersion(version));
+        }
+        Ok(Self {
+            reader,
+            failed: false,
+        })
+    }
+
+    fn read_frame(&mut self) -> Result<Option<CapturedFrame>, CaptureError> {
+        let mut direction = [0u8; 1];
+        match self.reader.read_exact(&mut direction) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+        let direction = match direction[0] {
+            0 => FrameDirection::Request,
+            1 => FrameDirection::Response,
+            other => {
+                return Err(CaptureError::MalformedFrame(format!(
+                    "unknown direction {}",
+                    other
+                )))
+            }
+        };
+
+        let timestamp = UNIX_EPOCH + Duration::from_micros(self.read_u64()?);
+        let ip = match self.read_u8()? {
+            4 => {
+                let mut octets = [0u8; 4];
+                self.reader.read_exact(&mut octets)?;
+                IpAddr::V4(Ipv4Addr::from(octets))
+            }
+            6 => {
+                let mut octets = [0u8; 16];
+                self.reader.read_exact(&mut octets)?;
+                IpAddr::V6(Ipv6Addr::from(octets))
+            }
+            other => {
+                return Err(CaptureError::MalformedFrame(format!(
+                    "unknown IP version {}",
+                    other
+                )))
+            }
+        };
+        let address = SocketAddr::new(ip, self.read_u16()?);
+        let compression = match self.read_u8()? {
+            0 => None,
+            1 => Some(Compression::Lz4),
+            2 => Some(Compression::Snappy),
+            3 => Some(Compression::Zstd { level: 0 }),
+            other => {
+                return Err(CaptureError::MalformedFrame(format!(
+                    "unsupported compression {}",
+                    other
+                )))
+            }
+        };
+
+        let mut header = [0u8; HEADER_SIZE];
+        self.reader.read_exact(&mut header)?;
+        let mut params = FrameParams {
+            version: header[0],
+            flags: header[1],
+            stream: i16::from_be_bytes([header[2], header[3]]),
+        };
+        let opcode = header[4];
+        let length = u32::from_be_bytes([header[5], header[6], header[7], header[8]]) as usize;
+        let mut body = vec![0u8; length];
+        self.reader.read_exact(&mut body)?;
+
+        if params.flags & FLAG_COMPRESSION != 0 {
+            let compression = compression.ok_or(CaptureError::Decompression(
+                FrameError::NoCompressionNegotiated,
+            ))?;
+            body = decompress(&body, compression).map_err(CaptureError::Decompression)?;
+            params.flags &= !FLAG_COMPRESSION;
+        }
+
+        Ok(Some(CapturedFrame {
+            direction,
+            timestamp,
+            address,
+            params,
+            opcode,
+            body: body.into(),
+        }))
+    }
+
+    fn read_u8(&mut self) -> io::Result<u8> {
+        let mut buf = [0u8; 1];
+        self.reader.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn read_u16(&mut self) -> io::Result<u16> {
+        let mut buf = [0u8; 2];
+        self.reader.read_exact(&mut buf)?;
+        Ok(u16::from_be_bytes(buf))
+    }
+
+    fn read_u64(&mut self) -> io::Result<u64> {
+        let mut buf = [0u8; 8];
+        self.reader.read_exact(&mut buf)?;
+        Ok(u64::from_be_bytes(buf))
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = Result<CapturedFrame, CaptureError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let result = self.read_frame().transpose();
+        if let Some(Err(_)) = result {
+            // The position in the capture is unknown after an error.
+            self.failed = true;
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::net::SocketAddr;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use assert_matches::assert_matches;
+
+    use super::{CaptureError, CaptureReader, CaptureWriter, FrameDirection};
+    use crate::frame::protocol_features::ProtocolFeatures;
+    use crate::frame::request::query::{Query, QueryParameters};
+    use crate::frame::request::Request;
+    use crate::frame::response::{Response, ResponseOpcode};
+    use crate::frame::{Compression, FrameParams, SerializedRequest};
+
+    #[test]
+    fn captured_frames_are_decoded() {
+        let address: SocketAddr = "127.0.0.1:9042".parse().unwrap();
+        let timestamp = UNIX_EPOCH + Duration::from_micros(1_700_000_000_123_456);
+
+        let query = Query {
+            contents: Cow::Borrowed("SELECT * FROM ks.t"),
+            parameters: QueryParameters::default(),
+        };
+        let mut request =
+            SerializedRequest::make(&query, Some(Compression::Lz4), false, None).unwrap();
+        request.set_stream(42);
+
+        let mut writer = CaptureWriter::new(Vec::new()).unwrap();
+        writer
+            .write_request(
+                timestamp,
+                address,
+                Some(Compression::Lz4),
+                request.get_data(),
+            )
+            .unwrap();
+        writer
+            .write_response(
+                timestamp,
+                address,
+                Some(Compression::Lz4),
+                FrameParams {
+                    version: 0x84,
+                    flags: 0,
+                    stream: 42,
+                },
+                ResponseOpcode::Ready,
+                &[],
+            )
+            .unwrap();
+        let capture = writer.into_inner();
+
+        let frames = CaptureReader::new(&capture[..])
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(frames.len(), 2);
+
+        let request = &frames[0];
+        assert_eq!(request.direction, FrameDirection::Request);
+        assert_eq!(request.timestamp, timestamp);
+        assert_eq!(request.address, address);
+        assert_eq!(request.params.stream, 42);
+        match request.decode_request().unwrap() {
+            Some(Request::Query(q)) => assert_eq!(q.contents, "SELECT * FROM ks.t"),
+            _ => panic!("expected a QUERY request"),
+        }
+        assert!(request
+            .decode_response(&ProtocolFeatures::default())
+            .unwrap()
+            .is_none());
+
+        let response = &frames[1];
+        assert_eq!(response.direction, FrameDirection::Response);
+        assert_eq!(response.params.stream, 42);
+        assert_matches!(
+            response.decode_response(&ProtocolFeatures::default()),
+            Ok(Some(Response::Ready))
+        );
+    }
+
+    #[test]
+    fn truncated_capture_is_an_error() {
+        assert_matches!(
+            CaptureReader::new(&b"CQL"[..]).err(),
+            Some(CaptureError::NotACapture)
+        );
+        assert_matches!(
+            CaptureReader::new(&b"CQLCAP\x07"[..]).err(),
+            Some(CaptureError::UnsupportedVersion(7))
+        );
+
+        let mut capture = CaptureWriter::new(Vec::new()).unwrap().into_inner();
+        capture.extend_from_slice(&[0, 1, 2]);
+        let mut reader = CaptureReader::new(&capture[..]).unwrap();
+        assert_matches!(reader.next(), Some(Err(CaptureError::Io(_))));
+        assert!(reader.next().is_none());
+    }
+}
@@ -1,3 +1,4 @@
+use super::validation::ProtocolViolation;
 use super::TryFromPrimitiveError;
 use crate::cql_to_rust::CqlTypeError;
 use crate::frame::value::SerializeValuesError;
@@ -31,6 +32,8 @@ pub enum FrameError {
     Lz4CompressError(#[from] lz4_flex::block::CompressError),
     #[error("Error decompressing lz4 data {0}")]
     Lz4DecompressError(#[from] lz4_flex::block::DecompressError),
+    #[error(transparent)]
+    ProtocolViolation(#[from] ProtocolViolation),
 }
 
 #[derive(Error, Debug)]
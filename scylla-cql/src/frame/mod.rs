@@ -1,17 +1,20 @@
+pub mod capture;
 pub mod frame_errors;
 pub mod protocol_features;
 pub mod request;
 pub mod response;
 pub mod server_event_type;
 pub mod types;
+pub mod validation;
 pub mod value;
 
 #[cfg(test)]
 mod value_tests;
 
-use crate::frame::frame_errors::FrameError;
+use crate::frame::frame_errors::{FrameError, LowLevelDeserializationError, ParseError};
 use bytes::{Buf, BufMut, Bytes};
 use thiserror::Error;
+#[cfg(feature = "tokio")]
 use tokio::io::{AsyncRead, AsyncReadExt};
 use uuid::Uuid;
 
@@ -41,11 +44,19 @@ pub enum Authenticator {
 
 /// The wire protocol compression algorithm.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[non_exhaustive]
 pub enum Compression {
     /// LZ4 compression algorithm.
     Lz4,
     /// Snappy compression algorithm.
     Snappy,
+    /// Zstandard compression algorithm with the given compression level.
+    /// It can only be negotiated if the server advertises `zstd` as a supported compression,
+    /// and if the driver was built with the `zstd` feature (see [`Compression::is_supported`]).
+    Zstd {
+        /// Compression level passed to zstd. `0` selects zstd's default level.
+        level: i32,
+    },
 }
 
 impl Compression {
@@ -53,6 +64,16 @@ impl Compression {
         match self {
             Compression::Lz4 => "lz4",
             Compression::Snappy => "snappy",
+            Compression::Zstd { .. } => "zstd",
+        }
+    }
+
+    /// Returns whether the driver was built with support of this algorithm.
+    /// The algorithms which aren't supported are never requested from the server.
+    pub fn is_supported(&self) -> bool {
+        match self {
+            Compression::Lz4 | Compression::Snappy => true,
+            Compression::Zstd { .. } => cfg!(feature = "zstd"),
         }
     }
 }
@@ -65,6 +86,7 @@ impl Display for Compression {
 
 pub struct SerializedRequest {
     data: Vec<u8>,
+    uncompressed_body_len: Option<usize>,
 }
 
 impl SerializedRequest {
@@ -72,16 +94,69 @@ impl SerializedRequest {
         req: &R,
         compression: Option<Compression>,
         tracing: bool,
+        custom_payload: Option<&HashMap<String, Bytes>>,
+    ) -> Result<SerializedRequest, FrameError> {
+        Self::make_with_compression_threshold(req, compression, 0, tracing, custom_payload)
+    }
+
+    /// Same as [`SerializedRequest::make`], but frames with bodies shorter than
+    /// `compression_threshold` bytes are sent uncompressed even if `compression` is set.
+    pub fn make_with_compression_threshold<R: SerializableRequest>(
+        req: &R,
+        compression: Option<Compression>,
+        compression_threshold: usize,
+        tracing: bool,
+        custom_payload: Option<&HashMap<String, Bytes>>,
+    ) -> Result<SerializedRequest, FrameError> {
+        Self::make_with_buffer(
+            Vec::new(),
+            req,
+            compression,
+            compression_threshold,
+            tracing,
+            custom_payload,
+        )
+    }
+
+    /// Same as [`SerializedRequest::make_with_compression_threshold`], but serializes
+    /// the frame into the provided buffer, reusing its allocation.
+    /// The previous contents of the buffer are discarded.
+    ///
+    /// The buffer can be retrieved with [`SerializedRequest::into_buffer`]
+    /// after the frame is sent, and used again for the next request.
+    pub fn make_with_buffer<R: SerializableRequest>(
+        mut data: Vec<u8>,
+        req: &R,
+        compression: Option<Compression>,
+        compression_threshold: usize,
+        tracing: bool,
+        custom_payload: Option<&HashMap<String, Bytes>>,
     ) -> Result<SerializedRequest, FrameError> {
         let mut flags = 0;
-        let mut data = vec![0; HEADER_SIZE];
+        data.clear();
+        data.resize(HEADER_SIZE, 0);
+        let mut uncompressed_body_len = None;
+
+        if custom_payload.is_some() {
+            flags |= FLAG_CUSTOM_PAYLOAD;
+        }
+
+        if let Some(custom_payload) = custom_payload {
+            types::write_bytes_map(custom_payload, &mut data)
+                .map_err(frame_errors::ParseError::from)?;
+        }
+        req.serialize(&mut data)?;
+
+        #[cfg(feature = "protocol-validation")]
+        validation::validate_request(R::OPCODE, flags, &data[HEADER_SIZE..])?;
 
         if let Some(compression) = compression {
-            flags |= FLAG_COMPRESSION;
-            let body = req.to_bytes()?;
-            compress_append(&body, compression, &mut data)?;
-        } else {
-            req.serialize(&mut data)?;
+            let body_len = data.len() - HEADER_SIZE;
+            if body_len >= compression_threshold {
+                flags |= FLAG_COMPRESSION;
+                compress_in_place(&mut data, HEADER_SIZE, compression)?;
+                uncompressed_body_len = Some(body_len);
+            }
         }
 
         if tracing {
@@ -96,7 +171,10 @@ impl SerializedRequest {
         let req_size = (data.len() - HEADER_SIZE) as u32;
         data[5..9].copy_from_slice(&req_size.to_be_bytes());
 
-        Ok(Self { data })
+        Ok(Self {
+            data,
+            uncompressed_body_len,
+        })
     }
 
     pub fn set_stream(&mut self, stream: i16) {
@@ -106,6 +184,23 @@ impl SerializedRequest {
     pub fn get_data(&self) -> &[u8] {
         &self.data[..]
     }
+
+    /// Consumes the request and returns the buffer holding the serialized frame,
+    /// so that it can be reused with [`SerializedRequest::make_with_buffer`].
+    pub fn into_buffer(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Returns the size of the frame body before compression,
+    /// or `None` if the frame was not compressed.
+    pub fn uncompressed_body_len(&self) -> Option<usize> {
+        self.uncompressed_body_len
+    }
+
+    /// Returns the size of the frame body as it is sent on the wire.
+    pub fn body_len(&self) -> usize {
+        self.data.len() - HEADER_SIZE
+    }
 }
 
 // Parts of the frame header which are not determined by the request/response type.
@@ -126,13 +221,66 @@ impl Default for FrameParams {
     }
 }
 
+#[cfg(feature = "tokio")]
 pub async fn read_response_frame(
     reader: &mut (impl AsyncRead + Unpin),
 ) -> Result<(FrameParams, ResponseOpcode, Bytes), FrameError> {
+    let (frame_params, opcode, length) = read_response_frame_header(reader).await?;
+    let body = read_response_frame_body(reader, length).await?;
+    Ok((frame_params, opcode, body))
+}
+
+/// Reads the header of a response frame. Returns the frame parameters, the opcode
+/// and the length of the body, which has to be consumed next with
+/// [`read_response_frame_body`] or [`skip_response_frame_body`].
+#[cfg(feature = "tokio")]
+pub async fn read_response_frame_header(
+    reader: &mut (impl AsyncRead + Unpin),
+) -> Result<(FrameParams, ResponseOpcode, usize), FrameError> {
     let mut raw_header = [0u8; HEADER_SIZE];
     reader.read_exact(&mut raw_header[..]).await?;
 
-    let mut buf = &raw_header[..];
+    parse_response_frame_header(&mut &raw_header[..])
+}
+
+/// Parses a whole response frame from the buffer, advancing it past the frame.
+/// Returns the frame parameters, the opcode and the (possibly compressed) body,
+/// which can be parsed further with [`parse_response_body_extensions`].
+///
+/// Unlike [`read_response_frame`], does not require an async runtime,
+/// e.g. for parsing frames recorded in a file.
+pub fn parse_response_frame(
+    buf: &mut &[u8],
+) -> Result<(FrameParams, ResponseOpcode, Bytes), FrameError> {
+    let (frame_params, opcode, length) = parse_response_frame_header(buf)?;
+    if buf.len() < length {
+        return Err(
+            ParseError::from(LowLevelDeserializationError::TooFewBytesReceived {
+                expected: length,
+                received: buf.len(),
+            })
+            .into(),
+        );
+    }
+    let (body, rest) = buf.split_at(length);
+    *buf = rest;
+    Ok((frame_params, opcode, Bytes::copy_from_slice(body)))
+}
+
+/// Parses the header of a response frame from the buffer, advancing it past the header.
+/// Returns the frame parameters, the opcode and the length of the body.
+pub fn parse_response_frame_header(
+    buf: &mut &[u8],
+) -> Result<(FrameParams, ResponseOpcode, usize), FrameError> {
+    if buf.len() < HEADER_SIZE {
+        return Err(
+            ParseError::from(LowLevelDeserializationError::TooFewBytesReceived {
+                expected: HEADER_SIZE,
+                received: buf.len(),
+            })
+            .into(),
+        );
+    }
 
     // TODO: Validate version
     let version = buf.get_u8();
@@ -154,9 +302,17 @@ pub async fn read_response_frame(
 
     let opcode = ResponseOpcode::try_from(buf.get_u8())?;
 
-    // TODO: Guard from frames that are too large
     let length = buf.get_u32() as usize;
 
+    Ok((frame_params, opcode, length))
+}
+
+/// Reads a response frame body of the given length.
+#[cfg(feature = "tokio")]
+pub async fn read_response_frame_body(
+    reader: &mut (impl AsyncRead + Unpin),
+    length: usize,
+) -> Result<Bytes, FrameError> {
     let mut raw_body = Vec::with_capacity(length).limit(length);
     while raw_body.has_remaining_mut() {
         let n = reader.read_buf(&mut raw_body).await?;
@@ -169,7 +325,25 @@ pub async fn read_response_frame(
         }
     }
 
-    Ok((frame_params, opcode, raw_body.into_inner().into()))
+    Ok(raw_body.into_inner().into())
+}
+
+/// Consumes a response frame body of the given length without buffering it,
+/// e.g. because it is too large to be held in memory.
+#[cfg(feature = "tokio")]
+pub async fn skip_response_frame_body(
+    reader: &mut (impl AsyncRead + Unpin),
+    length: usize,
+) -> Result<(), FrameError> {
+    let skipped = tokio::io::copy(&mut reader.take(length as u64), &mut tokio::io::sink()).await?;
+    if skipped < length as u64 {
+        // EOF, too early
+        return Err(FrameError::ConnectionClosed(
+            length - skipped as usize,
+            length,
+        ));
+    }
+    Ok(())
 }
 
 pub struct ResponseBodyWithExtensions {
@@ -192,6 +366,9 @@ pub fn parse_response_body_extensions(
         }
     }
 
+    #[cfg(feature = "protocol-validation")]
+    validation::validate_response_extensions(flags, &body)?;
+
     let trace_id = if flags & FLAG_TRACING != 0 {
         let buf = &mut &*body;
         let trace_id = types::read_uuid(buf).map_err(frame_errors::ParseError::from)?;
@@ -231,29 +408,60 @@ pub fn parse_response_body_extensions(
     })
 }
 
-fn compress_append(
-    uncomp_body: &[u8],
+// Compresses the part of `data` starting at `body_start`, reusing the allocation of `data`.
+// The compressed body is written past the end of the uncompressed one and then moved
+// into its place, so that no separate buffer is allocated.
+fn compress_in_place(
+    data: &mut Vec<u8>,
+    body_start: usize,
     compression: Compression,
-    out: &mut Vec<u8>,
 ) -> Result<(), FrameError> {
+    let body_end = data.len();
+    let max_compressed_len = max_compressed_len(body_end - body_start, compression)?;
+    data.resize(body_end + max_compressed_len, 0);
+    let (uncomp, out) = data.split_at_mut(body_end);
+    let compressed_len = compress_into(&uncomp[body_start..], compression, out)?;
+    data.copy_within(body_end..body_end + compressed_len, body_start);
+    data.truncate(body_start + compressed_len);
+    Ok(())
+}
+
+fn max_compressed_len(uncomp_len: usize, compression: Compression) -> Result<usize, FrameError> {
+    match compression {
+        Compression::Lz4 => {
+            Ok(std::mem::size_of::<u32>() + lz4_flex::block::get_maximum_output_size(uncomp_len))
+        }
+        Compression::Snappy => Ok(snap::raw::max_compress_len(uncomp_len)),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd { .. } => Ok(zstd::zstd_safe::compress_bound(uncomp_len)),
+        #[cfg(not(feature = "zstd"))]
+        Compression::Zstd { .. } => Err(FrameError::FrameCompression),
+    }
+}
+
+// Compresses `uncomp_body` into `out`, which has to be at least `max_compressed_len` long,
+// and returns the length of the compressed body.
+fn compress_into(
+    uncomp_body: &[u8],
+    compression: Compression,
+    mut out: &mut [u8],
+) -> Result<usize, FrameError> {
     match compression {
         Compression::Lz4 => {
             let uncomp_len = uncomp_body.len() as u32;
-            let tmp = lz4_flex::compress(uncomp_body);
-            out.reserve_exact(std::mem::size_of::<u32>() + tmp.len());
             out.put_u32(uncomp_len);
-            out.extend_from_slice(&tmp[..]);
-            Ok(())
-        }
-        Compression::Snappy => {
-            let old_size = out.len();
-            out.resize(old_size + snap::raw::max_compress_len(uncomp_body.len()), 0);
-            let compressed_size = snap::raw::Encoder::new()
-                .compress(uncomp_body, &mut out[old_size..])
+            let compressed_len = lz4_flex::compress_into(uncomp_body, out)
                 .map_err(|_| FrameError::FrameCompression)?;
-            out.truncate(old_size + compressed_size);
-            Ok(())
+            Ok(std::mem::size_of::<u32>() + compressed_len)
         }
+        Compression::Snappy => snap::raw::Encoder::new()
+            .compress(uncomp_body, out)
+            .map_err(|_| FrameError::FrameCompression),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd { level } => zstd::bulk::compress_to_buffer(uncomp_body, out, level)
+            .map_err(|_| FrameError::FrameCompression),
+        #[cfg(not(feature = "zstd"))]
+        Compression::Zstd { .. } => Err(FrameError::FrameCompression),
     }
 }
 
@@ -267,6 +475,12 @@ fn decompress(mut comp_body: &[u8], compression: Compression) -> Result<Vec<u8>,
         Compression::Snappy => snap::raw::Decoder::new()
             .decompress_vec(comp_body)
             .map_err(|_| FrameError::FrameDecompression),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd { .. } => {
+            zstd::stream::decode_all(comp_body).map_err(|_| FrameError::FrameDecompression)
+        }
+        #[cfg(not(feature = "zstd"))]
+        Compression::Zstd { .. } => Err(FrameError::FrameDecompression),
     }
 }
 
@@ -284,25 +498,217 @@ mod test {
 
     #[test]
     fn test_lz4_compress() {
-        let mut out = Vec::from(&b"Hello"[..]);
-        let uncomp_body = b", World!";
+        let mut out = Vec::from(&b"Hello, World!"[..]);
         let compression = Compression::Lz4;
         let expect = vec![
             72, 101, 108, 108, 111, 0, 0, 0, 8, 128, 44, 32, 87, 111, 114, 108, 100, 33,
         ];
 
-        compress_append(uncomp_body, compression, &mut out).unwrap();
+        compress_in_place(&mut out, 5, compression).unwrap();
         assert_eq!(expect, out);
     }
 
     #[test]
     fn test_lz4_decompress() {
-        let mut comp_body = Vec::new();
         let uncomp_body = "Hello, World!".repeat(100);
+        let mut comp_body = uncomp_body.clone().into_bytes();
         let compression = Compression::Lz4;
-        compress_append(uncomp_body.as_bytes(), compression, &mut comp_body).unwrap();
+        compress_in_place(&mut comp_body, 0, compression).unwrap();
         let result = decompress(&comp_body[..], compression).unwrap();
         assert_eq!(32, comp_body.len());
         assert_eq!(uncomp_body.as_bytes(), result);
     }
+
+    #[test]
+    fn test_custom_payload_serialization() {
+        let query = request::Query {
+            contents: std::borrow::Cow::Borrowed("SELECT * FROM ks.t"),
+            parameters: Default::default(),
+        };
+        let custom_payload: HashMap<String, Bytes> = [
+            ("key1".to_owned(), Bytes::from_static(&[1, 2, 3])),
+            ("key2".to_owned(), Bytes::new()),
+        ]
+        .into_iter()
+        .collect();
+
+        for compression in [None, Some(Compression::Lz4), Some(Compression::Snappy)] {
+            let serialized =
+                SerializedRequest::make(&query, compression, false, Some(&custom_payload)).unwrap();
+            let data = serialized.get_data();
+            let flags = data[1];
+            assert_ne!(flags & FLAG_CUSTOM_PAYLOAD, 0);
+
+            let mut body: Vec<u8> = data[HEADER_SIZE..].to_vec();
+            if let Some(compression) = compression {
+                body = decompress(&body, compression).unwrap();
+            }
+            let buf = &mut &body[..];
+            let payload = types::read_bytes_map(buf).unwrap();
+            assert_eq!(payload.len(), custom_payload.len());
+            for (key, value) in &custom_payload {
+                assert_eq!(payload[key], value.as_ref());
+            }
+            assert_eq!(*buf, &query.to_bytes().unwrap()[..]);
+        }
+
+        let serialized = SerializedRequest::make(&query, None, false, None).unwrap();
+        assert_eq!(serialized.get_data()[1] & FLAG_CUSTOM_PAYLOAD, 0);
+    }
+
+    #[test]
+    fn test_compression_threshold() {
+        let query = request::Query {
+            contents: std::borrow::Cow::Borrowed("SELECT host_id FROM system.local"),
+            parameters: Default::default(),
+        };
+        let body_len = SerializedRequest::make(&query, None, false, None)
+            .unwrap()
+            .body_len();
+
+        let compressed = SerializedRequest::make_with_compression_threshold(
+            &query,
+            Some(Compression::Lz4),
+            body_len,
+            false,
+            None,
+        )
+        .unwrap();
+        assert_ne!(compressed.get_data()[1] & FLAG_COMPRESSION, 0);
+        assert_eq!(compressed.uncompressed_body_len(), Some(body_len));
+
+        let uncompressed = SerializedRequest::make_with_compression_threshold(
+            &query,
+            Some(Compression::Lz4),
+            body_len + 1,
+            false,
+            None,
+        )
+        .unwrap();
+        assert_eq!(uncompressed.get_data()[1] & FLAG_COMPRESSION, 0);
+        assert_eq!(uncompressed.uncompressed_body_len(), None);
+        assert_eq!(uncompressed.body_len(), body_len);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_read_and_skip_response_frame_bodies() {
+        // Two RESULT frames with bodies of 4 and 2 bytes.
+        let mut frames: Vec<u8> = Vec::new();
+        for (stream, body) in [(1_i16, &b"skip"[..]), (2_i16, &b"ok"[..])] {
+            frames.extend_from_slice(&[0x84, 0]);
+            frames.extend_from_slice(&stream.to_be_bytes());
+            frames.push(ResponseOpcode::Result as u8);
+            frames.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            frames.extend_from_slice(body);
+        }
+        let mut reader = &frames[..];
+
+        let (params, opcode, length) = read_response_frame_header(&mut reader).await.unwrap();
+        assert_eq!(
+            (params.stream, opcode, length),
+            (1, ResponseOpcode::Result, 4)
+        );
+        skip_response_frame_body(&mut reader, length).await.unwrap();
+
+        let (params, _, length) = read_response_frame_header(&mut reader).await.unwrap();
+        assert_eq!(params.stream, 2);
+        let body = read_response_frame_body(&mut reader, length).await.unwrap();
+        assert_eq!(&body[..], b"ok");
+
+        // A body cut short by the end of the stream.
+        let mut reader = &b"abc"[..];
+        assert!(matches!(
+            skip_response_frame_body(&mut reader, 8).await,
+            Err(FrameError::ConnectionClosed(5, 8))
+        ));
+    }
+
+    #[test]
+    fn test_parse_response_frames_from_slice() {
+        let mut frames: Vec<u8> = Vec::new();
+        for (stream, body) in [(1_i16, &b"first"[..]), (2_i16, &b""[..])] {
+            frames.extend_from_slice(&[0x84, FLAG_TRACING]);
+            frames.extend_from_slice(&stream.to_be_bytes());
+            frames.push(ResponseOpcode::Result as u8);
+            frames.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            frames.extend_from_slice(body);
+        }
+        let mut buf = &frames[..];
+
+        let (params, opcode, body) = parse_response_frame(&mut buf).unwrap();
+        assert_eq!(
+            (params.stream, params.flags, opcode),
+            (1, FLAG_TRACING, ResponseOpcode::Result)
+        );
+        assert_eq!(&body[..], b"first");
+        let (params, _, body) = parse_response_frame(&mut buf).unwrap();
+        assert_eq!(params.stream, 2);
+        assert!(body.is_empty());
+        assert!(buf.is_empty());
+
+        // Truncated header and body
+        assert!(parse_response_frame(&mut &frames[..5]).is_err());
+        assert!(parse_response_frame(&mut &frames[..HEADER_SIZE + 2]).is_err());
+        // A request frame
+        let mut request = frames.clone();
+        request[0] = 0x04;
+        assert!(matches!(
+            parse_response_frame(&mut &request[..]),
+            Err(FrameError::FrameFromClient)
+        ));
+    }
+
+    #[test]
+    fn test_make_with_buffer() {
+        let query = request::Query {
+            contents: std::borrow::Cow::Borrowed("SELECT host_id FROM system.local"),
+            parameters: Default::default(),
+        };
+        for compression in [Compression::Lz4, Compression::Snappy] {
+            let expected = SerializedRequest::make(&query, Some(compression), true, None).unwrap();
+            let body = &expected.get_data()[HEADER_SIZE..];
+            let mut expected_body = Vec::new();
+            query.serialize(&mut expected_body).unwrap();
+            assert_eq!(decompress(body, compression).unwrap(), expected_body);
+
+            let mut buffer = vec![0xff; 4096];
+            let capacity = buffer.capacity();
+            for _ in 0..2 {
+                let serialized = SerializedRequest::make_with_buffer(
+                    buffer,
+                    &query,
+                    Some(compression),
+                    0,
+                    true,
+                    None,
+                )
+                .unwrap();
+                assert_eq!(serialized.get_data(), expected.get_data());
+                buffer = serialized.into_buffer();
+                assert_eq!(buffer.capacity(), capacity);
+            }
+        }
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_compress_decompress() {
+        let uncomp_body = "Hello, World!".repeat(100);
+        let compression = Compression::Zstd { level: 3 };
+        let mut comp_body = uncomp_body.clone().into_bytes();
+        compress_in_place(&mut comp_body, 0, compression).unwrap();
+        assert!(comp_body.len() < uncomp_body.len());
+        let result = decompress(&comp_body[..], compression).unwrap();
+        assert_eq!(result, uncomp_body.as_bytes());
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[test]
+    fn test_zstd_not_supported() {
+        let compression = Compression::Zstd { level: 3 };
+        assert!(!compression.is_supported());
+        let mut comp_body = Vec::from(&b"Hello, World!"[..]);
+        assert!(compress_in_place(&mut comp_body, 0, compression).is_err());
+    }
 }
@@ -28,3 +28,33 @@ pub const CLIENT_ID: &str = "CLIENT_ID";
 pub const DEFAULT_CQL_PROTOCOL_VERSION: &str = "4.0.0";
 pub const DEFAULT_DRIVER_NAME: &str = "ScyllaDB Rust Driver";
 pub const DEFAULT_DRIVER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Checks if the option is set in STARTUP by the driver itself, so that it can't be
+/// set as a custom option. Apart from the listed keys, this includes all `SCYLLA_*` options,
+/// by which the driver negotiates ScyllaDB protocol extensions.
+pub fn is_reserved_startup_option(key: &str) -> bool {
+    const RESERVED: [&str; 7] = [
+        COMPRESSION,
+        CQL_VERSION,
+        DRIVER_NAME,
+        DRIVER_VERSION,
+        APPLICATION_NAME,
+        APPLICATION_VERSION,
+        CLIENT_ID,
+    ];
+    RESERVED.contains(&key) || key.starts_with("SCYLLA_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_reserved_startup_option;
+
+    #[test]
+    fn reserved_startup_options() {
+        assert!(is_reserved_startup_option("CQL_VERSION"));
+        assert!(is_reserved_startup_option("DRIVER_NAME"));
+        assert!(is_reserved_startup_option("SCYLLA_LWT_ADD_METADATA_MARK"));
+        assert!(!is_reserved_startup_option("TENANT_ID"));
+        assert!(!is_reserved_startup_option("compression"));
+    }
+}
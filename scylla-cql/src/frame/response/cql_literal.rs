@@ -0,0 +1,1317 @@
+//! Conversion of [`CqlValue`]s to and from CQL literals.
+//!
+//! A CQL literal is the textual form of a value as it can be embedded
+//! in a CQL statement, e.g. `'it''s'`, `{'a': [1, 2]}` or `'2023-05-01 12:00:00.000+0000'`.
+//! It comes in handy when generating scripts or audit logs from data read from the database.
+
+use std::fmt::Write;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::cql_lexer::{self, TokenKind};
+use crate::frame::response::result::{ColumnType, CqlValue};
+use crate::frame::value::{
+    Counter, CqlDate, CqlDecimal, CqlDuration, CqlTime, CqlTimestamp, CqlTimeuuid, CqlVarint,
+};
+
+/// An error returned when a CQL literal can't be parsed as a value of the requested type.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("Invalid CQL literal at position {position}: {reason}")]
+pub struct CqlLiteralParseError {
+    /// Byte offset in the parsed text at which the problem was detected.
+    pub position: usize,
+    /// Description of the problem.
+    pub reason: String,
+}
+
+/// An error returned when a value can't be written as a CQL literal.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CqlLiteralFormatError {
+    /// The components of the duration have different signs, which CQL duration literals
+    /// can't express.
+    #[error(
+        "Duration {0:?} has components with different signs and can't be written as a CQL literal"
+    )]
+    MixedSignDuration(CqlDuration),
+}
+
+impl CqlValue {
+    /// Formats the value as a CQL literal which can be embedded in a CQL statement.
+    ///
+    /// Parsing the result with [`CqlValue::parse_cql_literal`] and the column type of the value
+    /// gives back an equal value.
+    ///
+    /// Strings are single-quoted, with embedded quotes doubled. Dates, times and timestamps
+    /// are written as quoted strings in UTC (`'2023-05-01'`, `'12:30:00.000000000'`,
+    /// `'2023-05-01 12:30:00.000+0000'`); values whose year can't be written with four digits
+    /// fall back to their raw numeric form. [`CqlValue::Empty`] is written as `0x`.
+    ///
+    /// A duration whose components have mixed signs can't be expressed in CQL, so formatting
+    /// it (or a collection containing it) fails with [`CqlLiteralFormatError::MixedSignDuration`].
+    pub fn to_cql_literal(&self) -> Result<String, CqlLiteralFormatError> {
+        let mut literal = String::new();
+        write_literal(&mut literal, self)?;
+        Ok(literal)
+    }
+
+    /// Parses a CQL literal as a value of the given type.
+    ///
+    /// Accepts the output of [`CqlValue::to_cql_literal`], as well as the other literal forms
+    /// commonly used in CQL statements: `$$`-quoted strings, timestamps given as milliseconds
+    /// since the epoch or without a time zone (which is then assumed to be UTC),
+    /// durations with any of the units supported by CQL or in the ISO 8601 format with
+    /// designators (see [`CqlDuration`]'s `FromStr` implementation), and UDT literals with fields
+    /// in any order. UDT fields missing from the literal are set to `None`.
+    ///
+    /// Custom types are not supported. `null` is only accepted as an element of a tuple
+    /// or as a field of a UDT, since [`CqlValue`] can't represent it on its own.
+    pub fn parse_cql_literal(
+        literal: &str,
+        typ: &ColumnType,
+    ) -> Result<CqlValue, CqlLiteralParseError> {
+        let mut parser = Parser {
+            input: literal,
+            pos: 0,
+        };
+        let value = parser.parse_value(typ)?;
+        parser.skip_whitespace();
+        if parser.pos != literal.len() {
+            return Err(parser.error("unexpected characters after the literal"));
+        }
+        Ok(value)
+    }
+}
+
+/// Displays the varint in decimal notation, e.g. `-1234`, as in CQL literals.
+impl std::fmt::Display for CqlVarint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&signed_bytes_to_decimal(self.as_signed_bytes_be_slice()))
+    }
+}
+
+/// Displays the decimal in decimal notation, e.g. `12.34` or `5E3`, as in CQL literals.
+impl std::fmt::Display for CqlDecimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (bytes, scale) = self.as_signed_be_bytes_slice_and_exponent();
+        let mut out = String::new();
+        write_decimal(&mut out, bytes, scale);
+        f.write_str(&out)
+    }
+}
+
+fn write_literal(out: &mut String, value: &CqlValue) -> Result<(), CqlLiteralFormatError> {
+    match value {
+        CqlValue::Ascii(s) | CqlValue::Text(s) => write_string(out, s),
+        CqlValue::Boolean(b) => out.push_str(if *b { "true" } else { "false" }),
+        CqlValue::Blob(bytes) => {
+            out.push_str("0x");
+            for byte in bytes {
+                let _ = write!(out, "{:02x}", byte);
+            }
+        }
+        CqlValue::Empty => out.push_str("0x"),
+        CqlValue::Counter(Counter(c)) => {
+            let _ = write!(out, "{}", c);
+        }
+        CqlValue::Decimal(d) => {
+            let _ = write!(out, "{}", d);
+        }
+        CqlValue::Varint(v) => {
+            let _ = write!(out, "{}", v);
+        }
+        CqlValue::Float(f) => write_float(out, format!("{:?}", f)),
+        CqlValue::Double(d) => write_float(out, format!("{:?}", d)),
+        CqlValue::Int(i) => {
+            let _ = write!(out, "{}", i);
+        }
+        CqlValue::BigInt(i) => {
+            let _ = write!(out, "{}", i);
+        }
+        CqlValue::SmallInt(i) => {
+            let _ = write!(out, "{}", i);
+        }
+        CqlValue::TinyInt(i) => {
+            let _ = write!(out, "{}", i);
+        }
+        CqlValue::Date(CqlDate(raw)) => {
+            let (year, month, day) = civil_from_days(*raw as i64 - DATE_EPOCH);
+            if (0..=9999).contains(&year) {
+                let _ = write!(out, "'{:04}-{:02}-{:02}'", year, month, day);
+            } else {
+                let _ = write!(out, "'{}'", raw);
+            }
+        }
+        CqlValue::Time(CqlTime(nanos)) => {
+            if (0..NANOS_PER_DAY).contains(nanos) {
+                out.push('\'');
+                write_time_of_day(out, *nanos);
+                out.push('\'');
+            } else {
+                let _ = write!(out, "'{}'", nanos);
+            }
+        }
+        CqlValue::Timestamp(CqlTimestamp(millis)) => {
+            let days = millis.div_euclid(MILLIS_PER_DAY);
+            let millis_of_day = millis.rem_euclid(MILLIS_PER_DAY);
+            let (year, month, day) = civil_from_days(days);
+            if (0..=9999).contains(&year) {
+                let secs = millis_of_day / 1000;
+                let _ = write!(
+                    out,
+                    "'{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}+0000'",
+                    year,
+                    month,
+                    day,
+                    secs / 3600,
+                    secs / 60 % 60,
+                    secs % 60,
+                    millis_of_day % 1000
+                );
+            } else {
+                let _ = write!(out, "{}", millis);
+            }
+        }
+        CqlValue::Duration(d) => write_duration(out, d)?,
+        CqlValue::Inet(addr) => {
+            let _ = write!(out, "'{}'", addr);
+        }
+        CqlValue::Uuid(uuid) => {
+            let _ = write!(out, "{}", uuid);
+        }
+        CqlValue::Timeuuid(uuid) => {
+            let _ = write!(out, "{}", uuid);
+        }
+        CqlValue::List(elements) => write_elements(out, '[', ']', elements.iter().map(Some))?,
+        CqlValue::Set(elements) => write_elements(out, '{', '}', elements.iter().map(Some))?,
+        CqlValue::Tuple(elements) => {
+            write_elements(out, '(', ')', elements.iter().map(Option::as_ref))?
+        }
+        CqlValue::Map(entries) => {
+            out.push('{');
+            for (i, (key, value)) in entries.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_literal(out, key)?;
+                out.push_str(": ");
+                write_literal(out, value)?;
+            }
+            out.push('}');
+        }
+        CqlValue::UserDefinedType { fields, .. } => {
+            out.push('{');
+            for (i, (name, value)) in fields.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                // Field names are always quoted, so that names which are not lowercase
+                // or collide with CQL keywords are written correctly.
+                out.push('"');
+                out.push_str(&name.replace('"', "\"\""));
+                out.push_str("\": ");
+                write_nullable(out, value.as_ref())?;
+            }
+            out.push('}');
+        }
+    }
+    Ok(())
+}
+
+fn write_nullable(out: &mut String, value: Option<&CqlValue>) -> Result<(), CqlLiteralFormatError> {
+    match value {
+        Some(value) => write_literal(out, value)?,
+        None => out.push_str("null"),
+    }
+    Ok(())
+}
+
+fn write_elements<'a>(
+    out: &mut String,
+    open: char,
+    close: char,
+    elements: impl Iterator<Item = Option<&'a CqlValue>>,
+) -> Result<(), CqlLiteralFormatError> {
+    out.push(open);
+    for (i, element) in elements.enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        write_nullable(out, element)?;
+    }
+    out.push(close);
+    Ok(())
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('\'');
+    out.push_str(&s.replace('\'', "''"));
+    out.push('\'');
+}
+
+// Takes the `Debug` representation of a float, which is the shortest one that parses
+// back to the same value, and replaces the infinities with their CQL spelling.
+fn write_float(out: &mut String, repr: String) {
+    out.push_str(match repr.as_str() {
+        "inf" => "Infinity",
+        "-inf" => "-Infinity",
+        repr => repr,
+    });
+}
+
+fn write_decimal(out: &mut String, unscaled: &[u8], scale: i32) {
+    let digits = signed_bytes_to_decimal(unscaled);
+    let (sign, digits) = match digits.strip_prefix('-') {
+        Some(magnitude) => ("-", magnitude),
+        None => ("", digits.as_str()),
+    };
+    out.push_str(sign);
+    if scale <= 0 {
+        out.push_str(digits);
+        if scale < 0 {
+            let _ = write!(out, "E{}", -(scale as i64));
+        }
+    } else {
+        let scale = scale as usize;
+        if digits.len() > scale {
+            let (integral, fractional) = digits.split_at(digits.len() - scale);
+            let _ = write!(out, "{}.{}", integral, fractional);
+        } else {
+            let _ = write!(out, "0.{:0>width$}", digits, width = scale);
+        }
+    }
+}
+
+fn write_time_of_day(out: &mut String, nanos: i64) {
+    let secs = nanos / 1_000_000_000;
+    let _ = write!(
+        out,
+        "{:02}:{:02}:{:02}.{:09}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        nanos % 1_000_000_000
+    );
+}
+
+fn write_duration(out: &mut String, d: &CqlDuration) -> Result<(), CqlLiteralFormatError> {
+    let negative = d.months < 0 || d.days < 0 || d.nanoseconds < 0;
+    if negative && (d.months > 0 || d.days > 0 || d.nanoseconds > 0) {
+        return Err(CqlLiteralFormatError::MixedSignDuration(*d));
+    }
+    let _ = write!(out, "{}", d);
+    Ok(())
+}
+
+// Converts a big-endian two's complement integer to its decimal representation.
+fn signed_bytes_to_decimal(bytes: &[u8]) -> String {
+    let negative = matches!(bytes.first(), Some(byte) if byte & 0x80 != 0);
+    let mut magnitude = bytes.to_vec();
+    if negative {
+        negate(&mut magnitude);
+    }
+
+    // Base 10^9 digits, least significant first.
+    let mut chunks = Vec::new();
+    loop {
+        let leading_zeros = magnitude.iter().take_while(|byte| **byte == 0).count();
+        magnitude.drain(..leading_zeros);
+        if magnitude.is_empty() {
+            break;
+        }
+        let mut remainder = 0u64;
+        for byte in magnitude.iter_mut() {
+            let current = remainder << 8 | *byte as u64;
+            *byte = (current / 1_000_000_000) as u8;
+            remainder = current % 1_000_000_000;
+        }
+        chunks.push(remainder);
+    }
+
+    let mut result = String::new();
+    if negative {
+        result.push('-');
+    }
+    match chunks.split_last() {
+        None => result.push('0'),
+        Some((most_significant, rest)) => {
+            let _ = write!(result, "{}", most_significant);
+            for chunk in rest.iter().rev() {
+                let _ = write!(result, "{:09}", chunk);
+            }
+        }
+    }
+    result
+}
+
+// Converts a string of decimal digits to a minimal big-endian two's complement integer.
+fn decimal_to_signed_bytes(negative: bool, digits: &str) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+    for digit in digits.bytes() {
+        let mut carry = (digit - b'0') as u32;
+        for byte in bytes.iter_mut().rev() {
+            let current = *byte as u32 * 10 + carry;
+            *byte = current as u8;
+            carry = current >> 8;
+        }
+        if carry > 0 {
+            bytes.insert(0, carry as u8);
+        }
+    }
+    // Make room for the sign bit.
+    if !matches!(bytes.first(), Some(byte) if byte & 0x80 == 0) {
+        bytes.insert(0, 0);
+    }
+    if negative {
+        negate(&mut bytes);
+        while bytes.len() > 1 && bytes[0] == 0xff && bytes[1] & 0x80 != 0 {
+            bytes.remove(0);
+        }
+    }
+    bytes
+}
+
+// Negates a big-endian two's complement integer in place, wrapping on overflow.
+fn negate(bytes: &mut [u8]) {
+    let mut carry = true;
+    for byte in bytes.iter_mut().rev() {
+        let (negated, overflow) = (!*byte).overflowing_add(carry as u8);
+        *byte = negated;
+        carry = overflow;
+    }
+}
+
+const DATE_EPOCH: i64 = 1 << 31;
+const MILLIS_PER_DAY: i64 = 24 * 60 * 60 * 1000;
+const NANOS_PER_DAY: i64 = MILLIS_PER_DAY * 1_000_000;
+// Timestamps, the widest date type, end in year 292278994.
+const MAX_YEAR_DIGITS: usize = 9;
+
+// Number of days since 1970-01-01 of the given date of the proleptic Gregorian calendar.
+// See http://howardhinnant.github.io/date_algorithms.html#days_from_civil.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month_from_march = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+// Inverse of `days_from_civil`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, reason: impl Into<String>) -> CqlLiteralParseError {
+        Self::error_at(self.pos, reason)
+    }
+
+    fn error_at(position: usize, reason: impl Into<String>) -> CqlLiteralParseError {
+        CqlLiteralParseError {
+            position,
+            reason: reason.into(),
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.pos = self.input.len() - self.rest().trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), CqlLiteralParseError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected '{}'", c)))
+        }
+    }
+
+    // Reads an unquoted token: a number, a boolean, a UUID, a duration, a blob or `null`.
+    fn token(&mut self) -> (usize, &'a str) {
+        self.skip_whitespace();
+        let start = self.pos;
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_alphanumeric() || matches!(c, '-' | '+' | '.' | '_')))
+            .unwrap_or(rest.len());
+        self.pos += len;
+        (start, &self.input[start..self.pos])
+    }
+
+    // Checks whether the next token is the given keyword, and consumes it if so.
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let saved_pos = self.pos;
+        let (_, token) = self.token();
+        if token.eq_ignore_ascii_case(keyword) {
+            true
+        } else {
+            self.pos = saved_pos;
+            false
+        }
+    }
+
+    fn parse_token<T: FromStr>(&mut self, type_name: &str) -> Result<T, CqlLiteralParseError> {
+        let (start, token) = self.token();
+        token
+            .parse()
+            .map_err(|_| Self::error_at(start, format!("invalid {} literal", type_name)))
+    }
+
+    fn quoted_string(&mut self) -> Result<(usize, String), CqlLiteralParseError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        match cql_lexer::next_token(self.rest()) {
+            Some(token) if token.kind == TokenKind::String => {
+                if !token.terminated {
+                    return Err(Self::error_at(start, "unterminated string literal"));
+                }
+                self.pos += token.len;
+                Ok((start, cql_lexer::unquote(&self.input[start..self.pos])))
+            }
+            _ => Err(self.error("expected a string literal")),
+        }
+    }
+
+    fn is_at_string(&mut self) -> bool {
+        matches!(self.peek(), Some('\'' | '$'))
+    }
+
+    fn identifier(&mut self) -> Result<String, CqlLiteralParseError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        if let Some(token) = cql_lexer::next_token(self.rest()) {
+            if token.kind == TokenKind::QuotedIdentifier {
+                if !token.terminated {
+                    return Err(Self::error_at(start, "unterminated quoted identifier"));
+                }
+                self.pos += token.len;
+                return Ok(cql_lexer::unquote(&self.input[start..self.pos]));
+            }
+        }
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.error("expected a field name"));
+        }
+        self.pos += len;
+        // Unquoted identifiers are case-insensitive.
+        Ok(rest[..len].to_ascii_lowercase())
+    }
+
+    fn parse_nullable(
+        &mut self,
+        typ: &ColumnType,
+    ) -> Result<Option<CqlValue>, CqlLiteralParseError> {
+        if self.eat_keyword("null") {
+            Ok(None)
+        } else {
+            self.parse_value(typ).map(Some)
+        }
+    }
+
+    fn parse_value(&mut self, typ: &ColumnType) -> Result<CqlValue, CqlLiteralParseError> {
+        self.skip_whitespace();
+        let start = self.pos;
+
+        // An empty blob literal stands for an empty value of any type.
+        if !matches!(typ, ColumnType::Blob) && self.eat_keyword("0x") {
+            return Ok(CqlValue::Empty);
+        }
+        if self.eat_keyword("null") {
+            return Err(Self::error_at(start, "null is not supported here"));
+        }
+
+        let value = match typ {
+            ColumnType::Custom(name) => {
+                return Err(self.error(format!(
+                    "literals of custom type {} are not supported",
+                    name
+                )))
+            }
+            ColumnType::Ascii => {
+                let (start, s) = self.quoted_string()?;
+                if !s.is_ascii() {
+                    return Err(Self::error_at(
+                        start,
+                        "ascii literal contains non-ASCII characters",
+                    ));
+                }
+                CqlValue::Ascii(s)
+            }
+            ColumnType::Text => CqlValue::Text(self.quoted_string()?.1),
+            ColumnType::Boolean => {
+                let (start, token) = self.token();
+                if token.eq_ignore_ascii_case("true") {
+                    CqlValue::Boolean(true)
+                } else if token.eq_ignore_ascii_case("false") {
+                    CqlValue::Boolean(false)
+                } else {
+                    return Err(Self::error_at(start, "invalid boolean literal"));
+                }
+            }
+            ColumnType::Blob => {
+                let (start, token) = self.token();
+                CqlValue::Blob(
+                    parse_blob(token)
+                        .ok_or_else(|| Self::error_at(start, "invalid blob literal"))?,
+                )
+            }
+            ColumnType::Counter => CqlValue::Counter(Counter(self.parse_token("counter")?)),
+            ColumnType::Int => CqlValue::Int(self.parse_token("int")?),
+            ColumnType::BigInt => CqlValue::BigInt(self.parse_token("bigint")?),
+            ColumnType::SmallInt => CqlValue::SmallInt(self.parse_token("smallint")?),
+            ColumnType::TinyInt => CqlValue::TinyInt(self.parse_token("tinyint")?),
+            ColumnType::Float => CqlValue::Float(self.parse_token("float")?),
+            ColumnType::Double => CqlValue::Double(self.parse_token("double")?),
+            ColumnType::Decimal => {
+                let (start, token) = self.token();
+                CqlValue::Decimal(
+                    parse_decimal(token)
+                        .ok_or_else(|| Self::error_at(start, "invalid decimal literal"))?,
+                )
+            }
+            ColumnType::Varint => {
+                let (start, token) = self.token();
+                let (negative, digits) = split_sign(token);
+                if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(Self::error_at(start, "invalid varint literal"));
+                }
+                CqlValue::Varint(CqlVarint::from_signed_bytes_be(decimal_to_signed_bytes(
+                    negative, digits,
+                )))
+            }
+            ColumnType::Date => {
+                let (start, s) = self.quoted_string()?;
+                CqlValue::Date(CqlDate(
+                    parse_date(&s).ok_or_else(|| Self::error_at(start, "invalid date literal"))?,
+                ))
+            }
+            ColumnType::Time => {
+                let (start, s) = if self.is_at_string() {
+                    self.quoted_string()?
+                } else {
+                    let (start, token) = self.token();
+                    (start, token.to_owned())
+                };
+                CqlValue::Time(CqlTime(
+                    parse_time(&s).ok_or_else(|| Self::error_at(start, "invalid time literal"))?,
+                ))
+            }
+            ColumnType::Timestamp => {
+                let (start, s) = if self.is_at_string() {
+                    self.quoted_string()?
+                } else {
+                    let (start, token) = self.token();
+                    (start, token.to_owned())
+                };
+                CqlValue::Timestamp(CqlTimestamp(
+                    parse_timestamp(&s)
+                        .ok_or_else(|| Self::error_at(start, "invalid timestamp literal"))?,
+                ))
+            }
+            ColumnType::Duration => CqlValue::Duration(self.parse_token("duration")?),
+            ColumnType::Inet => {
+                let (start, s) = self.quoted_string()?;
+                CqlValue::Inet(
+                    IpAddr::from_str(&s)
+                        .map_err(|_| Self::error_at(start, "invalid inet literal"))?,
+                )
+            }
+            ColumnType::Uuid => CqlValue::Uuid(self.parse_token("uuid")?),
+            ColumnType::Timeuuid => {
+                CqlValue::Timeuuid(CqlTimeuuid::from(self.parse_token::<Uuid>("timeuuid")?))
+            }
+            ColumnType::List(element_type) => {
+                CqlValue::List(self.parse_elements('[', ']', element_type)?)
+            }
+            ColumnType::Set(element_type) => {
+                CqlValue::Set(self.parse_elements('{', '}', element_type)?)
+            }
+            ColumnType::Map(key_type, value_type) => {
+                self.expect('{')?;
+                let mut entries = Vec::new();
+                if !self.eat('}') {
+                    loop {
+                        let key = self.parse_value(key_type)?;
+                        self.expect(':')?;
+                        let value = self.parse_value(value_type)?;
+                        entries.push((key, value));
+                        if !self.eat(',') {
+                            self.expect('}')?;
+                            break;
+                        }
+                    }
+                }
+                CqlValue::Map(entries)
+            }
+            ColumnType::Tuple(element_types) => {
+                self.expect('(')?;
+                let mut elements = Vec::with_capacity(element_types.len());
+                for (i, element_type) in element_types.iter().enumerate() {
+                    if i > 0 {
+                        self.expect(',')?;
+                    }
+                    elements.push(self.parse_nullable(element_type)?);
+                }
+                self.expect(')')?;
+                CqlValue::Tuple(elements)
+            }
+            ColumnType::UserDefinedType {
+                type_name,
+                keyspace,
+                field_types,
+            } => {
+                self.expect('{')?;
+                let mut fields: Vec<(String, Option<CqlValue>)> = field_types
+                    .iter()
+                    .map(|(name, _)| (name.clone(), None))
+                    .collect();
+                let mut seen = vec![false; field_types.len()];
+                if !self.eat('}') {
+                    loop {
+                        self.skip_whitespace();
+                        let name_start = self.pos;
+                        let name = self.identifier()?;
+                        let idx = field_types
+                            .iter()
+                            .position(|(field_name, _)| *field_name == name)
+                            .ok_or_else(|| {
+                                Self::error_at(
+                                    name_start,
+                                    format!("unknown field {} of type {}", name, type_name),
+                                )
+                            })?;
+                        if seen[idx] {
+                            return Err(Self::error_at(
+                                name_start,
+                                format!("duplicate field {}", name),
+                            ));
+                        }
+                        seen[idx] = true;
+                        self.expect(':')?;
+                        fields[idx].1 = self.parse_nullable(&field_types[idx].1)?;
+                        if !self.eat(',') {
+                            self.expect('}')?;
+                            break;
+                        }
+                    }
+                }
+                CqlValue::UserDefinedType {
+                    keyspace: keyspace.clone(),
+                    type_name: type_name.clone(),
+                    fields,
+                }
+            }
+        };
+        Ok(value)
+    }
+
+    fn parse_elements(
+        &mut self,
+        open: char,
+        close: char,
+        element_type: &ColumnType,
+    ) -> Result<Vec<CqlValue>, CqlLiteralParseError> {
+        self.expect(open)?;
+        let mut elements = Vec::new();
+        if self.eat(close) {
+            return Ok(elements);
+        }
+        loop {
+            elements.push(self.parse_value(element_type)?);
+            if !self.eat(',') {
+                self.expect(close)?;
+                return Ok(elements);
+            }
+        }
+    }
+}
+
+fn split_sign(s: &str) -> (bool, &str) {
+    match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    }
+}
+
+fn parse_blob(s: &str) -> Option<Vec<u8>> {
+    let hex = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X"))?;
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn parse_decimal(s: &str) -> Option<CqlDecimal> {
+    let (negative, s) = split_sign(s);
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(idx) => (&s[..idx], s[idx + 1..].parse::<i64>().ok()?),
+        None => (s, 0),
+    };
+    let (integral, fractional) = match mantissa.find('.') {
+        Some(idx) => (&mantissa[..idx], &mantissa[idx + 1..]),
+        None => (mantissa, ""),
+    };
+    let digits = format!("{}{}", integral, fractional);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let scale = i32::try_from((fractional.len() as i64).checked_sub(exponent)?).ok()?;
+    Some(CqlDecimal::from_signed_be_bytes_and_exponent(
+        decimal_to_signed_bytes(negative, &digits),
+        scale,
+    ))
+}
+
+fn parse_number<T: FromStr>(s: &str, digits: usize) -> Option<T> {
+    if s.len() != digits || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+// Parses `yyyy-mm-dd` into the number of days since 1970-01-01.
+fn parse_civil_date(s: &str) -> Option<i64> {
+    let (negative, s) = split_sign(s);
+    let mut parts = s.splitn(3, '-');
+    let year_str = parts.next()?;
+    // Longer years are out of range of every date type, and could overflow `days_from_civil`.
+    if !(4..=MAX_YEAR_DIGITS).contains(&year_str.len())
+        || !year_str.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let year: i64 = year_str.parse().ok()?;
+    let year = if negative { -year } else { year };
+    let month: u32 = parse_number(parts.next()?, 2)?;
+    let day: u32 = parse_number(parts.next()?, 2)?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    // Rejects days past the end of the month, e.g. 2023-02-30.
+    if civil_from_days(days) != (year, month, day) {
+        return None;
+    }
+    Some(days)
+}
+
+fn parse_date(s: &str) -> Option<u32> {
+    if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+        return s.parse().ok();
+    }
+    u32::try_from(parse_civil_date(s)?.checked_add(DATE_EPOCH)?).ok()
+}
+
+// Parses `hh:mm[:ss[.fffffffff]]` into nanoseconds since midnight.
+// `max_fraction_digits` limits the precision of the fractional part.
+fn parse_time_of_day(s: &str, max_fraction_digits: usize) -> Option<i64> {
+    let (hms, fraction) = match s.find('.') {
+        Some(idx) => (&s[..idx], &s[idx + 1..]),
+        None => (s, ""),
+    };
+    if !fraction.is_empty() && (fraction.len() > max_fraction_digits || hms.len() != 8) {
+        return None;
+    }
+    let mut parts = hms.split(':');
+    let hours: i64 = parse_number(parts.next()?, 2)?;
+    let minutes: i64 = parse_number(parts.next()?, 2)?;
+    let seconds: i64 = match parts.next() {
+        Some(seconds) => parse_number(seconds, 2)?,
+        None => 0,
+    };
+    if parts.next().is_some() || hours >= 24 || minutes >= 60 || seconds >= 60 {
+        return None;
+    }
+    let fraction_nanos = if fraction.is_empty() {
+        0
+    } else {
+        let value: i64 = parse_number(fraction, fraction.len())?;
+        value * 10i64.pow(9 - fraction.len() as u32)
+    };
+    Some(((hours * 60 + minutes) * 60 + seconds) * 1_000_000_000 + fraction_nanos)
+}
+
+fn parse_time(s: &str) -> Option<i64> {
+    if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+        return s
+            .parse()
+            .ok()
+            .filter(|nanos| (0..NANOS_PER_DAY).contains(nanos));
+    }
+    parse_time_of_day(s, 9)
+}
+
+// Parses a time zone offset: `Z`, `+hh`, `+hhmm` or `+hh:mm`, into milliseconds.
+fn parse_offset(s: &str) -> Option<i64> {
+    if s == "Z" || s == "z" {
+        return Some(0);
+    }
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+')?),
+    };
+    let (hours, minutes) = match s.len() {
+        2 => (s, "00"),
+        4 => (s.get(..2)?, s.get(2..)?),
+        5 if s.get(2..3) == Some(":") => (&s[..2], &s[3..]),
+        _ => return None,
+    };
+    let hours: i64 = parse_number(hours, 2)?;
+    let minutes: i64 = parse_number(minutes, 2)?;
+    if hours > 23 || minutes >= 60 {
+        return None;
+    }
+    let offset = (hours * 60 + minutes) * 60 * 1000;
+    Some(if negative { -offset } else { offset })
+}
+
+fn parse_timestamp(s: &str) -> Option<i64> {
+    if let Ok(millis) = s.parse::<i64>() {
+        return Some(millis);
+    }
+
+    // The date is `yyyy-mm-dd`, possibly with a signed or longer year.
+    let sign_len = s.starts_with(['-', '+']) as usize;
+    let year_len = s[sign_len..].find(|c: char| !c.is_ascii_digit())?;
+    let date_len = sign_len + year_len + "-mm-dd".len();
+    let date = s.get(..date_len)?;
+    let rest = &s[date_len..];
+    let days = parse_civil_date(date)?;
+
+    let (time, offset) = match rest.strip_prefix([' ', 'T', 't']) {
+        Some(rest) => match rest.find(['+', '-', 'Z', 'z']) {
+            Some(idx) => (&rest[..idx], &rest[idx..]),
+            None => (rest, ""),
+        },
+        None => ("", rest),
+    };
+    let nanos_of_day = if time.is_empty() {
+        0
+    } else {
+        parse_time_of_day(time, 3)?
+    };
+    let offset = if offset.is_empty() {
+        0
+    } else {
+        parse_offset(offset)?
+    };
+
+    days.checked_mul(MILLIS_PER_DAY)?
+        .checked_add(nanos_of_day / 1_000_000)?
+        .checked_sub(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use std::str::FromStr;
+
+    use uuid::Uuid;
+
+    use super::{civil_from_days, days_from_civil, CqlLiteralFormatError, CqlLiteralParseError};
+    use crate::frame::response::result::{ColumnType, CqlValue};
+    use crate::frame::value::{
+        Counter, CqlDate, CqlDecimal, CqlDuration, CqlTime, CqlTimestamp, CqlTimeuuid, CqlVarint,
+    };
+
+    fn assert_round_trip(value: CqlValue, typ: ColumnType, expected_literal: &str) {
+        let literal = value.to_cql_literal().unwrap();
+        assert_eq!(literal, expected_literal);
+        assert_eq!(CqlValue::parse_cql_literal(&literal, &typ).unwrap(), value);
+    }
+
+    fn parse(literal: &str, typ: ColumnType) -> CqlValue {
+        CqlValue::parse_cql_literal(literal, &typ).unwrap()
+    }
+
+    #[test]
+    fn civil_date_conversions() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+        for days in [-719528, -1, 0, 1, 11016, 11017, 2932896] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn scalar_round_trips() {
+        assert_round_trip(
+            CqlValue::Text("it's".to_owned()),
+            ColumnType::Text,
+            "'it''s'",
+        );
+        assert_round_trip(CqlValue::Ascii("".to_owned()), ColumnType::Ascii, "''");
+        assert_round_trip(CqlValue::Boolean(true), ColumnType::Boolean, "true");
+        assert_round_trip(
+            CqlValue::Blob(vec![0x00, 0xab, 0xff]),
+            ColumnType::Blob,
+            "0x00abff",
+        );
+        assert_round_trip(CqlValue::Blob(vec![]), ColumnType::Blob, "0x");
+        assert_round_trip(CqlValue::Empty, ColumnType::Int, "0x");
+        assert_round_trip(CqlValue::Counter(Counter(-5)), ColumnType::Counter, "-5");
+        assert_round_trip(CqlValue::TinyInt(i8::MIN), ColumnType::TinyInt, "-128");
+        assert_round_trip(CqlValue::SmallInt(i16::MAX), ColumnType::SmallInt, "32767");
+        assert_round_trip(CqlValue::Int(i32::MIN), ColumnType::Int, "-2147483648");
+        assert_round_trip(
+            CqlValue::BigInt(i64::MAX),
+            ColumnType::BigInt,
+            "9223372036854775807",
+        );
+        assert_round_trip(CqlValue::Float(0.1), ColumnType::Float, "0.1");
+        assert_round_trip(CqlValue::Double(-1e300), ColumnType::Double, "-1e300");
+        assert_round_trip(
+            CqlValue::Double(f64::INFINITY),
+            ColumnType::Double,
+            "Infinity",
+        );
+        assert_round_trip(
+            CqlValue::Float(f32::NEG_INFINITY),
+            ColumnType::Float,
+            "-Infinity",
+        );
+        assert_eq!(CqlValue::Double(f64::NAN).to_cql_literal().unwrap(), "NaN");
+        assert!(matches!(
+            parse("NaN", ColumnType::Double),
+            CqlValue::Double(d) if d.is_nan()
+        ));
+        assert_round_trip(
+            CqlValue::Inet(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1))),
+            ColumnType::Inet,
+            "'127.0.0.1'",
+        );
+        assert_round_trip(
+            CqlValue::Inet(IpAddr::V6(Ipv6Addr::LOCALHOST)),
+            ColumnType::Inet,
+            "'::1'",
+        );
+        let uuid = Uuid::from_str("8e14e760-7fa8-11eb-bc66-000000000001").unwrap();
+        assert_round_trip(
+            CqlValue::Uuid(uuid),
+            ColumnType::Uuid,
+            "8e14e760-7fa8-11eb-bc66-000000000001",
+        );
+        assert_round_trip(
+            CqlValue::Timeuuid(CqlTimeuuid::from(uuid)),
+            ColumnType::Timeuuid,
+            "8e14e760-7fa8-11eb-bc66-000000000001",
+        );
+    }
+
+    #[test]
+    fn varint_and_decimal_round_trips() {
+        for (bytes, literal) in [
+            (vec![], "0"),
+            (vec![0x00], "0"),
+            (vec![0x7f], "127"),
+            (vec![0x80], "-128"),
+            (vec![0x00, 0x80], "128"),
+            (vec![0xff, 0x7f], "-129"),
+            (
+                vec![0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+                "18446744073709551616",
+            ),
+        ] {
+            assert_round_trip(
+                CqlValue::Varint(CqlVarint::from_signed_bytes_be(bytes)),
+                ColumnType::Varint,
+                literal,
+            );
+        }
+
+        for (bytes, scale, literal) in [
+            (vec![0x04, 0xd2], 2, "12.34"),
+            (vec![0xfb, 0x2e], 2, "-12.34"),
+            (vec![0x05], 3, "0.005"),
+            (vec![0x0c], -3, "12E3"),
+            (vec![0x00], 0, "0"),
+        ] {
+            assert_round_trip(
+                CqlValue::Decimal(CqlDecimal::from_signed_be_bytes_and_exponent(bytes, scale)),
+                ColumnType::Decimal,
+                literal,
+            );
+        }
+        assert_eq!(
+            parse("1.5e-2", ColumnType::Decimal),
+            CqlValue::Decimal(CqlDecimal::from_signed_be_bytes_and_exponent(vec![0x0f], 3))
+        );
+    }
+
+    #[test]
+    fn date_and_time_round_trips() {
+        assert_round_trip(
+            CqlValue::Date(CqlDate((1 << 31) + 19478)),
+            ColumnType::Date,
+            "'2023-05-01'",
+        );
+        assert_round_trip(CqlValue::Date(CqlDate(0)), ColumnType::Date, "'0'");
+        assert_round_trip(
+            CqlValue::Time(CqlTime(45_296_000_000_123)),
+            ColumnType::Time,
+            "'12:34:56.000000123'",
+        );
+        assert_round_trip(
+            CqlValue::Timestamp(CqlTimestamp(1_682_944_496_789)),
+            ColumnType::Timestamp,
+            "'2023-05-01 12:34:56.789+0000'",
+        );
+        assert_round_trip(
+            CqlValue::Timestamp(CqlTimestamp(-1)),
+            ColumnType::Timestamp,
+            "'1969-12-31 23:59:59.999+0000'",
+        );
+        assert_round_trip(
+            CqlValue::Timestamp(CqlTimestamp(i64::MIN)),
+            ColumnType::Timestamp,
+            "-9223372036854775808",
+        );
+
+        let expected = CqlValue::Timestamp(CqlTimestamp(1_682_944_496_000));
+        for literal in [
+            "1682944496000",
+            "'2023-05-01 12:34:56'",
+            "'2023-05-01T12:34:56Z'",
+            "'2023-05-01 14:34:56+0200'",
+            "'2023-05-01 04:34:56-08:00'",
+            "$$2023-05-01T12:34:56.000+00$$",
+        ] {
+            assert_eq!(
+                parse(literal, ColumnType::Timestamp),
+                expected,
+                "{}",
+                literal
+            );
+        }
+        assert_eq!(
+            parse("'2023-05-01'", ColumnType::Timestamp),
+            CqlValue::Timestamp(CqlTimestamp(1_682_899_200_000))
+        );
+        assert_eq!(
+            parse("'2023-05-01-0100'", ColumnType::Timestamp),
+            CqlValue::Timestamp(CqlTimestamp(1_682_902_800_000))
+        );
+        assert_eq!(
+            parse("'12:00'", ColumnType::Time),
+            CqlValue::Time(CqlTime(43_200_000_000_000))
+        );
+        assert!(CqlValue::parse_cql_literal("'2023-02-29'", &ColumnType::Date).is_err());
+        assert!(CqlValue::parse_cql_literal("'24:00:00'", &ColumnType::Time).is_err());
+        for literal in [
+            "'2023-01-01 10:00+1é0'",
+            "'9223372036854775807-03-01'",
+            "'-9223372036854775807-01-01'",
+            "'1000000000-01-01'",
+        ] {
+            assert!(
+                CqlValue::parse_cql_literal(literal, &ColumnType::Timestamp).is_err(),
+                "{}",
+                literal
+            );
+            assert!(
+                CqlValue::parse_cql_literal(literal, &ColumnType::Date).is_err(),
+                "{}",
+                literal
+            );
+        }
+    }
+
+    #[test]
+    fn duration_round_trips() {
+        assert_round_trip(
+            CqlValue::Duration(CqlDuration {
+                months: 14,
+                days: 3,
+                nanoseconds: 5_000_000_001,
+            }),
+            ColumnType::Duration,
+            "1y2mo3d5s1ns",
+        );
+        assert_round_trip(
+            CqlValue::Duration(CqlDuration {
+                months: 0,
+                days: -2,
+                nanoseconds: -1,
+            }),
+            ColumnType::Duration,
+            "-2d1ns",
+        );
+        assert_round_trip(
+            CqlValue::Duration(CqlDuration {
+                months: 0,
+                days: 0,
+                nanoseconds: 0,
+            }),
+            ColumnType::Duration,
+            "0s",
+        );
+        assert_eq!(
+            parse("1y2w3h4m5s6ms7us8ns", ColumnType::Duration),
+            CqlValue::Duration(CqlDuration {
+                months: 12,
+                days: 14,
+                nanoseconds: 3 * 3_600_000_000_000 + 4 * 60_000_000_000 + 5_006_007_008,
+            })
+        );
+        assert_eq!(
+            parse("-P1DT2H", ColumnType::Duration),
+            CqlValue::Duration(CqlDuration {
+                months: 0,
+                days: -1,
+                nanoseconds: -2 * 3_600_000_000_000,
+            })
+        );
+
+        let mixed = CqlDuration {
+            months: 1,
+            days: -1,
+            nanoseconds: 0,
+        };
+        assert_eq!(
+            CqlValue::List(vec![CqlValue::Duration(mixed)]).to_cql_literal(),
+            Err(CqlLiteralFormatError::MixedSignDuration(mixed))
+        );
+    }
+
+    #[test]
+    fn collection_and_udt_round_trips() {
+        assert_round_trip(
+            CqlValue::List(vec![
+                CqlValue::Text("a".to_owned()),
+                CqlValue::Text("b'".to_owned()),
+            ]),
+            ColumnType::List(Box::new(ColumnType::Text)),
+            "['a', 'b''']",
+        );
+        assert_round_trip(
+            CqlValue::Set(vec![]),
+            ColumnType::Set(Box::new(ColumnType::Int)),
+            "{}",
+        );
+        assert_round_trip(
+            CqlValue::Map(vec![(
+                CqlValue::Int(1),
+                CqlValue::Set(vec![CqlValue::Boolean(false)]),
+            )]),
+            ColumnType::Map(
+                Box::new(ColumnType::Int),
+                Box::new(ColumnType::Set(Box::new(ColumnType::Boolean))),
+            ),
+            "{1: {false}}",
+        );
+        assert_round_trip(
+            CqlValue::Tuple(vec![Some(CqlValue::Int(1)), None]),
+            ColumnType::Tuple(vec![ColumnType::Int, ColumnType::Text]),
+            "(1, null)",
+        );
+
+        let udt_type = ColumnType::UserDefinedType {
+            type_name: "address".to_owned(),
+            keyspace: "ks".to_owned(),
+            field_types: vec![
+                ("street".to_owned(), ColumnType::Text),
+                ("Number".to_owned(), ColumnType::Int),
+                ("zip".to_owned(), ColumnType::Text),
+            ],
+        };
+        let udt = |street: Option<&str>, number: Option<i32>| CqlValue::UserDefinedType {
+            keyspace: "ks".to_owned(),
+            type_name: "address".to_owned(),
+            fields: vec![
+                (
+                    "street".to_owned(),
+                    street.map(|s| CqlValue::Text(s.to_owned())),
+                ),
+                ("Number".to_owned(), number.map(CqlValue::Int)),
+                ("zip".to_owned(), None),
+            ],
+        };
+        assert_round_trip(
+            udt(Some("Main"), Some(7)),
+            udt_type.clone(),
+            "{\"street\": 'Main', \"Number\": 7, \"zip\": null}",
+        );
+        // Fields may come in any order, unquoted names are case-insensitive
+        // and missing fields are null.
+        assert_eq!(
+            parse("{ \"Number\" : 7 , STREET : 'Main' }", udt_type.clone()),
+            udt(Some("Main"), Some(7))
+        );
+        assert_eq!(parse("{}", udt_type.clone()), udt(None, None));
+        assert!(CqlValue::parse_cql_literal("{number: 7}", &udt_type).is_err());
+        assert!(CqlValue::parse_cql_literal("{zip: 'a', zip: 'b'}", &udt_type).is_err());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            CqlValue::parse_cql_literal("[1, 2", &ColumnType::List(Box::new(ColumnType::Int))),
+            Err(CqlLiteralParseError {
+                position: 5,
+                reason: "expected ']'".to_owned(),
+            })
+        );
+        assert_eq!(
+            CqlValue::parse_cql_literal("128", &ColumnType::TinyInt)
+                .unwrap_err()
+                .position,
+            0
+        );
+        assert!(CqlValue::parse_cql_literal("'abc", &ColumnType::Text).is_err());
+        assert!(CqlValue::parse_cql_literal("'ż'", &ColumnType::Ascii).is_err());
+        assert!(CqlValue::parse_cql_literal("1 2", &ColumnType::Int).is_err());
+        assert!(CqlValue::parse_cql_literal("null", &ColumnType::Int).is_err());
+        assert!(CqlValue::parse_cql_literal("0x0", &ColumnType::Blob).is_err());
+        assert!(CqlValue::parse_cql_literal("'a'", &ColumnType::Custom("Foo".to_owned())).is_err());
+    }
+}
@@ -253,6 +253,22 @@ impl<T: FromCqlVal<CqlValue>> FromCqlVal<CqlValue> for Vec<T> {
     }
 }
 
+#[cfg(feature = "smallvec-1")]
+impl<A> FromCqlVal<CqlValue> for smallvec_1::SmallVec<A>
+where
+    A: smallvec_1::Array,
+    A::Item: FromCqlVal<CqlValue>,
+{
+    fn from_cql(cql_val: CqlValue) -> Result<Self, FromCqlValError> {
+        cql_val
+            .into_vec()
+            .ok_or(FromCqlValError::BadCqlType)?
+            .into_iter()
+            .map(A::Item::from_cql)
+            .collect()
+    }
+}
+
 impl<T1: FromCqlVal<CqlValue> + Eq + Hash, T2: FromCqlVal<CqlValue>, T3: BuildHasher + Default>
     FromCqlVal<CqlValue> for HashMap<T1, T2, T3>
 {
@@ -4,6 +4,8 @@ use crate::frame::protocol_features::ProtocolFeatures;
 use crate::frame::types;
 use byteorder::ReadBytesExt;
 use bytes::Bytes;
+use std::collections::HashMap;
+use std::net::IpAddr;
 
 #[derive(Debug, Clone)]
 pub struct Error {
@@ -13,6 +15,21 @@ pub struct Error {
 
 impl Error {
     pub fn deserialize(features: &ProtocolFeatures, buf: &mut &[u8]) -> Result<Self, ParseError> {
+        Self::deserialize_with_protocol_version(features, 4, buf)
+    }
+
+    /// Deserializes an error sent in a frame of the given protocol version.
+    ///
+    /// Since protocol v5, READ_FAILURE and WRITE_FAILURE errors carry a map of failure codes
+    /// per replica instead of the number of failures, and only then is `failure_reasons`
+    /// set. The driver itself negotiates protocol v4 and deserializes errors with
+    /// [`Error::deserialize`], so the errors it returns never have the map; this method
+    /// is meant for frames of other protocol versions, e.g. ones captured from other clients.
+    pub fn deserialize_with_protocol_version(
+        features: &ProtocolFeatures,
+        protocol_version: u8,
+        buf: &mut &[u8],
+    ) -> Result<Self, ParseError> {
         let code = types::read_int(buf)?;
         let reason = types::read_string(buf)?.to_owned();
 
@@ -40,25 +57,39 @@ impl Error {
                 required: types::read_int(buf)?,
                 data_present: buf.read_u8()? != 0,
             },
-            0x1300 => DbError::ReadFailure {
-                consistency: types::read_consistency(buf)?,
-                received: types::read_int(buf)?,
-                required: types::read_int(buf)?,
-                numfailures: types::read_int(buf)?,
-                data_present: buf.read_u8()? != 0,
-            },
+            0x1300 => {
+                let consistency = types::read_consistency(buf)?;
+                let received = types::read_int(buf)?;
+                let required = types::read_int(buf)?;
+                let (numfailures, failure_reasons) = read_failures(protocol_version, buf)?;
+                DbError::ReadFailure {
+                    consistency,
+                    received,
+                    required,
+                    numfailures,
+                    failure_reasons,
+                    data_present: buf.read_u8()? != 0,
+                }
+            }
             0x1400 => DbError::FunctionFailure {
                 keyspace: types::read_string(buf)?.to_string(),
                 function: types::read_string(buf)?.to_string(),
                 arg_types: types::read_string_list(buf)?,
             },
-            0x1500 => DbError::WriteFailure {
-                consistency: types::read_consistency(buf)?,
-                received: types::read_int(buf)?,
-                required: types::read_int(buf)?,
-                numfailures: types::read_int(buf)?,
-                write_type: WriteType::from(types::read_string(buf)?),
-            },
+            0x1500 => {
+                let consistency = types::read_consistency(buf)?;
+                let received = types::read_int(buf)?;
+                let required = types::read_int(buf)?;
+                let (numfailures, failure_reasons) = read_failures(protocol_version, buf)?;
+                DbError::WriteFailure {
+                    consistency,
+                    received,
+                    required,
+                    numfailures,
+                    failure_reasons,
+                    write_type: WriteType::from(types::read_string(buf)?),
+                }
+            }
             0x2000 => DbError::SyntaxError,
             0x2100 => DbError::Unauthorized,
             0x2200 => DbError::Invalid,
@@ -81,9 +112,33 @@ impl Error {
     }
 }
 
+// Reads <numfailures> (protocol v4) or <reasonmap> (protocol v5 and later)
+fn read_failures(
+    protocol_version: u8,
+    buf: &mut &[u8],
+) -> Result<(i32, Option<HashMap<IpAddr, u16>>), ParseError> {
+    if protocol_version < 5 {
+        return Ok((types::read_int(buf)?, None));
+    }
+
+    let numfailures = types::read_int(buf)?;
+    let mut failure_reasons = HashMap::new();
+    for _ in 0..numfailures {
+        let endpoint = types::read_inetaddr(buf)?;
+        let failure_code = types::read_short(buf)?;
+        failure_reasons.insert(endpoint, failure_code);
+    }
+
+    Ok((numfailures, Some(failure_reasons)))
+}
+
 impl From<Error> for QueryError {
     fn from(error: Error) -> QueryError {
-        QueryError::DbError(error.error, error.reason)
+        QueryError::DbError {
+            error: error.error,
+            message: error.reason,
+            node: None,
+        }
     }
 }
 
@@ -94,7 +149,9 @@ mod tests {
     use crate::frame::protocol_features::ProtocolFeatures;
     use crate::Consistency;
     use bytes::Bytes;
+    use std::collections::HashMap;
     use std::convert::TryInto;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
     // Serializes the beginning of an ERROR response - error code and message
     // All custom data depending on the error type is appended after these bytes
@@ -230,12 +287,50 @@ mod tests {
                 received: 4,
                 required: 5,
                 numfailures: 6,
+                failure_reasons: None,
                 data_present: true,
             }
         );
         assert_eq!(error.reason, "message 2");
     }
 
+    #[test]
+    fn deserialize_read_failure_reason_map() {
+        let features = ProtocolFeatures::default();
+
+        let mut bytes = make_error_request_bytes(0x1300, "message 2");
+        bytes.extend(0x0003_i16.to_be_bytes());
+        bytes.extend(4_i32.to_be_bytes());
+        bytes.extend(5_i32.to_be_bytes());
+        bytes.extend(2_i32.to_be_bytes());
+        bytes.push(4_u8);
+        bytes.extend([127, 0, 0, 1]);
+        bytes.extend(0x0001_u16.to_be_bytes());
+        bytes.push(16_u8);
+        bytes.extend(Ipv6Addr::LOCALHOST.octets());
+        bytes.extend(0x0000_u16.to_be_bytes());
+        bytes.push(0_u8);
+
+        let error: Error =
+            Error::deserialize_with_protocol_version(&features, 5, &mut bytes.as_slice()).unwrap();
+
+        assert_eq!(
+            error.error,
+            DbError::ReadFailure {
+                consistency: Consistency::Three,
+                received: 4,
+                required: 5,
+                numfailures: 2,
+                failure_reasons: Some(HashMap::from([
+                    (IpAddr::V4(Ipv4Addr::LOCALHOST), 0x0001),
+                    (IpAddr::V6(Ipv6Addr::LOCALHOST), 0x0000),
+                ])),
+                data_present: false,
+            }
+        );
+        assert_eq!(error.reason, "message 2");
+    }
+
     #[test]
     fn deserialize_function_failure() {
         let features = ProtocolFeatures::default();
@@ -302,12 +397,51 @@ mod tests {
                 received: 2,
                 required: 4,
                 numfailures: 8,
+                failure_reasons: None,
                 write_type: WriteType::Counter,
             }
         );
         assert_eq!(error.reason, "message 2");
     }
 
+    #[test]
+    fn deserialize_write_failure_reason_map() {
+        let features = ProtocolFeatures::default();
+
+        let mut bytes = make_error_request_bytes(0x1500, "message 2");
+        bytes.extend(0x0000_i16.to_be_bytes());
+        bytes.extend(2_i32.to_be_bytes());
+        bytes.extend(4_i32.to_be_bytes());
+        bytes.extend(1_i32.to_be_bytes());
+        bytes.push(4_u8);
+        bytes.extend([10, 0, 0, 2]);
+        bytes.extend(0x0003_u16.to_be_bytes());
+
+        let write_type_str = "SIMPLE";
+        let write_type_str_len: u16 = write_type_str.len().try_into().unwrap();
+        bytes.extend(write_type_str_len.to_be_bytes());
+        bytes.extend(write_type_str.as_bytes());
+
+        let error: Error =
+            Error::deserialize_with_protocol_version(&features, 5, &mut bytes.as_slice()).unwrap();
+
+        assert_eq!(
+            error.error,
+            DbError::WriteFailure {
+                consistency: Consistency::Any,
+                received: 2,
+                required: 4,
+                numfailures: 1,
+                failure_reasons: Some(HashMap::from([(
+                    IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+                    0x0003
+                )])),
+                write_type: WriteType::Simple,
+            }
+        );
+        assert_eq!(error.reason, "message 2");
+    }
+
     #[test]
     fn deserialize_already_exists() {
         let features = ProtocolFeatures::default();
@@ -1,4 +1,5 @@
 pub mod authenticate;
+pub mod cql_literal;
 pub mod cql_to_rust;
 pub mod error;
 pub mod event;
@@ -468,10 +468,56 @@ pub struct Rows {
     pub serialized_size: usize,
 }
 
+/// Rows of a RESULT response which were not deserialized yet.
+///
+/// Created with [`RawRows::deserialize`], for consumers which deserialize
+/// the rows themselves, e.g. directly into columnar formats.
+#[derive(Debug, Default)]
+pub struct RawRows {
+    pub metadata: ResultMetadata,
+    /// Number of rows in `raw_rows`.
+    pub rows_count: usize,
+    /// The serialized rows.
+    pub raw_rows: Bytes,
+}
+
+impl RawRows {
+    /// Deserializes a RESULT response body, keeping the rows serialized.
+    /// Returns `None` if the result is not of the `Rows` kind.
+    pub fn deserialize(
+        body: &Bytes,
+        cached_metadata: Option<&ResultMetadata>,
+    ) -> StdResult<Option<Self>, ParseError> {
+        let mut buf = &body[..];
+        if types::read_int(&mut buf)? != 0x0002 {
+            return Ok(None);
+        }
+        let metadata = deser_rows_metadata(&mut buf, cached_metadata)?;
+        let rows_count: usize = types::read_int(&mut buf)?.try_into()?;
+        let raw_rows = body.slice_ref(buf);
+        Ok(Some(Self {
+            metadata,
+            rows_count,
+            raw_rows,
+        }))
+    }
+
+    /// Returns an iterator over the serialized rows.
+    pub fn rows_iter(&self) -> RowIterator<'_> {
+        RowIterator::new(
+            self.rows_count,
+            &self.metadata.col_specs,
+            FrameSlice::new(&self.raw_rows),
+        )
+    }
+}
+
 #[derive(Debug)]
 pub enum Result {
     Void,
     Rows(Rows),
+    /// Rows kept serialized, see [`RawRows`]. Never returned by [`deserialize`].
+    RawRows(RawRows),
     SetKeyspace(SetKeyspace),
     Prepared(Prepared),
     SchemaChange(SchemaChange),
@@ -805,13 +851,13 @@ pub fn deser_cql_value(
     })
 }
 
-fn deser_rows(
+fn deser_rows_metadata(
     buf: &mut &[u8],
     cached_metadata: Option<&ResultMetadata>,
-) -> StdResult<Rows, ParseError> {
+) -> StdResult<ResultMetadata, ParseError> {
     let server_metadata = deser_result_metadata(buf)?;
 
-    let metadata = match cached_metadata {
+    Ok(match cached_metadata {
         Some(metadata) => metadata.clone(),
         None => {
             // No cached_metadata provided. Server is supposed to provide the result metadata.
@@ -824,7 +870,14 @@ fn deser_rows(
             }
             server_metadata
         }
-    };
+    })
+}
+
+fn deser_rows(
+    buf: &mut &[u8],
+    cached_metadata: Option<&ResultMetadata>,
+) -> StdResult<Rows, ParseError> {
+    let metadata = deser_rows_metadata(buf, cached_metadata)?;
 
     let original_size = buf.len();
 
@@ -874,6 +927,18 @@ fn deser_schema_change(buf: &mut &[u8]) -> StdResult<SchemaChange, ParseError> {
     })
 }
 
+/// Reads the number of rows from a serialized RESULT response body,
+/// without deserializing the rows themselves.
+/// Returns `None` if the result is not of the `Rows` kind.
+pub fn peek_rows_count(mut buf: &[u8]) -> StdResult<Option<usize>, ParseError> {
+    if types::read_int(&mut buf)? != 0x0002 {
+        return Ok(None);
+    }
+    deser_result_metadata(&mut buf)?;
+    let rows_count: usize = types::read_int(&mut buf)?.try_into()?;
+    Ok(Some(rows_count))
+}
+
 pub fn deserialize(
     buf: &mut &[u8],
     cached_metadata: Option<&ResultMetadata>,
@@ -1715,4 +1780,66 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_peek_rows_count() {
+        // Rows result with no metadata, one column and two rows.
+        let mut rows_buf: Vec<u8> = Vec::new();
+        rows_buf.extend_from_slice(&0x0002_i32.to_be_bytes()); // kind: Rows
+        rows_buf.extend_from_slice(&0x0004_i32.to_be_bytes()); // flags: no metadata
+        rows_buf.extend_from_slice(&1_i32.to_be_bytes()); // column count
+        rows_buf.extend_from_slice(&2_i32.to_be_bytes()); // rows count
+        for value in [1_i32, 2] {
+            rows_buf.extend_from_slice(&4_i32.to_be_bytes());
+            rows_buf.extend_from_slice(&value.to_be_bytes());
+        }
+        assert_eq!(super::peek_rows_count(&rows_buf).unwrap(), Some(2));
+        // The rows themselves are not inspected.
+        assert_eq!(super::peek_rows_count(&rows_buf[..16]).unwrap(), Some(2));
+        assert!(super::peek_rows_count(&rows_buf[..12]).is_err());
+
+        let void_buf = 0x0001_i32.to_be_bytes();
+        assert_eq!(super::peek_rows_count(&void_buf).unwrap(), None);
+    }
+
+    #[test]
+    fn test_raw_rows() {
+        use super::{ColumnSpec, RawRows, ResultMetadata, TableSpec};
+        use crate::types::deserialize::DeserializeRow;
+
+        // Rows result with no metadata, one column and two rows.
+        let mut rows_buf: Vec<u8> = Vec::new();
+        rows_buf.extend_from_slice(&0x0002_i32.to_be_bytes()); // kind: Rows
+        rows_buf.extend_from_slice(&0x0004_i32.to_be_bytes()); // flags: no metadata
+        rows_buf.extend_from_slice(&1_i32.to_be_bytes()); // column count
+        rows_buf.extend_from_slice(&2_i32.to_be_bytes()); // rows count
+        for value in [1_i32, 2] {
+            rows_buf.extend_from_slice(&4_i32.to_be_bytes());
+            rows_buf.extend_from_slice(&value.to_be_bytes());
+        }
+        let cached_metadata = ResultMetadata {
+            col_count: 1,
+            paging_state: None,
+            col_specs: vec![ColumnSpec {
+                table_spec: TableSpec::borrowed("ks", "t"),
+                name: "a".to_owned(),
+                typ: ColumnType::Int,
+            }],
+        };
+
+        let body = rows_buf.into();
+        let raw_rows = RawRows::deserialize(&body, Some(&cached_metadata))
+            .unwrap()
+            .unwrap();
+        assert_eq!(raw_rows.rows_count, 2);
+        assert_eq!(raw_rows.raw_rows.len(), 16);
+        let values: Vec<(i32,)> = raw_rows
+            .rows_iter()
+            .map(|row| <(i32,)>::deserialize(row.unwrap()).unwrap())
+            .collect();
+        assert_eq!(values, vec![(1,), (2,)]);
+
+        let void_body = 0x0001_i32.to_be_bytes().to_vec().into();
+        assert!(RawRows::deserialize(&void_body, None).unwrap().is_none());
+    }
 }
@@ -582,6 +582,13 @@ fn type_consistency() {
 }
 
 pub fn read_inet(buf: &mut &[u8]) -> Result<SocketAddr, LowLevelDeserializationError> {
+    let ip_addr = read_inetaddr(buf)?;
+    let port = read_int(buf)?;
+
+    Ok(SocketAddr::new(ip_addr, port as u16))
+}
+
+pub fn read_inetaddr(buf: &mut &[u8]) -> Result<IpAddr, LowLevelDeserializationError> {
     let len = buf.read_u8()?;
     let ip_addr = match len {
         4 => {
@@ -596,9 +603,8 @@ pub fn read_inet(buf: &mut &[u8]) -> Result<SocketAddr, LowLevelDeserializationE
         }
         v => return Err(LowLevelDeserializationError::InvalidInetLength(v)),
     };
-    let port = read_int(buf)?;
 
-    Ok(SocketAddr::new(ip_addr, port as u16))
+    Ok(ip_addr)
 }
 
 pub fn write_inet(addr: SocketAddr, buf: &mut impl BufMut) {
@@ -0,0 +1,1120 @@
+//! Validation of frames against the CQL native protocol v4 specification.
+//!
+//! The validators check the structure of frame bodies more strictly than the driver's
+//! serializers and parsers: the consistency of flags with the fields they announce,
+//! all declared lengths and counts, UTF-8 in strings, the values of enumerations
+//! and the absence of trailing bytes. A violation is reported as a [`ProtocolViolation`],
+//! which points at the offending field and its offset in the body.
+//!
+//! The validators can be used directly, e.g. in fuzzing harnesses. With the
+//! `protocol-validation` crate feature enabled, every request frame is also validated
+//! before it is sent and the extensions of every response frame before they are parsed.
+//! The feature of the `scylla` crate additionally validates the bodies of all responses
+//! and the number of values bound to prepared statements, and fails the requests
+//! with the found violations. Validating frames costs a full pass over their bodies,
+//! so the feature is meant for debugging and testing only.
+//!
+//! # Example
+//! ```rust
+//! use scylla_cql::frame::request::{Query, RequestOpcode, SerializableRequest};
+//! use scylla_cql::frame::validation::validate_request;
+//!
+//! let query = Query {
+//!     contents: "SELECT * FROM ks.t".into(),
+//!     parameters: Default::default(),
+//! };
+//! let body = query.to_bytes().unwrap();
+//! validate_request(RequestOpcode::Query, 0, &body).unwrap();
+//!
+//! // Consistency 0x00FF does not exist
+//! let mut broken = body.to_vec();
+//! let consistency_offset = 4 + query.contents.len();
+//! broken[consistency_offset + 1] = 0xFF;
+//! let violation = validate_request(RequestOpcode::Query, 0, &broken).unwrap_err();
+//! assert_eq!(violation.field, "query parameters > consistency");
+//! assert_eq!(violation.offset, consistency_offset);
+//! ```
+
+use thiserror::Error;
+
+use super::request::RequestOpcode;
+use super::response::ResponseOpcode;
+use super::{FLAG_COMPRESSION, FLAG_CUSTOM_PAYLOAD, FLAG_TRACING, FLAG_WARNING};
+
+// Deeper nesting of column types is rejected to bound the recursion of the validator
+const MAX_TYPE_DEPTH: usize = 64;
+
+/// A violation of the protocol specification found in a frame body.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{frame} violates the protocol in {field} at byte {offset}: {reason}")]
+#[non_exhaustive]
+pub struct ProtocolViolation {
+    /// Kind of the frame, e.g. `QUERY request` or `RESULT response`.
+    pub frame: String,
+    /// Path of the offending field, e.g. `query parameters > value 2`.
+    pub field: String,
+    /// Offset of the offending field, in bytes from the start of the validated body.
+    pub offset: usize,
+    /// Description of the violation.
+    pub reason: String,
+}
+
+/// Validates the body of a request frame with the given opcode and header flags.
+///
+/// `body` is the uncompressed body, starting with the custom payload if the flags announce it.
+pub fn validate_request(
+    opcode: RequestOpcode,
+    flags: u8,
+    body: &[u8],
+) -> std::result::Result<(), ProtocolViolation> {
+    let mut v = Validator::new(format!("{} request", request_name(opcode)), body);
+
+    let unknown_flags = flags & !(FLAG_COMPRESSION | FLAG_TRACING | FLAG_CUSTOM_PAYLOAD);
+    if unknown_flags != 0 {
+        return Err(v.violation(
+            0,
+            format!("header flags {unknown_flags:#04x} are not allowed in requests"),
+        ));
+    }
+    if flags & FLAG_CUSTOM_PAYLOAD != 0 {
+        v.field("custom payload", Validator::bytes_map)?;
+    }
+
+    match opcode {
+        RequestOpcode::Startup => v.field("options", |v| {
+            let start = v.pos;
+            let options = v.string_map()?;
+            if !options.iter().any(|(key, _)| *key == "CQL_VERSION") {
+                return Err(v.violation(start, "the mandatory CQL_VERSION option is missing"));
+            }
+            Ok(())
+        })?,
+        RequestOpcode::Options => {}
+        RequestOpcode::Query => {
+            v.field("query", Validator::long_string)?;
+            v.field("query parameters", |v| v.query_parameters(false))?;
+        }
+        RequestOpcode::Prepare => {
+            v.field("query", Validator::long_string)?;
+        }
+        RequestOpcode::Execute => {
+            v.field("id", |v| {
+                let start = v.pos;
+                if v.short_bytes()?.is_empty() {
+                    return Err(v.violation(start, "the id of the prepared statement is empty"));
+                }
+                Ok(())
+            })?;
+            v.field("query parameters", |v| v.query_parameters(false))?;
+        }
+        RequestOpcode::Register => v.field("event types", |v| {
+            let start = v.pos;
+            for event_type in v.string_list()? {
+                if !matches!(
+                    event_type,
+                    "TOPOLOGY_CHANGE" | "STATUS_CHANGE" | "SCHEMA_CHANGE"
+                ) {
+                    return Err(v.violation(start, format!("unknown event type {event_type:?}")));
+                }
+            }
+            Ok(())
+        })?,
+        RequestOpcode::Batch => v.batch()?,
+        RequestOpcode::AuthResponse => {
+            v.field("token", Validator::bytes)?;
+        }
+    }
+
+    v.end()
+}
+
+/// Checks that the number of values bound in an `EXECUTE` request matches the number
+/// of bind markers of the prepared statement, which the request frame does not carry.
+pub fn validate_values_count(
+    values_count: usize,
+    bind_markers_count: usize,
+) -> std::result::Result<(), ProtocolViolation> {
+    if values_count == bind_markers_count {
+        return Ok(());
+    }
+    Err(ProtocolViolation {
+        frame: format!("{} request", request_name(RequestOpcode::Execute)),
+        field: "query parameters > values".to_owned(),
+        offset: 0,
+        reason: format!(
+            "{values_count} values are bound, but the prepared statement has {bind_markers_count} bind markers"
+        ),
+    })
+}
+
+/// Validates the body of a response frame with the given opcode and header flags.
+///
+/// `body` is the uncompressed body, starting with the extensions announced by the flags.
+pub fn validate_response(
+    opcode: ResponseOpcode,
+    flags: u8,
+    body: &[u8],
+) -> std::result::Result<(), ProtocolViolation> {
+    let mut v = Validator::new(format!("{} response", response_name(opcode)), body);
+    v.response_extensions(flags)?;
+    v.response_body(opcode)
+}
+
+/// Validates the tracing id, warnings and custom payload at the start of
+/// the uncompressed body of a response frame, as announced by the header flags.
+pub fn validate_response_extensions(
+    flags: u8,
+    body: &[u8],
+) -> std::result::Result<(), ProtocolViolation> {
+    Validator::new("response".to_owned(), body).response_extensions(flags)
+}
+
+/// Validates the body of a response frame with the given opcode,
+/// which follows the extensions announced by the header flags.
+pub fn validate_response_body(
+    opcode: ResponseOpcode,
+    body: &[u8],
+) -> std::result::Result<(), ProtocolViolation> {
+    Validator::new(format!("{} response", response_name(opcode)), body).response_body(opcode)
+}
+
+fn request_name(opcode: RequestOpcode) -> &'static str {
+    match opcode {
+        RequestOpcode::Startup => "STARTUP",
+        RequestOpcode::Options => "OPTIONS",
+        RequestOpcode::Query => "QUERY",
+        RequestOpcode::Prepare => "PREPARE",
+        RequestOpcode::Execute => "EXECUTE",
+        RequestOpcode::Register => "REGISTER",
+        RequestOpcode::Batch => "BATCH",
+        RequestOpcode::AuthResponse => "AUTH_RESPONSE",
+    }
+}
+
+fn response_name(opcode: ResponseOpcode) -> &'static str {
+    match opcode {
+        ResponseOpcode::Error => "ERROR",
+        ResponseOpcode::Ready => "READY",
+        ResponseOpcode::Authenticate => "AUTHENTICATE",
+        ResponseOpcode::Supported => "SUPPORTED",
+        ResponseOpcode::Result => "RESULT",
+        ResponseOpcode::Event => "EVENT",
+        ResponseOpcode::AuthChallenge => "AUTH_CHALLENGE",
+        ResponseOpcode::AuthSuccess => "AUTH_SUCCESS",
+    }
+}
+
+type Result<T> = std::result::Result<T, ProtocolViolation>;
+
+// A cursor over a frame body, which tracks the path of the field being validated.
+struct Validator<'a> {
+    frame: String,
+    body: &'a [u8],
+    pos: usize,
+    path: Vec<String>,
+}
+
+impl<'a> Validator<'a> {
+    fn new(frame: String, body: &'a [u8]) -> Self {
+        Self {
+            frame,
+            body,
+            pos: 0,
+            path: Vec::new(),
+        }
+    }
+
+    fn violation(&self, offset: usize, reason: impl Into<String>) -> ProtocolViolation {
+        ProtocolViolation {
+            frame: self.frame.clone(),
+            field: if self.path.is_empty() {
+                "body".to_owned()
+            } else {
+                self.path.join(" > ")
+            },
+            offset,
+            reason: reason.into(),
+        }
+    }
+
+    // A violation of the field with the given name, nested in the current one
+    fn field_violation(
+        &mut self,
+        name: &str,
+        offset: usize,
+        reason: impl Into<String>,
+    ) -> ProtocolViolation {
+        self.path.push(name.to_owned());
+        let violation = self.violation(offset, reason);
+        self.path.pop();
+        violation
+    }
+
+    fn field<T>(
+        &mut self,
+        name: impl Into<String>,
+        validate: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        self.path.push(name.into());
+        let result = validate(self);
+        self.path.pop();
+        result
+    }
+
+    fn end(&self) -> Result<()> {
+        let remaining = self.body.len() - self.pos;
+        if remaining != 0 {
+            return Err(self.violation(
+                self.pos,
+                format!("{remaining} unexpected bytes follow the end of the body"),
+            ));
+        }
+        Ok(())
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let remaining = self.body.len() - self.pos;
+        if len > remaining {
+            return Err(self.violation(
+                self.pos,
+                format!("{len} bytes are needed, but only {remaining} remain"),
+            ));
+        }
+        let taken = &self.body[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(taken)
+    }
+
+    // Takes the contents of a field whose length is declared at `start`
+    fn take_declared(&mut self, start: usize, len: usize) -> Result<&'a [u8]> {
+        let remaining = self.body.len() - self.pos;
+        if len > remaining {
+            return Err(self.violation(
+                start,
+                format!("the declared length {len} exceeds the {remaining} remaining bytes"),
+            ));
+        }
+        self.take(len)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn short(&mut self) -> Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn int(&mut self) -> Result<i32> {
+        let bytes = self.take(4)?;
+        Ok(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    // Reads an [int] which must not be negative, e.g. a count
+    fn count(&mut self) -> Result<usize> {
+        let start = self.pos;
+        let count = self.int()?;
+        usize::try_from(count).map_err(|_| self.violation(start, format!("negative count {count}")))
+    }
+
+    fn utf8(&self, start: usize, bytes: &'a [u8]) -> Result<&'a str> {
+        std::str::from_utf8(bytes).map_err(|err| {
+            self.violation(start + err.valid_up_to(), "the string is not valid UTF-8")
+        })
+    }
+
+    fn string(&mut self) -> Result<&'a str> {
+        let start = self.pos;
+        let len = self.short()?;
+        let contents_start = self.pos;
+        let bytes = self.take_declared(start, len.into())?;
+        self.utf8(contents_start, bytes)
+    }
+
+    fn long_string(&mut self) -> Result<&'a str> {
+        let start = self.pos;
+        let len = self.int()?;
+        let len = usize::try_from(len)
+            .map_err(|_| self.violation(start, format!("negative string length {len}")))?;
+        let contents_start = self.pos;
+        let bytes = self.take_declared(start, len)?;
+        self.utf8(contents_start, bytes)
+    }
+
+    fn bytes(&mut self) -> Result<Option<&'a [u8]>> {
+        let start = self.pos;
+        match self.int()? {
+            -1 => Ok(None),
+            len if len < 0 => Err(self.violation(start, format!("invalid length {len}"))),
+            len => self.take_declared(start, len as usize).map(Some),
+        }
+    }
+
+    fn short_bytes(&mut self) -> Result<&'a [u8]> {
+        let start = self.pos;
+        let len = self.short()?;
+        self.take_declared(start, len.into())
+    }
+
+    // A bound value, which unlike [bytes] can also be unset
+    fn value(&mut self) -> Result<()> {
+        let start = self.pos;
+        match self.int()? {
+            -1 | -2 => Ok(()),
+            len if len < 0 => Err(self.violation(start, format!("invalid value length {len}"))),
+            len => self.take_declared(start, len as usize).map(drop),
+        }
+    }
+
+    fn string_list(&mut self) -> Result<Vec<&'a str>> {
+        let len = self.short()?;
+        (0..len)
+            .map(|i| self.field(format!("string {i}"), Validator::string))
+            .collect()
+    }
+
+    fn string_map(&mut self) -> Result<Vec<(&'a str, &'a str)>> {
+        let len = self.short()?;
+        (0..len)
+            .map(|i| {
+                self.field(format!("entry {i}"), |v| {
+                    let key = v.field("key", Validator::string)?;
+                    let value = v.field(format!("value of {key}"), Validator::string)?;
+                    Ok((key, value))
+                })
+            })
+            .collect()
+    }
+
+    fn string_multimap(&mut self) -> Result<()> {
+        let len = self.short()?;
+        for i in 0..len {
+            self.field(format!("entry {i}"), |v| {
+                let key = v.field("key", Validator::string)?;
+                v.field(format!("values of {key}"), Validator::string_list)?;
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+
+    fn bytes_map(&mut self) -> Result<()> {
+        let len = self.short()?;
+        for i in 0..len {
+            self.field(format!("entry {i}"), |v| {
+                let key = v.field("key", Validator::string)?;
+                v.field(format!("value of {key}"), Validator::bytes)?;
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+
+    fn consistency(&mut self) -> Result<()> {
+        let start = self.pos;
+        match self.short()? {
+            0x0000..=0x000A => Ok(()),
+            other => Err(self.violation(start, format!("unknown consistency {other:#06x}"))),
+        }
+    }
+
+    fn serial_consistency(&mut self) -> Result<()> {
+        let start = self.pos;
+        match self.short()? {
+            0x0008 | 0x0009 => Ok(()),
+            other => {
+                Err(self.violation(start, format!("{other:#06x} is not a serial consistency")))
+            }
+        }
+    }
+
+    fn inet(&mut self) -> Result<()> {
+        let start = self.pos;
+        match self.byte()? {
+            len @ (4 | 16) => {
+                self.take_declared(start, len.into())?;
+            }
+            len => {
+                return Err(self.violation(start, format!("invalid address length {len}")));
+            }
+        }
+        self.int()?;
+        Ok(())
+    }
+
+    fn query_parameters(&mut self, in_batch: bool) -> Result<()> {
+        const VALUES: u8 = 0x01;
+        const SKIP_METADATA: u8 = 0x02;
+        const PAGE_SIZE: u8 = 0x04;
+        const PAGING_STATE: u8 = 0x08;
+        const SERIAL_CONSISTENCY: u8 = 0x10;
+        const TIMESTAMP: u8 = 0x20;
+        const NAMES_FOR_VALUES: u8 = 0x40;
+
+        self.field("consistency", Validator::consistency)?;
+
+        let flags_start = self.pos;
+        let flags = self.field("flags", Validator::byte)?;
+        let allowed = if in_batch {
+            SERIAL_CONSISTENCY | TIMESTAMP | NAMES_FOR_VALUES
+        } else {
+            VALUES
+                | SKIP_METADATA
+                | PAGE_SIZE
+                | PAGING_STATE
+                | SERIAL_CONSISTENCY
+                | TIMESTAMP
+                | NAMES_FOR_VALUES
+        };
+        if flags & !allowed != 0 {
+            return Err(self.field_violation(
+                "flags",
+                flags_start,
+                format!("unknown flags {:#04x}", flags & !allowed),
+            ));
+        }
+        if !in_batch && flags & NAMES_FOR_VALUES != 0 && flags & VALUES == 0 {
+            return Err(self.field_violation(
+                "flags",
+                flags_start,
+                "the names for values flag is set without the values flag",
+            ));
+        }
+
+        if flags & VALUES != 0 {
+            let names = flags & NAMES_FOR_VALUES != 0;
+            self.field("values", |v| v.values(names))?;
+        }
+        if flags & PAGE_SIZE != 0 {
+            self.field("page size", Validator::int)?;
+        }
+        if flags & PAGING_STATE != 0 {
+            self.field("paging state", Validator::bytes)?;
+        }
+        if flags & SERIAL_CONSISTENCY != 0 {
+            self.field("serial consistency", Validator::serial_consistency)?;
+        }
+        if flags & TIMESTAMP != 0 {
+            self.field("timestamp", |v| v.take(8).map(drop))?;
+        }
+        Ok(())
+    }
+
+    fn values(&mut self, names: bool) -> Result<()> {
+        let count = self.short()?;
+        for i in 0..count {
+            self.field(format!("value {i}"), |v| {
+                if names {
+                    v.field("name", Validator::string)?;
+                }
+                v.value()
+            })?;
+        }
+        Ok(())
+    }
+
+    fn batch(&mut self) -> Result<()> {
+        self.field("type", |v| {
+            let start = v.pos;
+            match v.byte()? {
+                0..=2 => Ok(()),
+                other => Err(v.violation(start, format!("unknown batch type {other}"))),
+            }
+        })?;
+
+        let count = self.field("statements count", Validator::short)?;
+        for i in 0..count {
+            self.field(format!("statement {i}"), |v| {
+                let start = v.pos;
+                match v.byte()? {
+                    0 => v.field("query", Validator::long_string).map(drop)?,
+                    1 => v.field("id", Validator::short_bytes).map(drop)?,
+                    other => {
+                        return Err(v.violation(start, format!("unknown statement kind {other}")))
+                    }
+                }
+                // Names for values are not supported in batches by the servers,
+                // so they are never expected here
+                v.field("values", |v| v.values(false))
+            })?;
+        }
+
+        self.field("batch parameters", |v| v.query_parameters(true))
+    }
+
+    fn response_extensions(&mut self, flags: u8) -> Result<()> {
+        let unknown_flags =
+            flags & !(FLAG_COMPRESSION | FLAG_TRACING | FLAG_CUSTOM_PAYLOAD | FLAG_WARNING);
+        if unknown_flags != 0 {
+            return Err(self.violation(0, format!("unknown header flags {unknown_flags:#04x}")));
+        }
+
+        if flags & FLAG_TRACING != 0 {
+            self.field("tracing id", |v| v.take(16).map(drop))?;
+        }
+        if flags & FLAG_WARNING != 0 {
+            self.field("warnings", Validator::string_list)?;
+        }
+        if flags & FLAG_CUSTOM_PAYLOAD != 0 {
+            self.field("custom payload", Validator::bytes_map)?;
+        }
+        Ok(())
+    }
+
+    fn response_body(&mut self, opcode: ResponseOpcode) -> Result<()> {
+        match opcode {
+            ResponseOpcode::Error => {
+                // The contents of unknown errors, e.g. protocol extensions, can't be validated
+                if !self.error()? {
+                    return Ok(());
+                }
+            }
+            ResponseOpcode::Ready => {}
+            ResponseOpcode::Authenticate => {
+                self.field("authenticator", Validator::string)?;
+            }
+            ResponseOpcode::Supported => {
+                self.field("options", Validator::string_multimap)?;
+            }
+            ResponseOpcode::Result => self.result()?,
+            ResponseOpcode::Event => self.event()?,
+            ResponseOpcode::AuthChallenge | ResponseOpcode::AuthSuccess => {
+                self.field("token", Validator::bytes)?;
+            }
+        }
+        self.end()
+    }
+
+    // Returns whether the error code is known, and thus its contents were validated
+    fn error(&mut self) -> Result<bool> {
+        let code = self.field("code", Validator::int)?;
+        self.field("message", Validator::string)?;
+
+        self.field(format!("error {code:#06x}"), |v| {
+            match code {
+                0x0000 | 0x000A | 0x0100 | 0x1001 | 0x1002 | 0x1003 | 0x2000 | 0x2100 | 0x2200
+                | 0x2300 => {}
+                // Unavailable
+                0x1000 => {
+                    v.field("consistency", Validator::consistency)?;
+                    v.field("required", Validator::int)?;
+                    v.field("alive", Validator::int)?;
+                }
+                // Write timeout and write failure
+                0x1100 | 0x1500 => {
+                    v.field("consistency", Validator::consistency)?;
+                    v.field("received", Validator::int)?;
+                    v.field("block for", Validator::int)?;
+                    if code == 0x1500 {
+                        v.field("failures", Validator::int)?;
+                    }
+                    v.field("write type", Validator::string)?;
+                }
+                // Read timeout and read failure
+                0x1200 | 0x1300 => {
+                    v.field("consistency", Validator::consistency)?;
+                    v.field("received", Validator::int)?;
+                    v.field("block for", Validator::int)?;
+                    if code == 0x1300 {
+                        v.field("failures", Validator::int)?;
+                    }
+                    v.field("data present", Validator::byte)?;
+                }
+                // Function failure
+                0x1400 => {
+                    v.field("keyspace", Validator::string)?;
+                    v.field("function", Validator::string)?;
+                    v.field("argument types", Validator::string_list)?;
+                }
+                // Already exists
+                0x2400 => {
+                    v.field("keyspace", Validator::string)?;
+                    v.field("table", Validator::string)?;
+                }
+                // Unprepared
+                0x2500 => {
+                    v.field("id", Validator::short_bytes)?;
+                }
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })
+    }
+
+    fn result(&mut self) -> Result<()> {
+        let start = self.pos;
+        match self.field("kind", Validator::int)? {
+            // Void
+            0x0001 => {}
+            0x0002 => self.field("rows", |v| {
+                let columns_count = v.field("metadata", Validator::result_metadata)?;
+                let rows_count = v.field("rows count", Validator::count)?;
+                if columns_count == 0 {
+                    return Ok(());
+                }
+                for row in 0..rows_count {
+                    for column in 0..columns_count {
+                        v.field(format!("row {row} column {column}"), Validator::bytes)?;
+                    }
+                }
+                Ok(())
+            })?,
+            0x0003 => {
+                self.field("keyspace", Validator::string)?;
+            }
+            0x0004 => self.field("prepared", |v| {
+                v.field("id", Validator::short_bytes)?;
+                v.field("metadata", Validator::prepared_metadata)?;
+                v.field("result metadata", Validator::result_metadata)
+                    .map(drop)
+            })?,
+            0x0005 => self.field("schema change", Validator::schema_change)?,
+            kind => {
+                return Err(self.field_violation(
+                    "kind",
+                    start,
+                    format!("unknown result kind {kind:#06x}"),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    // Returns the number of columns
+    fn result_metadata(&mut self) -> Result<usize> {
+        const GLOBAL_TABLES_SPEC: i32 = 0x0001;
+        const HAS_MORE_PAGES: i32 = 0x0002;
+        const NO_METADATA: i32 = 0x0004;
+
+        let flags_start = self.pos;
+        let flags = self.field("flags", Validator::int)?;
+        let unknown = flags & !(GLOBAL_TABLES_SPEC | HAS_MORE_PAGES | NO_METADATA);
+        if unknown != 0 {
+            return Err(self.field_violation(
+                "flags",
+                flags_start,
+                format!("unknown flags {unknown:#010x}"),
+            ));
+        }
+
+        let columns_count = self.field("columns count", Validator::count)?;
+        if flags & HAS_MORE_PAGES != 0 {
+            self.field("paging state", Validator::bytes)?;
+        }
+        if flags & NO_METADATA == 0 {
+            self.column_specs(columns_count, flags & GLOBAL_TABLES_SPEC != 0)?;
+        }
+        Ok(columns_count)
+    }
+
+    fn prepared_metadata(&mut self) -> Result<()> {
+        const GLOBAL_TABLES_SPEC: i32 = 0x0001;
+
+        // Other flags are not validated, as servers use them for protocol extensions,
+        // e.g. ScyllaDB marks LWT statements with a negotiated flag
+        let flags = self.field("flags", Validator::int)?;
+        let columns_count = self.field("columns count", Validator::count)?;
+        let pk_count = self.field("partition key count", Validator::count)?;
+        for i in 0..pk_count {
+            self.field(format!("partition key index {i}"), |v| {
+                let start = v.pos;
+                let index = v.short()?;
+                if usize::from(index) >= columns_count {
+                    return Err(v.violation(
+                        start,
+                        format!("index {index} is out of the {columns_count} columns"),
+                    ));
+                }
+                Ok(())
+            })?;
+        }
+        self.column_specs(columns_count, flags & GLOBAL_TABLES_SPEC != 0)
+    }
+
+    fn column_specs(&mut self, columns_count: usize, global_tables_spec: bool) -> Result<()> {
+        if global_tables_spec {
+            self.field("global keyspace", Validator::string)?;
+            self.field("global table", Validator::string)?;
+        }
+        for i in 0..columns_count {
+            self.field(format!("column {i}"), |v| {
+                if !global_tables_spec {
+                    v.field("keyspace", Validator::string)?;
+                    v.field("table", Validator::string)?;
+                }
+                v.field("name", Validator::string)?;
+                v.field("type", |v| v.column_type(0))
+            })?;
+        }
+        Ok(())
+    }
+
+    fn column_type(&mut self, depth: usize) -> Result<()> {
+        let start = self.pos;
+        if depth > MAX_TYPE_DEPTH {
+            return Err(self.violation(
+                start,
+                format!("types nested deeper than {MAX_TYPE_DEPTH} levels are not supported"),
+            ));
+        }
+        match self.short()? {
+            // Custom
+            0x0000 => self.field("class", Validator::string).map(drop),
+            0x0001..=0x0009 | 0x000B..=0x0015 => Ok(()),
+            // List and set
+            0x0020 | 0x0022 => self.field("element", |v| v.column_type(depth + 1)),
+            // Map
+            0x0021 => {
+                self.field("key", |v| v.column_type(depth + 1))?;
+                self.field("value", |v| v.column_type(depth + 1))
+            }
+            // User defined type
+            0x0030 => {
+                self.field("keyspace", Validator::string)?;
+                self.field("name", Validator::string)?;
+                let fields_count = self.field("fields count", Validator::short)?;
+                for i in 0..fields_count {
+                    self.field(format!("field {i}"), |v| {
+                        v.field("name", Validator::string)?;
+                        v.field("type", |v| v.column_type(depth + 1))
+                    })?;
+                }
+                Ok(())
+            }
+            // Tuple
+            0x0031 => {
+                let elements_count = self.field("elements count", Validator::short)?;
+                for i in 0..elements_count {
+                    self.field(format!("element {i}"), |v| v.column_type(depth + 1))?;
+                }
+                Ok(())
+            }
+            id => Err(self.violation(start, format!("unknown type id {id:#06x}"))),
+        }
+    }
+
+    fn schema_change(&mut self) -> Result<()> {
+        self.field("change type", |v| {
+            let start = v.pos;
+            match v.string()? {
+                "CREATED" | "UPDATED" | "DROPPED" => Ok(()),
+                other => Err(v.violation(start, format!("unknown change type {other:?}"))),
+            }
+        })?;
+
+        let target_start = self.pos;
+        let target = self.field("target", Validator::string)?;
+        match target {
+            "KEYSPACE" => {
+                self.field("keyspace", Validator::string)?;
+            }
+            "TABLE" | "TYPE" => {
+                self.field("keyspace", Validator::string)?;
+                self.field("name", Validator::string)?;
+            }
+            "FUNCTION" | "AGGREGATE" => {
+                self.field("keyspace", Validator::string)?;
+                self.field("name", Validator::string)?;
+                self.field("argument types", Validator::string_list)?;
+            }
+            other => {
+                return Err(self.field_violation(
+                    "target",
+                    target_start,
+                    format!("unknown target {other:?}"),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn event(&mut self) -> Result<()> {
+        let start = self.pos;
+        match self.field("type", Validator::string)? {
+            "TOPOLOGY_CHANGE" | "STATUS_CHANGE" => {
+                self.field("change", Validator::string)?;
+                self.field("address", Validator::inet)?;
+            }
+            "SCHEMA_CHANGE" => self.field("schema change", Validator::schema_change)?,
+            other => {
+                return Err(self.field_violation(
+                    "type",
+                    start,
+                    format!("unknown event type {other:?}"),
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::collections::HashMap;
+
+    use bytes::Bytes;
+
+    use super::{
+        validate_request, validate_response, validate_response_body, validate_values_count,
+        ProtocolViolation,
+    };
+    use crate::frame::request::batch::{Batch, BatchStatement, BatchType};
+    use crate::frame::request::query::QueryParameters;
+    use crate::frame::request::{
+        Execute, Prepare, Query, RequestOpcode, SerializableRequest, Startup,
+    };
+    use crate::frame::response::ResponseOpcode;
+    use crate::frame::types::{self, SerialConsistency};
+    use crate::frame::{SerializedRequest, FLAG_CUSTOM_PAYLOAD, FLAG_TRACING, FLAG_WARNING};
+    use crate::types::serialize::row::SerializedValues;
+    use crate::Consistency;
+
+    fn violation(field: &str, offset: usize) -> impl Fn(&ProtocolViolation) -> bool + '_ {
+        move |violation| violation.field == field && violation.offset == offset
+    }
+
+    #[test]
+    fn accepts_driver_requests() {
+        let mut values = SerializedValues::new();
+        values
+            .add_value(&1_i32, &crate::frame::response::result::ColumnType::Int)
+            .unwrap();
+        let parameters = || QueryParameters {
+            consistency: Consistency::LocalQuorum,
+            serial_consistency: Some(SerialConsistency::LocalSerial),
+            timestamp: Some(42),
+            page_size: Some(100),
+            paging_state: Some(Bytes::from_static(&[1, 2, 3])),
+            skip_metadata: true,
+            values: Cow::Borrowed(&values),
+        };
+
+        let query = Query {
+            contents: Cow::Borrowed("SELECT * FROM ks.t WHERE a = ?"),
+            parameters: parameters(),
+        };
+        let execute = Execute {
+            id: Bytes::from_static(&[1, 2, 3]),
+            parameters: parameters(),
+        };
+        let prepare = Prepare {
+            query: "SELECT * FROM ks.t",
+        };
+        let batch = Batch {
+            statements: Cow::Owned(vec![
+                BatchStatement::Query {
+                    text: Cow::Borrowed("INSERT INTO ks.t (a) VALUES (?)"),
+                },
+                BatchStatement::Prepared {
+                    id: Cow::Borrowed(&[4, 5]),
+                },
+            ]),
+            batch_type: BatchType::Unlogged,
+            consistency: Consistency::One,
+            serial_consistency: None,
+            timestamp: Some(1),
+            values: vec![values.clone(), values.clone()],
+        };
+        let startup = Startup {
+            options: HashMap::from([
+                (Cow::Borrowed("CQL_VERSION"), Cow::Borrowed("4.0.0")),
+                (Cow::Borrowed("COMPRESSION"), Cow::Borrowed("lz4")),
+            ]),
+        };
+
+        validate_request(RequestOpcode::Query, 0, &query.to_bytes().unwrap()).unwrap();
+        validate_request(RequestOpcode::Execute, 0, &execute.to_bytes().unwrap()).unwrap();
+        validate_request(RequestOpcode::Prepare, 0, &prepare.to_bytes().unwrap()).unwrap();
+        validate_request(RequestOpcode::Batch, 0, &batch.to_bytes().unwrap()).unwrap();
+        validate_request(RequestOpcode::Startup, 0, &startup.to_bytes().unwrap()).unwrap();
+        validate_request(RequestOpcode::Options, 0, &[]).unwrap();
+
+        // Custom payload precedes the body
+        let payload = HashMap::from([("key".to_owned(), Bytes::from_static(b"value"))]);
+        let frame = SerializedRequest::make(&query, None, true, Some(&payload)).unwrap();
+        validate_request(
+            RequestOpcode::Query,
+            FLAG_TRACING | FLAG_CUSTOM_PAYLOAD,
+            &frame.get_data()[9..],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn reports_request_violations() {
+        let query = Query {
+            contents: Cow::Borrowed("SELECT 1"),
+            parameters: Default::default(),
+        };
+        let body = query.to_bytes().unwrap().to_vec();
+        // [long string] query, [short] consistency, [byte] flags
+        let flags_offset = 4 + 8 + 2;
+
+        let with_flags = |flags: u8| {
+            let mut body = body.clone();
+            body[flags_offset] = flags;
+            body
+        };
+        let check = |body: &[u8], field: &str, offset: usize| {
+            let err = validate_request(RequestOpcode::Query, 0, body).unwrap_err();
+            assert!(violation(field, offset)(&err), "{err}");
+            err
+        };
+
+        let err = check(&with_flags(0x80), "query parameters > flags", flags_offset);
+        assert_eq!(err.reason, "unknown flags 0x80");
+        check(&with_flags(0x40), "query parameters > flags", flags_offset);
+        // The page size is announced, but missing
+        check(
+            &with_flags(0x04),
+            "query parameters > page size",
+            flags_offset + 1,
+        );
+
+        let mut invalid_utf8 = body.clone();
+        invalid_utf8[4 + 3] = 0xFF;
+        let err = check(&invalid_utf8, "query", 4 + 3);
+        assert_eq!(err.reason, "the string is not valid UTF-8");
+
+        let mut too_long = body.clone();
+        too_long[3] = 100;
+        let err = check(&too_long, "query", 0);
+        assert!(err.reason.starts_with("the declared length 100 exceeds"));
+
+        let mut trailing = body.clone();
+        trailing.push(0);
+        check(&trailing, "body", body.len());
+
+        // Values with an invalid length
+        let mut values = with_flags(0x01);
+        values.extend_from_slice(&1_u16.to_be_bytes());
+        values.extend_from_slice(&(-3_i32).to_be_bytes());
+        let err = check(
+            &values,
+            "query parameters > values > value 0",
+            flags_offset + 3,
+        );
+        assert_eq!(err.reason, "invalid value length -3");
+
+        assert!(validate_request(RequestOpcode::Query, 0x08, &body).is_err());
+        assert!(validate_request(
+            RequestOpcode::Startup,
+            0,
+            &Startup {
+                options: HashMap::new()
+            }
+            .to_bytes()
+            .unwrap()
+        )
+        .is_err());
+        assert!(validate_values_count(2, 2).is_ok());
+        assert_eq!(
+            validate_values_count(1, 2).unwrap_err().to_string(),
+            "EXECUTE request violates the protocol in query parameters > values at byte 0: \
+            1 values are bound, but the prepared statement has 2 bind markers"
+        );
+    }
+
+    fn rows_body() -> Vec<u8> {
+        let mut body = Vec::new();
+        types::write_int(0x0002, &mut body); // Rows
+        types::write_int(0x0001, &mut body); // Global tables spec
+        types::write_int(2, &mut body); // Columns count
+        types::write_string("ks", &mut body).unwrap();
+        types::write_string("t", &mut body).unwrap();
+        types::write_string("a", &mut body).unwrap();
+        types::write_short(0x0009, &mut body); // int
+        types::write_string("b", &mut body).unwrap();
+        types::write_short(0x0021, &mut body); // map<text, frozen<list<int>>>
+        types::write_short(0x000D, &mut body);
+        types::write_short(0x0020, &mut body);
+        types::write_short(0x0009, &mut body);
+        types::write_int(1, &mut body); // Rows count
+        types::write_bytes(&1_i32.to_be_bytes(), &mut body).unwrap();
+        types::write_int(-1, &mut body); // Null
+        body
+    }
+
+    #[test]
+    fn accepts_valid_responses() {
+        let body = rows_body();
+        validate_response_body(ResponseOpcode::Result, &body).unwrap();
+
+        let mut with_extensions = vec![7; 16]; // Tracing id
+        types::write_string_list(&["warning".to_owned()], &mut with_extensions).unwrap();
+        with_extensions.extend_from_slice(&body);
+        validate_response(
+            ResponseOpcode::Result,
+            FLAG_TRACING | FLAG_WARNING,
+            &with_extensions,
+        )
+        .unwrap();
+
+        let mut error = Vec::new();
+        types::write_int(0x1000, &mut error);
+        types::write_string("Cannot achieve consistency", &mut error).unwrap();
+        types::write_short(0x0004, &mut error);
+        types::write_int(2, &mut error);
+        types::write_int(1, &mut error);
+        validate_response_body(ResponseOpcode::Error, &error).unwrap();
+
+        // The contents of unknown errors are not validated
+        let mut unknown_error = Vec::new();
+        types::write_int(0xF000, &mut unknown_error);
+        types::write_string("Rate limit", &mut unknown_error).unwrap();
+        unknown_error.extend_from_slice(&[0, 1]);
+        validate_response_body(ResponseOpcode::Error, &unknown_error).unwrap();
+
+        let mut event = Vec::new();
+        types::write_string("STATUS_CHANGE", &mut event).unwrap();
+        types::write_string("UP", &mut event).unwrap();
+        event.extend_from_slice(&[4, 127, 0, 0, 1]);
+        types::write_int(9042, &mut event);
+        validate_response_body(ResponseOpcode::Event, &event).unwrap();
+        validate_response_body(ResponseOpcode::Ready, &[]).unwrap();
+    }
+
+    #[test]
+    fn reports_response_violations() {
+        let body = rows_body();
+        let check = |body: &[u8], field: &str, offset: usize| {
+            let err = validate_response_body(ResponseOpcode::Result, body).unwrap_err();
+            assert!(violation(field, offset)(&err), "{err}");
+            err
+        };
+
+        // A cell declared longer than the rest of the body
+        let mut cell_too_long = body.clone();
+        let cell_offset = body.len() - 12;
+        cell_too_long[cell_offset + 3] = 9;
+        let err = check(&cell_too_long, "rows > row 0 column 0", cell_offset);
+        assert_eq!(
+            err.to_string(),
+            "RESULT response violates the protocol in rows > row 0 column 0 at byte 39: \
+            the declared length 9 exceeds the 8 remaining bytes"
+        );
+
+        // Unknown type id of the list element
+        let element_offset = body.len() - 18;
+        let mut unknown_type = body.clone();
+        unknown_type[element_offset + 1] = 0x0A;
+        check(
+            &unknown_type,
+            "rows > metadata > column 1 > type > value > element",
+            element_offset,
+        );
+
+        // Missing column of the last row
+        check(
+            &body[..body.len() - 4],
+            "rows > row 0 column 1",
+            body.len() - 4,
+        );
+
+        let err = validate_response(ResponseOpcode::Result, FLAG_TRACING, &body[..10]).unwrap_err();
+        assert!(violation("tracing id", 0)(&err), "{err}");
+        assert!(validate_response(ResponseOpcode::Result, 0x40, &body).is_err());
+    }
+}
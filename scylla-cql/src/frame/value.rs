@@ -6,6 +6,8 @@ use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::convert::TryInto;
 use std::hash::BuildHasher;
 use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 use uuid::Uuid;
 
@@ -139,6 +141,134 @@ impl CqlTimeuuid {
     }
 }
 
+/// Number of 100ns intervals between the beginning of the Gregorian calendar
+/// (1582-10-15), which is the epoch of timeuuids, and the unix epoch.
+const GREGORIAN_TO_UNIX_OFFSET: u64 = 0x01B2_1DD2_1381_4000;
+
+/// Timestamp (in 100ns intervals since the Gregorian epoch) of the last
+/// timeuuid generated by [`CqlTimeuuid::now`].
+static LAST_TIMEUUID_TIMESTAMP: AtomicU64 = AtomicU64::new(0);
+
+/// Clock sequence and node of the timeuuids generated by [`CqlTimeuuid::now`].
+/// Zero means that they were not chosen yet.
+static TIMEUUID_CLOCK_SEQ_AND_NODE: AtomicU64 = AtomicU64::new(0);
+
+/// Generation of timeuuids and timestamp helpers
+impl CqlTimeuuid {
+    /// Generates a new timeuuid for the current time.
+    ///
+    /// The timeuuids generated by this function within a single process
+    /// are unique and strictly increasing (according to the [`Ord`] implementation),
+    /// even if they are generated in the same 100ns interval or the system
+    /// clock goes backwards. In such cases the embedded timestamp is advanced
+    /// ahead of the system clock, which is what Scylla and Cassandra do as well.
+    ///
+    /// The clock sequence and the node ID are chosen randomly once per process.
+    /// The node ID has the multicast bit set, as recommended by RFC 4122 for node IDs
+    /// which are not MAC addresses, so the generated timeuuids never collide with
+    /// the ones generated from a real MAC address.
+    pub fn now() -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let now = (now.as_nanos() / 100) as u64 + GREGORIAN_TO_UNIX_OFFSET;
+
+        let mut last = LAST_TIMEUUID_TIMESTAMP.load(Ordering::Relaxed);
+        let timestamp = loop {
+            let next = if now > last { now } else { last + 1 };
+            match LAST_TIMEUUID_TIMESTAMP.compare_exchange_weak(
+                last,
+                next,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break next,
+                Err(actual) => last = actual,
+            }
+        };
+
+        Self::from_gregorian_timestamp(timestamp, Self::clock_seq_and_node())
+    }
+
+    /// Returns the smallest timeuuid (according to the [`Ord`] implementation)
+    /// with the given timestamp, equivalent to the `minTimeuuid` CQL function.
+    ///
+    /// Meant to be used in range queries, for example
+    /// `WHERE t > ? AND t < ?` with bounds created by this function and
+    /// [`max_for_timestamp`](Self::max_for_timestamp). It should not be
+    /// inserted into the database, as it is not unique.
+    pub fn min_for_timestamp(timestamp: CqlTimestamp) -> Self {
+        Self::from_gregorian_timestamp(Self::gregorian_from_millis(timestamp.0), 0x8080808080808080)
+    }
+
+    /// Returns the largest timeuuid (according to the [`Ord`] implementation)
+    /// with the given timestamp, equivalent to the `maxTimeuuid` CQL function.
+    ///
+    /// See [`min_for_timestamp`](Self::min_for_timestamp) for how it is meant to be used.
+    pub fn max_for_timestamp(timestamp: CqlTimestamp) -> Self {
+        // maxTimeuuid takes the last 100ns interval of the millisecond
+        Self::from_gregorian_timestamp(
+            Self::gregorian_from_millis(timestamp.0) + 9999,
+            0x7f7f7f7f7f7f7f7f,
+        )
+    }
+
+    /// Returns the timestamp embedded in the timeuuid, truncated to milliseconds,
+    /// equivalent to the `toTimestamp` CQL function.
+    pub fn timestamp(&self) -> CqlTimestamp {
+        CqlTimestamp(self.unix_timestamp_100ns().div_euclid(10_000))
+    }
+
+    /// Returns the timestamp embedded in the timeuuid, as the number of
+    /// 100ns intervals since the unix epoch (negative for dates before it).
+    pub fn unix_timestamp_100ns(&self) -> i64 {
+        self.msb() as i64 - GREGORIAN_TO_UNIX_OFFSET as i64
+    }
+
+    /// Compares only the timestamps embedded in the timeuuids,
+    /// ignoring their clock sequences and nodes.
+    pub fn cmp_timestamp(&self, other: &Self) -> std::cmp::Ordering {
+        self.msb().cmp(&other.msb())
+    }
+
+    fn gregorian_from_millis(millis: i64) -> u64 {
+        (millis as i128 * 10_000 + GREGORIAN_TO_UNIX_OFFSET as i128).clamp(0, (1 << 60) - 1) as u64
+    }
+
+    fn from_gregorian_timestamp(timestamp: u64, lsb: u64) -> Self {
+        // Reverse of `msb()`: time_low - time_mid - time_hi_and_version
+        let time_low = timestamp & 0xFFFF_FFFF;
+        let time_mid = (timestamp >> 32) & 0xFFFF;
+        let time_hi_and_version = ((timestamp >> 48) & 0x0FFF) | 0x1000;
+        Self::from_u64_pair(time_low << 32 | time_mid << 16 | time_hi_and_version, lsb)
+    }
+
+    fn clock_seq_and_node() -> u64 {
+        let current = TIMEUUID_CLOCK_SEQ_AND_NODE.load(Ordering::Relaxed);
+        if current != 0 {
+            return current;
+        }
+
+        // Uses the randomness of the OS, or of the JavaScript environment on wasm32-unknown-unknown
+        let mut random = [0; 8];
+        getrandom::getrandom(&mut random).expect("Could not generate the timeuuid clock sequence");
+        let random = u64::from_le_bytes(random);
+        // The two most significant bits of the clock sequence hold the variant (0b10),
+        // and the multicast bit is the least significant bit of the node's first octet.
+        let generated = (random & 0x3FFF_FFFF_FFFF_FFFF) | 0x8000_0000_0000_0000 | 1 << 40;
+        match TIMEUUID_CLOCK_SEQ_AND_NODE.compare_exchange(
+            0,
+            generated,
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        ) {
+            Ok(_) => generated,
+            // Some other thread was faster, use its value
+            Err(actual) => actual,
+        }
+    }
+}
+
 impl std::str::FromStr for CqlTimeuuid {
     type Err = uuid::Error;
 
@@ -661,6 +791,15 @@ pub struct LegacySerializedValues {
 }
 
 /// Represents a CQL Duration value
+///
+/// All components of a valid CQL duration have the same sign.
+/// Months and days cannot be converted to a fixed number of nanoseconds
+/// (in general, a month has a variable number of days, and a day, outside
+/// of UTC, has a variable number of hours), so they are kept separately.
+///
+/// The [`Display`](std::fmt::Display) and [`FromStr`](std::str::FromStr)
+/// implementations use the CQL duration syntax, e.g. `1y2mo3d4h5m6s` or
+/// ISO 8601 `P1Y2M3DT4H5M6S`.
 #[derive(Clone, Debug, Copy, PartialEq, Eq)]
 pub struct CqlDuration {
     pub months: i32,
@@ -668,6 +807,398 @@ pub struct CqlDuration {
     pub nanoseconds: i64,
 }
 
+const NANOS_PER_DAY: i64 = 86_400_000_000_000;
+
+impl CqlDuration {
+    /// Adds two durations component by component.
+    ///
+    /// Returns `None` if any of the components overflows, or if the components
+    /// of the result have different signs, i.e. it is not a valid CQL duration.
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        Self {
+            months: self.months.checked_add(rhs.months)?,
+            days: self.days.checked_add(rhs.days)?,
+            nanoseconds: self.nanoseconds.checked_add(rhs.nanoseconds)?,
+        }
+        .valid()
+    }
+
+    /// Subtracts two durations component by component.
+    ///
+    /// Returns `None` under the same conditions as [`checked_add`](Self::checked_add).
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.checked_add(rhs.checked_neg()?)
+    }
+
+    /// Negates all the components of the duration.
+    ///
+    /// Returns `None` if any of the components overflows.
+    pub fn checked_neg(self) -> Option<Self> {
+        Some(Self {
+            months: self.months.checked_neg()?,
+            days: self.days.checked_neg()?,
+            nanoseconds: self.nanoseconds.checked_neg()?,
+        })
+    }
+
+    /// Moves whole days from the nanoseconds component to the days component,
+    /// treating a day as 24 hours, like the database does for UTC timestamps.
+    ///
+    /// Months are left untouched, as they don't have a fixed number of days.
+    /// Returns `None` if the days component overflows.
+    pub fn normalized(self) -> Option<Self> {
+        let days = i32::try_from(self.nanoseconds / NANOS_PER_DAY).ok()?;
+        Some(Self {
+            months: self.months,
+            days: self.days.checked_add(days)?,
+            nanoseconds: self.nanoseconds % NANOS_PER_DAY,
+        })
+    }
+
+    fn is_negative(&self) -> bool {
+        self.months < 0 || self.days < 0 || self.nanoseconds < 0
+    }
+
+    fn is_positive(&self) -> bool {
+        self.months > 0 || self.days > 0 || self.nanoseconds > 0
+    }
+
+    fn valid(self) -> Option<Self> {
+        (!(self.is_negative() && self.is_positive())).then_some(self)
+    }
+
+    /// Number of nanoseconds in the duration, treating a day as 24 hours,
+    /// or `None` if the duration has months
+    fn total_nanoseconds(&self) -> Option<i128> {
+        (self.months == 0)
+            .then(|| self.days as i128 * NANOS_PER_DAY as i128 + self.nanoseconds as i128)
+    }
+}
+
+impl TryFrom<std::time::Duration> for CqlDuration {
+    type Error = ValueOverflow;
+
+    fn try_from(value: std::time::Duration) -> Result<Self, Self::Error> {
+        Ok(Self {
+            months: 0,
+            days: 0,
+            nanoseconds: value.as_nanos().try_into().map_err(|_| ValueOverflow)?,
+        })
+    }
+}
+
+/// Only nonnegative durations without months can be converted.
+/// A day is treated as 24 hours.
+impl TryInto<std::time::Duration> for CqlDuration {
+    type Error = ValueOverflow;
+
+    fn try_into(self) -> Result<std::time::Duration, Self::Error> {
+        let nanos = self.total_nanoseconds().ok_or(ValueOverflow)?;
+        let nanos = u128::try_from(nanos).map_err(|_| ValueOverflow)?;
+
+        // Both values fit: there are at most (2^31 * 86400 + 2^63 / 10^9) seconds
+        Ok(std::time::Duration::new(
+            (nanos / 1_000_000_000) as u64,
+            (nanos % 1_000_000_000) as u32,
+        ))
+    }
+}
+
+#[cfg(feature = "chrono-04")]
+impl TryFrom<chrono_04::Duration> for CqlDuration {
+    type Error = ValueOverflow;
+
+    fn try_from(value: chrono_04::Duration) -> Result<Self, Self::Error> {
+        Ok(Self {
+            months: 0,
+            days: 0,
+            nanoseconds: value.num_nanoseconds().ok_or(ValueOverflow)?,
+        })
+    }
+}
+
+/// Only durations without months can be converted.
+/// A day is treated as 24 hours.
+#[cfg(feature = "chrono-04")]
+impl TryInto<chrono_04::Duration> for CqlDuration {
+    type Error = ValueOverflow;
+
+    fn try_into(self) -> Result<chrono_04::Duration, Self::Error> {
+        if self.months != 0 {
+            return Err(ValueOverflow);
+        }
+        chrono_04::Duration::try_days(self.days.into())
+            .and_then(|days| days.checked_add(&chrono_04::Duration::nanoseconds(self.nanoseconds)))
+            .ok_or(ValueOverflow)
+    }
+}
+
+/// Displays the duration in the CQL duration syntax, e.g. `-1y2mo3d4h5m6s7ms8us9ns`,
+/// omitting the zero components. The zero duration is displayed as `0s`.
+///
+/// The output can be parsed back using the [`FromStr`](std::str::FromStr) implementation,
+/// unless the components have different signs, i.e. the duration is not a valid
+/// CQL duration. In such case, the sign of the negative components is ignored.
+impl std::fmt::Display for CqlDuration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.months == 0 && self.days == 0 && self.nanoseconds == 0 {
+            return write!(f, "0s");
+        }
+        if self.is_negative() {
+            write!(f, "-")?;
+        }
+
+        let months = self.months.unsigned_abs() as u64;
+        let mut nanoseconds = self.nanoseconds.unsigned_abs();
+        let components = [
+            (months / 12, "y"),
+            (months % 12, "mo"),
+            (self.days.unsigned_abs() as u64, "d"),
+        ];
+        let nanosecond_units = [
+            (3_600_000_000_000, "h"),
+            (60_000_000_000, "m"),
+            (1_000_000_000, "s"),
+            (1_000_000, "ms"),
+            (1_000, "us"),
+            (1, "ns"),
+        ];
+        let components = components
+            .into_iter()
+            .chain(nanosecond_units.into_iter().map(|(nanos_per_unit, unit)| {
+                let count = nanoseconds / nanos_per_unit;
+                nanoseconds %= nanos_per_unit;
+                (count, unit)
+            }));
+
+        for (count, unit) in components {
+            if count != 0 {
+                write!(f, "{}{}", count, unit)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Error returned when parsing a [`CqlDuration`] from a string fails.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum CqlDurationParseError {
+    #[error("Unable to convert '{0}' to a duration")]
+    InvalidFormat(String),
+    #[error("The duration '{0}' is out of the supported range")]
+    Overflow(String),
+}
+
+/// Parses a duration in one of the formats supported by CQL:
+/// - `(quantity unit)+`, e.g. `12h30m`, where unit is one of `y`, `mo`, `w`, `d`,
+///   `h`, `m`, `s`, `ms`, `us` (or `µs`) and `ns` (case-insensitive), with each unit
+///   used at most once, in this order,
+/// - ISO 8601 format with designators: `P[n]Y[n]M[n]DT[n]H[n]M[n]S` or `P[n]W`,
+/// - ISO 8601 alternative format: `PYYYY-MM-DDThh:mm:ss`.
+///
+/// Each format accepts a leading `-` for negative durations.
+impl std::str::FromStr for CqlDuration {
+    type Err = CqlDurationParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, unsigned) = match s.strip_prefix('-') {
+            Some(unsigned) => (true, unsigned),
+            None => (false, s),
+        };
+
+        let mut builder = CqlDurationBuilder::default();
+        let result = match unsigned.strip_prefix('P') {
+            Some(iso) => builder.parse_iso(iso),
+            None => builder.parse_standard(unsigned),
+        };
+
+        match result {
+            Ok(()) => Ok(builder.build(negative)),
+            Err(DurationParseFailure::InvalidFormat) => {
+                Err(CqlDurationParseError::InvalidFormat(s.to_owned()))
+            }
+            Err(DurationParseFailure::Overflow) => {
+                Err(CqlDurationParseError::Overflow(s.to_owned()))
+            }
+        }
+    }
+}
+
+enum DurationParseFailure {
+    InvalidFormat,
+    Overflow,
+}
+
+#[derive(Clone, Copy)]
+enum CqlDurationUnit {
+    Months(i64),
+    Days(i64),
+    Nanoseconds(i64),
+}
+
+/// Accumulates the magnitudes of the components of a parsed duration.
+#[derive(Default)]
+struct CqlDurationBuilder {
+    months: i64,
+    days: i64,
+    nanoseconds: i64,
+}
+
+impl CqlDurationBuilder {
+    fn add(&mut self, count: &str, unit: CqlDurationUnit) -> Result<(), DurationParseFailure> {
+        if count.is_empty() || !count.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(DurationParseFailure::InvalidFormat);
+        }
+        let count: i64 = count.parse().map_err(|_| DurationParseFailure::Overflow)?;
+
+        let (component, multiplier, limit) = match unit {
+            CqlDurationUnit::Months(multiplier) => (&mut self.months, multiplier, i32::MAX as i64),
+            CqlDurationUnit::Days(multiplier) => (&mut self.days, multiplier, i32::MAX as i64),
+            CqlDurationUnit::Nanoseconds(multiplier) => {
+                (&mut self.nanoseconds, multiplier, i64::MAX)
+            }
+        };
+        *component = count
+            .checked_mul(multiplier)
+            .and_then(|value| component.checked_add(value))
+            .filter(|value| *value <= limit)
+            .ok_or(DurationParseFailure::Overflow)?;
+        Ok(())
+    }
+
+    fn parse_standard(&mut self, mut s: &str) -> Result<(), DurationParseFailure> {
+        if s.is_empty() {
+            return Err(DurationParseFailure::InvalidFormat);
+        }
+
+        let mut previous_unit = None;
+        while !s.is_empty() {
+            let count_end = s
+                .find(|c: char| !c.is_ascii_digit())
+                .ok_or(DurationParseFailure::InvalidFormat)?;
+            let (count, rest) = s.split_at(count_end);
+            let unit_end = rest
+                .find(|c: char| c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let (unit, rest) = rest.split_at(unit_end);
+
+            let (unit_index, unit) = match unit.to_ascii_lowercase().as_str() {
+                "y" => (0, CqlDurationUnit::Months(12)),
+                "mo" => (1, CqlDurationUnit::Months(1)),
+                "w" => (2, CqlDurationUnit::Days(7)),
+                "d" => (3, CqlDurationUnit::Days(1)),
+                "h" => (4, CqlDurationUnit::Nanoseconds(3_600_000_000_000)),
+                "m" => (5, CqlDurationUnit::Nanoseconds(60_000_000_000)),
+                "s" => (6, CqlDurationUnit::Nanoseconds(1_000_000_000)),
+                "ms" => (7, CqlDurationUnit::Nanoseconds(1_000_000)),
+                "us" | "µs" => (8, CqlDurationUnit::Nanoseconds(1_000)),
+                "ns" => (9, CqlDurationUnit::Nanoseconds(1)),
+                _ => return Err(DurationParseFailure::InvalidFormat),
+            };
+            // Units must be used in the decreasing order, each at most once
+            if matches!(previous_unit, Some(previous) if previous >= unit_index) {
+                return Err(DurationParseFailure::InvalidFormat);
+            }
+            previous_unit = Some(unit_index);
+
+            self.add(count, unit)?;
+            s = rest;
+        }
+        Ok(())
+    }
+
+    fn parse_iso(&mut self, s: &str) -> Result<(), DurationParseFailure> {
+        if let Some(weeks) = s.strip_suffix('W') {
+            return self.add(weeks, CqlDurationUnit::Days(7));
+        }
+        if s.contains(['-', ':']) {
+            return self.parse_iso_alternative(s);
+        }
+
+        let (date, time) = match s.split_once('T') {
+            Some((date, time)) if !time.is_empty() => (date, time),
+            Some(_) => return Err(DurationParseFailure::InvalidFormat),
+            None if !s.is_empty() => (s, ""),
+            None => return Err(DurationParseFailure::InvalidFormat),
+        };
+        self.parse_iso_designators(
+            date,
+            &[
+                ('Y', CqlDurationUnit::Months(12)),
+                ('M', CqlDurationUnit::Months(1)),
+                ('D', CqlDurationUnit::Days(1)),
+            ],
+        )?;
+        self.parse_iso_designators(
+            time,
+            &[
+                ('H', CqlDurationUnit::Nanoseconds(3_600_000_000_000)),
+                ('M', CqlDurationUnit::Nanoseconds(60_000_000_000)),
+                ('S', CqlDurationUnit::Nanoseconds(1_000_000_000)),
+            ],
+        )
+    }
+
+    fn parse_iso_designators(
+        &mut self,
+        mut s: &str,
+        designators: &[(char, CqlDurationUnit)],
+    ) -> Result<(), DurationParseFailure> {
+        // Each designator can be used at most once and in the given order,
+        // so the iterator is advanced past the used ones.
+        let mut designators = designators.iter();
+        while !s.is_empty() {
+            let count_end = s
+                .find(|c: char| !c.is_ascii_digit())
+                .ok_or(DurationParseFailure::InvalidFormat)?;
+            let (count, rest) = s.split_at(count_end);
+            let designator = rest.chars().next().unwrap();
+            let (_, unit) = designators
+                .find(|(d, _)| *d == designator)
+                .ok_or(DurationParseFailure::InvalidFormat)?;
+
+            self.add(count, *unit)?;
+            s = &rest[designator.len_utf8()..];
+        }
+        Ok(())
+    }
+
+    fn parse_iso_alternative(&mut self, s: &str) -> Result<(), DurationParseFailure> {
+        let (date, time) = s
+            .split_once('T')
+            .ok_or(DurationParseFailure::InvalidFormat)?;
+        let date: Vec<&str> = date.split('-').collect();
+        let time: Vec<&str> = time.split(':').collect();
+
+        match (date.as_slice(), time.as_slice()) {
+            ([years, months, days], [hours, minutes, seconds])
+                if years.len() == 4
+                    && [months, days, hours, minutes, seconds]
+                        .iter()
+                        .all(|part| part.len() == 2) =>
+            {
+                self.add(years, CqlDurationUnit::Months(12))?;
+                self.add(months, CqlDurationUnit::Months(1))?;
+                self.add(days, CqlDurationUnit::Days(1))?;
+                self.add(hours, CqlDurationUnit::Nanoseconds(3_600_000_000_000))?;
+                self.add(minutes, CqlDurationUnit::Nanoseconds(60_000_000_000))?;
+                self.add(seconds, CqlDurationUnit::Nanoseconds(1_000_000_000))
+            }
+            _ => Err(DurationParseFailure::InvalidFormat),
+        }
+    }
+
+    fn build(self, negative: bool) -> CqlDuration {
+        let sign = if negative { -1 } else { 1 };
+        // Magnitudes of months and days were checked to fit in i32 in `add`
+        CqlDuration {
+            months: (sign * self.months) as i32,
+            days: (sign * self.days) as i32,
+            nanoseconds: sign * self.nanoseconds,
+        }
+    }
+}
+
 #[derive(Debug, Error, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum SerializeValuesError {
     #[error("Too many values to add, max 65,535 values can be sent in a request")]
@@ -1683,7 +2214,10 @@ where
     IT: Iterator<Item = &'a VL> + Clone,
     VL: ValueList + 'a,
 {
-    type LegacyBatchValuesIter<'r> = LegacyBatchValuesIteratorFromIterator<IT> where Self: 'r;
+    type LegacyBatchValuesIter<'r>
+        = LegacyBatchValuesIteratorFromIterator<IT>
+    where
+        Self: 'r;
     fn batch_values_iter(&self) -> Self::LegacyBatchValuesIter<'_> {
         self.it.clone().into()
     }
@@ -1691,7 +2225,10 @@ where
 
 // Implement BatchValues for slices of ValueList types
 impl<T: ValueList> LegacyBatchValues for [T] {
-    type LegacyBatchValuesIter<'r> = LegacyBatchValuesIteratorFromIterator<std::slice::Iter<'r, T>> where Self: 'r;
+    type LegacyBatchValuesIter<'r>
+        = LegacyBatchValuesIteratorFromIterator<std::slice::Iter<'r, T>>
+    where
+        Self: 'r;
     fn batch_values_iter(&self) -> Self::LegacyBatchValuesIter<'_> {
         self.iter().into()
     }
@@ -1699,7 +2236,10 @@ impl<T: ValueList> LegacyBatchValues for [T] {
 
 // Implement BatchValues for Vec<ValueList>
 impl<T: ValueList> LegacyBatchValues for Vec<T> {
-    type LegacyBatchValuesIter<'r> = LegacyBatchValuesIteratorFromIterator<std::slice::Iter<'r, T>> where Self: 'r;
+    type LegacyBatchValuesIter<'r>
+        = LegacyBatchValuesIteratorFromIterator<std::slice::Iter<'r, T>>
+    where
+        Self: 'r;
     fn batch_values_iter(&self) -> Self::LegacyBatchValuesIter<'_> {
         LegacyBatchValues::batch_values_iter(self.as_slice())
     }
@@ -1708,7 +2248,10 @@ impl<T: ValueList> LegacyBatchValues for Vec<T> {
 // Here is an example implementation for (T0, )
 // Further variants are done using a macro
 impl<T0: ValueList> LegacyBatchValues for (T0,) {
-    type LegacyBatchValuesIter<'r> = LegacyBatchValuesIteratorFromIterator<std::iter::Once<&'r T0>> where Self: 'r;
+    type LegacyBatchValuesIter<'r>
+        = LegacyBatchValuesIteratorFromIterator<std::iter::Once<&'r T0>>
+    where
+        Self: 'r;
     fn batch_values_iter(&self) -> Self::LegacyBatchValuesIter<'_> {
         std::iter::once(&self.0).into()
     }
@@ -1797,7 +2340,10 @@ impl_batch_values_for_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T
 
 // Every &impl BatchValues should also implement BatchValues
 impl<'a, T: LegacyBatchValues + ?Sized> LegacyBatchValues for &'a T {
-    type LegacyBatchValuesIter<'r> = <T as LegacyBatchValues>::LegacyBatchValuesIter<'r> where Self: 'r;
+    type LegacyBatchValuesIter<'r>
+        = <T as LegacyBatchValues>::LegacyBatchValuesIter<'r>
+    where
+        Self: 'r;
     fn batch_values_iter(&self) -> Self::LegacyBatchValuesIter<'_> {
         <T as LegacyBatchValues>::batch_values_iter(*self)
     }
@@ -1827,8 +2373,10 @@ impl<'f, T: LegacyBatchValues> LegacyBatchValuesFirstSerialized<'f, T> {
 }
 
 impl<'f, BV: LegacyBatchValues> LegacyBatchValues for LegacyBatchValuesFirstSerialized<'f, BV> {
-    type LegacyBatchValuesIter<'r> =
-        LegacyBatchValuesFirstSerialized<'f, <BV as LegacyBatchValues>::LegacyBatchValuesIter<'r>> where Self: 'r;
+    type LegacyBatchValuesIter<'r>
+        = LegacyBatchValuesFirstSerialized<'f, <BV as LegacyBatchValues>::LegacyBatchValuesIter<'r>>
+    where
+        Self: 'r;
     fn batch_values_iter(&self) -> Self::LegacyBatchValuesIter<'_> {
         LegacyBatchValuesFirstSerialized {
             first: self.first,
@@ -7,8 +7,9 @@ use crate::types::serialize::{CellWriter, RowWriter};
 
 use super::response::result::{ColumnSpec, ColumnType, TableSpec};
 use super::value::{
-    CqlDate, CqlDuration, CqlTime, CqlTimestamp, LegacyBatchValues, LegacySerializedValues,
-    MaybeUnset, SerializeValuesError, Unset, Value, ValueList, ValueTooBig,
+    CqlDate, CqlDuration, CqlDurationParseError, CqlTime, CqlTimestamp, LegacyBatchValues,
+    LegacySerializedValues, MaybeUnset, SerializeValuesError, Unset, Value, ValueList,
+    ValueOverflow, ValueTooBig,
 };
 #[cfg(test)]
 use assert_matches::assert_matches;
@@ -644,6 +645,56 @@ fn timeuuid_ordering_properties() {
     assert_eq!(compute_hash(&x), compute_hash(&y));
 }
 
+#[test]
+fn timeuuid_generation() {
+    let before = CqlTimestamp(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as i64,
+    );
+    let generated: Vec<CqlTimeuuid> = (0..1000).map(|_| CqlTimeuuid::now()).collect();
+
+    for pair in generated.windows(2) {
+        assert!(pair[0] < pair[1]);
+        assert_eq!(pair[0].as_ref().get_version_num(), 1);
+        assert_eq!(pair[0].as_ref().get_variant(), uuid::Variant::RFC4122);
+        // Clock sequence and node are the same within the process
+        assert_eq!(pair[0].as_u64_pair().1, pair[1].as_u64_pair().1);
+    }
+    // The multicast bit of the node is set
+    assert_eq!(generated[0].as_bytes()[10] & 1, 1);
+    assert!(generated[0].timestamp().0 >= before.0);
+}
+
+#[test]
+fn timeuuid_timestamps() {
+    // 2021-03-08T00:52:27.990Z, the timestamp of the uuids in `timeuuid_serialization`
+    let uuid = CqlTimeuuid::from_str("8e14e760-7fa8-11eb-bc66-000000000001").unwrap();
+    let timestamp = uuid.timestamp();
+    assert_eq!(timestamp, CqlTimestamp(1615164747990));
+
+    let min = CqlTimeuuid::min_for_timestamp(timestamp);
+    let max = CqlTimeuuid::max_for_timestamp(timestamp);
+    assert!(min <= uuid && uuid <= max);
+    assert_eq!(min.timestamp(), timestamp);
+    assert_eq!(max.timestamp(), timestamp);
+    assert_eq!(min.cmp_timestamp(&max), std::cmp::Ordering::Less);
+    assert_eq!(max.cmp_timestamp(&uuid), std::cmp::Ordering::Greater);
+    // Only the timestamps are compared
+    let other_node = CqlTimeuuid::from_str("8e14e760-7fa8-11eb-8000-000000000000").unwrap();
+    assert_ne!(uuid, other_node);
+    assert_eq!(uuid.cmp_timestamp(&other_node), std::cmp::Ordering::Equal);
+
+    let next = CqlTimeuuid::min_for_timestamp(CqlTimestamp(timestamp.0 + 1));
+    assert!(max < next);
+
+    // Dates before the unix epoch
+    let old = CqlTimeuuid::min_for_timestamp(CqlTimestamp(-1));
+    assert_eq!(old.timestamp(), CqlTimestamp(-1));
+    assert_eq!(old.unix_timestamp_100ns(), -10_000);
+}
+
 #[test]
 fn cqlduration_serialization() {
     let duration = CqlDuration {
@@ -657,6 +708,257 @@ fn cqlduration_serialization() {
     );
 }
 
+#[test]
+fn cqlduration_parsing() {
+    let duration = |months, days, nanoseconds| CqlDuration {
+        months,
+        days,
+        nanoseconds,
+    };
+    let hour = 3_600_000_000_000;
+
+    let tests = [
+        ("12h30m", duration(0, 0, 12 * hour + hour / 2)),
+        ("1y2mo3w4d", duration(14, 25, 0)),
+        ("1ms3µs4ns", duration(0, 0, 1_003_004)),
+        ("1s2US", duration(0, 0, 1_000_002_000)),
+        ("7MO", duration(7, 0, 0)),
+        ("-5d", duration(0, -5, 0)),
+        ("P3Y6M4DT12H", duration(42, 4, 12 * hour)),
+        ("PT30M", duration(0, 0, hour / 2)),
+        ("P2W", duration(0, 14, 0)),
+        (
+            "-P0001-02-03T04:05:06",
+            duration(-14, -3, -(4 * hour + 306_000_000_000)),
+        ),
+    ];
+    for (text, expected) in tests {
+        assert_eq!(text.parse::<CqlDuration>(), Ok(expected), "{}", text);
+    }
+
+    for text in [
+        "",
+        "12",
+        "h",
+        "1m1h",
+        "1h1h",
+        "1x",
+        "P",
+        "PT",
+        "P1M1Y",
+        "P1-02-03T04:05:06",
+    ] {
+        assert_eq!(
+            text.parse::<CqlDuration>(),
+            Err(CqlDurationParseError::InvalidFormat(text.to_owned())),
+            "{}",
+            text
+        );
+    }
+    for text in [
+        "2147483648mo",
+        "179000000y",
+        "9223372036854775808ns",
+        "3000000h",
+    ] {
+        assert_eq!(
+            text.parse::<CqlDuration>(),
+            Err(CqlDurationParseError::Overflow(text.to_owned())),
+            "{}",
+            text
+        );
+    }
+}
+
+#[test]
+fn cqlduration_display() {
+    let tests = [
+        (
+            CqlDuration {
+                months: 0,
+                days: 0,
+                nanoseconds: 0,
+            },
+            "0s",
+        ),
+        (
+            CqlDuration {
+                months: 14,
+                days: 3,
+                nanoseconds: 45_296_007_008_009,
+            },
+            "1y2mo3d12h34m56s7ms8us9ns",
+        ),
+        (
+            CqlDuration {
+                months: 0,
+                days: -1,
+                nanoseconds: -60_000_000_000,
+            },
+            "-1d1m",
+        ),
+    ];
+    for (duration, expected) in tests {
+        assert_eq!(duration.to_string(), expected);
+        assert_eq!(expected.parse::<CqlDuration>(), Ok(duration));
+    }
+}
+
+#[test]
+fn cqlduration_arithmetic() {
+    let a = CqlDuration {
+        months: 1,
+        days: 2,
+        nanoseconds: 3,
+    };
+    let b = CqlDuration {
+        months: 4,
+        days: 5,
+        nanoseconds: 6,
+    };
+
+    assert_eq!(
+        a.checked_add(b),
+        Some(CqlDuration {
+            months: 5,
+            days: 7,
+            nanoseconds: 9
+        })
+    );
+    assert_eq!(
+        b.checked_sub(a),
+        Some(CqlDuration {
+            months: 3,
+            days: 3,
+            nanoseconds: 3
+        })
+    );
+    assert_eq!(
+        a.checked_neg(),
+        Some(CqlDuration {
+            months: -1,
+            days: -2,
+            nanoseconds: -3
+        })
+    );
+    // Components with mixed signs
+    let minus_week = CqlDuration {
+        months: 0,
+        days: -7,
+        nanoseconds: 0,
+    };
+    assert_eq!(a.checked_add(minus_week), None);
+    // Overflow
+    let max = CqlDuration {
+        months: i32::MAX,
+        days: 0,
+        nanoseconds: 0,
+    };
+    assert_eq!(max.checked_add(a), None);
+
+    assert_eq!(
+        CqlDuration {
+            months: -1,
+            days: 0,
+            nanoseconds: -90_000_000_000_000
+        }
+        .normalized(),
+        Some(CqlDuration {
+            months: -1,
+            days: -1,
+            nanoseconds: -3_600_000_000_000
+        })
+    );
+    assert_eq!(
+        CqlDuration {
+            months: 0,
+            days: i32::MAX,
+            nanoseconds: i64::MAX
+        }
+        .normalized(),
+        None
+    );
+}
+
+#[test]
+fn cqlduration_conversions() {
+    let day_and_second = CqlDuration {
+        months: 0,
+        days: 1,
+        nanoseconds: 1_000_000_000,
+    };
+    let std_duration: std::time::Duration = day_and_second.try_into().unwrap();
+    assert_eq!(std_duration, std::time::Duration::from_secs(86_401));
+    assert_eq!(
+        CqlDuration::try_from(std_duration),
+        Ok(CqlDuration {
+            months: 0,
+            days: 0,
+            nanoseconds: 86_401_000_000_000
+        })
+    );
+
+    let with_months = CqlDuration {
+        months: 1,
+        days: 0,
+        nanoseconds: 0,
+    };
+    let negative = CqlDuration {
+        months: 0,
+        days: -1,
+        nanoseconds: 0,
+    };
+    assert_eq!(
+        TryInto::<std::time::Duration>::try_into(with_months),
+        Err(ValueOverflow)
+    );
+    assert_eq!(
+        TryInto::<std::time::Duration>::try_into(negative),
+        Err(ValueOverflow)
+    );
+    assert_eq!(
+        CqlDuration::try_from(std::time::Duration::MAX),
+        Err(ValueOverflow)
+    );
+}
+
+#[cfg(feature = "chrono-04")]
+#[test]
+fn cqlduration_chrono_conversions() {
+    let negative = CqlDuration {
+        months: 0,
+        days: -1,
+        nanoseconds: -5,
+    };
+    let chrono_duration: chrono_04::Duration = negative.try_into().unwrap();
+    assert_eq!(
+        chrono_duration,
+        chrono_04::Duration::try_days(-1).unwrap() - chrono_04::Duration::nanoseconds(5)
+    );
+    assert_eq!(
+        CqlDuration::try_from(chrono_duration),
+        Ok(CqlDuration {
+            months: 0,
+            days: 0,
+            nanoseconds: -86_400_000_000_005
+        })
+    );
+
+    let with_months = CqlDuration {
+        months: 1,
+        days: 0,
+        nanoseconds: 0,
+    };
+    assert_eq!(
+        TryInto::<chrono_04::Duration>::try_into(with_months),
+        Err(ValueOverflow)
+    );
+    assert_eq!(
+        CqlDuration::try_from(chrono_04::Duration::MAX),
+        Err(ValueOverflow)
+    );
+}
+
 #[test]
 fn box_serialization() {
     let x: Box<i32> = Box::new(123);
@@ -1095,7 +1397,7 @@ fn serialize_values<T: ValueList + SerializeRow>(
     let mut old_serialized = Vec::new();
     serialized.write_to_request(&mut old_serialized);
 
-    let ctx = RowSerializationContext { columns };
+    let ctx = RowSerializationContext::from_specs(columns);
     let mut new_serialized = vec![0, 0];
     let mut writer = RowWriter::new(&mut new_serialized);
     <T as SerializeRow>::serialize(&vl, &ctx, &mut writer).unwrap();
@@ -1113,7 +1415,7 @@ fn serialize_values<T: ValueList + SerializeRow>(
 }
 
 fn serialize_values_only_new<T: SerializeRow>(vl: T, columns: &[ColumnSpec]) -> Vec<u8> {
-    let ctx = RowSerializationContext { columns };
+    let ctx = RowSerializationContext::from_specs(columns);
     let mut serialized = vec![0, 0];
     let mut writer = RowWriter::new(&mut serialized);
     <T as SerializeRow>::serialize(&vl, &ctx, &mut writer).unwrap();
@@ -1322,7 +1624,7 @@ fn serialize_batch_value_iterators<'a>(
         data
     }
 
-    let ctx = RowSerializationContext { columns };
+    let ctx = RowSerializationContext::from_specs(columns);
     let data = serialize_bvi(bvi, &ctx);
     let adapted_data = serialize_bvi(bvi_adapted, &ctx);
 
@@ -1368,7 +1670,7 @@ fn slice_batch_values() {
 
     assert_eq!(iters.0.write_next_to_request(&mut Vec::new()), None);
 
-    let ctx = RowSerializationContext { columns: &[] };
+    let ctx = RowSerializationContext::from_specs(&[]);
     let mut data = Vec::new();
     let mut writer = RowWriter::new(&mut data);
     assert!(iters.1.serialize_next(&ctx, &mut writer).is_none());
@@ -1674,7 +1976,7 @@ fn check_batch_values_iterator_is_not_lending() {
         let mut it2 = bv.batch_values_iter();
 
         let columns = &[col_spec("a", ColumnType::Int)];
-        let ctx = RowSerializationContext { columns };
+        let ctx = RowSerializationContext::from_specs(columns);
         let mut data = Vec::new();
         let mut writer = RowWriter::new(&mut data);
 
@@ -1,3 +1,5 @@
+#[doc(hidden)]
+pub mod cql_lexer;
 pub mod errors;
 pub mod frame;
 #[macro_use]
@@ -14,6 +16,7 @@ pub mod macros {
     // Reexports for derive(IntoUserType)
     pub use bytes::{BufMut, Bytes, BytesMut};
 
+    pub use crate::impl_deserialize_value_via_from_cql_collection;
     pub use crate::impl_from_cql_value_from_method;
 }
 
@@ -49,6 +52,7 @@ pub mod _macro_internal {
         BuiltinDeserializationError as BuiltinTypeDeserializationError,
         BuiltinDeserializationErrorKind as BuiltinTypeDeserializationErrorKind,
         BuiltinTypeCheckErrorKind as DeserBuiltinTypeTypeCheckErrorKind, DeserializeValue,
+        TupleDeserializationErrorKind, TupleTypeCheckErrorKind as DeserTupleTypeCheckErrorKind,
         UdtDeserializationErrorKind, UdtIterator,
         UdtTypeCheckErrorKind as DeserUdtTypeCheckErrorKind,
     };
@@ -65,7 +69,8 @@ pub mod _macro_internal {
         BuiltinSerializationErrorKind as BuiltinTypeSerializationErrorKind,
         BuiltinTypeCheckError as BuiltinTypeTypeCheckError,
         BuiltinTypeCheckErrorKind as BuiltinTypeTypeCheckErrorKind, SerializeValue,
-        UdtSerializationErrorKind, UdtTypeCheckErrorKind,
+        TupleSerializationErrorKind, TupleTypeCheckErrorKind, UdtSerializationErrorKind,
+        UdtTypeCheckErrorKind,
     };
     pub use crate::types::serialize::writers::WrittenCellProof;
     pub use crate::types::serialize::{
@@ -0,0 +1,571 @@
+//! Client-side encryption of column values.
+//!
+//! Encrypted columns are stored in the database as `blob`s. When a value is bound
+//! to such a column, the driver serializes it according to its cleartext type,
+//! encrypts the serialized bytes with a user-supplied [`ColumnCryptoProvider`]
+//! and sends the ciphertext as the blob. Values of encrypted columns received
+//! in query results are decrypted and deserialized according to the cleartext type,
+//! so that they look like regular columns of that type.
+//!
+//! Columns are identified by keyspace, table and column name, as reported by the server
+//! in the metadata of bind markers and result columns. Selecting an encrypted column
+//! under an alias, or binding it through a named bind marker whose name differs
+//! from the column name, is not supported.
+
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::frame::response::result::{
+    deser_cql_value, ColumnSpec, ColumnType, CqlValue, RawRows, Rows,
+};
+use crate::frame::types;
+use crate::types::deserialize::DeserializationError;
+use crate::types::serialize::writers::CellOverflowError;
+use crate::types::serialize::SerializationError;
+
+/// Encrypts and decrypts values of encrypted columns.
+///
+/// Values are passed to the provider in their serialized form.
+/// If the provider is to support using an encrypted column in a `WHERE` clause,
+/// e.g. as a partition key, its encryption must be deterministic.
+pub trait ColumnCryptoProvider: Send + Sync {
+    /// Encrypts a serialized value of the given column.
+    fn encrypt(
+        &self,
+        column: &EncryptedColumn,
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>>;
+
+    /// Decrypts a value of the given column, returning the serialized cleartext value.
+    fn decrypt(
+        &self,
+        column: &EncryptedColumn,
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>>;
+}
+
+/// A column whose values are encrypted on the client side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedColumn {
+    pub keyspace: String,
+    pub table: String,
+    pub column: String,
+    /// Type of the values before encryption.
+    pub cleartext_type: ColumnType,
+}
+
+impl EncryptedColumn {
+    fn matches(&self, spec: &ColumnSpec) -> bool {
+        self.column == spec.name
+            && self.table == spec.table_spec.table_name()
+            && self.keyspace == spec.table_spec.ks_name()
+    }
+}
+
+/// An error returned when encrypting or decrypting a value of an encrypted column fails.
+#[derive(Error, Debug, Clone)]
+#[non_exhaustive]
+pub enum ColumnEncryptionError {
+    /// The column is configured as encrypted, but its type in the database is not `blob`.
+    #[error("Encrypted column {column} has type {typ:?} instead of blob")]
+    NotABlob { column: String, typ: ColumnType },
+
+    /// The crypto provider failed to encrypt a value.
+    #[error("Failed to encrypt a value of column {column}: {err}")]
+    EncryptionFailed {
+        column: String,
+        err: Arc<dyn Error + Send + Sync>,
+    },
+
+    /// The crypto provider failed to decrypt a value.
+    #[error("Failed to decrypt a value of column {column}: {err}")]
+    DecryptionFailed {
+        column: String,
+        err: Arc<dyn Error + Send + Sync>,
+    },
+
+    /// The decrypted value is not a valid value of the cleartext type.
+    #[error("Failed to deserialize a decrypted value of column {column}: {err}")]
+    DeserializationFailed {
+        column: String,
+        err: DeserializationError,
+    },
+}
+
+/// Configures which columns are encrypted on the client side, and how.
+///
+/// # Example
+/// ```rust
+/// # use scylla_cql::frame::response::result::ColumnType;
+/// # use scylla_cql::types::column_encryption::{ColumnCryptoProvider, ColumnEncryptionPolicy, EncryptedColumn};
+/// # use std::error::Error;
+/// # use std::sync::Arc;
+/// struct MyProvider;
+///
+/// impl ColumnCryptoProvider for MyProvider {
+///     fn encrypt(&self, _: &EncryptedColumn, plaintext: &[u8]) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+///         // Use a real cipher here.
+///         Ok(plaintext.to_vec())
+///     }
+///
+///     fn decrypt(&self, _: &EncryptedColumn, ciphertext: &[u8]) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+///         Ok(ciphertext.to_vec())
+///     }
+/// }
+///
+/// let policy = ColumnEncryptionPolicy::new(Arc::new(MyProvider))
+///     .with_encrypted_column("ks", "users", "ssn", ColumnType::Text);
+/// ```
+pub struct ColumnEncryptionPolicy {
+    provider: Arc<dyn ColumnCryptoProvider>,
+    columns: Vec<Arc<EncryptedColumn>>,
+}
+
+impl fmt::Debug for ColumnEncryptionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ColumnEncryptionPolicy")
+            .field("columns", &self.columns)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ColumnEncryptionPolicy {
+    /// Creates a policy which encrypts values with the given provider.
+    /// No columns are encrypted until they are added with [`Self::with_encrypted_column`].
+    pub fn new(provider: Arc<dyn ColumnCryptoProvider>) -> Self {
+        Self {
+            provider,
+            columns: Vec::new(),
+        }
+    }
+
+    /// Marks the column as encrypted. The column must have the `blob` type in the database,
+    /// and its values are converted from and to values of `cleartext_type`.
+    pub fn with_encrypted_column(
+        mut self,
+        keyspace: impl Into<String>,
+        table: impl Into<String>,
+        column: impl Into<String>,
+        cleartext_type: ColumnType,
+    ) -> Self {
+        let column = EncryptedColumn {
+            keyspace: keyspace.into(),
+            table: table.into(),
+            column: column.into(),
+            cleartext_type,
+        };
+        self.columns.retain(|c| {
+            (&c.keyspace, &c.table, &c.column) != (&column.keyspace, &column.table, &column.column)
+        });
+        self.columns.push(Arc::new(column));
+        self
+    }
+
+    /// Returns the encrypted column with the given name, if there is one.
+    pub fn encrypted_column(
+        &self,
+        keyspace: &str,
+        table: &str,
+        column: &str,
+    ) -> Option<&EncryptedColumn> {
+        self.columns
+            .iter()
+            .find(|c| c.keyspace == keyspace && c.table == table && c.column == column)
+            .map(|c| c.as_ref())
+    }
+
+    fn find(&self, spec: &ColumnSpec) -> Option<&Arc<EncryptedColumn>> {
+        self.columns.iter().find(|c| c.matches(spec))
+    }
+
+    /// Prepares encryption of values bound to a statement with the given bind markers.
+    /// Returns `None` if none of the bind markers refers to an encrypted column.
+    pub fn for_bind_markers(
+        self: &Arc<Self>,
+        col_specs: &[ColumnSpec],
+    ) -> Option<BindMarkersEncryption> {
+        let encrypted: Vec<_> = col_specs
+            .iter()
+            .map(|spec| self.find(spec).cloned())
+            .collect();
+        if encrypted.iter().all(Option::is_none) {
+            return None;
+        }
+        let cleartext_columns = col_specs
+            .iter()
+            .zip(encrypted.iter())
+            .map(|(spec, column)| match column {
+                Some(column) => ColumnSpec {
+                    typ: column.cleartext_type.clone(),
+                    ..spec.clone()
+                },
+                None => spec.clone(),
+            })
+            .collect();
+        Some(BindMarkersEncryption {
+            policy: self.clone(),
+            cleartext_columns,
+            server_types: col_specs.iter().map(|spec| spec.typ.clone()).collect(),
+            encrypted,
+        })
+    }
+
+    /// Decrypts the values of encrypted columns in the given rows, and changes the types
+    /// of those columns in the metadata to the cleartext types.
+    pub fn decrypt_rows(&self, rows: &mut Rows) -> Result<(), ColumnEncryptionError> {
+        for (idx, spec) in rows.metadata.col_specs.iter_mut().enumerate() {
+            let column = match self.find(spec) {
+                Some(column) => column,
+                None => continue,
+            };
+            if spec.typ != ColumnType::Blob {
+                return Err(ColumnEncryptionError::NotABlob {
+                    column: spec.name.clone(),
+                    typ: spec.typ.clone(),
+                });
+            }
+            for row in rows.rows.iter_mut() {
+                let value = match row.columns.get_mut(idx) {
+                    Some(value) => value,
+                    None => continue,
+                };
+                let ciphertext = match value.take() {
+                    Some(CqlValue::Blob(ciphertext)) => ciphertext,
+                    other => {
+                        *value = other;
+                        continue;
+                    }
+                };
+                let plaintext = self.provider.decrypt(column, &ciphertext).map_err(|err| {
+                    ColumnEncryptionError::DecryptionFailed {
+                        column: spec.name.clone(),
+                        err: err.into(),
+                    }
+                })?;
+                let decrypted = deser_cql_value(&column.cleartext_type, &mut plaintext.as_slice())
+                    .map_err(|err| ColumnEncryptionError::DeserializationFailed {
+                        column: spec.name.clone(),
+                        err,
+                    })?;
+                *value = Some(decrypted);
+            }
+            spec.typ = column.cleartext_type.clone();
+        }
+        Ok(())
+    }
+
+    /// Decrypts the values of encrypted columns in the given serialized rows, and changes
+    /// the types of those columns in the metadata to the cleartext types.
+    pub fn decrypt_raw_rows(&self, rows: &mut RawRows) -> Result<(), ColumnEncryptionError> {
+        let encrypted: Vec<_> = rows
+            .metadata
+            .col_specs
+            .iter()
+            .map(|spec| self.find(spec))
+            .collect();
+        if encrypted.iter().all(Option::is_none) {
+            return Ok(());
+        }
+        for (spec, column) in rows.metadata.col_specs.iter().zip(encrypted.iter()) {
+            if column.is_some() && spec.typ != ColumnType::Blob {
+                return Err(ColumnEncryptionError::NotABlob {
+                    column: spec.name.clone(),
+                    typ: spec.typ.clone(),
+                });
+            }
+        }
+
+        let mut decrypted = Vec::with_capacity(rows.raw_rows.len());
+        let mut rest: &[u8] = &rows.raw_rows;
+        for _ in 0..rows.rows_count {
+            for (spec, column) in rows.metadata.col_specs.iter().zip(encrypted.iter()) {
+                let serialized = rest;
+                let value = types::read_bytes_opt(&mut rest).map_err(|err| {
+                    ColumnEncryptionError::DeserializationFailed {
+                        column: spec.name.clone(),
+                        err: DeserializationError::new(err),
+                    }
+                })?;
+                let (column, ciphertext) = match (column, value) {
+                    (Some(column), Some(ciphertext)) => (column, ciphertext),
+                    _ => {
+                        decrypted.extend_from_slice(&serialized[..serialized.len() - rest.len()]);
+                        continue;
+                    }
+                };
+                let plaintext = self.provider.decrypt(column, ciphertext).map_err(|err| {
+                    ColumnEncryptionError::DecryptionFailed {
+                        column: spec.name.clone(),
+                        err: err.into(),
+                    }
+                })?;
+                deser_cql_value(&column.cleartext_type, &mut plaintext.as_slice()).map_err(
+                    |err| ColumnEncryptionError::DeserializationFailed {
+                        column: spec.name.clone(),
+                        err,
+                    },
+                )?;
+                types::write_bytes(&plaintext, &mut decrypted).map_err(|err| {
+                    ColumnEncryptionError::DeserializationFailed {
+                        column: spec.name.clone(),
+                        err: DeserializationError::new(err),
+                    }
+                })?;
+            }
+        }
+        rows.raw_rows = decrypted.into();
+
+        for (spec, column) in rows.metadata.col_specs.iter_mut().zip(encrypted.iter()) {
+            if let Some(column) = column {
+                spec.typ = column.cleartext_type.clone();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Encryption of values bound to a statement, created with
+/// [`ColumnEncryptionPolicy::for_bind_markers`].
+///
+/// Use [`RowSerializationContext::with_column_encryption`](crate::types::serialize::row::RowSerializationContext::with_column_encryption)
+/// to serialize the values with it.
+#[derive(Debug, Clone)]
+pub struct BindMarkersEncryption {
+    policy: Arc<ColumnEncryptionPolicy>,
+    // Bind markers, with encrypted ones having the cleartext types.
+    cleartext_columns: Vec<ColumnSpec>,
+    // Types of the bind markers reported by the server.
+    server_types: Vec<ColumnType>,
+    encrypted: Vec<Option<Arc<EncryptedColumn>>>,
+}
+
+impl BindMarkersEncryption {
+    /// Returns the bind markers of the statement, with encrypted ones having the cleartext types.
+    pub fn cleartext_columns(&self) -> &[ColumnSpec] {
+        &self.cleartext_columns
+    }
+
+    // Encrypts values of encrypted columns among the serialized values
+    // at the end of the buffer, starting at the given offset.
+    pub(crate) fn encrypt_values(
+        &self,
+        buf: &mut Vec<u8>,
+        start: usize,
+    ) -> Result<(), SerializationError> {
+        let serialized = buf.split_off(start);
+        let mut rest = serialized.as_slice();
+        let mut idx = 0;
+        while rest.len() >= 4 {
+            let len = i32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]);
+            // Nulls and unset values have negative lengths and no contents.
+            let contents_len = usize::try_from(len).unwrap_or(0).min(rest.len() - 4);
+            let contents = &rest[4..4 + contents_len];
+            match self.encrypted.get(idx) {
+                Some(Some(column)) if len >= 0 => {
+                    if self.server_types[idx] != ColumnType::Blob {
+                        return Err(SerializationError::new(ColumnEncryptionError::NotABlob {
+                            column: column.column.clone(),
+                            typ: self.server_types[idx].clone(),
+                        }));
+                    }
+                    let ciphertext =
+                        self.policy
+                            .provider
+                            .encrypt(column, contents)
+                            .map_err(|err| {
+                                SerializationError::new(ColumnEncryptionError::EncryptionFailed {
+                                    column: column.column.clone(),
+                                    err: err.into(),
+                                })
+                            })?;
+                    let ciphertext_len: i32 = ciphertext
+                        .len()
+                        .try_into()
+                        .map_err(|_| SerializationError::new(CellOverflowError))?;
+                    buf.extend_from_slice(&ciphertext_len.to_be_bytes());
+                    buf.extend_from_slice(&ciphertext);
+                }
+                _ => buf.extend_from_slice(&rest[..4 + contents_len]),
+            }
+            rest = &rest[4 + contents_len..];
+            idx += 1;
+        }
+        buf.extend_from_slice(rest);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+    use std::sync::Arc;
+
+    use super::{
+        ColumnCryptoProvider, ColumnEncryptionError, ColumnEncryptionPolicy, EncryptedColumn,
+    };
+    use crate::frame::response::result::{
+        ColumnSpec, ColumnType, CqlValue, RawRows, ResultMetadata, Row, Rows, TableSpec,
+    };
+    use crate::frame::types::RawValue;
+    use crate::types::deserialize::row::DeserializeRow;
+    use crate::types::serialize::row::{RowSerializationContext, SerializedValues};
+
+    // Reverses the bytes and prepends a marker, which makes it easy to check
+    // what was encrypted.
+    struct ReversingProvider;
+
+    impl ColumnCryptoProvider for ReversingProvider {
+        fn encrypt(
+            &self,
+            _column: &EncryptedColumn,
+            plaintext: &[u8],
+        ) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+            let mut ciphertext = vec![0xee];
+            ciphertext.extend(plaintext.iter().rev());
+            Ok(ciphertext)
+        }
+
+        fn decrypt(
+            &self,
+            _column: &EncryptedColumn,
+            ciphertext: &[u8],
+        ) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+            match ciphertext.split_first() {
+                Some((0xee, rest)) => Ok(rest.iter().rev().copied().collect()),
+                _ => Err("bad ciphertext".into()),
+            }
+        }
+    }
+
+    fn spec(name: &str, typ: ColumnType) -> ColumnSpec {
+        ColumnSpec {
+            table_spec: TableSpec::owned("ks".to_owned(), "t".to_owned()),
+            name: name.to_owned(),
+            typ,
+        }
+    }
+
+    fn policy() -> Arc<ColumnEncryptionPolicy> {
+        Arc::new(
+            ColumnEncryptionPolicy::new(Arc::new(ReversingProvider))
+                .with_encrypted_column("ks", "t", "secret", ColumnType::Int)
+                .with_encrypted_column("ks", "other", "a", ColumnType::Text),
+        )
+    }
+
+    #[test]
+    fn bind_markers_without_encrypted_columns() {
+        let col_specs = [spec("a", ColumnType::Int), spec("b", ColumnType::Blob)];
+        assert!(policy().for_bind_markers(&col_specs).is_none());
+    }
+
+    #[test]
+    fn encrypts_bound_values() {
+        let col_specs = [
+            spec("a", ColumnType::Int),
+            spec("secret", ColumnType::Blob),
+            spec("secret", ColumnType::Blob),
+        ];
+        let encryption = policy().for_bind_markers(&col_specs).unwrap();
+        assert_eq!(encryption.cleartext_columns()[1].typ, ColumnType::Int);
+
+        let ctx = RowSerializationContext::with_column_encryption(&encryption);
+        let values =
+            SerializedValues::from_serializable(&ctx, &(1i32, 0x01020304i32, None::<i32>)).unwrap();
+        let values: Vec<_> = values.iter().collect();
+        assert_eq!(
+            values,
+            vec![
+                RawValue::Value(&[0, 0, 0, 1]),
+                RawValue::Value(&[0xee, 4, 3, 2, 1]),
+                RawValue::Null,
+            ]
+        );
+
+        // Without the encryption, the cleartext value doesn't type check against a blob.
+        let ctx = RowSerializationContext::from_specs(&col_specs);
+        assert!(SerializedValues::from_serializable(&ctx, &(1i32, 2i32, None::<i32>)).is_err());
+    }
+
+    #[test]
+    fn rejects_encrypted_columns_of_non_blob_type() {
+        let col_specs = [spec("secret", ColumnType::Int)];
+        let encryption = policy().for_bind_markers(&col_specs).unwrap();
+        let ctx = RowSerializationContext::with_column_encryption(&encryption);
+        let err = SerializedValues::from_serializable(&ctx, &(1i32,)).unwrap_err();
+        assert!(err.to_string().contains("instead of blob"), "{}", err);
+    }
+
+    #[test]
+    fn decrypts_rows() {
+        let mut metadata = ResultMetadata::default();
+        metadata.col_specs = vec![spec("a", ColumnType::Int), spec("secret", ColumnType::Blob)];
+        let mut rows = Rows {
+            metadata,
+            rows_count: 2,
+            rows: vec![
+                Row {
+                    columns: vec![
+                        Some(CqlValue::Int(1)),
+                        Some(CqlValue::Blob(vec![0xee, 4, 3, 2, 1])),
+                    ],
+                },
+                Row {
+                    columns: vec![Some(CqlValue::Int(2)), None],
+                },
+            ],
+            serialized_size: 0,
+        };
+        policy().decrypt_rows(&mut rows).unwrap();
+        assert_eq!(rows.metadata.col_specs[1].typ, ColumnType::Int);
+        assert_eq!(rows.rows[0].columns[1], Some(CqlValue::Int(0x01020304)));
+        assert_eq!(rows.rows[1].columns[1], None);
+
+        rows.metadata.col_specs[1].typ = ColumnType::Blob;
+        rows.rows[0].columns[1] = Some(CqlValue::Blob(vec![1, 2, 3]));
+        assert!(matches!(
+            policy().decrypt_rows(&mut rows),
+            Err(ColumnEncryptionError::DecryptionFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn decrypts_raw_rows() {
+        let mut metadata = ResultMetadata::default();
+        metadata.col_specs = vec![spec("a", ColumnType::Int), spec("secret", ColumnType::Blob)];
+        let mut serialized = Vec::new();
+        // The first row has an encrypted value, the second one a null.
+        serialized.extend_from_slice(&[0, 0, 0, 4, 0, 0, 0, 1]);
+        serialized.extend_from_slice(&[0, 0, 0, 5, 0xee, 4, 3, 2, 1]);
+        serialized.extend_from_slice(&[0, 0, 0, 4, 0, 0, 0, 2]);
+        serialized.extend_from_slice(&[0xff, 0xff, 0xff, 0xff]);
+        let mut rows = RawRows {
+            metadata,
+            rows_count: 2,
+            raw_rows: serialized.into(),
+        };
+        policy().decrypt_raw_rows(&mut rows).unwrap();
+        assert_eq!(rows.metadata.col_specs[1].typ, ColumnType::Int);
+        let rows: Vec<Row> = rows
+            .rows_iter()
+            .map(|row| Row::deserialize(row.unwrap()).unwrap())
+            .collect();
+        assert_eq!(rows[0].columns[1], Some(CqlValue::Int(0x01020304)));
+        assert_eq!(rows[1].columns, vec![Some(CqlValue::Int(2)), None]);
+
+        let mut metadata = ResultMetadata::default();
+        metadata.col_specs = vec![spec("secret", ColumnType::Blob)];
+        let mut rows = RawRows {
+            metadata,
+            rows_count: 1,
+            raw_rows: vec![0, 0, 0, 3, 1, 2, 3].into(),
+        };
+        assert!(matches!(
+            policy().decrypt_raw_rows(&mut rows),
+            Err(ColumnEncryptionError::DecryptionFailed { .. })
+        ));
+    }
+}
@@ -38,6 +38,13 @@ impl<'frame> RowIterator<'frame> {
     pub fn rows_remaining(&self) -> usize {
         self.remaining
     }
+
+    /// Returns a [FrameSlice] that points to the serialized data of the rows
+    /// which were not returned yet.
+    #[inline]
+    pub fn remaining_slice(&self) -> FrameSlice<'frame> {
+        self.slice
+    }
 }
 
 impl<'frame> Iterator for RowIterator<'frame> {
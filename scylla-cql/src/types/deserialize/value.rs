@@ -985,6 +985,154 @@ where
     }
 }
 
+#[cfg(feature = "smallvec-1")]
+impl<'frame, A> DeserializeValue<'frame> for smallvec_1::SmallVec<A>
+where
+    A: smallvec_1::Array,
+    A::Item: DeserializeValue<'frame>,
+{
+    fn type_check(typ: &ColumnType) -> Result<(), TypeCheckError> {
+        // Like Vec, SmallVec can be deserialized from both Set and List.
+        ListlikeIterator::<'frame, A::Item>::type_check(typ)
+            .map_err(typck_error_replace_rust_name::<Self>)
+    }
+
+    fn deserialize(
+        typ: &'frame ColumnType,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Self, DeserializationError> {
+        ListlikeIterator::<'frame, A::Item>::deserialize(typ, v)
+            .and_then(|it| it.collect::<Result<_, DeserializationError>>())
+            .map_err(deser_error_replace_rust_name::<Self>)
+    }
+}
+
+// custom collections
+
+/// A container which can be deserialized from a CQL collection.
+///
+/// The collection is first deserialized into the [`Source`](FromCqlCollection::Source)
+/// iterator - [`ListlikeIterator`] for lists and sets or [`MapIterator`] for maps -
+/// and the container is built straight from the elements yielded by it,
+/// so no intermediate `Vec` or `HashMap` is created.
+///
+/// The [`impl_deserialize_value_via_from_cql_collection`](crate::impl_deserialize_value_via_from_cql_collection)
+/// macro implements [`DeserializeValue`] for containers implementing this trait.
+///
+/// # Example
+/// ```rust
+/// # use scylla_cql::types::deserialize::DeserializationError;
+/// use scylla_cql::impl_deserialize_value_via_from_cql_collection;
+/// use scylla_cql::types::deserialize::value::{FromCqlCollection, ListlikeIterator};
+///
+/// struct SortedInts(Vec<i32>);
+///
+/// impl<'frame> FromCqlCollection<'frame> for SortedInts {
+///     type Source = ListlikeIterator<'frame, i32>;
+///
+///     fn from_cql_collection(source: Self::Source) -> Result<Self, DeserializationError> {
+///         let mut ints = source.collect::<Result<Vec<_>, _>>()?;
+///         ints.sort_unstable();
+///         Ok(SortedInts(ints))
+///     }
+/// }
+/// impl_deserialize_value_via_from_cql_collection!(SortedInts);
+/// ```
+pub trait FromCqlCollection<'frame>: Sized {
+    /// Iterator over the serialized collection.
+    type Source: DeserializeValue<'frame>;
+
+    /// Checks that the column type matches what this container expects.
+    ///
+    /// By default, all types accepted by the source are accepted.
+    /// A container which can't preserve the order or duplicates of a list, like a set,
+    /// should accept only the matching collection type.
+    fn type_check(typ: &ColumnType) -> Result<(), TypeCheckError> {
+        <Self::Source as DeserializeValue<'frame>>::type_check(typ)
+    }
+
+    /// Builds the container from the deserialized collection.
+    fn from_cql_collection(source: Self::Source) -> Result<Self, DeserializationError>;
+}
+
+/// Type checks a [`FromCqlCollection`] container, as its [`DeserializeValue::type_check`] does.
+///
+/// See [`impl_deserialize_value_via_from_cql_collection`](crate::impl_deserialize_value_via_from_cql_collection),
+/// which generates a [`DeserializeValue`] implementation that uses this function.
+pub fn type_check_cql_collection<'frame, C>(typ: &ColumnType) -> Result<(), TypeCheckError>
+where
+    C: FromCqlCollection<'frame>,
+{
+    <C as FromCqlCollection<'frame>>::type_check(typ).map_err(typck_error_replace_rust_name::<C>)
+}
+
+/// Deserializes a [`FromCqlCollection`] container, as its [`DeserializeValue::deserialize`] does.
+///
+/// See [`impl_deserialize_value_via_from_cql_collection`](crate::impl_deserialize_value_via_from_cql_collection),
+/// which generates a [`DeserializeValue`] implementation that uses this function.
+pub fn deserialize_cql_collection<'frame, C>(
+    typ: &'frame ColumnType,
+    v: Option<FrameSlice<'frame>>,
+) -> Result<C, DeserializationError>
+where
+    C: FromCqlCollection<'frame>,
+{
+    <C::Source as DeserializeValue<'frame>>::deserialize(typ, v)
+        .and_then(C::from_cql_collection)
+        .map_err(deser_error_replace_rust_name::<C>)
+}
+
+/// Implements [`DeserializeValue`] for a type implementing [`FromCqlCollection`].
+///
+/// # Example
+///
+/// ```rust
+/// # use scylla_cql::types::deserialize::DeserializationError;
+/// use scylla_cql::impl_deserialize_value_via_from_cql_collection;
+/// use scylla_cql::types::deserialize::value::{DeserializeValue, FromCqlCollection, MapIterator};
+///
+/// // Generic types are also supported. You must specify the bounds if the
+/// // struct/enum contains any.
+/// struct Entries<K, V: Clone>(Vec<(K, V)>);
+///
+/// impl<'frame, K, V> FromCqlCollection<'frame> for Entries<K, V>
+/// where
+///     K: DeserializeValue<'frame>,
+///     V: DeserializeValue<'frame> + Clone,
+/// {
+///     type Source = MapIterator<'frame, K, V>;
+///
+///     fn from_cql_collection(source: Self::Source) -> Result<Self, DeserializationError> {
+///         source.collect::<Result<_, _>>().map(Entries)
+///     }
+/// }
+/// impl_deserialize_value_via_from_cql_collection!(Entries<K, V: Clone>);
+/// ```
+#[macro_export]
+macro_rules! impl_deserialize_value_via_from_cql_collection {
+    ($t:ident$(<$($targ:tt $(: $tbound:tt)?),*>)?) => {
+        impl<'frame, $($($targ $(: $tbound)?),*)?>
+            $crate::types::deserialize::value::DeserializeValue<'frame>
+        for $t$(<$($targ),*>)?
+        where
+            Self: $crate::types::deserialize::value::FromCqlCollection<'frame>,
+        {
+            fn type_check(
+                typ: &$crate::frame::response::result::ColumnType,
+            ) -> ::std::result::Result<(), $crate::types::deserialize::TypeCheckError> {
+                $crate::types::deserialize::value::type_check_cql_collection::<Self>(typ)
+            }
+
+            fn deserialize(
+                typ: &'frame $crate::frame::response::result::ColumnType,
+                v: ::std::option::Option<$crate::types::deserialize::FrameSlice<'frame>>,
+            ) -> ::std::result::Result<Self, $crate::types::deserialize::DeserializationError> {
+                $crate::types::deserialize::value::deserialize_cql_collection::<Self>(typ, v)
+            }
+        }
+    };
+}
+
 // tuples
 
 // Implements tuple deserialization.
@@ -971,6 +971,104 @@ fn test_udt_cross_rename_fields() {
     }
 }
 
+#[test]
+fn test_transparent_tuple() {
+    #[derive(
+        scylla_macros::DeserializeValue, scylla_macros::SerializeValue, PartialEq, Eq, Debug,
+    )]
+    #[scylla(crate = crate, transparent_tuple)]
+    struct Point {
+        x: i32,
+        #[scylla(skip)]
+        label: String,
+        y: Option<String>,
+    }
+
+    let typ = ColumnType::Tuple(vec![ColumnType::Int, ColumnType::Text]);
+
+    let mut tuple_contents = BytesMut::new();
+    append_bytes(&mut tuple_contents, &42i32.to_be_bytes());
+    append_null(&mut tuple_contents);
+    let tuple = make_bytes(&tuple_contents);
+
+    let point = deserialize::<Point>(&typ, &tuple).unwrap();
+    assert_eq!(
+        point,
+        Point {
+            x: 42,
+            label: String::new(),
+            y: None,
+        }
+    );
+
+    // ser/de identity
+    assert_ser_de_identity(
+        &typ,
+        &Point {
+            x: 7,
+            label: String::new(),
+            y: Some("seven".to_owned()),
+        },
+        &mut Bytes::new(),
+    );
+
+    // Not a tuple
+    {
+        let err = <Point as DeserializeValue>::type_check(&ColumnType::Int).unwrap_err();
+        let err = get_typeck_err_inner(err.0.as_ref());
+        assert_eq!(err.rust_name, std::any::type_name::<Point>());
+        assert_matches!(
+            err.kind,
+            BuiltinTypeCheckErrorKind::TupleError(TupleTypeCheckErrorKind::NotTuple)
+        );
+    }
+
+    // Wrong element count
+    {
+        let typ = ColumnType::Tuple(vec![ColumnType::Int]);
+        let err = <Point as DeserializeValue>::type_check(&typ).unwrap_err();
+        let err = get_typeck_err_inner(err.0.as_ref());
+        assert_matches!(
+            err.kind,
+            BuiltinTypeCheckErrorKind::TupleError(TupleTypeCheckErrorKind::WrongElementCount {
+                rust_type_el_count: 2,
+                cql_type_el_count: 1,
+            })
+        );
+    }
+
+    // Wrong element type
+    {
+        let typ = ColumnType::Tuple(vec![ColumnType::Int, ColumnType::Boolean]);
+        let err = <Point as DeserializeValue>::type_check(&typ).unwrap_err();
+        let err = get_typeck_err_inner(err.0.as_ref());
+        assert_matches!(
+            err.kind,
+            BuiltinTypeCheckErrorKind::TupleError(TupleTypeCheckErrorKind::FieldTypeCheckFailed {
+                position: 1,
+                ..
+            })
+        );
+    }
+
+    // Element deserialization failed
+    {
+        let mut tuple_contents = BytesMut::new();
+        append_bytes(&mut tuple_contents, &42i64.to_be_bytes());
+        append_null(&mut tuple_contents);
+        let tuple = make_bytes(&tuple_contents);
+
+        let err = deserialize::<Point>(&typ, &tuple).unwrap_err();
+        let err = get_deser_err(&err);
+        assert_matches!(
+            err.kind,
+            BuiltinDeserializationErrorKind::TupleError(
+                TupleDeserializationErrorKind::FieldDeserializationFailed { position: 0, .. }
+            )
+        );
+    }
+}
+
 #[test]
 fn test_custom_type_parser() {
     #[derive(Default, Debug, PartialEq, Eq)]
@@ -1003,6 +1101,74 @@ fn test_custom_type_parser() {
     assert_eq!(tup, SwappedPair("foo", 42));
 }
 
+#[test]
+fn test_custom_collections() {
+    use crate::types::serialize::value::serialize_mapping;
+    use crate::types::serialize::writers::WrittenCellProof;
+    use crate::types::serialize::SerializationError;
+
+    use super::FromCqlCollection;
+
+    // Map entries, kept in the order in which they were received.
+    #[derive(Debug, PartialEq, Eq)]
+    struct Entries<K, V>(Vec<(K, V)>);
+
+    impl<'frame, K, V> FromCqlCollection<'frame> for Entries<K, V>
+    where
+        K: DeserializeValue<'frame>,
+        V: DeserializeValue<'frame>,
+    {
+        type Source = MapIterator<'frame, K, V>;
+
+        fn from_cql_collection(source: Self::Source) -> Result<Self, DeserializationError> {
+            source.collect::<Result<_, _>>().map(Self)
+        }
+    }
+    crate::impl_deserialize_value_via_from_cql_collection!(Entries<K, V>);
+
+    impl<K: SerializeValue, V: SerializeValue> SerializeValue for Entries<K, V> {
+        fn serialize<'b>(
+            &self,
+            typ: &ColumnType,
+            writer: CellWriter<'b>,
+        ) -> Result<WrittenCellProof<'b>, SerializationError> {
+            serialize_mapping(
+                std::any::type_name::<Self>(),
+                self.0.len(),
+                self.0.iter().map(|(k, v)| (k, v)),
+                typ,
+                writer,
+            )
+        }
+    }
+
+    let map_typ = ColumnType::Map(Box::new(ColumnType::Int), Box::new(ColumnType::Ascii));
+    let list_typ = ColumnType::List(Box::new(map_typ.clone()));
+
+    let entries = Entries(vec![(3, "fox"), (1, "quick"), (2, "brown")]);
+    assert_ser_de_identity(&map_typ, &entries, &mut Bytes::new());
+
+    // Nested in a list
+    let nested = vec![entries, Entries(vec![]), Entries(vec![(-1, "qwik")])];
+    assert_ser_de_identity(&list_typ, &nested, &mut Bytes::new());
+
+    // Wrong type
+    let err = <Entries<i32, &str> as DeserializeValue>::type_check(&list_typ).unwrap_err();
+    let err = get_typeck_err_inner(err.0.as_ref());
+    assert_eq!(err.rust_name, std::any::type_name::<Entries<i32, &str>>());
+    assert_matches!(
+        err.kind,
+        BuiltinTypeCheckErrorKind::MapError(MapTypeCheckErrorKind::NotMap)
+    );
+
+    #[cfg(feature = "smallvec-1")]
+    {
+        let set_typ = ColumnType::Set(Box::new(ColumnType::Int));
+        let small: smallvec_1::SmallVec<[i32; 4]> = smallvec_1::smallvec![1, 2, 3];
+        assert_ser_de_identity(&set_typ, &small, &mut Bytes::new());
+    }
+}
+
 fn deserialize<'frame, T>(
     typ: &'frame ColumnType,
     bytes: &'frame Bytes,
@@ -1,2 +1,3 @@
+pub mod column_encryption;
 pub mod deserialize;
 pub mod serialize;
@@ -144,7 +144,13 @@ where
     #[inline]
     fn serialize_next(&mut self, writer: &mut RowWriter) -> Option<Result<(), SerializationError>> {
         let ctx = self.contexts.next()?;
-        self.batch_values_iterator.serialize_next(&ctx, writer)
+        let batch_values_iterator = &mut self.batch_values_iterator;
+        ctx.serialize_row(writer, |ctx, writer| {
+            batch_values_iterator
+                .serialize_next(ctx, writer)
+                .transpose()
+        })
+        .transpose()
     }
 
     fn is_empty_next(&mut self) -> Option<bool> {
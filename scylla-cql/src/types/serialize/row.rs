@@ -16,6 +16,7 @@ use crate::frame::types;
 use crate::frame::value::SerializeValuesError;
 use crate::frame::value::{LegacySerializedValues, ValueList};
 use crate::frame::{response::result::ColumnSpec, types::RawValue};
+use crate::types::column_encryption::BindMarkersEncryption;
 
 use super::value::SerializeValue;
 use super::{CellWriter, RowWriter, SerializationError};
@@ -23,14 +24,36 @@ use super::{CellWriter, RowWriter, SerializationError};
 /// Contains information needed to serialize a row.
 pub struct RowSerializationContext<'a> {
     pub(crate) columns: &'a [ColumnSpec],
+    pub(crate) encryption: Option<&'a BindMarkersEncryption>,
 }
 
 impl<'a> RowSerializationContext<'a> {
     /// Creates the serialization context from prepared statement metadata.
     #[inline]
     pub fn from_prepared(prepared: &'a PreparedMetadata) -> Self {
+        Self::from_specs(prepared.col_specs.as_slice())
+    }
+
+    /// Creates the serialization context for values of the given columns,
+    /// without column encryption.
+    #[inline]
+    pub(crate) const fn from_specs(columns: &'a [ColumnSpec]) -> Self {
+        Self {
+            columns,
+            encryption: None,
+        }
+    }
+
+    /// Creates the serialization context for a prepared statement whose values
+    /// are subject to client-side encryption.
+    ///
+    /// Values of encrypted columns are type checked against the cleartext types,
+    /// and are encrypted by [`Self::serialize_row`].
+    #[inline]
+    pub fn with_column_encryption(encryption: &'a BindMarkersEncryption) -> Self {
         Self {
-            columns: prepared.col_specs.as_slice(),
+            columns: encryption.cleartext_columns(),
+            encryption: Some(encryption),
         }
     }
 
@@ -38,7 +61,26 @@ impl<'a> RowSerializationContext<'a> {
     /// with no bind markers.
     #[inline]
     pub const fn empty() -> Self {
-        Self { columns: &[] }
+        Self::from_specs(&[])
+    }
+
+    /// Serializes a row with the given closure, and then encrypts the values
+    /// of encrypted columns, if the context has column encryption.
+    ///
+    /// Row serialization should go through this method rather than calling
+    /// [`SerializeRow::serialize`] directly, so that no encrypted column
+    /// is sent in cleartext.
+    pub fn serialize_row<R>(
+        &self,
+        writer: &mut RowWriter,
+        f: impl FnOnce(&Self, &mut RowWriter) -> Result<R, SerializationError>,
+    ) -> Result<R, SerializationError> {
+        let start = writer.buf_len();
+        let ret = f(self, writer)?;
+        if let Some(encryption) = self.encryption {
+            encryption.encrypt_values(writer.buf_mut(), start)?;
+        }
+        Ok(ret)
     }
 
     /// Returns column/bind marker specifications for given query.
@@ -717,7 +759,10 @@ impl SerializedValues {
         ctx: &RowSerializationContext,
         row: &T,
     ) -> Result<Self, SerializationError> {
-        Self::from_closure(|writer| row.serialize(ctx, writer)).map(|(sr, _)| sr)
+        Self::from_closure(|writer| {
+            ctx.serialize_row(writer, |ctx, writer| row.serialize(ctx, writer))
+        })
+        .map(|(sr, _)| sr)
     }
 
     /// Constructs `SerializedValues` via given closure.
@@ -889,14 +934,13 @@ mod tests {
 
         let mut new_data = Vec::new();
         let mut new_data_writer = RowWriter::new(&mut new_data);
-        let ctx = RowSerializationContext {
-            columns: &[
-                col_spec("a", ColumnType::Int),
-                col_spec("b", ColumnType::Text),
-                col_spec("c", ColumnType::BigInt),
-                col_spec("b", ColumnType::Ascii),
-            ],
-        };
+        let columns = [
+            col_spec("a", ColumnType::Int),
+            col_spec("b", ColumnType::Text),
+            col_spec("c", ColumnType::BigInt),
+            col_spec("b", ColumnType::Ascii),
+        ];
+        let ctx = RowSerializationContext::from_specs(&columns);
         <_ as SerializeRow>::serialize(&row, &ctx, &mut new_data_writer).unwrap();
         assert_eq!(new_data_writer.value_count(), 4);
 
@@ -926,14 +970,13 @@ mod tests {
 
         let mut unsorted_row_data = Vec::new();
         let mut unsorted_row_data_writer = RowWriter::new(&mut unsorted_row_data);
-        let ctx = RowSerializationContext {
-            columns: &[
-                col_spec("a", ColumnType::Int),
-                col_spec("b", ColumnType::Text),
-                col_spec("c", ColumnType::BigInt),
-                col_spec("d", ColumnType::Ascii),
-            ],
-        };
+        let columns = [
+            col_spec("a", ColumnType::Int),
+            col_spec("b", ColumnType::Text),
+            col_spec("c", ColumnType::BigInt),
+            col_spec("d", ColumnType::Ascii),
+        ];
+        let ctx = RowSerializationContext::from_specs(&columns);
         <_ as SerializeRow>::serialize(&unsorted_row, &ctx, &mut unsorted_row_data_writer).unwrap();
         assert_eq!(unsorted_row_data_writer.value_count(), 4);
 
@@ -949,14 +992,13 @@ mod tests {
             None::<i64>,
             MaybeUnset::Unset::<String>,
         );
-        let ctx = RowSerializationContext {
-            columns: &[
-                col_spec("a", ColumnType::Int),
-                col_spec("b", ColumnType::Text),
-                col_spec("c", ColumnType::BigInt),
-                col_spec("d", ColumnType::Ascii),
-            ],
-        };
+        let columns = [
+            col_spec("a", ColumnType::Int),
+            col_spec("b", ColumnType::Text),
+            col_spec("c", ColumnType::BigInt),
+            col_spec("d", ColumnType::Ascii),
+        ];
+        let ctx = RowSerializationContext::from_specs(&columns);
 
         let mut typed_data = Vec::new();
         let mut typed_data_writer = RowWriter::new(&mut typed_data);
@@ -975,7 +1017,7 @@ mod tests {
     }
 
     fn do_serialize<T: SerializeRow>(t: T, columns: &[ColumnSpec]) -> Vec<u8> {
-        let ctx = RowSerializationContext { columns };
+        let ctx = RowSerializationContext::from_specs(columns);
         let mut ret = Vec::new();
         let mut builder = RowWriter::new(&mut ret);
         t.serialize(&ctx, &mut builder).unwrap();
@@ -983,7 +1025,7 @@ mod tests {
     }
 
     fn do_serialize_err<T: SerializeRow>(t: T, columns: &[ColumnSpec]) -> SerializationError {
-        let ctx = RowSerializationContext { columns };
+        let ctx = RowSerializationContext::from_specs(columns);
         let mut ret = Vec::new();
         let mut builder = RowWriter::new(&mut ret);
         t.serialize(&ctx, &mut builder).unwrap_err()
@@ -1209,9 +1251,7 @@ mod tests {
             // Missing column c
         ];
 
-        let ctx = RowSerializationContext {
-            columns: &spec_without_c,
-        };
+        let ctx = RowSerializationContext::from_specs(&spec_without_c);
         let err = <_ as SerializeRow>::serialize(&row, &ctx, &mut row_writer).unwrap_err();
         let err = err.0.downcast_ref::<BuiltinTypeCheckError>().unwrap();
         assert_matches!(
@@ -1227,9 +1267,7 @@ mod tests {
             col("d", ColumnType::Counter),
         ];
 
-        let ctx = RowSerializationContext {
-            columns: &spec_duplicate_column,
-        };
+        let ctx = RowSerializationContext::from_specs(&spec_duplicate_column);
         let err = <_ as SerializeRow>::serialize(&row, &ctx, &mut row_writer).unwrap_err();
         let err = err.0.downcast_ref::<BuiltinTypeCheckError>().unwrap();
         assert_matches!(err.kind, BuiltinTypeCheckErrorKind::NoColumnWithName { .. });
@@ -1240,9 +1278,7 @@ mod tests {
             col("c", ColumnType::TinyInt), // Wrong type
         ];
 
-        let ctx = RowSerializationContext {
-            columns: &spec_wrong_type,
-        };
+        let ctx = RowSerializationContext::from_specs(&spec_wrong_type);
         let err = <_ as SerializeRow>::serialize(&row, &ctx, &mut row_writer).unwrap_err();
         let err = err.0.downcast_ref::<BuiltinSerializationError>().unwrap();
         assert_matches!(
@@ -1319,7 +1355,7 @@ mod tests {
             col("c", ColumnType::List(Box::new(ColumnType::BigInt))),
             col("b", ColumnType::Int),
         ];
-        let ctx = RowSerializationContext { columns: &spec };
+        let ctx = RowSerializationContext::from_specs(&spec);
         let err = <_ as SerializeRow>::serialize(&row, &ctx, &mut writer).unwrap_err();
         let err = err.0.downcast_ref::<BuiltinTypeCheckError>().unwrap();
         assert_matches!(
@@ -1333,9 +1369,7 @@ mod tests {
             // Missing column c
         ];
 
-        let ctx = RowSerializationContext {
-            columns: &spec_without_c,
-        };
+        let ctx = RowSerializationContext::from_specs(&spec_without_c);
         let err = <_ as SerializeRow>::serialize(&row, &ctx, &mut writer).unwrap_err();
         let err = err.0.downcast_ref::<BuiltinTypeCheckError>().unwrap();
         assert_matches!(
@@ -1351,9 +1385,7 @@ mod tests {
             col("d", ColumnType::Counter),
         ];
 
-        let ctx = RowSerializationContext {
-            columns: &spec_duplicate_column,
-        };
+        let ctx = RowSerializationContext::from_specs(&spec_duplicate_column);
         let err = <_ as SerializeRow>::serialize(&row, &ctx, &mut writer).unwrap_err();
         let err = err.0.downcast_ref::<BuiltinTypeCheckError>().unwrap();
         assert_matches!(err.kind, BuiltinTypeCheckErrorKind::NoColumnWithName { .. });
@@ -1364,9 +1396,7 @@ mod tests {
             col("c", ColumnType::TinyInt), // Wrong type
         ];
 
-        let ctx = RowSerializationContext {
-            columns: &spec_wrong_type,
-        };
+        let ctx = RowSerializationContext::from_specs(&spec_wrong_type);
         let err = <_ as SerializeRow>::serialize(&row, &ctx, &mut writer).unwrap_err();
         let err = err.0.downcast_ref::<BuiltinSerializationError>().unwrap();
         assert_matches!(
@@ -471,6 +471,26 @@ impl<'a, T: SerializeValue + 'a> SerializeValue for &'a [T] {
         )
     }
 }
+#[cfg(feature = "smallvec-1")]
+impl<A> SerializeValue for smallvec_1::SmallVec<A>
+where
+    A: smallvec_1::Array,
+    A::Item: SerializeValue,
+{
+    fn serialize<'b>(
+        &self,
+        typ: &ColumnType,
+        writer: CellWriter<'b>,
+    ) -> Result<WrittenCellProof<'b>, SerializationError> {
+        serialize_sequence(
+            std::any::type_name::<Self>(),
+            self.len(),
+            self.iter(),
+            typ,
+            writer,
+        )
+    }
+}
 impl SerializeValue for CqlValue {
     fn serialize<'b>(
         &self,
@@ -787,7 +807,12 @@ impl_tuples!(
     16
 );
 
-fn serialize_sequence<'t, 'b, T: SerializeValue + 't>(
+/// Serializes the elements of a list or a set.
+///
+/// This can be used to implement [`SerializeValue`] for custom containers.
+/// `rust_name` is the name of the container type used in errors,
+/// and `len` must be equal to the number of elements yielded by `iter`.
+pub fn serialize_sequence<'t, 'b, T: SerializeValue + 't>(
     rust_name: &'static str,
     len: usize,
     iter: impl Iterator<Item = &'t T>,
@@ -831,7 +856,12 @@ fn serialize_sequence<'t, 'b, T: SerializeValue + 't>(
         .map_err(|_| mk_ser_err_named(rust_name, typ, BuiltinSerializationErrorKind::SizeOverflow))
 }
 
-fn serialize_mapping<'t, 'b, K: SerializeValue + 't, V: SerializeValue + 't>(
+/// Serializes the entries of a map.
+///
+/// This can be used to implement [`SerializeValue`] for custom containers.
+/// `rust_name` is the name of the container type used in errors,
+/// and `len` must be equal to the number of entries yielded by `iter`.
+pub fn serialize_mapping<'t, 'b, K: SerializeValue + 't, V: SerializeValue + 't>(
     rust_name: &'static str,
     len: usize,
     iter: impl Iterator<Item = (&'t K, &'t V)>,
@@ -2271,6 +2301,60 @@ mod tests {
         );
     }
 
+    #[derive(SerializeValue, Default)]
+    #[scylla(crate = crate, transparent_tuple)]
+    struct TestTransparentTuple {
+        a: String,
+        #[scylla(skip)]
+        _skipped: i32,
+        b: i32,
+    }
+
+    #[test]
+    fn test_transparent_tuple_serialization() {
+        let typ = ColumnType::Tuple(vec![ColumnType::Text, ColumnType::Int]);
+        let value = TestTransparentTuple {
+            a: "Ala ma kota".to_owned(),
+            _skipped: 123,
+            b: 42,
+        };
+
+        let reference = do_serialize(("Ala ma kota", 42i32), &typ);
+        let tuple = do_serialize(value, &typ);
+        assert_eq!(reference, tuple);
+    }
+
+    #[test]
+    fn test_transparent_tuple_serialization_failing_type_check() {
+        let err = do_serialize_err(TestTransparentTuple::default(), &ColumnType::Text);
+        let err = err.0.downcast_ref::<BuiltinTypeCheckError>().unwrap();
+        assert_matches!(
+            err.kind,
+            BuiltinTypeCheckErrorKind::TupleError(TupleTypeCheckErrorKind::NotTuple)
+        );
+
+        let typ_too_short = ColumnType::Tuple(vec![ColumnType::Text]);
+        let err = do_serialize_err(TestTransparentTuple::default(), &typ_too_short);
+        let err = err.0.downcast_ref::<BuiltinTypeCheckError>().unwrap();
+        assert_matches!(
+            err.kind,
+            BuiltinTypeCheckErrorKind::TupleError(TupleTypeCheckErrorKind::WrongElementCount {
+                rust_type_el_count: 2,
+                cql_type_el_count: 1,
+            })
+        );
+
+        let typ_wrong_type = ColumnType::Tuple(vec![ColumnType::Text, ColumnType::TinyInt]);
+        let err = do_serialize_err(TestTransparentTuple::default(), &typ_wrong_type);
+        let err = err.0.downcast_ref::<BuiltinSerializationError>().unwrap();
+        assert_matches!(
+            err.kind,
+            BuiltinSerializationErrorKind::TupleError(
+                TupleSerializationErrorKind::ElementSerializationFailed { index: 1, .. }
+            )
+        );
+    }
+
     #[derive(SerializeValue)]
     #[scylla(crate = crate)]
     struct TestUdtWithGenerics<'a, T: SerializeValue> {
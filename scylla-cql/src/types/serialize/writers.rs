@@ -49,6 +49,18 @@ impl<'buf> RowWriter<'buf> {
         self.value_count += sv.element_count() as usize;
         self.buf.extend_from_slice(sv.get_contents())
     }
+
+    #[inline]
+    pub(crate) fn buf_len(&self) -> usize {
+        self.buf.len()
+    }
+
+    // Gives access to the values written so far, e.g. to encrypt them.
+    // The number of values must not change.
+    #[inline]
+    pub(crate) fn buf_mut(&mut self) -> &mut Vec<u8> {
+        self.buf
+    }
 }
 
 /// Represents a handle to a CQL value that needs to be written into.
@@ -33,6 +33,11 @@ struct StructAttrs {
     // they will be ignored. With true, an error will be raised.
     #[darling(default)]
     forbid_excess_udt_fields: bool,
+
+    // If true, then the struct is deserialized from a CQL tuple instead of a UDT.
+    // Tuple elements are matched to struct fields based solely on the order.
+    #[darling(default)]
+    transparent_tuple: bool,
 }
 
 impl DeserializeCommonStructAttrs for StructAttrs {
@@ -110,6 +115,35 @@ pub(crate) fn deserialize_value_derive(
 fn validate_attrs(attrs: &StructAttrs, fields: &[Field]) -> Result<(), darling::Error> {
     let mut errors = darling::Error::accumulator();
 
+    if attrs.transparent_tuple {
+        // Tuple elements are always matched by position, have no names
+        // and all of them must be present
+        for (attr, is_set) in [
+            ("enforce_order", attrs.enforce_order),
+            ("skip_name_checks", attrs.skip_name_checks),
+            ("forbid_excess_udt_fields", attrs.forbid_excess_udt_fields),
+        ] {
+            if is_set {
+                let error = darling::Error::custom(format!(
+                    "attribute <{attr}> is not allowed with <transparent_tuple>."
+                ));
+                errors.push(error);
+            }
+        }
+
+        for field in fields {
+            if field.rename.is_some() || field.default_when_missing {
+                let err = darling::Error::custom(
+                    "<rename> and <allow_missing> annotations don't make sense with <transparent_tuple> attribute",
+                )
+                .with_span(&field.ident);
+                errors.push(err);
+            }
+        }
+
+        return errors.finish();
+    }
+
     if attrs.skip_name_checks {
         // Skipping name checks is only available in enforce_order mode
         if !attrs.enforce_order {
@@ -207,7 +241,9 @@ impl StructDesc {
     }
 
     fn generate_type_check_method(&self) -> syn::ImplItemFn {
-        if self.attrs.enforce_order {
+        if self.attrs.transparent_tuple {
+            TypeCheckTupleGenerator(self).generate()
+        } else if self.attrs.enforce_order {
             TypeCheckAssumeOrderGenerator(self).generate()
         } else {
             TypeCheckUnorderedGenerator(self).generate()
@@ -215,7 +251,9 @@ impl StructDesc {
     }
 
     fn generate_deserialize_method(&self) -> syn::ImplItemFn {
-        if self.attrs.enforce_order {
+        if self.attrs.transparent_tuple {
+            DeserializeTupleGenerator(self).generate()
+        } else if self.attrs.enforce_order {
             DeserializeAssumeOrderGenerator(self).generate()
         } else {
             DeserializeUnorderedGenerator(self).generate()
@@ -896,3 +934,152 @@ impl<'sd> DeserializeUnorderedGenerator<'sd> {
         }
     }
 }
+
+struct TypeCheckTupleGenerator<'sd>(&'sd StructDesc);
+
+impl<'sd> TypeCheckTupleGenerator<'sd> {
+    // Generates the type_check method for when transparent_tuple == true.
+    fn generate(&self) -> syn::ImplItemFn {
+        // The generated method will:
+        // - Check that the type is a tuple with as many elements as there are non-skipped fields
+        // - Check that the type of each element matches the type of the corresponding field
+
+        let macro_internal = self.0.struct_attrs().macro_internal_path();
+        let constraint_lifetime = self.0.constraint_lifetime();
+
+        let nonskipped_fields = self.0.fields().iter().filter(|f| !f.skip);
+        let element_count = nonskipped_fields.clone().count();
+        let element_validations = nonskipped_fields.enumerate().map(|(position, field)| {
+            let rust_field_typ = field.deserialize_target();
+            let validation: syn::Expr = parse_quote! {
+                <#rust_field_typ as #macro_internal::DeserializeValue<#constraint_lifetime>>::type_check(&element_types[#position])
+                    .map_err(|err| #macro_internal::mk_value_typck_err::<Self>(
+                        typ,
+                        #macro_internal::DeserTupleTypeCheckErrorKind::FieldTypeCheckFailed {
+                            position: #position,
+                            err,
+                        }
+                    ))?
+            };
+            validation
+        });
+
+        parse_quote! {
+            fn type_check(
+                typ: &#macro_internal::ColumnType,
+            ) -> ::std::result::Result<(), #macro_internal::TypeCheckError> {
+                let element_types = match typ {
+                    #macro_internal::ColumnType::Tuple(element_types) => element_types,
+                    _ => return ::std::result::Result::Err(
+                        #macro_internal::mk_value_typck_err::<Self>(
+                            typ,
+                            #macro_internal::DeserTupleTypeCheckErrorKind::NotTuple,
+                        )
+                    ),
+                };
+                if element_types.len() != #element_count {
+                    return ::std::result::Result::Err(
+                        #macro_internal::mk_value_typck_err::<Self>(
+                            typ,
+                            #macro_internal::DeserTupleTypeCheckErrorKind::WrongElementCount {
+                                rust_type_el_count: #element_count,
+                                cql_type_el_count: element_types.len(),
+                            }
+                        )
+                    );
+                }
+
+                #(#element_validations;)*
+
+                // All is good!
+                ::std::result::Result::Ok(())
+            }
+        }
+    }
+}
+
+struct DeserializeTupleGenerator<'sd>(&'sd StructDesc);
+
+impl<'sd> DeserializeTupleGenerator<'sd> {
+    fn generate_finalize_field(&self, position: usize, field: &Field) -> syn::Expr {
+        let macro_internal = self.0.struct_attrs().macro_internal_path();
+        let deserializer = field.deserialize_target();
+        let constraint_lifetime = self.0.constraint_lifetime();
+
+        let deserialize: syn::Expr = parse_quote! {
+            <#deserializer as #macro_internal::DeserializeValue<#constraint_lifetime>>::deserialize(&element_types[#position], value)
+        };
+        let maybe_default_deserialize: syn::Expr = if field.default_when_null {
+            parse_quote! {
+                if value.is_none() {
+                    ::std::result::Result::Ok(::std::default::Default::default())
+                } else {
+                    #deserialize
+                }
+            }
+        } else {
+            deserialize
+        };
+
+        parse_quote! {
+            #macro_internal::FrameSlice::read_cql_bytes(&mut v)
+                .map_err(#macro_internal::DeserializationError::new)
+                .and_then(|value| #maybe_default_deserialize)
+                .map_err(|err| #macro_internal::mk_value_deser_err::<Self>(
+                    typ,
+                    #macro_internal::TupleDeserializationErrorKind::FieldDeserializationFailed {
+                        position: #position,
+                        err,
+                    }
+                ))?
+        }
+    }
+
+    fn generate(&self) -> syn::ImplItemFn {
+        // We can assume that type_check was called.
+
+        let macro_internal = self.0.struct_attrs().macro_internal_path();
+        let constraint_lifetime = self.0.constraint_lifetime();
+        let fields = self.0.fields();
+
+        let field_idents = fields.iter().map(|f| f.ident.as_ref().unwrap());
+        let mut position = 0;
+        let field_finalizers = fields
+            .iter()
+            .map(|f| {
+                if f.skip {
+                    // Skipped fields are initialized with Default::default()
+                    parse_quote! {
+                        ::std::default::Default::default()
+                    }
+                } else {
+                    position += 1;
+                    self.generate_finalize_field(position - 1, f)
+                }
+            })
+            .collect::<Vec<syn::Expr>>();
+
+        parse_quote! {
+            fn deserialize(
+                typ: &#constraint_lifetime #macro_internal::ColumnType,
+                v: ::std::option::Option<#macro_internal::FrameSlice<#constraint_lifetime>>,
+            ) -> ::std::result::Result<Self, #macro_internal::DeserializationError> {
+                let element_types = match typ {
+                    #macro_internal::ColumnType::Tuple(element_types) => element_types,
+                    _ => ::std::unreachable!("Type check should have prevented this scenario!"),
+                };
+                #[allow(unused_mut)]
+                let mut v = v.ok_or_else(|| #macro_internal::mk_value_deser_err::<Self>(
+                    typ,
+                    #macro_internal::BuiltinTypeDeserializationErrorKind::ExpectedNonNull,
+                ))?;
+
+                // Fields are initialized in the order of declaration,
+                // which is the order of elements in the serialized tuple.
+                ::std::result::Result::Ok(Self {
+                    #(#field_idents: #field_finalizers,)*
+                })
+            }
+        }
+    }
+}
@@ -5,6 +5,7 @@ mod from_row;
 mod from_user_type;
 mod into_user_type;
 mod parser;
+mod prepared;
 mod value_list;
 
 mod serialize;
@@ -84,3 +85,14 @@ pub fn deserialize_value_derive(tokens_input: TokenStream) -> TokenStream {
         Err(err) => err.into_compile_error().into(),
     }
 }
+
+/// Documentation for this macro can only be found
+/// in `scylla` crate - not in scylla-macros nor in scylla-cql.
+/// This is because of rustdocs limitations that are hard to explain here.
+#[proc_macro_attribute]
+pub fn prepared(args: TokenStream, item: TokenStream) -> TokenStream {
+    match prepared::prepared_attribute(args, item) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.into_compile_error().into(),
+    }
+}
@@ -62,7 +62,7 @@ pub(crate) fn get_path(input: &DeriveInput) -> Result<syn::Path, syn::Error> {
                     lit: Lit::Str(lit), ..
                 }) = &name_value.value
                 {
-                    let path = syn::Ident::new(&lit.value(), lit.span());
+                    let path: syn::Path = lit.parse()?;
                     if this_path.is_none() {
                         this_path = Some(syn::parse_quote!(#path::_macro_internal));
                     } else {
@@ -0,0 +1,1003 @@
+//! Parsing of schema dumps and of the statements of typed prepared statements.
+//!
+//! Only the subset of CQL needed to find the types of bind markers and selected columns
+//! is understood. Anything else is reported as an error, so that a statement is never
+//! given wrong types silently.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum NativeType {
+    Ascii,
+    BigInt,
+    Blob,
+    Boolean,
+    Counter,
+    Date,
+    Decimal,
+    Double,
+    Duration,
+    Float,
+    Inet,
+    Int,
+    SmallInt,
+    Text,
+    Time,
+    Timestamp,
+    Timeuuid,
+    TinyInt,
+    Uuid,
+    Varint,
+}
+
+impl NativeType {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "ascii" => Self::Ascii,
+            "bigint" => Self::BigInt,
+            "blob" => Self::Blob,
+            "boolean" => Self::Boolean,
+            "counter" => Self::Counter,
+            "date" => Self::Date,
+            "decimal" => Self::Decimal,
+            "double" => Self::Double,
+            "duration" => Self::Duration,
+            "float" => Self::Float,
+            "inet" => Self::Inet,
+            "int" => Self::Int,
+            "smallint" => Self::SmallInt,
+            "text" | "varchar" => Self::Text,
+            "time" => Self::Time,
+            "timestamp" => Self::Timestamp,
+            "timeuuid" => Self::Timeuuid,
+            "tinyint" => Self::TinyInt,
+            "uuid" => Self::Uuid,
+            "varint" => Self::Varint,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum CqlType {
+    Native(NativeType),
+    List(Box<CqlType>),
+    Set(Box<CqlType>),
+    Map(Box<CqlType>, Box<CqlType>),
+    Tuple(Vec<CqlType>),
+}
+
+impl CqlType {
+    // Whether the Rust type of the values can be a key of a `HashMap`.
+    fn is_hashable(&self) -> bool {
+        match self {
+            CqlType::Native(native) => !matches!(
+                native,
+                NativeType::Counter
+                    | NativeType::Date
+                    | NativeType::Decimal
+                    | NativeType::Double
+                    | NativeType::Duration
+                    | NativeType::Float
+                    | NativeType::Time
+                    | NativeType::Timestamp
+            ),
+            CqlType::List(element) | CqlType::Set(element) => element.is_hashable(),
+            CqlType::Map(_, _) => false,
+            CqlType::Tuple(elements) => elements.iter().all(CqlType::is_hashable),
+        }
+    }
+
+    // Maps are represented as `HashMap`s, so their keys must be hashable.
+    fn check_map_keys(&self) -> Result<(), String> {
+        match self {
+            CqlType::Native(_) => Ok(()),
+            CqlType::List(element) | CqlType::Set(element) => element.check_map_keys(),
+            CqlType::Map(key, value) => {
+                if !key.is_hashable() {
+                    return Err(
+                        "maps with keys of type counter, date, decimal, double, duration, float, \
+                         time, timestamp or map, possibly nested, are not supported"
+                            .to_string(),
+                    );
+                }
+                key.check_map_keys()?;
+                value.check_map_keys()
+            }
+            CqlType::Tuple(elements) => elements.iter().try_for_each(CqlType::check_map_keys),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ColumnKind {
+    PartitionKey,
+    Clustering,
+    Static,
+    Regular,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Column {
+    pub(crate) name: String,
+    pub(crate) typ: CqlType,
+    pub(crate) kind: ColumnKind,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Table {
+    pub(crate) keyspace: Option<String>,
+    pub(crate) name: String,
+    pub(crate) columns: Vec<Column>,
+}
+
+impl Table {
+    // Whether the column can be null in a row of the table. Clustering columns are null
+    // in the rows of partitions which only have static columns set.
+    fn is_nullable_in_rows(&self, column: &Column) -> bool {
+        match column.kind {
+            ColumnKind::PartitionKey => false,
+            ColumnKind::Clustering => self
+                .columns
+                .iter()
+                .any(|column| column.kind == ColumnKind::Static),
+            ColumnKind::Static | ColumnKind::Regular => true,
+        }
+    }
+
+    fn column(&self, name: &str) -> Result<&Column, String> {
+        self.columns
+            .iter()
+            .find(|column| column.name == name)
+            .ok_or_else(|| format!("table {} has no column {}", self.name, name))
+    }
+}
+
+/// A bind marker of a statement, with the type of the values bound to it.
+#[derive(Debug, Clone)]
+pub(crate) struct BindMarker {
+    pub(crate) typ: CqlType,
+    /// The marker sets the value of a column which can be null.
+    pub(crate) nullable: bool,
+}
+
+/// A column of the result of a `SELECT`.
+#[derive(Debug, Clone)]
+pub(crate) struct ResultColumn {
+    pub(crate) name: String,
+    pub(crate) typ: CqlType,
+    pub(crate) nullable: bool,
+}
+
+#[derive(Debug)]
+pub(crate) struct Statement {
+    pub(crate) bind_markers: Vec<BindMarker>,
+    /// `None` if the statement is not a `SELECT`.
+    pub(crate) result_columns: Option<Vec<ResultColumn>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    // Unquoted identifiers and keywords are lowercased
+    Ident(String),
+    QuotedIdent(String),
+    Literal,
+    Symbol(&'static str),
+}
+
+impl Token {
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Token::Ident(ident) if ident == keyword)
+    }
+
+    fn is_symbol(&self, symbol: &str) -> bool {
+        matches!(self, Token::Symbol(s) if *s == symbol)
+    }
+
+    fn ident(&self) -> Option<&str> {
+        match self {
+            Token::Ident(ident) | Token::QuotedIdent(ident) => Some(ident),
+            _ => None,
+        }
+    }
+}
+
+const SYMBOLS: &[&str] = &[
+    "<=", ">=", "!=", "(", ")", "<", ">", ",", ".", ";", "=", "?", "[", "]", "{", "}", "+", "-",
+    "*", ":",
+];
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if rest.starts_with("--") || rest.starts_with("//") {
+            rest = &rest[rest.find('\n').unwrap_or(rest.len())..];
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            let end = comment.find("*/").ok_or("unterminated comment")?;
+            rest = &comment[end + 2..];
+        } else if c == '\'' || c == '"' {
+            let mut value = String::new();
+            let mut chars = rest.char_indices().skip(1);
+            let end = loop {
+                match chars.next() {
+                    Some((i, ch)) if ch == c => {
+                        // A doubled quote is an escaped quote
+                        if rest[i + 1..].starts_with(c) {
+                            value.push(c);
+                            chars.next();
+                        } else {
+                            break i + 1;
+                        }
+                    }
+                    Some((_, ch)) => value.push(ch),
+                    None => return Err("unterminated quoted string".to_string()),
+                }
+            };
+            tokens.push(if c == '"' {
+                Token::QuotedIdent(value)
+            } else {
+                Token::Literal
+            });
+            rest = &rest[end..];
+        } else if let Some(body) = rest.strip_prefix("$$") {
+            let end = body.find("$$").ok_or("unterminated $$ string")?;
+            tokens.push(Token::Literal);
+            rest = &body[end + 2..];
+        } else if c.is_ascii_alphanumeric() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..len];
+            tokens.push(if c.is_ascii_digit() {
+                Token::Literal
+            } else {
+                Token::Ident(word.to_ascii_lowercase())
+            });
+            rest = &rest[len..];
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| rest.starts_with(**symbol))
+                .ok_or_else(|| format!("unexpected character {:?}", c))?;
+            tokens.push(Token::Symbol(symbol));
+            rest = &rest[symbol.len()..];
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(tokens: &'a [Token]) -> Self {
+        Self { tokens, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let matches = matches!(self.peek(), Some(t) if t.is_keyword(keyword));
+        if matches {
+            self.pos += 1;
+        }
+        matches
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let matches = matches!(self.peek(), Some(t) if t.is_symbol(symbol));
+        if matches {
+            self.pos += 1;
+        }
+        matches
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), String> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(format!("expected {}", keyword.to_ascii_uppercase()))
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), String> {
+        if self.eat_symbol(symbol) {
+            Ok(())
+        } else {
+            Err(format!("expected `{}`", symbol))
+        }
+    }
+
+    fn expect_ident(&mut self) -> Result<&'a str, String> {
+        self.next()
+            .and_then(Token::ident)
+            .ok_or_else(|| "expected an identifier".to_string())
+    }
+
+    // [keyspace.]name
+    fn qualified_name(&mut self) -> Result<(Option<String>, String), String> {
+        let first = self.expect_ident()?;
+        if self.eat_symbol(".") {
+            Ok((Some(first.to_string()), self.expect_ident()?.to_string()))
+        } else {
+            Ok((None, first.to_string()))
+        }
+    }
+
+    fn cql_type(&mut self) -> Result<CqlType, String> {
+        let name = self.expect_ident()?;
+        let mut parameters = Vec::new();
+        if self.eat_symbol("<") {
+            loop {
+                parameters.push(self.cql_type()?);
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+            self.expect_symbol(">")?;
+        }
+
+        let mut parameters = parameters.into_iter();
+        let mut parameter = || {
+            parameters
+                .next()
+                .map(Box::new)
+                .ok_or_else(|| format!("missing parameter of type {}", name))
+        };
+        let typ = match name {
+            "frozen" => *parameter()?,
+            "list" => CqlType::List(parameter()?),
+            "set" => CqlType::Set(parameter()?),
+            "map" => CqlType::Map(parameter()?, parameter()?),
+            "tuple" => CqlType::Tuple(parameters.by_ref().collect()),
+            _ => match NativeType::from_name(name) {
+                Some(native) => CqlType::Native(native),
+                None => {
+                    return Err(format!(
+                        "type {} is not supported, only native types, collections and tuples are",
+                        name
+                    ))
+                }
+            },
+        };
+        if parameters.next().is_some() {
+            return Err(format!("too many parameters of type {}", name));
+        }
+        Ok(typ)
+    }
+}
+
+/// Parses the `CREATE TABLE` statements of a schema dump, like the output
+/// of `DESCRIBE SCHEMA`. Other statements are ignored, except for `USE`, which sets
+/// the keyspace of the tables created after it without one.
+pub(crate) fn parse_schema(schema: &str) -> Result<Vec<Table>, String> {
+    let tokens = tokenize(schema)?;
+    let mut tables = Vec::new();
+    let mut current_keyspace = None;
+    for statement in tokens.split(|token| token.is_symbol(";")) {
+        let mut parser = Parser::new(statement);
+        if parser.eat_keyword("use") {
+            current_keyspace = Some(parser.expect_ident()?.to_string());
+        } else if parser.eat_keyword("create")
+            && (parser.eat_keyword("table") || parser.eat_keyword("columnfamily"))
+        {
+            let mut table = parse_create_table(&mut parser)
+                .map_err(|err| format!("invalid CREATE TABLE statement in the schema: {}", err))?;
+            if table.keyspace.is_none() {
+                table.keyspace = current_keyspace.clone();
+            }
+            tables.push(table);
+        }
+    }
+    Ok(tables)
+}
+
+fn parse_create_table(parser: &mut Parser<'_>) -> Result<Table, String> {
+    if parser.eat_keyword("if") {
+        parser.expect_keyword("not")?;
+        parser.expect_keyword("exists")?;
+    }
+    let (keyspace, name) = parser.qualified_name()?;
+    parser.expect_symbol("(")?;
+
+    let mut columns = Vec::new();
+    let mut partition_key = Vec::new();
+    let mut clustering_key = Vec::new();
+    loop {
+        if parser.eat_keyword("primary") {
+            parser.expect_keyword("key")?;
+            parser.expect_symbol("(")?;
+            if parser.eat_symbol("(") {
+                loop {
+                    partition_key.push(parser.expect_ident()?.to_string());
+                    if !parser.eat_symbol(",") {
+                        break;
+                    }
+                }
+                parser.expect_symbol(")")?;
+            } else {
+                partition_key.push(parser.expect_ident()?.to_string());
+            }
+            while parser.eat_symbol(",") {
+                clustering_key.push(parser.expect_ident()?.to_string());
+            }
+            parser.expect_symbol(")")?;
+        } else {
+            let column = parser.expect_ident()?.to_string();
+            let typ = parser.cql_type()?;
+            let kind = if parser.eat_keyword("static") {
+                ColumnKind::Static
+            } else {
+                ColumnKind::Regular
+            };
+            if parser.eat_keyword("primary") {
+                parser.expect_keyword("key")?;
+                partition_key.push(column.clone());
+            }
+            columns.push(Column {
+                name: column,
+                typ,
+                kind,
+            });
+        }
+        if !parser.eat_symbol(",") {
+            break;
+        }
+    }
+    parser.expect_symbol(")")?;
+
+    for column in &mut columns {
+        if partition_key.contains(&column.name) {
+            column.kind = ColumnKind::PartitionKey;
+        } else if clustering_key.contains(&column.name) {
+            column.kind = ColumnKind::Clustering;
+        }
+    }
+    Ok(Table {
+        keyspace,
+        name,
+        columns,
+    })
+}
+
+fn find_table<'a>(
+    tables: &'a [Table],
+    keyspace: Option<&str>,
+    name: &str,
+) -> Result<&'a Table, String> {
+    let mut matching = tables.iter().filter(|table| {
+        table.name == name && (keyspace.is_none() || table.keyspace.as_deref() == keyspace)
+    });
+    let qualified_name = match keyspace {
+        Some(keyspace) => format!("{}.{}", keyspace, name),
+        None => name.to_string(),
+    };
+    match (matching.next(), matching.next()) {
+        (Some(table), None) => Ok(table),
+        (Some(_), Some(_)) => Err(format!(
+            "table {} is ambiguous, qualify it with its keyspace",
+            qualified_name
+        )),
+        (None, _) => Err(format!("table {} not found in the schema", qualified_name)),
+    }
+}
+
+/// Parses a `SELECT`, `INSERT`, `UPDATE` or `DELETE` statement and finds the types
+/// of its bind markers, and of its result columns if it's a `SELECT`.
+pub(crate) fn parse_statement(statement: &str, tables: &[Table]) -> Result<Statement, String> {
+    let mut tokens = tokenize(statement)?;
+    if matches!(tokens.last(), Some(token) if token.is_symbol(";")) {
+        tokens.pop();
+    }
+    if tokens.iter().any(|token| token.is_symbol(";")) {
+        return Err("expected a single statement".to_string());
+    }
+    if let Some(pos) = tokens.iter().position(|token| token.is_symbol(":")) {
+        if tokens.get(pos + 1).and_then(Token::ident).is_some() {
+            return Err("named bind markers are not supported, use `?`".to_string());
+        }
+    }
+
+    let mut parser = Parser::new(&tokens);
+    let kind = parser
+        .next()
+        .and_then(Token::ident)
+        .unwrap_or_default()
+        .to_string();
+    let statement = match kind.as_str() {
+        "select" => parse_select(&mut parser, tables),
+        "insert" => parse_insert(&mut parser, tables),
+        "update" => {
+            let (keyspace, name) = parser.qualified_name()?;
+            let table = find_table(tables, keyspace.as_deref(), &name)?;
+            let bind_markers = bind_markers(&tokens, parser.pos, table)?;
+            Ok(Statement {
+                bind_markers,
+                result_columns: None,
+            })
+        }
+        "delete" => {
+            // The deleted columns precede the table
+            let from = tokens
+                .iter()
+                .position(|token| token.is_keyword("from"))
+                .ok_or("expected FROM")?;
+            parser.pos = from + 1;
+            let (keyspace, name) = parser.qualified_name()?;
+            let table = find_table(tables, keyspace.as_deref(), &name)?;
+            let bind_markers = bind_markers(&tokens, 1, table)?;
+            Ok(Statement {
+                bind_markers,
+                result_columns: None,
+            })
+        }
+        _ => Err("only SELECT, INSERT, UPDATE and DELETE statements are supported".to_string()),
+    }?;
+
+    let result_columns = statement.result_columns.iter().flatten();
+    for typ in statement
+        .bind_markers
+        .iter()
+        .map(|marker| &marker.typ)
+        .chain(result_columns.map(|column| &column.typ))
+    {
+        typ.check_map_keys()?;
+    }
+    Ok(statement)
+}
+
+fn parse_select(parser: &mut Parser<'_>, tables: &[Table]) -> Result<Statement, String> {
+    parser.eat_keyword("distinct");
+    if matches!(parser.peek(), Some(t) if t.is_keyword("json")) {
+        return Err("SELECT JSON is not supported".to_string());
+    }
+
+    let mut selectors = Vec::new();
+    loop {
+        if parser.eat_symbol("*") {
+            return Err(
+                "SELECT * is not supported, list the selected columns explicitly".to_string(),
+            );
+        }
+        let column = parser.expect_ident()?;
+        if matches!(parser.peek(), Some(t) if t.is_symbol("(")) {
+            return Err(format!(
+                "selecting the result of function {} is not supported",
+                column
+            ));
+        }
+        let alias = if parser.eat_keyword("as") {
+            parser.expect_ident()?
+        } else {
+            column
+        };
+        selectors.push((column, alias));
+        if !parser.eat_symbol(",") {
+            break;
+        }
+    }
+
+    parser.expect_keyword("from")?;
+    let (keyspace, name) = parser.qualified_name()?;
+    let table = find_table(tables, keyspace.as_deref(), &name)?;
+
+    let result_columns = selectors
+        .into_iter()
+        .map(|(column, alias)| {
+            let column = table.column(column)?;
+            Ok(ResultColumn {
+                name: alias.to_string(),
+                typ: column.typ.clone(),
+                nullable: table.is_nullable_in_rows(column),
+            })
+        })
+        .collect::<Result<_, String>>()?;
+    let bind_markers = bind_markers(parser.tokens, parser.pos, table)?;
+    Ok(Statement {
+        bind_markers,
+        result_columns: Some(result_columns),
+    })
+}
+
+fn parse_insert(parser: &mut Parser<'_>, tables: &[Table]) -> Result<Statement, String> {
+    parser.expect_keyword("into")?;
+    let (keyspace, name) = parser.qualified_name()?;
+    let table = find_table(tables, keyspace.as_deref(), &name)?;
+    if matches!(parser.peek(), Some(t) if t.is_keyword("json")) {
+        return Err("INSERT JSON is not supported".to_string());
+    }
+
+    parser.expect_symbol("(")?;
+    let mut columns = Vec::new();
+    loop {
+        columns.push(table.column(parser.expect_ident()?)?);
+        if !parser.eat_symbol(",") {
+            break;
+        }
+    }
+    parser.expect_symbol(")")?;
+
+    // Each value is either a bind marker or a literal
+    parser.expect_keyword("values")?;
+    let values_start = parser.pos;
+    parser.expect_symbol("(")?;
+    let mut bind_markers = Vec::new();
+    for (i, column) in columns.iter().enumerate() {
+        if i > 0 {
+            parser.expect_symbol(",")?;
+        }
+        if parser.eat_symbol("?") {
+            bind_markers.push(BindMarker {
+                typ: column.typ.clone(),
+                nullable: matches!(column.kind, ColumnKind::Static | ColumnKind::Regular),
+            });
+        } else {
+            skip_term(parser)?;
+        }
+    }
+    parser.expect_symbol(")")?;
+    if parser.tokens[values_start..parser.pos]
+        .iter()
+        .filter(|token| token.is_symbol("?"))
+        .count()
+        != bind_markers.len()
+    {
+        return Err("bind markers nested in values are not supported".to_string());
+    }
+
+    bind_markers.extend(self::bind_markers(parser.tokens, parser.pos, table)?);
+    Ok(Statement {
+        bind_markers,
+        result_columns: None,
+    })
+}
+
+// Skips a literal term, possibly a collection or a tuple.
+fn skip_term(parser: &mut Parser<'_>) -> Result<(), String> {
+    let mut depth = 0usize;
+    loop {
+        let token = parser.peek().ok_or("unexpected end of statement")?;
+        if depth == 0 && (token.is_symbol(",") || token.is_symbol(")")) {
+            return Ok(());
+        }
+        match token {
+            Token::Symbol("(" | "[" | "{") => depth += 1,
+            Token::Symbol(")" | "]" | "}") => depth -= 1,
+            _ => {}
+        }
+        parser.pos += 1;
+    }
+}
+
+// Finds the types of the bind markers in `tokens[start..]`, based on the tokens preceding
+// each of them: the column they are compared with or assigned to, or the clause they
+// are a part of.
+fn bind_markers(tokens: &[Token], start: usize, table: &Table) -> Result<Vec<BindMarker>, String> {
+    // Assignments in the SET clause of an UPDATE can set column values to null
+    let set_clause = match tokens.iter().position(|t| t.is_keyword("set")) {
+        Some(set) => {
+            let end = tokens
+                .iter()
+                .position(|t| t.is_keyword("where"))
+                .unwrap_or(tokens.len());
+            set..end
+        }
+        None => 0..0,
+    };
+
+    let token = |i: usize| tokens.get(i).filter(|_| i >= start);
+    let column = |i: Option<usize>| -> Result<&Column, String> {
+        match i.and_then(token).and_then(Token::ident) {
+            Some(name) => table.column(name),
+            None => Err("can't determine the type of a bind marker".to_string()),
+        }
+    };
+    let before = |i: usize| i.checked_sub(1);
+
+    let mut markers = Vec::new();
+    for (i, _) in tokens
+        .iter()
+        .enumerate()
+        .skip(start)
+        .filter(|(_, token)| token.is_symbol("?"))
+    {
+        let prev = before(i).and_then(token).ok_or("unexpected bind marker")?;
+        let marker = |typ: CqlType| BindMarker {
+            typ,
+            nullable: false,
+        };
+
+        let bind_marker = match prev {
+            Token::Ident(keyword) if keyword == "limit" || keyword == "ttl" => {
+                marker(CqlType::Native(NativeType::Int))
+            }
+            Token::Ident(keyword) if keyword == "timestamp" => {
+                marker(CqlType::Native(NativeType::BigInt))
+            }
+            Token::Ident(keyword) if keyword == "in" => {
+                let column = column(before(i - 1))?;
+                marker(CqlType::List(Box::new(column.typ.clone())))
+            }
+            Token::Ident(keyword) if keyword == "contains" => match &column(before(i - 1))?.typ {
+                CqlType::List(element) | CqlType::Set(element) | CqlType::Map(_, element) => {
+                    marker((**element).clone())
+                }
+                _ => return Err("CONTAINS on a column which is not a collection".to_string()),
+            },
+            Token::Ident(keyword)
+                if keyword == "key"
+                    && matches!(before(i - 1).and_then(token), Some(t) if t.is_keyword("contains")) =>
+            {
+                match &column(before(i - 2))?.typ {
+                    CqlType::Map(key, _) => marker((**key).clone()),
+                    _ => return Err("CONTAINS KEY on a column which is not a map".to_string()),
+                }
+            }
+            // `column[?]`, a map key or a list index
+            Token::Symbol("[") => match &column(before(i - 1))?.typ {
+                CqlType::Map(key, _) => marker((**key).clone()),
+                CqlType::List(_) => marker(CqlType::Native(NativeType::Int)),
+                _ => return Err("element access on a column which is not a collection".to_string()),
+            },
+            Token::Symbol("=" | "<" | ">" | "<=" | ">=" | "!=" | "+" | "-") => {
+                match before(i - 1).and_then(token) {
+                    // `column[...] = ?`, a map value or a list element
+                    Some(Token::Symbol("]")) => {
+                        let open = (start..i - 1)
+                            .rev()
+                            .find(|&j| tokens[j].is_symbol("["))
+                            .ok_or("unbalanced brackets")?;
+                        match &column(before(open))?.typ {
+                            CqlType::Map(_, element) | CqlType::List(element) => {
+                                marker((**element).clone())
+                            }
+                            _ => {
+                                return Err("element access on a column which is not a collection"
+                                    .to_string())
+                            }
+                        }
+                    }
+                    Some(Token::Symbol(")")) => {
+                        return Err(
+                            "bind markers compared with multiple columns or with the result \
+                             of a function are not supported"
+                                .to_string(),
+                        )
+                    }
+                    _ => {
+                        let column = column(before(i - 1))?;
+                        // Only a plain `column = ?` assignment can set a null value
+                        let assignment = prev.is_symbol("=")
+                            && set_clause.contains(&i)
+                            && !matches!(tokens.get(i + 1), Some(t) if t.is_symbol("+"));
+                        BindMarker {
+                            typ: column.typ.clone(),
+                            nullable: assignment
+                                && matches!(column.kind, ColumnKind::Static | ColumnKind::Regular),
+                        }
+                    }
+                }
+            }
+            // `column IN (?, ?)`
+            Token::Symbol("(" | ",") => {
+                let open = (start..i)
+                    .rev()
+                    .find(|&j| tokens[j].is_symbol("("))
+                    .ok_or("unbalanced parentheses")?;
+                let is_in = matches!(before(open).and_then(token), Some(t) if t.is_keyword("in"));
+                if !is_in {
+                    return Err("can't determine the type of a bind marker".to_string());
+                }
+                marker(column(before(open - 1))?.typ.clone())
+            }
+            _ => return Err("can't determine the type of a bind marker".to_string()),
+        };
+        markers.push(bind_marker);
+    }
+    Ok(markers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = "
+        CREATE KEYSPACE ks WITH replication = {'class': 'SimpleStrategy', 'replication_factor': 1};
+        CREATE TABLE ks.users (
+            id uuid,
+            bucket int,
+            name text,
+            \"Emails\" set<text>,
+            tags frozen<map<text, list<int>>>,
+            PRIMARY KEY ((id, bucket), name)
+        ) WITH comment = 'users; all of them';
+        USE other;
+        CREATE TABLE IF NOT EXISTS counters (k bigint PRIMARY KEY, c counter, p tuple<int, blob>);
+    ";
+
+    fn types(statement: &Statement) -> Vec<(CqlType, bool)> {
+        statement
+            .bind_markers
+            .iter()
+            .map(|marker| (marker.typ.clone(), marker.nullable))
+            .collect()
+    }
+
+    const TEXT: CqlType = CqlType::Native(NativeType::Text);
+    const INT: CqlType = CqlType::Native(NativeType::Int);
+    const UUID: CqlType = CqlType::Native(NativeType::Uuid);
+
+    #[test]
+    fn parses_schema() {
+        let tables = parse_schema(SCHEMA).unwrap();
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].keyspace.as_deref(), Some("ks"));
+        let kinds: Vec<_> = tables[0].columns.iter().map(|c| c.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                ColumnKind::PartitionKey,
+                ColumnKind::PartitionKey,
+                ColumnKind::Clustering,
+                ColumnKind::Regular,
+                ColumnKind::Regular
+            ]
+        );
+        assert_eq!(tables[0].columns[3].name, "Emails");
+        assert_eq!(
+            tables[0].columns[4].typ,
+            CqlType::Map(Box::new(TEXT), Box::new(CqlType::List(Box::new(INT))))
+        );
+        assert_eq!(tables[1].keyspace.as_deref(), Some("other"));
+        assert_eq!(tables[1].columns[0].kind, ColumnKind::PartitionKey);
+        assert!(parse_schema("CREATE TABLE t (a address PRIMARY KEY)").is_err());
+    }
+
+    #[test]
+    fn finds_select_types() {
+        let tables = parse_schema(SCHEMA).unwrap();
+        let statement = parse_statement(
+            "SELECT name, \"Emails\" AS emails FROM ks.users \
+             WHERE id = ? AND bucket IN ? AND name > ? AND tags CONTAINS KEY ? LIMIT ?",
+            &tables,
+        )
+        .unwrap();
+        assert_eq!(
+            types(&statement),
+            [
+                (UUID, false),
+                (CqlType::List(Box::new(INT)), false),
+                (TEXT, false),
+                (TEXT, false),
+                (INT, false)
+            ]
+        );
+        let columns = statement.result_columns.unwrap();
+        assert_eq!(columns[1].name, "emails");
+        assert_eq!(columns[1].typ, CqlType::Set(Box::new(TEXT)));
+        // Clustering columns can't be null, like the partition key
+        assert!(!columns[0].nullable);
+        assert!(columns[1].nullable);
+
+        let statement =
+            parse_statement("select k from counters where k in (?, 1, ?)", &tables).unwrap();
+        assert_eq!(statement.bind_markers.len(), 2);
+    }
+
+    #[test]
+    fn finds_nullable_columns_of_tables_with_static_columns() {
+        let tables = parse_schema(
+            "CREATE TABLE ks.events (p int, c int, s text STATIC, v text, PRIMARY KEY (p, c))",
+        )
+        .unwrap();
+        assert_eq!(tables[0].columns[2].kind, ColumnKind::Static);
+        let statement = parse_statement("SELECT p, c, s, v FROM ks.events", &tables).unwrap();
+        let nullable: Vec<_> = statement
+            .result_columns
+            .unwrap()
+            .iter()
+            .map(|column| column.nullable)
+            .collect();
+        // Rows of partitions with only the static column set have no clustering key
+        assert_eq!(nullable, [false, true, true, true]);
+
+        let statement = parse_statement("UPDATE ks.events SET s = ? WHERE p = ?", &tables).unwrap();
+        assert_eq!(
+            types(&statement),
+            [(TEXT, true), (CqlType::Native(NativeType::Int), false)]
+        );
+    }
+
+    #[test]
+    fn rejects_maps_with_unhashable_keys() {
+        let tables = parse_schema(
+            "CREATE TABLE ks.prices (k int PRIMARY KEY, by_price map<double, text>, \
+             by_day list<frozen<map<tuple<int, date>, int>>>, by_name map<text, int>)",
+        )
+        .unwrap();
+        for statement in [
+            "SELECT by_price FROM ks.prices",
+            "SELECT by_day FROM ks.prices",
+            "UPDATE ks.prices SET by_price = ? WHERE k = 1",
+        ] {
+            let err = parse_statement(statement, &tables).unwrap_err();
+            assert!(err.contains("maps with keys of type"), "{}", err);
+        }
+        assert!(parse_statement("SELECT k, by_name FROM ks.prices", &tables).is_ok());
+    }
+
+    #[test]
+    fn finds_modification_types() {
+        let tables = parse_schema(SCHEMA).unwrap();
+        let statement = parse_statement(
+            "INSERT INTO ks.users (id, bucket, name, tags) VALUES (?, 1, ?, ?) USING TTL ?",
+            &tables,
+        )
+        .unwrap();
+        assert_eq!(
+            types(&statement)
+                .into_iter()
+                .map(|(_, nullable)| nullable)
+                .collect::<Vec<_>>(),
+            [false, false, true, false]
+        );
+
+        let statement = parse_statement(
+            "UPDATE ks.users USING TIMESTAMP ? SET \"Emails\" = \"Emails\" + ?, tags[?] = ?, \
+             name = ? WHERE id = ? AND bucket = ? IF tags = ?",
+            &tables,
+        )
+        .unwrap();
+        assert_eq!(
+            types(&statement),
+            [
+                (CqlType::Native(NativeType::BigInt), false),
+                (CqlType::Set(Box::new(TEXT)), false),
+                (TEXT, false),
+                (CqlType::List(Box::new(INT)), false),
+                (TEXT, false),
+                (UUID, false),
+                (INT, false),
+                (tables[0].columns[4].typ.clone(), false)
+            ]
+        );
+
+        let statement =
+            parse_statement("DELETE tags[?] FROM ks.users WHERE id = ?;", &tables).unwrap();
+        assert_eq!(types(&statement), [(TEXT, false), (UUID, false)]);
+    }
+
+    #[test]
+    fn rejects_unsupported_statements() {
+        let tables = parse_schema(SCHEMA).unwrap();
+        for statement in [
+            "SELECT * FROM ks.users",
+            "SELECT count(*) FROM ks.users",
+            "SELECT name FROM ks.missing",
+            "SELECT missing FROM ks.users",
+            "SELECT name FROM ks.users WHERE id = :id",
+            "SELECT name FROM ks.users WHERE (id, bucket) = (?, ?)",
+            "INSERT INTO ks.users (id, tags) VALUES (?, {'a': [?]})",
+            "INSERT INTO ks.users JSON ?",
+            "TRUNCATE ks.users",
+            "SELECT name FROM ks.users; SELECT name FROM ks.users",
+        ] {
+            assert!(
+                parse_statement(statement, &tables).is_err(),
+                "{} was accepted",
+                statement
+            );
+        }
+    }
+}
@@ -0,0 +1,382 @@
+use std::path::PathBuf;
+
+use darling::FromMeta;
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::parse_quote;
+
+use cql::{CqlType, NativeType, ResultColumn};
+
+mod cql;
+
+#[derive(FromMeta)]
+struct Attributes {
+    schema: String,
+
+    statement: String,
+
+    #[darling(rename = "crate")]
+    crate_path: Option<syn::Path>,
+
+    row: Option<syn::Ident>,
+}
+
+impl Attributes {
+    fn scylla_crate(&self) -> syn::Path {
+        self.crate_path
+            .clone()
+            .unwrap_or_else(|| parse_quote!(::scylla))
+    }
+
+    fn crate_path(&self) -> syn::Path {
+        let scylla_crate = self.scylla_crate();
+        parse_quote!(#scylla_crate::_macro_internal)
+    }
+}
+
+pub(crate) fn prepared_attribute(
+    args: TokenStream,
+    item: TokenStream,
+) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let args = darling::ast::NestedMeta::parse_meta_list(args.into())?;
+    let attributes = Attributes::from_list(&args)?;
+    let item: syn::ItemStruct = syn::parse(item)?;
+    if !matches!(item.fields, syn::Fields::Unit) || !item.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &item,
+            "#[prepared] can only be used on a unit struct without generics",
+        ));
+    }
+
+    // The schema path is relative to the crate being compiled, like `include_str!` paths
+    // are relative to the source file
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let schema_path = PathBuf::from(manifest_dir).join(&attributes.schema);
+    let schema = std::fs::read_to_string(&schema_path).map_err(|err| {
+        syn::Error::new(
+            Span::call_site(),
+            format!("can't read schema {}: {}", schema_path.display(), err),
+        )
+    })?;
+    let tables =
+        cql::parse_schema(&schema).map_err(|err| syn::Error::new(Span::call_site(), err))?;
+    let statement = cql::parse_statement(&attributes.statement, &tables).map_err(|err| {
+        syn::Error::new(
+            Span::call_site(),
+            format!("can't type statement {:?}: {}", attributes.statement, err),
+        )
+    })?;
+
+    let internal = attributes.crate_path();
+    let attrs = &item.attrs;
+    let vis = &item.vis;
+    let name = &item.ident;
+    let statement_text = &attributes.statement;
+    let schema_path = schema_path.to_string_lossy();
+
+    let param_types = statement.bind_markers.iter().map(|marker| {
+        let typ = param_type(&marker.typ, &internal);
+        if marker.nullable {
+            quote!(::std::option::Option<#typ>)
+        } else {
+            typ
+        }
+    });
+    let param_types = quote!((#(#param_types,)*));
+    let bind_marker_types = statement
+        .bind_markers
+        .iter()
+        .map(|marker| column_type(&marker.typ, &internal));
+
+    let (row_definition, result_check, execute) = match &statement.result_columns {
+        Some(columns) => {
+            let row_name = attributes
+                .row
+                .clone()
+                .unwrap_or_else(|| format_ident!("{}Row", name));
+            let row_definition = row_definition(
+                vis,
+                name,
+                &row_name,
+                columns,
+                &attributes.scylla_crate(),
+                &internal,
+            )?;
+            let result_column_types = columns.iter().map(|column| {
+                let column_name = &column.name;
+                let typ = column_type(&column.typ, &internal);
+                quote!((#column_name, #typ))
+            });
+            let result_check =
+                quote!(::std::option::Option::Some(&[#(#result_column_types),*][..]));
+            let execute = quote! {
+                /// Executes the statement, and returns the rows of its result.
+                pub async fn execute(
+                    &self,
+                    session: &#internal::Session,
+                    values: #param_types,
+                ) -> ::std::result::Result<#internal::TypedRowIter<#row_name>, #internal::QueryError> {
+                    session
+                        .execute(&self.prepared, values)
+                        .await?
+                        .rows_typed::<#row_name>()
+                        .map_err(|_| {
+                            #internal::QueryError::ProtocolError(
+                                "Response to a typed SELECT statement is not a Rows response",
+                            )
+                        })
+                }
+
+                /// Executes the statement with paging, and returns a stream of the rows of
+                /// its result fetched page by page.
+                pub async fn execute_iter(
+                    &self,
+                    session: &#internal::Session,
+                    values: #param_types,
+                ) -> ::std::result::Result<#internal::TypedRowIterator<#row_name>, #internal::QueryError> {
+                    ::std::result::Result::Ok(
+                        session
+                            .execute_iter(::std::clone::Clone::clone(&self.prepared), values)
+                            .await?
+                            .into_typed::<#row_name>(),
+                    )
+                }
+            };
+            (row_definition, result_check, execute)
+        }
+        None => {
+            let execute = quote! {
+                /// Executes the statement.
+                pub async fn execute(
+                    &self,
+                    session: &#internal::Session,
+                    values: #param_types,
+                ) -> ::std::result::Result<#internal::QueryResult, #internal::QueryError> {
+                    session.execute(&self.prepared, values).await
+                }
+            };
+            (quote!(), quote!(::std::option::Option::None), execute)
+        }
+    };
+
+    Ok(quote! {
+        #(#attrs)*
+        #[derive(::std::fmt::Debug, ::std::clone::Clone)]
+        #vis struct #name {
+            prepared: #internal::PreparedStatement,
+        }
+
+        #row_definition
+
+        // Rebuilds the crate when the schema changes
+        const _: &str = ::std::include_str!(#schema_path);
+
+        impl #name {
+            /// The CQL text of the statement.
+            pub const STATEMENT: &'static str = #statement_text;
+
+            /// Prepares the statement, and checks that the types of its bind markers and of its
+            /// result columns reported by the server are the ones taken from the schema.
+            pub async fn prepare(
+                session: &#internal::Session,
+            ) -> ::std::result::Result<Self, #internal::QueryError> {
+                let prepared = session.prepare(Self::STATEMENT).await?;
+                #internal::check_typed_statement(
+                    &prepared,
+                    &[#(#bind_marker_types),*],
+                    #result_check,
+                )?;
+                ::std::result::Result::Ok(Self { prepared })
+            }
+
+            /// Returns the underlying prepared statement.
+            pub fn get_prepared(&self) -> &#internal::PreparedStatement {
+                &self.prepared
+            }
+
+            /// Returns the underlying prepared statement, to change its configuration,
+            /// e.g. its consistency or page size.
+            pub fn get_prepared_mut(&mut self) -> &mut #internal::PreparedStatement {
+                &mut self.prepared
+            }
+
+            #execute
+        }
+    })
+}
+
+fn row_definition(
+    vis: &syn::Visibility,
+    name: &syn::Ident,
+    row_name: &syn::Ident,
+    columns: &[ResultColumn],
+    scylla_crate: &syn::Path,
+    internal: &syn::Path,
+) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let field_idents = columns
+        .iter()
+        .map(|column| field_ident(&column.name))
+        .collect::<Result<Vec<_>, _>>()?;
+    let field_types = columns.iter().map(|column| {
+        let typ = value_type(&column.typ, internal);
+        if column.nullable {
+            quote!(::std::option::Option<#typ>)
+        } else {
+            typ
+        }
+    });
+    let doc = format!("A row of the result of [`{}`].", name);
+    let scylla_crate = quote!(#scylla_crate).to_string();
+
+    Ok(quote! {
+        #[doc = #doc]
+        #[derive(
+            ::std::fmt::Debug,
+            ::std::clone::Clone,
+            ::std::cmp::PartialEq,
+            #internal::FromRow,
+        )]
+        #[scylla_crate = #scylla_crate]
+        #[allow(non_snake_case)]
+        #vis struct #row_name {
+            #(pub #field_idents: #field_types,)*
+        }
+    })
+}
+
+// Column names which aren't valid identifiers, e.g. quoted names with spaces, need an alias.
+fn field_ident(column: &str) -> Result<syn::Ident, syn::Error> {
+    let valid = matches!(column.chars().next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && column
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !matches!(column, "_" | "self" | "Self" | "super" | "crate");
+    if !valid {
+        return Err(syn::Error::new(
+            Span::call_site(),
+            format!(
+                "column {:?} is not a valid field name, select it with an alias: AS name",
+                column
+            ),
+        ));
+    }
+    Ok(match syn::parse_str::<syn::Ident>(column) {
+        Ok(ident) => ident,
+        // A keyword
+        Err(_) => syn::Ident::new_raw(column, Span::call_site()),
+    })
+}
+
+fn native_value_type(typ: &NativeType, internal: &syn::Path) -> proc_macro2::TokenStream {
+    match typ {
+        NativeType::Ascii | NativeType::Text => quote!(::std::string::String),
+        NativeType::BigInt => quote!(i64),
+        NativeType::Blob => quote!(::std::vec::Vec<u8>),
+        NativeType::Boolean => quote!(bool),
+        NativeType::Counter => quote!(#internal::Counter),
+        NativeType::Date => quote!(#internal::CqlDate),
+        NativeType::Decimal => quote!(#internal::CqlDecimal),
+        NativeType::Double => quote!(f64),
+        NativeType::Duration => quote!(#internal::CqlDuration),
+        NativeType::Float => quote!(f32),
+        NativeType::Inet => quote!(::std::net::IpAddr),
+        NativeType::Int => quote!(i32),
+        NativeType::SmallInt => quote!(i16),
+        NativeType::Time => quote!(#internal::CqlTime),
+        NativeType::Timestamp => quote!(#internal::CqlTimestamp),
+        NativeType::Timeuuid => quote!(#internal::CqlTimeuuid),
+        NativeType::TinyInt => quote!(i8),
+        NativeType::Uuid => quote!(#internal::Uuid),
+        NativeType::Varint => quote!(#internal::CqlVarint),
+    }
+}
+
+// The owned Rust type of values of a CQL type, used in rows.
+fn value_type(typ: &CqlType, internal: &syn::Path) -> proc_macro2::TokenStream {
+    match typ {
+        CqlType::Native(native) => native_value_type(native, internal),
+        CqlType::List(element) | CqlType::Set(element) => {
+            let element = value_type(element, internal);
+            quote!(::std::vec::Vec<#element>)
+        }
+        CqlType::Map(key, value) => {
+            let key = value_type(key, internal);
+            let value = value_type(value, internal);
+            quote!(::std::collections::HashMap<#key, #value>)
+        }
+        CqlType::Tuple(elements) => {
+            let elements = elements.iter().map(|element| value_type(element, internal));
+            quote!((#(#elements,)*))
+        }
+    }
+}
+
+// The Rust type of values bound to a bind marker. Strings, blobs and lists are borrowed,
+// so that binding them doesn't need an allocation.
+fn param_type(typ: &CqlType, internal: &syn::Path) -> proc_macro2::TokenStream {
+    match typ {
+        CqlType::Native(NativeType::Ascii | NativeType::Text) => quote!(&str),
+        CqlType::Native(NativeType::Blob) => quote!(&[u8]),
+        CqlType::List(element) | CqlType::Set(element) => {
+            let element = value_type(element, internal);
+            quote!(&[#element])
+        }
+        _ => value_type(typ, internal),
+    }
+}
+
+// The `ColumnType` of a CQL type, checked against the metadata of the prepared statement.
+fn column_type(typ: &CqlType, internal: &syn::Path) -> proc_macro2::TokenStream {
+    let ty = quote!(#internal::ColumnType);
+    match typ {
+        CqlType::Native(native) => {
+            let variant = match native {
+                NativeType::Ascii => "Ascii",
+                NativeType::BigInt => "BigInt",
+                NativeType::Blob => "Blob",
+                NativeType::Boolean => "Boolean",
+                NativeType::Counter => "Counter",
+                NativeType::Date => "Date",
+                NativeType::Decimal => "Decimal",
+                NativeType::Double => "Double",
+                NativeType::Duration => "Duration",
+                NativeType::Float => "Float",
+                NativeType::Inet => "Inet",
+                NativeType::Int => "Int",
+                NativeType::SmallInt => "SmallInt",
+                NativeType::Text => "Text",
+                NativeType::Time => "Time",
+                NativeType::Timestamp => "Timestamp",
+                NativeType::Timeuuid => "Timeuuid",
+                NativeType::TinyInt => "TinyInt",
+                NativeType::Uuid => "Uuid",
+                NativeType::Varint => "Varint",
+            };
+            let variant = syn::Ident::new(variant, Span::call_site());
+            quote!(#ty::#variant)
+        }
+        CqlType::List(element) => {
+            let element = column_type(element, internal);
+            quote!(#ty::List(::std::boxed::Box::new(#element)))
+        }
+        CqlType::Set(element) => {
+            let element = column_type(element, internal);
+            quote!(#ty::Set(::std::boxed::Box::new(#element)))
+        }
+        CqlType::Map(key, value) => {
+            let key = column_type(key, internal);
+            let value = column_type(value, internal);
+            quote!(#ty::Map(
+                ::std::boxed::Box::new(#key),
+                ::std::boxed::Box::new(#value),
+            ))
+        }
+        CqlType::Tuple(elements) => {
+            let elements = elements
+                .iter()
+                .map(|element| column_type(element, internal));
+            quote!(#ty::Tuple(::std::vec![#(#elements),*]))
+        }
+    }
+}
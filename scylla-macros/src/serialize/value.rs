@@ -21,6 +21,11 @@ struct Attributes {
 
     #[darling(default)]
     force_exact_match: bool,
+
+    // If true, the struct is serialized as a CQL tuple instead of a UDT,
+    // with its fields being the tuple elements in the order of declaration.
+    #[darling(default)]
+    transparent_tuple: bool,
 }
 
 impl Attributes {
@@ -90,9 +95,13 @@ pub(crate) fn derive_serialize_value(
     let ctx = Context { attributes, fields };
     ctx.validate(&input.ident)?;
 
-    let gen: Box<dyn Generator> = match ctx.attributes.flavor {
-        Flavor::MatchByName => Box::new(FieldSortingGenerator { ctx: &ctx }),
-        Flavor::EnforceOrder => Box::new(FieldOrderedGenerator { ctx: &ctx }),
+    let gen: Box<dyn Generator> = if ctx.attributes.transparent_tuple {
+        Box::new(TupleGenerator { ctx: &ctx })
+    } else {
+        match ctx.attributes.flavor {
+            Flavor::MatchByName => Box::new(FieldSortingGenerator { ctx: &ctx }),
+            Flavor::EnforceOrder => Box::new(FieldOrderedGenerator { ctx: &ctx }),
+        }
     };
 
     let serialize_item = gen.generate_serialize();
@@ -109,6 +118,34 @@ impl Context {
     fn validate(&self, struct_ident: &syn::Ident) -> Result<(), syn::Error> {
         let mut errors = darling::Error::accumulator();
 
+        if self.attributes.transparent_tuple {
+            // Tuple elements have no names, and all of them must be present
+            for (attr, is_set) in [
+                ("skip_name_checks", self.attributes.skip_name_checks),
+                ("force_exact_match", self.attributes.force_exact_match),
+            ] {
+                if is_set {
+                    let err = darling::Error::custom(format!(
+                        "the `{attr}` attribute is not allowed with the `transparent_tuple` attribute",
+                    ))
+                    .with_span(struct_ident);
+                    errors.push(err);
+                }
+            }
+
+            for field in self.fields.iter() {
+                if field.attrs.rename.is_some() {
+                    let err = darling::Error::custom(
+                        "the `rename` annotations don't make sense with `transparent_tuple` attribute",
+                    )
+                    .with_span(&field.ident);
+                    errors.push(err);
+                }
+            }
+
+            return errors.finish().map_err(Into::into);
+        }
+
         if self.attributes.skip_name_checks {
             // Skipping name checks is only available in enforce_order mode
             if self.attributes.flavor != Flavor::EnforceOrder {
@@ -478,3 +515,89 @@ impl<'a> Generator for FieldOrderedGenerator<'a> {
         }
     }
 }
+
+// Generates an implementation of the trait which serializes the struct
+// as a CQL tuple, with fields being the elements in the order of declaration.
+struct TupleGenerator<'a> {
+    ctx: &'a Context,
+}
+
+impl<'a> Generator for TupleGenerator<'a> {
+    fn generate_serialize(&self) -> syn::TraitItemFn {
+        let crate_path = self.ctx.attributes.crate_path();
+
+        let field_count = self.ctx.fields.len();
+        let element_serializations = self.ctx.fields.iter().enumerate().map(|(index, field)| {
+            let rust_field_ident = &field.ident;
+            let typ = &field.ty;
+            let serialization: syn::Stmt = parse_quote! {
+                <#typ as #crate_path::SerializeValue>::serialize(
+                    &self.#rust_field_ident,
+                    &element_types[#index],
+                    #crate_path::CellValueBuilder::make_sub_writer(&mut builder),
+                )
+                .map_err(|err| mk_ser_err(
+                    #crate_path::TupleSerializationErrorKind::ElementSerializationFailed {
+                        index: #index,
+                        err,
+                    }
+                ))?;
+            };
+            serialization
+        });
+
+        parse_quote! {
+            fn serialize<'b>(
+                &self,
+                typ: &#crate_path::ColumnType,
+                writer: #crate_path::CellWriter<'b>,
+            ) -> ::std::result::Result<#crate_path::WrittenCellProof<'b>, #crate_path::SerializationError> {
+                let mk_typck_err = |kind: #crate_path::TupleTypeCheckErrorKind| -> #crate_path::SerializationError {
+                    #crate_path::SerializationError::new(
+                        #crate_path::BuiltinTypeTypeCheckError {
+                            rust_name: ::std::any::type_name::<Self>(),
+                            got: <_ as ::std::clone::Clone>::clone(typ),
+                            kind: #crate_path::BuiltinTypeTypeCheckErrorKind::TupleError(kind),
+                        }
+                    )
+                };
+                let mk_ser_err = |kind: #crate_path::TupleSerializationErrorKind| -> #crate_path::SerializationError {
+                    #crate_path::SerializationError::new(
+                        #crate_path::BuiltinTypeSerializationError {
+                            rust_name: ::std::any::type_name::<Self>(),
+                            got: <_ as ::std::clone::Clone>::clone(typ),
+                            kind: #crate_path::BuiltinTypeSerializationErrorKind::TupleError(kind),
+                        }
+                    )
+                };
+
+                let element_types = match typ {
+                    #crate_path::ColumnType::Tuple(element_types) => element_types,
+                    _ => return ::std::result::Result::Err(mk_typck_err(
+                        #crate_path::TupleTypeCheckErrorKind::NotTuple,
+                    )),
+                };
+                if element_types.len() != #field_count {
+                    return ::std::result::Result::Err(mk_typck_err(
+                        #crate_path::TupleTypeCheckErrorKind::WrongElementCount {
+                            rust_type_el_count: #field_count,
+                            cql_type_el_count: element_types.len(),
+                        }
+                    ));
+                }
+
+                let mut builder = #crate_path::CellWriter::into_value_builder(writer);
+                #(#element_serializations)*
+                let proof = #crate_path::CellValueBuilder::finish(builder)
+                    .map_err(|_| #crate_path::SerializationError::new(
+                        #crate_path::BuiltinTypeSerializationError {
+                            rust_name: ::std::any::type_name::<Self>(),
+                            got: <_ as ::std::clone::Clone>::clone(typ),
+                            kind: #crate_path::BuiltinTypeSerializationErrorKind::SizeOverflow,
+                        }
+                    ) as #crate_path::SerializationError)?;
+                ::std::result::Result::Ok(proof)
+            }
+        }
+    }
+}
@@ -481,6 +481,7 @@ pub mod example_db_errors {
             required: 3,
             data_present: true,
             numfailures: 1,
+            failure_reasons: None,
         }
     }
     pub fn write_failure() -> DbError {
@@ -490,6 +491,7 @@ pub mod example_db_errors {
             required: 3,
             write_type: WriteType::UnloggedBatch,
             numfailures: 1,
+            failure_reasons: None,
         }
     }
     pub fn unprepared() -> DbError {
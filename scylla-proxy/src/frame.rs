@@ -182,6 +182,7 @@ fn serialize_error_specific_fields(buf: &mut BytesMut, error: DbError) -> Result
             received,
             required,
             numfailures,
+            failure_reasons: _,
             data_present,
         } => {
             types::write_consistency(consistency, buf);
@@ -195,6 +196,7 @@ fn serialize_error_specific_fields(buf: &mut BytesMut, error: DbError) -> Result
             received,
             required,
             numfailures,
+            failure_reasons: _,
             write_type,
         } => {
             types::write_consistency(consistency, buf);
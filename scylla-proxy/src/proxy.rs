@@ -715,6 +715,12 @@ impl Doorkeeper {
             self.listener.accept().await.map_err(|err| {
                 DoorkeeperError::DriverConnectionAttempt(self.node.proxy_addr(), err)
             })?;
+        // Frames are written to the driver with two writes, the header and the body.
+        // With Nagle's algorithm the body would wait for the driver to acknowledge the header,
+        // which it may delay by tens of milliseconds. Scylla nodes disable it too.
+        driver_stream
+            .set_nodelay(true)
+            .map_err(|err| DoorkeeperError::DriverConnectionAttempt(self.node.proxy_addr(), err))?;
         info!(
             "Connected driver from {} to {}, connection no={}.",
             driver_addr,
@@ -3,8 +3,13 @@ use criterion::{criterion_group, criterion_main, Criterion};
 use bytes::BytesMut;
 use scylla::transport::partitioner::{calculate_token_for_partition_key, Murmur3Partitioner};
 use scylla_cql::{
-    frame::{response::result::ColumnType, types},
-    types::serialize::row::SerializedValues,
+    frame::{
+        response::result::{
+            ColumnSpec, ColumnType, PartitionKeyIndex, PreparedMetadata, TableSpec,
+        },
+        types,
+    },
+    types::serialize::row::{RowSerializationContext, SerializedValues},
 };
 
 fn types_benchmark(c: &mut Criterion) {
@@ -117,5 +122,69 @@ fn calculate_token_bench(c: &mut Criterion) {
     );
 }
 
-criterion_group!(benches, types_benchmark, calculate_token_bench);
+fn make_prepared_metadata(columns: &[(&str, ColumnType)]) -> PreparedMetadata {
+    PreparedMetadata {
+        flags: 0,
+        col_count: columns.len(),
+        pk_indexes: vec![PartitionKeyIndex {
+            index: 0,
+            sequence: 0,
+        }],
+        col_specs: columns
+            .iter()
+            .map(|(name, typ)| ColumnSpec {
+                table_spec: TableSpec::borrowed("ks", "table"),
+                name: name.to_string(),
+                typ: typ.clone(),
+            })
+            .collect(),
+    }
+}
+
+fn serialize_row_bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("SerializedValues::from_serializable");
+
+    let small_metadata = make_prepared_metadata(&[("a", ColumnType::Int), ("b", ColumnType::Text)]);
+    let small_ctx = RowSerializationContext::from_prepared(&small_metadata);
+    let small_row = (17_i32, "I'm prepared!!!");
+    group.bench_function("int, text", |b| {
+        b.iter(|| SerializedValues::from_serializable(&small_ctx, &small_row).unwrap())
+    });
+
+    let blob_metadata = make_prepared_metadata(&[("a", ColumnType::Int), ("b", ColumnType::Blob)]);
+    let blob_ctx = RowSerializationContext::from_prepared(&blob_metadata);
+    let blob_row = (17_i32, vec![0xCD_u8; 1024]);
+    group.bench_function("int, 1KiB blob", |b| {
+        b.iter(|| SerializedValues::from_serializable(&blob_ctx, &blob_row).unwrap())
+    });
+
+    let collections_metadata = make_prepared_metadata(&[
+        ("a", ColumnType::Int),
+        ("b", ColumnType::List(Box::new(ColumnType::BigInt))),
+        (
+            "c",
+            ColumnType::Map(Box::new(ColumnType::Text), Box::new(ColumnType::Int)),
+        ),
+    ]);
+    let collections_ctx = RowSerializationContext::from_prepared(&collections_metadata);
+    let collections_row = (
+        17_i32,
+        (0..100_i64).collect::<Vec<_>>(),
+        (0..100_i32)
+            .map(|i| (format!("key{}", i), i))
+            .collect::<std::collections::BTreeMap<_, _>>(),
+    );
+    group.bench_function("int, list<bigint>, map<text, int>", |b| {
+        b.iter(|| SerializedValues::from_serializable(&collections_ctx, &collections_row).unwrap())
+    });
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    types_benchmark,
+    calculate_token_bench,
+    serialize_row_bench
+);
 criterion_main!(benches);
@@ -0,0 +1,181 @@
+//! End-to-end benchmarks of the session, against the mock server of the
+//! `scylla::benchmarks` module, which runs the same benchmarks without criterion.
+//!
+//! The server answers immediately, so the results show the overhead of the driver
+//! itself: serialization, routing, frame encoding and the connection's write coalescing.
+//!
+//! The results are reproduced with `cargo bench -p scylla --features benchmarks --bench session`.
+
+use std::time::{Duration, Instant};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use futures::{StreamExt, TryStreamExt};
+use scylla::benchmarks::{MockConfig, MockServer, INSERT, SELECT};
+use scylla::frame::response::result::TableSpec;
+use scylla::load_balancing::{DefaultPolicy, LoadBalancingPolicy, Plan, RoutingInfo};
+use scylla::prepared_statement::PreparedStatement;
+use scylla::Session;
+use tokio::runtime::Runtime;
+
+const CONCURRENCY: usize = 256;
+
+fn runtime() -> Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+fn start_server(config: MockConfig) -> MockServer {
+    MockServer::start(config).unwrap()
+}
+
+fn connect(runtime: &Runtime, server: &MockServer, write_coalescing: bool) -> Session {
+    runtime
+        .block_on(
+            server
+                .session_builder()
+                .write_coalescing(write_coalescing)
+                .build(),
+        )
+        .unwrap()
+}
+
+fn prepare(runtime: &Runtime, session: &Session, statement: &str) -> PreparedStatement {
+    runtime.block_on(session.prepare(statement)).unwrap()
+}
+
+// Executes the statement `iters` times, with up to `concurrency` executions in flight.
+async fn execute_many(
+    session: &Session,
+    prepared: &PreparedStatement,
+    value: &[u8],
+    iters: u64,
+    concurrency: usize,
+) -> Duration {
+    let start = Instant::now();
+    futures::stream::iter(0..iters)
+        .map(|k| session.execute(prepared, (k as i32, value)))
+        .buffer_unordered(concurrency)
+        .try_for_each(|_| futures::future::ready(Ok(())))
+        .await
+        .unwrap();
+    start.elapsed()
+}
+
+fn insert_bench(c: &mut Criterion) {
+    let runtime = runtime();
+    let server = start_server(MockConfig::default());
+    let value = vec![0xCD_u8; 100];
+
+    let mut group = c.benchmark_group("Session::execute insert");
+    group.throughput(Throughput::Elements(1));
+    for write_coalescing in [false, true] {
+        let session = connect(&runtime, &server, write_coalescing);
+        let insert = prepare(&runtime, &session, INSERT);
+
+        for concurrency in [1, CONCURRENCY] {
+            let id = format!("concurrency {concurrency}, write coalescing {write_coalescing}");
+            group.bench_function(BenchmarkId::from_parameter(id), |b| {
+                b.iter_custom(|iters| {
+                    runtime.block_on(execute_many(&session, &insert, &value, iters, concurrency))
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+// Requests are serialized into buffers from the connection's buffer pool.
+// The largest value doesn't fit in a pooled buffer, so its buffers are allocated per request.
+fn insert_value_size_bench(c: &mut Criterion) {
+    let runtime = runtime();
+    let server = start_server(MockConfig::default());
+    let session = connect(&runtime, &server, true);
+    let insert = prepare(&runtime, &session, INSERT);
+
+    let mut group = c.benchmark_group("Session::execute insert by value size");
+    for value_size in [100, 4 * 1024, 64 * 1024] {
+        let value = vec![0xCD_u8; value_size];
+        group.throughput(Throughput::Bytes(value_size as u64));
+        group.bench_function(BenchmarkId::from_parameter(value_size), |b| {
+            b.iter_custom(|iters| {
+                runtime.block_on(execute_many(&session, &insert, &value, iters, CONCURRENCY))
+            })
+        });
+    }
+    group.finish();
+}
+
+fn select_bench(c: &mut Criterion) {
+    let runtime = runtime();
+
+    let mut group = c.benchmark_group("Session::execute select");
+    for select_rows in [1, 100, 1000] {
+        let server = start_server(MockConfig {
+            select_rows,
+            ..Default::default()
+        });
+        let session = connect(&runtime, &server, true);
+        let select = prepare(&runtime, &session, SELECT);
+
+        group.throughput(Throughput::Elements(select_rows as u64));
+        group.bench_function(BenchmarkId::new("rows", select_rows), |b| {
+            b.iter_custom(|iters| {
+                runtime.block_on(async {
+                    let start = Instant::now();
+                    for k in 0..iters {
+                        let result = session.execute(&select, (k as i32,)).await.unwrap();
+                        criterion::black_box(result.rows_typed::<(i32, Vec<u8>)>().unwrap())
+                            .for_each(|row| {
+                                row.unwrap();
+                            });
+                    }
+                    start.elapsed()
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+fn routing_bench(c: &mut Criterion) {
+    let runtime = runtime();
+    let server = start_server(MockConfig::default());
+    let session = connect(&runtime, &server, true);
+    let insert = prepare(&runtime, &session, INSERT);
+    let cluster_data = session.get_cluster_data();
+    let policy = DefaultPolicy::default();
+    let table = TableSpec::borrowed("bench", "kv");
+
+    let mut group = c.benchmark_group("routing");
+    group.bench_function("PreparedStatement::calculate_token", |b| {
+        b.iter(|| insert.calculate_token(&(17_i32, &[0xCD_u8][..])).unwrap())
+    });
+    group.bench_function("DefaultPolicy::pick", |b| {
+        let routing_info = RoutingInfo {
+            token: insert.calculate_token(&(17_i32, &[0xCD_u8][..])).unwrap(),
+            table: Some(&table),
+            ..Default::default()
+        };
+        b.iter(|| policy.pick(&routing_info, &cluster_data).unwrap())
+    });
+    group.bench_function("Plan", |b| {
+        let routing_info = RoutingInfo {
+            token: insert.calculate_token(&(17_i32, &[0xCD_u8][..])).unwrap(),
+            table: Some(&table),
+            ..Default::default()
+        };
+        b.iter(|| Plan::new(&policy, &routing_info, &cluster_data).count())
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    insert_bench,
+    insert_value_size_bench,
+    select_bench,
+    routing_bench
+);
+criterion_main!(benches);
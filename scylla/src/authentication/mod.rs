@@ -1,5 +1,6 @@
 use async_trait::async_trait;
 use bytes::{BufMut, BytesMut};
+use std::sync::Arc;
 
 /// Type to represent an authentication error message.
 pub type AuthError = String;
@@ -10,6 +11,10 @@ pub trait AuthenticatorSession: Send + Sync {
     /// To handle an authentication challenge initiated by the server.
     /// The information contained in the token parameter is authentication protocol specific.
     /// It may be NULL or empty.
+    ///
+    /// It is called once for every AUTH_CHALLENGE received from the server, so multi-step
+    /// mechanisms (e.g. SASL-based ones) can keep their state in `self` between the calls.
+    /// The returned value is sent back to the server in an AUTH_RESPONSE.
     async fn evaluate_challenge(
         &mut self,
         token: Option<&[u8]>,
@@ -72,18 +77,101 @@ impl AuthenticatorProvider for PlainTextAuthenticator {
         &self,
         _authenticator_name: &str,
     ) -> Result<(Option<Vec<u8>>, Box<dyn AuthenticatorSession>), AuthError> {
-        let mut response = BytesMut::new();
-        let username_as_bytes = self.username.as_bytes();
-        let password_as_bytes = self.password.as_bytes();
+        Ok((
+            Some(plain_text_response(&self.username, &self.password)),
+            Box::new(PlainTextAuthenticatorSession),
+        ))
+    }
+}
 
-        response.put_u8(0);
-        response.put_slice(username_as_bytes);
-        response.put_u8(0);
-        response.put_slice(password_as_bytes);
+/// Trait used to supply username and password for plain text authentication
+/// which may change during the lifetime of a session.
+///
+/// The provider is consulted each time a new connection authenticates, so short-lived
+/// credentials (e.g. tokens issued by a secrets vault) keep working after they are rotated.
+/// Connections which have already authenticated are not affected by the rotation.
+///
+/// It can be set using SessionBuilder::user_credentials_provider method.
+#[async_trait]
+pub trait CredentialsProvider: Send + Sync {
+    /// Returns a pair of username and password to be used for the next authentication.
+    async fn credentials(&self) -> Result<(String, String), AuthError>;
+}
+
+/// Authenticator provider that performs plain text authentication with credentials
+/// fetched from a [`CredentialsProvider`] on every new connection.
+pub struct RotatingPlainTextAuthenticator {
+    credentials_provider: Arc<dyn CredentialsProvider>,
+}
+
+impl RotatingPlainTextAuthenticator {
+    /// Creates new [`RotatingPlainTextAuthenticator`] instance that fetches credentials
+    /// from the provided [`CredentialsProvider`].
+    pub fn new(credentials_provider: Arc<dyn CredentialsProvider>) -> Self {
+        RotatingPlainTextAuthenticator {
+            credentials_provider,
+        }
+    }
+}
+
+#[async_trait]
+impl AuthenticatorProvider for RotatingPlainTextAuthenticator {
+    async fn start_authentication_session(
+        &self,
+        _authenticator_name: &str,
+    ) -> Result<(Option<Vec<u8>>, Box<dyn AuthenticatorSession>), AuthError> {
+        let (username, password) = self.credentials_provider.credentials().await?;
 
         Ok((
-            Some(response.to_vec()),
+            Some(plain_text_response(&username, &password)),
             Box::new(PlainTextAuthenticatorSession),
         ))
     }
 }
+
+fn plain_text_response(username: &str, password: &str) -> Vec<u8> {
+    let mut response = BytesMut::new();
+
+    response.put_u8(0);
+    response.put_slice(username.as_bytes());
+    response.put_u8(0);
+    response.put_slice(password.as_bytes());
+
+    response.to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+
+    use super::{
+        AuthError, AuthenticatorProvider, CredentialsProvider, RotatingPlainTextAuthenticator,
+    };
+
+    struct CountingCredentials(AtomicUsize);
+
+    #[async_trait]
+    impl CredentialsProvider for CountingCredentials {
+        async fn credentials(&self) -> Result<(String, String), AuthError> {
+            let n = self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(("user".to_string(), format!("pass{}", n)))
+        }
+    }
+
+    #[tokio::test]
+    async fn rotating_authenticator_fetches_credentials_for_each_session() {
+        let authenticator =
+            RotatingPlainTextAuthenticator::new(Arc::new(CountingCredentials(AtomicUsize::new(0))));
+
+        for expected in [b"\0user\0pass0", b"\0user\0pass1"] {
+            let (response, _session) = authenticator
+                .start_authentication_session("PasswordAuthenticator")
+                .await
+                .unwrap();
+            assert_eq!(response.as_deref(), Some(&expected[..]));
+        }
+    }
+}
@@ -0,0 +1,395 @@
+//! A mock CQL node, which lets the benchmarks measure the driver end-to-end
+//! without a real cluster.
+//!
+//! The node is a dry-mode [`scylla_proxy`] node, which handles the framing, with rules
+//! forging the responses. It pretends to be a single node cluster, without compression
+//! or authentication. It answers the queries which the driver sends when connecting,
+//! and serves a single table, `bench.kv (k int PRIMARY KEY, v blob)`: prepared `INSERT`s
+//! into it are acknowledged without storing anything, and prepared `SELECT`s from it
+//! return a fixed number of rows, configured with [`MockConfig`].
+//! Nothing is validated - the node is only as strict as the benchmarks need.
+//!
+//! The node listens on an address from the `127.0.0.0/8` range other than `127.0.0.1`,
+//! so that many of them can be started. Such addresses are available on Linux by default.
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::{BufMut, Bytes, BytesMut};
+use scylla_cql::frame::request::Request;
+use scylla_cql::frame::types;
+use scylla_proxy::{
+    Condition, Node, Proxy, Reaction, RequestFrame, RequestOpcode, RequestReaction, RequestRule,
+    ResponseFrame, ResponseOpcode,
+};
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+use crate::transport::execution_profile::ExecutionProfile;
+use crate::SessionBuilder;
+
+const TOKENS_NUM: i64 = 256;
+
+// Bounds the waits of the sessions connected to the node, so that a misbehaving node
+// fails the benchmarks instead of hanging them
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// Type ids of the column types used by the responses
+const TYPE_BLOB: u16 = 0x0003;
+const TYPE_INT: u16 = 0x0009;
+const TYPE_UUID: u16 = 0x000C;
+const TYPE_TEXT: u16 = 0x000D;
+const TYPE_INET: u16 = 0x0010;
+const TYPE_LIST: u16 = 0x0020;
+const TYPE_MAP: u16 = 0x0021;
+
+/// Configuration of the responses of a [`MockServer`].
+#[derive(Debug, Clone)]
+pub struct MockConfig {
+    /// Number of rows returned by each `SELECT` from `bench.kv`.
+    pub select_rows: usize,
+    /// Size of the `v` value of each returned row, in bytes.
+    pub value_size: usize,
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        Self {
+            select_rows: 1,
+            value_size: 100,
+        }
+    }
+}
+
+/// A running mock node. It serves connections on a separate thread
+/// until it is dropped.
+#[derive(Debug)]
+pub struct MockServer {
+    address: SocketAddr,
+    // Dropping it stops the node
+    _stop_sender: oneshot::Sender<()>,
+}
+
+impl MockServer {
+    /// Starts a node with responses configured by `config`.
+    pub fn start(config: MockConfig) -> io::Result<Self> {
+        let ip = scylla_proxy::get_exclusive_local_address();
+        let address = SocketAddr::new(ip, 9042);
+        let state = Arc::new(ServerState {
+            config,
+            ip,
+            host_id: Uuid::new_v4(),
+            prepared: Mutex::new(HashMap::new()),
+        });
+        // Only the first matching rule is applied, so this one covers all requests
+        let respond = RequestRule(
+            Condition::True,
+            RequestReaction::forge_response(Arc::new(move |frame: RequestFrame| {
+                // A panic would only stop the proxy's worker, leaving the driver waiting
+                // for the response, so it is reported to the driver instead
+                let (opcode, body) =
+                    panic::catch_unwind(AssertUnwindSafe(|| state.respond(&frame)))
+                        .unwrap_or_else(|_| error(0x0000, "Mock server failed to respond"));
+                ResponseFrame {
+                    params: frame.params.for_response(),
+                    opcode,
+                    body: body.freeze(),
+                }
+            })),
+        );
+        let proxy = Proxy::new([Node::new_dry_mode(address, Some(vec![respond]))]);
+
+        let (started_sender, started_receiver) = std::sync::mpsc::channel();
+        let (stop_sender, stop_receiver) = oneshot::channel::<()>();
+        std::thread::Builder::new()
+            .name("scylla-mock-server".to_owned())
+            .spawn(move || {
+                let runtime = match tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                {
+                    Ok(runtime) => runtime,
+                    Err(err) => {
+                        let _ = started_sender.send(Err(err));
+                        return;
+                    }
+                };
+                runtime.block_on(async move {
+                    let running_proxy = match proxy.run().await {
+                        Ok(running_proxy) => running_proxy,
+                        Err(err) => {
+                            let err = io::Error::new(io::ErrorKind::Other, err);
+                            let _ = started_sender.send(Err(err));
+                            return;
+                        }
+                    };
+                    let _ = started_sender.send(Ok(()));
+                    // Resolved when the server is dropped
+                    let _ = stop_receiver.await;
+                    let _ = running_proxy.finish().await;
+                });
+            })?;
+        started_receiver.recv().map_err(|_| {
+            io::Error::new(io::ErrorKind::Other, "Mock server thread exited early")
+        })??;
+
+        Ok(Self {
+            address,
+            _stop_sender: stop_sender,
+        })
+    }
+
+    /// Returns the address to pass as the known node of the session.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Returns a builder of sessions connected to the node, with timeouts which fail
+    /// the requests, instead of hanging them, if the node misbehaves.
+    pub fn session_builder(&self) -> SessionBuilder {
+        let profile = ExecutionProfile::builder()
+            .request_timeout(Some(TIMEOUT))
+            .build();
+        SessionBuilder::new()
+            .known_node_addr(self.address)
+            .connection_timeout(TIMEOUT)
+            .metadata_request_timeout(TIMEOUT)
+            .default_execution_profile_handle(profile.into_handle())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum PreparedKind {
+    Insert,
+    Select,
+    Other,
+}
+
+struct ServerState {
+    config: MockConfig,
+    ip: IpAddr,
+    host_id: Uuid,
+    prepared: Mutex<HashMap<Bytes, PreparedKind>>,
+}
+
+type Response = (ResponseOpcode, BytesMut);
+
+impl ServerState {
+    fn respond(&self, frame: &RequestFrame) -> Response {
+        match frame.opcode {
+            RequestOpcode::Options => {
+                let mut supported = HashMap::new();
+                supported.insert("CQL_VERSION".to_owned(), vec!["3.4.5".to_owned()]);
+                supported.insert("COMPRESSION".to_owned(), Vec::new());
+                let mut body = BytesMut::new();
+                types::write_string_multimap(&supported, &mut body).unwrap();
+                (ResponseOpcode::Supported, body)
+            }
+            RequestOpcode::Startup | RequestOpcode::Register => {
+                (ResponseOpcode::Ready, BytesMut::new())
+            }
+            RequestOpcode::Prepare => {
+                let statement = types::read_long_string(&mut &frame.body[..]).unwrap();
+                self.prepared_result(statement)
+            }
+            RequestOpcode::Query | RequestOpcode::Execute | RequestOpcode::Batch => {
+                match frame.deserialize().unwrap() {
+                    Request::Query(query) => self.query_result(&query.contents),
+                    Request::Execute(execute) => {
+                        let kind = self.prepared.lock().unwrap().get(&execute.id).copied();
+                        match kind {
+                            Some(PreparedKind::Select) => self.select_result(),
+                            Some(PreparedKind::Insert | PreparedKind::Other) => void_result(),
+                            None => error(0x2200, "Prepared statement not found"),
+                        }
+                    }
+                    _ => void_result(),
+                }
+            }
+            _ => error(0x000A, "Unsupported request"),
+        }
+    }
+
+    fn query_result(&self, statement: &str) -> Response {
+        let statement = statement.to_ascii_lowercase();
+        if statement.contains("schema_version") {
+            return rows_result(
+                ("system", "local"),
+                &[("schema_version", &[TYPE_UUID])],
+                &[vec![Some(self.host_id.as_bytes().to_vec())]],
+            );
+        }
+        if statement.contains("system.local") || statement.contains("system.peers") {
+            let columns: &[(&str, &[u16])] = &[
+                ("host_id", &[TYPE_UUID]),
+                ("rpc_address", &[TYPE_INET]),
+                ("data_center", &[TYPE_TEXT]),
+                ("rack", &[TYPE_TEXT]),
+                ("tokens", &[TYPE_LIST, TYPE_TEXT]),
+            ];
+            // There are no other nodes
+            let rows = if statement.contains("system.local") {
+                vec![self.local_row()]
+            } else {
+                Vec::new()
+            };
+            return rows_result(("system", "local"), columns, &rows);
+        }
+        if statement.contains("system_schema.keyspaces") {
+            let replication = [
+                ("class", "org.apache.cassandra.locator.SimpleStrategy"),
+                ("replication_factor", "1"),
+            ];
+            let mut map = Vec::new();
+            types::write_int(replication.len() as i32, &mut map);
+            for (key, value) in replication {
+                types::write_bytes(key.as_bytes(), &mut map).unwrap();
+                types::write_bytes(value.as_bytes(), &mut map).unwrap();
+            }
+            return rows_result(
+                ("system_schema", "keyspaces"),
+                &[
+                    ("keyspace_name", &[TYPE_TEXT]),
+                    ("replication", &[TYPE_MAP, TYPE_TEXT, TYPE_TEXT]),
+                ],
+                &[vec![Some(b"bench".to_vec()), Some(map)]],
+            );
+        }
+        if statement.starts_with("select") {
+            // Other schema tables are empty
+            return rows_result(("system_schema", "unknown"), &[], &[]);
+        }
+        void_result()
+    }
+
+    fn local_row(&self) -> Vec<Option<Vec<u8>>> {
+        // Tokens evenly distributed over the ring. They are computed in i128,
+        // as the distance between the first token and the last ones doesn't fit in i64.
+        let step = u64::MAX as i128 / TOKENS_NUM as i128;
+        let mut tokens = Vec::new();
+        types::write_int(TOKENS_NUM as i32, &mut tokens);
+        for i in 0..TOKENS_NUM as i128 {
+            let token = (i64::MIN as i128 + i * step).to_string();
+            types::write_bytes(token.as_bytes(), &mut tokens).unwrap();
+        }
+
+        vec![
+            Some(self.host_id.as_bytes().to_vec()),
+            Some(match self.ip {
+                IpAddr::V4(ip) => ip.octets().to_vec(),
+                IpAddr::V6(ip) => ip.octets().to_vec(),
+            }),
+            Some(b"datacenter1".to_vec()),
+            Some(b"rack1".to_vec()),
+            Some(tokens),
+        ]
+    }
+
+    fn prepared_result(&self, statement: &str) -> Response {
+        let lowercase = statement.to_ascii_lowercase();
+        let (kind, bind_markers, result_columns): (_, &[&str], &[&str]) =
+            if lowercase.starts_with("insert") {
+                (PreparedKind::Insert, &["k", "v"], &[])
+            } else if lowercase.starts_with("select") {
+                (PreparedKind::Select, &["k"], &["k", "v"])
+            } else {
+                (PreparedKind::Other, &[], &[])
+            };
+        let id = Bytes::copy_from_slice(Uuid::new_v4().as_bytes());
+        self.prepared.lock().unwrap().insert(id.clone(), kind);
+
+        let mut body = BytesMut::new();
+        types::write_int(0x0004, &mut body);
+        types::write_short_bytes(&id, &mut body).unwrap();
+
+        // Prepared metadata, with `k` as the partition key
+        types::write_int(0x0001, &mut body);
+        types::write_int(bind_markers.len() as i32, &mut body);
+        let pk_indexes: &[u16] = if bind_markers.is_empty() { &[] } else { &[0] };
+        types::write_int(pk_indexes.len() as i32, &mut body);
+        for index in pk_indexes {
+            types::write_short(*index, &mut body);
+        }
+        write_table_spec(("bench", "kv"), &mut body);
+        for column in bind_markers {
+            write_kv_column(column, &mut body);
+        }
+
+        // Result metadata
+        types::write_int(0x0001, &mut body);
+        types::write_int(result_columns.len() as i32, &mut body);
+        write_table_spec(("bench", "kv"), &mut body);
+        for column in result_columns {
+            write_kv_column(column, &mut body);
+        }
+
+        (ResponseOpcode::Result, body)
+    }
+
+    fn select_result(&self) -> Response {
+        let value = vec![0xCD; self.config.value_size];
+        let rows: Vec<_> = (0..self.config.select_rows as i32)
+            .map(|k| vec![Some(k.to_be_bytes().to_vec()), Some(value.clone())])
+            .collect();
+        rows_result(
+            ("bench", "kv"),
+            &[("k", &[TYPE_INT]), ("v", &[TYPE_BLOB])],
+            &rows,
+        )
+    }
+}
+
+fn write_table_spec((keyspace, table): (&str, &str), buf: &mut impl BufMut) {
+    types::write_string(keyspace, buf).unwrap();
+    types::write_string(table, buf).unwrap();
+}
+
+fn write_kv_column(name: &str, buf: &mut impl BufMut) {
+    types::write_string(name, buf).unwrap();
+    types::write_short(if name == "k" { TYPE_INT } else { TYPE_BLOB }, buf);
+}
+
+// Column types are given as the sequence of the type ids of a type and its parameters
+fn rows_result(
+    table: (&str, &str),
+    columns: &[(&str, &[u16])],
+    rows: &[Vec<Option<Vec<u8>>>],
+) -> Response {
+    let mut body = BytesMut::new();
+    types::write_int(0x0002, &mut body);
+    types::write_int(0x0001, &mut body);
+    types::write_int(columns.len() as i32, &mut body);
+    write_table_spec(table, &mut body);
+    for (name, type_ids) in columns {
+        types::write_string(name, &mut body).unwrap();
+        for type_id in *type_ids {
+            types::write_short(*type_id, &mut body);
+        }
+    }
+
+    types::write_int(rows.len() as i32, &mut body);
+    for row in rows {
+        for cell in row {
+            types::write_bytes_opt(cell.as_deref(), &mut body).unwrap();
+        }
+    }
+
+    (ResponseOpcode::Result, body)
+}
+
+fn void_result() -> Response {
+    let mut body = BytesMut::new();
+    types::write_int(0x0001, &mut body);
+    (ResponseOpcode::Result, body)
+}
+
+fn error(code: i32, message: &str) -> Response {
+    let mut body = BytesMut::new();
+    types::write_int(code, &mut body);
+    types::write_string(message, &mut body).unwrap();
+    (ResponseOpcode::Error, body)
+}
@@ -0,0 +1,195 @@
+//! End-to-end benchmarks of the driver, against a mock server.
+//!
+//! [`run`] starts a [`MockServer`], connects a session to it and measures the throughput
+//! of prepared inserts and selects. The server answers immediately, so the results show
+//! the overhead of the driver itself: serialization, routing, frame encoding, the connection's
+//! write coalescing and the deserialization of rows. They can be compared between versions
+//! of the driver to catch performance regressions, without maintaining a separate harness.
+//!
+//! The same benchmarks are measured with criterion by
+//! `cargo bench -p scylla --features benchmarks --bench session`.
+//!
+//! This module is available with the `benchmarks` feature.
+//!
+//! # Example
+//! ```rust
+//! # use std::error::Error;
+//! # async fn check_only_compiles() -> Result<(), Box<dyn Error>> {
+//! use scylla::benchmarks::{self, BenchmarkConfig};
+//!
+//! let config = BenchmarkConfig {
+//!     requests: 100_000,
+//!     ..Default::default()
+//! };
+//! for result in benchmarks::run(&config).await? {
+//!     println!("{}: {:.0} requests/s", result.name, result.requests_per_second());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod mock_server;
+
+use std::future::Future;
+use std::io;
+use std::time::{Duration, Instant};
+
+use futures::{StreamExt, TryStreamExt};
+use thiserror::Error;
+
+use crate::frame::response::cql_to_rust::FromRowError;
+use crate::transport::errors::{NewSessionError, QueryError};
+use crate::transport::query_result::RowsExpectedError;
+
+pub use mock_server::{MockConfig, MockServer};
+
+/// Statement inserting a row into the table served by the [`MockServer`].
+pub const INSERT: &str = "INSERT INTO bench.kv (k, v) VALUES (?, ?)";
+/// Statement selecting the rows of a partition of the table served by the [`MockServer`].
+pub const SELECT: &str = "SELECT k, v FROM bench.kv WHERE k = ?";
+
+/// Parameters of a run of the benchmarks.
+#[derive(Debug, Clone)]
+pub struct BenchmarkConfig {
+    /// Number of executions of each benchmarked statement.
+    pub requests: u64,
+    /// Maximum number of executions in flight at once.
+    pub concurrency: usize,
+    /// Size of the inserted and selected values, in bytes.
+    pub value_size: usize,
+    /// Number of rows returned by each select.
+    pub select_rows: usize,
+    /// Whether the session coalesces the writes to its connections.
+    pub write_coalescing: bool,
+}
+
+impl Default for BenchmarkConfig {
+    fn default() -> Self {
+        Self {
+            requests: 10_000,
+            concurrency: 256,
+            value_size: 100,
+            select_rows: 1,
+            write_coalescing: true,
+        }
+    }
+}
+
+/// The measurement of a single benchmark.
+#[derive(Debug, Clone)]
+pub struct BenchmarkResult {
+    /// Name of the benchmark, `"insert"` or `"select"`.
+    pub name: &'static str,
+    /// Number of executed requests.
+    pub requests: u64,
+    /// Time in which all the requests were executed.
+    pub elapsed: Duration,
+}
+
+impl BenchmarkResult {
+    /// Returns the number of requests executed per second.
+    pub fn requests_per_second(&self) -> f64 {
+        self.requests as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+/// An error which stopped a run of the benchmarks.
+#[derive(Error, Debug)]
+pub enum BenchmarkError {
+    #[error("Could not start the mock server: {0}")]
+    MockServer(#[from] io::Error),
+    #[error("Could not connect to the mock server: {0}")]
+    NewSession(#[from] NewSessionError),
+    #[error("Request failed: {0}")]
+    Query(#[from] QueryError),
+    #[error("Select returned no rows: {0}")]
+    RowsExpected(#[from] RowsExpectedError),
+    #[error("Could not deserialize a selected row: {0}")]
+    FromRow(#[from] FromRowError),
+}
+
+/// Runs the benchmarks against a new [`MockServer`] and returns their results,
+/// in the order: inserts, selects.
+///
+/// Must be called within a Tokio runtime. The mock server runs on its own thread.
+pub async fn run(config: &BenchmarkConfig) -> Result<Vec<BenchmarkResult>, BenchmarkError> {
+    let server = MockServer::start(MockConfig {
+        select_rows: config.select_rows,
+        value_size: config.value_size,
+    })?;
+    let session = server
+        .session_builder()
+        .write_coalescing(config.write_coalescing)
+        .build()
+        .await?;
+    let insert = session.prepare(INSERT).await?;
+    let select = session.prepare(SELECT).await?;
+    let value = vec![0xCD_u8; config.value_size];
+
+    let insert_result = measure("insert", config, |k| {
+        let (session, insert, value) = (&session, &insert, &value);
+        async move {
+            session.execute(insert, (k, value)).await?;
+            Ok(())
+        }
+    })
+    .await?;
+    let select_result = measure("select", config, |k| {
+        let (session, select) = (&session, &select);
+        async move {
+            let result = session.execute(select, (k,)).await?;
+            for row in result.rows_typed::<(i32, Vec<u8>)>()? {
+                row?;
+            }
+            Ok(())
+        }
+    })
+    .await?;
+
+    Ok(vec![insert_result, select_result])
+}
+
+// Executes the requests with up to `config.concurrency` of them in flight.
+async fn measure<F, Fut>(
+    name: &'static str,
+    config: &BenchmarkConfig,
+    execute: F,
+) -> Result<BenchmarkResult, BenchmarkError>
+where
+    F: Fn(i32) -> Fut,
+    Fut: Future<Output = Result<(), BenchmarkError>>,
+{
+    let start = Instant::now();
+    futures::stream::iter(0..config.requests)
+        .map(|k| execute(k as i32))
+        .buffer_unordered(config.concurrency.max(1))
+        .try_for_each(|()| futures::future::ready(Ok(())))
+        .await?;
+    Ok(BenchmarkResult {
+        name,
+        requests: config.requests,
+        elapsed: start.elapsed(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run, BenchmarkConfig};
+
+    #[tokio::test]
+    async fn runs_against_mock_server() {
+        let config = BenchmarkConfig {
+            requests: 50,
+            concurrency: 8,
+            select_rows: 3,
+            ..Default::default()
+        };
+        let results = run(&config).await.unwrap();
+        let names: Vec<_> = results.iter().map(|result| result.name).collect();
+        assert_eq!(names, ["insert", "select"]);
+        for result in results {
+            assert_eq!(result.requests, 50);
+            assert!(result.requests_per_second() > 0.0);
+        }
+    }
+}
@@ -469,7 +469,7 @@ mod tests {
     use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
     use futures::StreamExt;
     use scylla_cql::{
-        errors::{DbError, QueryError},
+        errors::{ConnectionError, DbError, QueryError, TimeoutError},
         Consistency,
     };
 
@@ -535,7 +535,7 @@ mod tests {
     // Setting it to one value makes it possible to run tests consistently.
     fn set_one_db_error_message(mut history: StructuredHistory) -> StructuredHistory {
         let set_msg = |err: &mut QueryError| {
-            if let QueryError::DbError(_, msg) = err {
+            if let QueryError::DbError { message: msg, .. } = err {
                 *msg = "Error message from database".to_string();
             }
         };
@@ -571,22 +571,23 @@ mod tests {
     }
 
     fn timeout_error() -> QueryError {
-        QueryError::TimeoutError
+        TimeoutError::Connect.into()
     }
 
     fn unavailable_error() -> QueryError {
-        QueryError::DbError(
-            DbError::Unavailable {
+        QueryError::DbError {
+            error: DbError::Unavailable {
                 consistency: Consistency::Quorum,
                 required: 2,
                 alive: 1,
             },
-            "Not enough nodes to satisfy consistency".to_string(),
-        )
+            message: "Not enough nodes to satisfy consistency".to_string(),
+            node: None,
+        }
     }
 
     fn no_stream_id_error() -> QueryError {
-        QueryError::UnableToAllocStreamId
+        ConnectionError::UnableToAllocStreamId.into()
     }
 
     #[test]
@@ -673,7 +674,7 @@ mod tests {
             history_collector.log_attempt_start(query_id, None, node1_addr());
         history_collector.log_attempt_error(
             attempt_id,
-            &QueryError::TimeoutError,
+            &timeout_error(),
             &RetryDecision::RetrySameNode(Some(Consistency::Quorum)),
         );
 
@@ -697,7 +698,7 @@ mod tests {
 | - Attempt #0 sent to 127.0.0.1:19042
 |   request send time: 2022-02-22 20:22:22 UTC
 |   Error at 2022-02-22 20:22:22 UTC
-|   Error: Timeout Error
+|   Error: Connect timeout
 |   Retry decision: RetrySameNode(Some(Quorum))
 |
 | - Attempt #1 sent to 127.0.0.1:19042
@@ -814,7 +815,7 @@ mod tests {
 | - Attempt #0 sent to 127.0.0.1:19042
 |   request send time: 2022-02-22 20:22:22 UTC
 |   Error at 2022-02-22 20:22:22 UTC
-|   Error: Timeout Error
+|   Error: Connect timeout
 |   Retry decision: RetryNextNode(Some(Quorum))
 |
 | - Attempt #1 sent to 127.0.0.3:19042
@@ -893,7 +894,7 @@ mod tests {
 | - Attempt #0 sent to 127.0.0.1:19042
 |   request send time: 2022-02-22 20:22:22 UTC
 |   Error at 2022-02-22 20:22:22 UTC
-|   Error: Timeout Error
+|   Error: Connect timeout
 |   Retry decision: RetryNextNode(Some(Quorum))
 |
 | - Attempt #1 sent to 127.0.0.2:19042
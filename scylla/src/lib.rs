@@ -96,6 +96,17 @@
 #[doc(hidden)]
 pub mod _macro_internal {
     pub use scylla_cql::_macro_internal::*;
+
+    pub use crate::frame::value::{
+        Counter, CqlDate, CqlDecimal, CqlDuration, CqlTime, CqlTimestamp, CqlTimeuuid, CqlVarint,
+    };
+    pub use crate::statement::prepared_statement::PreparedStatement;
+    pub use crate::statement::typed::check_typed_statement;
+    pub use crate::transport::errors::QueryError;
+    pub use crate::transport::iterator::TypedRowIterator;
+    pub use crate::transport::query_result::QueryResult;
+    pub use crate::transport::session::{Session, TypedRowIter};
+    pub use uuid::Uuid;
 }
 
 pub mod macros;
@@ -103,10 +114,11 @@ pub mod macros;
 pub use macros::*;
 
 pub mod frame {
-    pub use scylla_cql::frame::{frame_errors, value, Authenticator, Compression};
+    pub use scylla_cql::frame::{frame_errors, validation, value, Authenticator, Compression};
     pub(crate) use scylla_cql::frame::{
-        parse_response_body_extensions, protocol_features, read_response_frame, request,
-        server_event_type, FrameParams, SerializedRequest,
+        parse_response_body_extensions, protocol_features, read_response_frame_body,
+        read_response_frame_header, request, server_event_type, skip_response_frame_body,
+        FrameParams, SerializedRequest,
     };
 
     pub mod types {
@@ -131,6 +143,11 @@ pub mod serialize {
     pub use scylla_cql::types::serialize::*;
 }
 
+/// Client-side encryption of column values.
+pub mod column_encryption {
+    pub use scylla_cql::types::column_encryption::*;
+}
+
 /// Deserializing DB response containing CQL query results.
 pub mod deserialize {
     pub use scylla_cql::types::deserialize::{
@@ -153,8 +170,9 @@ pub mod deserialize {
     /// Deserializing a single CQL value from a column of the query result row.
     pub mod value {
         pub use scylla_cql::types::deserialize::value::{
-            BuiltinDeserializationError, BuiltinDeserializationErrorKind, BuiltinTypeCheckError,
-            BuiltinTypeCheckErrorKind, Emptiable, ListlikeIterator, MapDeserializationErrorKind,
+            deserialize_cql_collection, type_check_cql_collection, BuiltinDeserializationError,
+            BuiltinDeserializationErrorKind, BuiltinTypeCheckError, BuiltinTypeCheckErrorKind,
+            Emptiable, FromCqlCollection, ListlikeIterator, MapDeserializationErrorKind,
             MapIterator, MapTypeCheckErrorKind, MaybeEmpty, SetOrListDeserializationErrorKind,
             SetOrListTypeCheckErrorKind, TupleDeserializationErrorKind, TupleTypeCheckErrorKind,
             UdtIterator, UdtTypeCheckErrorKind,
@@ -163,6 +181,9 @@ pub mod deserialize {
 }
 
 pub mod authentication;
+// Also built for the unit tests, which use its mock server
+#[cfg(all(any(test, feature = "benchmarks"), not(target_family = "wasm")))]
+pub mod benchmarks;
 #[cfg(feature = "cloud")]
 pub mod cloud;
 
@@ -201,4 +222,4 @@ pub use transport::load_balancing;
 pub use transport::retry_policy;
 pub use transport::speculative_execution;
 
-pub use transport::metrics::Metrics;
+pub use transport::metrics::{Metrics, TableMetrics};
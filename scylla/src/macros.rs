@@ -122,6 +122,14 @@ pub use scylla_cql::macros::IntoUserType;
 /// Forces Rust struct to have all the fields present in UDT, otherwise
 /// serialization fails.
 ///
+/// `#[scylla(transparent_tuple)]`
+///
+/// Serializes the struct as a CQL tuple instead of a UDT. The i-th non-skipped
+/// field of the Rust struct is serialized as the i-th element of the tuple,
+/// and the tuple type must have exactly as many elements as there are such
+/// fields. The `flavor` attribute has no effect in this mode, and neither
+/// `skip_name_checks`, `force_exact_match` nor `rename` are allowed.
+///
 /// # Field attributes
 ///
 /// `#[scylla(rename = "name_in_the_udt")]`
@@ -329,6 +337,15 @@ pub use scylla_cql::macros::SerializeRow;
 /// If more strictness is desired, this flag makes sure that no excess fields
 /// are present and forces error in case there are some.
 ///
+/// `#[scylla(transparent_tuple)]`
+///
+/// Deserializes the struct from a CQL tuple instead of a UDT. The i-th element
+/// of the tuple is deserialized into the i-th non-skipped field of the Rust
+/// struct, and the tuple type must have exactly as many elements as there are
+/// such fields. This attribute cannot be combined with `enforce_order`,
+/// `skip_name_checks` or `forbid_excess_udt_fields`, nor with the `rename`
+/// and `allow_missing` field attributes.
+///
 /// ## Field attributes
 ///
 /// `#[scylla(skip)]`
@@ -462,6 +479,87 @@ pub use scylla_macros::DeserializeValue;
 /// with provided name.
 pub use scylla_macros::DeserializeRow;
 
+/// Attribute macro generating a typed wrapper of a prepared statement,
+/// whose types are checked at compile time against a schema dump.
+///
+/// The macro is put on a unit struct, and takes the path to a file with the schema,
+/// e.g. the output of `DESCRIBE SCHEMA` in cqlsh, and the statement:
+///
+/// ```text
+/// CREATE TABLE ks.users (id uuid, bucket int, name text, emails set<text>, PRIMARY KEY ((id, bucket), name));
+/// ```
+///
+/// ```rust,ignore
+/// #[scylla::macros::prepared(
+///     schema = "schema.cql",
+///     statement = "SELECT name, emails FROM ks.users WHERE id = ? AND bucket = ?"
+/// )]
+/// struct SelectUsers;
+///
+/// let select = SelectUsers::prepare(&session).await?;
+/// for row in select.execute(&session, (id, 0)).await? {
+///     let SelectUsersRow { name, emails } = row?;
+/// }
+/// ```
+///
+/// The types of the bind markers and of the selected columns are taken from
+/// the `CREATE TABLE` statements of the schema. The struct gets:
+/// - `prepare(&Session)`, which prepares the statement. It fails if the types reported
+///   by the server differ from the ones in the schema, i.e. if the schema dump is outdated,
+/// - `execute(&Session, values)`, where `values` is a tuple of the types of the bind markers.
+///   It returns a [`QueryResult`](crate::QueryResult), or for a `SELECT` an iterator
+///   of typed rows,
+/// - for a `SELECT`, `execute_iter(&Session, values)`, which returns
+///   a [`TypedRowIterator`](crate::transport::iterator::TypedRowIterator) fetching
+///   the rows page by page,
+/// - `get_prepared()` and `get_prepared_mut()`, which return the underlying
+///   [`PreparedStatement`](crate::prepared_statement::PreparedStatement).
+///
+/// For a `SELECT`, the macro also defines a struct with a public field per selected column,
+/// named `<struct name>Row`, deriving [`FromRow`]. Fields of columns outside of the primary
+/// key are `Option`s, as those columns can be null. So are the fields of clustering columns
+/// of tables with static columns, which are null in partitions with only static columns set.
+///
+/// The CQL types are mapped to Rust types as follows:
+/// - `ascii`, `text` and `varchar` to `String` (`&str` when bound),
+/// - `blob` to `Vec<u8>` (`&[u8]` when bound),
+/// - `boolean`, `tinyint`, `smallint`, `int`, `bigint`, `float` and `double`
+///   to `bool`, `i8`, `i16`, `i32`, `i64`, `f32` and `f64`,
+/// - `counter`, `date`, `decimal`, `duration`, `time`, `timestamp`, `timeuuid` and `varint`
+///   to the types of the [`value`](crate::frame::value) module,
+/// - `uuid` to `uuid::Uuid` and `inet` to `std::net::IpAddr`,
+/// - `list` and `set` to `Vec` (a slice when bound), `map` to `HashMap` and tuples to tuples.
+///   Maps with keys of types which can't be keys of a `HashMap`, e.g. `double` or `timestamp`,
+///   are not supported.
+///
+/// Values assigned to regular and static columns in `INSERT` and `UPDATE` statements are `Option`s,
+/// so that `None` sets the column to null.
+///
+/// Only the statements whose types can be determined without ambiguity are supported:
+/// `SELECT` of explicitly listed columns (`SELECT *` and function calls are not supported),
+/// `INSERT`, `UPDATE` and `DELETE` with positional bind markers. Other statements,
+/// and user defined types, are rejected at compile time.
+///
+/// # Attributes
+///
+/// `schema = "path"`
+///
+/// Path to the schema dump, relative to the directory of the crate's `Cargo.toml`.
+/// Tables used without a keyspace are looked up by name in all keyspaces.
+///
+/// `statement = "..."`
+///
+/// The CQL statement.
+///
+/// `row = Name`
+///
+/// Name of the generated row struct.
+///
+/// `crate = crate_name`
+///
+/// Path to the `scylla` crate, if it's imported under a different name.
+pub use scylla_macros::prepared;
+
 /// #[derive(ValueList)] allows to pass struct as a list of values for a query
 ///
 /// ---
@@ -470,5 +568,7 @@ pub use scylla_cql::macros::ValueList;
 
 pub use scylla_cql::macros::impl_from_cql_value_from_method;
 
+pub use scylla_cql::macros::impl_deserialize_value_via_from_cql_collection;
+
 // Reexports for derive(IntoUserType)
 pub use bytes::{BufMut, Bytes, BytesMut};
@@ -1,13 +1,17 @@
+use bytes::Bytes;
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use crate::history::HistoryListener;
 use crate::retry_policy::RetryPolicy;
 use crate::statement::{prepared_statement::PreparedStatement, query::Query};
+use crate::transport::cancellation::CancelHandle;
+use crate::transport::execution_info::ExecutionInfoCollector;
 use crate::transport::execution_profile::ExecutionProfileHandle;
 
-use super::StatementConfig;
 use super::{Consistency, SerialConsistency};
+use super::{Priority, StatementConfig};
 pub use crate::frame::request::batch::BatchType;
 
 /// CQL batch statement.
@@ -109,6 +113,30 @@ impl Batch {
         self.config.tracing
     }
 
+    /// Enable or disable collecting information about the attempts, retries and speculative
+    /// executions of this batch. If enabled, the returned [`QueryResult`](crate::QueryResult)
+    /// contains the [`ExecutionInfo`](crate::transport::execution_info::ExecutionInfo).
+    pub fn set_collect_execution_info(&mut self, collect: bool) {
+        self.config.collect_execution_info = collect;
+    }
+
+    /// Gets whether collecting execution info is enabled for this batch
+    pub fn get_collect_execution_info(&self) -> bool {
+        self.config.collect_execution_info
+    }
+
+    /// Attaches a collector which receives the
+    /// [`ExecutionInfo`](crate::transport::execution_info::ExecutionInfo) of the requests
+    /// of this batch, including the failed ones. See [`ExecutionInfoCollector`] for details.
+    pub fn set_execution_info_collector(&mut self, collector: Option<ExecutionInfoCollector>) {
+        self.config.execution_info_collector = collector;
+    }
+
+    /// Gets the execution info collector attached to this batch.
+    pub fn get_execution_info_collector(&self) -> Option<&ExecutionInfoCollector> {
+        self.config.execution_info_collector.as_ref()
+    }
+
     /// Sets the default timestamp for this batch in microseconds.
     /// If not None, it will replace the server side assigned timestamp as default timestamp for
     /// all the statements contained in the batch.
@@ -121,6 +149,19 @@ impl Batch {
         self.config.timestamp
     }
 
+    /// Sets the custom payload to be sent along with this batch.
+    /// Custom payload is a map of arbitrary key-value pairs, which is passed
+    /// to the server in the request frame and can be consumed by server-side
+    /// extensions. Pass `None` to stop sending it.
+    pub fn set_custom_payload(&mut self, custom_payload: Option<HashMap<String, Bytes>>) {
+        self.config.custom_payload = custom_payload.map(Arc::new);
+    }
+
+    /// Gets the custom payload to be sent along with this batch.
+    pub fn get_custom_payload(&self) -> Option<&HashMap<String, Bytes>> {
+        self.config.custom_payload.as_deref()
+    }
+
     /// Set the retry policy for this batch, overriding the one from execution profile if not None.
     #[inline]
     pub fn set_retry_policy(&mut self, retry_policy: Option<Arc<dyn RetryPolicy>>) {
@@ -143,6 +184,29 @@ impl Batch {
         self.config.history_listener.take()
     }
 
+    /// Attaches a handle which cancels the requests of this batch when cancelled.
+    /// See [`CancelHandle`] for details.
+    pub fn set_cancel_handle(&mut self, cancel_handle: Option<CancelHandle>) {
+        self.config.cancel_handle = cancel_handle;
+    }
+
+    /// Gets the cancel handle attached to this batch.
+    pub fn get_cancel_handle(&self) -> Option<&CancelHandle> {
+        self.config.cancel_handle.as_ref()
+    }
+
+    /// Sets the priority of this batch, which determines the order in which
+    /// requests are shed when the session is saturated.
+    /// See [`LoadSheddingPolicy`](crate::transport::load_shedding::LoadSheddingPolicy) for details.
+    pub fn set_priority(&mut self, priority: Priority) {
+        self.config.priority = priority;
+    }
+
+    /// Gets the priority of this batch.
+    pub fn get_priority(&self) -> Priority {
+        self.config.priority
+    }
+
     /// Associates the batch with execution profile referred by the provided handle.
     /// Handle may be later remapped to another profile, and batch will reflect those changes.
     pub fn set_execution_profile_handle(&mut self, profile_handle: Option<ExecutionProfileHandle>) {
@@ -239,20 +303,26 @@ pub(crate) mod batch_values {
         let mut values_iter = values.batch_values_iter();
         let (token, first_values) = match statement {
             Some(BatchStatement::PreparedStatement(ps)) => {
-                let ctx = RowSerializationContext::from_prepared(ps.get_prepared_metadata());
+                let ctx = ps.serialization_context();
                 let (first_values, did_write) = SerializedValues::from_closure(|writer| {
-                    values_iter
-                        .serialize_next(&ctx, writer)
-                        .transpose()
-                        .map(|o| o.is_some())
+                    ctx.serialize_row(writer, |ctx, writer| {
+                        values_iter
+                            .serialize_next(ctx, writer)
+                            .transpose()
+                            .map(|o| o.is_some())
+                    })
                 })?;
                 if did_write {
                     let token = ps.calculate_token_untyped(&first_values)?;
-                    (token, Some(first_values))
+                    // Encrypted values can't be cached, as they would be
+                    // encrypted again when the batch is serialized.
+                    let first_values = ps.get_column_encryption().is_none().then_some(first_values);
+                    (token, first_values)
                 } else {
                     (None, None)
                 }
             }
+            Some(BatchStatement::Query(q)) => (q.get_routing_token(), None),
             _ => (None, None),
         };
 
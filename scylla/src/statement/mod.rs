@@ -1,11 +1,16 @@
-use std::{sync::Arc, time::Duration};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
+use bytes::Bytes;
+
+use crate::transport::cancellation::CancelHandle;
+use crate::transport::execution_info::ExecutionInfoCollector;
 use crate::transport::execution_profile::ExecutionProfileHandle;
 use crate::{history::HistoryListener, retry_policy::RetryPolicy};
 
 pub mod batch;
 pub mod prepared_statement;
 pub mod query;
+pub(crate) mod typed;
 
 pub use crate::frame::types::{Consistency, SerialConsistency};
 
@@ -18,13 +23,34 @@ pub(crate) struct StatementConfig {
 
     pub(crate) skip_result_metadata: bool,
     pub(crate) tracing: bool,
+    pub(crate) collect_execution_info: bool,
+    pub(crate) execution_info_collector: Option<ExecutionInfoCollector>,
     pub(crate) timestamp: Option<i64>,
     pub(crate) request_timeout: Option<Duration>,
+    pub(crate) custom_payload: Option<Arc<HashMap<String, Bytes>>>,
 
     pub(crate) history_listener: Option<Arc<dyn HistoryListener>>,
 
     pub(crate) execution_profile_handle: Option<ExecutionProfileHandle>,
     pub(crate) retry_policy: Option<Arc<dyn RetryPolicy>>,
+
+    pub(crate) cancel_handle: Option<CancelHandle>,
+
+    pub(crate) priority: Priority,
+}
+
+/// Priority of a statement, used by the
+/// [`LoadSheddingPolicy`](crate::transport::load_shedding::LoadSheddingPolicy)
+/// to decide which requests to shed first when the session is saturated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Priority {
+    /// Shed first, e.g. background jobs which can be delayed.
+    Low,
+    /// The default priority.
+    #[default]
+    Normal,
+    /// Shed last, allowed to use the whole capacity of the session.
+    High,
 }
 
 impl StatementConfig {
@@ -4,21 +4,26 @@ use scylla_cql::frame::response::result::{
     ColumnSpec, PartitionKeyIndex, ResultMetadata, TableSpec,
 };
 use scylla_cql::frame::types::RawValue;
+use scylla_cql::types::column_encryption::BindMarkersEncryption;
 use scylla_cql::types::serialize::row::{RowSerializationContext, SerializeRow, SerializedValues};
 use scylla_cql::types::serialize::SerializationError;
 use smallvec::{smallvec, SmallVec};
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::sync::Arc;
 use std::time::Duration;
 use thiserror::Error;
 use uuid::Uuid;
 
-use super::StatementConfig;
+use super::query::Query;
+use super::{Priority, StatementConfig};
 use crate::frame::response::result::PreparedMetadata;
 use crate::frame::types::{Consistency, SerialConsistency};
 use crate::history::HistoryListener;
 use crate::retry_policy::RetryPolicy;
 use crate::routing::Token;
+use crate::transport::cancellation::CancelHandle;
+use crate::transport::execution_info::ExecutionInfoCollector;
 use crate::transport::execution_profile::ExecutionProfileHandle;
 use crate::transport::partitioner::{Partitioner, PartitionerHasher, PartitionerName};
 
@@ -95,6 +100,8 @@ pub struct PreparedStatement {
     page_size: Option<i32>,
     partitioner_name: PartitionerName,
     is_confirmed_lwt: bool,
+    routing_key: Option<Bytes>,
+    column_encryption: Option<Arc<BindMarkersEncryption>>,
 }
 
 #[derive(Debug)]
@@ -114,6 +121,8 @@ impl Clone for PreparedStatement {
             page_size: self.page_size,
             partitioner_name: self.partitioner_name.clone(),
             is_confirmed_lwt: self.is_confirmed_lwt,
+            routing_key: self.routing_key.clone(),
+            column_encryption: self.column_encryption.clone(),
         }
     }
 }
@@ -140,9 +149,20 @@ impl PreparedStatement {
             config,
             partitioner_name: Default::default(),
             is_confirmed_lwt: is_lwt,
+            routing_key: None,
+            column_encryption: None,
         }
     }
 
+    /// Returns a copy of this statement with the settings of `query`,
+    /// as if it was prepared from `query` itself.
+    pub(crate) fn with_settings_of(&self, query: &Query) -> Self {
+        let mut statement = self.clone();
+        statement.config = query.config.clone();
+        statement.page_size = query.get_page_size();
+        statement
+    }
+
     pub fn get_id(&self) -> &Bytes {
         &self.id
     }
@@ -176,7 +196,7 @@ impl PreparedStatement {
     /// to be routed in a token-aware manner. If false, the query
     /// will always be sent to a random node/shard.
     pub fn is_token_aware(&self) -> bool {
-        !self.get_prepared_metadata().pk_indexes.is_empty()
+        self.routing_key.is_some() || !self.get_prepared_metadata().pk_indexes.is_empty()
     }
 
     /// Returns true if it is known that the prepared statement contains
@@ -213,6 +233,32 @@ impl PreparedStatement {
         Ok(buf.freeze())
     }
 
+    /// Sets the routing key of this statement, i.e. the encoded partition key the token
+    /// for token-aware routing is computed from, in the format returned by
+    /// [Self::compute_partition_key()].
+    ///
+    /// If set, the routing key is used instead of the partition key values bound to
+    /// the statement. This allows routing statements whose partition key is not bound
+    /// to markers, e.g. `SELECT * FROM ks.t WHERE pk = 1 AND ck = ?`, to the replicas
+    /// owning the data. Pass `None` to compute the partition key from the bound values again.
+    pub fn set_routing_key(&mut self, routing_key: Option<Bytes>) {
+        self.routing_key = routing_key;
+    }
+
+    /// Gets the routing key set with [Self::set_routing_key()].
+    pub fn get_routing_key(&self) -> Option<&Bytes> {
+        self.routing_key.as_ref()
+    }
+
+    /// Calculates the token of the routing key set by the user, if any.
+    pub(crate) fn calculate_routing_key_token(&self) -> Option<Token> {
+        self.routing_key.as_ref().map(|routing_key| {
+            let mut partitioner_hasher = self.partitioner_name.build_hasher();
+            partitioner_hasher.write(routing_key);
+            partitioner_hasher.finish()
+        })
+    }
+
     /// Determines which values constitute the partition key and puts them in order.
     ///
     /// This is a preparation step necessary for calculating token based on a prepared statement.
@@ -253,7 +299,8 @@ impl PreparedStatement {
     /// Calculates the token for given prepared statement and values.
     ///
     /// Returns the token that would be computed for executing the provided
-    /// prepared statement with the provided values.
+    /// prepared statement with the provided values. If the routing key is set
+    /// with [Self::set_routing_key()], its token is returned instead.
     // As this function creates a `PartitionKey`, it is intended rather for external usage (by users).
     // For internal purposes, `PartitionKey::calculate_token()` is preferred, as `PartitionKey`
     // is either way used internally, among others for display in traces.
@@ -267,6 +314,9 @@ impl PreparedStatement {
         &self,
         values: &SerializedValues,
     ) -> Result<Option<Token>, QueryError> {
+        if let Some(token) = self.calculate_routing_key_token() {
+            return Ok(Some(token));
+        }
         self.extract_partition_key_and_calculate_token(&self.partitioner_name, values)
             .map(|opt| opt.map(|(_pk, token)| token))
     }
@@ -344,6 +394,30 @@ impl PreparedStatement {
         self.config.tracing
     }
 
+    /// Enable or disable collecting information about the attempts, retries and speculative
+    /// executions of this statement. If enabled, the returned [`QueryResult`](crate::QueryResult)
+    /// contains the [`ExecutionInfo`](crate::transport::execution_info::ExecutionInfo).
+    pub fn set_collect_execution_info(&mut self, collect: bool) {
+        self.config.collect_execution_info = collect;
+    }
+
+    /// Gets whether collecting execution info is enabled for this statement
+    pub fn get_collect_execution_info(&self) -> bool {
+        self.config.collect_execution_info
+    }
+
+    /// Attaches a collector which receives the
+    /// [`ExecutionInfo`](crate::transport::execution_info::ExecutionInfo) of the requests
+    /// of this statement, including the failed ones. See [`ExecutionInfoCollector`] for details.
+    pub fn set_execution_info_collector(&mut self, collector: Option<ExecutionInfoCollector>) {
+        self.config.execution_info_collector = collector;
+    }
+
+    /// Gets the execution info collector attached to this statement.
+    pub fn get_execution_info_collector(&self) -> Option<&ExecutionInfoCollector> {
+        self.config.execution_info_collector.as_ref()
+    }
+
     /// Make use of cached metadata to decode results
     /// of the statement's execution.
     ///
@@ -401,6 +475,27 @@ impl PreparedStatement {
         &self.shared.metadata
     }
 
+    pub(crate) fn set_column_encryption(
+        &mut self,
+        column_encryption: Option<Arc<BindMarkersEncryption>>,
+    ) {
+        self.column_encryption = column_encryption;
+    }
+
+    pub(crate) fn get_column_encryption(&self) -> Option<&Arc<BindMarkersEncryption>> {
+        self.column_encryption.as_ref()
+    }
+
+    /// Returns the context for serializing values bound to this statement,
+    /// which encrypts values of encrypted columns if the session has
+    /// a column encryption policy.
+    pub(crate) fn serialization_context(&self) -> RowSerializationContext<'_> {
+        match &self.column_encryption {
+            Some(encryption) => RowSerializationContext::with_column_encryption(encryption),
+            None => RowSerializationContext::from_prepared(self.get_prepared_metadata()),
+        }
+    }
+
     /// Access column specifications of the bind variables of this statement
     pub fn get_variable_col_specs(&self) -> &[ColumnSpec] {
         &self.shared.metadata.col_specs
@@ -426,6 +521,19 @@ impl PreparedStatement {
         &self.partitioner_name
     }
 
+    /// Sets the custom payload to be sent along with this statement.
+    /// Custom payload is a map of arbitrary key-value pairs, which is passed
+    /// to the server in the request frame and can be consumed by server-side
+    /// extensions. Pass `None` to stop sending it.
+    pub fn set_custom_payload(&mut self, custom_payload: Option<HashMap<String, Bytes>>) {
+        self.config.custom_payload = custom_payload.map(Arc::new);
+    }
+
+    /// Gets the custom payload to be sent along with this statement.
+    pub fn get_custom_payload(&self) -> Option<&HashMap<String, Bytes>> {
+        self.config.custom_payload.as_deref()
+    }
+
     /// Set the retry policy for this statement, overriding the one from execution profile if not None.
     #[inline]
     pub fn set_retry_policy(&mut self, retry_policy: Option<Arc<dyn RetryPolicy>>) {
@@ -448,6 +556,29 @@ impl PreparedStatement {
         self.config.history_listener.take()
     }
 
+    /// Attaches a handle which cancels the requests of this statement when cancelled.
+    /// See [`CancelHandle`] for details.
+    pub fn set_cancel_handle(&mut self, cancel_handle: Option<CancelHandle>) {
+        self.config.cancel_handle = cancel_handle;
+    }
+
+    /// Gets the cancel handle attached to this statement.
+    pub fn get_cancel_handle(&self) -> Option<&CancelHandle> {
+        self.config.cancel_handle.as_ref()
+    }
+
+    /// Sets the priority of this statement, which determines the order in which
+    /// requests are shed when the session is saturated.
+    /// See [`LoadSheddingPolicy`](crate::transport::load_shedding::LoadSheddingPolicy) for details.
+    pub fn set_priority(&mut self, priority: Priority) {
+        self.config.priority = priority;
+    }
+
+    /// Gets the priority of this statement.
+    pub fn get_priority(&self) -> Priority {
+        self.config.priority
+    }
+
     /// Associates the query with execution profile referred by the provided handle.
     /// Handle may be later remapped to another profile, and query will reflect those changes.
     pub fn set_execution_profile_handle(&mut self, profile_handle: Option<ExecutionProfileHandle>) {
@@ -463,7 +594,7 @@ impl PreparedStatement {
         &self,
         values: &impl SerializeRow,
     ) -> Result<SerializedValues, SerializationError> {
-        let ctx = RowSerializationContext::from_prepared(self.get_prepared_metadata());
+        let ctx = self.serialization_context();
         SerializedValues::from_serializable(&ctx, values)
     }
 }
@@ -598,6 +729,7 @@ impl<'ps> PartitionKey<'ps> {
 
 #[cfg(test)]
 mod tests {
+    use bytes::Bytes;
     use scylla_cql::{
         frame::response::result::{
             ColumnSpec, ColumnType, PartitionKeyIndex, PreparedMetadata, TableSpec,
@@ -605,7 +737,11 @@ mod tests {
         types::serialize::row::SerializedValues,
     };
 
-    use crate::{prepared_statement::PartitionKey, test_utils::setup_tracing};
+    use crate::{
+        prepared_statement::{PartitionKey, PreparedStatement},
+        test_utils::setup_tracing,
+        transport::partitioner::{Murmur3Partitioner, Partitioner},
+    };
 
     fn make_meta(
         cols: impl IntoIterator<Item = ColumnType>,
@@ -671,4 +807,38 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_routing_key_overrides_bound_values() {
+        setup_tracing();
+        let meta = make_meta([ColumnType::Int, ColumnType::Int], [0]);
+        let mut prepared = PreparedStatement::new(
+            Default::default(),
+            false,
+            meta,
+            Default::default(),
+            "SELECT * FROM ks.t WHERE a = ? AND b = ?".to_owned(),
+            None,
+            Default::default(),
+        );
+        let values = (1i32, 2i32);
+        let bound_token = prepared.calculate_token(&values).unwrap();
+        assert_eq!(
+            bound_token,
+            Some(Murmur3Partitioner.hash_one(&1i32.to_be_bytes()))
+        );
+
+        prepared.set_routing_key(Some(Bytes::from_static(&[0, 0, 0, 42])));
+        assert_eq!(
+            prepared.calculate_token(&values).unwrap(),
+            Some(Murmur3Partitioner.hash_one(&42i32.to_be_bytes()))
+        );
+        assert_eq!(
+            prepared.clone().get_routing_key().map(|key| &key[..]),
+            Some(&[0, 0, 0, 42][..])
+        );
+
+        prepared.set_routing_key(None);
+        assert_eq!(prepared.calculate_token(&values).unwrap(), bound_token);
+    }
 }
@@ -1,8 +1,14 @@
-use super::StatementConfig;
+use super::{Priority, StatementConfig};
 use crate::frame::types::{Consistency, SerialConsistency};
 use crate::history::HistoryListener;
 use crate::retry_policy::RetryPolicy;
+use crate::routing::Token;
+use crate::transport::cancellation::CancelHandle;
+use crate::transport::execution_info::ExecutionInfoCollector;
 use crate::transport::execution_profile::ExecutionProfileHandle;
+use bytes::Bytes;
+use scylla_cql::frame::response::result::TableSpec;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -15,6 +21,8 @@ pub struct Query {
 
     pub contents: String,
     page_size: Option<i32>,
+    routing_token: Option<Token>,
+    routing_table: Option<TableSpec<'static>>,
 }
 
 impl Query {
@@ -23,6 +31,8 @@ impl Query {
         Self {
             contents: query_text.into(),
             page_size: None,
+            routing_token: None,
+            routing_table: None,
             config: Default::default(),
         }
     }
@@ -98,6 +108,30 @@ impl Query {
         self.config.tracing
     }
 
+    /// Enable or disable collecting information about the attempts, retries and speculative
+    /// executions of this statement. If enabled, the returned [`QueryResult`](crate::QueryResult)
+    /// contains the [`ExecutionInfo`](crate::transport::execution_info::ExecutionInfo).
+    pub fn set_collect_execution_info(&mut self, collect: bool) {
+        self.config.collect_execution_info = collect;
+    }
+
+    /// Gets whether collecting execution info is enabled for this statement
+    pub fn get_collect_execution_info(&self) -> bool {
+        self.config.collect_execution_info
+    }
+
+    /// Attaches a collector which receives the
+    /// [`ExecutionInfo`](crate::transport::execution_info::ExecutionInfo) of the requests
+    /// of this statement, including the failed ones. See [`ExecutionInfoCollector`] for details.
+    pub fn set_execution_info_collector(&mut self, collector: Option<ExecutionInfoCollector>) {
+        self.config.execution_info_collector = collector;
+    }
+
+    /// Gets the execution info collector attached to this statement.
+    pub fn get_execution_info_collector(&self) -> Option<&ExecutionInfoCollector> {
+        self.config.execution_info_collector.as_ref()
+    }
+
     /// Sets the default timestamp for this statement in microseconds.
     /// If not None, it will replace the server side assigned timestamp as default timestamp
     /// If a statement contains a `USING TIMESTAMP` clause, calling this method won't change
@@ -124,6 +158,19 @@ impl Query {
         self.config.request_timeout
     }
 
+    /// Sets the custom payload to be sent along with this statement.
+    /// Custom payload is a map of arbitrary key-value pairs, which is passed
+    /// to the server in the request frame and can be consumed by server-side
+    /// extensions. Pass `None` to stop sending it.
+    pub fn set_custom_payload(&mut self, custom_payload: Option<HashMap<String, Bytes>>) {
+        self.config.custom_payload = custom_payload.map(Arc::new);
+    }
+
+    /// Gets the custom payload to be sent along with this statement.
+    pub fn get_custom_payload(&self) -> Option<&HashMap<String, Bytes>> {
+        self.config.custom_payload.as_deref()
+    }
+
     /// Set the retry policy for this statement, overriding the one from execution profile if not None.
     #[inline]
     pub fn set_retry_policy(&mut self, retry_policy: Option<Arc<dyn RetryPolicy>>) {
@@ -146,6 +193,29 @@ impl Query {
         self.config.history_listener.take()
     }
 
+    /// Attaches a handle which cancels the requests of this query when cancelled.
+    /// See [`CancelHandle`] for details.
+    pub fn set_cancel_handle(&mut self, cancel_handle: Option<CancelHandle>) {
+        self.config.cancel_handle = cancel_handle;
+    }
+
+    /// Gets the cancel handle attached to this query.
+    pub fn get_cancel_handle(&self) -> Option<&CancelHandle> {
+        self.config.cancel_handle.as_ref()
+    }
+
+    /// Sets the priority of this query, which determines the order in which
+    /// requests are shed when the session is saturated.
+    /// See [`LoadSheddingPolicy`](crate::transport::load_shedding::LoadSheddingPolicy) for details.
+    pub fn set_priority(&mut self, priority: Priority) {
+        self.config.priority = priority;
+    }
+
+    /// Gets the priority of this query.
+    pub fn get_priority(&self) -> Priority {
+        self.config.priority
+    }
+
     /// Associates the query with execution profile referred by the provided handle.
     /// Handle may be later remapped to another profile, and query will reflect those changes.
     pub fn set_execution_profile_handle(&mut self, profile_handle: Option<ExecutionProfileHandle>) {
@@ -156,6 +226,37 @@ impl Query {
     pub fn get_execution_profile_handle(&self) -> Option<&ExecutionProfileHandle> {
         self.config.execution_profile_handle.as_ref()
     }
+
+    /// Sets the token used for token-aware routing of this query.
+    ///
+    /// The driver does not know the partition key of an unprepared query, so
+    /// such queries are sent to a random node unless the token is provided here,
+    /// e.g. computed with a custom partitioner or for a `token()`-based range read.
+    /// The query is routed to the replicas owning the token
+    /// only if the table is set, too, with [`Self::set_routing_table`].
+    ///
+    /// Note that if [`Session::query_iter`](crate::Session::query_iter) is called
+    /// with non-empty values, the query is prepared first and routed
+    /// based on the bound values instead.
+    pub fn set_routing_token(&mut self, token: Option<Token>) {
+        self.routing_token = token;
+    }
+
+    /// Gets the token used for token-aware routing of this query, if set.
+    pub fn get_routing_token(&self) -> Option<Token> {
+        self.routing_token
+    }
+
+    /// Sets the table this query operates on, used together with the token
+    /// set with [`Self::set_routing_token`] to find the replicas of the query.
+    pub fn set_routing_table(&mut self, table: Option<TableSpec<'static>>) {
+        self.routing_table = table;
+    }
+
+    /// Gets the table used for token-aware routing of this query, if set.
+    pub fn get_routing_table(&self) -> Option<&TableSpec<'static>> {
+        self.routing_table.as_ref()
+    }
 }
 
 impl From<String> for Query {
@@ -0,0 +1,127 @@
+//! Runtime support of the statements generated by the [`prepared`](crate::macros::prepared)
+//! attribute macro.
+
+use scylla_cql::errors::{BadQuery, QueryError};
+use scylla_cql::frame::response::result::ColumnType;
+
+use super::prepared_statement::PreparedStatement;
+
+/// Checks that the metadata of a prepared statement matches the types its typed wrapper
+/// was generated with, i.e. that the schema dump used at compile time is up to date.
+///
+/// `result_columns` are the names and types of the selected columns, or `None` if the
+/// statement isn't a `SELECT`. They aren't checked if the server sent no result metadata.
+pub fn check_typed_statement(
+    prepared: &PreparedStatement,
+    bind_markers: &[ColumnType],
+    result_columns: Option<&[(&str, ColumnType)]>,
+) -> Result<(), QueryError> {
+    let mismatch = |what: String| {
+        QueryError::BadQuery(BadQuery::Other(format!(
+            "Typed statement {:?} doesn't match the schema of the cluster: {}",
+            prepared.get_statement(),
+            what
+        )))
+    };
+
+    let variables = prepared.get_variable_col_specs();
+    if variables.len() != bind_markers.len() {
+        return Err(mismatch(format!(
+            "expected {} bind markers, the server reported {}",
+            bind_markers.len(),
+            variables.len()
+        )));
+    }
+    for (i, (spec, expected)) in variables.iter().zip(bind_markers).enumerate() {
+        if spec.typ != *expected {
+            return Err(mismatch(format!(
+                "bind marker {} ({}) has type {:?}, expected {:?}",
+                i, spec.name, spec.typ, expected
+            )));
+        }
+    }
+
+    let result_specs = prepared.get_result_set_col_specs();
+    match result_columns {
+        Some(columns) if !result_specs.is_empty() => {
+            if result_specs.len() != columns.len() {
+                return Err(mismatch(format!(
+                    "expected {} result columns, the server reported {}",
+                    columns.len(),
+                    result_specs.len()
+                )));
+            }
+            for (spec, (name, expected)) in result_specs.iter().zip(columns) {
+                if spec.name != *name || spec.typ != *expected {
+                    return Err(mismatch(format!(
+                        "result column {} has type {:?}, expected column {} of type {:?}",
+                        spec.name, spec.typ, name, expected
+                    )));
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use scylla_cql::frame::response::result::{
+        ColumnSpec, PartitionKeyIndex, PreparedMetadata, ResultMetadata, TableSpec,
+    };
+
+    use super::*;
+
+    fn spec(name: &str, typ: ColumnType) -> ColumnSpec {
+        ColumnSpec {
+            table_spec: TableSpec::borrowed("ks", "t"),
+            name: name.to_string(),
+            typ,
+        }
+    }
+
+    fn prepared(variables: Vec<ColumnSpec>, results: Vec<ColumnSpec>) -> PreparedStatement {
+        let mut result_metadata = ResultMetadata::default();
+        result_metadata.col_specs = results;
+        PreparedStatement::new(
+            Bytes::from_static(b"id"),
+            false,
+            PreparedMetadata {
+                flags: 0,
+                col_count: variables.len(),
+                pk_indexes: vec![PartitionKeyIndex {
+                    index: 0,
+                    sequence: 0,
+                }],
+                col_specs: variables,
+            },
+            result_metadata,
+            "SELECT v FROM ks.t WHERE k = ?".to_string(),
+            None,
+            Default::default(),
+        )
+    }
+
+    #[test]
+    fn checks_types() {
+        let statement = prepared(
+            vec![spec("k", ColumnType::Int)],
+            vec![spec("v", ColumnType::List(Box::new(ColumnType::Text)))],
+        );
+        let result = [("v", ColumnType::List(Box::new(ColumnType::Text)))];
+        check_typed_statement(&statement, &[ColumnType::Int], Some(&result)).unwrap();
+
+        let wrong_result = [("v", ColumnType::List(Box::new(ColumnType::Ascii)))];
+        assert!(
+            check_typed_statement(&statement, &[ColumnType::Int], Some(&wrong_result)).is_err()
+        );
+        assert!(check_typed_statement(&statement, &[ColumnType::BigInt], None).is_err());
+        assert!(check_typed_statement(&statement, &[], None).is_err());
+
+        // Statements prepared without result metadata are checked only by their bind markers
+        let statement = prepared(vec![spec("k", ColumnType::Int)], vec![]);
+        check_typed_statement(&statement, &[ColumnType::Int], Some(&result)).unwrap();
+    }
+}
@@ -2,6 +2,7 @@ use itertools::Itertools;
 use scylla_cql::frame::value::CqlTimeuuid;
 use std::collections::HashMap;
 use std::net::IpAddr;
+use std::time::Duration;
 
 use crate::cql_to_rust::{FromRow, FromRowError};
 use crate::frame::response::result::Row;
@@ -14,6 +15,8 @@ pub struct TracingInfo {
     pub client: Option<IpAddr>,
     pub command: Option<String>,
     pub coordinator: Option<IpAddr>,
+    /// Duration of the whole request in microseconds, as measured by the coordinator.
+    /// See [`TracingInfo::request_duration`] for a typed version.
     pub duration: Option<i32>,
     pub parameters: Option<HashMap<String, String>>,
     pub request: Option<String>,
@@ -29,6 +32,8 @@ pub struct TracingEvent {
     pub event_id: CqlTimeuuid,
     pub activity: Option<String>,
     pub source: Option<IpAddr>,
+    /// Time elapsed on the `source` node since the beginning of the request, in microseconds.
+    /// See [`TracingEvent::elapsed_on_source`] for a typed version.
     pub source_elapsed: Option<i32>,
     pub thread: Option<String>,
 }
@@ -42,6 +47,29 @@ impl TracingInfo {
             .unique()
             .collect()
     }
+
+    /// Returns the duration of the whole request, as measured by the coordinator.
+    /// Returns `None` if the server did not report it (e.g. the request is still in progress).
+    pub fn request_duration(&self) -> Option<Duration> {
+        micros_to_duration(self.duration?)
+    }
+
+    /// Returns events that happened on the given node, in the order they were reported.
+    pub fn events_on_node(&self, node: IpAddr) -> impl Iterator<Item = &TracingEvent> {
+        self.events.iter().filter(move |e| e.source == Some(node))
+    }
+}
+
+impl TracingEvent {
+    /// Returns the time elapsed on the source node since the beginning of the request
+    /// until this event happened.
+    pub fn elapsed_on_source(&self) -> Option<Duration> {
+        micros_to_duration(self.source_elapsed?)
+    }
+}
+
+fn micros_to_duration(micros: i32) -> Option<Duration> {
+    u64::try_from(micros).ok().map(Duration::from_micros)
 }
 
 // A query used to query TracingInfo from system_traces.sessions
@@ -101,3 +129,73 @@ impl FromRow for TracingEvent {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::time::Duration;
+
+    use uuid::Uuid;
+
+    use super::{TracingEvent, TracingInfo};
+
+    fn event(source: Ipv4Addr, source_elapsed: Option<i32>) -> TracingEvent {
+        TracingEvent {
+            event_id: Uuid::new_v4().into(),
+            activity: None,
+            source: Some(IpAddr::V4(source)),
+            source_elapsed,
+            thread: None,
+        }
+    }
+
+    fn info(duration: Option<i32>, events: Vec<TracingEvent>) -> TracingInfo {
+        TracingInfo {
+            client: None,
+            command: None,
+            coordinator: None,
+            duration,
+            parameters: None,
+            request: None,
+            started_at: None,
+            events,
+        }
+    }
+
+    #[test]
+    fn durations_are_typed() {
+        assert_eq!(
+            info(Some(1500), Vec::new()).request_duration(),
+            Some(Duration::from_micros(1500))
+        );
+        assert_eq!(info(None, Vec::new()).request_duration(), None);
+        // Negative durations are not valid
+        assert_eq!(info(Some(-1), Vec::new()).request_duration(), None);
+
+        let localhost = Ipv4Addr::LOCALHOST;
+        assert_eq!(
+            event(localhost, Some(20)).elapsed_on_source(),
+            Some(Duration::from_micros(20))
+        );
+        assert_eq!(event(localhost, None).elapsed_on_source(), None);
+    }
+
+    #[test]
+    fn events_are_grouped_by_node() {
+        let (first, second) = (Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2));
+        let events = vec![
+            event(first, Some(1)),
+            event(second, Some(2)),
+            event(first, Some(3)),
+        ];
+        let info = info(Some(10), events.clone());
+
+        assert_eq!(info.nodes(), vec![IpAddr::V4(first), IpAddr::V4(second)]);
+        let on_first: Vec<_> = info.events_on_node(IpAddr::V4(first)).collect();
+        assert_eq!(on_first, vec![&events[0], &events[2]]);
+        assert_eq!(
+            info.events_on_node(IpAddr::V4(Ipv4Addr::LOCALHOST)).count(),
+            0
+        );
+    }
+}
@@ -0,0 +1,976 @@
+//! Conversion of query results to Apache Arrow record batches.
+//!
+//! Requires the `arrow-50` feature. Use [`RowIterator::into_record_batches`]
+//! to turn the rows of a paged query into a stream of [`RecordBatch`]es,
+//! which can be handed over to Arrow-based tools like DataFusion or Polars.
+//!
+//! CQL types are mapped to Arrow types as follows:
+//!
+//! | CQL type                  | Arrow type                                  |
+//! |---------------------------|---------------------------------------------|
+//! | `ascii`, `text`           | `Utf8`                                      |
+//! | `boolean`                 | `Boolean`                                   |
+//! | `blob`                    | `Binary`                                    |
+//! | `tinyint`, `smallint`     | `Int8`, `Int16`                             |
+//! | `int`, `bigint`           | `Int32`, `Int64`                            |
+//! | `counter`                 | `Int64`                                     |
+//! | `float`, `double`         | `Float32`, `Float64`                        |
+//! | `decimal`, `varint`       | `Utf8`, holding the decimal representation  |
+//! | `date`                    | `Date32`                                    |
+//! | `time`                    | `Time64(Nanosecond)`                        |
+//! | `timestamp`               | `Timestamp(Millisecond, "UTC")`             |
+//! | `duration`                | `Interval(MonthDayNano)`                    |
+//! | `uuid`, `timeuuid`        | `FixedSizeBinary(16)`                       |
+//! | `inet`                    | `Utf8`                                      |
+//! | `list<T>`, `set<T>`       | `List(T)`                                   |
+//! | `map<K, V>`               | `Map(K, V)`                                 |
+//! | `tuple<...>`              | `Struct`, with fields named `0`, `1`, ...   |
+//! | user defined types        | `Struct`, with the fields of the UDT        |
+//!
+//! Decimals and varints have arbitrary precision and decimals can have a different scale
+//! in each row, so they are converted to strings instead of one of the Arrow decimal types.
+//! Empty values are converted to nulls. Columns of custom types are not supported.
+//!
+//! # Performance
+//!
+//! The arrays are built straight from the serialized pages of results: the values
+//! of each column are decoded from the bytes received from the database and appended
+//! to the Arrow array builders, so the rows are never deserialized into [`Row`]s
+//! of [`CqlValue`]s. Only decimals, varints and inet addresses, which are converted
+//! to strings, need an allocation per value.
+//!
+//! [`Row`]: crate::frame::response::result::Row
+//! [`CqlValue`]: crate::frame::response::result::CqlValue
+
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use arrow_array::builder::{
+    BinaryBuilder, BooleanBuilder, Date32Builder, FixedSizeBinaryBuilder, Float32Builder,
+    Float64Builder, Int16Builder, Int32Builder, Int64Builder, Int8Builder,
+    IntervalMonthDayNanoBuilder, StringBuilder, Time64NanosecondBuilder,
+    TimestampMillisecondBuilder,
+};
+use arrow_array::types::IntervalMonthDayNanoType;
+use arrow_array::{ArrayRef, ListArray, MapArray, RecordBatch, StructArray};
+use arrow_buffer::{NullBuffer, OffsetBuffer};
+use arrow_schema::{
+    ArrowError, DataType, Field, Fields, IntervalUnit, Schema, SchemaRef, TimeUnit,
+};
+use futures::Stream;
+use scylla_cql::frame::frame_errors::LowLevelDeserializationError;
+use scylla_cql::frame::types;
+use scylla_cql::types::deserialize::value::BytesSequenceIterator;
+use scylla_cql::types::deserialize::{DeserializationError, DeserializeValue, FrameSlice};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::frame::response::result::{ColumnSpec, ColumnType};
+use crate::frame::value::{CqlDate, CqlDecimal, CqlDuration, CqlTime, CqlTimestamp, CqlVarint};
+use crate::transport::errors::QueryError;
+use crate::transport::iterator::RowIterator;
+
+const UTC: &str = "UTC";
+
+/// An error returned when converting query results to Arrow record batches.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum RecordBatchError {
+    /// Fetching the rows failed.
+    #[error(transparent)]
+    QueryError(#[from] QueryError),
+
+    /// The column has a type which can't be converted to Arrow.
+    #[error("Column {name} has type {typ:?}, which can't be converted to Arrow")]
+    UnsupportedColumnType { name: String, typ: ColumnType },
+
+    /// Decoding the serialized rows failed.
+    #[error(transparent)]
+    DeserializationError(#[from] DeserializationError),
+
+    /// Building the Arrow arrays failed.
+    #[error("Failed to build an Arrow record batch: {0}")]
+    ArrowError(#[from] ArrowError),
+}
+
+/// Returns the Arrow schema of record batches holding rows with the given columns.
+pub fn schema_for_columns(col_specs: &[ColumnSpec]) -> Result<Schema, RecordBatchError> {
+    let fields = col_specs
+        .iter()
+        .map(|spec| {
+            let data_type = arrow_type(&spec.typ).ok_or_else(|| unsupported_column_type(spec))?;
+            Ok(Field::new(spec.name.clone(), data_type, true))
+        })
+        .collect::<Result<Vec<_>, RecordBatchError>>()?;
+    Ok(Schema::new(fields))
+}
+
+fn unsupported_column_type(spec: &ColumnSpec) -> RecordBatchError {
+    RecordBatchError::UnsupportedColumnType {
+        name: spec.name.clone(),
+        typ: spec.typ.clone(),
+    }
+}
+
+/// A stream of Arrow record batches built from the rows of a paged query.
+///
+/// Created with [`RowIterator::into_record_batches`]. Every batch but the last
+/// has exactly the configured number of rows. The stream ends after returning an error.
+pub struct RecordBatchStream {
+    rows: RowIterator,
+    schema: SchemaRef,
+    columns: Vec<ColumnDecoder>,
+    batch_size: usize,
+    buffered_rows: usize,
+    finished: bool,
+}
+
+impl RecordBatchStream {
+    pub(crate) fn new(rows: RowIterator, batch_size: usize) -> Result<Self, RecordBatchError> {
+        assert!(batch_size > 0, "Record batch size must be positive");
+        let col_specs = rows.get_column_specs();
+        let schema = Arc::new(schema_for_columns(col_specs)?);
+        let columns = col_specs
+            .iter()
+            .map(|spec| {
+                ColumnDecoder::new(&spec.typ, batch_size)
+                    .ok_or_else(|| unsupported_column_type(spec))
+            })
+            .collect::<Result<Vec<_>, RecordBatchError>>()?;
+        Ok(Self {
+            rows,
+            schema,
+            columns,
+            batch_size,
+            buffered_rows: 0,
+            finished: false,
+        })
+    }
+
+    /// Returns the schema of the produced record batches.
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    /// If tracing was enabled, returns tracing ids of all finished page queries.
+    pub fn get_tracing_ids(&self) -> &[uuid::Uuid] {
+        self.rows.get_tracing_ids()
+    }
+
+    // Decodes the rows of the current page into the column builders,
+    // until the page is exhausted or the batch is full.
+    fn decode_rows(&mut self) -> Result<(), DeserializationError> {
+        let page = self.rows.current_page_mut();
+        let count = page.rows_count.min(self.batch_size - self.buffered_rows);
+        let mut rows = page.rows_iter();
+        for row in rows.by_ref().take(count) {
+            for (column, decoder) in row?.zip(self.columns.iter_mut()) {
+                decoder.append(column?.slice)?;
+            }
+        }
+        (page.rows_count, page.raw_rows) =
+            (rows.rows_remaining(), rows.remaining_slice().to_bytes());
+        self.buffered_rows += count;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<RecordBatch, RecordBatchError> {
+        self.buffered_rows = 0;
+        let arrays = self
+            .columns
+            .iter_mut()
+            .map(ColumnDecoder::finish)
+            .collect::<Result<Vec<_>, ArrowError>>()?;
+        Ok(RecordBatch::try_new(self.schema.clone(), arrays)?)
+    }
+}
+
+impl Stream for RecordBatchStream {
+    type Item = Result<RecordBatch, RecordBatchError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let s = self.get_mut();
+        while !s.finished {
+            if s.rows.current_page_mut().rows_count == 0 {
+                match s.rows.poll_next_page(cx) {
+                    Poll::Ready(Some(Ok(()))) => continue,
+                    Poll::Ready(Some(Err(err))) => {
+                        s.finished = true;
+                        return Poll::Ready(Some(Err(err.into())));
+                    }
+                    Poll::Ready(None) => {
+                        s.finished = true;
+                        if s.buffered_rows > 0 {
+                            return Poll::Ready(Some(s.flush()));
+                        }
+                    }
+                    Poll::Pending => return Poll::Pending,
+                }
+                continue;
+            }
+
+            if let Err(err) = s.decode_rows() {
+                // The columns decoded so far may have different lengths
+                s.finished = true;
+                return Poll::Ready(Some(Err(err.into())));
+            }
+            if s.buffered_rows == s.batch_size {
+                return Poll::Ready(Some(s.flush()));
+            }
+        }
+        Poll::Ready(None)
+    }
+}
+
+/// Returns the Arrow type of values of the given CQL type, or `None` if it's not supported.
+fn arrow_type(typ: &ColumnType) -> Option<DataType> {
+    let data_type = match typ {
+        ColumnType::Custom(_) => return None,
+        ColumnType::Ascii
+        | ColumnType::Text
+        | ColumnType::Decimal
+        | ColumnType::Varint
+        | ColumnType::Inet => DataType::Utf8,
+        ColumnType::Boolean => DataType::Boolean,
+        ColumnType::Blob => DataType::Binary,
+        ColumnType::TinyInt => DataType::Int8,
+        ColumnType::SmallInt => DataType::Int16,
+        ColumnType::Int => DataType::Int32,
+        ColumnType::BigInt | ColumnType::Counter => DataType::Int64,
+        ColumnType::Float => DataType::Float32,
+        ColumnType::Double => DataType::Float64,
+        ColumnType::Date => DataType::Date32,
+        ColumnType::Time => DataType::Time64(TimeUnit::Nanosecond),
+        ColumnType::Timestamp => DataType::Timestamp(TimeUnit::Millisecond, Some(UTC.into())),
+        ColumnType::Duration => DataType::Interval(IntervalUnit::MonthDayNano),
+        ColumnType::Uuid | ColumnType::Timeuuid => DataType::FixedSizeBinary(16),
+        ColumnType::List(element_type) | ColumnType::Set(element_type) => {
+            DataType::List(Arc::new(list_item_field(arrow_type(element_type)?)))
+        }
+        ColumnType::Map(key_type, value_type) => DataType::Map(
+            Arc::new(map_entries_field(
+                arrow_type(key_type)?,
+                arrow_type(value_type)?,
+            )),
+            false,
+        ),
+        ColumnType::Tuple(element_types) => DataType::Struct(
+            element_types
+                .iter()
+                .enumerate()
+                .map(|(i, element_type)| {
+                    Some(Field::new(i.to_string(), arrow_type(element_type)?, true))
+                })
+                .collect::<Option<Fields>>()?,
+        ),
+        ColumnType::UserDefinedType { field_types, .. } => DataType::Struct(
+            field_types
+                .iter()
+                .map(|(name, field_type)| Some(Field::new(name, arrow_type(field_type)?, true)))
+                .collect::<Option<Fields>>()?,
+        ),
+    };
+    Some(data_type)
+}
+
+fn list_item_field(data_type: DataType) -> Field {
+    Field::new("item", data_type, true)
+}
+
+fn map_entries_field(key_type: DataType, value_type: DataType) -> Field {
+    Field::new(
+        "entries",
+        DataType::Struct(map_entry_fields(key_type, value_type)),
+        false,
+    )
+}
+
+fn map_entry_fields(key_type: DataType, value_type: DataType) -> Fields {
+    Fields::from(vec![
+        Field::new("key", key_type, false),
+        Field::new("value", value_type, true),
+    ])
+}
+
+// Returns the validity buffer for the given per-row validity, omitting it if there are no nulls.
+fn null_buffer(validity: Vec<bool>) -> Option<NullBuffer> {
+    if validity.iter().all(|valid| *valid) {
+        None
+    } else {
+        Some(NullBuffer::from(validity))
+    }
+}
+
+fn offset_buffer(offsets: Vec<usize>) -> Result<OffsetBuffer<i32>, ArrowError> {
+    let offsets = offsets
+        .into_iter()
+        .map(|offset| {
+            i32::try_from(offset).map_err(|_| {
+                ArrowError::InvalidArgumentError(
+                    "Too many collection elements in a record batch".to_string(),
+                )
+            })
+        })
+        .collect::<Result<Vec<i32>, ArrowError>>()?;
+    Ok(OffsetBuffer::new(offsets.into()))
+}
+
+// Offsets and validity of a list-like array being built.
+struct ListLayout {
+    offsets: Vec<usize>,
+    validity: Vec<bool>,
+}
+
+impl ListLayout {
+    fn new(capacity: usize) -> Self {
+        let mut offsets = Vec::with_capacity(capacity + 1);
+        offsets.push(0);
+        Self {
+            offsets,
+            validity: Vec::with_capacity(capacity),
+        }
+    }
+
+    fn append(&mut self, len: usize, valid: bool) {
+        let last = self.offsets[self.offsets.len() - 1];
+        self.offsets.push(last + len);
+        self.validity.push(valid);
+    }
+
+    fn take(&mut self) -> Result<(OffsetBuffer<i32>, Option<NullBuffer>), ArrowError> {
+        let capacity = self.validity.len();
+        let layout = std::mem::replace(self, Self::new(capacity));
+        Ok((offset_buffer(layout.offsets)?, null_buffer(layout.validity)))
+    }
+}
+
+enum Builder {
+    TinyInt(Int8Builder),
+    SmallInt(Int16Builder),
+    Int(Int32Builder),
+    BigInt(Int64Builder),
+    Float(Float32Builder),
+    Double(Float64Builder),
+    Boolean(BooleanBuilder),
+    // Text, and values written as text: decimals, varints and inet addresses
+    String(StringBuilder),
+    Blob(BinaryBuilder),
+    Date(Date32Builder),
+    Time(Time64NanosecondBuilder),
+    Timestamp(TimestampMillisecondBuilder),
+    Duration(IntervalMonthDayNanoBuilder),
+    Uuid(FixedSizeBinaryBuilder),
+    List {
+        layout: ListLayout,
+        elements: Box<ColumnDecoder>,
+    },
+    Map {
+        layout: ListLayout,
+        keys: Box<ColumnDecoder>,
+        values: Box<ColumnDecoder>,
+    },
+    // Tuples and UDTs
+    Struct {
+        names: Vec<String>,
+        validity: Vec<bool>,
+        fields: Vec<ColumnDecoder>,
+    },
+}
+
+// Decodes serialized values of a column of the given type straight into an Arrow array builder.
+struct ColumnDecoder {
+    typ: ColumnType,
+    builder: Builder,
+}
+
+impl ColumnDecoder {
+    // Returns `None` if the type is not supported.
+    fn new(typ: &ColumnType, capacity: usize) -> Option<Self> {
+        let builder = match typ {
+            ColumnType::Custom(_) => return None,
+            ColumnType::Ascii
+            | ColumnType::Text
+            | ColumnType::Decimal
+            | ColumnType::Varint
+            | ColumnType::Inet => Builder::String(StringBuilder::with_capacity(capacity, 0)),
+            ColumnType::Boolean => Builder::Boolean(BooleanBuilder::with_capacity(capacity)),
+            ColumnType::Blob => Builder::Blob(BinaryBuilder::with_capacity(capacity, 0)),
+            ColumnType::TinyInt => Builder::TinyInt(Int8Builder::with_capacity(capacity)),
+            ColumnType::SmallInt => Builder::SmallInt(Int16Builder::with_capacity(capacity)),
+            ColumnType::Int => Builder::Int(Int32Builder::with_capacity(capacity)),
+            ColumnType::BigInt | ColumnType::Counter => {
+                Builder::BigInt(Int64Builder::with_capacity(capacity))
+            }
+            ColumnType::Float => Builder::Float(Float32Builder::with_capacity(capacity)),
+            ColumnType::Double => Builder::Double(Float64Builder::with_capacity(capacity)),
+            ColumnType::Date => Builder::Date(Date32Builder::with_capacity(capacity)),
+            ColumnType::Time => Builder::Time(Time64NanosecondBuilder::with_capacity(capacity)),
+            ColumnType::Timestamp => Builder::Timestamp(
+                TimestampMillisecondBuilder::with_capacity(capacity).with_timezone(UTC),
+            ),
+            ColumnType::Duration => {
+                Builder::Duration(IntervalMonthDayNanoBuilder::with_capacity(capacity))
+            }
+            ColumnType::Uuid | ColumnType::Timeuuid => {
+                Builder::Uuid(FixedSizeBinaryBuilder::with_capacity(capacity, 16))
+            }
+            ColumnType::List(element_type) | ColumnType::Set(element_type) => Builder::List {
+                layout: ListLayout::new(capacity),
+                elements: Box::new(Self::new(element_type, capacity)?),
+            },
+            ColumnType::Map(key_type, value_type) => Builder::Map {
+                layout: ListLayout::new(capacity),
+                keys: Box::new(Self::new(key_type, capacity)?),
+                values: Box::new(Self::new(value_type, capacity)?),
+            },
+            ColumnType::Tuple(element_types) => Builder::Struct {
+                names: (0..element_types.len()).map(|i| i.to_string()).collect(),
+                validity: Vec::with_capacity(capacity),
+                fields: element_types
+                    .iter()
+                    .map(|element_type| Self::new(element_type, capacity))
+                    .collect::<Option<_>>()?,
+            },
+            ColumnType::UserDefinedType { field_types, .. } => Builder::Struct {
+                names: field_types.iter().map(|(name, _)| name.clone()).collect(),
+                validity: Vec::with_capacity(capacity),
+                fields: field_types
+                    .iter()
+                    .map(|(_, field_type)| Self::new(field_type, capacity))
+                    .collect::<Option<_>>()?,
+            },
+        };
+        Some(Self {
+            typ: typ.clone(),
+            builder,
+        })
+    }
+
+    fn append_null(&mut self) {
+        match &mut self.builder {
+            Builder::TinyInt(b) => b.append_null(),
+            Builder::SmallInt(b) => b.append_null(),
+            Builder::Int(b) => b.append_null(),
+            Builder::BigInt(b) => b.append_null(),
+            Builder::Float(b) => b.append_null(),
+            Builder::Double(b) => b.append_null(),
+            Builder::Boolean(b) => b.append_null(),
+            Builder::String(b) => b.append_null(),
+            Builder::Blob(b) => b.append_null(),
+            Builder::Date(b) => b.append_null(),
+            Builder::Time(b) => b.append_null(),
+            Builder::Timestamp(b) => b.append_null(),
+            Builder::Duration(b) => b.append_null(),
+            Builder::Uuid(b) => b.append_null(),
+            Builder::List { layout, .. } | Builder::Map { layout, .. } => layout.append(0, false),
+            Builder::Struct {
+                validity, fields, ..
+            } => {
+                validity.push(false);
+                fields.iter_mut().for_each(Self::append_null);
+            }
+        }
+    }
+
+    // Appends a serialized value, `None` being a null.
+    fn append(&mut self, value: Option<FrameSlice<'_>>) -> Result<(), DeserializationError> {
+        let value = match value {
+            // Empty values are valid strings and blobs, and nulls of the other types.
+            Some(value)
+                if !value.is_empty()
+                    || matches!(
+                        self.typ,
+                        ColumnType::Ascii | ColumnType::Text | ColumnType::Blob
+                    ) =>
+            {
+                value
+            }
+            _ => {
+                self.append_null();
+                return Ok(());
+            }
+        };
+
+        let typ = &self.typ;
+        let v = Some(value);
+        match &mut self.builder {
+            Builder::TinyInt(b) => b.append_value(i8::deserialize(typ, v)?),
+            Builder::SmallInt(b) => b.append_value(i16::deserialize(typ, v)?),
+            Builder::Int(b) => b.append_value(i32::deserialize(typ, v)?),
+            Builder::BigInt(b) => b.append_value(i64::deserialize(typ, v)?),
+            Builder::Float(b) => b.append_value(f32::deserialize(typ, v)?),
+            Builder::Double(b) => b.append_value(f64::deserialize(typ, v)?),
+            Builder::Boolean(b) => b.append_value(bool::deserialize(typ, v)?),
+            Builder::String(b) => match typ {
+                ColumnType::Decimal => b.append_value(CqlDecimal::deserialize(typ, v)?.to_string()),
+                ColumnType::Varint => b.append_value(CqlVarint::deserialize(typ, v)?.to_string()),
+                ColumnType::Inet => {
+                    b.append_value(std::net::IpAddr::deserialize(typ, v)?.to_string())
+                }
+                _ => b.append_value(<&str>::deserialize(typ, v)?),
+            },
+            Builder::Blob(b) => b.append_value(<&[u8]>::deserialize(typ, v)?),
+            // CQL dates are unsigned numbers of days, with the epoch at 2^31.
+            Builder::Date(b) => {
+                let CqlDate(days) = CqlDate::deserialize(typ, v)?;
+                b.append_value(days.wrapping_sub(1 << 31) as i32)
+            }
+            Builder::Time(b) => b.append_value(CqlTime::deserialize(typ, v)?.0),
+            Builder::Timestamp(b) => b.append_value(CqlTimestamp::deserialize(typ, v)?.0),
+            Builder::Duration(b) => {
+                let CqlDuration {
+                    months,
+                    days,
+                    nanoseconds,
+                } = CqlDuration::deserialize(typ, v)?;
+                b.append_value(IntervalMonthDayNanoType::make_value(
+                    months,
+                    days,
+                    nanoseconds,
+                ))
+            }
+            // Timeuuids are serialized like uuids.
+            Builder::Uuid(b) => b
+                .append_value(Uuid::deserialize(typ, v)?.as_bytes())
+                .map_err(DeserializationError::new)?,
+            Builder::List { layout, elements } => {
+                let (len, mut items) = collection_items(value)?;
+                for _ in 0..len {
+                    elements.append(next_item(&mut items)?)?;
+                }
+                layout.append(len, true);
+            }
+            Builder::Map {
+                layout,
+                keys,
+                values,
+            } => {
+                let (len, mut items) = collection_items(value)?;
+                for _ in 0..len {
+                    keys.append(next_item(&mut items)?)?;
+                    values.append(next_item(&mut items)?)?;
+                }
+                layout.append(len, true);
+            }
+            Builder::Struct {
+                validity, fields, ..
+            } => {
+                // Values serialized before fields were added to a UDT lack the trailing fields.
+                let mut items = BytesSequenceIterator::from(value);
+                for field in fields.iter_mut() {
+                    let item = items
+                        .next()
+                        .transpose()
+                        .map_err(DeserializationError::new)?;
+                    field.append(item.flatten())?;
+                }
+                validity.push(true);
+            }
+        }
+        Ok(())
+    }
+
+    // Returns the array built from the values appended so far, and starts a new one.
+    fn finish(&mut self) -> Result<ArrayRef, ArrowError> {
+        let array: ArrayRef = match &mut self.builder {
+            Builder::TinyInt(b) => Arc::new(b.finish()),
+            Builder::SmallInt(b) => Arc::new(b.finish()),
+            Builder::Int(b) => Arc::new(b.finish()),
+            Builder::BigInt(b) => Arc::new(b.finish()),
+            Builder::Float(b) => Arc::new(b.finish()),
+            Builder::Double(b) => Arc::new(b.finish()),
+            Builder::Boolean(b) => Arc::new(b.finish()),
+            Builder::String(b) => Arc::new(b.finish()),
+            Builder::Blob(b) => Arc::new(b.finish()),
+            Builder::Date(b) => Arc::new(b.finish()),
+            Builder::Time(b) => Arc::new(b.finish()),
+            Builder::Timestamp(b) => Arc::new(b.finish()),
+            Builder::Duration(b) => Arc::new(b.finish()),
+            Builder::Uuid(b) => Arc::new(b.finish()),
+            Builder::List { layout, elements } => {
+                let (offsets, nulls) = layout.take()?;
+                let elements = elements.finish()?;
+                Arc::new(ListArray::try_new(
+                    Arc::new(list_item_field(elements.data_type().clone())),
+                    offsets,
+                    elements,
+                    nulls,
+                )?)
+            }
+            Builder::Map {
+                layout,
+                keys,
+                values,
+            } => {
+                let (offsets, nulls) = layout.take()?;
+                let keys = keys.finish()?;
+                let values = values.finish()?;
+                let entry_fields =
+                    map_entry_fields(keys.data_type().clone(), values.data_type().clone());
+                let entries = StructArray::try_new(entry_fields.clone(), vec![keys, values], None)?;
+                Arc::new(MapArray::try_new(
+                    Arc::new(Field::new("entries", DataType::Struct(entry_fields), false)),
+                    offsets,
+                    entries,
+                    nulls,
+                    false,
+                )?)
+            }
+            Builder::Struct {
+                names,
+                validity,
+                fields,
+            } => {
+                let arrays = fields
+                    .iter_mut()
+                    .map(Self::finish)
+                    .collect::<Result<Vec<_>, ArrowError>>()?;
+                let fields = names
+                    .iter()
+                    .zip(arrays.iter())
+                    .map(|(name, array)| Field::new(name, array.data_type().clone(), true))
+                    .collect::<Fields>();
+                let capacity = validity.len();
+                let validity = std::mem::replace(validity, Vec::with_capacity(capacity));
+                Arc::new(StructArray::try_new(fields, arrays, null_buffer(validity))?)
+            }
+        };
+        Ok(array)
+    }
+}
+
+// Reads the number of items of a serialized list, set or map, and returns it
+// together with an iterator over the items (keys and values for maps).
+fn collection_items(
+    mut value: FrameSlice<'_>,
+) -> Result<(usize, BytesSequenceIterator<'_>), DeserializationError> {
+    let len = types::read_int_length(value.as_slice_mut()).map_err(DeserializationError::new)?;
+    Ok((len, BytesSequenceIterator::from(value)))
+}
+
+fn next_item<'frame>(
+    items: &mut BytesSequenceIterator<'frame>,
+) -> Result<Option<FrameSlice<'frame>>, DeserializationError> {
+    items
+        .next()
+        .unwrap_or(Err(LowLevelDeserializationError::TooFewBytesReceived {
+            expected: 4,
+            received: 0,
+        }))
+        .map_err(DeserializationError::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use arrow_array::types::IntervalMonthDayNanoType;
+    use arrow_array::{
+        Array, ArrayRef, Date32Array, FixedSizeBinaryArray, Int32Array, IntervalMonthDayNanoArray,
+        ListArray, MapArray, StringArray, StructArray, TimestampMillisecondArray,
+    };
+    use bytes::Bytes;
+    use scylla_cql::types::deserialize::value::BytesSequenceIterator;
+    use scylla_cql::types::deserialize::FrameSlice;
+    use scylla_cql::types::serialize::value::SerializeValue;
+    use scylla_cql::types::serialize::writers::CellWriter;
+    use uuid::Uuid;
+
+    use super::{arrow_type, schema_for_columns, ColumnDecoder, RecordBatchError};
+    use crate::frame::response::result::{ColumnSpec, ColumnType, CqlValue, TableSpec};
+    use crate::frame::value::{CqlDate, CqlDecimal, CqlDuration, CqlTimestamp, CqlTimeuuid};
+
+    fn spec(name: &str, typ: ColumnType) -> ColumnSpec {
+        ColumnSpec {
+            table_spec: TableSpec::borrowed("ks", "t"),
+            name: name.to_owned(),
+            typ,
+        }
+    }
+
+    fn udt_type() -> ColumnType {
+        ColumnType::UserDefinedType {
+            type_name: "address".to_owned(),
+            keyspace: "ks".to_owned(),
+            field_types: vec![
+                ("street".to_owned(), ColumnType::Text),
+                ("number".to_owned(), ColumnType::Int),
+            ],
+        }
+    }
+
+    // Serializes the values as the cells of a column of type `typ`.
+    fn serialize(typ: &ColumnType, values: &[Option<CqlValue>]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        for value in values {
+            value.serialize(typ, CellWriter::new(&mut buf)).unwrap();
+        }
+        buf
+    }
+
+    // Decodes the serialized cells into an array of type `typ`.
+    fn decode(typ: &ColumnType, cells: Vec<u8>) -> ArrayRef {
+        let cells = Bytes::from(cells);
+        let mut decoder = ColumnDecoder::new(typ, 1).unwrap();
+        for cell in BytesSequenceIterator::from(FrameSlice::new(&cells)) {
+            decoder.append(cell.unwrap()).unwrap();
+        }
+        decoder.finish().unwrap()
+    }
+
+    fn build_array(typ: &ColumnType, values: &[Option<CqlValue>]) -> ArrayRef {
+        decode(typ, serialize(typ, values))
+    }
+
+    #[test]
+    fn built_arrays_match_schema() {
+        let types = [
+            ColumnType::Text,
+            ColumnType::Timestamp,
+            ColumnType::Duration,
+            ColumnType::Uuid,
+            ColumnType::List(Box::new(ColumnType::Set(Box::new(ColumnType::Int)))),
+            ColumnType::Map(Box::new(ColumnType::Text), Box::new(udt_type())),
+            ColumnType::Tuple(vec![ColumnType::Int, ColumnType::Varint]),
+            udt_type(),
+        ];
+        for typ in types {
+            let array = build_array(&typ, &[None]);
+            assert_eq!(Some(array.data_type().clone()), arrow_type(&typ));
+            assert!(array.is_null(0));
+        }
+    }
+
+    #[test]
+    fn unsupported_columns() {
+        let err = schema_for_columns(&[
+            spec("a", ColumnType::Int),
+            spec(
+                "b",
+                ColumnType::List(Box::new(ColumnType::Custom("Foo".to_owned()))),
+            ),
+        ])
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            RecordBatchError::UnsupportedColumnType { name, .. } if name == "b"
+        ));
+    }
+
+    #[test]
+    fn scalar_conversions() {
+        let ints = build_array(
+            &ColumnType::Int,
+            &[Some(CqlValue::Int(7)), None, Some(CqlValue::Empty)],
+        );
+        let ints = ints.as_any().downcast_ref::<Int32Array>().unwrap();
+        assert_eq!(ints.value(0), 7);
+        assert_eq!(ints.null_count(), 2);
+
+        let texts = build_array(
+            &ColumnType::Text,
+            &[Some(CqlValue::Text(String::new())), None],
+        );
+        let texts = texts.as_any().downcast_ref::<StringArray>().unwrap();
+        assert!(texts.is_valid(0));
+        assert_eq!(texts.value(0), "");
+
+        let dates = build_array(
+            &ColumnType::Date,
+            &[Some(CqlValue::Date(CqlDate((1 << 31) - 1)))],
+        );
+        let dates = dates.as_any().downcast_ref::<Date32Array>().unwrap();
+        assert_eq!(dates.value(0), -1);
+
+        let timestamps = build_array(
+            &ColumnType::Timestamp,
+            &[Some(CqlValue::Timestamp(CqlTimestamp(1_000)))],
+        );
+        let timestamps = timestamps
+            .as_any()
+            .downcast_ref::<TimestampMillisecondArray>()
+            .unwrap();
+        assert_eq!(timestamps.value(0), 1_000);
+
+        let durations = build_array(
+            &ColumnType::Duration,
+            &[Some(CqlValue::Duration(CqlDuration {
+                months: 1,
+                days: -2,
+                nanoseconds: 3,
+            }))],
+        );
+        let durations = durations
+            .as_any()
+            .downcast_ref::<IntervalMonthDayNanoArray>()
+            .unwrap();
+        assert_eq!(
+            IntervalMonthDayNanoType::to_parts(durations.value(0)),
+            (1, -2, 3)
+        );
+
+        let uuid = Uuid::from_u128(0x8e14e760_7fa8_11eb_bc66_000000000001);
+        let uuids = build_array(
+            &ColumnType::Timeuuid,
+            &[Some(CqlValue::Timeuuid(CqlTimeuuid::from(uuid))), None],
+        );
+        let uuids = uuids
+            .as_any()
+            .downcast_ref::<FixedSizeBinaryArray>()
+            .unwrap();
+        assert_eq!(uuids.value(0), uuid.as_bytes());
+        assert!(uuids.is_null(1));
+
+        let decimals = build_array(
+            &ColumnType::Decimal,
+            &[Some(CqlValue::Decimal(
+                CqlDecimal::from_signed_be_bytes_and_exponent(vec![0x04, 0xd2], 2),
+            ))],
+        );
+        let decimals = decimals.as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(decimals.value(0), "12.34");
+
+        let inets = build_array(
+            &ColumnType::Inet,
+            &[Some(CqlValue::Inet(IpAddr::V4(Ipv4Addr::LOCALHOST)))],
+        );
+        let inets = inets.as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(inets.value(0), "127.0.0.1");
+    }
+
+    #[test]
+    fn malformed_values() {
+        // A bigint where an int is expected
+        let cells = serialize(&ColumnType::BigInt, &[Some(CqlValue::BigInt(1))]);
+        let cells = Bytes::from(cells);
+        let mut decoder = ColumnDecoder::new(&ColumnType::Int, 1).unwrap();
+        let cell = BytesSequenceIterator::from(FrameSlice::new(&cells))
+            .next()
+            .unwrap()
+            .unwrap();
+        assert!(decoder.append(cell).is_err());
+
+        // A list claiming more elements than it has
+        let mut list = serialize(
+            &ColumnType::List(Box::new(ColumnType::Int)),
+            &[Some(CqlValue::List(vec![CqlValue::Int(1)]))],
+        );
+        list[7] = 2;
+        let list = Bytes::from(list);
+        let mut decoder =
+            ColumnDecoder::new(&ColumnType::List(Box::new(ColumnType::Int)), 1).unwrap();
+        let cell = BytesSequenceIterator::from(FrameSlice::new(&list))
+            .next()
+            .unwrap()
+            .unwrap();
+        assert!(decoder.append(cell).is_err());
+    }
+
+    #[test]
+    fn collection_conversions() {
+        let lists = build_array(
+            &ColumnType::List(Box::new(ColumnType::Int)),
+            &[
+                Some(CqlValue::List(vec![CqlValue::Int(1), CqlValue::Int(2)])),
+                None,
+                Some(CqlValue::List(vec![CqlValue::Int(3)])),
+            ],
+        );
+        let lists = lists.as_any().downcast_ref::<ListArray>().unwrap();
+        assert_eq!(lists.value_offsets(), &[0, 2, 2, 3]);
+        assert!(lists.is_null(1));
+        let elements = lists
+            .values()
+            .as_any()
+            .downcast_ref::<Int32Array>()
+            .unwrap();
+        assert_eq!(elements.values(), &[1, 2, 3]);
+
+        let maps = build_array(
+            &ColumnType::Map(Box::new(ColumnType::Text), Box::new(ColumnType::Int)),
+            &[Some(CqlValue::Map(vec![
+                (CqlValue::Text("a".to_owned()), CqlValue::Int(1)),
+                (CqlValue::Text("b".to_owned()), CqlValue::Int(2)),
+            ]))],
+        );
+        let maps = maps.as_any().downcast_ref::<MapArray>().unwrap();
+        assert_eq!(maps.value_offsets(), &[0, 2]);
+        let keys = maps.keys().as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(keys.value(1), "b");
+    }
+
+    #[test]
+    fn struct_conversions() {
+        let mut cells = serialize(
+            &udt_type(),
+            &[
+                Some(CqlValue::UserDefinedType {
+                    keyspace: "ks".to_owned(),
+                    type_name: "address".to_owned(),
+                    fields: vec![
+                        ("street".to_owned(), Some(CqlValue::Text("Main".to_owned()))),
+                        ("number".to_owned(), None),
+                    ],
+                }),
+                None,
+            ],
+        );
+        // A value written before the `number` field was added.
+        let old_udt_type = ColumnType::UserDefinedType {
+            type_name: "address".to_owned(),
+            keyspace: "ks".to_owned(),
+            field_types: vec![("street".to_owned(), ColumnType::Text)],
+        };
+        cells.extend(serialize(
+            &old_udt_type,
+            &[Some(CqlValue::UserDefinedType {
+                keyspace: "ks".to_owned(),
+                type_name: "address".to_owned(),
+                fields: vec![("street".to_owned(), Some(CqlValue::Text("Side".to_owned())))],
+            })],
+        ));
+        let udts = decode(&udt_type(), cells);
+        let udts = udts.as_any().downcast_ref::<StructArray>().unwrap();
+        assert!(udts.is_null(1));
+        let streets = udts
+            .column_by_name("street")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(streets.value(0), "Main");
+        assert_eq!(streets.value(2), "Side");
+        let numbers = udts.column_by_name("number").unwrap();
+        assert_eq!(numbers.null_count(), 3);
+
+        let tuples = build_array(
+            &ColumnType::Tuple(vec![ColumnType::Int, ColumnType::Text]),
+            &[Some(CqlValue::Tuple(vec![Some(CqlValue::Int(5)), None]))],
+        );
+        let tuples = tuples.as_any().downcast_ref::<StructArray>().unwrap();
+        assert_eq!(tuples.column_names(), vec!["0", "1"]);
+        assert_eq!(tuples.column(1).null_count(), 1);
+    }
+
+    #[test]
+    fn finish_starts_a_new_array() {
+        let typ = ColumnType::List(Box::new(ColumnType::Int));
+        let cells = Bytes::from(serialize(
+            &typ,
+            &[
+                Some(CqlValue::List(vec![CqlValue::Int(1)])),
+                Some(CqlValue::List(vec![CqlValue::Int(2), CqlValue::Int(3)])),
+            ],
+        ));
+        let mut decoder = ColumnDecoder::new(&typ, 1).unwrap();
+        let mut lists = Vec::new();
+        for cell in BytesSequenceIterator::from(FrameSlice::new(&cells)) {
+            decoder.append(cell.unwrap()).unwrap();
+            lists.push(decoder.finish().unwrap());
+        }
+        let second = lists[1].as_any().downcast_ref::<ListArray>().unwrap();
+        assert_eq!(second.value_offsets(), &[0, 2]);
+        assert_eq!(second.values().len(), 2);
+    }
+}
@@ -9,10 +9,12 @@ use bytes::Bytes;
 use dashmap::DashMap;
 use futures::future::try_join_all;
 use scylla_cql::frame::response::result::{PreparedMetadata, ResultMetadata};
+use scylla_cql::types::column_encryption::BindMarkersEncryption;
 use scylla_cql::types::serialize::batch::BatchValues;
 use scylla_cql::types::serialize::row::SerializeRow;
 use std::collections::hash_map::RandomState;
 use std::hash::BuildHasher;
+use std::sync::Arc;
 
 /// Contains just the parts of a prepared statement that were returned
 /// from the database. All remaining parts (query string, page size,
@@ -25,6 +27,7 @@ struct RawPreparedStatementData {
     metadata: PreparedMetadata,
     result_metadata: ResultMetadata,
     partitioner_name: PartitionerName,
+    column_encryption: Option<Arc<BindMarkersEncryption>>,
 }
 
 /// Provides auto caching while executing queries
@@ -175,6 +178,7 @@ where
                 query.config,
             );
             stmt.set_partitioner_name(raw.partitioner_name.clone());
+            stmt.set_column_encryption(raw.column_encryption.clone());
             Ok(stmt)
         } else {
             let query_contents = query.contents.clone();
@@ -199,6 +203,7 @@ where
                 metadata: prepared.get_prepared_metadata().clone(),
                 result_metadata: prepared.get_result_metadata().clone(),
                 partitioner_name: prepared.get_partitioner_name().clone(),
+                column_encryption: prepared.get_column_encryption().cloned(),
             };
             self.cache.insert(query_contents, raw);
 
@@ -0,0 +1,119 @@
+//! Cancelling requests which are in progress.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::Notify;
+
+/// A handle which cancels the requests of the statements it is attached to.
+///
+/// The handle is attached to a statement with e.g.
+/// [`Query::set_cancel_handle`](crate::query::Query::set_cancel_handle).
+/// After [`cancel`](CancelHandle::cancel) is called, the requests of the statement
+/// which are in progress, as well as further requests, fail with
+/// [`QueryError::RequestCancelled`](crate::transport::errors::QueryError::RequestCancelled).
+/// The driver doesn't retry cancelled requests, nor does it send speculative executions for them.
+///
+/// Dropping the future of a request has the same effect on that request.
+///
+/// The CQL protocol has no way of telling the database to stop working on a request
+/// which was already sent, so a cancelled request may still be applied.
+///
+/// # Example
+/// ```rust
+/// # use scylla::Session;
+/// # use std::error::Error;
+/// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+/// use scylla::query::Query;
+/// use scylla::transport::cancellation::CancelHandle;
+///
+/// let handle = CancelHandle::new();
+/// let mut query = Query::new("SELECT a FROM ks.tab");
+/// query.set_cancel_handle(Some(handle.clone()));
+///
+/// tokio::spawn(async move {
+///     tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+///     handle.cancel();
+/// });
+/// let result = session.query(query, &[]).await;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CancelHandle {
+    inner: Arc<CancelHandleInner>,
+}
+
+#[derive(Debug, Default)]
+struct CancelHandleInner {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelHandle {
+    /// Creates a new handle, which is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the requests of all the statements the handle is attached to.
+    /// Cancellation can't be undone.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::Release);
+        self.inner.notify.notify_waiters();
+    }
+
+    /// Checks if [`cancel`](CancelHandle::cancel) was called.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Completes when the handle is cancelled.
+    pub(crate) async fn cancelled(&self) {
+        // The future has to be created before checking the flag,
+        // so that a concurrent `cancel()` is not missed.
+        let notified = self.inner.notify.notified();
+        if self.is_cancelled() {
+            return;
+        }
+        notified.await;
+    }
+}
+
+/// Completes when the handle, if any, is cancelled.
+pub(crate) async fn cancelled(handle: Option<&CancelHandle>) {
+    match handle {
+        Some(handle) => handle.cancelled().await,
+        None => futures::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::CancelHandle;
+
+    #[tokio::test]
+    async fn cancel_wakes_waiters() {
+        let handle = CancelHandle::new();
+        assert!(!handle.is_cancelled());
+
+        let waiter = tokio::spawn({
+            let handle = handle.clone();
+            async move { handle.cancelled().await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        handle.cancel();
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(handle.is_cancelled());
+        // Already cancelled handles complete right away.
+        tokio::time::timeout(Duration::from_secs(1), handle.cancelled())
+            .await
+            .unwrap();
+    }
+}
@@ -2,14 +2,16 @@
 use crate::frame::response::event::{Event, StatusChangeEvent};
 use crate::prepared_statement::TokenCalculationError;
 use crate::routing::{Shard, Token};
+use crate::transport::control_connection::{ControlConnectionConfig, ReconnectBackoff};
 use crate::transport::host_filter::HostFilter;
 use crate::transport::session::TABLET_CHANNEL_SIZE;
 use crate::transport::{
     connection::{Connection, VerifiedKeyspaceName},
     connection_pool::PoolConfig,
-    errors::QueryError,
+    errors::{ConnectionError, QueryError},
     node::Node,
     partitioner::PartitionerName,
+    runtime,
     topology::{Keyspace, Metadata, MetadataReader},
 };
 
@@ -21,6 +23,7 @@ use scylla_cql::errors::{BadQuery, NewSessionError};
 use scylla_cql::frame::response::result::TableSpec;
 use scylla_cql::types::serialize::row::SerializedValues;
 use std::collections::{HashMap, HashSet};
+use std::io;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
@@ -28,11 +31,12 @@ use tracing::{debug, warn};
 use uuid::Uuid;
 
 use super::locator::tablets::{RawTablet, Tablet, TabletsInfo};
-use super::node::{KnownNode, NodeAddr};
+use super::node::{DnsResolver, KnownNode, NodeAddr};
 use super::NodeRef;
 
 use super::locator::ReplicaLocator;
 use super::partitioner::calculate_token_for_partition_key;
+use super::ring_report::RingReport;
 use super::topology::Strategy;
 
 /// Cluster manages up to date information and connections to database nodes.
@@ -131,6 +135,10 @@ struct ClusterWorker {
     // This value determines how frequently the cluster
     // worker will refresh the cluster metadata
     cluster_metadata_refresh_interval: Duration,
+
+    // Determines how frequently the cluster worker attempts
+    // to repair a broken control connection
+    control_connection_reconnect_backoff: ReconnectBackoff,
 }
 
 #[derive(Debug)]
@@ -145,6 +153,7 @@ struct UseKeyspaceRequest {
 }
 
 impl Cluster {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) async fn new(
         known_nodes: Vec<KnownNode>,
         pool_config: PoolConfig,
@@ -152,6 +161,7 @@ impl Cluster {
         fetch_schema_metadata: bool,
         host_filter: Option<Arc<dyn HostFilter>>,
         cluster_metadata_refresh_interval: Duration,
+        control_connection_config: ControlConnectionConfig,
         tablet_receiver: tokio::sync::mpsc::Receiver<(TableSpec<'static>, RawTablet)>,
     ) -> Result<Cluster, NewSessionError> {
         let (refresh_sender, refresh_receiver) = tokio::sync::mpsc::channel(32);
@@ -162,6 +172,9 @@ impl Cluster {
 
         let mut metadata_reader = MetadataReader::new(
             known_nodes,
+            Arc::new(DnsResolver::new(
+                pool_config.connection_config.runtime.clone(),
+            )),
             control_connection_repair_sender,
             pool_config.connection_config.clone(),
             pool_config.keepalive_interval,
@@ -169,6 +182,7 @@ impl Cluster {
             keyspaces_to_fetch,
             fetch_schema_metadata,
             &host_filter,
+            control_connection_config.clone(),
         )
         .await?;
 
@@ -181,7 +195,7 @@ impl Cluster {
             host_filter.as_deref(),
             TabletsInfo::new(),
         )
-        .await;
+        .await?;
         cluster_data.wait_until_all_pools_are_initialized().await;
         let cluster_data: Arc<ArcSwap<ClusterData>> =
             Arc::new(ArcSwap::from(Arc::new(cluster_data)));
@@ -202,10 +216,12 @@ impl Cluster {
 
             host_filter,
             cluster_metadata_refresh_interval,
+            control_connection_reconnect_backoff: ReconnectBackoff::new(&control_connection_config),
         };
 
+        let runtime = worker.pool_config.connection_config.runtime.clone();
         let (fut, worker_handle) = worker.work().remote_handle();
-        tokio::spawn(fut);
+        runtime.spawn(Box::pin(fut));
 
         let result = Cluster {
             data: cluster_data,
@@ -278,6 +294,7 @@ impl ClusterData {
 
     /// Creates new ClusterData using information about topology held in `metadata`.
     /// Uses provided `known_peers` hashmap to recycle nodes if possible.
+    /// Fails if the replica locator could not be built on the runtime.
     pub(crate) async fn new(
         metadata: Metadata,
         pool_config: &PoolConfig,
@@ -285,7 +302,7 @@ impl ClusterData {
         used_keyspace: &Option<VerifiedKeyspaceName>,
         host_filter: Option<&dyn HostFilter>,
         mut tablets: TabletsInfo,
-    ) -> Self {
+    ) -> io::Result<Self> {
         // Create new updated known_peers and ring
         let mut new_known_peers: HashMap<Uuid, Arc<Node>> =
             HashMap::with_capacity(metadata.peers.len());
@@ -388,19 +405,19 @@ impl ClusterData {
         Self::update_rack_count(&mut datacenters);
 
         let keyspaces = metadata.keyspaces;
-        let (locator, keyspaces) = tokio::task::spawn_blocking(move || {
-            let keyspace_strategies = keyspaces.values().map(|ks| &ks.strategy);
-            let locator = ReplicaLocator::new(ring.into_iter(), keyspace_strategies, tablets);
-            (locator, keyspaces)
-        })
-        .await
-        .unwrap();
+        let (locator, keyspaces) =
+            runtime::spawn_blocking(&*pool_config.connection_config.runtime, move || {
+                let keyspace_strategies = keyspaces.values().map(|ks| &ks.strategy);
+                let locator = ReplicaLocator::new(ring.into_iter(), keyspace_strategies, tablets);
+                (locator, keyspaces)
+            })
+            .await?;
 
-        ClusterData {
+        Ok(ClusterData {
             known_peers: new_known_peers,
             keyspaces,
             locator,
-        }
+        })
     }
 
     /// Access keyspaces details collected by the driver
@@ -501,6 +518,13 @@ impl ClusterData {
         &self.locator
     }
 
+    /// Exports a report of the token ring: token ranges of the nodes, their
+    /// datacenter and rack layout, and the replica sets of the keyspaces.
+    /// See the [ring_report](crate::transport::ring_report) module for details.
+    pub fn ring_report(&self) -> RingReport {
+        RingReport::new(self)
+    }
+
     /// Returns nonempty iterator of working connections to all shards.
     pub(crate) fn iter_working_connections(
         &self,
@@ -556,7 +580,6 @@ impl ClusterWorker {
     pub(crate) async fn work(mut self) {
         use tokio::time::Instant;
 
-        let control_connection_repair_duration = Duration::from_secs(1); // Attempt control connection repair every second
         let mut last_refresh_time = Instant::now();
         let mut control_connection_works = true;
 
@@ -568,14 +591,17 @@ impl ClusterWorker {
                 .checked_add(if control_connection_works {
                     self.cluster_metadata_refresh_interval
                 } else {
-                    control_connection_repair_duration
+                    self.control_connection_reconnect_backoff.get_interval()
                 })
                 .unwrap_or_else(Instant::now);
 
             let mut tablets = Vec::new();
 
-            let sleep_future = tokio::time::sleep_until(sleep_until);
-            tokio::pin!(sleep_future);
+            let sleep_future = self
+                .pool_config
+                .connection_config
+                .runtime
+                .sleep(sleep_until.saturating_duration_since(Instant::now()));
 
             tokio::select! {
                 _ = sleep_future => {},
@@ -646,7 +672,7 @@ impl ClusterWorker {
 
                             let cluster_data = self.cluster_data.load_full();
                             let use_keyspace_future = Self::handle_use_keyspace_request(cluster_data, request);
-                            tokio::spawn(use_keyspace_future);
+                            self.pool_config.connection_config.runtime.spawn(Box::pin(use_keyspace_future));
                         },
                         None => return, // If use_keyspace_channel was closed then cluster was dropped, we can stop working
                     }
@@ -659,7 +685,7 @@ impl ClusterWorker {
                             // The control connection was broken. Acknowledge that and start attempting to reconnect.
                             // The first reconnect attempt will be immediate (by attempting metadata refresh below),
                             // and if it does not succeed, then `control_connection_works` will be set to `false`,
-                            // so subsequent attempts will be issued every second.
+                            // so subsequent attempts will be issued with the configured backoff.
                         }
                         Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {
                             // This is very unlikely; we would have to have a lot of concurrent
@@ -680,7 +706,15 @@ impl ClusterWorker {
             last_refresh_time = Instant::now();
             let refresh_res = self.perform_refresh().await;
 
+            let control_connection_worked = control_connection_works;
             control_connection_works = refresh_res.is_ok();
+            if control_connection_works {
+                self.control_connection_reconnect_backoff.on_success();
+            } else if !control_connection_worked {
+                // The first reconnect attempt is made after the minimal interval,
+                // and the interval grows with each subsequent failed attempt.
+                self.control_connection_reconnect_backoff.on_failure();
+            }
 
             // Send refresh result if there was a request
             if let Some(request) = cur_request {
@@ -739,13 +773,16 @@ impl ClusterWorker {
         // If there was an error different than IoError return this error - something is wrong
 
         let mut was_ok: bool = false;
-        let mut io_error: Option<Arc<std::io::Error>> = None;
+        let mut io_error: Option<QueryError> = None;
 
         for result in use_keyspace_results {
             match result {
                 Ok(()) => was_ok = true,
                 Err(err) => match err {
-                    QueryError::IoError(io_err) => io_error = Some(io_err),
+                    QueryError::ConnectionError {
+                        error: ConnectionError::IoError(_),
+                        ..
+                    } => io_error = Some(err),
                     _ => return Err(err),
                 },
             }
@@ -756,7 +793,7 @@ impl ClusterWorker {
         }
 
         // We can unwrap io_error because use_keyspace_futures must be nonempty
-        Err(QueryError::IoError(io_error.unwrap()))
+        Err(io_error.unwrap())
     }
 
     async fn perform_refresh(&mut self) -> Result<(), QueryError> {
@@ -773,7 +810,7 @@ impl ClusterWorker {
                 self.host_filter.as_deref(),
                 cluster_data.locator.tablets.clone(),
             )
-            .await,
+            .await?,
         );
 
         new_cluster_data
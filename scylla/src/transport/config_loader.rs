@@ -0,0 +1,550 @@
+//! Loading [`Session`](crate::Session) configuration from a file or from environment variables.
+//!
+//! See `SessionBuilder::from_config_file` (requires the `config-file` feature)
+//! and [`SessionBuilder::from_env`](crate::SessionBuilder::from_env).
+//!
+//! Both sources support the same set of options. In a file they are top-level keys,
+//! and in the environment they are upper-cased and prefixed with `SCYLLA_`,
+//! e.g. `connection_timeout_ms` is read from `SCYLLA_CONNECTION_TIMEOUT_MS`.
+//!
+//! | Option                   | Type              | Meaning                                              |
+//! |--------------------------|-------------------|------------------------------------------------------|
+//! | `known_nodes`            | list of strings   | Contact points (comma separated in the environment)  |
+//! | `keyspace`               | string            | Keyspace to use                                      |
+//! | `keyspace_case_sensitive`| bool              | Whether the keyspace name is case sensitive          |
+//! | `username`, `password`   | string            | Credentials for plain text authentication            |
+//! | `tls_enabled`            | bool              | Connect using TLS (implied by any of the TLS paths)  |
+//! | `tls_ca_file`            | path              | PEM file with trusted certificate authorities        |
+//! | `tls_cert_file`          | path              | PEM file with the client certificate                 |
+//! | `tls_key_file`           | path              | PEM file with the client private key                 |
+//! | `tls_verify_peer`        | bool              | Whether to verify the server certificate (default: true) |
+//! | `compression`            | string            | `none`, `lz4`, `snappy` or `zstd`                    |
+//! | `connection_timeout_ms`  | integer           | Connection setup timeout                             |
+//! | `request_timeout_ms`     | integer           | Request timeout of the default execution profile     |
+//! | `pool_size_per_host`     | integer           | Number of connections per node                       |
+//! | `pool_size_per_shard`    | integer           | Number of connections per shard                      |
+//! | `local_datacenter`       | string            | Preferred datacenter of the load balancing policy    |
+//! | `local_rack`             | string            | Preferred rack (requires `local_datacenter`)         |
+//! | `token_aware`            | bool              | Whether the load balancing is token aware            |
+//! | `permit_dc_failover`     | bool              | Whether remote datacenters may be used on failure    |
+
+use std::ffi::OsString;
+use std::io;
+use std::num::NonZeroUsize;
+#[cfg(feature = "config-file")]
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use thiserror::Error;
+
+use crate::load_balancing::DefaultPolicy;
+use crate::transport::connection_pool::PoolSize;
+use crate::transport::session_builder::SessionBuilder;
+use crate::transport::{Compression, ExecutionProfile};
+
+/// Prefix of environment variables read by [`SessionBuilder::from_env`].
+pub const ENV_PREFIX: &str = "SCYLLA_";
+
+/// An error that occurred while loading the session configuration
+/// from a file or from environment variables.
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum ConfigLoadError {
+    #[error("Error while reading config file: {0}")]
+    Io(#[from] io::Error),
+
+    #[cfg(feature = "config-file")]
+    #[error("Error while parsing TOML config file: {0}")]
+    TomlParse(#[from] toml::de::Error),
+
+    #[cfg(feature = "config-file")]
+    #[error("Error while parsing YAML config file: {0}")]
+    YamlParse(#[from] serde_yaml::Error),
+
+    #[error(
+        "Unknown format of config file {}, expected a .toml, .yaml or .yml extension",
+        .0.display()
+    )]
+    UnknownFormat(PathBuf),
+
+    #[error("Invalid value of environment variable {name}: {reason}")]
+    InvalidEnvVar { name: String, reason: String },
+
+    #[cfg(feature = "ssl")]
+    #[error("Error while setting up TLS: {0}")]
+    Ssl(#[from] openssl::error::ErrorStack),
+
+    #[error("Invalid driver configuration: {0}")]
+    Invalid(String),
+}
+
+/// Options read from a config file or from the environment.
+/// Options which are not set leave the defaults of [`SessionBuilder`] intact.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "config-file",
+    derive(serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
+pub(crate) struct DriverConfig {
+    known_nodes: Option<Vec<String>>,
+    keyspace: Option<String>,
+    keyspace_case_sensitive: Option<bool>,
+
+    username: Option<String>,
+    password: Option<String>,
+
+    tls_enabled: Option<bool>,
+    tls_ca_file: Option<PathBuf>,
+    tls_cert_file: Option<PathBuf>,
+    tls_key_file: Option<PathBuf>,
+    tls_verify_peer: Option<bool>,
+
+    compression: Option<String>,
+    connection_timeout_ms: Option<u64>,
+    request_timeout_ms: Option<u64>,
+
+    pool_size_per_host: Option<NonZeroUsize>,
+    pool_size_per_shard: Option<NonZeroUsize>,
+
+    local_datacenter: Option<String>,
+    local_rack: Option<String>,
+    token_aware: Option<bool>,
+    permit_dc_failover: Option<bool>,
+}
+
+impl DriverConfig {
+    /// Reads the config from a TOML or YAML file, depending on its extension.
+    #[cfg(feature = "config-file")]
+    pub(crate) fn read_from_file(path: &Path) -> Result<Self, ConfigLoadError> {
+        let contents = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Ok(toml::from_str(&contents)?),
+            Some("yaml" | "yml") => Ok(serde_yaml::from_str(&contents)?),
+            _ => Err(ConfigLoadError::UnknownFormat(path.to_owned())),
+        }
+    }
+
+    /// Reads the config from `SCYLLA_*` environment variables.
+    pub(crate) fn read_from_env() -> Result<Self, ConfigLoadError> {
+        Self::read_from_vars(|name| std::env::var_os(name))
+    }
+
+    fn read_from_vars(var: impl Fn(&str) -> Option<OsString>) -> Result<Self, ConfigLoadError> {
+        let reader = EnvReader { var };
+        Ok(Self {
+            known_nodes: reader.string("KNOWN_NODES")?.map(|nodes| {
+                nodes
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|node| !node.is_empty())
+                    .map(String::from)
+                    .collect()
+            }),
+            keyspace: reader.string("KEYSPACE")?,
+            keyspace_case_sensitive: reader.parse("KEYSPACE_CASE_SENSITIVE")?,
+            username: reader.string("USERNAME")?,
+            password: reader.string("PASSWORD")?,
+            tls_enabled: reader.parse("TLS_ENABLED")?,
+            tls_ca_file: reader.string("TLS_CA_FILE")?.map(PathBuf::from),
+            tls_cert_file: reader.string("TLS_CERT_FILE")?.map(PathBuf::from),
+            tls_key_file: reader.string("TLS_KEY_FILE")?.map(PathBuf::from),
+            tls_verify_peer: reader.parse("TLS_VERIFY_PEER")?,
+            compression: reader.string("COMPRESSION")?,
+            connection_timeout_ms: reader.parse("CONNECTION_TIMEOUT_MS")?,
+            request_timeout_ms: reader.parse("REQUEST_TIMEOUT_MS")?,
+            pool_size_per_host: reader.parse("POOL_SIZE_PER_HOST")?,
+            pool_size_per_shard: reader.parse("POOL_SIZE_PER_SHARD")?,
+            local_datacenter: reader.string("LOCAL_DATACENTER")?,
+            local_rack: reader.string("LOCAL_RACK")?,
+            token_aware: reader.parse("TOKEN_AWARE")?,
+            permit_dc_failover: reader.parse("PERMIT_DC_FAILOVER")?,
+        })
+    }
+
+    /// Applies the options which are set to the given builder.
+    pub(crate) fn apply(
+        self,
+        mut builder: SessionBuilder,
+    ) -> Result<SessionBuilder, ConfigLoadError> {
+        if let Some(known_nodes) = self.known_nodes {
+            builder = builder.known_nodes(known_nodes);
+        }
+
+        if let Some(keyspace) = self.keyspace {
+            let case_sensitive = self.keyspace_case_sensitive.unwrap_or(false);
+            builder = builder.use_keyspace(keyspace, case_sensitive);
+        } else if self.keyspace_case_sensitive.is_some() {
+            return Err(invalid(
+                "keyspace_case_sensitive is set, but keyspace is not",
+            ));
+        }
+
+        match (self.username, self.password) {
+            (Some(username), Some(password)) => builder = builder.user(username, password),
+            (None, None) => {}
+            _ => return Err(invalid("username and password must be set together")),
+        }
+
+        let tls_enabled = self.tls_enabled.unwrap_or(
+            self.tls_ca_file.is_some()
+                || self.tls_cert_file.is_some()
+                || self.tls_key_file.is_some(),
+        );
+        if tls_enabled {
+            #[cfg(feature = "ssl")]
+            {
+                let ssl_context = build_ssl_context(
+                    self.tls_ca_file.as_deref(),
+                    self.tls_cert_file.as_deref(),
+                    self.tls_key_file.as_deref(),
+                    self.tls_verify_peer.unwrap_or(true),
+                )?;
+                builder = builder.ssl_context(Some(ssl_context));
+            }
+            #[cfg(not(feature = "ssl"))]
+            return Err(invalid("TLS options require the `ssl` feature"));
+        }
+
+        if let Some(compression) = self.compression {
+            builder = builder.compression(parse_compression(&compression)?);
+        }
+
+        if let Some(timeout) = self.connection_timeout_ms {
+            builder = builder.connection_timeout(Duration::from_millis(timeout));
+        }
+
+        match (self.pool_size_per_host, self.pool_size_per_shard) {
+            (Some(_), Some(_)) => {
+                return Err(invalid(
+                    "pool_size_per_host and pool_size_per_shard are mutually exclusive",
+                ))
+            }
+            (Some(size), None) => builder = builder.pool_size(PoolSize::PerHost(size)),
+            (None, Some(size)) => builder = builder.pool_size(PoolSize::PerShard(size)),
+            (None, None) => {}
+        }
+
+        let mut policy_builder = DefaultPolicy::builder();
+        let mut policy_configured = false;
+        match (self.local_datacenter, self.local_rack) {
+            (Some(datacenter), Some(rack)) => {
+                policy_builder = policy_builder.prefer_datacenter_and_rack(datacenter, rack);
+                policy_configured = true;
+            }
+            (Some(datacenter), None) => {
+                policy_builder = policy_builder.prefer_datacenter(datacenter);
+                policy_configured = true;
+            }
+            (None, Some(_)) => {
+                return Err(invalid("local_rack is set, but local_datacenter is not"))
+            }
+            (None, None) => {}
+        }
+        if let Some(token_aware) = self.token_aware {
+            policy_builder = policy_builder.token_aware(token_aware);
+            policy_configured = true;
+        }
+        if let Some(permit) = self.permit_dc_failover {
+            policy_builder = policy_builder.permit_dc_failover(permit);
+            policy_configured = true;
+        }
+
+        if policy_configured || self.request_timeout_ms.is_some() {
+            let mut profile_builder = ExecutionProfile::builder();
+            if policy_configured {
+                profile_builder = profile_builder.load_balancing_policy(policy_builder.build());
+            }
+            if let Some(timeout) = self.request_timeout_ms {
+                profile_builder =
+                    profile_builder.request_timeout(Some(Duration::from_millis(timeout)));
+            }
+            builder =
+                builder.default_execution_profile_handle(profile_builder.build().into_handle());
+        }
+
+        Ok(builder)
+    }
+}
+
+fn invalid(reason: &str) -> ConfigLoadError {
+    ConfigLoadError::Invalid(reason.to_owned())
+}
+
+fn parse_compression(compression: &str) -> Result<Option<Compression>, ConfigLoadError> {
+    match compression.to_ascii_lowercase().as_str() {
+        "none" => Ok(None),
+        "lz4" => Ok(Some(Compression::Lz4)),
+        "snappy" => Ok(Some(Compression::Snappy)),
+        "zstd" if Compression::Zstd { level: 0 }.is_supported() => {
+            Ok(Some(Compression::Zstd { level: 0 }))
+        }
+        "zstd" => Err(invalid("zstd compression requires the `zstd` feature")),
+        other => Err(ConfigLoadError::Invalid(format!(
+            "unknown compression {:?}, expected one of: none, lz4, snappy, zstd",
+            other
+        ))),
+    }
+}
+
+#[cfg(feature = "ssl")]
+fn build_ssl_context(
+    ca_file: Option<&std::path::Path>,
+    cert_file: Option<&std::path::Path>,
+    key_file: Option<&std::path::Path>,
+    verify_peer: bool,
+) -> Result<openssl::ssl::SslContext, ConfigLoadError> {
+    use openssl::ssl::{SslContextBuilder, SslFiletype, SslMethod, SslVerifyMode};
+
+    let mut context_builder = SslContextBuilder::new(SslMethod::tls())?;
+    match ca_file {
+        Some(ca_file) => context_builder.set_ca_file(ca_file)?,
+        None => context_builder.set_default_verify_paths()?,
+    }
+    match (cert_file, key_file) {
+        (Some(cert_file), Some(key_file)) => {
+            context_builder.set_certificate_file(cert_file, SslFiletype::PEM)?;
+            context_builder.set_private_key_file(key_file, SslFiletype::PEM)?;
+            context_builder.check_private_key()?;
+        }
+        (None, None) => {}
+        _ => {
+            return Err(invalid(
+                "tls_cert_file and tls_key_file must be set together",
+            ))
+        }
+    }
+    context_builder.set_verify(if verify_peer {
+        SslVerifyMode::PEER
+    } else {
+        SslVerifyMode::NONE
+    });
+    Ok(context_builder.build())
+}
+
+struct EnvReader<F> {
+    var: F,
+}
+
+impl<F: Fn(&str) -> Option<OsString>> EnvReader<F> {
+    fn string(&self, option: &str) -> Result<Option<String>, ConfigLoadError> {
+        let name = format!("{}{}", ENV_PREFIX, option);
+        match (self.var)(&name) {
+            Some(value) => {
+                value
+                    .into_string()
+                    .map(Some)
+                    .map_err(|_| ConfigLoadError::InvalidEnvVar {
+                        name,
+                        reason: "value is not valid unicode".to_owned(),
+                    })
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn parse<T>(&self, option: &str) -> Result<Option<T>, ConfigLoadError>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Display,
+    {
+        match self.string(option)? {
+            Some(value) => value.trim().parse().map(Some).map_err(|err: T::Err| {
+                ConfigLoadError::InvalidEnvVar {
+                    name: format!("{}{}", ENV_PREFIX, option),
+                    reason: err.to_string(),
+                }
+            }),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::ffi::OsString;
+    use std::time::Duration;
+
+    use assert_matches::assert_matches;
+
+    use super::{ConfigLoadError, DriverConfig};
+    use crate::test_utils::setup_tracing;
+    use crate::transport::connection_pool::PoolSize;
+    use crate::transport::Compression;
+    use crate::transport::KnownNode;
+    use crate::SessionBuilder;
+
+    fn read_from_vars(vars: &[(&str, &str)]) -> Result<DriverConfig, ConfigLoadError> {
+        let vars: HashMap<String, OsString> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), OsString::from(value)))
+            .collect();
+        DriverConfig::read_from_vars(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn empty_config_keeps_defaults() {
+        setup_tracing();
+        let config = read_from_vars(&[]).unwrap();
+        assert_eq!(config, DriverConfig::default());
+
+        let builder = config.apply(SessionBuilder::new()).unwrap();
+        let default_builder = SessionBuilder::new();
+        assert!(builder.config.known_nodes.is_empty());
+        assert_eq!(builder.config.compression, None);
+        assert_eq!(
+            builder.config.connect_timeout,
+            default_builder.config.connect_timeout
+        );
+        assert!(builder.config.authenticator.is_none());
+    }
+
+    #[test]
+    fn config_from_env_vars() {
+        setup_tracing();
+        let config = read_from_vars(&[
+            ("SCYLLA_KNOWN_NODES", "127.0.0.1:9042, db1.example.com,"),
+            ("SCYLLA_KEYSPACE", "ks"),
+            ("SCYLLA_USERNAME", "user"),
+            ("SCYLLA_PASSWORD", "pass"),
+            ("SCYLLA_COMPRESSION", "lz4"),
+            ("SCYLLA_CONNECTION_TIMEOUT_MS", "1500"),
+            ("SCYLLA_REQUEST_TIMEOUT_MS", "2500"),
+            ("SCYLLA_POOL_SIZE_PER_SHARD", "2"),
+            ("SCYLLA_LOCAL_DATACENTER", "dc1"),
+            ("SCYLLA_TOKEN_AWARE", "false"),
+        ])
+        .unwrap();
+
+        let builder = config
+            .apply(SessionBuilder::new())
+            .unwrap()
+            // Programmatic options are applied on top of the loaded ones.
+            .known_node("127.0.0.2:9042")
+            .compression(Some(Compression::Snappy));
+
+        assert_eq!(
+            builder.config.known_nodes,
+            vec![
+                KnownNode::Hostname("127.0.0.1:9042".to_owned()),
+                KnownNode::Hostname("db1.example.com".to_owned()),
+                KnownNode::Hostname("127.0.0.2:9042".to_owned()),
+            ]
+        );
+        assert_eq!(builder.config.used_keyspace.as_deref(), Some("ks"));
+        assert!(!builder.config.keyspace_case_sensitive);
+        assert!(builder.config.authenticator.is_some());
+        assert_eq!(builder.config.compression, Some(Compression::Snappy));
+        assert_eq!(builder.config.connect_timeout, Duration::from_millis(1500));
+        assert_matches!(
+            builder.config.connection_pool_size,
+            PoolSize::PerShard(size) if size.get() == 2
+        );
+        assert_eq!(
+            builder
+                .config
+                .default_execution_profile_handle
+                .access()
+                .request_timeout,
+            Some(Duration::from_millis(2500))
+        );
+    }
+
+    #[test]
+    fn invalid_env_vars_are_rejected() {
+        setup_tracing();
+        assert_matches!(
+            read_from_vars(&[("SCYLLA_CONNECTION_TIMEOUT_MS", "soon")]),
+            Err(ConfigLoadError::InvalidEnvVar { name, .. }) if name == "SCYLLA_CONNECTION_TIMEOUT_MS"
+        );
+        assert_matches!(
+            read_from_vars(&[("SCYLLA_POOL_SIZE_PER_HOST", "0")]),
+            Err(ConfigLoadError::InvalidEnvVar { .. })
+        );
+
+        let invalid_configs: &[&[(&str, &str)]] = &[
+            &[("SCYLLA_USERNAME", "user")],
+            &[("SCYLLA_COMPRESSION", "gzip")],
+            &[("SCYLLA_LOCAL_RACK", "rack1")],
+            &[
+                ("SCYLLA_POOL_SIZE_PER_HOST", "1"),
+                ("SCYLLA_POOL_SIZE_PER_SHARD", "1"),
+            ],
+        ];
+        for vars in invalid_configs {
+            let config = read_from_vars(vars).unwrap();
+            assert_matches!(
+                config.apply(SessionBuilder::new()).err(),
+                Some(ConfigLoadError::Invalid(_))
+            );
+        }
+    }
+
+    #[cfg(feature = "config-file")]
+    #[test]
+    fn config_from_toml_and_yaml_files() {
+        setup_tracing();
+        let toml = r#"
+            known_nodes = ["127.0.0.1:9042"]
+            compression = "snappy"
+            pool_size_per_host = 3
+            local_datacenter = "dc1"
+            local_rack = "rack1"
+        "#;
+        let yaml = r#"
+            known_nodes:
+              - 127.0.0.1:9042
+            compression: snappy
+            pool_size_per_host: 3
+            local_datacenter: dc1
+            local_rack: rack1
+        "#;
+        let from_toml: DriverConfig = toml::from_str(toml).unwrap();
+        let from_yaml: DriverConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(from_toml, from_yaml);
+        assert_eq!(from_toml.pool_size_per_host, std::num::NonZeroUsize::new(3));
+
+        let builder = from_toml.apply(SessionBuilder::new()).unwrap();
+        assert_eq!(builder.config.compression, Some(Compression::Snappy));
+        assert_matches!(
+            builder.config.connection_pool_size,
+            PoolSize::PerHost(size) if size.get() == 3
+        );
+
+        assert!(toml::from_str::<DriverConfig>("unknown_option = 1").is_err());
+    }
+
+    #[cfg(feature = "config-file")]
+    #[test]
+    fn config_file_format_is_chosen_by_extension() {
+        setup_tracing();
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!(
+            "scylla_config_loader_test_{}.toml",
+            std::process::id()
+        ));
+        std::fs::write(&path, "known_nodes = [\"127.0.0.1:9042\"]\n").unwrap();
+        let config = DriverConfig::read_from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            config.unwrap().known_nodes,
+            Some(vec!["127.0.0.1:9042".to_owned()])
+        );
+
+        assert_matches!(
+            DriverConfig::read_from_file(&dir.join("config.ini")),
+            Err(ConfigLoadError::Io(_))
+        );
+        let path = dir.join(format!(
+            "scylla_config_loader_test_{}.ini",
+            std::process::id()
+        ));
+        std::fs::write(&path, "").unwrap();
+        let config = DriverConfig::read_from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_matches!(config, Err(ConfigLoadError::UnknownFormat(_)));
+    }
+}
@@ -1,15 +1,19 @@
 use bytes::Bytes;
-use futures::{future::RemoteHandle, FutureExt};
+use futures::{future::RemoteHandle, stream::FuturesUnordered, FutureExt, StreamExt};
 use scylla_cql::errors::TranslationError;
+use scylla_cql::frame::frame_errors::ParseError;
 use scylla_cql::frame::request::options::{self, Options};
 use scylla_cql::frame::response::result::{ResultMetadata, TableSpec};
-use scylla_cql::frame::response::Error;
 use scylla_cql::frame::types::SerialConsistency;
+use scylla_cql::types::column_encryption::ColumnEncryptionPolicy;
+use scylla_cql::types::deserialize::DeserializationError;
 use scylla_cql::types::serialize::batch::{BatchValues, BatchValuesIterator};
 use scylla_cql::types::serialize::raw_batch::RawBatchValuesAdapter;
 use scylla_cql::types::serialize::row::{RowSerializationContext, SerializedValues};
+#[cfg(not(target_family = "wasm"))]
 use socket2::{SockRef, TcpKeepalive};
 use tokio::io::{split, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, BufWriter};
+#[cfg(not(target_family = "wasm"))]
 use tokio::net::{TcpSocket, TcpStream};
 use tokio::sync::{mpsc, oneshot};
 use tokio::time::Instant;
@@ -17,9 +21,10 @@ use tracing::{debug, error, trace, warn};
 use uuid::Uuid;
 
 use std::borrow::Cow;
+use std::num::NonZeroU32;
 #[cfg(feature = "ssl")]
 use std::pin::Pin;
-use std::sync::atomic::AtomicU64;
+use std::sync::atomic::{AtomicU64, AtomicUsize};
 use std::time::Duration;
 #[cfg(feature = "ssl")]
 use tokio_openssl::SslStream;
@@ -29,20 +34,20 @@ pub(crate) use ssl_config::SslConfig;
 
 use crate::authentication::AuthenticatorProvider;
 use scylla_cql::frame::response::authenticate::Authenticate;
+use std::cmp::Ordering;
 use std::collections::{BTreeSet, HashMap, HashSet};
 use std::convert::TryFrom;
 use std::io::ErrorKind;
 use std::net::{IpAddr, SocketAddr};
+#[cfg(not(target_family = "wasm"))]
+use std::net::{Ipv4Addr, Ipv6Addr};
 use std::sync::Arc;
 use std::sync::Mutex as StdMutex;
-use std::{
-    cmp::Ordering,
-    net::{Ipv4Addr, Ipv6Addr},
-};
 
-use super::errors::{BadKeyspaceName, DbError, QueryError};
+use super::errors::{BadKeyspaceName, ConnectionError, DbError, QueryError, TimeoutError};
 use super::iterator::RowIterator;
 use super::locator::tablets::{RawTablet, TabletParsingError};
+use super::node::ResolvedContactPoint;
 use super::query_result::SingleRowTypedError;
 use super::session::AddressTranslator;
 use super::topology::{PeerEndpoint, UntranslatedEndpoint, UntranslatedPeer};
@@ -60,23 +65,29 @@ use crate::frame::{
     FrameParams, SerializedRequest,
 };
 use crate::query::Query;
-use crate::routing::ShardInfo;
+use crate::routing::{Shard, ShardCount, ShardInfo};
 use crate::statement::prepared_statement::PreparedStatement;
 use crate::statement::Consistency;
+use crate::transport::buffer_pool::BufferPool;
+use crate::transport::connector::{ConnectionStream, Connector};
+use crate::transport::frame_capture::{ConnectionCapture, FrameCapture};
+use crate::transport::metrics::Metrics;
+use crate::transport::runtime::{self, default_runtime, Runtime};
 use crate::transport::Compression;
 use crate::QueryResult;
 
 // Queries for schema agreement
 const LOCAL_VERSION: &str = "SELECT schema_version FROM system.local WHERE key='local'";
 
-// FIXME: Make this constants configurable
 // The term "orphan" refers to stream ids, that were allocated for a {request, response} that no
 // one is waiting anymore (due to cancellation of `Connection::send_request`). Old orphan refers to
-// a stream id that is orphaned for a long time. This long time is defined below
-// (`OLD_AGE_ORPHAN_THRESHOLD`). Connection that has a big number (`OLD_ORPHAN_COUNT_THRESHOLD`)
-// of old orphans is shut down (and created again by a connection management layer).
-const OLD_ORPHAN_COUNT_THRESHOLD: usize = 1024;
-const OLD_AGE_ORPHAN_THRESHOLD: std::time::Duration = std::time::Duration::from_secs(1);
+// a stream id that is orphaned for a long time. This long time is defined by
+// `ConnectionConfig::orphan_age_threshold`. Connection that has a big number
+// (`ConnectionConfig::orphan_count_threshold`) of old orphans is shut down
+// (and created again by a connection management layer).
+
+// Total number of stream ids available in a connection.
+const STREAM_IDS_COUNT: usize = i16::MAX as usize + 1;
 
 pub(crate) struct Connection {
     _worker_handle: RemoteHandle<()>,
@@ -99,6 +110,11 @@ struct RouterHandle {
     // pushing values in a synchronous way (without an `.await`), which is
     // needed for pushing values in `Drop` implementations.
     orphan_notification_sender: mpsc::UnboundedSender<RequestId>,
+    compression_threshold: usize,
+    metrics: Option<Arc<Metrics>>,
+    // Buffers for serialized requests, given back by `Connection::writer`
+    // after the requests are written to the socket.
+    buffer_pool: Arc<BufferPool>,
 }
 
 impl RouterHandle {
@@ -112,8 +128,21 @@ impl RouterHandle {
         request: &impl SerializableRequest,
         compression: Option<Compression>,
         tracing: bool,
+        custom_payload: Option<&HashMap<String, Bytes>>,
     ) -> Result<TaskResponse, QueryError> {
-        let serialized_request = SerializedRequest::make(request, compression, tracing)?;
+        let serialized_request = SerializedRequest::make_with_buffer(
+            self.buffer_pool.get(),
+            request,
+            compression,
+            self.compression_threshold,
+            tracing,
+            custom_payload,
+        )?;
+        if let (Some(metrics), Some(uncompressed_len)) =
+            (&self.metrics, serialized_request.uncompressed_body_len())
+        {
+            metrics.log_compressed_request(uncompressed_len, serialized_request.body_len());
+        }
         let request_id = self.allocate_request_id();
 
         let (response_sender, receiver) = oneshot::channel();
@@ -134,17 +163,11 @@ impl RouterHandle {
             })
             .await
             .map_err(|_| {
-                QueryError::IoError(Arc::new(std::io::Error::new(
-                    ErrorKind::Other,
-                    "Connection broken",
-                )))
+                QueryError::from(std::io::Error::new(ErrorKind::Other, "Connection broken"))
             })?;
 
         let task_response = receiver.await.map_err(|_| {
-            QueryError::IoError(Arc::new(std::io::Error::new(
-                ErrorKind::Other,
-                "Connection broken",
-            )))
+            QueryError::from(std::io::Error::new(ErrorKind::Other, "Connection broken"))
         })?;
 
         // Response was successfully received, so it's time to disable
@@ -155,11 +178,51 @@ impl RouterHandle {
     }
 }
 
+/// The request sent by connections as a keepalive (heartbeat).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum KeepaliveRequest {
+    /// An OPTIONS request, which is handled without touching the storage layer.
+    #[default]
+    Options,
+    /// A query which reads the local node's row from `system.local`.
+    /// Unlike OPTIONS, it checks that the node is able to execute queries.
+    LocalQuery,
+}
+
+/// Information about a single connection to a node, as negotiated
+/// in the OPTIONS/SUPPORTED/STARTUP exchange when the connection was opened.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct ConnectionInfo {
+    /// Address the connection was opened to.
+    pub connect_address: SocketAddr,
+    /// Version of the CQL protocol used on the connection.
+    pub protocol_version: u8,
+    /// Compression used on the connection, `None` if frames are not compressed.
+    pub compression: Option<Compression>,
+    /// Shard of the node the connection is attached to, `None` if the node is not sharded.
+    pub shard: Option<Shard>,
+    /// Number of shards of the node, `None` if the node is not sharded.
+    pub nr_shards: Option<ShardCount>,
+    /// Shard-aware port advertised by the node, if any.
+    pub shard_aware_port: Option<u16>,
+    /// Whether the connection was opened to the shard-aware port,
+    /// i.e. the shard it is attached to was chosen by the driver.
+    pub connected_to_shard_aware_port: bool,
+    /// Scylla protocol extensions negotiated on the connection.
+    pub protocol_features: ProtocolFeatures,
+    /// All options received from the node in the SUPPORTED message,
+    /// including the ones interpreted by the driver.
+    pub supported_options: Arc<HashMap<String, Vec<String>>>,
+}
+
 #[derive(Default)]
 pub(crate) struct ConnectionFeatures {
     shard_info: Option<ShardInfo>,
     shard_aware_port: Option<u16>,
     protocol_features: ProtocolFeatures,
+    supported_options: Arc<HashMap<String, Vec<String>>>,
 }
 
 type RequestId = u64;
@@ -213,11 +276,21 @@ struct TaskResponse {
     body: Bytes,
 }
 
+// How the rows of a RESULT response are handed over.
+// Paged iterators keep them serialized and deserialize them lazily,
+// e.g. straight into Arrow arrays.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum RowsFormat {
+    // As `result::Result::Rows`
+    Deserialized,
+    // As `result::Result::RawRows`
+    Serialized,
+}
+
 pub(crate) struct QueryResponse {
     pub(crate) response: Response,
     pub(crate) tracing_id: Option<Uuid>,
     pub(crate) warnings: Vec<String>,
-    #[allow(dead_code)] // This is not exposed to user (yet?)
     pub(crate) custom_payload: Option<HashMap<String, Vec<u8>>>,
 }
 
@@ -226,6 +299,7 @@ pub(crate) struct NonErrorQueryResponse {
     pub(crate) response: NonErrorResponse,
     pub(crate) tracing_id: Option<Uuid>,
     pub(crate) warnings: Vec<String>,
+    pub(crate) custom_payload: Option<HashMap<String, Vec<u8>>>,
 }
 
 impl QueryResponse {
@@ -234,6 +308,7 @@ impl QueryResponse {
             response: self.response.into_non_error_response()?,
             tracing_id: self.tracing_id,
             warnings: self.warnings,
+            custom_payload: self.custom_payload,
         })
     }
 
@@ -280,6 +355,8 @@ impl NonErrorQueryResponse {
             paging_state,
             col_specs,
             serialized_size,
+            custom_payload: self.custom_payload,
+            execution_info: None,
         })
     }
 }
@@ -533,10 +610,18 @@ impl<'id: 'map, 'map> SelfIdentity<'id> {
 }
 
 #[derive(Clone)]
+// TCP options are not used on WebAssembly targets, which can only connect through a connector.
+#[cfg_attr(target_family = "wasm", allow(dead_code))]
 pub(crate) struct ConnectionConfig {
     pub(crate) compression: Option<Compression>,
+    pub(crate) compression_threshold: usize,
     pub(crate) tcp_nodelay: bool,
     pub(crate) tcp_keepalive_interval: Option<Duration>,
+    pub(crate) tcp_keepalive_probe_interval: Duration,
+    pub(crate) tcp_keepalive_retries: u32,
+    pub(crate) tcp_send_buffer_size: Option<u32>,
+    pub(crate) tcp_recv_buffer_size: Option<u32>,
+    pub(crate) local_ip_address: Option<IpAddr>,
     #[cfg(feature = "ssl")]
     pub(crate) ssl_config: Option<SslConfig>,
     pub(crate) connect_timeout: std::time::Duration,
@@ -547,21 +632,46 @@ pub(crate) struct ConnectionConfig {
     pub(crate) cloud_config: Option<Arc<CloudConfig>>,
     pub(crate) authenticator: Option<Arc<dyn AuthenticatorProvider>>,
     pub(crate) address_translator: Option<Arc<dyn AddressTranslator>>,
+    pub(crate) connector: Option<Arc<dyn Connector>>,
+    pub(crate) column_encryption_policy: Option<Arc<ColumnEncryptionPolicy>>,
+    pub(crate) frame_capture: Option<Arc<FrameCapture>>,
+    pub(crate) runtime: Arc<dyn Runtime>,
     pub(crate) enable_write_coalescing: bool,
+    pub(crate) write_buffer_size: usize,
+    pub(crate) max_response_frame_size: Option<usize>,
+    pub(crate) max_rows_per_page: Option<usize>,
+
+    pub(crate) orphan_count_threshold: usize,
+    pub(crate) orphan_age_threshold: Duration,
+    pub(crate) min_free_stream_ids: Option<usize>,
 
     pub(crate) keepalive_interval: Option<Duration>,
     pub(crate) keepalive_timeout: Option<Duration>,
+    pub(crate) keepalive_max_missed: NonZeroU32,
+    pub(crate) keepalive_request: KeepaliveRequest,
+    // Counts the connections of a pool whose last keepalive was missed,
+    // shared by all connections of the pool.
+    pub(crate) keepalive_failures: Option<Arc<KeepaliveFailures>>,
     pub(crate) tablet_sender: Option<mpsc::Sender<(TableSpec<'static>, RawTablet)>>,
 
     pub(crate) identity: SelfIdentity<'static>,
+    pub(crate) custom_startup_options: Arc<HashMap<String, String>>,
+
+    pub(crate) metrics: Option<Arc<Metrics>>,
 }
 
 impl Default for ConnectionConfig {
     fn default() -> Self {
         Self {
             compression: None,
+            compression_threshold: 0,
             tcp_nodelay: true,
             tcp_keepalive_interval: None,
+            tcp_keepalive_probe_interval: Duration::from_secs(1),
+            tcp_keepalive_retries: 10,
+            tcp_send_buffer_size: None,
+            tcp_recv_buffer_size: None,
+            local_ip_address: None,
             event_sender: None,
             #[cfg(feature = "ssl")]
             ssl_config: None,
@@ -569,17 +679,34 @@ impl Default for ConnectionConfig {
             default_consistency: Default::default(),
             authenticator: None,
             address_translator: None,
+            connector: None,
+            column_encryption_policy: None,
+            frame_capture: None,
+            runtime: default_runtime(),
             #[cfg(feature = "cloud")]
             cloud_config: None,
             enable_write_coalescing: true,
+            write_buffer_size: 8192,
+            max_response_frame_size: None,
+            max_rows_per_page: None,
+
+            orphan_count_threshold: 1024,
+            orphan_age_threshold: Duration::from_secs(1),
+            min_free_stream_ids: None,
 
             // Note: this is different than SessionConfig default values.
             keepalive_interval: None,
             keepalive_timeout: None,
+            keepalive_max_missed: NonZeroU32::new(1).unwrap(),
+            keepalive_request: KeepaliveRequest::Options,
+            keepalive_failures: None,
 
             tablet_sender: None,
 
             identity: SelfIdentity::default(),
+            custom_startup_options: Arc::default(),
+
+            metrics: None,
         }
     }
 }
@@ -612,24 +739,9 @@ impl Connection {
         source_port: Option<u16>,
         config: ConnectionConfig,
     ) -> Result<(Self, ErrorReceiver), QueryError> {
-        let stream_connector = match source_port {
-            Some(p) => {
-                tokio::time::timeout(config.connect_timeout, connect_with_source_port(addr, p))
-                    .await
-            }
-            None => tokio::time::timeout(config.connect_timeout, TcpStream::connect(addr)).await,
-        };
-        let stream = match stream_connector {
-            Ok(stream) => stream?,
-            Err(_) => {
-                return Err(QueryError::TimeoutError);
-            }
-        };
-        stream.set_nodelay(config.tcp_nodelay)?;
-
-        if let Some(tcp_keepalive_interval) = config.tcp_keepalive_interval {
-            Self::setup_tcp_keepalive(&stream, tcp_keepalive_interval)?;
-        }
+        let stream = Self::connect_stream(addr, source_port, &config)
+            .await
+            .map_err(|error| error.with_node(addr))?;
 
         // TODO: What should be the size of the channel?
         let (sender, receiver) = mpsc::channel(1024);
@@ -641,6 +753,9 @@ impl Connection {
             submit_channel: sender,
             request_id_generator: AtomicU64::new(0),
             orphan_notification_sender,
+            compression_threshold: config.compression_threshold,
+            metrics: config.metrics.clone(),
+            buffer_pool: Arc::new(BufferPool::new()),
         });
 
         let _worker_handle = Self::run_router(
@@ -650,7 +765,7 @@ impl Connection {
             error_sender,
             orphan_notification_receiver,
             router_handle.clone(),
-            addr.ip(),
+            addr,
         )
         .await?;
 
@@ -665,10 +780,74 @@ impl Connection {
         Ok((connection, error_receiver))
     }
 
-    fn setup_tcp_keepalive(
-        stream: &TcpStream,
-        tcp_keepalive_interval: Duration,
-    ) -> std::io::Result<()> {
+    async fn connect_stream(
+        addr: SocketAddr,
+        source_port: Option<u16>,
+        config: &ConnectionConfig,
+    ) -> Result<Box<dyn ConnectionStream>, QueryError> {
+        let stream: Box<dyn ConnectionStream> = match &config.connector {
+            Some(connector) => {
+                let stream_connector = runtime::timeout(
+                    &*config.runtime,
+                    config.connect_timeout,
+                    connector.connect(addr, source_port),
+                )
+                .await;
+                match stream_connector {
+                    Ok(stream) => stream?,
+                    Err(_) => {
+                        return Err(TimeoutError::Connect.into());
+                    }
+                }
+            }
+            #[cfg(not(target_family = "wasm"))]
+            None => Box::new(Self::connect_tcp(addr, source_port, config).await?),
+            // There are no TCP sockets on WebAssembly targets, the host has to provide
+            // the streams through a connector.
+            #[cfg(target_family = "wasm")]
+            None => {
+                return Err(QueryError::from(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "TCP connections are not available on this target, \
+                    use SessionBuilder::connector to provide a custom connector",
+                )))
+            }
+        };
+        Ok(stream)
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    async fn connect_tcp(
+        addr: SocketAddr,
+        source_port: Option<u16>,
+        config: &ConnectionConfig,
+    ) -> Result<TcpStream, QueryError> {
+        let stream_connector = runtime::timeout(
+            &*config.runtime,
+            config.connect_timeout,
+            connect_tcp_socket(addr, source_port, config),
+        )
+        .await;
+        let stream = match stream_connector {
+            Ok(stream) => stream?,
+            Err(_) => {
+                return Err(TimeoutError::Connect.into());
+            }
+        };
+        stream.set_nodelay(config.tcp_nodelay)?;
+        Self::setup_tcp_keepalive(&stream, config)?;
+
+        Ok(stream)
+    }
+
+    // Enables TCP keepalive if `tcp_keepalive_interval` is configured. The probe interval
+    // and the number of retries are applied only on platforms which support setting them.
+    #[cfg(not(target_family = "wasm"))]
+    fn setup_tcp_keepalive(stream: &TcpStream, config: &ConnectionConfig) -> std::io::Result<()> {
+        let Some(tcp_keepalive_interval) = config.tcp_keepalive_interval else {
+            return Ok(());
+        };
+
         // It may be surprising why we call `with_time()` with `tcp_keepalive_interval`
         // and `with_interval() with some other value. This is due to inconsistent naming:
         // our interval means time after connection becomes idle until keepalives
@@ -693,7 +872,7 @@ impl Connection {
             target_os = "windows",
         ))]
         {
-            tcp_keepalive = tcp_keepalive.with_interval(Duration::from_secs(1));
+            tcp_keepalive = tcp_keepalive.with_interval(config.tcp_keepalive_probe_interval);
         }
 
         #[cfg(any(
@@ -710,7 +889,7 @@ impl Connection {
             target_os = "watchos",
         ))]
         {
-            tcp_keepalive = tcp_keepalive.with_retries(10);
+            tcp_keepalive = tcp_keepalive.with_retries(config.tcp_keepalive_retries);
         }
 
         let sf = SockRef::from(&stream);
@@ -722,14 +901,14 @@ impl Connection {
         options: HashMap<Cow<'_, str>, Cow<'_, str>>,
     ) -> Result<Response, QueryError> {
         Ok(self
-            .send_request(&request::Startup { options }, false, false, None)
+            .send_request(&request::Startup { options }, false, false, None, None)
             .await?
             .response)
     }
 
     pub(crate) async fn get_options(&self) -> Result<Response, QueryError> {
         Ok(self
-            .send_request(&request::Options {}, false, false, None)
+            .send_request(&request::Options {}, false, false, None, None)
             .await?
             .response)
     }
@@ -743,6 +922,7 @@ impl Connection {
                 true,
                 query.config.tracing,
                 None,
+                None,
             )
             .await?;
 
@@ -766,6 +946,14 @@ impl Connection {
             }
         };
 
+        if let Some(policy) = &self.config.column_encryption_policy {
+            prepared_statement.set_column_encryption(
+                policy
+                    .for_bind_markers(prepared_statement.get_variable_col_specs())
+                    .map(Arc::new),
+            );
+        }
+
         if let Some(tracing_id) = query_response.tracing_id {
             prepared_statement.prepare_tracing_ids.push(tracing_id);
         }
@@ -794,8 +982,14 @@ impl Connection {
         &self,
         response: Option<Vec<u8>>,
     ) -> Result<QueryResponse, QueryError> {
-        self.send_request(&request::AuthResponse { response }, false, false, None)
-            .await
+        self.send_request(
+            &request::AuthResponse { response },
+            false,
+            false,
+            None,
+            None,
+        )
+        .await
     }
 
     pub(crate) async fn query_single_page(
@@ -821,9 +1015,16 @@ impl Connection {
         serial_consistency: Option<SerialConsistency>,
     ) -> Result<QueryResult, QueryError> {
         let query: Query = query.into();
-        self.query_with_consistency(&query, consistency, serial_consistency, None)
-            .await?
-            .into_query_result()
+        self.query_with_consistency(
+            &query,
+            consistency,
+            serial_consistency,
+            None,
+            None,
+            RowsFormat::Deserialized,
+        )
+        .await?
+        .into_query_result()
     }
 
     pub(crate) async fn query(
@@ -839,16 +1040,21 @@ impl Connection {
                 .determine_consistency(self.config.default_consistency),
             query.config.serial_consistency.flatten(),
             paging_state,
+            None,
+            RowsFormat::Deserialized,
         )
         .await
     }
 
+    // `default_timestamp` is sent if the statement has no timestamp set.
     pub(crate) async fn query_with_consistency(
         &self,
         query: &Query,
         consistency: Consistency,
         serial_consistency: Option<SerialConsistency>,
         paging_state: Option<Bytes>,
+        default_timestamp: Option<i64>,
+        rows_format: RowsFormat,
     ) -> Result<QueryResponse, QueryError> {
         let query_frame = query::Query {
             contents: Cow::Borrowed(&query.contents),
@@ -859,12 +1065,19 @@ impl Connection {
                 page_size: query.get_page_size(),
                 paging_state,
                 skip_metadata: false,
-                timestamp: query.get_timestamp(),
+                timestamp: query.get_timestamp().or(default_timestamp),
             },
         };
 
-        self.send_request(&query_frame, true, query.config.tracing, None)
-            .await
+        self.send_request_with_rows_format(
+            &query_frame,
+            true,
+            query.config.tracing,
+            None,
+            query.get_custom_payload(),
+            rows_format,
+        )
+        .await
     }
 
     #[allow(dead_code)]
@@ -883,10 +1096,14 @@ impl Connection {
                 .determine_consistency(self.config.default_consistency),
             prepared.config.serial_consistency.flatten(),
             paging_state,
+            None,
+            RowsFormat::Deserialized,
         )
         .await
     }
 
+    // `default_timestamp` is sent if the statement has no timestamp set.
+    #[allow(clippy::too_many_arguments)]
     pub(crate) async fn execute_with_consistency(
         &self,
         prepared_statement: &PreparedStatement,
@@ -894,7 +1111,16 @@ impl Connection {
         consistency: Consistency,
         serial_consistency: Option<SerialConsistency>,
         paging_state: Option<Bytes>,
+        default_timestamp: Option<i64>,
+        rows_format: RowsFormat,
     ) -> Result<QueryResponse, QueryError> {
+        #[cfg(feature = "protocol-validation")]
+        frame::validation::validate_values_count(
+            values.element_count().into(),
+            prepared_statement.get_variable_col_specs().len(),
+        )
+        .map_err(frame::frame_errors::FrameError::from)?;
+
         let execute_frame = execute::Execute {
             id: prepared_statement.get_id().to_owned(),
             parameters: query::QueryParameters {
@@ -902,7 +1128,7 @@ impl Connection {
                 serial_consistency,
                 values: Cow::Borrowed(values),
                 page_size: prepared_statement.get_page_size(),
-                timestamp: prepared_statement.get_timestamp(),
+                timestamp: prepared_statement.get_timestamp().or(default_timestamp),
                 skip_metadata: prepared_statement.get_use_cached_result_metadata(),
                 paging_state,
             },
@@ -913,11 +1139,13 @@ impl Connection {
             .then(|| prepared_statement.get_result_metadata());
 
         let query_response = self
-            .send_request(
+            .send_request_with_rows_format(
                 &execute_frame,
                 true,
                 prepared_statement.config.tracing,
                 cached_metadata,
+                prepared_statement.get_custom_payload(),
+                rows_format,
             )
             .await?;
 
@@ -940,11 +1168,13 @@ impl Connection {
                 self.reprepare(prepared_statement.get_statement(), prepared_statement)
                     .await?;
                 let new_response = self
-                    .send_request(
+                    .send_request_with_rows_format(
                         &execute_frame,
                         true,
                         prepared_statement.config.tracing,
                         cached_metadata,
+                        prepared_statement.get_custom_payload(),
+                        rows_format,
                     )
                     .await?;
 
@@ -965,6 +1195,10 @@ impl Connection {
 
     /// Executes a query and fetches its results over multiple pages, using
     /// the asynchronous iterator interface.
+    pub(crate) fn get_runtime(&self) -> &Arc<dyn Runtime> {
+        &self.config.runtime
+    }
+
     pub(crate) async fn query_iter(
         self: Arc<Self>,
         query: Query,
@@ -1013,24 +1247,25 @@ impl Connection {
                 .config
                 .determine_consistency(self.config.default_consistency),
             batch.config.serial_consistency.flatten(),
+            None,
         )
         .await
     }
 
+    // `default_timestamp` is sent if the batch has no timestamp set.
     pub(crate) async fn batch_with_consistency(
         &self,
         init_batch: &Batch,
         values: impl BatchValues,
         consistency: Consistency,
         serial_consistency: Option<SerialConsistency>,
+        default_timestamp: Option<i64>,
     ) -> Result<QueryResult, QueryError> {
         let batch = self.prepare_batch(init_batch, &values).await?;
 
         let contexts = batch.statements.iter().map(|bs| match bs {
             BatchStatement::Query(_) => RowSerializationContext::empty(),
-            BatchStatement::PreparedStatement(ps) => {
-                RowSerializationContext::from_prepared(ps.get_prepared_metadata())
-            }
+            BatchStatement::PreparedStatement(ps) => ps.serialization_context(),
         });
 
         let values = RawBatchValuesAdapter::new(values, contexts);
@@ -1041,12 +1276,18 @@ impl Connection {
             batch_type: batch.get_type(),
             consistency,
             serial_consistency,
-            timestamp: batch.get_timestamp(),
+            timestamp: batch.get_timestamp().or(default_timestamp),
         };
 
         loop {
             let query_response = self
-                .send_request(&batch_frame, true, batch.config.tracing, None)
+                .send_request(
+                    &batch_frame,
+                    true,
+                    batch.config.tracing,
+                    None,
+                    batch.get_custom_payload(),
+                )
                 .await?;
 
             return match query_response.response {
@@ -1167,7 +1408,7 @@ impl Connection {
         };
 
         match self
-            .send_request(&register_frame, true, false, None)
+            .send_request(&register_frame, true, false, None, None)
             .await?
             .response
         {
@@ -1204,6 +1445,27 @@ impl Connection {
         compress: bool,
         tracing: bool,
         cached_metadata: Option<&ResultMetadata>,
+        custom_payload: Option<&HashMap<String, Bytes>>,
+    ) -> Result<QueryResponse, QueryError> {
+        self.send_request_with_rows_format(
+            request,
+            compress,
+            tracing,
+            cached_metadata,
+            custom_payload,
+            RowsFormat::Deserialized,
+        )
+        .await
+    }
+
+    async fn send_request_with_rows_format(
+        &self,
+        request: &impl SerializableRequest,
+        compress: bool,
+        tracing: bool,
+        cached_metadata: Option<&ResultMetadata>,
+        custom_payload: Option<&HashMap<String, Bytes>>,
+        rows_format: RowsFormat,
     ) -> Result<QueryResponse, QueryError> {
         let compression = if compress {
             self.config.compression
@@ -1213,15 +1475,28 @@ impl Connection {
 
         let task_response = self
             .router_handle
-            .send_request(request, compression, tracing)
+            .send_request(request, compression, tracing, custom_payload)
             .await?;
 
-        Self::parse_response(
+        let mut response = Self::parse_response(
             task_response,
             self.config.compression,
             &self.features.protocol_features,
             cached_metadata,
-        )
+            self.config.max_rows_per_page,
+            rows_format,
+        )?;
+
+        if let Some(policy) = &self.config.column_encryption_policy {
+            let decrypted = match &mut response.response {
+                Response::Result(result::Result::Rows(rows)) => policy.decrypt_rows(rows),
+                Response::Result(result::Result::RawRows(rows)) => policy.decrypt_raw_rows(rows),
+                _ => Ok(()),
+            };
+            decrypted.map_err(|err| ParseError::from(DeserializationError::new(err)))?;
+        }
+
+        Ok(response)
     }
 
     fn parse_response(
@@ -1229,6 +1504,8 @@ impl Connection {
         compression: Option<Compression>,
         features: &ProtocolFeatures,
         cached_metadata: Option<&ResultMetadata>,
+        max_rows: Option<usize>,
+        rows_format: RowsFormat,
     ) -> Result<QueryResponse, QueryError> {
         let body_with_ext = frame::parse_response_body_extensions(
             task_response.params.flags,
@@ -1243,12 +1520,35 @@ impl Connection {
             );
         }
 
-        let response = Response::deserialize(
-            features,
-            task_response.opcode,
-            &mut &*body_with_ext.body,
-            cached_metadata,
-        )?;
+        if let (Some(max), ResponseOpcode::Result) = (max_rows, task_response.opcode) {
+            // Checking the count before deserialization avoids allocating
+            // the rows of a page that is going to be rejected anyway.
+            if let Some(rows) = result::peek_rows_count(&body_with_ext.body)? {
+                if rows > max {
+                    return Err(QueryError::TooManyRows { rows, max });
+                }
+            }
+        }
+
+        #[cfg(feature = "protocol-validation")]
+        frame::validation::validate_response_body(task_response.opcode, &body_with_ext.body)
+            .map_err(frame::frame_errors::FrameError::from)?;
+
+        let raw_rows = match (rows_format, task_response.opcode) {
+            (RowsFormat::Serialized, ResponseOpcode::Result) => {
+                result::RawRows::deserialize(&body_with_ext.body, cached_metadata)?
+            }
+            _ => None,
+        };
+        let response = match raw_rows {
+            Some(raw_rows) => Response::Result(result::Result::RawRows(raw_rows)),
+            None => Response::deserialize(
+                features,
+                task_response.opcode,
+                &mut &*body_with_ext.body,
+                cached_metadata,
+            )?,
+        };
 
         Ok(QueryResponse {
             response,
@@ -1260,13 +1560,15 @@ impl Connection {
 
     async fn run_router(
         config: ConnectionConfig,
-        stream: TcpStream,
+        stream: Box<dyn ConnectionStream>,
         receiver: mpsc::Receiver<Task>,
         error_sender: tokio::sync::oneshot::Sender<QueryError>,
         orphan_notification_receiver: mpsc::UnboundedReceiver<RequestId>,
         router_handle: Arc<RouterHandle>,
-        node_address: IpAddr,
+        node_address: SocketAddr,
     ) -> Result<RemoteHandle<()>, std::io::Error> {
+        let runtime = config.runtime.clone();
+
         #[cfg(feature = "ssl")]
         if let Some(ssl_config) = &config.ssl_config {
             let ssl = ssl_config.new_ssl()?;
@@ -1283,7 +1585,7 @@ impl Connection {
                 node_address,
             )
             .remote_handle();
-            tokio::task::spawn(task);
+            runtime.spawn(Box::pin(task));
             return Ok(handle);
         }
 
@@ -1297,7 +1599,7 @@ impl Connection {
             node_address,
         )
         .remote_handle();
-        tokio::task::spawn(task);
+        runtime.spawn(Box::pin(task));
         Ok(handle)
     }
 
@@ -1308,7 +1610,7 @@ impl Connection {
         error_sender: tokio::sync::oneshot::Sender<QueryError>,
         orphan_notification_receiver: mpsc::UnboundedReceiver<RequestId>,
         router_handle: Arc<RouterHandle>,
-        node_address: IpAddr,
+        node_address: SocketAddr,
     ) {
         let (read_half, write_half) = split(stream);
         // Why are we using a mutex here?
@@ -1323,29 +1625,63 @@ impl Connection {
         // and writer futures are run on the same fiber, and both of them
         // are carefully written in such a way that they do not hold the lock
         // across .await points. Therefore, it should not be too expensive.
-        let handler_map = StdMutex::new(ResponseHandlerMap::new());
+        let handler_map = StdMutex::new(ResponseHandlerMap::new(config.metrics.clone()));
 
         let enable_write_coalescing = config.enable_write_coalescing;
+        let write_buffer_size = config.write_buffer_size;
+        let orphaner_config = OrphanerConfig {
+            orphan_count_threshold: config.orphan_count_threshold,
+            orphan_age_threshold: config.orphan_age_threshold,
+            min_free_stream_ids: config.min_free_stream_ids,
+        };
+
+        let buffer_pool = router_handle.buffer_pool.clone();
+        let runtime = config.runtime.clone();
+        let capture = config
+            .frame_capture
+            .clone()
+            .map(|capture| ConnectionCapture {
+                capture,
+                address: node_address,
+                compression: config.compression,
+            });
+
+        let keepalive_config = KeepaliveConfig {
+            interval: config.keepalive_interval,
+            timeout: config.keepalive_timeout,
+            max_missed: config.keepalive_max_missed,
+            request: config.keepalive_request,
+            compression: config.compression,
+            keepalive_failures: config.keepalive_failures.clone(),
+        };
 
         let k = Self::keepaliver(
             router_handle,
-            config.keepalive_interval,
-            config.keepalive_timeout,
-            node_address,
+            keepalive_config,
+            node_address.ip(),
+            &*runtime,
         );
 
         let r = Self::reader(
             BufReader::with_capacity(8192, read_half),
             &handler_map,
             config,
+            capture.as_ref(),
         );
         let w = Self::writer(
-            BufWriter::with_capacity(8192, write_half),
+            BufWriter::with_capacity(write_buffer_size, write_half),
             &handler_map,
             receiver,
             enable_write_coalescing,
+            &buffer_pool,
+            capture.as_ref(),
+        );
+        let o = Self::orphaner(
+            &handler_map,
+            orphan_notification_receiver,
+            orphaner_config,
+            &*runtime,
         );
-        let o = Self::orphaner(&handler_map, orphan_notification_receiver);
 
         let result = futures::try_join!(r, w, o, k);
 
@@ -1371,13 +1707,37 @@ impl Connection {
         mut read_half: (impl AsyncRead + Unpin),
         handler_map: &StdMutex<ResponseHandlerMap>,
         config: ConnectionConfig,
+        capture: Option<&ConnectionCapture>,
     ) -> Result<(), QueryError> {
         loop {
-            let (params, opcode, body) = frame::read_response_frame(&mut read_half).await?;
-            let response = TaskResponse {
-                params,
-                opcode,
-                body,
+            let (params, opcode, length) =
+                frame::read_response_frame_header(&mut read_half).await?;
+
+            let response = match config.max_response_frame_size {
+                Some(max) if length > max => {
+                    frame::skip_response_frame_body(&mut read_half, length).await?;
+                    warn!(
+                        stream = params.stream,
+                        size = length,
+                        max,
+                        "Discarded a response frame exceeding the maximum size"
+                    );
+                    if params.stream < 0 {
+                        continue;
+                    }
+                    Err(QueryError::ResponseTooLarge { size: length, max })
+                }
+                _ => {
+                    let body = frame::read_response_frame_body(&mut read_half, length).await?;
+                    if let Some(capture) = capture {
+                        capture.record_response(params, opcode, body.clone());
+                    }
+                    Ok(TaskResponse {
+                        params,
+                        opcode,
+                        body,
+                    })
+                }
             };
 
             match params.stream.cmp(&-1) {
@@ -1388,7 +1748,9 @@ impl Connection {
                     continue;
                 }
                 Ordering::Equal => {
-                    if let Some(event_sender) = config.event_sender.as_ref() {
+                    if let (Some(event_sender), Ok(response)) =
+                        (config.event_sender.as_ref(), response)
+                    {
                         Self::handle_event(response, config.compression, event_sender).await?;
                     }
                     continue;
@@ -1409,7 +1771,7 @@ impl Connection {
                     // Don't care if sending of the response fails. This must
                     // mean that the receiver side was impatient and is not
                     // waiting for the result anymore.
-                    let _ = handler.response_sender.send(Ok(response));
+                    let _ = handler.response_sender.send(response);
                 }
                 Missing => {
                     // Unsolicited frame. This should not happen and indicates
@@ -1443,7 +1805,7 @@ impl Connection {
                 error!("Could not allocate stream id");
                 let _ = response_handler
                     .response_sender
-                    .send(Err(QueryError::UnableToAllocStreamId));
+                    .send(Err(ConnectionError::UnableToAllocStreamId.into()));
                 None
             }
         }
@@ -1454,6 +1816,8 @@ impl Connection {
         handler_map: &StdMutex<ResponseHandlerMap>,
         mut task_receiver: mpsc::Receiver<Task>,
         enable_write_coalescing: bool,
+        buffer_pool: &BufferPool,
+        capture: Option<&ConnectionCapture>,
     ) -> Result<(), QueryError> {
         // When the Connection object is dropped, the sender half
         // of the channel will be dropped, this task will return an error
@@ -1467,7 +1831,11 @@ impl Connection {
                 let req_data: &[u8] = req.get_data();
                 total_sent += req_data.len();
                 num_requests += 1;
+                if let Some(capture) = capture {
+                    capture.record_request(req_data);
+                }
                 write_half.write_all(req_data).await?;
+                buffer_pool.put(req.into_buffer());
                 task = match task_receiver.try_recv() {
                     Ok(t) => t,
                     Err(_) if enable_write_coalescing => {
@@ -1492,26 +1860,42 @@ impl Connection {
 
     // This task receives notifications from `OrphanhoodNotifier`s and tries to
     // mark streams as orphaned. It also checks count of old orphans periodically.
-    // After an ald orphan threshold is reached, `orphaner` returns an error
-    // causing the connection to break.
+    // After an old orphan threshold is reached, `orphaner` returns an error
+    // causing the connection to break. The same happens if the number of free
+    // stream ids drops below the configured minimum.
     async fn orphaner(
         handler_map: &StdMutex<ResponseHandlerMap>,
         mut orphan_receiver: mpsc::UnboundedReceiver<RequestId>,
+        config: OrphanerConfig,
+        runtime: &dyn Runtime,
     ) -> Result<(), QueryError> {
-        let mut interval = tokio::time::interval(OLD_AGE_ORPHAN_THRESHOLD);
+        let mut check_timer = runtime.sleep(config.orphan_age_threshold);
         loop {
             tokio::select! {
-                _ = interval.tick() => {
+                _ = &mut check_timer => {
+                    check_timer = runtime.sleep(config.orphan_age_threshold);
                     // We are guaranteed here that handler_map will not be locked
                     // by anybody else, so we can do try_lock().unwrap()
                     let handler_map_guard = handler_map.try_lock().unwrap();
-                    let old_orphan_count = handler_map_guard.old_orphans_count();
-                    if old_orphan_count > OLD_ORPHAN_COUNT_THRESHOLD {
+                    let old_orphan_count =
+                        handler_map_guard.old_orphans_count(config.orphan_age_threshold);
+                    if old_orphan_count > config.orphan_count_threshold {
                         warn!(
                             "Too many old orphaned stream ids: {}",
                             old_orphan_count,
                         );
-                        return Err(QueryError::TooManyOrphanedStreamIds(old_orphan_count as u16))
+                        return Err(ConnectionError::TooManyOrphanedStreamIds(old_orphan_count as u16).into())
+                    }
+                    if let Some(min_free_stream_ids) = config.min_free_stream_ids {
+                        let free_stream_ids = handler_map_guard.free_stream_ids();
+                        if free_stream_ids < min_free_stream_ids {
+                            warn!(
+                                "Too few free stream ids: {} (minimum: {}), closing the connection",
+                                free_stream_ids,
+                                min_free_stream_ids,
+                            );
+                            return Err(ConnectionError::UnableToAllocStreamId.into())
+                        }
                     }
                 }
                 Some(request_id) = orphan_receiver.recv() => {
@@ -1531,59 +1915,110 @@ impl Connection {
 
     async fn keepaliver(
         router_handle: Arc<RouterHandle>,
-        keepalive_interval: Option<Duration>,
-        keepalive_timeout: Option<Duration>,
+        config: KeepaliveConfig,
         node_address: IpAddr, // This address is only used to enrich the log messages
+        runtime: &dyn Runtime,
     ) -> Result<(), QueryError> {
-        async fn issue_keepalive_query(router_handle: &RouterHandle) -> Result<(), QueryError> {
-            router_handle
-                .send_request(&Options, None, false)
-                .await
-                .map(|_| ())
+        async fn issue_keepalive_query(
+            router_handle: &RouterHandle,
+            request: KeepaliveRequest,
+            compression: Option<Compression>,
+        ) -> Result<(), QueryError> {
+            let task_response = match request {
+                KeepaliveRequest::Options => {
+                    router_handle
+                        .send_request(&Options, None, false, None)
+                        .await?
+                }
+                KeepaliveRequest::LocalQuery => {
+                    let query_frame = query::Query {
+                        contents: Cow::Borrowed("SELECT key FROM system.local WHERE key = 'local'"),
+                        parameters: query::QueryParameters {
+                            consistency: Consistency::One,
+                            ..Default::default()
+                        },
+                    };
+                    router_handle
+                        .send_request(&query_frame, None, false, None)
+                        .await?
+                }
+            };
+            // A node which responds with an error (e.g. because it can't read `system.local`)
+            // is not healthy, so the keepalive counts as missed.
+            if task_response.opcode == ResponseOpcode::Error {
+                // As with events, the negotiated protocol features aren't known here.
+                let features = ProtocolFeatures::default();
+                Connection::parse_response(
+                    task_response,
+                    compression,
+                    &features,
+                    None,
+                    None,
+                    RowsFormat::Deserialized,
+                )?
+                .into_non_error_query_response()?;
+            }
+            Ok(())
         }
 
-        if let Some(keepalive_interval) = keepalive_interval {
-            let mut interval = tokio::time::interval(keepalive_interval);
-            interval.tick().await; // Use up the first, instant tick.
-
-            // Default behaviour (Burst) is not suitable for sending keepalives.
-            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
-
-            loop {
-                interval.tick().await;
+        let keepalive_interval = match config.interval {
+            Some(interval) => interval,
+            // No keepalives are to be sent.
+            None => return Ok(()),
+        };
 
-                let keepalive_query = issue_keepalive_query(&router_handle);
-                let query_result = if let Some(timeout) = keepalive_timeout {
-                    match tokio::time::timeout(timeout, keepalive_query).await {
-                        Ok(res) => res,
-                        Err(_) => {
-                            warn!(
+        // Keepalives are sent every `keepalive_interval`. If sending one is delayed,
+        // the next ones are delayed as well, instead of being sent in a burst.
+        let mut next_keepalive = Instant::now() + keepalive_interval;
+        let mut missed_keepalives = 0;
+        // Held while the last keepalive was missed, until the connection is closed
+        let mut keepalive_failure: Option<KeepaliveFailure> = None;
+        loop {
+            runtime
+                .sleep(next_keepalive.saturating_duration_since(Instant::now()))
+                .await;
+            next_keepalive = Instant::now() + keepalive_interval;
+
+            let keepalive_query =
+                issue_keepalive_query(&router_handle, config.request, config.compression);
+            let query_result = if let Some(timeout) = config.timeout {
+                runtime::timeout(runtime, timeout, keepalive_query)
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(QueryError::from(std::io::Error::new(
+                            std::io::ErrorKind::Other,
+                            format!(
                                 "Timed out while waiting for response to keepalive request on connection to node {}",
                                 node_address
-                            );
-                            return Err(QueryError::IoError(Arc::new(std::io::Error::new(
-                                std::io::ErrorKind::Other,
-                                format!(
-                                    "Timed out while waiting for response to keepalive request on connection to node {}",
-                                        node_address
-                                )
-                            ))));
-                        }
-                    }
-                } else {
-                    keepalive_query.await
-                };
-                if let Err(err) = query_result {
+                            ),
+                        )))
+                    })
+            } else {
+                keepalive_query.await
+            };
+
+            match query_result {
+                Ok(()) => {
+                    missed_keepalives = 0;
+                    keepalive_failure = None;
+                }
+                Err(err) => {
+                    missed_keepalives += 1;
                     warn!(
-                        "Failed to execute keepalive request on connection to node {} - {}",
-                        node_address, err
+                        "Failed to execute keepalive request on connection to node {} ({} of {} allowed) - {}",
+                        node_address, missed_keepalives, config.max_missed, err
                     );
-                    return Err(err);
+                    if let Some(failures) = &config.keepalive_failures {
+                        // Counted again even if it was counted before, as the count
+                        // might have been cleared by a successful request since.
+                        drop(keepalive_failure.take());
+                        keepalive_failure = Some(failures.record_failure());
+                    }
+                    if missed_keepalives >= config.max_missed.get() {
+                        return Err(err);
+                    }
                 }
             }
-        } else {
-            // No keepalives are to be sent.
-            Ok(())
         }
     }
 
@@ -1603,7 +2038,15 @@ impl Connection {
         // future implementers.
         let features = ProtocolFeatures::default(); // TODO: Use the right features
 
-        let response = Self::parse_response(task_response, compression, &features, None)?.response;
+        let response = Self::parse_response(
+            task_response,
+            compression,
+            &features,
+            None,
+            None,
+            RowsFormat::Deserialized,
+        )?
+        .response;
         let event = match response {
             Response::Event(e) => e,
             _ => {
@@ -1613,10 +2056,7 @@ impl Connection {
         };
 
         event_sender.send(event).await.map_err(|_| {
-            QueryError::IoError(Arc::new(std::io::Error::new(
-                ErrorKind::Other,
-                "Connection broken",
-            )))
+            QueryError::from(std::io::Error::new(ErrorKind::Other, "Connection broken"))
         })
     }
 
@@ -1636,6 +2076,26 @@ impl Connection {
         self.connect_address
     }
 
+    pub(crate) fn get_info(&self) -> ConnectionInfo {
+        let shard_aware_port = self.features.shard_aware_port;
+        ConnectionInfo {
+            connect_address: self.connect_address,
+            // We only support version 4 for now
+            protocol_version: 4,
+            compression: self.config.compression,
+            shard: self
+                .features
+                .shard_info
+                .as_ref()
+                .map(|info| info.shard.into()),
+            nr_shards: self.features.shard_info.as_ref().map(|info| info.nr_shards),
+            shard_aware_port,
+            connected_to_shard_aware_port: shard_aware_port == Some(self.connect_address.port()),
+            protocol_features: self.features.protocol_features,
+            supported_options: self.features.supported_options.clone(),
+        }
+    }
+
     async fn update_tablets_from_response(
         &self,
         table: &TableSpec<'_>,
@@ -1702,6 +2162,70 @@ async fn maybe_translated_addr(
     }
 }
 
+// The delay after which the next connection attempt is started if the previous ones
+// have neither succeeded nor failed yet, as recommended by RFC 8305.
+const HAPPY_EYEBALLS_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+// Connects to one of the addresses a hostname resolved to, following the Happy Eyeballs
+// algorithm (RFC 8305): the attempts are started in order, each one when the previous
+// attempt fails or after `HAPPY_EYEBALLS_ATTEMPT_DELAY`, and the first established
+// connection is used. The other attempts are then abandoned.
+async fn connect_happy_eyeballs(
+    addresses: &[SocketAddr],
+    source_port: Option<u16>,
+    config: &ConnectionConfig,
+) -> Result<(Connection, ErrorReceiver), QueryError> {
+    // Sockets bound to the configured local address can only connect
+    // to the addresses of the same family, so the others are skipped.
+    // If there are no such addresses, connecting fails with an error which says why.
+    let matching_addresses: Vec<SocketAddr> = match config.local_ip_address {
+        Some(local_ip) if config.connector.is_none() => addresses
+            .iter()
+            .copied()
+            .filter(|addr| is_same_address_family(local_ip, *addr))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let addresses = if matching_addresses.is_empty() {
+        addresses
+    } else {
+        &matching_addresses
+    };
+
+    let mut remaining = addresses.iter();
+    let mut attempts = FuturesUnordered::new();
+    let mut last_error = None;
+
+    loop {
+        match remaining.next() {
+            Some(&addr) => {
+                trace!("Starting connection attempt to {}", addr);
+                attempts.push(Connection::new(addr, source_port, config.clone()));
+            }
+            None if attempts.is_empty() => {
+                return Err(last_error.unwrap_or(QueryError::ProtocolError(
+                    "No addresses to connect to - driver bug!",
+                )));
+            }
+            None => {}
+        }
+
+        let has_remaining = remaining.len() > 0;
+        tokio::select! {
+            Some(result) = attempts.next() => match result {
+                Ok(connection) => return Ok(connection),
+                Err(err) => {
+                    // The next attempt is started right away, without waiting for the delay.
+                    debug!("Connection attempt failed: {}", err);
+                    last_error = Some(err);
+                }
+            },
+            _ = config.runtime.sleep(HAPPY_EYEBALLS_ATTEMPT_DELAY), if has_remaining => {}
+            else => {}
+        }
+    }
+}
+
 /// Opens a connection and performs its setup on CQL level:
 /// - performs OPTIONS/STARTUP handshake (chooses desired connections options);
 /// - registers for all event types using REGISTER request (if this is control connection).
@@ -1712,13 +2236,39 @@ pub(crate) async fn open_connection(
     source_port: Option<u16>,
     config: &ConnectionConfig,
 ) -> Result<(Connection, ErrorReceiver), QueryError> {
-    /* Translate the address, if applicable. */
-    let addr = maybe_translated_addr(endpoint, config.address_translator.as_deref()).await?;
-
     /* Setup connection on TCP level and prepare for sending/receiving CQL frames. */
-    let (mut connection, error_receiver) =
-        Connection::new(addr, source_port, config.clone()).await?;
+    let (mut connection, error_receiver) = match endpoint {
+        UntranslatedEndpoint::ContactPoint(ResolvedContactPoint {
+            address,
+            alternative_addresses,
+            ..
+        }) if !alternative_addresses.is_empty() => {
+            let addresses: Vec<SocketAddr> = std::iter::once(address)
+                .chain(alternative_addresses)
+                .collect();
+            connect_happy_eyeballs(&addresses, source_port, config).await?
+        }
+        endpoint => {
+            /* Translate the address, if applicable. */
+            let addr =
+                maybe_translated_addr(endpoint, config.address_translator.as_deref()).await?;
+            Connection::new(addr, source_port, config.clone()).await?
+        }
+    };
+
+    perform_handshake(&mut connection, config)
+        .await
+        .map_err(|error| error.with_node(connection.get_connect_address()))?;
+
+    Ok((connection, error_receiver))
+}
 
+/// Performs the OPTIONS/SUPPORTED/STARTUP handshake on a freshly opened connection
+/// and, for control connections, registers for events.
+async fn perform_handshake(
+    connection: &mut Connection,
+    config: &ConnectionConfig,
+) -> Result<(), QueryError> {
     /* Perform OPTIONS/SUPPORTED/STARTUP handshake. */
 
     // Get OPTIONS SUPPORTED by the cluster.
@@ -1731,7 +2281,7 @@ pub(crate) async fn open_connection(
 
     let mut supported = match options_result {
         Response::Supported(supported) => supported,
-        Response::Error(Error { error, reason }) => return Err(QueryError::DbError(error, reason)),
+        Response::Error(err) => return Err(err.into()),
         _ => {
             return Err(QueryError::ProtocolError(
                 "Wrong response to OPTIONS message was received",
@@ -1739,6 +2289,8 @@ pub(crate) async fn open_connection(
         }
     };
 
+    let supported_options = Arc::new(supported.options.clone());
+
     // If this is ScyllaDB that we connected to, we received sharding information.
     let shard_info = ShardInfo::try_from(&supported.options).ok();
     let supported_compression = supported
@@ -1761,6 +2313,7 @@ pub(crate) async fn open_connection(
         shard_info,
         shard_aware_port,
         protocol_features,
+        supported_options,
     };
     connection.set_features(features);
 
@@ -1777,10 +2330,27 @@ pub(crate) async fn open_connection(
     // Application & driver's identity.
     config.identity.add_startup_options(&mut options);
 
+    // Custom options, which can't override the ones set by the driver.
+    // `Session::connect` rejects reserved ones, so this only guards connections
+    // opened with a hand-made config.
+    for (key, value) in config.custom_startup_options.iter() {
+        if options::is_reserved_startup_option(key) {
+            continue;
+        }
+        options.insert(Cow::Borrowed(key.as_str()), Cow::Borrowed(value.as_str()));
+    }
+
     // Optional compression.
     if let Some(compression) = &config.compression {
         let compression_str = compression.as_str();
-        if supported_compression.iter().any(|c| c == compression_str) {
+        if !compression.is_supported() {
+            tracing::warn!(
+                "Requested compression <{}> is not supported by this build of the driver, \
+                 enable its crate feature. Falling back to no compression",
+                compression_str
+            );
+            connection.config.compression = None;
+        } else if supported_compression.iter().any(|c| c == compression_str) {
             // Compression is reported to be supported by the server,
             // request it from the server
             options.insert(
@@ -1802,9 +2372,9 @@ pub(crate) async fn open_connection(
     match result {
         Response::Ready => {}
         Response::Authenticate(authenticate) => {
-            perform_authenticate(&mut connection, &authenticate).await?;
+            perform_authenticate(connection, &authenticate).await?;
         }
-        Response::Error(Error { error, reason }) => return Err(QueryError::DbError(error, reason)),
+        Response::Error(err) => return Err(err.into()),
         _ => {
             return Err(QueryError::ProtocolError(
                 "Unexpected response to STARTUP message",
@@ -1822,7 +2392,7 @@ pub(crate) async fn open_connection(
         connection.register(all_event_types).await?;
     }
 
-    Ok((connection, error_receiver))
+    Ok(())
 }
 
 async fn perform_authenticate(
@@ -1878,28 +2448,49 @@ async fn perform_authenticate(
     Ok(())
 }
 
-async fn connect_with_source_port(
+#[cfg(not(target_family = "wasm"))]
+async fn connect_tcp_socket(
     addr: SocketAddr,
-    source_port: u16,
+    source_port: Option<u16>,
+    config: &ConnectionConfig,
 ) -> Result<TcpStream, std::io::Error> {
-    match addr {
-        SocketAddr::V4(_) => {
-            let socket = TcpSocket::new_v4()?;
-            socket.bind(SocketAddr::new(
-                Ipv4Addr::new(0, 0, 0, 0).into(),
-                source_port,
-            ))?;
-            Ok(socket.connect(addr).await?)
-        }
-        SocketAddr::V6(_) => {
-            let socket = TcpSocket::new_v6()?;
-            socket.bind(SocketAddr::new(
-                Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0).into(),
-                source_port,
-            ))?;
-            Ok(socket.connect(addr).await?)
+    let (socket, unspecified_ip): (_, IpAddr) = match addr {
+        SocketAddr::V4(_) => (TcpSocket::new_v4()?, Ipv4Addr::UNSPECIFIED.into()),
+        SocketAddr::V6(_) => (TcpSocket::new_v6()?, Ipv6Addr::UNSPECIFIED.into()),
+    };
+
+    if let Some(size) = config.tcp_send_buffer_size {
+        socket.set_send_buffer_size(size)?;
+    }
+    if let Some(size) = config.tcp_recv_buffer_size {
+        socket.set_recv_buffer_size(size)?;
+    }
+
+    if let Some(local_ip) = config.local_ip_address {
+        if !is_same_address_family(local_ip, addr) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Can't connect to {} from the configured local address {}, \
+                    as their address families differ",
+                    addr, local_ip
+                ),
+            ));
         }
     }
+
+    if source_port.is_some() || config.local_ip_address.is_some() {
+        socket.bind(SocketAddr::new(
+            config.local_ip_address.unwrap_or(unspecified_ip),
+            source_port.unwrap_or(0),
+        ))?;
+    }
+
+    socket.connect(addr).await
+}
+
+fn is_same_address_family(ip: IpAddr, addr: SocketAddr) -> bool {
+    ip.is_ipv4() == addr.is_ipv4()
 }
 
 struct OrphanageTracker {
@@ -1942,12 +2533,103 @@ impl OrphanageTracker {
     }
 }
 
+struct KeepaliveConfig {
+    interval: Option<Duration>,
+    timeout: Option<Duration>,
+    max_missed: NonZeroU32,
+    request: KeepaliveRequest,
+    compression: Option<Compression>,
+    keepalive_failures: Option<Arc<KeepaliveFailures>>,
+}
+
+/// Counts the connections of a pool whose last keepalive was missed. A request which
+/// succeeds on any connection of the pool clears the count, as it shows that the node
+/// is responding.
+#[derive(Debug, Default)]
+pub(crate) struct KeepaliveFailures {
+    state: StdMutex<KeepaliveFailuresState>,
+    // A copy of `state.failing`, which can be read without locking
+    failing: AtomicUsize,
+}
+
+#[derive(Debug, Default)]
+struct KeepaliveFailuresState {
+    failing: usize,
+    // Incremented each time the count is cleared, so that the failures
+    // recorded before that are not subtracted from the count again
+    generation: u64,
+}
+
+impl KeepaliveFailures {
+    /// Returns the number of connections whose last keepalive was missed.
+    pub(crate) fn failing(&self) -> usize {
+        self.failing.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Returns whether the node is suspected to be down: the last keepalive was missed
+    /// on at least half of its `open_connections`. A single connection with missed
+    /// keepalives is not enough if the pool has more of them, as it might be broken
+    /// on its own while the node is fine.
+    pub(crate) fn is_down_suspected(&self, open_connections: usize) -> bool {
+        let failing = self.failing();
+        failing > 0 && failing * 2 >= open_connections
+    }
+
+    pub(crate) fn clear(&self) {
+        if self.failing() == 0 {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.failing = 0;
+        state.generation += 1;
+        self.failing.store(0, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    // Counts the connection as failing until the returned guard is dropped
+    fn record_failure(self: &Arc<Self>) -> KeepaliveFailure {
+        let mut state = self.state.lock().unwrap();
+        state.failing += 1;
+        self.failing
+            .store(state.failing, std::sync::atomic::Ordering::Relaxed);
+        KeepaliveFailure {
+            failures: self.clone(),
+            generation: state.generation,
+        }
+    }
+}
+
+// A connection whose last keepalive was missed.
+struct KeepaliveFailure {
+    failures: Arc<KeepaliveFailures>,
+    generation: u64,
+}
+
+impl Drop for KeepaliveFailure {
+    fn drop(&mut self) {
+        let mut state = self.failures.state.lock().unwrap();
+        if state.generation == self.generation {
+            state.failing -= 1;
+            self.failures
+                .failing
+                .store(state.failing, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+}
+
+struct OrphanerConfig {
+    orphan_count_threshold: usize,
+    orphan_age_threshold: Duration,
+    min_free_stream_ids: Option<usize>,
+}
+
 struct ResponseHandlerMap {
     stream_set: StreamIdSet,
     handlers: HashMap<i16, ResponseHandler>,
 
     request_to_stream: HashMap<RequestId, i16>,
     orphanage_tracker: OrphanageTracker,
+
+    metrics: Option<Arc<Metrics>>,
 }
 
 enum HandlerLookupResult {
@@ -1957,12 +2639,13 @@ enum HandlerLookupResult {
 }
 
 impl ResponseHandlerMap {
-    fn new() -> Self {
+    fn new(metrics: Option<Arc<Metrics>>) -> Self {
         Self {
             stream_set: StreamIdSet::new(),
             handlers: HashMap::new(),
             request_to_stream: HashMap::new(),
             orphanage_tracker: OrphanageTracker::new(),
+            metrics,
         }
     }
 
@@ -1990,12 +2673,18 @@ impl ResponseHandlerMap {
             self.orphanage_tracker.insert(*stream_id);
             self.handlers.remove(stream_id);
             self.request_to_stream.remove(&request_id);
+            if let Some(metrics) = &self.metrics {
+                metrics.inc_orphaned_requests();
+            }
         }
     }
 
-    fn old_orphans_count(&self) -> usize {
-        self.orphanage_tracker
-            .orphans_older_than(OLD_AGE_ORPHAN_THRESHOLD)
+    fn old_orphans_count(&self, age: Duration) -> usize {
+        self.orphanage_tracker.orphans_older_than(age)
+    }
+
+    fn free_stream_ids(&self) -> usize {
+        STREAM_IDS_COUNT - self.stream_set.used_count()
     }
 
     fn lookup(&mut self, stream_id: i16) -> HandlerLookupResult {
@@ -2029,13 +2718,15 @@ impl ResponseHandlerMap {
 
 struct StreamIdSet {
     used_bitmap: Box<[u64]>,
+    used_count: usize,
 }
 
 impl StreamIdSet {
     fn new() -> Self {
-        const BITMAP_SIZE: usize = (i16::MAX as usize + 1) / 64;
+        const BITMAP_SIZE: usize = STREAM_IDS_COUNT / 64;
         Self {
             used_bitmap: vec![0; BITMAP_SIZE].into_boxed_slice(),
+            used_count: 0,
         }
     }
 
@@ -2044,6 +2735,7 @@ impl StreamIdSet {
             if *block != !0 {
                 let off = block.trailing_ones();
                 *block |= 1u64 << off;
+                self.used_count += 1;
                 let stream_id = off as i16 + block_id as i16 * 64;
                 return Some(stream_id);
             }
@@ -2054,8 +2746,15 @@ impl StreamIdSet {
     fn free(&mut self, stream_id: i16) {
         let block_id = stream_id as usize / 64;
         let off = stream_id as usize % 64;
+        if self.used_bitmap[block_id] & (1 << off) != 0 {
+            self.used_count -= 1;
+        }
         self.used_bitmap[block_id] &= !(1 << off);
     }
+
+    fn used_count(&self) -> usize {
+        self.used_count
+    }
 }
 
 /// This type can only hold a valid keyspace name
@@ -2066,10 +2765,21 @@ pub(crate) struct VerifiedKeyspaceName {
 }
 
 impl VerifiedKeyspaceName {
+    /// A name enclosed in double quotes, like in CQL, is treated as case sensitive
+    /// and stripped of the quotes, regardless of `case_sensitive`.
     pub(crate) fn new(
-        keyspace_name: String,
-        case_sensitive: bool,
+        mut keyspace_name: String,
+        mut case_sensitive: bool,
     ) -> Result<Self, BadKeyspaceName> {
+        if keyspace_name.len() >= 2
+            && keyspace_name.starts_with('"')
+            && keyspace_name.ends_with('"')
+        {
+            keyspace_name.pop();
+            keyspace_name.remove(0);
+            case_sensitive = true;
+        }
+
         Self::verify_keyspace_name_is_valid(&keyspace_name)?;
 
         Ok(VerifiedKeyspaceName {
@@ -2082,6 +2792,16 @@ impl VerifiedKeyspaceName {
         self.name.as_str()
     }
 
+    /// Returns the name of the keyspace as seen by the database,
+    /// i.e. lowercased unless the name is case sensitive.
+    pub(crate) fn effective_name(&self) -> String {
+        if self.is_case_sensitive {
+            self.name.as_ref().clone()
+        } else {
+            self.name.to_lowercase()
+        }
+    }
+
     // "Keyspace names can have up to 48 alphanumeric characters and contain underscores;
     // only letters and numbers are supported as the first character."
     // https://docs.datastax.com/en/cql-oss/3.3/cql/cql_reference/cqlCreateKeyspace.html
@@ -2122,10 +2842,11 @@ impl VerifiedKeyspaceName {
 #[cfg(test)]
 mod tests {
     use assert_matches::assert_matches;
-    use scylla_cql::errors::QueryError;
+    use scylla_cql::errors::{ConnectionError, DbError, QueryError};
     use scylla_cql::frame::protocol_features::{
         LWT_OPTIMIZATION_META_BIT_MASK_KEY, SCYLLA_LWT_ADD_METADATA_MARK_EXTENSION,
     };
+    use scylla_cql::frame::request::options;
     use scylla_cql::frame::types;
     use scylla_proxy::{
         Condition, Node, Proxy, Reaction, RequestFrame, RequestOpcode, RequestReaction,
@@ -2135,10 +2856,16 @@ mod tests {
     use tokio::select;
     use tokio::sync::mpsc;
 
-    use super::ConnectionConfig;
+    use super::{
+        connect_happy_eyeballs, Connection, ConnectionConfig, KeepaliveFailures, KeepaliveRequest,
+        ResponseHandler, ResponseHandlerMap,
+    };
+    use crate::frame::response::Response;
     use crate::query::Query;
+    use crate::routing::{ShardCount, ShardInfo};
     use crate::test_utils::setup_tracing;
     use crate::transport::connection::open_connection;
+    use crate::transport::connector::{ConnectionStream, Connector};
     use crate::transport::node::ResolvedContactPoint;
     use crate::transport::topology::UntranslatedEndpoint;
     use crate::utils::test_utils::unique_keyspace_name;
@@ -2146,6 +2873,7 @@ mod tests {
     use futures::{StreamExt, TryStreamExt};
     use std::collections::HashMap;
     use std::net::SocketAddr;
+    use std::num::NonZeroU32;
     use std::sync::Arc;
     use std::time::Duration;
 
@@ -2163,6 +2891,73 @@ mod tests {
         }
     }
 
+    struct InMemoryConnector;
+
+    #[async_trait::async_trait]
+    impl Connector for InMemoryConnector {
+        async fn connect(
+            &self,
+            _address: SocketAddr,
+            _source_port: Option<u16>,
+        ) -> std::io::Result<Box<dyn ConnectionStream>> {
+            let (client_side, server_side) = tokio::io::duplex(1024);
+            // Nothing is ever sent, the server side only has to stay open
+            std::mem::forget(server_side);
+            Ok(Box::new(client_side))
+        }
+    }
+
+    impl Connection {
+        // Opens a connection to nowhere, as if it was attached to the given shard
+        // of a node advertising the given shard-aware port.
+        pub(crate) async fn new_for_test(
+            shard_info: Option<super::ShardInfo>,
+            shard_aware_port: Option<u16>,
+        ) -> (Connection, super::ErrorReceiver) {
+            let config = ConnectionConfig {
+                connector: Some(Arc::new(InMemoryConnector)),
+                ..Default::default()
+            };
+            let (mut connection, error_receiver) =
+                Connection::new("127.0.0.1:9042".parse().unwrap(), None, config)
+                    .await
+                    .unwrap();
+            connection.set_features(super::ConnectionFeatures {
+                shard_info,
+                shard_aware_port,
+                ..Default::default()
+            });
+            (connection, error_receiver)
+        }
+    }
+
+    #[tokio::test]
+    async fn connection_info_reports_negotiated_features() {
+        setup_tracing();
+        let (connection, _error_receiver) = Connection::new_for_test(None, None).await;
+        let info = connection.get_info();
+        assert_eq!(info.connect_address, "127.0.0.1:9042".parse().unwrap());
+        assert_eq!(info.protocol_version, 4);
+        assert_eq!(info.compression, None);
+        assert_eq!((info.shard, info.nr_shards), (None, None));
+        assert_eq!(info.shard_aware_port, None);
+        assert!(!info.connected_to_shard_aware_port);
+
+        let nr_shards = ShardCount::new(4).unwrap();
+        let shard_info = ShardInfo::new(3, nr_shards, 12);
+        // Connections to the regular port are attached to a shard chosen by the node
+        let (connection, _error_receiver) =
+            Connection::new_for_test(Some(shard_info.clone()), Some(19042)).await;
+        let info = connection.get_info();
+        assert_eq!((info.shard, info.nr_shards), (Some(3), Some(nr_shards)));
+        assert_eq!(info.shard_aware_port, Some(19042));
+        assert!(!info.connected_to_shard_aware_port);
+
+        let (connection, _error_receiver) =
+            Connection::new_for_test(Some(shard_info), Some(9042)).await;
+        assert!(connection.get_info().connected_to_shard_aware_port);
+    }
+
     /// Tests for Connection::query_iter
     /// 1. SELECT from an empty table.
     /// 2. Create table and insert ints 0..100.
@@ -2179,6 +2974,7 @@ mod tests {
             UntranslatedEndpoint::ContactPoint(ResolvedContactPoint {
                 address: addr,
                 datacenter: None,
+                alternative_addresses: Vec::new(),
             }),
             None,
             &ConnectionConfig::default(),
@@ -2304,6 +3100,7 @@ mod tests {
                 UntranslatedEndpoint::ContactPoint(ResolvedContactPoint {
                     address: addr,
                     datacenter: None,
+                    alternative_addresses: Vec::new(),
                 }),
                 None,
                 &ConnectionConfig {
@@ -2435,7 +3232,7 @@ mod tests {
 
         // We must interrupt the driver's full connection opening, because our proxy does not interact further after Startup.
         let (startup_without_lwt_optimisation, _shard) = select! {
-            _ = open_connection(UntranslatedEndpoint::ContactPoint(ResolvedContactPoint{address: proxy_addr, datacenter: None}), None, &config) => unreachable!(),
+            _ = open_connection(UntranslatedEndpoint::ContactPoint(ResolvedContactPoint{address: proxy_addr, datacenter: None, alternative_addresses: Vec::new()}), None, &config) => unreachable!(),
             startup = startup_rx.recv() => startup.unwrap(),
         };
 
@@ -2443,7 +3240,7 @@ mod tests {
             .change_request_rules(Some(make_rules(options_with_lwt_optimisation_support)));
 
         let (startup_with_lwt_optimisation, _shard) = select! {
-            _ = open_connection(UntranslatedEndpoint::ContactPoint(ResolvedContactPoint{address: proxy_addr, datacenter: None}), None, &config) => unreachable!(),
+            _ = open_connection(UntranslatedEndpoint::ContactPoint(ResolvedContactPoint{address: proxy_addr, datacenter: None, alternative_addresses: Vec::new()}), None, &config) => unreachable!(),
             startup = startup_rx.recv() => startup.unwrap(),
         };
 
@@ -2464,6 +3261,63 @@ mod tests {
         )
     }
 
+    #[tokio::test]
+    async fn test_custom_startup_options() {
+        setup_tracing();
+        let proxy_addr = SocketAddr::new(scylla_proxy::get_exclusive_local_address(), 9042);
+
+        let config = ConnectionConfig {
+            custom_startup_options: Arc::new(HashMap::from([
+                ("TENANT_ID".to_owned(), "tenant-17".to_owned()),
+                (options::DRIVER_NAME.to_owned(), "impostor".to_owned()),
+            ])),
+            ..Default::default()
+        };
+
+        let (startup_tx, mut startup_rx) = mpsc::unbounded_channel();
+        let supported = HashMap::from([("PROXY_VERSION".to_owned(), vec!["1".to_owned()])]);
+        let rules = vec![
+            RequestRule(
+                Condition::RequestOpcode(RequestOpcode::Options),
+                RequestReaction::forge_response(Arc::new(move |frame: RequestFrame| {
+                    ResponseFrame::forged_supported(frame.params, &supported).unwrap()
+                })),
+            ),
+            RequestRule(
+                Condition::RequestOpcode(RequestOpcode::Startup),
+                RequestReaction::drop_frame().with_feedback_when_performed(startup_tx),
+            ),
+        ];
+
+        let proxy = Proxy::builder()
+            .with_node(
+                Node::builder()
+                    .proxy_address(proxy_addr)
+                    .request_rules(rules)
+                    .build_dry_mode(),
+            )
+            .build()
+            .run()
+            .await
+            .unwrap();
+
+        // The proxy does not respond to STARTUP, so the connection never opens.
+        let (startup, _shard) = select! {
+            _ = open_connection(UntranslatedEndpoint::ContactPoint(ResolvedContactPoint{address: proxy_addr, datacenter: None, alternative_addresses: Vec::new()}), None, &config) => unreachable!(),
+            startup = startup_rx.recv() => startup.unwrap(),
+        };
+
+        let _ = proxy.finish().await;
+
+        let chosen_options = types::read_string_map(&mut &*startup.body).unwrap();
+        assert_eq!(chosen_options.get("TENANT_ID").unwrap(), "tenant-17");
+        // Options reserved for the driver can't be overridden
+        assert_eq!(
+            chosen_options.get(options::DRIVER_NAME).unwrap(),
+            options::DEFAULT_DRIVER_NAME
+        );
+    }
+
     #[tokio::test]
     #[ntest::timeout(20000)]
     #[cfg(not(scylla_cloud_tests))]
@@ -2502,6 +3356,7 @@ mod tests {
             UntranslatedEndpoint::ContactPoint(ResolvedContactPoint {
                 address: proxy_addr,
                 datacenter: None,
+                alternative_addresses: Vec::new(),
             }),
             None,
             &config,
@@ -2528,7 +3383,13 @@ mod tests {
         // Wait until keepaliver gots impatient and terminates router.
         // Then, the error from keepaliver will be propagated to the error receiver.
         let err = error_receiver.await.unwrap();
-        assert_matches!(err, QueryError::IoError(_));
+        assert_matches!(
+            err,
+            QueryError::ConnectionError {
+                error: ConnectionError::IoError(_),
+                ..
+            }
+        );
 
         // As the router is invalidated, all further queries should immediately
         // return error.
@@ -2538,4 +3399,400 @@ mod tests {
 
         let _ = proxy.finish().await;
     }
+
+    #[tokio::test]
+    #[ntest::timeout(20000)]
+    #[cfg(not(scylla_cloud_tests))]
+    async fn connection_survives_missed_keepalives_below_limit() {
+        setup_tracing();
+        let proxy_addr = SocketAddr::new(scylla_proxy::get_exclusive_local_address(), 9042);
+        let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+        let node_addr: SocketAddr = resolve_hostname(&uri).await;
+
+        let drop_options_rule = RequestRule(
+            Condition::RequestOpcode(RequestOpcode::Options),
+            RequestReaction::drop_frame(),
+        );
+
+        let keepalive_failures = Arc::new(KeepaliveFailures::default());
+        let config = ConnectionConfig {
+            keepalive_interval: Some(Duration::from_millis(200)),
+            keepalive_timeout: Some(Duration::from_millis(200)),
+            keepalive_max_missed: NonZeroU32::new(100).unwrap(),
+            keepalive_failures: Some(keepalive_failures.clone()),
+            ..Default::default()
+        };
+
+        let mut proxy = Proxy::builder()
+            .with_node(
+                Node::builder()
+                    .proxy_address(proxy_addr)
+                    .real_address(node_addr)
+                    .shard_awareness(ShardAwareness::QueryNode)
+                    .build(),
+            )
+            .build()
+            .run()
+            .await
+            .unwrap();
+
+        let (conn, mut error_receiver) = open_connection(
+            UntranslatedEndpoint::ContactPoint(ResolvedContactPoint {
+                address: proxy_addr,
+                datacenter: None,
+                alternative_addresses: Vec::new(),
+            }),
+            None,
+            &config,
+        )
+        .await
+        .unwrap();
+
+        // Drop keepalives for a while, so that some of them are missed.
+        proxy.running_nodes[0].change_request_rules(Some(vec![drop_options_rule]));
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(keepalive_failures.failing(), 1);
+
+        // After keepalives get through again, the connection is no longer counted.
+        proxy.running_nodes[0].change_request_rules(None);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(keepalive_failures.failing(), 0);
+
+        // The connection was not closed, as the limit of missed keepalives was not reached.
+        assert_matches!(
+            error_receiver.try_recv(),
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty)
+        );
+        conn.query_single_page("SELECT host_id FROM system.local")
+            .await
+            .unwrap();
+
+        let _ = proxy.finish().await;
+    }
+
+    #[tokio::test]
+    #[ntest::timeout(20000)]
+    #[cfg(not(scylla_cloud_tests))]
+    async fn connection_is_closed_on_error_responses_to_keepalives() {
+        setup_tracing();
+        let proxy_addr = SocketAddr::new(scylla_proxy::get_exclusive_local_address(), 9042);
+        let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+        let node_addr: SocketAddr = resolve_hostname(&uri).await;
+
+        let fail_keepalive_rule = RequestRule(
+            Condition::RequestOpcode(RequestOpcode::Query).and(
+                Condition::BodyContainsCaseSensitive(Box::new(*b"WHERE key = 'local'")),
+            ),
+            RequestReaction::forge_with_error(DbError::Overloaded),
+        );
+
+        let config = ConnectionConfig {
+            keepalive_interval: Some(Duration::from_millis(200)),
+            keepalive_timeout: Some(Duration::from_secs(1)),
+            keepalive_request: KeepaliveRequest::LocalQuery,
+            ..Default::default()
+        };
+
+        let proxy = Proxy::builder()
+            .with_node(
+                Node::builder()
+                    .proxy_address(proxy_addr)
+                    .real_address(node_addr)
+                    .shard_awareness(ShardAwareness::QueryNode)
+                    .request_rules(vec![fail_keepalive_rule])
+                    .build(),
+            )
+            .build()
+            .run()
+            .await
+            .unwrap();
+
+        let (_conn, error_receiver) = open_connection(
+            UntranslatedEndpoint::ContactPoint(ResolvedContactPoint {
+                address: proxy_addr,
+                datacenter: None,
+                alternative_addresses: Vec::new(),
+            }),
+            None,
+            &config,
+        )
+        .await
+        .unwrap();
+
+        // The node responds to the keepalive query, but with an error, which counts as
+        // a missed keepalive.
+        let err = error_receiver.await.unwrap();
+        assert_matches!(
+            err,
+            QueryError::DbError {
+                error: DbError::Overloaded,
+                ..
+            }
+        );
+
+        let _ = proxy.finish().await;
+    }
+
+    #[tokio::test]
+    async fn happy_eyeballs_falls_back_to_next_address() {
+        setup_tracing();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let listening_addr = listener.local_addr().unwrap();
+        let refusing_addr = {
+            let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            closed.local_addr().unwrap()
+        };
+        let config = ConnectionConfig::default();
+
+        let (connection, _) =
+            connect_happy_eyeballs(&[refusing_addr, listening_addr], None, &config)
+                .await
+                .unwrap();
+        assert_eq!(connection.get_connect_address(), listening_addr);
+
+        let err = connect_happy_eyeballs(&[refusing_addr], None, &config)
+            .await
+            .err()
+            .unwrap();
+        assert_matches!(
+            err,
+            QueryError::ConnectionError {
+                error: ConnectionError::IoError(_),
+                ..
+            }
+        );
+    }
+
+    // Never finishes connecting to `stalled_addr`, and connects to any other address.
+    struct StallingConnector {
+        stalled_addr: SocketAddr,
+    }
+
+    #[async_trait::async_trait]
+    impl Connector for StallingConnector {
+        async fn connect(
+            &self,
+            address: SocketAddr,
+            _source_port: Option<u16>,
+        ) -> std::io::Result<Box<dyn ConnectionStream>> {
+            if address == self.stalled_addr {
+                futures::future::pending::<()>().await;
+            }
+            let (client_side, _server_side) = tokio::io::duplex(1024);
+            Ok(Box::new(client_side))
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn happy_eyeballs_starts_next_attempt_after_delay() {
+        setup_tracing();
+        let stalled_addr: SocketAddr = "[::1]:9042".parse().unwrap();
+        let next_addr: SocketAddr = "127.0.0.1:9042".parse().unwrap();
+        let config = ConnectionConfig {
+            connector: Some(Arc::new(StallingConnector { stalled_addr })),
+            ..Default::default()
+        };
+
+        let start = tokio::time::Instant::now();
+        let (connection, _) = connect_happy_eyeballs(&[stalled_addr, next_addr], None, &config)
+            .await
+            .unwrap();
+        assert_eq!(connection.get_connect_address(), next_addr);
+        // The stalled attempt was neither waited for until the connect timeout,
+        // nor raced with the next attempt from the start
+        assert_eq!(start.elapsed(), super::HAPPY_EYEBALLS_ATTEMPT_DELAY);
+    }
+
+    struct DuplexConnector {
+        server_side: std::sync::Mutex<Option<tokio::io::DuplexStream>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Connector for DuplexConnector {
+        async fn connect(
+            &self,
+            _address: SocketAddr,
+            _source_port: Option<u16>,
+        ) -> std::io::Result<Box<dyn ConnectionStream>> {
+            let (client_side, server_side) = tokio::io::duplex(1024);
+            *self.server_side.lock().unwrap() = Some(server_side);
+            Ok(Box::new(client_side))
+        }
+    }
+
+    #[tokio::test]
+    #[ntest::timeout(5000)]
+    async fn connection_uses_custom_connector() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        setup_tracing();
+        let connector = Arc::new(DuplexConnector {
+            server_side: std::sync::Mutex::new(None),
+        });
+        let config = ConnectionConfig {
+            connector: Some(connector.clone()),
+            ..Default::default()
+        };
+
+        let (conn, _error_receiver) =
+            Connection::new("127.0.0.1:9042".parse().unwrap(), None, config)
+                .await
+                .unwrap();
+        let mut server_side = connector.server_side.lock().unwrap().take().unwrap();
+
+        let server = async move {
+            // Read the OPTIONS request and respond with an empty SUPPORTED
+            let mut header = [0u8; 9];
+            server_side.read_exact(&mut header).await.unwrap();
+            assert_eq!(header[4], RequestOpcode::Options as u8);
+            let mut response = vec![0x84, 0, header[2], header[3], 0x06];
+            response.extend_from_slice(&2u32.to_be_bytes());
+            response.extend_from_slice(&[0, 0]);
+            server_side.write_all(&response).await.unwrap();
+            server_side
+        };
+
+        let (response, _server_side) = tokio::join!(conn.get_options(), server);
+        assert_matches!(response, Ok(Response::Supported(_)));
+    }
+
+    #[tokio::test]
+    async fn tcp_socket_options_are_applied() {
+        setup_tracing();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let local_ip: std::net::IpAddr = "127.0.0.1".parse().unwrap();
+        let config = ConnectionConfig {
+            tcp_send_buffer_size: Some(64 * 1024),
+            tcp_recv_buffer_size: Some(64 * 1024),
+            local_ip_address: Some(local_ip),
+            ..Default::default()
+        };
+
+        let stream = super::connect_tcp_socket(listener.local_addr().unwrap(), None, &config)
+            .await
+            .unwrap();
+        assert_eq!(stream.local_addr().unwrap().ip(), local_ip);
+        let (_server_side, peer_addr) = listener.accept().await.unwrap();
+        assert_eq!(peer_addr, stream.local_addr().unwrap());
+    }
+
+    #[tokio::test]
+    async fn addresses_of_other_family_than_local_address_are_skipped() {
+        setup_tracing();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let listening_addr = listener.local_addr().unwrap();
+        let v6_addr: SocketAddr = "[::1]:9042".parse().unwrap();
+        let config = ConnectionConfig {
+            local_ip_address: Some("127.0.0.1".parse().unwrap()),
+            ..Default::default()
+        };
+
+        let err = super::connect_tcp_socket(v6_addr, None, &config)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+        let (connection, _) = connect_happy_eyeballs(&[v6_addr, listening_addr], None, &config)
+            .await
+            .unwrap();
+        assert_eq!(connection.get_connect_address(), listening_addr);
+
+        let err = connect_happy_eyeballs(&[v6_addr], None, &config)
+            .await
+            .err()
+            .unwrap();
+        assert_matches!(
+            err,
+            QueryError::ConnectionError {
+                error: ConnectionError::IoError(io_err),
+                ..
+            } if io_err.kind() == std::io::ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    fn orphaned_requests_are_counted_and_hold_stream_ids() {
+        setup_tracing();
+        let metrics = Arc::new(crate::transport::metrics::Metrics::new());
+        let mut handler_map = ResponseHandlerMap::new(Some(metrics.clone()));
+        let all_stream_ids = handler_map.free_stream_ids();
+
+        let mut receivers = Vec::new();
+        let mut stream_ids = Vec::new();
+        for request_id in 0..3 {
+            let (response_sender, receiver) = tokio::sync::oneshot::channel();
+            receivers.push(receiver);
+            let handler = ResponseHandler {
+                response_sender,
+                request_id,
+            };
+            stream_ids.push(handler_map.allocate(handler).ok().unwrap());
+        }
+        assert_eq!(handler_map.free_stream_ids(), all_stream_ids - 3);
+
+        handler_map.orphan(1);
+        // Orphaning an unknown request does nothing.
+        handler_map.orphan(42);
+        assert_eq!(metrics.get_orphaned_requests_num(), 1);
+        // The orphaned stream id is still occupied until its response arrives.
+        assert_eq!(handler_map.free_stream_ids(), all_stream_ids - 3);
+        assert_eq!(handler_map.old_orphans_count(Duration::ZERO), 1);
+
+        for stream_id in stream_ids {
+            handler_map.lookup(stream_id);
+        }
+        assert_eq!(handler_map.free_stream_ids(), all_stream_ids);
+        assert_eq!(handler_map.old_orphans_count(Duration::ZERO), 0);
+    }
+
+    #[test]
+    fn node_is_suspected_when_half_of_connections_miss_keepalives() {
+        setup_tracing();
+        let failures = Arc::new(KeepaliveFailures::default());
+        assert!(!failures.is_down_suspected(4));
+
+        // A single connection with missed keepalives might be broken on its own
+        let first = failures.record_failure();
+        assert!(!failures.is_down_suspected(4));
+        assert!(failures.is_down_suspected(1));
+
+        let second = failures.record_failure();
+        assert!(failures.is_down_suspected(4));
+
+        // A keepalive which gets through again, or a closed connection, is no longer counted
+        drop(first);
+        assert_eq!(failures.failing(), 1);
+        assert!(!failures.is_down_suspected(4));
+
+        // A successful request clears the count, without the failures recorded
+        // before it being subtracted again
+        let third = failures.record_failure();
+        failures.clear();
+        assert_eq!(failures.failing(), 0);
+        drop(second);
+        drop(third);
+        assert_eq!(failures.failing(), 0);
+        let _fourth = failures.record_failure();
+        assert_eq!(failures.failing(), 1);
+    }
+
+    #[test]
+    fn verified_keyspace_name_case_sensitivity() {
+        setup_tracing();
+        let ks = super::VerifiedKeyspaceName::new("MyKs".to_owned(), false).unwrap();
+        assert!(!ks.is_case_sensitive);
+        assert_eq!(ks.effective_name(), "myks");
+
+        let ks = super::VerifiedKeyspaceName::new("MyKs".to_owned(), true).unwrap();
+        assert_eq!(ks.effective_name(), "MyKs");
+
+        // Quoted names are case sensitive, like in CQL.
+        let ks = super::VerifiedKeyspaceName::new("\"MyKs\"".to_owned(), false).unwrap();
+        assert!(ks.is_case_sensitive);
+        assert_eq!(ks.as_str(), "MyKs");
+        assert_eq!(ks.effective_name(), "MyKs");
+
+        assert!(super::VerifiedKeyspaceName::new("\"".to_owned(), false).is_err());
+        assert!(super::VerifiedKeyspaceName::new("\"\"".to_owned(), false).is_err());
+    }
 }
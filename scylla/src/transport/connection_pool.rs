@@ -2,14 +2,18 @@
 use crate::cloud::set_ssl_config_for_scylla_cloud_host;
 
 use crate::routing::{Shard, ShardCount, Sharder};
-use crate::transport::errors::QueryError;
+use crate::transport::errors::{ConnectionError, QueryError, TimeoutError};
+use crate::transport::metrics::Metrics;
+use crate::transport::runtime;
 use crate::transport::{
     connection,
-    connection::{Connection, ConnectionConfig, ErrorReceiver, VerifiedKeyspaceName},
+    connection::{
+        Connection, ConnectionConfig, ErrorReceiver, KeepaliveFailures, VerifiedKeyspaceName,
+    },
 };
 
 #[cfg(feature = "cloud")]
-use super::node::resolve_hostname;
+use super::node::{resolve_hostname, DnsResolver};
 
 #[cfg(feature = "cloud")]
 use super::node::ResolvedContactPoint;
@@ -23,8 +27,9 @@ use std::convert::TryInto;
 use std::io::ErrorKind;
 use std::num::NonZeroUsize;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock, Weak};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use tokio::sync::{broadcast, mpsc, Notify};
 use tracing::{debug, error, trace, warn};
@@ -53,11 +58,108 @@ impl Default for PoolSize {
     }
 }
 
+/// Describes whether the connection pool of a node opens its connections
+/// through the shard-aware port, directly to the shards chosen by the driver.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ShardAwarenessStatus {
+    /// It is not known yet whether the node is sharded, e.g. because no connection
+    /// to it was established yet, or the node is disabled.
+    Unknown,
+    /// The node is not sharded (e.g. it is a Cassandra node), so shard-awareness does not apply.
+    NotSharded,
+    /// Connections are opened to the shard-aware port.
+    Active,
+    /// Connections are opened to the regular port and the node assigns their shards.
+    Inactive {
+        /// Why the shard-aware port is not used.
+        reason: ShardAwarenessInactiveReason,
+    },
+}
+
+/// The reason why the driver does not use the shard-aware port of a node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ShardAwarenessInactiveReason {
+    /// The shard-aware port was disabled with `SessionBuilder::disallow_shard_aware_port`.
+    DisallowedByConfig,
+    /// The pool size is specified per host, so the driver does not target particular shards.
+    PerHostPoolSize,
+    /// The node does not advertise a shard-aware port.
+    PortNotAdvertised,
+    /// None of the source ports corresponding to the target shard was free to use.
+    NoFreeSourcePort,
+    /// Opening a connection to the shard-aware port failed with the given error.
+    ConnectionFailed(String),
+    /// A connection opened to the shard-aware port was attached to a different shard than requested,
+    /// probably because of a NAT between the driver and the node which changes source ports.
+    ShardMismatch,
+}
+
+/// A snapshot of the state of the connection pool of a node.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct PoolState {
+    /// The number of open connections to each shard of the node.
+    /// Contains a single element if the node is not sharded,
+    /// and is empty if the pool has no connections.
+    pub connections_per_shard: Vec<usize>,
+    /// The number of connections which are being opened.
+    pub connecting_connections: usize,
+    /// The number of connections which broke since the pool was created.
+    pub broken_connections: u64,
+    /// The last error with which a connection broke or failed to open.
+    pub last_error: Option<QueryError>,
+    /// The time since a request sent to the node last succeeded,
+    /// `None` if no request succeeded yet.
+    pub time_since_last_successful_request: Option<Duration>,
+    /// Whether the node is suspected to be down because the last keepalive sent on
+    /// at least half of its open connections failed and no request has succeeded since.
+    pub down_suspected: bool,
+}
+
+impl PoolState {
+    /// The total number of open connections.
+    pub fn open_connections(&self) -> usize {
+        self.connections_per_shard.iter().sum()
+    }
+}
+
+// Updated by `PoolRefiller` and by the session, read by `NodeConnectionPool::state`.
+struct PoolStats {
+    connecting_connections: AtomicUsize,
+    broken_connections: AtomicU64,
+    last_error: RwLock<Option<QueryError>>,
+    created_at: Instant,
+    // Microseconds since `created_at` plus one, or zero if no request succeeded yet.
+    last_successful_request: AtomicU64,
+    // Shared with the keepalive tasks of the connections.
+    keepalive_failures: Arc<KeepaliveFailures>,
+}
+
+impl PoolStats {
+    fn new() -> Self {
+        Self {
+            connecting_connections: AtomicUsize::new(0),
+            broken_connections: AtomicU64::new(0),
+            last_error: RwLock::new(None),
+            created_at: Instant::now(),
+            last_successful_request: AtomicU64::new(0),
+            keepalive_failures: Arc::new(KeepaliveFailures::default()),
+        }
+    }
+
+    fn set_last_error(&self, error: &QueryError) {
+        *self.last_error.write().unwrap() = Some(error.clone());
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct PoolConfig {
     pub(crate) connection_config: ConnectionConfig,
     pub(crate) pool_size: PoolSize,
     pub(crate) can_use_shard_aware_port: bool,
+    pub(crate) shard_aware_port_retry_interval: Option<Duration>,
     pub(crate) keepalive_interval: Option<Duration>,
 }
 
@@ -67,6 +169,7 @@ impl Default for PoolConfig {
             connection_config: Default::default(),
             pool_size: Default::default(),
             can_use_shard_aware_port: true,
+            shard_aware_port_retry_interval: None,
             keepalive_interval: None,
         }
     }
@@ -154,6 +257,9 @@ pub(crate) struct NodeConnectionPool {
     _refiller_handle: Arc<RemoteHandle<()>>,
     pool_updated_notify: Arc<Notify>,
     endpoint: Arc<RwLock<UntranslatedEndpoint>>,
+    shard_awareness_status: Arc<RwLock<ShardAwarenessStatus>>,
+    stats: Arc<PoolStats>,
+    metrics: Option<Arc<Metrics>>,
 }
 
 impl std::fmt::Debug for NodeConnectionPool {
@@ -180,6 +286,7 @@ impl NodeConnectionPool {
                 UntranslatedEndpoint::ContactPoint(ResolvedContactPoint {
                     address,
                     ref datacenter,
+                    ..
                 }) => (None, address, datacenter.as_deref()), // FIXME: Pass DC in ContactPoint
                 UntranslatedEndpoint::Peer(PeerEndpoint {
                     host_id,
@@ -200,9 +307,15 @@ impl NodeConnectionPool {
         }
 
         let arced_endpoint = Arc::new(RwLock::new(endpoint));
+        let shard_awareness_status = Arc::new(RwLock::new(ShardAwarenessStatus::Unknown));
+        let stats = Arc::new(PoolStats::new());
+        let metrics = pool_config.connection_config.metrics.clone();
+        let runtime = pool_config.connection_config.runtime.clone();
 
         let refiller = PoolRefiller::new(
             arced_endpoint.clone(),
+            shard_awareness_status.clone(),
+            stats.clone(),
             pool_config,
             current_keyspace,
             pool_updated_notify.clone(),
@@ -211,7 +324,7 @@ impl NodeConnectionPool {
 
         let conns = refiller.get_shared_connections();
         let (fut, refiller_handle) = refiller.run(use_keyspace_request_receiver).remote_handle();
-        tokio::spawn(fut);
+        runtime.spawn(Box::pin(fut));
 
         Self {
             conns,
@@ -219,9 +332,65 @@ impl NodeConnectionPool {
             _refiller_handle: Arc::new(refiller_handle),
             pool_updated_notify,
             endpoint: arced_endpoint,
+            shard_awareness_status,
+            stats,
+            metrics,
+        }
+    }
+
+    pub(crate) fn shard_awareness_status(&self) -> ShardAwarenessStatus {
+        self.shard_awareness_status.read().unwrap().clone()
+    }
+
+    pub(crate) fn state(&self) -> PoolState {
+        let connections_per_shard = self
+            .with_connections(|pool_conns| match pool_conns {
+                PoolConnections::NotSharded(conns) => vec![conns.len()],
+                PoolConnections::Sharded { connections, .. } => {
+                    connections.iter().map(Vec::len).collect()
+                }
+            })
+            .unwrap_or_default();
+        let time_since_last_successful_request =
+            match self.stats.last_successful_request.load(Ordering::Relaxed) {
+                0 => None,
+                micros => {
+                    let at = self.stats.created_at + Duration::from_micros(micros - 1);
+                    Some(Instant::now().saturating_duration_since(at))
+                }
+            };
+        PoolState {
+            connections_per_shard,
+            connecting_connections: self.stats.connecting_connections.load(Ordering::Relaxed),
+            broken_connections: self.stats.broken_connections.load(Ordering::Relaxed),
+            last_error: self.stats.last_error.read().unwrap().clone(),
+            time_since_last_successful_request,
+            down_suspected: self.is_down_suspected(),
         }
     }
 
+    pub(crate) fn is_down_suspected(&self) -> bool {
+        let failures = &self.stats.keepalive_failures;
+        // Counting the connections is skipped in the common case of no failures
+        failures.failing() > 0 && failures.is_down_suspected(self.open_connections())
+    }
+
+    fn open_connections(&self) -> usize {
+        self.with_connections(|pool_conns| match pool_conns {
+            PoolConnections::NotSharded(conns) => conns.len(),
+            PoolConnections::Sharded { connections, .. } => connections.iter().map(Vec::len).sum(),
+        })
+        .unwrap_or(0)
+    }
+
+    pub(crate) fn record_successful_request(&self) {
+        let micros = self.stats.created_at.elapsed().as_micros() as u64 + 1;
+        self.stats
+            .last_successful_request
+            .fetch_max(micros, Ordering::Relaxed);
+        self.stats.keepalive_failures.clear();
+    }
+
     pub(crate) fn update_endpoint(&self, new_endpoint: PeerEndpoint) {
         *self.endpoint.write().unwrap() = UntranslatedEndpoint::Peer(new_endpoint);
     }
@@ -252,7 +421,13 @@ impl NodeConnectionPool {
                         error!("The provided shard number: {} does not fit u16! Using 0 as the shard number. Check your LoadBalancingPolicy implementation.", shard);
                         0
                     });
-                Self::connection_for_shard_helper(shard, sharder.nr_shards, connections.as_slice())
+                let conn = Self::connection_for_shard_helper(shard, sharder.nr_shards, connections.as_slice());
+                if let Some(metrics) = &self.metrics {
+                    if conn.get_shard_info().as_ref().map(|info| info.shard) != Some(shard) {
+                        metrics.inc_missed_shard_requests();
+                    }
+                }
+                conn
             }
         })
     }
@@ -374,21 +549,17 @@ impl NodeConnectionPool {
         let conns = self.conns.load_full();
         match &*conns {
             MaybePoolConnections::Ready(pool_connections) => Ok(f(pool_connections)),
-            MaybePoolConnections::Broken(err) => {
-                Err(QueryError::IoError(Arc::new(std::io::Error::new(
-                    ErrorKind::Other,
-                    format!(
-                        "No connections in the pool; last connection failed with: {}",
-                        err
-                    ),
-                ))))
-            }
-            MaybePoolConnections::Initializing => {
-                Err(QueryError::IoError(Arc::new(std::io::Error::new(
-                    ErrorKind::Other,
-                    "No connections in the pool, pool is still being initialized",
-                ))))
-            }
+            MaybePoolConnections::Broken(err) => Err(QueryError::from(std::io::Error::new(
+                ErrorKind::Other,
+                format!(
+                    "No connections in the pool; last connection failed with: {}",
+                    err
+                ),
+            ))),
+            MaybePoolConnections::Initializing => Err(QueryError::from(std::io::Error::new(
+                ErrorKind::Other,
+                "No connections in the pool, pool is still being initialized",
+            ))),
         }
     }
 }
@@ -439,6 +610,22 @@ struct PoolRefiller {
     shard_aware_port: Option<u16>,
     sharder: Option<Sharder>,
 
+    // Updated after each attempt to open a connection, read by `NodeConnectionPool`
+    shard_awareness_status: Arc<RwLock<ShardAwarenessStatus>>,
+    stats: Arc<PoolStats>,
+
+    // If `shard_aware_port_retry_interval` is configured and connecting
+    // to the shard-aware port fails, the port is not used until this moment.
+    shard_aware_port_disabled_until: Option<tokio::time::Instant>,
+
+    // Connections of a sharded node opened to the regular port because the shard-aware
+    // port could not be used. If `shard_aware_port_retry_interval` is configured, they are
+    // replaced with connections opened to the shard-aware port once it works again.
+    regular_port_conns: Vec<Weak<Connection>>,
+
+    // Number of shard-aware connections being opened to replace `regular_port_conns`
+    migrations_in_flight: usize,
+
     // `shared_conns` is updated only after `conns` change
     shared_conns: Arc<ArcSwap<MaybePoolConnections>>,
     conns: Vec<Vec<Arc<Connection>>>,
@@ -491,7 +678,9 @@ struct UseKeyspaceRequest {
 impl PoolRefiller {
     pub(crate) fn new(
         endpoint: Arc<RwLock<UntranslatedEndpoint>>,
-        pool_config: PoolConfig,
+        shard_awareness_status: Arc<RwLock<ShardAwarenessStatus>>,
+        stats: Arc<PoolStats>,
+        mut pool_config: PoolConfig,
         current_keyspace: Option<VerifiedKeyspaceName>,
         pool_updated_notify: Arc<Notify>,
         pool_empty_notifier: broadcast::Sender<()>,
@@ -500,6 +689,7 @@ impl PoolRefiller {
         // and assume that the node is a Cassandra node
         let conns = vec![Vec::new()];
         let shared_conns = Arc::new(ArcSwap::new(Arc::new(MaybePoolConnections::Initializing)));
+        pool_config.connection_config.keepalive_failures = Some(stats.keepalive_failures.clone());
 
         Self {
             endpoint,
@@ -508,6 +698,13 @@ impl PoolRefiller {
             shard_aware_port: None,
             sharder: None,
 
+            shard_awareness_status,
+            stats,
+            shard_aware_port_disabled_until: None,
+
+            regular_port_conns: Vec::new(),
+            migrations_in_flight: 0,
+
             shared_conns,
             conns,
 
@@ -547,14 +744,28 @@ impl PoolRefiller {
         let mut next_refill_time = tokio::time::Instant::now();
         let mut refill_scheduled = true;
 
+        let mut next_migration_time = tokio::time::Instant::now();
+        let mut migration_scheduled = false;
+
         loop {
             tokio::select! {
-                _ = tokio::time::sleep_until(next_refill_time), if refill_scheduled => {
+                _ = self.pool_config.connection_config.runtime.sleep(
+                    next_refill_time.saturating_duration_since(tokio::time::Instant::now())
+                ), if refill_scheduled => {
                     self.had_error_since_last_refill = false;
                     self.start_filling();
                     refill_scheduled = false;
                 }
 
+                _ = self.pool_config.connection_config.runtime.sleep(
+                    next_migration_time.saturating_duration_since(tokio::time::Instant::now())
+                ), if migration_scheduled => {
+                    // Probe the shard-aware port by migrating a single connection.
+                    // If it succeeds, the remaining connections are migrated too.
+                    self.start_migrating_connections(1);
+                    migration_scheduled = false;
+                }
+
                 evt = self.ready_connections.select_next_some(), if !self.ready_connections.is_empty() => {
                     self.handle_ready_connection(evt);
 
@@ -571,6 +782,8 @@ impl PoolRefiller {
                 evt = self.connection_errors.select_next_some(), if !self.connection_errors.is_empty() => {
                     if let Some(conn) = evt.connection.upgrade() {
                         debug!("[{}] Got error for connection {:p}: {:?}", self.endpoint_description(), Arc::as_ptr(&conn), evt.error);
+                        self.stats.broken_connections.fetch_add(1, Ordering::Relaxed);
+                        self.stats.set_last_error(&evt.error);
                         self.remove_connection(conn, evt.error);
                     }
                 }
@@ -591,6 +804,9 @@ impl PoolRefiller {
             trace!(
                 pool_state = format!("{:?}", ShardedConnectionVectorWrapper(&self.conns)).as_str()
             );
+            self.stats
+                .connecting_connections
+                .store(self.ready_connections.len(), Ordering::Relaxed);
 
             // Schedule refilling here
             if !refill_scheduled && self.need_filling() {
@@ -609,6 +825,17 @@ impl PoolRefiller {
                 next_refill_time = tokio::time::Instant::now() + delay;
                 refill_scheduled = true;
             }
+
+            // Schedule retrying the shard-aware port for connections opened to the regular port
+            if !migration_scheduled && self.need_migration() {
+                let delay = self
+                    .shard_aware_port_disabled_until
+                    .map_or(Duration::ZERO, |until| {
+                        until.saturating_duration_since(tokio::time::Instant::now())
+                    });
+                next_migration_time = tokio::time::Instant::now() + delay;
+                migration_scheduled = true;
+            }
         }
     }
 
@@ -633,10 +860,170 @@ impl PoolRefiller {
         !self.is_filling() && !self.is_full()
     }
 
+    // Returns true if there are connections opened to the regular port which should be
+    // replaced with shard-aware ones, and no attempt to do so is in progress.
+    fn need_migration(&mut self) -> bool {
+        self.regular_port_conns
+            .retain(|conn| conn.strong_count() > 0);
+        self.pool_config.shard_aware_port_retry_interval.is_some()
+            && self.migrations_in_flight == 0
+            && !self.regular_port_conns.is_empty()
+            && self.static_shard_awareness_status().is_none()
+    }
+
+    // Starts opening shard-aware connections to replace at most `max_count` connections
+    // opened to the regular port.
+    fn start_migrating_connections(&mut self, max_count: usize) {
+        if !self.can_use_shard_aware_port() {
+            return;
+        }
+        let shards: Vec<Shard> = self
+            .regular_port_conns
+            .iter()
+            .filter_map(Weak::upgrade)
+            .filter_map(|conn| {
+                conn.get_shard_info()
+                    .as_ref()
+                    .map(|info| info.shard as Shard)
+            })
+            .take(max_count)
+            .collect();
+        debug!(
+            "[{}] Will replace {} connections opened to the regular port with shard-aware ones",
+            self.endpoint_description(),
+            shards.len(),
+        );
+        for shard in shards {
+            self.migrations_in_flight += 1;
+            self.start_opening_migration_connection(shard);
+        }
+    }
+
+    // Replaces a connection opened to the regular port for the given shard
+    // with a new connection opened to the shard-aware port.
+    // Returns the new connection back if there is no connection to replace.
+    fn replace_regular_port_connection(
+        &mut self,
+        shard_id: usize,
+        connection: Connection,
+        error_receiver: ErrorReceiver,
+    ) -> Option<(Connection, ErrorReceiver)> {
+        let position = self.conns[shard_id].iter().position(|conn| {
+            self.regular_port_conns
+                .iter()
+                .any(|regular| std::ptr::eq(regular.as_ptr(), Arc::as_ptr(conn)))
+        });
+        let Some(position) = position else {
+            return Some((connection, error_receiver));
+        };
+
+        let old_conn = self.conns[shard_id].swap_remove(position);
+        self.regular_port_conns
+            .retain(|regular| !std::ptr::eq(regular.as_ptr(), Arc::as_ptr(&old_conn)));
+        let conn = Arc::new(connection);
+        debug!(
+            "[{}] Replacing connection {:p} opened to the regular port with {:p} opened to the shard-aware port, shard {}",
+            self.endpoint_description(),
+            Arc::as_ptr(&old_conn),
+            Arc::as_ptr(&conn),
+            shard_id,
+        );
+        self.connection_errors
+            .push(wait_for_error(Arc::downgrade(&conn), error_receiver).boxed());
+        self.conns[shard_id].push(conn);
+        self.update_shared_conns(None);
+        // The old connection is closed once the requests which use it finish
+        None
+    }
+
+    // Handles a connection opened to the shard-aware port for which there was
+    // no regular-port connection left to replace: it fills the pool if the shard
+    // has room for it, and is closed otherwise.
+    fn add_migrated_connection(
+        &mut self,
+        shard_id: usize,
+        connection: Connection,
+        error_receiver: ErrorReceiver,
+    ) {
+        let can_be_accepted = match self.pool_config.pool_size {
+            PoolSize::PerHost(target) => self.active_connection_count() < target.get(),
+            PoolSize::PerShard(target) => self.conns[shard_id].len() < target.get(),
+        };
+        if !can_be_accepted {
+            debug!(
+                "[{}] Closing a connection opened to the shard-aware port, as there is no connection to replace and shard {} is full",
+                self.endpoint_description(),
+                shard_id,
+            );
+            drop(connection);
+            return;
+        }
+
+        let conn = Arc::new(connection);
+        trace!(
+            "[{}] Adding connection {:p} opened to the shard-aware port to shard {} pool",
+            self.endpoint_description(),
+            Arc::as_ptr(&conn),
+            shard_id,
+        );
+        self.connection_errors
+            .push(wait_for_error(Arc::downgrade(&conn), error_receiver).boxed());
+        self.conns[shard_id].push(conn);
+        self.update_shared_conns(None);
+    }
+
     fn can_use_shard_aware_port(&self) -> bool {
         self.sharder.is_some()
             && self.shard_aware_port.is_some()
             && self.pool_config.can_use_shard_aware_port
+            && !matches!(
+                self.shard_aware_port_disabled_until,
+                Some(until) if tokio::time::Instant::now() < until
+            )
+    }
+
+    // Returns the shard-awareness status which follows from the configuration
+    // and the information from OPTIONS, if it does not depend on connection attempts.
+    fn static_shard_awareness_status(&self) -> Option<ShardAwarenessStatus> {
+        let reason = if self.sharder.is_none() {
+            return Some(ShardAwarenessStatus::NotSharded);
+        } else if !self.pool_config.can_use_shard_aware_port {
+            ShardAwarenessInactiveReason::DisallowedByConfig
+        } else if let PoolSize::PerHost(_) = self.pool_config.pool_size {
+            ShardAwarenessInactiveReason::PerHostPoolSize
+        } else if self.shard_aware_port.is_none() {
+            ShardAwarenessInactiveReason::PortNotAdvertised
+        } else {
+            return None;
+        };
+        Some(ShardAwarenessStatus::Inactive { reason })
+    }
+
+    fn set_shard_awareness_status(&self, status: ShardAwarenessStatus) {
+        let mut current = self.shard_awareness_status.write().unwrap();
+        if *current != status {
+            debug!(
+                "[{}] Shard-awareness status changed: {:?}",
+                self.endpoint_description(),
+                status,
+            );
+            *current = status;
+        }
+    }
+
+    // Called when connecting to the shard-aware port did not give us
+    // a connection to the requested shard.
+    fn on_shard_aware_port_failure(&mut self, reason: ShardAwarenessInactiveReason) {
+        if let Some(retry_interval) = self.pool_config.shard_aware_port_retry_interval {
+            debug!(
+                "[{}] Not using the shard-aware port for the next {} ms",
+                self.endpoint_description(),
+                retry_interval.as_millis(),
+            );
+            self.shard_aware_port_disabled_until =
+                Some(tokio::time::Instant::now() + retry_interval);
+        }
+        self.set_shard_awareness_status(ShardAwarenessStatus::Inactive { reason });
     }
 
     // Begins opening a number of connections in order to fill the connection pool.
@@ -708,7 +1095,18 @@ impl PoolRefiller {
     fn handle_ready_connection(&mut self, evt: OpenedConnectionEvent) {
         match evt.result {
             Err(err) => {
-                if evt.requested_shard.is_some() {
+                self.stats.set_last_error(&err);
+                if evt.migration {
+                    // The connection which was supposed to be replaced is still in the pool,
+                    // so there is no need to fall back to the regular port.
+                    self.migrations_in_flight -= 1;
+                    debug!(
+                        "[{}] Failed to open connection to the shard-aware port: {:?}, not replacing connections opened to the regular port",
+                        self.endpoint_description(),
+                        err,
+                    );
+                    self.on_shard_aware_port_failure(shard_aware_port_failure_reason(&err));
+                } else if evt.requested_shard.is_some() {
                     // If we failed to connect to a shard-aware port,
                     // fall back to the non-shard-aware port.
                     // Don't set `had_error_since_last_refill` here;
@@ -725,6 +1123,7 @@ impl PoolRefiller {
                         self.endpoint_description(),
                         err,
                     );
+                    self.on_shard_aware_port_failure(shard_aware_port_failure_reason(&err));
                     self.start_opening_connection(None);
                 } else {
                     // Encountered an error while connecting to the non-shard-aware
@@ -761,6 +1160,19 @@ impl PoolRefiller {
                     self.shard_aware_port = connection.get_shard_aware_port();
                 }
 
+                if let Some(status) = self.static_shard_awareness_status() {
+                    self.set_shard_awareness_status(status);
+                } else if let Some(requested_shard) = evt.requested_shard {
+                    if requested_shard == shard_id as Shard {
+                        self.shard_aware_port_disabled_until = None;
+                        self.set_shard_awareness_status(ShardAwarenessStatus::Active);
+                    } else {
+                        self.on_shard_aware_port_failure(
+                            ShardAwarenessInactiveReason::ShardMismatch,
+                        );
+                    }
+                }
+
                 // Before the connection can be put to the pool, we need
                 // to make sure that it uses appropriate keyspace
                 if let Some(keyspace) = &self.current_keyspace {
@@ -774,9 +1186,38 @@ impl PoolRefiller {
                             connection,
                             error_receiver,
                             evt.requested_shard,
+                            evt.migration,
+                            evt.keyspace_retry_delay,
+                        );
+                        return;
+                    }
+                }
+
+                if evt.migration {
+                    self.migrations_in_flight -= 1;
+                    if evt.requested_shard != Some(shard_id as Shard) {
+                        // The shard-aware port still doesn't work. The connection
+                        // which was supposed to be replaced stays in the pool.
+                        debug!(
+                            "[{}] Closing a connection opened to the shard-aware port, as it landed on shard {} instead of {:?}",
+                            self.endpoint_description(),
+                            shard_id,
+                            evt.requested_shard,
                         );
+                        drop(connection);
                         return;
                     }
+                    if let Some((connection, error_receiver)) =
+                        self.replace_regular_port_connection(shard_id, connection, error_receiver)
+                    {
+                        // The connection to be replaced left the pool in the meantime
+                        self.add_migrated_connection(shard_id, connection, error_receiver);
+                    }
+                    if self.migrations_in_flight == 0 {
+                        // The shard-aware port works again, so migrate the rest of connections
+                        self.start_migrating_connections(usize::MAX);
+                    }
+                    return;
                 }
 
                 // Decide if the connection can be accepted, according to
@@ -801,6 +1242,16 @@ impl PoolRefiller {
                         self.active_connection_count() + 1,
                     );
 
+                    if evt.requested_shard.is_none()
+                        && self.static_shard_awareness_status().is_none()
+                        && matches!(
+                            *self.shard_awareness_status.read().unwrap(),
+                            ShardAwarenessStatus::Inactive { .. }
+                        )
+                    {
+                        self.regular_port_conns.push(Arc::downgrade(&conn));
+                    }
+
                     self.connection_errors
                         .push(wait_for_error(Arc::downgrade(&conn), error_receiver).boxed());
                     self.conns[shard_id].push(conn);
@@ -867,6 +1318,7 @@ impl PoolRefiller {
         mut endpoint: UntranslatedEndpoint,
     ) -> impl Future<Output = UntranslatedEndpoint> {
         let cloud_config = self.pool_config.connection_config.cloud_config.clone();
+        let resolver = DnsResolver::new(self.pool_config.connection_config.runtime.clone());
         async move {
             if let Some(cloud_config) = cloud_config {
                 // If we operate in the serverless Cloud, then we substitute every node's address
@@ -881,7 +1333,7 @@ impl PoolRefiller {
                     if let Some(dc) = datacenter.as_deref() {
                         if let Some(dc_config) = cloud_config.get_datacenters().get(dc) {
                             let hostname = dc_config.get_server();
-                            if let Ok(resolved) = resolve_hostname(hostname).await {
+                            if let Ok(resolved) = resolve_hostname(&resolver, hostname).await {
                                 *address = NodeAddr::Untranslatable(resolved)
                             } else {
                                 warn!(
@@ -918,6 +1370,16 @@ impl PoolRefiller {
     // the shard aware port is available, it will attempt to connect directly
     // to the shard using the port.
     fn start_opening_connection(&self, shard: Option<Shard>) {
+        self.start_opening_connection_impl(shard, false);
+    }
+
+    // Starts opening a connection to the shard-aware port which will replace
+    // a connection to the given shard opened to the regular port.
+    fn start_opening_migration_connection(&self, shard: Shard) {
+        self.start_opening_connection_impl(Some(shard), true);
+    }
+
+    fn start_opening_connection_impl(&self, shard: Option<Shard>, migration: bool) {
         let cfg = self.pool_config.connection_config.clone();
         let endpoint = self.endpoint.read().unwrap().clone();
 
@@ -944,6 +1406,8 @@ impl PoolRefiller {
                     result,
                     requested_shard: Some(shard),
                     keyspace_name: None,
+                    migration,
+                    keyspace_retry_delay: MIN_FILL_BACKOFF,
                 }
             }
             .boxed(),
@@ -955,6 +1419,8 @@ impl PoolRefiller {
                     result,
                     requested_shard: None,
                     keyspace_name: None,
+                    migration,
+                    keyspace_retry_delay: MIN_FILL_BACKOFF,
                 }
             }
             .boxed(),
@@ -984,6 +1450,7 @@ impl PoolRefiller {
         self.conns.resize_with(shard_count, Vec::new);
 
         self.excess_connections.clear();
+        self.regular_port_conns.clear();
     }
 
     // Updates `shared_conns` based on `conns`.
@@ -1088,6 +1555,7 @@ impl PoolRefiller {
         let keyspace_name = keyspace_name.clone();
         let address = self.endpoint.read().unwrap().address();
         let connect_timeout = self.pool_config.connection_config.connect_timeout;
+        let runtime = self.pool_config.connection_config.runtime.clone();
 
         let fut = async move {
             let mut use_keyspace_futures = Vec::new();
@@ -1103,12 +1571,13 @@ impl PoolRefiller {
                 return Ok(());
             }
 
-            let use_keyspace_results: Vec<Result<(), QueryError>> = tokio::time::timeout(
+            let use_keyspace_results: Vec<Result<(), QueryError>> = runtime::timeout(
+                &*runtime,
                 connect_timeout,
                 futures::future::join_all(use_keyspace_futures),
             )
             .await
-            .map_err(|_| QueryError::TimeoutError)?;
+            .map_err(|_| QueryError::from(TimeoutError::Connect))?;
 
             // If there was at least one Ok and the rest were IoErrors we can return Ok
             // keyspace name is correct and will be used on broken connection on the next reconnect
@@ -1117,13 +1586,16 @@ impl PoolRefiller {
             // If there was an error different than IoError return this error - something is wrong
 
             let mut was_ok: bool = false;
-            let mut io_error: Option<Arc<std::io::Error>> = None;
+            let mut io_error: Option<QueryError> = None;
 
             for result in use_keyspace_results {
                 match result {
                     Ok(()) => was_ok = true,
                     Err(err) => match err {
-                        QueryError::IoError(io_err) => io_error = Some(io_err),
+                        QueryError::ConnectionError {
+                            error: ConnectionError::IoError(_),
+                            ..
+                        } => io_error = Some(err),
                         _ => return Err(err),
                     },
                 }
@@ -1134,17 +1606,20 @@ impl PoolRefiller {
             }
 
             // We can unwrap io_error because use_keyspace_futures must be nonempty
-            Err(QueryError::IoError(io_error.unwrap()))
+            Err(io_error.unwrap())
         };
 
-        tokio::task::spawn(async move {
-            let res = fut.await;
-            match &res {
-                Ok(()) => debug!("[{}] Successfully changed current keyspace", address),
-                Err(err) => warn!("[{}] Failed to change keyspace: {:?}", address, err),
-            }
-            let _ = response_sender.send(res);
-        });
+        self.pool_config
+            .connection_config
+            .runtime
+            .spawn(Box::pin(async move {
+                let res = fut.await.map_err(|err| err.with_node(address.into_inner()));
+                match &res {
+                    Ok(()) => debug!("[{}] Successfully changed current keyspace", address),
+                    Err(err) => warn!("[{}] Failed to change keyspace: {:?}", address, err),
+                }
+                let _ = response_sender.send(res);
+            }));
     }
 
     // Requires the keyspace to be set
@@ -1152,26 +1627,74 @@ impl PoolRefiller {
     fn start_setting_keyspace_for_connection(
         &mut self,
         connection: Connection,
-        error_receiver: ErrorReceiver,
+        mut error_receiver: ErrorReceiver,
         requested_shard: Option<Shard>,
+        migration: bool,
+        retry_delay: Duration,
     ) {
-        // TODO: There should be a timeout for this
-
         let keyspace_name = self.current_keyspace.as_ref().cloned().unwrap();
+        let timeout = self.pool_config.connection_config.connect_timeout;
+        let runtime = self.pool_config.connection_config.runtime.clone();
         self.ready_connections.push(
             async move {
-                let result = connection.use_keyspace(&keyspace_name).await;
-                if let Err(err) = result {
-                    warn!(
-                        "[{}] Failed to set keyspace for new connection: {}",
-                        connection.get_connect_address().ip(),
-                        err,
-                    );
-                }
-                OpenedConnectionEvent {
-                    result: Ok((connection, error_receiver)),
-                    requested_shard,
-                    keyspace_name: Some(keyspace_name),
+                let result =
+                    runtime::timeout(&*runtime, timeout, connection.use_keyspace(&keyspace_name))
+                        .await
+                        .unwrap_or_else(|_| Err(TimeoutError::Connect.into()))
+                        .map_err(|err| err.with_node(connection.get_connect_address()));
+                match result {
+                    Ok(()) => OpenedConnectionEvent {
+                        result: Ok((connection, error_receiver)),
+                        requested_shard,
+                        keyspace_name: Some(keyspace_name),
+                        migration,
+                        keyspace_retry_delay: MIN_FILL_BACKOFF,
+                    },
+                    Err(err)
+                        if matches!(
+                            error_receiver.try_recv(),
+                            Err(tokio::sync::oneshot::error::TryRecvError::Empty)
+                        ) =>
+                    {
+                        // A connection using a different keyspace than the rest of the pool
+                        // would silently execute queries against wrong tables, so it doesn't
+                        // join the pool yet. The connection itself works, so instead of opening
+                        // a new one, setting the keyspace is retried after a delay. The event
+                        // travels through `handle_ready_connection` again, which uses
+                        // the keyspace that is current at that time.
+                        warn!(
+                            "[{}] Failed to set keyspace for new connection: {}, will retry in {} ms",
+                            connection.get_connect_address().ip(),
+                            err,
+                            retry_delay.as_millis(),
+                        );
+                        runtime.sleep(retry_delay).await;
+                        OpenedConnectionEvent {
+                            result: Ok((connection, error_receiver)),
+                            requested_shard,
+                            keyspace_name: None,
+                            migration,
+                            keyspace_retry_delay: std::cmp::min(
+                                MAX_FILL_BACKOFF,
+                                retry_delay * FILL_BACKOFF_MULTIPLIER,
+                            ),
+                        }
+                    }
+                    Err(err) => {
+                        // The connection broke, the pool will open a new one with the usual backoff.
+                        warn!(
+                            "[{}] Failed to set keyspace for new connection: {}",
+                            connection.get_connect_address().ip(),
+                            err,
+                        );
+                        OpenedConnectionEvent {
+                            result: Err(err),
+                            requested_shard: None,
+                            keyspace_name: None,
+                            migration,
+                            keyspace_retry_delay: MIN_FILL_BACKOFF,
+                        }
+                    }
                 }
             }
             .boxed(),
@@ -1214,10 +1737,7 @@ async fn wait_for_error(
     BrokenConnectionEvent {
         connection,
         error: error_receiver.await.unwrap_or_else(|_| {
-            QueryError::IoError(Arc::new(std::io::Error::new(
-                ErrorKind::Other,
-                "Connection broken",
-            )))
+            QueryError::from(std::io::Error::new(ErrorKind::Other, "Connection broken"))
         }),
     }
 }
@@ -1226,6 +1746,22 @@ struct OpenedConnectionEvent {
     result: Result<(Connection, ErrorReceiver), QueryError>,
     requested_shard: Option<Shard>,
     keyspace_name: Option<VerifiedKeyspaceName>,
+    // Whether the connection was opened to replace a connection opened to the regular port
+    migration: bool,
+    // Delay before retrying to set the keyspace, if it fails for this connection
+    keyspace_retry_delay: Duration,
+}
+
+fn shard_aware_port_failure_reason(err: &QueryError) -> ShardAwarenessInactiveReason {
+    match err {
+        QueryError::ConnectionError {
+            error: ConnectionError::IoError(io_err),
+            ..
+        } if io_err.kind() == ErrorKind::AddrInUse => {
+            ShardAwarenessInactiveReason::NoFreeSourcePort
+        }
+        _ => ShardAwarenessInactiveReason::ConnectionFailed(err.to_string()),
+    }
 }
 
 async fn open_connection_to_shard_aware_port(
@@ -1248,21 +1784,228 @@ async fn open_connection_to_shard_aware_port(
     }
 
     // Tried all source ports for that shard, give up
-    Err(QueryError::IoError(Arc::new(std::io::Error::new(
+    Err(QueryError::from(std::io::Error::new(
         std::io::ErrorKind::AddrInUse,
         "Could not find free source port for shard",
-    ))))
+    )))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::open_connection_to_shard_aware_port;
-    use crate::routing::{ShardCount, Sharder};
+    use super::{
+        open_connection_to_shard_aware_port, OpenedConnectionEvent, PoolConfig, PoolRefiller,
+        PoolSize, PoolStats, ShardAwarenessInactiveReason, ShardAwarenessStatus, MIN_FILL_BACKOFF,
+    };
+    use crate::routing::{Shard, ShardCount, ShardInfo, Sharder};
     use crate::test_utils::setup_tracing;
-    use crate::transport::connection::ConnectionConfig;
+    use crate::transport::connection::{Connection, ConnectionConfig};
+    use crate::transport::errors::QueryError;
     use crate::transport::node::ResolvedContactPoint;
     use crate::transport::topology::UntranslatedEndpoint;
     use std::net::{SocketAddr, ToSocketAddrs};
+    use std::num::NonZeroUsize;
+    use std::sync::{Arc, RwLock, Weak};
+    use std::time::Duration;
+    use tokio::sync::{broadcast, Notify};
+
+    const SHARD_COUNT: u16 = 2;
+
+    async fn opened_connection(
+        requested_shard: Option<Shard>,
+        shard: u16,
+        migration: bool,
+    ) -> OpenedConnectionEvent {
+        let shard_info = ShardInfo::new(shard, ShardCount::new(SHARD_COUNT).unwrap(), 12);
+        OpenedConnectionEvent {
+            result: Ok(Connection::new_for_test(Some(shard_info), Some(19042)).await),
+            requested_shard,
+            keyspace_name: None,
+            migration,
+            keyspace_retry_delay: MIN_FILL_BACKOFF,
+        }
+    }
+
+    fn failed_connection(requested_shard: Shard, migration: bool) -> OpenedConnectionEvent {
+        OpenedConnectionEvent {
+            result: Err(QueryError::from(std::io::Error::new(
+                std::io::ErrorKind::ConnectionRefused,
+                "Connection refused",
+            ))),
+            requested_shard: Some(requested_shard),
+            keyspace_name: None,
+            migration,
+            keyspace_retry_delay: MIN_FILL_BACKOFF,
+        }
+    }
+
+    // Returns a refiller whose pool, one connection per shard, was filled through
+    // the regular port because the shard-aware port failed, and which may use
+    // the shard-aware port again.
+    async fn refiller_with_regular_port_connections() -> PoolRefiller {
+        let pool_config = PoolConfig {
+            pool_size: PoolSize::PerShard(NonZeroUsize::new(1).unwrap()),
+            shard_aware_port_retry_interval: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        let endpoint = UntranslatedEndpoint::ContactPoint(ResolvedContactPoint {
+            address: "127.0.0.1:9042".parse().unwrap(),
+            datacenter: None,
+            alternative_addresses: Vec::new(),
+        });
+        let mut refiller = PoolRefiller::new(
+            Arc::new(RwLock::new(endpoint)),
+            Arc::new(RwLock::new(ShardAwarenessStatus::Unknown)),
+            Arc::new(PoolStats::new()),
+            pool_config,
+            None,
+            Arc::new(Notify::new()),
+            broadcast::channel(1).0,
+        );
+
+        refiller.on_shard_aware_port_failure(ShardAwarenessInactiveReason::ConnectionFailed(
+            "Connection refused".to_owned(),
+        ));
+        for shard in 0..SHARD_COUNT {
+            refiller.handle_ready_connection(opened_connection(None, shard, false).await);
+        }
+        assert_eq!(refiller.regular_port_conns.len(), SHARD_COUNT as usize);
+        assert!(refiller.is_full());
+
+        refiller.shard_aware_port_disabled_until = None;
+        assert!(refiller.need_migration());
+        refiller
+    }
+
+    fn pool_connections(refiller: &PoolRefiller) -> Vec<Weak<Connection>> {
+        refiller
+            .conns
+            .iter()
+            .flatten()
+            .map(Arc::downgrade)
+            .collect()
+    }
+
+    fn is_in_pool(refiller: &PoolRefiller, connection: &Weak<Connection>) -> bool {
+        refiller
+            .conns
+            .iter()
+            .flatten()
+            .any(|conn| std::ptr::eq(Arc::as_ptr(conn), connection.as_ptr()))
+    }
+
+    #[tokio::test]
+    async fn all_regular_port_connections_are_migrated() {
+        setup_tracing();
+        let mut refiller = refiller_with_regular_port_connections().await;
+        let regular_port_conns = pool_connections(&refiller);
+
+        // The first connection probes the shard-aware port
+        refiller.start_migrating_connections(1);
+        assert_eq!(refiller.migrations_in_flight, 1);
+        refiller.handle_ready_connection(opened_connection(Some(0), 0, true).await);
+
+        // It works, so the other connection is migrated too
+        assert_eq!(refiller.migrations_in_flight, 1);
+        assert_eq!(refiller.regular_port_conns.len(), 1);
+        refiller.handle_ready_connection(opened_connection(Some(1), 1, true).await);
+
+        assert_eq!(refiller.migrations_in_flight, 0);
+        assert!(!refiller.need_migration());
+        assert!(refiller.is_full());
+        for (shard, conns) in refiller.conns.iter().enumerate() {
+            assert_eq!(conns.len(), 1);
+            assert_eq!(
+                conns[0].get_shard_info().as_ref().unwrap().shard as usize,
+                shard
+            );
+        }
+        // The replaced connections were released by the pool
+        for conn in regular_port_conns {
+            assert_eq!(conn.strong_count(), 0);
+        }
+    }
+
+    #[tokio::test]
+    async fn regular_port_connections_are_kept_until_migrated() {
+        setup_tracing();
+        let mut refiller = refiller_with_regular_port_connections().await;
+        let regular_port_conns = pool_connections(&refiller);
+
+        refiller.start_migrating_connections(1);
+        refiller.handle_ready_connection(opened_connection(Some(0), 0, true).await);
+        // The shard-aware port breaks again before the other connection is migrated
+        refiller.handle_ready_connection(failed_connection(1, true));
+
+        assert_eq!(refiller.migrations_in_flight, 0);
+        assert!(refiller.shard_aware_port_disabled_until.is_some());
+        assert!(refiller.is_full());
+        let remaining: Vec<_> = regular_port_conns
+            .iter()
+            .filter(|conn| is_in_pool(&refiller, conn))
+            .collect();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(refiller.regular_port_conns.len(), 1);
+        assert!(std::ptr::eq(
+            refiller.regular_port_conns[0].as_ptr(),
+            remaining[0].as_ptr()
+        ));
+    }
+
+    #[tokio::test]
+    async fn failed_shard_aware_connection_does_not_replace_connections() {
+        setup_tracing();
+        let mut refiller = refiller_with_regular_port_connections().await;
+        let regular_port_conns = pool_connections(&refiller);
+
+        refiller.start_migrating_connections(1);
+        refiller.handle_ready_connection(failed_connection(0, true));
+        assert_eq!(refiller.migrations_in_flight, 0);
+        assert!(refiller.shard_aware_port_disabled_until.is_some());
+
+        // A connection which lands on a wrong shard doesn't replace anything either
+        refiller.shard_aware_port_disabled_until = None;
+        refiller.start_migrating_connections(1);
+        refiller.handle_ready_connection(opened_connection(Some(0), 1, true).await);
+        assert_eq!(refiller.migrations_in_flight, 0);
+        assert!(refiller.shard_aware_port_disabled_until.is_some());
+
+        assert_eq!(refiller.regular_port_conns.len(), SHARD_COUNT as usize);
+        assert_eq!(refiller.active_connection_count(), SHARD_COUNT as usize);
+        for conn in &regular_port_conns {
+            assert!(is_in_pool(&refiller, conn));
+        }
+    }
+
+    #[tokio::test]
+    async fn migrated_connection_fills_pool_if_replaced_connection_is_gone() {
+        setup_tracing();
+        let mut refiller = refiller_with_regular_port_connections().await;
+
+        refiller.start_migrating_connections(1);
+        // The connection which was to be replaced breaks in the meantime
+        let broken = refiller.conns[0][0].clone();
+        refiller.remove_connection(
+            broken,
+            QueryError::from(std::io::Error::new(
+                std::io::ErrorKind::ConnectionReset,
+                "Connection reset",
+            )),
+        );
+        assert!(refiller.conns[0].is_empty());
+
+        refiller.handle_ready_connection(opened_connection(Some(0), 0, true).await);
+        assert_eq!(refiller.conns[0].len(), 1);
+        assert_eq!(
+            refiller.conns[0][0]
+                .get_shard_info()
+                .as_ref()
+                .unwrap()
+                .shard,
+            0
+        );
+        // The shard-aware port works, so the other connection is being migrated
+        assert_eq!(refiller.migrations_in_flight, 1);
+    }
 
     // Open many connections to a node
     // Port collision should occur
@@ -1301,6 +2044,7 @@ mod tests {
                 UntranslatedEndpoint::ContactPoint(ResolvedContactPoint {
                     address: connect_address,
                     datacenter: None,
+                    alternative_addresses: Vec::new(),
                 }),
                 0,
                 sharder.clone(),
@@ -0,0 +1,46 @@
+//! Connectors.
+//!
+//! A [`Connector`] opens the byte streams over which the driver speaks CQL.
+//! By default, the driver opens TCP connections, but a custom connector
+//! can be supplied to the [`Session`](crate::transport::session::Session)
+//! in order to connect e.g. over Unix domain sockets, through SOCKS or HTTP CONNECT
+//! proxies, or to an in-process transport in tests.
+//!
+//! On WebAssembly targets (`wasm32-wasip1`, as well as `wasm32-unknown-unknown` with
+//! a `getrandom` backend enabled by the application) the driver can't open TCP connections
+//! nor resolve hostnames, so a connector is required, e.g. one which tunnels the connection
+//! through a websocket provided by the host. Known nodes have to be given as IP addresses.
+
+use std::net::SocketAddr;
+
+use async_trait::async_trait;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// A bidirectional byte stream that can be used by a connection.
+///
+/// It is implemented for every type which implements [`AsyncRead`] and [`AsyncWrite`]
+/// and can be sent between threads, e.g. `tokio::net::UnixStream`.
+pub trait ConnectionStream: AsyncRead + AsyncWrite + Send + Unpin + 'static {}
+
+impl<T> ConnectionStream for T where T: AsyncRead + AsyncWrite + Send + Unpin + 'static {}
+
+/// The `Connector` trait.
+///
+/// When set, it is used by the driver to open all connections instead of `TcpStream::connect`.
+/// TLS (if configured) is still established by the driver on top of the returned stream,
+/// but TCP-specific options, like `tcp_nodelay` or `tcp_keepalive_interval`, are not applied.
+#[async_trait]
+pub trait Connector: Send + Sync {
+    /// Opens a stream to the node at the given address.
+    ///
+    /// `source_port` is set when the driver connects to the shard-aware port of a node
+    /// and needs the connection to originate from that local port. If the port cannot be used,
+    /// the connector should return an error of kind `AddrInUse` or `AddrNotAvailable`,
+    /// so that the driver tries another one. Connectors which have no notion of source ports
+    /// may ignore it; consider disabling the shard-aware port in such case.
+    async fn connect(
+        &self,
+        address: SocketAddr,
+        source_port: Option<u16>,
+    ) -> std::io::Result<Box<dyn ConnectionStream>>;
+}
@@ -0,0 +1,115 @@
+//! Configuration of the control connection.
+//!
+//! The control connection is a single connection, opened to one of the nodes,
+//! which the driver uses to fetch the cluster metadata and to receive server events.
+//! When it breaks or a metadata fetch fails on it, the driver moves it to another node.
+//!
+//! Its behavior can be adjusted with the following [`SessionBuilder`](crate::transport::session_builder::GenericSessionBuilder) methods:
+//! - [`control_connection_host_filter`](crate::transport::session_builder::GenericSessionBuilder::control_connection_host_filter)
+//!   restricts the nodes which may host the control connection,
+//! - [`control_connection_reconnect_backoff`](crate::transport::session_builder::GenericSessionBuilder::control_connection_reconnect_backoff)
+//!   sets the delays between the attempts to re-establish a broken control connection,
+//! - [`metadata_request_timeout`](crate::transport::session_builder::GenericSessionBuilder::metadata_request_timeout)
+//!   sets the timeout of the metadata fetch on the control connection,
+//! - [`control_connection_listener`](crate::transport::session_builder::GenericSessionBuilder::control_connection_listener)
+//!   sets a [`ControlConnectionListener`], notified when the control connection changes hosts.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::transport::host_filter::HostFilter;
+
+/// Receives notifications about the control connection.
+pub trait ControlConnectionListener: Send + Sync {
+    /// Called when the control connection is moved from the node with the `previous`
+    /// address to the node with the `current` address, before the connection to
+    /// the new node is established.
+    fn on_control_connection_change(&self, previous: SocketAddr, current: SocketAddr);
+}
+
+/// Control connection settings, extracted from the session config.
+#[derive(Clone)]
+pub(crate) struct ControlConnectionConfig {
+    pub(crate) host_filter: Option<Arc<dyn HostFilter>>,
+    pub(crate) metadata_request_timeout: Option<Duration>,
+    pub(crate) min_reconnect_interval: Duration,
+    pub(crate) max_reconnect_interval: Duration,
+    pub(crate) listener: Option<Arc<dyn ControlConnectionListener>>,
+}
+
+/// Exponential backoff of the attempts to re-establish a broken control connection.
+pub(crate) struct ReconnectBackoff {
+    min_interval: Duration,
+    max_interval: Duration,
+    current_interval: Duration,
+}
+
+impl ReconnectBackoff {
+    pub(crate) fn new(config: &ControlConnectionConfig) -> Self {
+        Self {
+            min_interval: config.min_reconnect_interval,
+            max_interval: config.max_reconnect_interval,
+            current_interval: config.min_reconnect_interval,
+        }
+    }
+
+    pub(crate) fn get_interval(&self) -> Duration {
+        self.current_interval
+    }
+
+    pub(crate) fn on_success(&mut self) {
+        self.current_interval = self.min_interval;
+    }
+
+    pub(crate) fn on_failure(&mut self) {
+        self.current_interval =
+            std::cmp::min(self.max_interval, self.current_interval.saturating_mul(2));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{ControlConnectionConfig, ReconnectBackoff};
+
+    #[test]
+    fn reconnect_backoff_grows_until_success() {
+        let mut backoff = ReconnectBackoff::new(&ControlConnectionConfig {
+            host_filter: None,
+            metadata_request_timeout: None,
+            min_reconnect_interval: Duration::from_millis(100),
+            max_reconnect_interval: Duration::from_millis(350),
+            listener: None,
+        });
+
+        let mut intervals = vec![backoff.get_interval()];
+        for _ in 0..3 {
+            backoff.on_failure();
+            intervals.push(backoff.get_interval());
+        }
+        assert_eq!(
+            intervals,
+            [100, 200, 350, 350].map(Duration::from_millis).to_vec()
+        );
+
+        backoff.on_success();
+        assert_eq!(backoff.get_interval(), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn reconnect_backoff_does_not_overflow() {
+        let mut backoff = ReconnectBackoff::new(&ControlConnectionConfig {
+            host_filter: None,
+            metadata_request_timeout: None,
+            min_reconnect_interval: Duration::MAX / 3,
+            max_reconnect_interval: Duration::MAX,
+            listener: None,
+        });
+
+        backoff.on_failure();
+        backoff.on_failure();
+        assert_eq!(backoff.get_interval(), Duration::MAX);
+    }
+}
@@ -1,5 +1,5 @@
 use scylla_cql::{
-    errors::{DbError, QueryError, WriteType},
+    errors::{ConnectionError, DbError, QueryError, WriteType},
     Consistency,
 };
 use tracing::debug;
@@ -57,7 +57,10 @@ impl RetrySession for DowngradingConsistencyRetrySession {
         let cl = match query_info.consistency {
             Consistency::Serial | Consistency::LocalSerial => {
                 return match query_info.error {
-                    QueryError::DbError(DbError::Unavailable { .. }, _) => {
+                    QueryError::DbError {
+                        error: DbError::Unavailable { .. },
+                        ..
+                    } => {
                         // JAVA-764: if the requested consistency level is serial, it means that the operation failed at
                         // the paxos phase of a LWT.
                         // Retry on the next host, on the assumption that the initial coordinator could be network-isolated.
@@ -94,10 +97,22 @@ impl RetrySession for DowngradingConsistencyRetrySession {
         match query_info.error {
             // Basic errors - there are some problems on this node
             // Retry on a different one if possible
-            QueryError::IoError(_)
-            | QueryError::DbError(DbError::Overloaded, _)
-            | QueryError::DbError(DbError::ServerError, _)
-            | QueryError::DbError(DbError::TruncateError, _) => {
+            QueryError::ConnectionError {
+                error: ConnectionError::IoError(_),
+                ..
+            }
+            | QueryError::DbError {
+                error: DbError::Overloaded,
+                ..
+            }
+            | QueryError::DbError {
+                error: DbError::ServerError,
+                ..
+            }
+            | QueryError::DbError {
+                error: DbError::TruncateError,
+                ..
+            } => {
                 if query_info.is_idempotent {
                     RetryDecision::RetryNextNode(None)
                 } else {
@@ -106,7 +121,10 @@ impl RetrySession for DowngradingConsistencyRetrySession {
             }
             // Unavailable - the current node believes that not enough nodes
             // are alive to satisfy specified consistency requirements.
-            QueryError::DbError(DbError::Unavailable { alive, .. }, _) => {
+            QueryError::DbError {
+                error: DbError::Unavailable { alive, .. },
+                ..
+            } => {
                 if !self.was_retry {
                     self.was_retry = true;
                     max_likely_to_work_cl(*alive, cl)
@@ -115,15 +133,16 @@ impl RetrySession for DowngradingConsistencyRetrySession {
                 }
             }
             // ReadTimeout - coordinator didn't receive enough replies in time.
-            QueryError::DbError(
-                DbError::ReadTimeout {
-                    received,
-                    required,
-                    data_present,
-                    ..
-                },
-                _,
-            ) => {
+            QueryError::DbError {
+                error:
+                    DbError::ReadTimeout {
+                        received,
+                        required,
+                        data_present,
+                        ..
+                    },
+                ..
+            } => {
                 if self.was_retry {
                     RetryDecision::DontRetry
                 } else if received < required {
@@ -137,14 +156,15 @@ impl RetrySession for DowngradingConsistencyRetrySession {
                 }
             }
             // Write timeout - coordinator didn't receive enough replies in time.
-            QueryError::DbError(
-                DbError::WriteTimeout {
-                    write_type,
-                    received,
-                    ..
-                },
-                _,
-            ) => {
+            QueryError::DbError {
+                error:
+                    DbError::WriteTimeout {
+                        write_type,
+                        received,
+                        ..
+                    },
+                ..
+            } => {
                 if self.was_retry || !query_info.is_idempotent {
                     RetryDecision::DontRetry
                 } else {
@@ -166,9 +186,15 @@ impl RetrySession for DowngradingConsistencyRetrySession {
                 }
             }
             // The node is still bootstrapping it can't execute the query, we should try another one
-            QueryError::DbError(DbError::IsBootstrapping, _) => RetryDecision::RetryNextNode(None),
+            QueryError::DbError {
+                error: DbError::IsBootstrapping,
+                ..
+            } => RetryDecision::RetryNextNode(None),
             // Connection to the contacted node is overloaded, try another one
-            QueryError::UnableToAllocStreamId => RetryDecision::RetryNextNode(None),
+            QueryError::ConnectionError {
+                error: ConnectionError::UnableToAllocStreamId,
+                ..
+            } => RetryDecision::RetryNextNode(None),
             // In all other cases propagate the error to the user
             _ => RetryDecision::DontRetry,
         }
@@ -181,10 +207,10 @@ impl RetrySession for DowngradingConsistencyRetrySession {
 
 #[cfg(test)]
 mod tests {
-    use std::{io::ErrorKind, sync::Arc};
+    use std::io::ErrorKind;
 
     use bytes::Bytes;
-    use scylla_cql::errors::BadQuery;
+    use scylla_cql::errors::{BadQuery, OperationType};
 
     use crate::test_utils::setup_tracing;
 
@@ -252,6 +278,7 @@ mod tests {
                 received: 1,
                 required: 2,
                 numfailures: 1,
+                failure_reasons: None,
                 data_present: false,
             },
             DbError::WriteFailure {
@@ -259,19 +286,28 @@ mod tests {
                 received: 1,
                 required: 2,
                 numfailures: 1,
+                failure_reasons: None,
                 write_type: WriteType::BatchLog,
             },
             DbError::Unprepared {
                 statement_id: Bytes::from_static(b"deadbeef"),
             },
             DbError::ProtocolError,
+            DbError::RateLimitReached {
+                op_type: OperationType::Write,
+                rejected_by_coordinator: true,
+            },
             DbError::Other(0x124816),
         ];
 
         for &cl in CONSISTENCY_LEVELS {
             for dberror in never_retried_dberrors.clone() {
                 downgrading_consistency_policy_assert_never_retries(
-                    QueryError::DbError(dberror, String::new()),
+                    QueryError::DbError {
+                        error: dberror,
+                        message: String::new(),
+                        node: None,
+                    },
                     cl,
                 );
             }
@@ -325,10 +361,22 @@ mod tests {
     fn downgrading_consistency_idempotent_next_retries() {
         setup_tracing();
         let idempotent_next_errors = vec![
-            QueryError::DbError(DbError::Overloaded, String::new()),
-            QueryError::DbError(DbError::TruncateError, String::new()),
-            QueryError::DbError(DbError::ServerError, String::new()),
-            QueryError::IoError(Arc::new(std::io::Error::new(ErrorKind::Other, "test"))),
+            QueryError::DbError {
+                error: DbError::Overloaded,
+                message: String::new(),
+                node: None,
+            },
+            QueryError::DbError {
+                error: DbError::TruncateError,
+                message: String::new(),
+                node: None,
+            },
+            QueryError::DbError {
+                error: DbError::ServerError,
+                message: String::new(),
+                node: None,
+            },
+            QueryError::from(std::io::Error::new(ErrorKind::Other, "test")),
         ];
 
         for &cl in CONSISTENCY_LEVELS {
@@ -342,7 +390,11 @@ mod tests {
     #[test]
     fn downgrading_consistency_bootstrapping() {
         setup_tracing();
-        let error = QueryError::DbError(DbError::IsBootstrapping, String::new());
+        let error = QueryError::DbError {
+            error: DbError::IsBootstrapping,
+            message: String::new(),
+            node: None,
+        };
 
         for &cl in CONSISTENCY_LEVELS {
             let mut policy = DowngradingConsistencyRetryPolicy::new().new_session();
@@ -364,14 +416,15 @@ mod tests {
     fn downgrading_consistency_unavailable() {
         setup_tracing();
         let alive = 1;
-        let error = QueryError::DbError(
-            DbError::Unavailable {
+        let error = QueryError::DbError {
+            error: DbError::Unavailable {
                 consistency: Consistency::Two,
                 required: 2,
                 alive,
             },
-            String::new(),
-        );
+            message: String::new(),
+            node: None,
+        };
 
         for &cl in CONSISTENCY_LEVELS {
             let mut policy_not_idempotent = DowngradingConsistencyRetryPolicy::new().new_session();
@@ -403,15 +456,16 @@ mod tests {
     fn downgrading_consistency_read_timeout() {
         setup_tracing();
         // Enough responses and data_present == false - coordinator received only checksums
-        let enough_responses_no_data = QueryError::DbError(
-            DbError::ReadTimeout {
+        let enough_responses_no_data = QueryError::DbError {
+            error: DbError::ReadTimeout {
                 consistency: Consistency::Two,
                 received: 2,
                 required: 2,
                 data_present: false,
             },
-            String::new(),
-        );
+            message: String::new(),
+            node: None,
+        };
 
         for &cl in CONSISTENCY_LEVELS {
             // Not idempotent
@@ -454,15 +508,16 @@ mod tests {
         }
         // Enough responses but data_present == true - coordinator probably timed out
         // waiting for read-repair acknowledgement.
-        let enough_responses_with_data = QueryError::DbError(
-            DbError::ReadTimeout {
+        let enough_responses_with_data = QueryError::DbError {
+            error: DbError::ReadTimeout {
                 consistency: Consistency::Two,
                 received: 2,
                 required: 2,
                 data_present: true,
             },
-            String::new(),
-        );
+            message: String::new(),
+            node: None,
+        };
 
         for &cl in CONSISTENCY_LEVELS {
             // Not idempotent
@@ -490,15 +545,16 @@ mod tests {
 
         // Not enough responses, data_present == true
         let received = 1;
-        let not_enough_responses_with_data = QueryError::DbError(
-            DbError::ReadTimeout {
+        let not_enough_responses_with_data = QueryError::DbError {
+            error: DbError::ReadTimeout {
                 consistency: Consistency::Two,
                 received,
                 required: 2,
                 data_present: true,
             },
-            String::new(),
-        );
+            message: String::new(),
+            node: None,
+        };
         for &cl in CONSISTENCY_LEVELS {
             let expected_decision = max_likely_to_work_cl(received, cl);
 
@@ -552,15 +608,16 @@ mod tests {
         setup_tracing();
         for (received, required) in (1..=5).zip(2..=6) {
             // WriteType == BatchLog
-            let write_type_batchlog = QueryError::DbError(
-                DbError::WriteTimeout {
+            let write_type_batchlog = QueryError::DbError {
+                error: DbError::WriteTimeout {
                     consistency: Consistency::Two,
                     received,
                     required,
                     write_type: WriteType::BatchLog,
                 },
-                String::new(),
-            );
+                message: String::new(),
+                node: None,
+            };
 
             for &cl in CONSISTENCY_LEVELS {
                 // Not idempotent
@@ -595,15 +652,16 @@ mod tests {
             }
 
             // WriteType == UnloggedBatch
-            let write_type_unlogged_batch = QueryError::DbError(
-                DbError::WriteTimeout {
+            let write_type_unlogged_batch = QueryError::DbError {
+                error: DbError::WriteTimeout {
                     consistency: Consistency::Two,
                     received,
                     required,
                     write_type: WriteType::UnloggedBatch,
                 },
-                String::new(),
-            );
+                message: String::new(),
+                node: None,
+            };
 
             for &cl in CONSISTENCY_LEVELS {
                 // Not idempotent
@@ -638,15 +696,16 @@ mod tests {
             }
 
             // WriteType == other
-            let write_type_other = QueryError::DbError(
-                DbError::WriteTimeout {
+            let write_type_other = QueryError::DbError {
+                error: DbError::WriteTimeout {
                     consistency: Consistency::Two,
                     received,
                     required,
                     write_type: WriteType::Simple,
                 },
-                String::new(),
-            );
+                message: String::new(),
+                node: None,
+            };
 
             for &cl in CONSISTENCY_LEVELS {
                 // Not idempotent
@@ -0,0 +1,337 @@
+//! Information about the attempts, retries and speculative executions of a request.
+//!
+//! Collecting it is disabled by default and can be enabled for a statement with
+//! [`Query::set_collect_execution_info`](crate::query::Query::set_collect_execution_info)
+//! (or the same method of [`PreparedStatement`](crate::prepared_statement::PreparedStatement)
+//! and [`Batch`](crate::batch::Batch)). The collected [`ExecutionInfo`] is then returned in
+//! [`QueryResult::execution_info`](crate::QueryResult::execution_info).
+//! To get it for failed requests as well, attach an [`ExecutionInfoCollector`] to the statement.
+//! Paged queries executed with `query_iter` and `execute_iter` report their attempts
+//! per page, see [`RowIterator::get_pages_info`](crate::transport::iterator::RowIterator::get_pages_info).
+//!
+//! # Example
+//! ```rust
+//! # use scylla::Session;
+//! # use std::error::Error;
+//! # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+//! use scylla::query::Query;
+//!
+//! let mut query = Query::new("SELECT a FROM ks.tab");
+//! query.set_is_idempotent(true);
+//! query.set_collect_execution_info(true);
+//!
+//! let result = session.query(query, &[]).await?;
+//! if let Some(info) = &result.execution_info {
+//!     for attempt in &info.attempts {
+//!         println!("{} took {:?}: {:?}", attempt.node, attempt.latency, attempt.outcome);
+//!     }
+//!     println!(
+//!         "Retries: {}, speculative executions: {}, won by a speculative execution: {}",
+//!         info.retries,
+//!         info.speculative_executions,
+//!         info.is_won_by_speculative_execution()
+//!     );
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::routing::Shard;
+use crate::statement::StatementConfig;
+use crate::transport::errors::QueryError;
+
+/// Attempts, retries and speculative executions of a single request.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct ExecutionInfo {
+    /// Attempts of sending the request, in the order in which they started.
+    pub attempts: Vec<AttemptInfo>,
+    /// Index of the attempt whose response was returned, in [`Self::attempts`].
+    /// `None` if the retry policy decided to ignore a write error
+    /// and the driver returned an empty result instead.
+    pub winning_attempt: Option<usize>,
+    /// Number of retries decided by the retry policy, in all executions of the request.
+    pub retries: usize,
+    /// Number of speculative executions started, apart from the original execution.
+    pub speculative_executions: usize,
+}
+
+impl ExecutionInfo {
+    /// Returns the attempt whose response was returned.
+    pub fn get_winning_attempt(&self) -> Option<&AttemptInfo> {
+        self.winning_attempt.map(|index| &self.attempts[index])
+    }
+
+    /// Checks if the returned response came from a speculative execution.
+    pub fn is_won_by_speculative_execution(&self) -> bool {
+        matches!(
+            self.get_winning_attempt(),
+            Some(AttemptInfo {
+                speculative: true,
+                ..
+            })
+        )
+    }
+}
+
+/// A single attempt of sending a request to a node.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct AttemptInfo {
+    /// Address of the node the request was sent to.
+    pub node: SocketAddr,
+    /// Shard the request was sent to, if the node is sharded.
+    pub shard: Option<Shard>,
+    /// Whether the attempt was made by a speculative execution.
+    pub speculative: bool,
+    /// Time between sending the request and receiving the response.
+    /// For an unfinished attempt, the time between sending the request
+    /// and the completion of the whole request.
+    pub latency: Duration,
+    /// How the attempt ended.
+    pub outcome: AttemptOutcome,
+}
+
+/// Outcome of an [`AttemptInfo`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum AttemptOutcome {
+    /// The attempt succeeded.
+    Success,
+    /// The attempt failed with the error.
+    Error(QueryError),
+    /// The attempt was still in progress when the request completed, and its response
+    /// was not waited for. This is the case for the attempts of speculative executions
+    /// which lost the race, and for attempts interrupted by the request timeout
+    /// or by cancelling the request.
+    Unfinished,
+}
+
+/// A handle which receives the [`ExecutionInfo`] of the requests of the statements
+/// it is attached to, no matter whether they succeeded or failed.
+///
+/// The handle is attached to a statement with e.g.
+/// [`Query::set_execution_info_collector`](crate::query::Query::set_execution_info_collector).
+/// It keeps only the information about the most recently completed request.
+///
+/// # Example
+/// ```rust
+/// # use scylla::Session;
+/// # use std::error::Error;
+/// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+/// use scylla::query::Query;
+/// use scylla::transport::execution_info::ExecutionInfoCollector;
+///
+/// let collector = ExecutionInfoCollector::new();
+/// let mut query = Query::new("SELECT a FROM ks.tab");
+/// query.set_execution_info_collector(Some(collector.clone()));
+///
+/// if let Err(err) = session.query(query, &[]).await {
+///     let info = collector.take_last().unwrap();
+///     for attempt in &info.attempts {
+///         println!("{} ({:?}): {:?}", attempt.node, attempt.shard, attempt.outcome);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ExecutionInfoCollector {
+    last: Arc<Mutex<Option<ExecutionInfo>>>,
+}
+
+impl ExecutionInfoCollector {
+    /// Creates a new collector, which has not received any information yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes the information about the most recently completed request,
+    /// leaving `None` in its place.
+    pub fn take_last(&self) -> Option<ExecutionInfo> {
+        self.last.lock().unwrap().take()
+    }
+
+    pub(crate) fn set_last(&self, info: ExecutionInfo) {
+        *self.last.lock().unwrap() = Some(info);
+    }
+}
+
+/// Collects the [`ExecutionInfo`] of a request, shared by all its executions.
+#[derive(Default)]
+pub(crate) struct ExecutionInfoRecorder {
+    state: Mutex<RecorderState>,
+}
+
+#[derive(Default)]
+struct RecorderState {
+    info: ExecutionInfo,
+    // Start times of the attempts, used to compute the latencies of the unfinished ones
+    attempt_starts: Vec<Instant>,
+}
+
+impl ExecutionInfoRecorder {
+    /// Creates a recorder if the statement collects its execution info in any way.
+    pub(crate) fn for_statement(config: &StatementConfig) -> Option<Self> {
+        (config.collect_execution_info || config.execution_info_collector.is_some())
+            .then(Self::default)
+    }
+
+    /// Completes the recorded info, hands it to the statement's collector, if any,
+    /// and returns it if it should be attached to the result.
+    pub(crate) fn finish(
+        recorder: Option<Self>,
+        config: &StatementConfig,
+    ) -> Option<ExecutionInfo> {
+        let info = recorder?.into_execution_info();
+        if let Some(collector) = &config.execution_info_collector {
+            collector.set_last(info.clone());
+        }
+        config.collect_execution_info.then_some(info)
+    }
+
+    pub(crate) fn record_speculative_execution(&self) {
+        self.state.lock().unwrap().info.speculative_executions += 1;
+    }
+
+    /// Records an attempt which has just started, as unfinished, and returns its index.
+    pub(crate) fn record_attempt_start(
+        &self,
+        node: SocketAddr,
+        shard: Option<Shard>,
+        speculative: bool,
+    ) -> usize {
+        let mut state = self.state.lock().unwrap();
+        state.info.attempts.push(AttemptInfo {
+            node,
+            shard,
+            speculative,
+            latency: Duration::ZERO,
+            outcome: AttemptOutcome::Unfinished,
+        });
+        state.attempt_starts.push(Instant::now());
+        state.info.attempts.len() - 1
+    }
+
+    pub(crate) fn record_attempt_end(
+        &self,
+        attempt_index: usize,
+        latency: Duration,
+        result: Result<(), &QueryError>,
+    ) {
+        let attempt = &mut self.state.lock().unwrap().info.attempts[attempt_index];
+        attempt.latency = latency;
+        attempt.outcome = match result {
+            Ok(()) => AttemptOutcome::Success,
+            Err(err) => AttemptOutcome::Error(err.clone()),
+        };
+    }
+
+    pub(crate) fn record_retry(&self) {
+        self.state.lock().unwrap().info.retries += 1;
+    }
+
+    pub(crate) fn record_winning_attempt(&self, attempt_index: usize) {
+        self.state.lock().unwrap().info.winning_attempt = Some(attempt_index);
+    }
+
+    pub(crate) fn into_execution_info(self) -> ExecutionInfo {
+        let RecorderState {
+            mut info,
+            attempt_starts,
+        } = self.state.into_inner().unwrap();
+        for (attempt, start) in info.attempts.iter_mut().zip(attempt_starts) {
+            if let AttemptOutcome::Unfinished = attempt.outcome {
+                attempt.latency = start.elapsed();
+            }
+        }
+        info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use std::time::Duration;
+
+    use assert_matches::assert_matches;
+
+    use super::{AttemptOutcome, ExecutionInfoCollector, ExecutionInfoRecorder};
+    use crate::transport::errors::{QueryError, TimeoutError};
+
+    fn node(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    #[test]
+    fn recorded_execution_info() {
+        let recorder = ExecutionInfoRecorder::default();
+        let first = recorder.record_attempt_start(node(1), Some(1), false);
+        recorder.record_speculative_execution();
+        let speculative = recorder.record_attempt_start(node(2), Some(1), true);
+        recorder.record_attempt_end(
+            first,
+            Duration::from_millis(1),
+            Err(&TimeoutError::Connect.into()),
+        );
+        recorder.record_retry();
+        let retry = recorder.record_attempt_start(node(3), None, false);
+        recorder.record_attempt_end(retry, Duration::from_millis(3), Ok(()));
+        recorder.record_winning_attempt(retry);
+
+        let info = recorder.into_execution_info();
+        assert_eq!(info.attempts.len(), 3);
+        assert_eq!(info.retries, 1);
+        assert_eq!(info.speculative_executions, 1);
+        assert_matches!(
+            info.attempts[first].outcome,
+            AttemptOutcome::Error(QueryError::TimeoutError {
+                error: TimeoutError::Connect,
+                ..
+            })
+        );
+        assert_eq!(info.attempts[first].latency, Duration::from_millis(1));
+        // The speculative execution lost the race and was not waited for
+        assert_matches!(
+            info.attempts[speculative].outcome,
+            AttemptOutcome::Unfinished
+        );
+        assert_eq!(
+            info.get_winning_attempt()
+                .map(|attempt| attempt.node.port()),
+            Some(3)
+        );
+        assert!(!info.is_won_by_speculative_execution());
+
+        let ignored_error = ExecutionInfoRecorder::default();
+        let attempt = ignored_error.record_attempt_start(node(1), None, false);
+        ignored_error.record_attempt_end(
+            attempt,
+            Duration::from_millis(1),
+            Err(&TimeoutError::Connect.into()),
+        );
+        let info = ignored_error.into_execution_info();
+        assert!(info.get_winning_attempt().is_none());
+        assert!(!info.is_won_by_speculative_execution());
+    }
+
+    #[test]
+    fn collector_keeps_the_last_info() {
+        let collector = ExecutionInfoCollector::new();
+        assert!(collector.take_last().is_none());
+
+        for retries in 0..2 {
+            let recorder = ExecutionInfoRecorder::default();
+            for _ in 0..retries {
+                recorder.record_retry();
+            }
+            collector.clone().set_last(recorder.into_execution_info());
+        }
+        assert_eq!(collector.take_last().unwrap().retries, 1);
+        assert!(collector.take_last().is_none());
+    }
+}
@@ -0,0 +1,275 @@
+//! Recording the frames exchanged with the cluster.
+//!
+//! When a [`FrameCapture`] is set with
+//! [`SessionBuilder::frame_capture`](crate::transport::session_builder::GenericSessionBuilder::frame_capture),
+//! every connection of the session records the raw request and response frames
+//! it sends and receives, together with their timestamps and the address of the node.
+//! The capture can be read offline with [`CaptureReader`], which decodes the frames
+//! into typed requests and responses.
+//!
+//! Captures contain all the data sent to and received from the cluster,
+//! including credentials and query results, so they should be handled with care.
+//!
+//! # Example
+//! ```rust
+//! # use scylla::{Session, SessionBuilder};
+//! # use std::error::Error;
+//! # async fn check_only_compiles() -> Result<(), Box<dyn Error>> {
+//! use scylla::transport::frame_capture::{CaptureReader, FrameCapture};
+//! use std::sync::Arc;
+//!
+//! let capture = Arc::new(FrameCapture::create("frames.cqlcap")?);
+//! let session: Session = SessionBuilder::new()
+//!     .known_node("127.0.0.1:9042")
+//!     .frame_capture(capture.clone())
+//!     .build()
+//!     .await?;
+//! session.query("SELECT * FROM ks.t", &[]).await?;
+//! capture.flush()?;
+//!
+//! for frame in CaptureReader::new(std::fs::File::open("frames.cqlcap")?)? {
+//!     let frame = frame?;
+//!     println!("{:?} stream {}", frame.direction, frame.params.stream);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc as std_mpsc, Arc};
+use std::time::SystemTime;
+
+use bytes::Bytes;
+use scylla_cql::frame::capture::CaptureWriter;
+use scylla_cql::frame::response::ResponseOpcode;
+use scylla_cql::frame::{Compression, FrameParams};
+use tokio::sync::mpsc;
+use tracing::warn;
+
+pub use scylla_cql::frame::capture::{CaptureError, CaptureReader, CapturedFrame, FrameDirection};
+
+/// Records the frames of all connections of a session.
+///
+/// The tasks serving the connections only pass the frames over a channel to a dedicated
+/// thread, which writes them, so a slow writer doesn't block them. The channel is unbounded:
+/// frames which the writer can't keep up with are buffered in memory. If writing fails,
+/// the capture stops and a warning is logged.
+pub struct FrameCapture {
+    sender: mpsc::UnboundedSender<CaptureMessage>,
+    failed: Arc<AtomicBool>,
+}
+
+enum CaptureMessage {
+    Request {
+        timestamp: SystemTime,
+        address: SocketAddr,
+        compression: Option<Compression>,
+        frame: Bytes,
+    },
+    Response {
+        timestamp: SystemTime,
+        address: SocketAddr,
+        compression: Option<Compression>,
+        params: FrameParams,
+        opcode: ResponseOpcode,
+        body: Bytes,
+    },
+    Flush(std_mpsc::SyncSender<io::Result<()>>),
+}
+
+impl FrameCapture {
+    /// Starts a capture, which is written to the given writer by a new thread.
+    /// The thread exits once the capture is dropped.
+    pub fn new(writer: impl Write + Send + 'static) -> io::Result<Self> {
+        let writer = CaptureWriter::new(writer)?;
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let failed = Arc::new(AtomicBool::new(false));
+        let writer_failed = failed.clone();
+        std::thread::Builder::new()
+            .name("scylla-frame-capture".to_owned())
+            .spawn(move || write_frames(writer, receiver, &writer_failed))?;
+        Ok(Self { sender, failed })
+    }
+
+    /// Starts a capture, which is written to a newly created file at the given path.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+
+    /// Waits until the frames recorded so far are written, and flushes the underlying writer.
+    pub fn flush(&self) -> io::Result<()> {
+        let stopped = || io::Error::new(io::ErrorKind::Other, "The frame capture has stopped");
+        let (result_sender, result_receiver) = std_mpsc::sync_channel(1);
+        self.sender
+            .send(CaptureMessage::Flush(result_sender))
+            .map_err(|_| stopped())?;
+        result_receiver.recv().map_err(|_| stopped())?
+    }
+
+    fn record(&self, message: CaptureMessage) {
+        if self.failed.load(Ordering::Relaxed) {
+            return;
+        }
+        // The writer thread exits only when it fails, which is checked above
+        let _ = self.sender.send(message);
+    }
+}
+
+// Runs on the writer thread until all senders are dropped.
+fn write_frames(
+    mut writer: CaptureWriter<impl Write>,
+    mut receiver: mpsc::UnboundedReceiver<CaptureMessage>,
+    failed: &AtomicBool,
+) {
+    while let Some(message) = receiver.blocking_recv() {
+        let result = match message {
+            CaptureMessage::Request {
+                timestamp,
+                address,
+                compression,
+                frame,
+            } => writer.write_request(timestamp, address, compression, &frame),
+            CaptureMessage::Response {
+                timestamp,
+                address,
+                compression,
+                params,
+                opcode,
+                body,
+            } => writer.write_response(timestamp, address, compression, params, opcode, &body),
+            CaptureMessage::Flush(result_sender) => {
+                let _ = result_sender.send(writer.flush());
+                continue;
+            }
+        };
+        if let Err(err) = result {
+            failed.store(true, Ordering::Relaxed);
+            warn!(error = %err, "Failed to record a frame, stopping the frame capture");
+            return;
+        }
+    }
+    let _ = writer.flush();
+}
+
+/// The capture of a single connection.
+pub(crate) struct ConnectionCapture {
+    pub(crate) capture: Arc<FrameCapture>,
+    pub(crate) address: SocketAddr,
+    pub(crate) compression: Option<Compression>,
+}
+
+impl ConnectionCapture {
+    pub(crate) fn record_request(&self, frame: &[u8]) {
+        self.capture.record(CaptureMessage::Request {
+            timestamp: SystemTime::now(),
+            address: self.address,
+            compression: self.compression,
+            // The buffer of the request is reused once it is sent
+            frame: Bytes::copy_from_slice(frame),
+        })
+    }
+
+    pub(crate) fn record_response(&self, params: FrameParams, opcode: ResponseOpcode, body: Bytes) {
+        self.capture.record(CaptureMessage::Response {
+            timestamp: SystemTime::now(),
+            address: self.address,
+            compression: self.compression,
+            params,
+            opcode,
+            body,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use std::sync::atomic::Ordering;
+    use std::sync::{Arc, Mutex};
+
+    use bytes::Bytes;
+    use scylla_cql::frame::response::ResponseOpcode;
+    use scylla_cql::frame::FrameParams;
+
+    use super::{CaptureReader, ConnectionCapture, FrameCapture, FrameDirection};
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn recorded_frames_can_be_read() {
+        let buffer = SharedBuffer::default();
+        let capture = ConnectionCapture {
+            capture: Arc::new(FrameCapture::new(buffer.clone()).unwrap()),
+            address: "127.0.0.1:9042".parse().unwrap(),
+            compression: None,
+        };
+
+        capture.record_response(
+            FrameParams {
+                version: 0x84,
+                flags: 0,
+                stream: 3,
+            },
+            ResponseOpcode::Ready,
+            Bytes::new(),
+        );
+        capture.capture.flush().unwrap();
+
+        let data = buffer.0.lock().unwrap().clone();
+        let frames = CaptureReader::new(&data[..])
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].direction, FrameDirection::Response);
+        assert_eq!(frames[0].address, capture.address);
+        assert_eq!(frames[0].params.stream, 3);
+    }
+
+    // Accepts the given number of bytes, and fails to write any more
+    struct FailingWriter(usize);
+
+    impl Write for FailingWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.0 < buf.len() {
+                return Err(io::Error::new(io::ErrorKind::Other, "disk full"));
+            }
+            self.0 -= buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn capture_stops_when_writing_fails() {
+        // Enough for the header of the capture only
+        let capture = ConnectionCapture {
+            capture: Arc::new(FrameCapture::new(FailingWriter(7)).unwrap()),
+            address: "127.0.0.1:9042".parse().unwrap(),
+            compression: None,
+        };
+        capture.capture.flush().unwrap();
+
+        capture.record_request(&[0x04, 0, 0, 1, 0x05, 0, 0, 0, 0]);
+        assert!(capture.capture.flush().is_err());
+        assert!(capture.capture.failed.load(Ordering::Relaxed));
+    }
+}
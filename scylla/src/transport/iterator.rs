@@ -10,13 +10,18 @@ use std::task::{Context, Poll};
 
 use bytes::Bytes;
 use futures::Stream;
+use scylla_cql::frame::frame_errors::ParseError;
 use scylla_cql::frame::response::NonErrorResponse;
 use scylla_cql::frame::types::SerialConsistency;
+use scylla_cql::types::deserialize::row::DeserializeRow;
 use scylla_cql::types::serialize::row::SerializedValues;
 use std::result::Result;
 use thiserror::Error;
 use tokio::sync::mpsc;
 
+#[cfg(feature = "arrow-50")]
+use super::arrow::{RecordBatchError, RecordBatchStream};
+use super::cancellation::{self, CancelHandle};
 use super::errors::QueryError;
 use super::execution_profile::ExecutionProfileInner;
 use super::session::RequestSpan;
@@ -24,16 +29,21 @@ use crate::cql_to_rust::{FromRow, FromRowError};
 
 use crate::frame::response::{
     result,
-    result::{ColumnSpec, Row, Rows},
+    result::{ColumnSpec, RawRows, Row},
 };
 use crate::history::{self, HistoryListener};
-use crate::statement::Consistency;
+use crate::routing::Shard;
 use crate::statement::{prepared_statement::PreparedStatement, query::Query};
+use crate::statement::{Consistency, Priority};
 use crate::transport::cluster::ClusterData;
-use crate::transport::connection::{Connection, NonErrorQueryResponse, QueryResponse};
+use crate::transport::connection::{Connection, NonErrorQueryResponse, QueryResponse, RowsFormat};
 use crate::transport::load_balancing::{self, RoutingInfo};
+use crate::transport::load_shedding::LoadSheddingPolicy;
 use crate::transport::metrics::Metrics;
+use crate::transport::query_log::{LoggedRequest, QueryLog};
+use crate::transport::query_result::DynamicRow;
 use crate::transport::retry_policy::{QueryInfo, RetryDecision, RetrySession};
+use crate::transport::runtime::Runtime;
 use crate::transport::NodeRef;
 use tracing::{trace, trace_span, warn, Instrument};
 use uuid::Uuid;
@@ -54,18 +64,86 @@ use uuid::Uuid;
 // value at the beginning of `query_iter` and `execute_iter`.
 const DEFAULT_ITER_PAGE_SIZE: i32 = 5000;
 
+// Long scans fetch an unbounded number of pages, so only the information about
+// the most recent ones is kept. The totals cover all of them.
+const RECENT_PAGES_INFO_LIMIT: usize = 100;
+
 /// Iterator over rows returned by paged queries\
 /// Allows to easily access rows without worrying about handling multiple pages
+///
+/// Pages are kept serialized, and each row is deserialized only when it is returned.
 pub struct RowIterator {
-    current_row_idx: usize,
-    current_page: Rows,
+    // Rows of the current page which were not returned yet
+    current_page: RawRows,
     page_receiver: mpsc::Receiver<Result<ReceivedPage, QueryError>>,
     tracing_ids: Vec<Uuid>,
+    pages_info: PagesInfo,
+    // Used to tell when the current page, and so possibly the column specs, changed
+    received_pages_count: usize,
 }
 
 struct ReceivedPage {
-    rows: Rows,
+    rows: RawRows,
     tracing_id: Option<Uuid>,
+    info: Option<PageInfo>,
+}
+
+/// Information about a single page fetched by a [`RowIterator`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PageInfo {
+    /// Address of the node which served the page.
+    pub node: SocketAddr,
+    /// Shard which served the page, if the node is sharded.
+    pub shard: Option<Shard>,
+    /// Number of retries which happened while fetching the page.
+    ///
+    /// Paged queries are never executed speculatively,
+    /// so each retry corresponds to exactly one failed attempt.
+    pub retries: usize,
+    /// Number of rows in the page.
+    pub rows: usize,
+    /// Size of the serialized page, in bytes.
+    pub bytes: usize,
+}
+
+// Totals over all the pages received by a `RowIterator`, together with
+// the information about the most recent ones.
+#[derive(Default)]
+struct PagesInfo {
+    recent: Vec<PageInfo>,
+    pages: usize,
+    retries: usize,
+    rows: usize,
+    bytes: usize,
+}
+
+impl PagesInfo {
+    fn record(&mut self, info: PageInfo) {
+        self.pages += 1;
+        self.retries += info.retries;
+        self.rows += info.rows;
+        self.bytes += info.bytes;
+        if self.recent.len() == RECENT_PAGES_INFO_LIMIT {
+            self.recent.remove(0);
+        }
+        self.recent.push(info);
+    }
+}
+
+impl PageInfo {
+    fn new(connection: &Connection, retries: usize, rows: &RawRows) -> Self {
+        Self {
+            node: connection.get_connect_address(),
+            shard: connection
+                .get_shard_info()
+                .as_ref()
+                .map(|info| info.shard.into()),
+            retries,
+            rows: rows.rows_count,
+            bytes: rows.raw_rows.len(),
+        }
+    }
 }
 
 pub(crate) struct PreparedIteratorConfig {
@@ -74,6 +152,9 @@ pub(crate) struct PreparedIteratorConfig {
     pub(crate) execution_profile: Arc<ExecutionProfileInner>,
     pub(crate) cluster_data: Arc<ClusterData>,
     pub(crate) metrics: Arc<Metrics>,
+    pub(crate) runtime: Arc<dyn Runtime>,
+    pub(crate) query_log: Option<Arc<QueryLog>>,
+    pub(crate) load_shedding_policy: Option<Arc<LoadSheddingPolicy>>,
 }
 
 /// Fetching pages is asynchronous so `RowIterator` does not implement the `Iterator` trait.\
@@ -82,29 +163,19 @@ impl Stream for RowIterator {
     type Item = Result<Row, QueryError>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let mut s = self.as_mut();
+        let s = self.as_mut().get_mut();
 
         if s.is_current_page_exhausted() {
-            match Pin::new(&mut s.page_receiver).poll_recv(cx) {
-                Poll::Ready(Some(Ok(received_page))) => {
-                    s.current_page = received_page.rows;
-                    s.current_row_idx = 0;
-
-                    if let Some(tracing_id) = received_page.tracing_id {
-                        s.tracing_ids.push(tracing_id);
-                    }
-                }
+            match s.poll_next_page(cx) {
+                Poll::Ready(Some(Ok(()))) => {}
                 Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
                 Poll::Ready(None) => return Poll::Ready(None),
                 Poll::Pending => return Poll::Pending,
             }
         }
 
-        let idx = s.current_row_idx;
-        if idx < s.current_page.rows.len() {
-            let row = mem::take(&mut s.current_page.rows[idx]);
-            s.current_row_idx += 1;
-            return Poll::Ready(Some(Ok(row)));
+        if let Some(row) = s.next_row_in_page() {
+            return Poll::Ready(Some(row));
         }
 
         // We probably got a zero-sized page
@@ -123,11 +194,39 @@ impl RowIterator {
         }
     }
 
+    /// Converts this iterator into an iterator over [`DynamicRow`]s, which allow to access
+    /// the values by column name, together with the column specifications
+    pub fn into_dynamic(self) -> DynamicRowIterator {
+        DynamicRowIterator {
+            row_iterator: self,
+            col_specs: None,
+        }
+    }
+
+    /// Converts this iterator into a stream of Arrow record batches with up to `batch_size` rows each.
+    ///
+    /// See the [`arrow`](crate::transport::arrow) module for the mapping of CQL types to Arrow types.
+    /// Fails if a column has a type which can't be converted to Arrow.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `batch_size` is zero.
+    #[cfg(feature = "arrow-50")]
+    pub fn into_record_batches(
+        self,
+        batch_size: usize,
+    ) -> Result<RecordBatchStream, RecordBatchError> {
+        RecordBatchStream::new(self, batch_size)
+    }
+
     pub(crate) async fn new_for_query(
         mut query: Query,
         execution_profile: Arc<ExecutionProfileInner>,
         cluster_data: Arc<ClusterData>,
         metrics: Arc<Metrics>,
+        runtime: Arc<dyn Runtime>,
+        query_log: Option<Arc<QueryLog>>,
+        load_shedding_policy: Option<Arc<LoadSheddingPolicy>>,
     ) -> Result<RowIterator, QueryError> {
         if query.get_page_size().is_none() {
             query.set_page_size(DEFAULT_ITER_PAGE_SIZE);
@@ -143,22 +242,25 @@ impl RowIterator {
             .serial_consistency
             .unwrap_or(execution_profile.serial_consistency);
 
-        let routing_info = RoutingInfo {
-            consistency,
-            serial_consistency,
-            ..Default::default()
-        };
-
         let retry_session = query
             .get_retry_policy()
             .map(|rp| &**rp)
             .unwrap_or(&*execution_profile.retry_policy)
             .new_session();
 
+        let worker_runtime = runtime.clone();
         let parent_span = tracing::Span::current();
         let worker_task = async move {
             let query_ref = &query;
 
+            let routing_info = RoutingInfo {
+                consistency,
+                serial_consistency,
+                token: query_ref.get_routing_token(),
+                table: query_ref.get_routing_table(),
+                ..Default::default()
+            };
+
             let page_query = |connection: Arc<Connection>,
                               consistency: Consistency,
                               paging_state: Option<Bytes>| {
@@ -169,6 +271,8 @@ impl RowIterator {
                             consistency,
                             serial_consistency,
                             paging_state,
+                            None,
+                            RowsFormat::Serialized,
                         )
                         .await
                 }
@@ -182,6 +286,10 @@ impl RowIterator {
                 span
             };
 
+            let logged_request = query_log
+                .as_deref()
+                .map(|log| log.unprepared(&query_ref.contents));
+
             let worker = RowIteratorWorker {
                 sender: sender.into(),
                 page_query,
@@ -191,10 +299,16 @@ impl RowIterator {
                 retry_session,
                 execution_profile,
                 metrics,
+                runtime: worker_runtime,
                 paging_state: None,
+                page_retries: 0,
                 history_listener: query.config.history_listener.clone(),
                 current_query_id: None,
                 current_attempt_id: None,
+                cancel_handle: query.config.cancel_handle.clone(),
+                load_shedding_policy,
+                priority: query.config.priority,
+                logged_request,
                 parent_span,
                 span_creator,
             };
@@ -202,7 +316,7 @@ impl RowIterator {
             worker.work(cluster_data).await
         };
 
-        Self::new_from_worker_future(worker_task, receiver).await
+        Self::new_from_worker_future(worker_task, receiver, &*runtime).await
     }
 
     pub(crate) async fn new_for_prepared_statement(
@@ -230,17 +344,24 @@ impl RowIterator {
             .unwrap_or(&*config.execution_profile.retry_policy)
             .new_session();
 
+        let runtime = config.runtime.clone();
         let parent_span = tracing::Span::current();
         let worker_task = async move {
             let prepared_ref = &config.prepared;
             let values_ref = &config.values;
 
-            let (partition_key, token) = match prepared_ref
-                .extract_partition_key_and_calculate_token(
-                    prepared_ref.get_partitioner_name(),
-                    values_ref,
-                ) {
-                Ok(res) => res.unzip(),
+            let routing_key_token = prepared_ref.calculate_routing_key_token();
+            let partition_key_and_token = match routing_key_token {
+                Some(token) => Ok((None, Some(token))),
+                None => prepared_ref
+                    .extract_partition_key_and_calculate_token(
+                        prepared_ref.get_partitioner_name(),
+                        values_ref,
+                    )
+                    .map(Option::unzip),
+            };
+            let (partition_key, token) = match partition_key_and_token {
+                Ok(res) => res,
                 Err(err) => {
                     let (proof, _res) = ProvingSender::from(sender).send(Err(err)).await;
                     return proof;
@@ -266,11 +387,17 @@ impl RowIterator {
                         consistency,
                         serial_consistency,
                         paging_state,
+                        None,
+                        RowsFormat::Serialized,
                     )
                     .await
             };
 
             let serialized_values_size = config.values.buffer_size();
+            let logged_request = config
+                .query_log
+                .as_deref()
+                .map(|log| log.prepared(prepared_ref, values_ref));
 
             let replicas: Option<smallvec::SmallVec<[_; 8]>> =
                 if let (Some(table_spec), Some(token)) =
@@ -308,10 +435,16 @@ impl RowIterator {
                 retry_session,
                 execution_profile: config.execution_profile,
                 metrics: config.metrics,
+                runtime: config.runtime.clone(),
                 paging_state: None,
+                page_retries: 0,
                 history_listener: config.prepared.config.history_listener.clone(),
                 current_query_id: None,
                 current_attempt_id: None,
+                cancel_handle: config.prepared.config.cancel_handle.clone(),
+                load_shedding_policy: config.load_shedding_policy,
+                priority: config.prepared.config.priority,
+                logged_request,
                 parent_span,
                 span_creator,
             };
@@ -319,7 +452,7 @@ impl RowIterator {
             worker.work(config.cluster_data).await
         };
 
-        Self::new_from_worker_future(worker_task, receiver).await
+        Self::new_from_worker_future(worker_task, receiver, &*runtime).await
     }
 
     pub(crate) async fn new_for_connection_query_iter(
@@ -332,23 +465,27 @@ impl RowIterator {
             query.set_page_size(DEFAULT_ITER_PAGE_SIZE);
         }
         let (sender, receiver) = mpsc::channel::<Result<ReceivedPage, QueryError>>(1);
+        let runtime = connection.get_runtime().clone();
 
         let worker_task = async move {
             let worker = SingleConnectionRowIteratorWorker {
                 sender: sender.into(),
+                connection: connection.clone(),
                 fetcher: |paging_state| {
                     connection.query_with_consistency(
                         &query,
                         consistency,
                         serial_consistency,
                         paging_state,
+                        None,
+                        RowsFormat::Serialized,
                     )
                 },
             };
             worker.work().await
         };
 
-        Self::new_from_worker_future(worker_task, receiver).await
+        Self::new_from_worker_future(worker_task, receiver, &*runtime).await
     }
 
     pub(crate) async fn new_for_connection_execute_iter(
@@ -362,10 +499,12 @@ impl RowIterator {
             prepared.set_page_size(DEFAULT_ITER_PAGE_SIZE);
         }
         let (sender, receiver) = mpsc::channel::<Result<ReceivedPage, QueryError>>(1);
+        let runtime = connection.get_runtime().clone();
 
         let worker_task = async move {
             let worker = SingleConnectionRowIteratorWorker {
                 sender: sender.into(),
+                connection: connection.clone(),
                 fetcher: |paging_state| {
                     connection.execute_with_consistency(
                         &prepared,
@@ -373,30 +512,34 @@ impl RowIterator {
                         consistency,
                         serial_consistency,
                         paging_state,
+                        None,
+                        RowsFormat::Serialized,
                     )
                 },
             };
             worker.work().await
         };
 
-        Self::new_from_worker_future(worker_task, receiver).await
+        Self::new_from_worker_future(worker_task, receiver, &*runtime).await
     }
 
     async fn new_from_worker_future(
         worker_task: impl Future<Output = PageSendAttemptedProof> + Send + 'static,
         mut receiver: mpsc::Receiver<Result<ReceivedPage, QueryError>>,
+        runtime: &dyn Runtime,
     ) -> Result<RowIterator, QueryError> {
-        tokio::task::spawn(worker_task);
+        runtime.spawn(Box::pin(async move {
+            worker_task.await;
+        }));
 
         // This unwrap is safe because:
         // - The future returned by worker.work sends at least one item
         //   to the channel (the PageSendAttemptedProof helps enforce this)
-        // - That future is polled in a tokio::task which isn't going to be
+        // - That future is polled in a task which isn't going to be
         //   cancelled
         let pages_received = receiver.recv().await.unwrap()?;
 
         Ok(RowIterator {
-            current_row_idx: 0,
             current_page: pages_received.rows,
             page_receiver: receiver,
             tracing_ids: if let Some(tracing_id) = pages_received.tracing_id {
@@ -404,6 +547,14 @@ impl RowIterator {
             } else {
                 Vec::new()
             },
+            pages_info: {
+                let mut pages_info = PagesInfo::default();
+                if let Some(info) = pages_received.info {
+                    pages_info.record(info);
+                }
+                pages_info
+            },
+            received_pages_count: 1,
         })
     }
 
@@ -412,20 +563,95 @@ impl RowIterator {
         &self.tracing_ids
     }
 
+    /// Returns information about the most recently received pages (at most 100 of them),
+    /// in the order they were fetched: which node and shard served each of them,
+    /// and how many retries it took.
+    pub fn get_pages_info(&self) -> &[PageInfo] {
+        &self.pages_info.recent
+    }
+
+    /// Returns the number of pages received so far.
+    pub fn get_fetched_pages_count(&self) -> usize {
+        self.pages_info.pages
+    }
+
+    /// Returns the total number of retries which happened while fetching the pages received so far.
+    pub fn get_retries_count(&self) -> usize {
+        self.pages_info.retries
+    }
+
+    /// Returns the total number of rows in the pages received so far.
+    pub fn get_fetched_rows_count(&self) -> usize {
+        self.pages_info.rows
+    }
+
+    /// Returns the total size of the pages received so far, in bytes.
+    pub fn get_fetched_bytes(&self) -> usize {
+        self.pages_info.bytes
+    }
+
     /// Returns specification of row columns
     pub fn get_column_specs(&self) -> &[ColumnSpec] {
         &self.current_page.metadata.col_specs
     }
 
     fn is_current_page_exhausted(&self) -> bool {
-        self.current_row_idx >= self.current_page.rows.len()
+        self.current_page.rows_count == 0
+    }
+
+    // Replaces the current page with the next one
+    pub(crate) fn poll_next_page(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<(), QueryError>>> {
+        let received_page = match std::task::ready!(self.page_receiver.poll_recv(cx)) {
+            Some(Ok(received_page)) => received_page,
+            Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+            None => return Poll::Ready(None),
+        };
+        self.current_page = received_page.rows;
+        self.received_pages_count += 1;
+
+        if let Some(tracing_id) = received_page.tracing_id {
+            self.tracing_ids.push(tracing_id);
+        }
+        if let Some(info) = received_page.info {
+            self.pages_info.record(info);
+        }
+        Poll::Ready(Some(Ok(())))
+    }
+
+    // Rows of the current page which were not returned yet, still serialized.
+    // Rows consumed from it by the caller must be removed from it.
+    #[cfg(feature = "arrow-50")]
+    pub(crate) fn current_page_mut(&mut self) -> &mut RawRows {
+        &mut self.current_page
+    }
+
+    fn next_row_in_page(&mut self) -> Option<Result<Row, QueryError>> {
+        let page = &mut self.current_page;
+        let mut rows = page.rows_iter();
+        let row = rows.next()?.and_then(Row::deserialize);
+        let remaining = (rows.rows_remaining(), rows.remaining_slice().to_bytes());
+        match row {
+            Ok(row) => {
+                (page.rows_count, page.raw_rows) = remaining;
+                Some(Ok(row))
+            }
+            Err(err) => {
+                // The rest of the page can't be trusted to be aligned to rows
+                page.rows_count = 0;
+                Some(Err(ParseError::from(err).into()))
+            }
+        }
     }
 }
 
 // A separate module is used here so that the parent module cannot construct
 // SendAttemptedProof directly.
 mod checked_channel_sender {
-    use scylla_cql::{errors::QueryError, frame::response::result::Rows};
+    use scylla_cql::{errors::QueryError, frame::response::result::RawRows};
+    use std::future::Future;
     use std::marker::PhantomData;
     use tokio::sync::mpsc;
     use uuid::Uuid;
@@ -446,6 +672,14 @@ mod checked_channel_sender {
         }
     }
 
+    impl<T: Send + 'static> ProvingSender<T> {
+        /// Returns a future which completes when the receiver is dropped.
+        pub(crate) fn receiver_dropped(&self) -> impl Future<Output = ()> + Send + 'static {
+            let sender = self.0.clone();
+            async move { sender.closed().await }
+        }
+    }
+
     impl<T> ProvingSender<T> {
         pub(crate) async fn send(
             &self,
@@ -466,13 +700,9 @@ mod checked_channel_sender {
             Result<(), mpsc::error::SendError<ResultPage>>,
         ) {
             let empty_page = ReceivedPage {
-                rows: Rows {
-                    metadata: Default::default(),
-                    rows_count: 0,
-                    rows: Vec::new(),
-                    serialized_size: 0,
-                },
+                rows: RawRows::default(),
                 tracing_id,
+                info: None,
             };
             self.send(Ok(empty_page)).await
         }
@@ -498,12 +728,19 @@ struct RowIteratorWorker<'a, QueryFunc, SpanCreatorFunc> {
     retry_session: Box<dyn RetrySession>,
     execution_profile: Arc<ExecutionProfileInner>,
     metrics: Arc<Metrics>,
+    runtime: Arc<dyn Runtime>,
 
     paging_state: Option<Bytes>,
+    // Retries since the last page was received
+    page_retries: usize,
 
     history_listener: Option<Arc<dyn HistoryListener>>,
     current_query_id: Option<history::QueryId>,
     current_attempt_id: Option<history::AttemptId>,
+    cancel_handle: Option<CancelHandle>,
+    load_shedding_policy: Option<Arc<LoadSheddingPolicy>>,
+    priority: Priority,
+    logged_request: Option<LoggedRequest<'a>>,
 
     parent_span: tracing::Span,
     span_creator: SpanCreatorFunc,
@@ -517,6 +754,31 @@ where
 {
     // Contract: this function MUST send at least one item through self.sender
     async fn work(mut self, cluster_data: Arc<ClusterData>) -> PageSendAttemptedProof {
+        // Stop fetching pages, together with any retries, if the RowIterator
+        // is dropped or the request is cancelled.
+        let receiver_dropped = self.sender.receiver_dropped();
+        let cancel_handle = self.cancel_handle.clone();
+        let proof = tokio::select! {
+            biased;
+            _ = receiver_dropped => None,
+            _ = cancellation::cancelled(cancel_handle.as_ref()) => None,
+            proof = self.fetch_pages(cluster_data) => Some(proof),
+        };
+
+        match proof {
+            Some(proof) => proof,
+            None => {
+                self.metrics.inc_cancelled_requests();
+                let error = QueryError::RequestCancelled;
+                self.log_query_error(&error);
+                let (proof, _) = self.sender.send(Err(error)).await;
+                proof
+            }
+        }
+    }
+
+    // Contract: this function MUST send at least one item through self.sender
+    async fn fetch_pages(&mut self, cluster_data: Arc<ClusterData>) -> PageSendAttemptedProof {
         let load_balancer = self.execution_profile.load_balancing_policy.clone();
         let statement_info = self.statement_info.clone();
         let query_plan =
@@ -545,7 +807,7 @@ where
                         error = %e,
                         "Choosing connection failed"
                     );
-                    last_error = e;
+                    last_error = e.with_node(node.address.into_inner());
                     // Broken connection doesn't count as a failed query, don't log in metrics
                     continue 'nodes_in_plan;
                 }
@@ -593,12 +855,16 @@ where
                 match retry_decision {
                     RetryDecision::RetrySameNode(cl) => {
                         self.metrics.inc_retries_num();
+                        self.page_retries += 1;
                         current_consistency = cl.unwrap_or(current_consistency);
+                        self.wait_before_retry().await;
                         continue 'same_node_retries;
                     }
                     RetryDecision::RetryNextNode(cl) => {
                         self.metrics.inc_retries_num();
+                        self.page_retries += 1;
                         current_consistency = cl.unwrap_or(current_consistency);
+                        self.wait_before_retry().await;
                         continue 'nodes_in_plan;
                     }
                     RetryDecision::DontRetry => break 'nodes_in_plan,
@@ -647,6 +913,13 @@ where
         }
     }
 
+    async fn wait_before_retry(&self) {
+        let delay = self.retry_session.retry_delay();
+        if !delay.is_zero() {
+            self.runtime.sleep(delay).await;
+        }
+    }
+
     async fn query_one_page(
         &mut self,
         connection: &Arc<Connection>,
@@ -654,6 +927,23 @@ where
         node: NodeRef<'_>,
         request_span: &RequestSpan,
     ) -> Result<ControlFlow<PageSendAttemptedProof, ()>, QueryError> {
+        // Each page is admitted separately, and the permit is released before the page
+        // is handed over to the iterator, which may not be read for a long time
+        let load_shedding_policy = self.load_shedding_policy.clone();
+        let in_flight_permit = match &load_shedding_policy {
+            Some(policy) => match policy.acquire(self.priority, &*self.runtime).await {
+                Ok(permit) => Some(permit),
+                Err(err) => {
+                    // Shedding doesn't depend on the node, so the retry policy isn't consulted
+                    self.metrics.inc_shed_requests();
+                    self.log_query_error(&err);
+                    let (proof, _) = self.sender.send(Err(err)).await;
+                    return Ok(ControlFlow::Break(proof));
+                }
+            },
+            None => None,
+        };
+
         self.metrics.inc_total_paged_queries();
         let query_start = std::time::Instant::now();
 
@@ -666,15 +956,31 @@ where
         let query_response =
             (self.page_query)(connection.clone(), consistency, self.paging_state.clone())
                 .await
-                .and_then(QueryResponse::into_non_error_query_response);
+                .and_then(QueryResponse::into_non_error_query_response)
+                .map_err(|error| error.with_node(connection.get_connect_address()));
 
         let elapsed = query_start.elapsed();
+        drop(in_flight_permit);
+
+        if let Some(table) = self.statement_info.table {
+            self.metrics
+                .log_table_request(table, elapsed, query_response.as_ref().err());
+        }
+
+        if let Some(logged_request) = &self.logged_request {
+            logged_request.log_attempt(
+                consistency,
+                connection.get_connect_address(),
+                elapsed,
+                query_response.as_ref().map(|_| ()),
+            );
+        }
 
         request_span.record_shard_id(connection);
 
         match query_response {
             Ok(NonErrorQueryResponse {
-                response: NonErrorResponse::Result(result::Result::Rows(mut rows)),
+                response: NonErrorResponse::Result(result::Result::RawRows(mut rows)),
                 tracing_id,
                 ..
             }) => {
@@ -684,12 +990,18 @@ where
                 self.execution_profile
                     .load_balancing_policy
                     .on_query_success(&self.statement_info, elapsed, node);
+                node.record_successful_request();
 
                 self.paging_state = rows.metadata.paging_state.take();
 
                 request_span.record_rows_fields(&rows);
 
-                let received_page = ReceivedPage { rows, tracing_id };
+                let info = PageInfo::new(connection, mem::take(&mut self.page_retries), &rows);
+                let received_page = ReceivedPage {
+                    rows,
+                    tracing_id,
+                    info: Some(info),
+                };
 
                 // Send next page to RowIterator
                 let (proof, res) = self.sender.send(Ok(received_page)).await;
@@ -724,6 +1036,8 @@ where
                 // We have most probably sent a modification statement (e.g. INSERT or UPDATE),
                 // so let's return an empty iterator as suggested in #631.
 
+                node.record_successful_request();
+
                 // We must attempt to send something because the iterator expects it.
                 let (proof, _) = self.sender.send_empty_page(tracing_id).await;
                 Ok(ControlFlow::Break(proof))
@@ -825,6 +1139,7 @@ where
 /// a single connection.
 struct SingleConnectionRowIteratorWorker<Fetcher> {
     sender: ProvingSender<Result<ReceivedPage, QueryError>>,
+    connection: Arc<Connection>,
     fetcher: Fetcher,
 }
 
@@ -849,13 +1164,15 @@ where
             let result = (self.fetcher)(paging_state).await?;
             let response = result.into_non_error_query_response()?;
             match response.response {
-                NonErrorResponse::Result(result::Result::Rows(mut rows)) => {
+                NonErrorResponse::Result(result::Result::RawRows(mut rows)) => {
                     paging_state = rows.metadata.paging_state.take();
+                    let info = PageInfo::new(&self.connection, 0, &rows);
                     let (proof, send_result) = self
                         .sender
                         .send(Ok(ReceivedPage {
                             rows,
                             tracing_id: response.tracing_id,
+                            info: Some(info),
                         }))
                         .await;
                     if paging_state.is_none() || send_result.is_err() {
@@ -894,6 +1211,31 @@ impl<RowT> TypedRowIterator<RowT> {
         self.row_iterator.get_tracing_ids()
     }
 
+    /// Returns information about the most recently received pages, see [`RowIterator::get_pages_info`].
+    pub fn get_pages_info(&self) -> &[PageInfo] {
+        self.row_iterator.get_pages_info()
+    }
+
+    /// Returns the number of pages received so far.
+    pub fn get_fetched_pages_count(&self) -> usize {
+        self.row_iterator.get_fetched_pages_count()
+    }
+
+    /// Returns the total number of retries which happened while fetching the pages received so far.
+    pub fn get_retries_count(&self) -> usize {
+        self.row_iterator.get_retries_count()
+    }
+
+    /// Returns the total number of rows in the pages received so far.
+    pub fn get_fetched_rows_count(&self) -> usize {
+        self.row_iterator.get_fetched_rows_count()
+    }
+
+    /// Returns the total size of the pages received so far, in bytes.
+    pub fn get_fetched_bytes(&self) -> usize {
+        self.row_iterator.get_fetched_bytes()
+    }
+
     /// Returns specification of row columns
     pub fn get_column_specs(&self) -> &[ColumnSpec] {
         self.row_iterator.get_column_specs()
@@ -938,3 +1280,156 @@ impl<RowT: FromRow> Stream for TypedRowIterator<RowT> {
 
 // TypedRowIterator can be moved freely for any RowT so it's Unpin
 impl<RowT> Unpin for TypedRowIterator<RowT> {}
+
+/// Iterator over rows returned by paged queries
+/// where each row is a [`DynamicRow`]\
+/// Returned by `RowIterator::into_dynamic`
+pub struct DynamicRowIterator {
+    row_iterator: RowIterator,
+    // Column specs of the current page, shared by its rows, with the page number
+    col_specs: Option<(usize, Arc<[ColumnSpec]>)>,
+}
+
+impl DynamicRowIterator {
+    /// If tracing was enabled returns tracing ids of all finished page queries
+    pub fn get_tracing_ids(&self) -> &[Uuid] {
+        self.row_iterator.get_tracing_ids()
+    }
+
+    /// Returns information about the most recently received pages, see [`RowIterator::get_pages_info`].
+    pub fn get_pages_info(&self) -> &[PageInfo] {
+        self.row_iterator.get_pages_info()
+    }
+
+    /// Returns the number of pages received so far.
+    pub fn get_fetched_pages_count(&self) -> usize {
+        self.row_iterator.get_fetched_pages_count()
+    }
+
+    /// Returns the total number of retries which happened while fetching the pages received so far.
+    pub fn get_retries_count(&self) -> usize {
+        self.row_iterator.get_retries_count()
+    }
+
+    /// Returns the total number of rows in the pages received so far.
+    pub fn get_fetched_rows_count(&self) -> usize {
+        self.row_iterator.get_fetched_rows_count()
+    }
+
+    /// Returns the total size of the pages received so far, in bytes.
+    pub fn get_fetched_bytes(&self) -> usize {
+        self.row_iterator.get_fetched_bytes()
+    }
+
+    /// Returns specification of row columns
+    pub fn get_column_specs(&self) -> &[ColumnSpec] {
+        self.row_iterator.get_column_specs()
+    }
+
+    fn current_col_specs(&mut self) -> Arc<[ColumnSpec]> {
+        let page = self.row_iterator.received_pages_count;
+        match &self.col_specs {
+            Some((specs_page, col_specs)) if *specs_page == page => col_specs.clone(),
+            _ => {
+                let col_specs: Arc<[ColumnSpec]> = self.row_iterator.get_column_specs().into();
+                self.col_specs = Some((page, col_specs.clone()));
+                col_specs
+            }
+        }
+    }
+}
+
+/// Fetching pages is asynchronous so `DynamicRowIterator` does not implement the `Iterator` trait.\
+/// Instead it uses the asynchronous `Stream` trait
+impl Stream for DynamicRowIterator {
+    type Item = Result<DynamicRow, QueryError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let s = self.as_mut().get_mut();
+
+        let next_ready: Option<Self::Item> = match Pin::new(&mut s.row_iterator).poll_next(cx) {
+            Poll::Ready(next_elem) => next_elem,
+            Poll::Pending => return Poll::Pending,
+        }
+        .map(|next_row| next_row.map(|row| DynamicRow::new(s.current_col_specs(), row)));
+
+        Poll::Ready(next_ready)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, SocketAddr};
+
+    use bytes::Bytes;
+    use futures::StreamExt;
+    use scylla_cql::frame::response::result::{ColumnType, CqlValue, RawRows, TableSpec};
+    use scylla_cql::types::serialize::value::SerializeValue;
+    use scylla_cql::types::serialize::writers::CellWriter;
+    use tokio::sync::mpsc;
+
+    use super::{PageInfo, PagesInfo, RowIterator, RECENT_PAGES_INFO_LIMIT};
+    use crate::frame::response::result::ColumnSpec;
+
+    #[test]
+    fn pages_info_is_bounded() {
+        let mut pages_info = PagesInfo::default();
+        for i in 0..RECENT_PAGES_INFO_LIMIT + 10 {
+            pages_info.record(PageInfo {
+                node: SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 9042),
+                shard: None,
+                retries: 1,
+                rows: i,
+                bytes: 2,
+            });
+        }
+        let total = RECENT_PAGES_INFO_LIMIT + 10;
+        assert_eq!(pages_info.recent.len(), RECENT_PAGES_INFO_LIMIT);
+        assert_eq!(pages_info.recent[0].rows, 10);
+        assert_eq!(pages_info.recent.last().unwrap().rows, total - 1);
+        assert_eq!(pages_info.pages, total);
+        assert_eq!(pages_info.retries, total);
+        assert_eq!(pages_info.rows, (0..total).sum::<usize>());
+        assert_eq!(pages_info.bytes, 2 * total);
+    }
+
+    #[tokio::test]
+    async fn rows_are_deserialized_from_serialized_pages() {
+        let mut page = RawRows::default();
+        page.metadata.col_specs = ["a", "b"]
+            .into_iter()
+            .map(|name| ColumnSpec {
+                table_spec: TableSpec::borrowed("ks", "t"),
+                name: name.to_owned(),
+                typ: ColumnType::Int,
+            })
+            .collect();
+        let mut raw_rows = Vec::new();
+        for value in [Some(1), None, Some(3), Some(4)] {
+            let value = value.map(CqlValue::Int);
+            value
+                .serialize(&ColumnType::Int, CellWriter::new(&mut raw_rows))
+                .unwrap();
+        }
+        page.rows_count = 2;
+        page.raw_rows = Bytes::from(raw_rows);
+
+        let (sender, receiver) = mpsc::channel(1);
+        drop(sender);
+        let rows = RowIterator {
+            current_page: page,
+            page_receiver: receiver,
+            tracing_ids: Vec::new(),
+            pages_info: PagesInfo::default(),
+            received_pages_count: 1,
+        };
+        let rows: Vec<_> = rows.map(|row| row.unwrap().columns).collect().await;
+        assert_eq!(
+            rows,
+            vec![
+                vec![Some(CqlValue::Int(1)), None],
+                vec![Some(CqlValue::Int(3)), Some(CqlValue::Int(4))],
+            ]
+        );
+    }
+}
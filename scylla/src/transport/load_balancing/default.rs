@@ -93,6 +93,8 @@ pub struct DefaultPolicy {
     pick_predicate: Box<dyn Fn(NodeRef<'_>, Option<Shard>) -> bool + Send + Sync>,
     latency_awareness: Option<LatencyAwareness>,
     fixed_seed: Option<u64>,
+    // If true, also the node that round-robin over non-replicas starts at is derived from `fixed_seed`.
+    deterministic_rotation: bool,
 }
 
 impl fmt::Debug for DefaultPolicy {
@@ -103,6 +105,7 @@ impl fmt::Debug for DefaultPolicy {
             .field("permit_dc_failover", &self.permit_dc_failover)
             .field("latency_awareness", &self.latency_awareness)
             .field("fixed_shuffle_seed", &self.fixed_seed)
+            .field("deterministic_rotation", &self.deterministic_rotation)
             .finish_non_exhaustive()
     }
 }
@@ -688,11 +691,20 @@ impl DefaultPolicy {
         }
     }
 
-    fn randomly_rotated_nodes(nodes: &[Arc<Node>]) -> impl Iterator<Item = NodeRef<'_>> {
+    fn randomly_rotated_nodes<'a>(
+        &self,
+        nodes: &'a [Arc<Node>],
+    ) -> impl Iterator<Item = NodeRef<'a>> {
         // Create a randomly rotated slice view
         let nodes_len = nodes.len();
         if nodes_len > 0 {
-            let index = rand::thread_rng().gen_range(0..nodes_len); // gen_range() panics when range is empty!
+            // gen_range() panics when range is empty!
+            let index = match self.fixed_seed {
+                Some(fixed) if self.deterministic_rotation => {
+                    Pcg32::new(fixed, 0).gen_range(0..nodes_len)
+                }
+                _ => rand::thread_rng().gen_range(0..nodes_len),
+            };
             Either::Left(
                 nodes[index..]
                     .iter()
@@ -710,7 +722,8 @@ impl DefaultPolicy {
         predicate: impl Fn(NodeRef<'a>) -> bool,
     ) -> Option<NodeRef<'_>> {
         // Select the first node that matches the predicate
-        Self::randomly_rotated_nodes(nodes).find(|&node| predicate(node))
+        self.randomly_rotated_nodes(nodes)
+            .find(|&node| predicate(node))
     }
 
     fn round_robin_nodes<'a>(
@@ -718,7 +731,8 @@ impl DefaultPolicy {
         nodes: &'a [Arc<Node>],
         predicate: impl Fn(NodeRef<'a>) -> bool,
     ) -> impl Iterator<Item = NodeRef<'_>> {
-        Self::randomly_rotated_nodes(nodes).filter(move |node| predicate(node))
+        self.randomly_rotated_nodes(nodes)
+            .filter(move |node| predicate(node))
     }
 
     fn shuffle<'a>(
@@ -743,6 +757,12 @@ impl DefaultPolicy {
         node.is_enabled()
     }
 
+    // Nodes suspected to be down are not picked, but remain in the fallback plan,
+    // so that the query is still sent to them if no other node is available.
+    fn is_pickable(node: NodeRef, shard: Option<Shard>) -> bool {
+        Self::is_alive(node, shard) && !node.is_down_suspected()
+    }
+
     fn is_datacenter_failover_possible(&self, routing_info: &ProcessedRoutingInfo) -> bool {
         self.preferences.datacenter().is_some()
             && self.permit_dc_failover
@@ -756,9 +776,10 @@ impl Default for DefaultPolicy {
             preferences: NodeLocationPreference::Any,
             is_token_aware: true,
             permit_dc_failover: false,
-            pick_predicate: Box::new(Self::is_alive),
+            pick_predicate: Box::new(Self::is_pickable),
             latency_awareness: None,
             fixed_seed: None,
+            deterministic_rotation: false,
         }
     }
 }
@@ -784,6 +805,7 @@ pub struct DefaultPolicyBuilder {
     permit_dc_failover: bool,
     latency_awareness: Option<LatencyAwarenessBuilder>,
     enable_replica_shuffle: bool,
+    fixed_seed: Option<u64>,
 }
 
 impl DefaultPolicyBuilder {
@@ -795,6 +817,7 @@ impl DefaultPolicyBuilder {
             permit_dc_failover: false,
             latency_awareness: None,
             enable_replica_shuffle: true,
+            fixed_seed: None,
         }
     }
 
@@ -804,11 +827,11 @@ impl DefaultPolicyBuilder {
         let pick_predicate = if let Some(ref latency_awareness) = latency_awareness {
             let latency_predicate = latency_awareness.generate_predicate();
             Box::new(move |node: NodeRef<'_>, shard| {
-                DefaultPolicy::is_alive(node, shard) && latency_predicate(node)
+                DefaultPolicy::is_pickable(node, shard) && latency_predicate(node)
             })
                 as Box<dyn Fn(NodeRef<'_>, Option<Shard>) -> bool + Send + Sync + 'static>
         } else {
-            Box::new(DefaultPolicy::is_alive)
+            Box::new(DefaultPolicy::is_pickable)
         };
 
         Arc::new(DefaultPolicy {
@@ -817,11 +840,14 @@ impl DefaultPolicyBuilder {
             permit_dc_failover: self.permit_dc_failover,
             pick_predicate,
             latency_awareness,
-            fixed_seed: (!self.enable_replica_shuffle).then(|| {
-                let seed = rand::random();
-                debug!("DefaultPolicy: setting fixed seed to {}", seed);
-                seed
+            fixed_seed: self.fixed_seed.or_else(|| {
+                (!self.enable_replica_shuffle).then(|| {
+                    let seed = rand::random();
+                    debug!("DefaultPolicy: setting fixed seed to {}", seed);
+                    seed
+                })
             }),
+            deterministic_rotation: self.fixed_seed.is_some(),
         })
     }
 
@@ -945,6 +971,22 @@ impl DefaultPolicyBuilder {
         self.enable_replica_shuffle = enable;
         self
     }
+
+    /// Makes query plans of this policy deterministic, which is useful e.g. in tests
+    /// asserting which node receives a request.
+    ///
+    /// Replicas are not shuffled for each query, and all choices which are otherwise
+    /// random - the order of the replicas and the node at which the round-robin
+    /// over the other nodes starts - are derived from the given seed. Therefore,
+    /// two policies built with the same seed and configuration return the same plans
+    /// for the same statements and cluster state.
+    ///
+    /// Note that this defeats load balancing over replicas and over nodes,
+    /// so it is not meant to be used in production.
+    pub fn fixed_seed(mut self, seed: u64) -> Self {
+        self.fixed_seed = Some(seed);
+        self
+    }
 }
 
 impl Default for DefaultPolicyBuilder {
@@ -1253,6 +1295,7 @@ mod tests {
                 TabletsInfo::new(),
             )
             .await
+            .unwrap()
         }
 
         // creates ClusterData with info about 5 nodes living in 2 different datacenters
@@ -1283,6 +1326,7 @@ mod tests {
                 TabletsInfo::new(),
             )
             .await
+            .unwrap()
         }
 
         pub(crate) fn get_plan_and_collect_node_identifiers(
@@ -1380,6 +1424,50 @@ mod tests {
         .await;
     }
 
+    #[tokio::test]
+    async fn test_default_policy_with_fixed_seed_is_deterministic() {
+        setup_tracing();
+        let cluster = mock_cluster_data_for_token_aware_tests().await;
+        let make_policy = |seed| {
+            DefaultPolicy::builder()
+                .prefer_datacenter("eu".to_owned())
+                .permit_dc_failover(true)
+                .fixed_seed(seed)
+                .build()
+        };
+        let collect_plan = |policy: &dyn crate::load_balancing::LoadBalancingPolicy,
+                            routing_info: &RoutingInfo| {
+            Plan::new(policy, routing_info, &cluster)
+                .map(|(node, _shard)| node.address.port())
+                .collect::<Vec<_>>()
+        };
+
+        let token_aware = RoutingInfo {
+            token: Some(Token::new(160)),
+            table: Some(TABLE_NTS_RF_3),
+            consistency: Consistency::Quorum,
+            ..Default::default()
+        };
+        let token_unaware = RoutingInfo {
+            consistency: Consistency::Quorum,
+            ..Default::default()
+        };
+
+        for routing_info in [&token_aware, &token_unaware] {
+            let policy = make_policy(42);
+            let expected = collect_plan(policy.as_ref(), routing_info);
+            assert!(!expected.is_empty());
+            for _ in 0..32 {
+                assert_eq!(collect_plan(policy.as_ref(), routing_info), expected);
+                // A policy built with the same seed yields the same plans.
+                assert_eq!(
+                    collect_plan(make_policy(42).as_ref(), routing_info),
+                    expected
+                );
+            }
+        }
+    }
+
     #[tokio::test]
     async fn test_default_policy_with_token_aware_statements() {
         setup_tracing();
@@ -2331,7 +2419,8 @@ mod tests {
             },
             TabletsInfo::new(),
         )
-        .await;
+        .await
+        .unwrap();
 
         let tests_with_disabled_node_f = [
             // Keyspace NTS with RF=3 without preferred DC.
@@ -2386,12 +2475,17 @@ mod tests {
 mod latency_awareness {
     use futures::{future::RemoteHandle, FutureExt};
     use itertools::Either;
-    use scylla_cql::errors::{DbError, QueryError};
+    use scylla_cql::errors::{ConnectionError, DbError, QueryError};
     use tokio::time::{Duration, Instant};
     use tracing::{trace, warn};
     use uuid::Uuid;
 
-    use crate::{load_balancing::NodeRef, routing::Shard, transport::node::Node};
+    use crate::{
+        load_balancing::NodeRef,
+        routing::Shard,
+        transport::node::Node,
+        transport::runtime::{default_runtime, Runtime},
+    };
     use std::{
         collections::HashMap,
         ops::Deref,
@@ -2556,6 +2650,7 @@ mod latency_awareness {
             update_rate: Duration,
             minimum_measurements: usize,
             scale: Duration,
+            runtime: Arc<dyn Runtime>,
         ) -> Self {
             let (self_, updater) = Self::new_for_test(
                 exclusion_threshold,
@@ -2565,15 +2660,15 @@ mod latency_awareness {
                 scale,
             );
 
+            let updater_runtime = runtime.clone();
             let (updater_fut, updater_handle) = async move {
-                let mut update_scheduler = tokio::time::interval(update_rate);
                 loop {
-                    update_scheduler.tick().await;
                     updater.tick().await;
+                    updater_runtime.sleep(update_rate).await;
                 }
             }
             .remote_handle();
-            tokio::task::spawn(updater_fut);
+            runtime.spawn(Box::pin(updater_fut));
 
             Self {
                 _updater_handle: Some(updater_handle),
@@ -2678,22 +2773,52 @@ mod latency_awareness {
             match error {
                 // "fast" errors, i.e. ones that are returned quickly after the query begins
                 QueryError::BadQuery(_)
-                | QueryError::TooManyOrphanedStreamIds(_)
-                | QueryError::UnableToAllocStreamId
-                | QueryError::DbError(DbError::IsBootstrapping, _)
-                | QueryError::DbError(DbError::Unavailable { .. }, _)
-                | QueryError::DbError(DbError::Unprepared { .. }, _)
-                | QueryError::TranslationError(_)
-                | QueryError::DbError(DbError::Overloaded { .. }, _)
-                | QueryError::DbError(DbError::RateLimitReached { .. }, _) => false,
+                | QueryError::SerializationError(_)
+                | QueryError::ConnectionError {
+                    error: ConnectionError::TooManyOrphanedStreamIds(_),
+                    ..
+                }
+                | QueryError::ConnectionError {
+                    error: ConnectionError::UnableToAllocStreamId,
+                    ..
+                }
+                | QueryError::DbError {
+                    error: DbError::IsBootstrapping,
+                    ..
+                }
+                | QueryError::DbError {
+                    error: DbError::Unavailable { .. },
+                    ..
+                }
+                | QueryError::DbError {
+                    error: DbError::Unprepared { .. },
+                    ..
+                }
+                | QueryError::ConnectionError {
+                    error: ConnectionError::TranslationError(_),
+                    ..
+                }
+                | QueryError::DbError {
+                    error: DbError::Overloaded { .. },
+                    ..
+                }
+                | QueryError::DbError {
+                    error: DbError::RateLimitReached { .. },
+                    ..
+                } => false,
 
                 // "slow" errors, i.e. ones that are returned after considerable time of query being run
-                QueryError::DbError(_, _)
+                QueryError::DbError { .. }
                 | QueryError::InvalidMessage(_)
-                | QueryError::IoError(_)
+                | QueryError::ConnectionError {
+                    error: ConnectionError::IoError(_),
+                    ..
+                }
                 | QueryError::ProtocolError(_)
-                | QueryError::TimeoutError
-                | QueryError::RequestTimeout(_) => true,
+                | QueryError::TimeoutError { .. } => true,
+
+                // `QueryError` is non-exhaustive
+                _ => true,
             }
         }
     }
@@ -2767,6 +2892,7 @@ mod latency_awareness {
         update_rate: Duration,
         minimum_measurements: usize,
         scale: Duration,
+        runtime: Arc<dyn Runtime>,
     }
 
     impl LatencyAwarenessBuilder {
@@ -2778,6 +2904,7 @@ mod latency_awareness {
                 update_rate: Duration::from_millis(100),
                 minimum_measurements: 50,
                 scale: Duration::from_millis(100),
+                runtime: default_runtime(),
             }
         }
 
@@ -2872,6 +2999,15 @@ mod latency_awareness {
             Self { scale, ..self }
         }
 
+        /// Sets the runtime which runs the task periodically updating the minimal average latency.
+        ///
+        /// The policy is built before the session, so it doesn't use the runtime set with
+        /// [`SessionBuilder::runtime`](crate::transport::session_builder::GenericSessionBuilder::runtime).
+        /// The default is [`TokioRuntime`](crate::transport::runtime::TokioRuntime).
+        pub fn runtime(self, runtime: Arc<dyn Runtime>) -> Self {
+            Self { runtime, ..self }
+        }
+
         pub(super) fn build(self) -> LatencyAwareness {
             let Self {
                 exclusion_threshold,
@@ -2879,6 +3015,7 @@ mod latency_awareness {
                 update_rate,
                 minimum_measurements,
                 scale,
+                runtime,
             } = self;
             LatencyAwareness::new(
                 exclusion_threshold,
@@ -2886,6 +3023,7 @@ mod latency_awareness {
                 update_rate,
                 minimum_measurements,
                 scale,
+                runtime,
             )
         }
 
@@ -2897,6 +3035,7 @@ mod latency_awareness {
                 update_rate,
                 minimum_measurements,
                 scale,
+                runtime: _,
             } = self;
             LatencyAwareness::new_for_test(
                 exclusion_threshold,
@@ -3032,6 +3171,7 @@ mod latency_awareness {
                 pick_predicate,
                 latency_awareness: Some(latency_awareness),
                 fixed_seed: None,
+                deterministic_rotation: false,
             }
         }
 
@@ -0,0 +1,349 @@
+//! Load shedding based on the priority of statements.
+//!
+//! A [`LoadSheddingPolicy`], set with
+//! [`SessionBuilder::load_shedding_policy`](crate::transport::session_builder::GenericSessionBuilder::load_shedding_policy),
+//! limits the number of requests the session executes at once.
+//! Each statement has a [`Priority`] (see e.g. [`Query::set_priority`](crate::query::Query::set_priority)),
+//! and requests of lower priorities are admitted only while fewer requests are in flight,
+//! so that when the session is saturated they are shed first and the capacity is left
+//! for the more important ones.
+//!
+//! A request which is not admitted either fails immediately with
+//! [`QueryError::RequestShed`], or, if a maximal queue time is configured,
+//! waits until a slot frees up. Freed slots are handed over to the waiting requests
+//! of the highest priority first.
+//!
+//! The limit covers the whole execution of a request, including its retries and
+//! speculative executions. Paged queries executed with `query_iter` and `execute_iter`
+//! are admitted separately for each page fetch (and for each retry of it), with the priority
+//! of their statement; the slot is freed as soon as the page is received, so it isn't held
+//! while the iterator waits to be read. A shed page fetch ends the iteration with
+//! [`QueryError::RequestShed`].
+//!
+//! # Example
+//! ```rust
+//! # use scylla::{Session, SessionBuilder};
+//! # use std::error::Error;
+//! # async fn check_only_compiles() -> Result<(), Box<dyn Error>> {
+//! use scylla::query::Query;
+//! use scylla::statement::Priority;
+//! use scylla::transport::load_shedding::LoadSheddingPolicy;
+//! use std::time::Duration;
+//!
+//! // At most 1000 requests in flight, of which at most 200 of low priority.
+//! // Requests over the limit wait for up to 10ms.
+//! let policy = LoadSheddingPolicy::new(1000)
+//!     .with_in_flight_limit(Priority::Low, 200)
+//!     .with_max_queue_time(Duration::from_millis(10));
+//! let session: Session = SessionBuilder::new()
+//!     .known_node("127.0.0.1:9042")
+//!     .load_shedding_policy(policy)
+//!     .build()
+//!     .await?;
+//!
+//! let mut cache_refill = Query::new("SELECT * FROM ks.t");
+//! cache_refill.set_priority(Priority::Low);
+//! session.query(cache_refill, &[]).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+
+use crate::statement::Priority;
+use crate::transport::errors::QueryError;
+use crate::transport::runtime::{self, Runtime};
+
+const PRIORITIES_NUM: usize = 3;
+
+/// Limits the number of requests in flight, shedding the requests of lower priorities first.
+///
+/// See the [module documentation](self) for details.
+#[derive(Debug)]
+pub struct LoadSheddingPolicy {
+    max_in_flight: usize,
+    // Maximal number of requests in flight at which a request
+    // of the given priority is still admitted, indexed by the priority
+    in_flight_limits: [usize; PRIORITIES_NUM],
+    max_queue_time: Option<Duration>,
+    state: Mutex<LoadSheddingState>,
+}
+
+#[derive(Debug, Default)]
+struct LoadSheddingState {
+    in_flight: usize,
+    // Requests waiting for a free slot, indexed by their priority
+    waiting: [VecDeque<oneshot::Sender<()>>; PRIORITIES_NUM],
+}
+
+impl LoadSheddingPolicy {
+    /// Creates a policy which allows at most `max_in_flight` requests at once.
+    ///
+    /// By default, requests of [`Priority::Low`] are admitted while fewer than
+    /// half of `max_in_flight` requests are in flight, requests of [`Priority::Normal`]
+    /// while fewer than 90% of them are, and requests of [`Priority::High`] use the whole
+    /// capacity. Requests which are not admitted are rejected immediately.
+    pub fn new(max_in_flight: usize) -> Self {
+        let mut in_flight_limits = [0; PRIORITIES_NUM];
+        in_flight_limits[Priority::Low as usize] = max_in_flight / 2;
+        in_flight_limits[Priority::Normal as usize] = max_in_flight - max_in_flight / 10;
+        in_flight_limits[Priority::High as usize] = max_in_flight;
+
+        Self {
+            max_in_flight,
+            in_flight_limits,
+            max_queue_time: None,
+            state: Mutex::new(LoadSheddingState::default()),
+        }
+    }
+
+    /// Sets the number of requests in flight up to which requests of the given priority
+    /// are admitted. The limit of each priority, including [`Priority::High`], is capped
+    /// at the `max_in_flight` value the policy was created with.
+    pub fn with_in_flight_limit(mut self, priority: Priority, limit: usize) -> Self {
+        self.in_flight_limits[priority as usize] = std::cmp::min(limit, self.max_in_flight);
+        self
+    }
+
+    /// Makes the requests which are not admitted wait for a free slot for up to
+    /// `max_queue_time`, instead of being rejected immediately.
+    pub fn with_max_queue_time(mut self, max_queue_time: Duration) -> Self {
+        self.max_queue_time = Some(max_queue_time);
+        self
+    }
+
+    /// Returns the number of requests currently in flight.
+    pub fn get_in_flight(&self) -> usize {
+        self.state.lock().unwrap().in_flight
+    }
+
+    /// Waits until the request of the given priority is admitted,
+    /// or fails with [`QueryError::RequestShed`] if it is shed.
+    pub(crate) async fn acquire(
+        &self,
+        priority: Priority,
+        runtime: &dyn Runtime,
+    ) -> Result<InFlightPermit<'_>, QueryError> {
+        let index = priority as usize;
+        let (receiver, max_queue_time) = {
+            let mut state = self.state.lock().unwrap();
+            // Requests which stopped waiting leave their senders behind until a slot is handed
+            // over to them. The ones at the heads of the queues would hold back this request.
+            for waiting in &mut state.waiting[index..] {
+                while matches!(waiting.front(), Some(sender) if sender.is_closed()) {
+                    waiting.pop_front();
+                }
+            }
+            // Waiting requests of the same or higher priorities go first
+            let nobody_waits = state.waiting[index..].iter().all(VecDeque::is_empty);
+            if nobody_waits && state.in_flight < self.in_flight_limits[index] {
+                state.in_flight += 1;
+                return Ok(InFlightPermit { policy: self });
+            }
+
+            let max_queue_time = self.max_queue_time.ok_or(QueryError::RequestShed)?;
+            let (sender, receiver) = oneshot::channel();
+            state.waiting[index].push_back(sender);
+            (receiver, max_queue_time)
+        };
+
+        let mut waiter = Waiter {
+            policy: self,
+            receiver,
+        };
+        match runtime::timeout(runtime, max_queue_time, &mut waiter.receiver).await {
+            Ok(Ok(())) => Ok(InFlightPermit { policy: self }),
+            Ok(Err(_)) => Err(QueryError::RequestShed),
+            Err(_) => {
+                // The slot might have been handed over right after the timeout elapsed
+                waiter.receiver.close();
+                match waiter.receiver.try_recv() {
+                    Ok(()) => Ok(InFlightPermit { policy: self }),
+                    Err(_) => Err(QueryError::RequestShed),
+                }
+            }
+        }
+    }
+
+    fn release(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.in_flight -= 1;
+
+        // Hand the freed slot over to the waiting request of the highest priority
+        for index in (0..PRIORITIES_NUM).rev() {
+            while state.in_flight < self.in_flight_limits[index] {
+                let Some(sender) = state.waiting[index].pop_front() else {
+                    break;
+                };
+                // Sending fails if the request stopped waiting
+                if sender.send(()).is_ok() {
+                    state.in_flight += 1;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Admission of a single request, which frees its slot when dropped.
+#[derive(Debug)]
+pub(crate) struct InFlightPermit<'a> {
+    policy: &'a LoadSheddingPolicy,
+}
+
+impl Drop for InFlightPermit<'_> {
+    fn drop(&mut self) {
+        self.policy.release();
+    }
+}
+
+// Frees the slot handed over to a request which stopped waiting for it,
+// e.g. because its future was dropped.
+struct Waiter<'a> {
+    policy: &'a LoadSheddingPolicy,
+    receiver: oneshot::Receiver<()>,
+}
+
+impl Drop for Waiter<'_> {
+    fn drop(&mut self) {
+        self.receiver.close();
+        if self.receiver.try_recv().is_ok() {
+            self.policy.release();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use assert_matches::assert_matches;
+    use tokio::sync::oneshot;
+
+    use super::LoadSheddingPolicy;
+    use crate::statement::Priority;
+    use crate::transport::errors::QueryError;
+    use crate::transport::runtime::TokioRuntime;
+
+    #[tokio::test]
+    async fn lower_priorities_are_shed_first() {
+        let policy = LoadSheddingPolicy::new(10).with_in_flight_limit(Priority::Low, 1);
+
+        let low = policy.acquire(Priority::Low, &TokioRuntime).await.unwrap();
+        assert_matches!(
+            policy.acquire(Priority::Low, &TokioRuntime).await,
+            Err(QueryError::RequestShed)
+        );
+        let mut normal = Vec::new();
+        for _ in 0..8 {
+            normal.push(
+                policy
+                    .acquire(Priority::Normal, &TokioRuntime)
+                    .await
+                    .unwrap(),
+            );
+        }
+        assert_matches!(
+            policy.acquire(Priority::Normal, &TokioRuntime).await,
+            Err(QueryError::RequestShed)
+        );
+        let _high = policy.acquire(Priority::High, &TokioRuntime).await.unwrap();
+        assert_matches!(
+            policy.acquire(Priority::High, &TokioRuntime).await,
+            Err(QueryError::RequestShed)
+        );
+        assert_eq!(policy.get_in_flight(), 10);
+
+        drop(low);
+        assert_matches!(
+            policy.acquire(Priority::Low, &TokioRuntime).await,
+            Err(QueryError::RequestShed)
+        );
+        assert_matches!(
+            policy.acquire(Priority::Normal, &TokioRuntime).await,
+            Err(QueryError::RequestShed)
+        );
+        normal.pop();
+        let _normal = policy
+            .acquire(Priority::Normal, &TokioRuntime)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn freed_slots_go_to_higher_priorities() {
+        let policy = LoadSheddingPolicy::new(1).with_max_queue_time(Duration::from_secs(10));
+
+        let permit = policy.acquire(Priority::High, &TokioRuntime).await.unwrap();
+        let normal = policy.acquire(Priority::Normal, &TokioRuntime);
+        let high = policy.acquire(Priority::High, &TokioRuntime);
+        tokio::pin!(normal, high);
+
+        // Register both requests as waiting
+        assert!(futures::poll!(normal.as_mut()).is_pending());
+        assert!(futures::poll!(high.as_mut()).is_pending());
+
+        drop(permit);
+        let high_permit = high.await.unwrap();
+        assert!(futures::poll!(normal.as_mut()).is_pending());
+        drop(high_permit);
+        let _normal_permit = normal.await.unwrap();
+        assert_eq!(policy.get_in_flight(), 1);
+    }
+
+    #[tokio::test]
+    async fn queued_requests_time_out() {
+        let policy = LoadSheddingPolicy::new(1).with_max_queue_time(Duration::from_millis(10));
+
+        let _permit = policy
+            .acquire(Priority::Normal, &TokioRuntime)
+            .await
+            .unwrap();
+        assert_matches!(
+            policy.acquire(Priority::High, &TokioRuntime).await,
+            Err(QueryError::RequestShed)
+        );
+        assert_eq!(policy.get_in_flight(), 1);
+    }
+
+    #[tokio::test]
+    async fn limits_are_capped_at_max_in_flight() {
+        let policy = LoadSheddingPolicy::new(2)
+            .with_in_flight_limit(Priority::High, 5)
+            .with_in_flight_limit(Priority::Low, 3);
+        assert_eq!(policy.in_flight_limits, [2, 2, 2]);
+
+        let _permits = [
+            policy.acquire(Priority::High, &TokioRuntime).await.unwrap(),
+            policy.acquire(Priority::High, &TokioRuntime).await.unwrap(),
+        ];
+        assert_matches!(
+            policy.acquire(Priority::High, &TokioRuntime).await,
+            Err(QueryError::RequestShed)
+        );
+    }
+
+    #[tokio::test]
+    async fn requests_which_stopped_waiting_are_pruned() {
+        let policy = LoadSheddingPolicy::new(1);
+        let (sender, receiver) = oneshot::channel();
+        drop(receiver);
+        policy.state.lock().unwrap().waiting[Priority::Normal as usize].push_back(sender);
+
+        let _permit = policy
+            .acquire(Priority::Normal, &TokioRuntime)
+            .await
+            .unwrap();
+        assert!(policy
+            .state
+            .lock()
+            .unwrap()
+            .waiting
+            .iter()
+            .all(|waiting| waiting.is_empty()));
+    }
+}
@@ -285,7 +285,11 @@ impl Tablet {
     }
 
     #[cfg(test)]
-    fn new_for_test(token: i64, replicas: Vec<Arc<Node>>, failed: Option<Vec<Uuid>>) -> Self {
+    pub(crate) fn new_for_test(
+        token: i64,
+        replicas: Vec<Arc<Node>>,
+        failed: Option<Vec<Uuid>>,
+    ) -> Self {
         Self {
             first_token: Token::new(token),
             last_token: Token::new(token),
@@ -482,6 +486,13 @@ impl TabletsInfo {
         table_tablets
     }
 
+    /// Whether the driver knows tablets of any table of the keyspace.
+    pub(crate) fn has_tablets_in_keyspace(&self, keyspace: &str) -> bool {
+        self.tablets
+            .keys()
+            .any(|table_spec| table_spec.ks_name() == keyspace)
+    }
+
     pub(crate) fn add_tablet(&mut self, table_spec: TableSpec<'static>, tablet: Tablet) {
         if tablet.failed.is_some() {
             self.has_unknown_replicas = true;
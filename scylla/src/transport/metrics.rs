@@ -1,6 +1,10 @@
 use histogram::Histogram;
+use scylla_cql::errors::QueryError;
+use scylla_cql::frame::response::result::TableSpec;
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
 const ORDER_TYPE: Ordering = Ordering::Relaxed;
 
@@ -28,7 +32,14 @@ pub struct Metrics {
     errors_iter_num: AtomicU64,
     queries_iter_num: AtomicU64,
     retries_num: AtomicU64,
+    request_bytes_before_compression: AtomicU64,
+    request_bytes_after_compression: AtomicU64,
+    missed_shard_requests_num: AtomicU64,
+    orphaned_requests_num: AtomicU64,
+    cancelled_requests_num: AtomicU64,
+    shed_requests_num: AtomicU64,
     histogram: Arc<Mutex<Histogram>>,
+    tables: Option<TablesMetrics>,
 }
 
 impl Metrics {
@@ -39,7 +50,28 @@ impl Metrics {
             errors_iter_num: AtomicU64::new(0),
             queries_iter_num: AtomicU64::new(0),
             retries_num: AtomicU64::new(0),
+            request_bytes_before_compression: AtomicU64::new(0),
+            request_bytes_after_compression: AtomicU64::new(0),
+            missed_shard_requests_num: AtomicU64::new(0),
+            orphaned_requests_num: AtomicU64::new(0),
+            cancelled_requests_num: AtomicU64::new(0),
+            shed_requests_num: AtomicU64::new(0),
             histogram: Arc::new(Mutex::new(Histogram::new())),
+            tables: None,
+        }
+    }
+
+    /// Creates metrics which also track the requests to each table,
+    /// separately for at most `max_tables` tables.
+    pub(crate) fn with_table_metrics(max_tables: usize) -> Self {
+        Self {
+            tables: Some(TablesMetrics {
+                max_tables,
+                tables: RwLock::new(HashMap::new()),
+                tables_num: AtomicU64::new(0),
+                untracked: Arc::new(TableMetrics::new()),
+            }),
+            ..Self::new()
         }
     }
 
@@ -69,6 +101,38 @@ impl Metrics {
         self.retries_num.fetch_add(1, ORDER_TYPE);
     }
 
+    /// Increments counter for requests which were sent to a different shard
+    /// than the one chosen by the load balancing policy.
+    pub(crate) fn inc_missed_shard_requests(&self) {
+        self.missed_shard_requests_num.fetch_add(1, ORDER_TYPE);
+    }
+
+    /// Increments counter for requests which were abandoned by the caller
+    /// before their response arrived.
+    pub(crate) fn inc_orphaned_requests(&self) {
+        self.orphaned_requests_num.fetch_add(1, ORDER_TYPE);
+    }
+
+    /// Increments counter for requests which were cancelled by the caller
+    /// before they completed.
+    pub(crate) fn inc_cancelled_requests(&self) {
+        self.cancelled_requests_num.fetch_add(1, ORDER_TYPE);
+    }
+
+    /// Increments counter for requests which were rejected by the load shedding policy.
+    pub(crate) fn inc_shed_requests(&self) {
+        self.shed_requests_num.fetch_add(1, ORDER_TYPE);
+    }
+
+    /// Adds sizes of a compressed request body, before and after compression,
+    /// to the compression counters.
+    pub(crate) fn log_compressed_request(&self, uncompressed_len: usize, compressed_len: usize) {
+        self.request_bytes_before_compression
+            .fetch_add(uncompressed_len as u64, ORDER_TYPE);
+        self.request_bytes_after_compression
+            .fetch_add(compressed_len as u64, ORDER_TYPE);
+    }
+
     /// Saves to histogram latency of completing single query.
     /// For paged queries it should log latency for every page.
     ///
@@ -81,6 +145,21 @@ impl Metrics {
         Ok(())
     }
 
+    /// Logs a single attempt of a request to the table in the metrics of the table,
+    /// if they are enabled.
+    pub(crate) fn log_table_request(
+        &self,
+        table: &TableSpec<'_>,
+        latency: Duration,
+        error: Option<&QueryError>,
+    ) {
+        if let Some(tables) = &self.tables {
+            tables
+                .get_or_insert(table.ks_name(), table.table_name())
+                .log_request(latency, error);
+        }
+    }
+
     /// Returns average latency in milliseconds
     pub fn get_latency_avg_ms(&self) -> Result<u64, MetricsError> {
         let histogram_unlocked = self.histogram.lock().unwrap();
@@ -120,4 +199,282 @@ impl Metrics {
     pub fn get_retries_num(&self) -> u64 {
         self.retries_num.load(ORDER_TYPE)
     }
+
+    /// Returns counter for requests which were sent to a different shard than the one
+    /// chosen by the load balancing policy, because there was no connection to the target shard.
+    pub fn get_missed_shard_requests_num(&self) -> u64 {
+        self.missed_shard_requests_num.load(ORDER_TYPE)
+    }
+
+    /// Returns counter for requests which were abandoned (e.g. their future was dropped)
+    /// before their response arrived. Stream ids of such requests stay occupied
+    /// until the response comes.
+    pub fn get_orphaned_requests_num(&self) -> u64 {
+        self.orphaned_requests_num.load(ORDER_TYPE)
+    }
+
+    /// Returns counter for requests which were cancelled before they completed,
+    /// either with a [`CancelHandle`](crate::transport::cancellation::CancelHandle)
+    /// or by dropping the future (or the [`RowIterator`](crate::transport::iterator::RowIterator))
+    /// of the request. The driver stops retrying and sending speculative executions
+    /// of a cancelled request.
+    pub fn get_cancelled_requests_num(&self) -> u64 {
+        self.cancelled_requests_num.load(ORDER_TYPE)
+    }
+
+    /// Returns counter for requests which were rejected by the
+    /// [`LoadSheddingPolicy`](crate::transport::load_shedding::LoadSheddingPolicy) of the session.
+    pub fn get_shed_requests_num(&self) -> u64 {
+        self.shed_requests_num.load(ORDER_TYPE)
+    }
+
+    /// Returns total size in bytes of the compressed request bodies, measured before compression.
+    /// Requests sent uncompressed are not counted, neither are responses.
+    pub fn get_request_bytes_before_compression(&self) -> u64 {
+        self.request_bytes_before_compression.load(ORDER_TYPE)
+    }
+
+    /// Returns total size in bytes of the compressed request bodies, measured after compression.
+    /// Requests sent uncompressed are not counted, neither are responses.
+    pub fn get_request_bytes_after_compression(&self) -> u64 {
+        self.request_bytes_after_compression.load(ORDER_TYPE)
+    }
+
+    /// Returns metrics of the requests to the given table, `None` if no requests to the table
+    /// were tracked separately, or the per-table metrics are disabled.
+    ///
+    /// The table of a request is known for prepared statements, and for batches
+    /// whose first statement is prepared.
+    pub fn get_table_metrics(&self, keyspace: &str, table: &str) -> Option<Arc<TableMetrics>> {
+        self.tables.as_ref()?.get(keyspace, table)
+    }
+
+    /// Returns metrics of the requests to each table tracked separately, as
+    /// `(keyspace, table, metrics)`, sorted by the keyspace and table names.
+    /// Empty if the per-table metrics are disabled.
+    pub fn get_all_table_metrics(&self) -> Vec<(String, String, Arc<TableMetrics>)> {
+        let Some(tables) = &self.tables else {
+            return Vec::new();
+        };
+        let mut all_metrics: Vec<_> = tables
+            .tables
+            .read()
+            .unwrap()
+            .iter()
+            .flat_map(|(keyspace, tables)| {
+                tables
+                    .iter()
+                    .map(move |(table, metrics)| (keyspace.clone(), table.clone(), metrics.clone()))
+            })
+            .collect();
+        all_metrics.sort_unstable_by(|(ks1, table1, _), (ks2, table2, _)| {
+            (ks1, table1).cmp(&(ks2, table2))
+        });
+        all_metrics
+    }
+
+    /// Returns aggregated metrics of the requests to the tables which are not tracked
+    /// separately, because the limit of tracked tables was reached.
+    /// `None` if the per-table metrics are disabled.
+    pub fn get_untracked_table_metrics(&self) -> Option<Arc<TableMetrics>> {
+        self.tables.as_ref().map(|tables| tables.untracked.clone())
+    }
+}
+
+/// Metrics of the requests to tables, with limited cardinality.
+#[derive(Debug)]
+struct TablesMetrics {
+    max_tables: usize,
+    // Keyspace name -> table name -> metrics
+    tables: RwLock<HashMap<String, HashMap<String, Arc<TableMetrics>>>>,
+    tables_num: AtomicU64,
+    // Aggregated metrics of the tables above the limit
+    untracked: Arc<TableMetrics>,
+}
+
+impl TablesMetrics {
+    fn get_or_insert(&self, keyspace: &str, table: &str) -> Arc<TableMetrics> {
+        if let Some(metrics) = self.get(keyspace, table) {
+            return metrics;
+        }
+        // Once the limit is reached, requests to new tables don't take the write lock
+        if self.tables_num.load(ORDER_TYPE) >= self.max_tables as u64 {
+            return self.untracked.clone();
+        }
+
+        let mut tables = self.tables.write().unwrap();
+        if let Some(metrics) = tables.get(keyspace).and_then(|tables| tables.get(table)) {
+            return metrics.clone();
+        }
+        if self.tables_num.load(ORDER_TYPE) >= self.max_tables as u64 {
+            return self.untracked.clone();
+        }
+        self.tables_num.fetch_add(1, ORDER_TYPE);
+        tables
+            .entry(keyspace.to_owned())
+            .or_default()
+            .entry(table.to_owned())
+            .or_insert_with(|| Arc::new(TableMetrics::new()))
+            .clone()
+    }
+
+    fn get(&self, keyspace: &str, table: &str) -> Option<Arc<TableMetrics>> {
+        let tables = self.tables.read().unwrap();
+        tables.get(keyspace)?.get(table).cloned()
+    }
+}
+
+/// Metrics of the requests to a single table, or to a group of tables.
+///
+/// Like the counters of [`Metrics`], they are updated on every attempt of sending a request,
+/// including retries and fetching subsequent pages.
+#[derive(Debug)]
+pub struct TableMetrics {
+    requests_num: AtomicU64,
+    errors_num: AtomicU64,
+    timeouts_num: AtomicU64,
+    histogram: Mutex<Histogram>,
+}
+
+impl TableMetrics {
+    // Latencies above the maximum are logged as the maximum
+    const MAX_LATENCY_MS: u64 = 60_000;
+
+    fn new() -> Self {
+        Self {
+            requests_num: AtomicU64::new(0),
+            errors_num: AtomicU64::new(0),
+            timeouts_num: AtomicU64::new(0),
+            // Coarser than the histogram of all requests, as there is one of them per table
+            histogram: Mutex::new(
+                Histogram::configure()
+                    .max_value(Self::MAX_LATENCY_MS)
+                    .precision(2)
+                    .build()
+                    .unwrap(),
+            ),
+        }
+    }
+
+    fn log_request(&self, latency: Duration, error: Option<&QueryError>) {
+        self.requests_num.fetch_add(1, ORDER_TYPE);
+        match error {
+            None => {
+                let latency_ms = (latency.as_millis() as u64).min(Self::MAX_LATENCY_MS);
+                let _ = self.histogram.lock().unwrap().increment(latency_ms);
+            }
+            Some(error) => {
+                self.errors_num.fetch_add(1, ORDER_TYPE);
+                if error.is_timeout() {
+                    self.timeouts_num.fetch_add(1, ORDER_TYPE);
+                }
+            }
+        }
+    }
+
+    /// Returns counter for requests
+    pub fn get_requests_num(&self) -> u64 {
+        self.requests_num.load(ORDER_TYPE)
+    }
+
+    /// Returns counter for failed requests, including the timed out ones
+    pub fn get_errors_num(&self) -> u64 {
+        self.errors_num.load(ORDER_TYPE)
+    }
+
+    /// Returns counter for requests which timed out, either on the client side
+    /// or with a read or write timeout reported by the database
+    pub fn get_timeouts_num(&self) -> u64 {
+        self.timeouts_num.load(ORDER_TYPE)
+    }
+
+    /// Returns average latency of successful requests in milliseconds
+    pub fn get_latency_avg_ms(&self) -> Result<u64, MetricsError> {
+        Ok(self.histogram.lock().unwrap().mean()?)
+    }
+
+    /// Returns latency of successful requests from histogram for a given percentile
+    /// # Arguments
+    ///
+    /// * `percentile` - float value (0.0 - 100.0)
+    pub fn get_latency_percentile_ms(&self, percentile: f64) -> Result<u64, MetricsError> {
+        Ok(self.histogram.lock().unwrap().percentile(percentile)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use scylla_cql::errors::{DbError, QueryError, WriteType};
+    use scylla_cql::frame::response::result::TableSpec;
+    use scylla_cql::Consistency;
+
+    use super::Metrics;
+
+    #[test]
+    fn table_metrics_with_limited_cardinality() {
+        let metrics = Metrics::with_table_metrics(2);
+        let timeout = QueryError::DbError {
+            error: DbError::WriteTimeout {
+                consistency: Consistency::One,
+                received: 0,
+                required: 1,
+                write_type: WriteType::Simple,
+            },
+            message: String::new(),
+            node: None,
+        };
+        let ms = Duration::from_millis;
+
+        metrics.log_table_request(&TableSpec::borrowed("ks", "a"), ms(10), None);
+        metrics.log_table_request(&TableSpec::borrowed("ks", "a"), ms(30), None);
+        metrics.log_table_request(&TableSpec::borrowed("ks", "a"), ms(5), Some(&timeout));
+        metrics.log_table_request(
+            &TableSpec::borrowed("other", "a"),
+            ms(1),
+            Some(&QueryError::ProtocolError("error")),
+        );
+        metrics.log_table_request(&TableSpec::borrowed("ks", "b"), ms(100_000), None);
+        metrics.log_table_request(&TableSpec::borrowed("ks", "c"), ms(1), Some(&timeout));
+
+        let a = metrics.get_table_metrics("ks", "a").unwrap();
+        assert_eq!(a.get_requests_num(), 3);
+        assert_eq!(a.get_errors_num(), 1);
+        assert_eq!(a.get_timeouts_num(), 1);
+        assert_eq!(a.get_latency_avg_ms().unwrap(), 20);
+
+        let other = metrics.get_table_metrics("other", "a").unwrap();
+        assert_eq!(
+            (
+                other.get_requests_num(),
+                other.get_errors_num(),
+                other.get_timeouts_num()
+            ),
+            (1, 1, 0)
+        );
+
+        // Above the limit
+        assert!(metrics.get_table_metrics("ks", "b").is_none());
+        let untracked = metrics.get_untracked_table_metrics().unwrap();
+        assert_eq!(untracked.get_requests_num(), 2);
+        assert_eq!(untracked.get_timeouts_num(), 1);
+        assert_eq!(untracked.get_latency_percentile_ms(100.0).unwrap(), 60_000);
+
+        let tables: Vec<_> = metrics
+            .get_all_table_metrics()
+            .into_iter()
+            .map(|(keyspace, table, _)| format!("{keyspace}.{table}"))
+            .collect();
+        assert_eq!(tables, ["ks.a", "other.a"]);
+    }
+
+    #[test]
+    fn disabled_table_metrics() {
+        let metrics = Metrics::new();
+        metrics.log_table_request(&TableSpec::borrowed("ks", "a"), Duration::ZERO, None);
+        assert!(metrics.get_table_metrics("ks", "a").is_none());
+        assert!(metrics.get_all_table_metrics().is_empty());
+        assert!(metrics.get_untracked_table_metrics().is_none());
+    }
 }
@@ -1,25 +1,43 @@
+#[cfg(feature = "arrow-50")]
+pub mod arrow;
+mod buffer_pool;
 pub(crate) mod caching_session;
+pub mod cancellation;
 mod cluster;
+pub mod config_loader;
 pub(crate) mod connection;
 mod connection_pool;
+pub mod connector;
+pub mod control_connection;
 pub mod downgrading_consistency_retry_policy;
+pub mod execution_info;
 pub mod execution_profile;
+pub mod frame_capture;
 pub mod host_filter;
 pub mod iterator;
 pub mod load_balancing;
+pub mod load_shedding;
 pub mod locator;
 pub(crate) mod metrics;
 mod node;
 pub mod partitioner;
+pub mod pipeline;
+pub mod query_log;
 pub mod query_result;
 pub mod retry_policy;
+pub mod ring_report;
+pub mod runtime;
+pub mod script;
 pub mod session;
 pub mod session_builder;
+#[cfg(all(feature = "smol-2", not(target_family = "wasm")))]
+pub mod smol;
 pub mod speculative_execution;
 pub mod topology;
+pub mod write_defaults;
 
 pub use crate::frame::{Authenticator, Compression};
-pub use connection::SelfIdentity;
+pub use connection::{ConnectionInfo, KeepaliveRequest, SelfIdentity};
 pub use execution_profile::ExecutionProfile;
 pub use scylla_cql::errors;
 
@@ -40,4 +58,4 @@ mod cql_value_test;
 mod large_batch_statements_test;
 
 pub use cluster::ClusterData;
-pub use node::{KnownNode, Node, NodeAddr, NodeRef};
+pub use node::{KnownNode, Node, NodeAddr, NodeHealth, NodeRef};
@@ -1,17 +1,20 @@
-use tokio::net::lookup_host;
+use async_trait::async_trait;
 use tracing::warn;
 use uuid::Uuid;
 
 /// Node represents a cluster node along with it's data and connections
 use crate::routing::{Shard, Sharder};
-use crate::transport::connection::Connection;
 use crate::transport::connection::VerifiedKeyspaceName;
-use crate::transport::connection_pool::{NodeConnectionPool, PoolConfig};
+use crate::transport::connection::{Connection, ConnectionInfo};
+use crate::transport::connection_pool::{
+    NodeConnectionPool, PoolConfig, PoolState, ShardAwarenessStatus,
+};
 use crate::transport::errors::QueryError;
+use crate::transport::runtime::{self, Runtime};
 
 use std::fmt::Display;
 use std::io;
-use std::net::IpAddr;
+use std::net::{IpAddr, ToSocketAddrs};
 use std::{
     hash::{Hash, Hasher},
     net::SocketAddr,
@@ -82,6 +85,16 @@ pub struct Node {
     down_marker: AtomicBool,
 }
 
+/// A snapshot of the health of a node, returned by [`Node::health`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct NodeHealth {
+    /// Whether the node is considered up, i.e. the cluster has not reported it as down.
+    pub is_up: bool,
+    /// The state of the connection pool, `None` if the node is disabled.
+    pub pool: Option<PoolState>,
+}
+
 /// A way that Nodes are often passed and accessed in the driver's code.
 pub type NodeRef<'a> = &'a Arc<Node>;
 
@@ -161,6 +174,44 @@ impl Node {
         self.get_pool()?.connection_for_shard(shard)
     }
 
+    /// Returns whether the connections to this node are opened through the shard-aware port,
+    /// and if not, why.
+    pub fn shard_awareness_status(&self) -> ShardAwarenessStatus {
+        self.pool
+            .as_ref()
+            .map_or(ShardAwarenessStatus::Unknown, |pool| {
+                pool.shard_awareness_status()
+            })
+    }
+
+    /// Returns a snapshot of the health of this node and the state of its connection pool.
+    pub fn health(&self) -> NodeHealth {
+        NodeHealth {
+            is_up: !self.is_down(),
+            pool: self.pool.as_ref().map(NodeConnectionPool::state),
+        }
+    }
+
+    /// Returns whether this node is suspected to be down, because the last keepalive sent
+    /// on at least half of its open connections failed and no request has succeeded since.
+    /// A single connection with failing keepalives doesn't make the node suspected if
+    /// the node has more connections, as the connection might be broken on its own.
+    ///
+    /// Unlike [`Node::is_down`], which follows the events sent by the cluster,
+    /// this reacts as soon as the node stops responding.
+    pub fn is_down_suspected(&self) -> bool {
+        match &self.pool {
+            Some(pool) => pool.is_down_suspected(),
+            None => false,
+        }
+    }
+
+    pub(crate) fn record_successful_request(&self) {
+        if let Some(pool) = &self.pool {
+            pool.record_successful_request();
+        }
+    }
+
     pub fn is_down(&self) -> bool {
         self.down_marker.load(Ordering::Relaxed)
     }
@@ -190,6 +241,15 @@ impl Node {
         self.get_pool()?.get_working_connections()
     }
 
+    /// Returns information about the working connections to this node,
+    /// such as the negotiated compression or the shard each connection is attached to.
+    /// Returns an empty list if the node is disabled or has no working connections.
+    pub fn get_connections_info(&self) -> Vec<ConnectionInfo> {
+        self.get_working_connections()
+            .map(|connections| connections.iter().map(|c| c.get_info()).collect())
+            .unwrap_or_default()
+    }
+
     pub(crate) async fn wait_until_pool_initialized(&self) {
         if let Some(pool) = &self.pool {
             pool.wait_until_initialized().await;
@@ -198,11 +258,11 @@ impl Node {
 
     fn get_pool(&self) -> Result<&NodeConnectionPool, QueryError> {
         self.pool.as_ref().ok_or_else(|| {
-            QueryError::IoError(Arc::new(std::io::Error::new(
+            QueryError::from(std::io::Error::new(
                 std::io::ErrorKind::Other,
                 "No connections in the pool: the node has been disabled \
                 by the host filter",
-            )))
+            ))
         })
     }
 }
@@ -248,33 +308,120 @@ pub struct CloudEndpoint {
 pub struct ResolvedContactPoint {
     pub address: SocketAddr,
     pub datacenter: Option<String>,
+    /// Other addresses the hostname resolved to, tried if connecting to `address`
+    /// fails or takes too long.
+    pub alternative_addresses: Vec<SocketAddr>,
 }
 
 // Resolve the given hostname using a DNS lookup if necessary.
 // The resolution may return multiple IPs and the function returns one of them.
 // It prefers to return IPv4s first, and only if there are none, IPv6s.
-pub(crate) async fn resolve_hostname(hostname: &str) -> Result<SocketAddr, io::Error> {
-    let mut ret = None;
-    let addrs: Vec<SocketAddr> = match lookup_host(hostname).await {
-        Ok(addrs) => addrs.collect(),
+#[cfg(feature = "cloud")]
+pub(crate) async fn resolve_hostname(
+    resolver: &dyn HostnameResolver,
+    hostname: &str,
+) -> Result<SocketAddr, io::Error> {
+    let addrs = resolve_hostname_addresses(resolver, hostname).await?;
+    Ok(addrs
+        .iter()
+        .find(|addr| addr.is_ipv4())
+        .copied()
+        .unwrap_or(addrs[0]))
+}
+
+// Resolve the given hostname using a DNS lookup if necessary, returning all of its addresses.
+// The addresses are ordered for connection attempts as described in RFC 8305:
+// IPv6 addresses are preferred, so the first address is an IPv6 one if there is any,
+// and then the address families alternate, keeping the order returned by DNS within each family.
+// The returned list is never empty.
+pub(crate) async fn resolve_hostname_addresses(
+    resolver: &dyn HostnameResolver,
+    hostname: &str,
+) -> Result<Vec<SocketAddr>, io::Error> {
+    let addrs: Vec<SocketAddr> = match resolver.lookup_host(hostname.to_owned(), None).await {
+        Ok(addrs) => addrs,
         // Use a default port in case of error, but propagate the original error on failure
-        Err(e) => lookup_host((hostname, 9042)).await.or(Err(e))?.collect(),
+        Err(e) => resolver
+            .lookup_host(hostname.to_owned(), Some(9042))
+            .await
+            .or(Err(e))?,
     };
-    for a in addrs {
-        match a {
-            SocketAddr::V4(_) => return Ok(a),
-            _ => {
-                ret = Some(a);
-            }
+    if addrs.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("Empty address list returned by DNS for {}", hostname),
+        ));
+    }
+    Ok(interleave_address_families(addrs))
+}
+
+fn interleave_address_families(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let (v6, v4): (Vec<_>, Vec<_>) = addrs.into_iter().partition(SocketAddr::is_ipv6);
+    let (preferred, other) = if v6.is_empty() { (v4, v6) } else { (v6, v4) };
+
+    let mut interleaved = Vec::with_capacity(preferred.len() + other.len());
+    let mut preferred = preferred.into_iter();
+    let mut other = other.into_iter();
+    loop {
+        match (preferred.next(), other.next()) {
+            (None, None) => break,
+            (first, second) => interleaved.extend(first.into_iter().chain(second)),
         }
     }
+    interleaved
+}
 
-    ret.ok_or_else(|| {
-        io::Error::new(
-            io::ErrorKind::Other,
-            format!("Empty address list returned by DNS for {}", hostname),
-        )
+// Looks up the addresses of hostnames.
+// The driver uses `DnsResolver`, tests substitute stubs in order to simulate changes of DNS records.
+#[async_trait]
+pub(crate) trait HostnameResolver: Send + Sync {
+    // Resolves `host`, which includes the port unless it is given separately.
+    async fn lookup_host(&self, host: String, port: Option<u16>) -> io::Result<Vec<SocketAddr>>;
+}
+
+// Resolves hostnames using the system resolver.
+pub(crate) struct DnsResolver {
+    runtime: Arc<dyn Runtime>,
+}
+
+impl DnsResolver {
+    pub(crate) fn new(runtime: Arc<dyn Runtime>) -> Self {
+        Self { runtime }
+    }
+}
+
+#[async_trait]
+impl HostnameResolver for DnsResolver {
+    async fn lookup_host(&self, host: String, port: Option<u16>) -> io::Result<Vec<SocketAddr>> {
+        lookup_host(&*self.runtime, host, port).await
+    }
+}
+
+// The lookup is blocking, so it is done through `Runtime::spawn_blocking`,
+// which makes it work with runtimes other than Tokio as well.
+async fn lookup_host(
+    runtime: &dyn Runtime,
+    host: String,
+    port: Option<u16>,
+) -> io::Result<Vec<SocketAddr>> {
+    // Addresses given as IPs don't need a lookup.
+    let parsed = match port {
+        Some(port) => host
+            .parse::<IpAddr>()
+            .map(|ip| SocketAddr::new(ip, port))
+            .ok(),
+        None => host.parse::<SocketAddr>().ok(),
+    };
+    if let Some(addr) = parsed {
+        return Ok(vec![addr]);
+    }
+    runtime::spawn_blocking(runtime, move || match port {
+        Some(port) => (host.as_str(), port)
+            .to_socket_addrs()
+            .map(Iterator::collect),
+        None => host.to_socket_addrs().map(Iterator::collect),
     })
+    .await?
 }
 
 /// Transforms the given [`KnownNode`]s into [`ContactPoint`]s.
@@ -282,6 +429,7 @@ pub(crate) async fn resolve_hostname(hostname: &str) -> Result<SocketAddr, io::E
 /// In case of a hostname, resolves it using a DNS lookup.
 /// In case of a plain IP address, parses it and uses straight.
 pub(crate) async fn resolve_contact_points(
+    resolver: &dyn HostnameResolver,
     known_nodes: &[KnownNode],
 ) -> (Vec<ResolvedContactPoint>, Vec<String>) {
     // Find IP addresses of all known nodes passed in the config
@@ -299,6 +447,7 @@ pub(crate) async fn resolve_contact_points(
             KnownNode::Address(address) => initial_peers.push(ResolvedContactPoint {
                 address: *address,
                 datacenter: None,
+                alternative_addresses: Vec::new(),
             }),
             #[cfg(feature = "cloud")]
             KnownNode::CloudEndpoint(CloudEndpoint {
@@ -308,10 +457,11 @@ pub(crate) async fn resolve_contact_points(
         };
     }
     let resolve_futures = to_resolve.iter().map(|(hostname, datacenter)| async move {
-        match resolve_hostname(hostname).await {
-            Ok(address) => Some(ResolvedContactPoint {
-                address,
+        match resolve_hostname_addresses(resolver, hostname).await {
+            Ok(mut addresses) => Some(ResolvedContactPoint {
+                address: addresses.remove(0),
                 datacenter: datacenter.clone(),
+                alternative_addresses: addresses,
             }),
             Err(e) => {
                 warn!("Hostname resolution failed for {}: {}", hostname, &e);
@@ -349,4 +499,65 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn address_families_are_interleaved() {
+        let addrs: Vec<SocketAddr> = ["[::1]:1", "[::2]:1", "[::3]:1", "1.0.0.1:1", "1.0.0.2:1"]
+            .iter()
+            .map(|addr| addr.parse().unwrap())
+            .collect();
+        let expected: Vec<SocketAddr> = ["[::1]:1", "1.0.0.1:1", "[::2]:1", "1.0.0.2:1", "[::3]:1"]
+            .iter()
+            .map(|addr| addr.parse().unwrap())
+            .collect();
+        assert_eq!(interleave_address_families(addrs), expected);
+
+        let v4_only: Vec<SocketAddr> =
+            vec!["1.0.0.1:1".parse().unwrap(), "1.0.0.2:1".parse().unwrap()];
+        assert_eq!(interleave_address_families(v4_only.clone()), v4_only);
+    }
+
+    // Runs blocking jobs on plain threads, without Tokio.
+    #[derive(Debug)]
+    struct ThreadRuntime;
+
+    impl Runtime for ThreadRuntime {
+        fn spawn(&self, task: runtime::BoxedFuture<()>) {
+            std::thread::spawn(move || futures::executor::block_on(task));
+        }
+
+        fn spawn_blocking(&self, job: Box<dyn FnOnce() + Send + 'static>) {
+            std::thread::spawn(job);
+        }
+
+        fn sleep(&self, duration: std::time::Duration) -> runtime::BoxedFuture<()> {
+            let (sender, receiver) = futures::channel::oneshot::channel::<()>();
+            std::thread::spawn(move || {
+                std::thread::sleep(duration);
+                let _ = sender.send(());
+            });
+            Box::pin(async move {
+                let _ = receiver.await;
+            })
+        }
+    }
+
+    #[test]
+    fn hostnames_are_resolved_outside_tokio() {
+        let resolve = |hostname: &str| {
+            let resolver = DnsResolver::new(Arc::new(ThreadRuntime));
+            futures::executor::block_on(resolve_hostname_addresses(&resolver, hostname)).unwrap()
+        };
+
+        let addrs = resolve("localhost:19042");
+        assert!(addrs
+            .iter()
+            .all(|addr| addr.ip().is_loopback() && addr.port() == 19042));
+        // The default port is used if the hostname doesn't have one
+        assert!(resolve("localhost").iter().all(|addr| addr.port() == 9042));
+        assert_eq!(
+            resolve("127.0.0.1"),
+            vec![SocketAddr::from(([127, 0, 0, 1], 9042))]
+        );
+    }
 }
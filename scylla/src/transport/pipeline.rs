@@ -0,0 +1,367 @@
+//! Pipelining of statements over a single connection, with results in submission order.
+//!
+//! A [`ConnectionHandle`], obtained with
+//! [`Session::connection_for_statement`](crate::Session::connection_for_statement), pins
+//! a single connection of the session: the one to the node and shard which the load balancing
+//! policy picks first for a statement, usually a replica owning its partition. All statements
+//! added to a [`Pipeline`] of the handle are sent over that connection, in the order in which
+//! they were added, without waiting for the responses to the preceding ones. Their results are
+//! returned in the same order.
+//!
+//! The server processes the requests received over a connection concurrently, so the protocol
+//! doesn't guarantee that they are applied in the order in which they were sent. The order of
+//! writes can be enforced with [`Pipeline::set_ordered_timestamps`], which assigns increasing
+//! client-side timestamps to the statements, so that each write wins over the writes preceding it.
+//! This doesn't order reads, lightweight transactions and counter updates - statements which
+//! depend on the effects of the preceding ones have to be sent in a separate pipeline, after
+//! the results of the previous one are received.
+//!
+//! Even the order of sending is not kept for a prepared statement which the node has evicted
+//! from its cache: the node rejects it as unprepared, and the driver prepares it again and
+//! re-sends it after the statements following it in the pipeline. Ordered timestamps still
+//! apply such writes in order, as they are assigned before the pipeline is sent.
+//!
+//! Only the first page of the result of each statement is fetched, with the page size
+//! of the statement. If the result has more rows, its
+//! [`paging_state`](crate::QueryResult::paging_state) can be passed to
+//! [`Session::query_paged`](crate::Session::query_paged) or
+//! [`Session::execute_paged`](crate::Session::execute_paged) to fetch the following pages
+//! outside of the pipeline.
+//!
+//! Pipelined statements bypass the load balancing, retry, speculative execution and load
+//! shedding policies of the session, and are not counted in its metrics: the errors of each
+//! statement, including the ones caused by a broken connection, are returned as they are.
+//! The request timeout of the statement, or of its execution profile, applies to each
+//! statement separately. `USE` statements must not be pipelined, as they would change
+//! the keyspace of the pinned connection only.
+//!
+//! The default timestamps of the session's
+//! [`WriteDefaultsPolicy`](crate::transport::write_defaults::WriteDefaultsPolicy) are not
+//! applied to pipelined statements either: a write is sent with its own timestamp, if set,
+//! or with the one assigned by [`Pipeline::set_ordered_timestamps`], or without any.
+//! Statements prepared with
+//! [`Session::prepare_with_default_ttl`](crate::Session::prepare_with_default_ttl) keep
+//! the default TTL, which is a part of their text.
+//!
+//! # Example
+//! ```rust
+//! # use scylla::Session;
+//! # use std::error::Error;
+//! # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+//! let insert = session
+//!     .prepare("INSERT INTO ks.events (id, seq, body) VALUES (?, ?, ?)")
+//!     .await?;
+//!
+//! // All events of the partition are written over the connection to its replica
+//! let handle = session.connection_for_statement(&insert, (7_i32, 0_i32, "")).await?;
+//! let mut pipeline = handle.pipeline();
+//! pipeline.set_ordered_timestamps(true);
+//! for (seq, body) in ["created", "updated", "deleted"].into_iter().enumerate() {
+//!     pipeline.append_prepared(&insert, (7_i32, seq as i32, body))?;
+//! }
+//!
+//! for result in pipeline.run().await {
+//!     result?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use futures::future::join_all;
+use scylla_cql::types::serialize::row::{SerializeRow, SerializedValues};
+use scylla_cql::types::serialize::SerializationError;
+
+use crate::prepared_statement::PreparedStatement;
+use crate::query::Query;
+use crate::routing::Shard;
+use crate::statement::StatementConfig;
+use crate::transport::connection::{Connection, QueryResponse, RowsFormat};
+use crate::transport::errors::{QueryError, TimeoutError};
+use crate::transport::execution_profile::ExecutionProfileHandle;
+use crate::transport::node::Node;
+use crate::transport::query_result::QueryResult;
+use crate::transport::runtime::{self, Runtime};
+
+/// A single connection of the session, to a single node and shard.
+///
+/// See the [module documentation](self) for details.
+pub struct ConnectionHandle {
+    connection: Arc<Connection>,
+    node: Arc<Node>,
+    default_execution_profile_handle: ExecutionProfileHandle,
+    runtime: Arc<dyn Runtime>,
+    // The last client-side timestamp assigned by the pipelines of this handle
+    last_timestamp: AtomicI64,
+}
+
+impl ConnectionHandle {
+    pub(crate) fn new(
+        connection: Arc<Connection>,
+        node: Arc<Node>,
+        default_execution_profile_handle: ExecutionProfileHandle,
+        runtime: Arc<dyn Runtime>,
+    ) -> Self {
+        Self {
+            connection,
+            node,
+            default_execution_profile_handle,
+            runtime,
+            last_timestamp: AtomicI64::new(0),
+        }
+    }
+
+    /// Returns the node the connection leads to.
+    pub fn get_node(&self) -> &Arc<Node> {
+        &self.node
+    }
+
+    /// Returns the shard the connection leads to, if the node is sharded.
+    pub fn get_shard(&self) -> Option<Shard> {
+        self.connection
+            .get_shard_info()
+            .as_ref()
+            .map(|info| info.shard as Shard)
+    }
+
+    /// Creates an empty pipeline of statements to be sent over this connection.
+    pub fn pipeline(&self) -> Pipeline<'_> {
+        Pipeline {
+            handle: self,
+            statements: Vec::new(),
+            ordered_timestamps: false,
+        }
+    }
+
+    // Reserves `count` consecutive timestamps, greater than all the ones reserved before,
+    // and returns the first of them.
+    fn reserve_timestamps(&self, count: usize) -> i64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_micros() as i64);
+        reserve_timestamps(&self.last_timestamp, now, count)
+    }
+}
+
+impl fmt::Debug for ConnectionHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionHandle")
+            .field("node", &self.node.address)
+            .field("shard", &self.get_shard())
+            .finish()
+    }
+}
+
+fn reserve_timestamps(last_timestamp: &AtomicI64, now: i64, count: usize) -> i64 {
+    let count = count as i64;
+    let previous = last_timestamp
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |last| {
+            Some(now.max(last + 1) + count - 1)
+        })
+        .unwrap();
+    now.max(previous + 1)
+}
+
+enum PipelinedStatement {
+    Query(Query),
+    Prepared(PreparedStatement, SerializedValues),
+}
+
+impl PipelinedStatement {
+    fn config(&self) -> &StatementConfig {
+        match self {
+            PipelinedStatement::Query(query) => &query.config,
+            PipelinedStatement::Prepared(prepared, _) => &prepared.config,
+        }
+    }
+}
+
+/// A sequence of statements sent over a single connection, created with
+/// [`ConnectionHandle::pipeline`].
+///
+/// See the [module documentation](self) for details.
+pub struct Pipeline<'a> {
+    handle: &'a ConnectionHandle,
+    statements: Vec<PipelinedStatement>,
+    ordered_timestamps: bool,
+}
+
+impl Pipeline<'_> {
+    /// Appends an unprepared query, without bound values, to the pipeline.
+    pub fn append_query(&mut self, query: impl Into<Query>) -> &mut Self {
+        self.statements
+            .push(PipelinedStatement::Query(query.into()));
+        self
+    }
+
+    /// Appends a prepared statement with its bound values to the pipeline.
+    /// The values are serialized immediately.
+    pub fn append_prepared(
+        &mut self,
+        prepared: &PreparedStatement,
+        values: impl SerializeRow,
+    ) -> Result<&mut Self, SerializationError> {
+        let values = prepared.serialize_values(&values)?;
+        self.statements
+            .push(PipelinedStatement::Prepared(prepared.clone(), values));
+        Ok(self)
+    }
+
+    /// Enables or disables assigning increasing client-side timestamps to the statements
+    /// of the pipeline, in their order, on each run. The timestamps are greater than
+    /// the ones assigned by the previous runs of the pipelines of the same [`ConnectionHandle`].
+    /// Statements which have their own timestamp set keep it.
+    ///
+    /// Disabled by default.
+    pub fn set_ordered_timestamps(&mut self, ordered_timestamps: bool) -> &mut Self {
+        self.ordered_timestamps = ordered_timestamps;
+        self
+    }
+
+    /// Gets whether increasing client-side timestamps are assigned to the statements.
+    pub fn get_ordered_timestamps(&self) -> bool {
+        self.ordered_timestamps
+    }
+
+    /// Returns the number of statements in the pipeline.
+    pub fn len(&self) -> usize {
+        self.statements.len()
+    }
+
+    /// Checks if the pipeline has no statements.
+    pub fn is_empty(&self) -> bool {
+        self.statements.is_empty()
+    }
+
+    /// Sends all statements of the pipeline over the connection, in order, and returns
+    /// their results in the same order. Only the first page of the results is fetched.
+    ///
+    /// See the [module documentation](self) for the exceptions to the order of sending.
+    /// The statements are sent without the default timestamps of the session's
+    /// [`WriteDefaultsPolicy`](crate::transport::write_defaults::WriteDefaultsPolicy).
+    pub async fn run(&self) -> Vec<Result<QueryResult, QueryError>> {
+        let first_timestamp = self
+            .ordered_timestamps
+            .then(|| self.handle.reserve_timestamps(self.statements.len()));
+        let timestamp = |index: usize| first_timestamp.map(|first| first + index as i64);
+
+        // The requests are submitted to the connection when first polled,
+        // which `join_all` does in order.
+        let requests = self
+            .statements
+            .iter()
+            .enumerate()
+            .map(|(index, statement)| self.send(statement, timestamp(index)));
+        join_all(requests).await
+    }
+
+    async fn send(
+        &self,
+        statement: &PipelinedStatement,
+        timestamp: Option<i64>,
+    ) -> Result<QueryResult, QueryError> {
+        let config = statement.config();
+        let request_timeout = config.request_timeout.or_else(|| {
+            config
+                .execution_profile_handle
+                .as_ref()
+                .unwrap_or(&self.handle.default_execution_profile_handle)
+                .access()
+                .request_timeout
+        });
+        let request = self.send_request(statement, timestamp);
+        let result = match request_timeout {
+            Some(timeout) => runtime::timeout(&*self.handle.runtime, timeout, request)
+                .await
+                .unwrap_or_else(|e| {
+                    Err(TimeoutError::Request(format!(
+                        "Request took longer than {}ms: {}",
+                        timeout.as_millis(),
+                        e
+                    ))
+                    .into())
+                }),
+            None => request.await,
+        };
+        result.map_err(|error| error.with_node(self.handle.connection.get_connect_address()))
+    }
+
+    async fn send_request(
+        &self,
+        statement: &PipelinedStatement,
+        timestamp: Option<i64>,
+    ) -> Result<QueryResult, QueryError> {
+        let connection = &self.handle.connection;
+        let default_handle = &self.handle.default_execution_profile_handle;
+        match statement {
+            PipelinedStatement::Query(query) => {
+                let execution_profile = query
+                    .get_execution_profile_handle()
+                    .unwrap_or(default_handle)
+                    .access();
+                connection
+                    .query_with_consistency(
+                        query,
+                        query
+                            .config
+                            .determine_consistency(execution_profile.consistency),
+                        query
+                            .config
+                            .serial_consistency
+                            .unwrap_or(execution_profile.serial_consistency),
+                        None,
+                        timestamp,
+                        RowsFormat::Deserialized,
+                    )
+                    .await
+                    .and_then(QueryResponse::into_query_result)
+            }
+            PipelinedStatement::Prepared(prepared, values) => {
+                let execution_profile = prepared
+                    .get_execution_profile_handle()
+                    .unwrap_or(default_handle)
+                    .access();
+                connection
+                    .execute_with_consistency(
+                        prepared,
+                        values,
+                        prepared
+                            .config
+                            .determine_consistency(execution_profile.consistency),
+                        prepared
+                            .config
+                            .serial_consistency
+                            .unwrap_or(execution_profile.serial_consistency),
+                        None,
+                        timestamp,
+                        RowsFormat::Deserialized,
+                    )
+                    .await
+                    .and_then(QueryResponse::into_query_result)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicI64;
+
+    use super::reserve_timestamps;
+
+    #[test]
+    fn reserved_timestamps_increase() {
+        let last = AtomicI64::new(0);
+        assert_eq!(reserve_timestamps(&last, 100, 3), 100);
+        // The clock didn't move, or went back
+        assert_eq!(reserve_timestamps(&last, 100, 2), 103);
+        assert_eq!(reserve_timestamps(&last, 50, 1), 105);
+        assert_eq!(reserve_timestamps(&last, 1000, 0), 1000);
+        assert_eq!(reserve_timestamps(&last, 1000, 1), 1000);
+        assert_eq!(reserve_timestamps(&last, 2000, 1), 2000);
+    }
+}
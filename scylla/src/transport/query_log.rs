@@ -0,0 +1,489 @@
+//! Structured logging of the statements sent to the cluster.
+//!
+//! A [`QueryLogger`] set with [`QueryLog`] receives an entry for every attempt
+//! of executing a statement: its text, the number of bound values, the consistency,
+//! the node the attempt was sent to, the latency and the outcome.
+//!
+//! By default, the log doesn't contain any data: literals in statement texts are replaced
+//! with `?` and bound values are not included. Bound values can be included for
+//! prepared statements which operate on tables allowed with
+//! [`QueryLog::with_value_logging_for_table`].
+//!
+//! # Example
+//! ```rust
+//! # use scylla::{Session, SessionBuilder};
+//! # use std::error::Error;
+//! # async fn check_only_compiles() -> Result<(), Box<dyn Error>> {
+//! use scylla::transport::query_log::{QueryLog, QueryLogEntry, QueryLogger};
+//! use std::sync::Arc;
+//!
+//! #[derive(Debug)]
+//! struct PrintingLogger;
+//!
+//! impl QueryLogger for PrintingLogger {
+//!     fn log_query(&self, entry: &QueryLogEntry<'_>) {
+//!         println!(
+//!             "{} ({} values) on {} took {:?}: {}",
+//!             entry.statement,
+//!             entry.bound_values_count,
+//!             entry.node,
+//!             entry.latency,
+//!             if entry.outcome.is_ok() { "ok" } else { "error" }
+//!         );
+//!     }
+//! }
+//!
+//! let session: Session = SessionBuilder::new()
+//!     .known_node("127.0.0.1:9042")
+//!     .query_log(QueryLog::new(Arc::new(PrintingLogger)))
+//!     .build()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use scylla_cql::cql_lexer::{self, TokenKind};
+use scylla_cql::errors::QueryError;
+use scylla_cql::frame::response::result::{deser_cql_value, CqlValue};
+use scylla_cql::frame::types::RawValue;
+use scylla_cql::types::serialize::row::SerializedValues;
+use scylla_cql::Consistency;
+
+use crate::batch::{Batch, BatchStatement};
+use crate::prepared_statement::PreparedStatement;
+
+/// Receives the entries of a [`QueryLog`].
+pub trait QueryLogger: Debug + Send + Sync {
+    /// Called after every attempt of executing a statement, including retries
+    /// and speculative executions, as well as fetching each page of paged queries.
+    ///
+    /// It is called by the task executing the statement, so it should not block.
+    fn log_query(&self, entry: &QueryLogEntry<'_>);
+}
+
+/// The kind of the logged statement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StatementKind {
+    Unprepared,
+    Prepared,
+    Batch,
+}
+
+/// A single attempt of executing a statement.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct QueryLogEntry<'a> {
+    pub kind: StatementKind,
+    /// Text of the statement, with literals replaced with `?` and comments removed unless statement redaction is disabled.
+    /// For batches, texts of the statements are separated with `; `.
+    pub statement: &'a str,
+    /// Number of values bound to the statement (for batches, to all of its statements).
+    pub bound_values_count: usize,
+    /// Values bound to the statement, included only for prepared statements
+    /// operating on tables with enabled value logging.
+    pub values: Option<&'a [LoggedValue]>,
+    pub consistency: Consistency,
+    /// Address of the node the attempt was sent to.
+    pub node: SocketAddr,
+    pub latency: Duration,
+    pub outcome: Result<(), &'a QueryError>,
+}
+
+/// A value bound to a logged statement.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum LoggedValue {
+    Null,
+    Unset,
+    Value(CqlValue),
+    /// The value doesn't match the type of its bind marker.
+    Undecodable,
+}
+
+/// Configuration of structured statement logging.
+#[derive(Debug, Clone)]
+pub struct QueryLog {
+    logger: Arc<dyn QueryLogger>,
+    redact_statements: bool,
+    value_logging_tables: HashSet<(String, String)>,
+}
+
+impl QueryLog {
+    /// Creates a log which passes entries to the given logger,
+    /// with redacted statements and without bound values.
+    pub fn new(logger: Arc<dyn QueryLogger>) -> Self {
+        Self {
+            logger,
+            redact_statements: true,
+            value_logging_tables: HashSet::new(),
+        }
+    }
+
+    /// Sets whether literals in statement texts are replaced with `?`. Enabled by default.
+    pub fn with_statement_redaction(mut self, redact: bool) -> Self {
+        self.redact_statements = redact;
+        self
+    }
+
+    /// Enables logging of values bound to prepared statements which operate on the given table.
+    pub fn with_value_logging_for_table(
+        mut self,
+        keyspace: impl Into<String>,
+        table: impl Into<String>,
+    ) -> Self {
+        self.value_logging_tables
+            .insert((keyspace.into(), table.into()));
+        self
+    }
+
+    fn statement_text(&self, statement: &str) -> String {
+        if self.redact_statements {
+            redact_statement(statement)
+        } else {
+            statement.to_owned()
+        }
+    }
+
+    pub(crate) fn unprepared(&self, contents: &str) -> LoggedRequest<'_> {
+        LoggedRequest {
+            log: self,
+            kind: StatementKind::Unprepared,
+            statement: self.statement_text(contents),
+            bound_values_count: count_bind_markers(contents),
+            values: None,
+        }
+    }
+
+    pub(crate) fn prepared(
+        &self,
+        prepared: &PreparedStatement,
+        values: &SerializedValues,
+    ) -> LoggedRequest<'_> {
+        let bound_values_count = values.element_count() as usize;
+        let log_values = match prepared.get_table_spec() {
+            Some(table) => self
+                .value_logging_tables
+                .contains(&(table.ks_name().to_owned(), table.table_name().to_owned())),
+            None => false,
+        };
+        let values = log_values.then(|| {
+            values
+                .iter()
+                .zip(prepared.get_variable_col_specs())
+                .map(|(value, spec)| match value {
+                    RawValue::Null => LoggedValue::Null,
+                    RawValue::Unset => LoggedValue::Unset,
+                    RawValue::Value(mut bytes) => deser_cql_value(&spec.typ, &mut bytes)
+                        .map_or(LoggedValue::Undecodable, LoggedValue::Value),
+                })
+                .collect()
+        });
+        LoggedRequest {
+            log: self,
+            kind: StatementKind::Prepared,
+            statement: self.statement_text(prepared.get_statement()),
+            bound_values_count,
+            values,
+        }
+    }
+
+    pub(crate) fn batch(&self, batch: &Batch) -> LoggedRequest<'_> {
+        let mut statements = Vec::with_capacity(batch.statements.len());
+        let mut bound_values_count = 0;
+        for statement in &batch.statements {
+            let text = match statement {
+                BatchStatement::Query(query) => {
+                    bound_values_count += count_bind_markers(&query.contents);
+                    &query.contents
+                }
+                BatchStatement::PreparedStatement(prepared) => {
+                    bound_values_count += prepared.get_variable_col_specs().len();
+                    prepared.get_statement()
+                }
+            };
+            statements.push(self.statement_text(text));
+        }
+        LoggedRequest {
+            log: self,
+            kind: StatementKind::Batch,
+            statement: statements.join("; "),
+            bound_values_count,
+            values: None,
+        }
+    }
+}
+
+/// The parts of the log entries which are the same for all attempts of a request.
+pub(crate) struct LoggedRequest<'a> {
+    log: &'a QueryLog,
+    kind: StatementKind,
+    statement: String,
+    bound_values_count: usize,
+    values: Option<Vec<LoggedValue>>,
+}
+
+impl LoggedRequest<'_> {
+    pub(crate) fn log_attempt(
+        &self,
+        consistency: Consistency,
+        node: SocketAddr,
+        latency: Duration,
+        outcome: Result<(), &QueryError>,
+    ) {
+        self.log.logger.log_query(&QueryLogEntry {
+            kind: self.kind,
+            statement: &self.statement,
+            bound_values_count: self.bound_values_count,
+            values: self.values.as_deref(),
+            consistency,
+            node,
+            latency,
+            outcome,
+        });
+    }
+}
+
+/// Replaces literals (strings, numbers, blobs, UUIDs, durations and booleans)
+/// in the text of a CQL statement with `?` and removes its comments.
+///
+/// Identifiers and keywords are left as they are.
+pub fn redact_statement(statement: &str) -> String {
+    let mut redacted = String::with_capacity(statement.len());
+    scan_statement(statement, |token| match token {
+        Token::Literal => redacted.push('?'),
+        Token::Comment => {}
+        Token::Text(text) => redacted.push_str(text),
+    });
+    redacted
+}
+
+/// Counts the bind markers (`?` and `:name`) in the text of a CQL statement.
+fn count_bind_markers(statement: &str) -> usize {
+    let mut count = 0;
+    let mut previous_colon = false;
+    scan_statement(statement, |token| {
+        if let Token::Text(text) = token {
+            if text == "?"
+                || (previous_colon
+                    && text.starts_with(|c: char| c.is_alphabetic() || c == '_' || c == '"'))
+            {
+                count += 1;
+            }
+            previous_colon = text == ":";
+        } else {
+            previous_colon = false;
+        }
+    });
+    count
+}
+
+enum Token<'a> {
+    Literal,
+    Comment,
+    Text(&'a str),
+}
+
+// Splits the statement into literals, comments and pieces of text, the latter comprising
+// whole words, quoted identifiers and single characters.
+fn scan_statement<'a>(statement: &'a str, mut emit: impl FnMut(Token<'a>)) {
+    let bytes = statement.as_bytes();
+    let mut i = 0;
+    // The last character which is not whitespace nor part of a comment.
+    let mut previous = None;
+    while let Some(token) = cql_lexer::next_token(&statement[i..]) {
+        let c = bytes[i];
+        let start = i;
+        let follows_operator = match previous {
+            Some(p) => b"=<>(,[{:+-*/".contains(&p),
+            None => true,
+        };
+        // Unterminated tokens extend to the end of the statement, which is enough here.
+        let is_literal = match token.kind {
+            TokenKind::Whitespace => {
+                i += token.len;
+                emit(Token::Text(&statement[start..i]));
+                continue;
+            }
+            TokenKind::Comment => {
+                i += token.len;
+                emit(Token::Comment);
+                continue;
+            }
+            TokenKind::String | TokenKind::QuotedIdentifier => {
+                i += token.len;
+                token.kind == TokenKind::String
+            }
+            TokenKind::Symbol
+                if c == b'-'
+                    && follows_operator
+                    && matches!(bytes.get(i + 1), Some(b) if b.is_ascii_digit()) =>
+            {
+                i = end_of_word(bytes, i + 1);
+                true
+            }
+            TokenKind::Word => {
+                if let Some(len) = alternative_iso_duration_len(&bytes[start..]) {
+                    i = start + len;
+                    previous = Some(bytes[i - 1]);
+                    emit(Token::Literal);
+                    continue;
+                }
+                i = end_of_word(bytes, i);
+                let word = &statement[start..i];
+                c.is_ascii_digit()
+                    || is_uuid(word)
+                    || is_iso_duration(word)
+                    || ["true", "false", "nan", "infinity"]
+                        .iter()
+                        .any(|literal| word.eq_ignore_ascii_case(literal))
+            }
+            TokenKind::Symbol => {
+                i += token.len;
+                false
+            }
+        };
+        previous = Some(bytes[i - 1]);
+        if is_literal {
+            emit(Token::Literal);
+        } else {
+            emit(Token::Text(&statement[start..i]));
+        }
+    }
+}
+
+// Words include UUIDs (which contain dashes), floating point numbers and exponents.
+fn end_of_word(bytes: &[u8], start: usize) -> usize {
+    let is_word_byte = |b: u8| b.is_ascii_alphanumeric() || b == b'_' || b >= 0x80;
+    let is_number = bytes[start].is_ascii_digit();
+    let mut i = start;
+    while i < bytes.len() {
+        let b = bytes[i];
+        let continues_word = is_word_byte(b)
+            || (b == b'.' && is_number)
+            || ((b == b'+' || b == b'-')
+                && is_number
+                && matches!(bytes[i - 1], b'e' | b'E')
+                && !bytes[start..i].starts_with(b"0x"))
+            || (b == b'-' && is_uuid_prefix(&bytes[start..]));
+        if !continues_word {
+            break;
+        }
+        i += 1;
+    }
+    i
+}
+
+fn is_uuid_prefix(bytes: &[u8]) -> bool {
+    bytes.len() >= 36 && is_uuid_bytes(&bytes[..36])
+}
+
+fn is_uuid(word: &str) -> bool {
+    word.len() == 36 && is_uuid_bytes(word.as_bytes())
+}
+
+fn is_uuid_bytes(bytes: &[u8]) -> bool {
+    bytes.iter().enumerate().all(|(i, b)| match i {
+        8 | 13 | 18 | 23 => *b == b'-',
+        _ => b.is_ascii_hexdigit(),
+    })
+}
+
+// ISO 8601 durations in the format with designators, e.g. `P1Y2M`, `PT3H` or `P2W`.
+fn is_iso_duration(word: &str) -> bool {
+    let bytes = word.as_bytes();
+    if bytes.len() < 3 || !bytes[0].eq_ignore_ascii_case(&b'P') {
+        return false;
+    }
+    let mut in_time_part = false;
+    let mut digits = 0;
+    for (i, b) in bytes.iter().enumerate().skip(1) {
+        match b.to_ascii_uppercase() {
+            b'0'..=b'9' => digits += 1,
+            b'T' if !in_time_part && digits == 0 && i + 1 < bytes.len() => in_time_part = true,
+            b'Y' | b'W' | b'D' if !in_time_part && digits > 0 => digits = 0,
+            b'H' | b'S' if in_time_part && digits > 0 => digits = 0,
+            b'M' if digits > 0 => digits = 0,
+            _ => return false,
+        }
+    }
+    digits == 0
+}
+
+// Length of an ISO 8601 duration in the alternative format, `P0001-02-03T04:05:06`,
+// at the start of `bytes`.
+fn alternative_iso_duration_len(bytes: &[u8]) -> Option<usize> {
+    const PATTERN: &[u8] = b"P0000-00-00T00:00:00";
+    let matches = bytes.len() >= PATTERN.len()
+        && PATTERN.iter().zip(bytes).all(|(p, b)| match p {
+            b'0' => b.is_ascii_digit(),
+            p => p.eq_ignore_ascii_case(b),
+        })
+        && !matches!(bytes.get(PATTERN.len()), Some(b) if b.is_ascii_alphanumeric() || *b == b'_');
+    matches.then_some(PATTERN.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{count_bind_markers, redact_statement};
+
+    #[test]
+    fn literals_are_redacted() {
+        let cases = [
+            (
+                "INSERT INTO ks.t (a, b, \"C\") VALUES ('it''s', 42, -1.5e-3)",
+                "INSERT INTO ks.t (a, b, \"C\") VALUES (?, ?, ?)",
+            ),
+            (
+                "SELECT * FROM t WHERE id = 123e4567-e89b-12d3-a456-426614174000 AND c > 0xcafe",
+                "SELECT * FROM t WHERE id = ? AND c > ?",
+            ),
+            (
+                "UPDATE t USING TTL 86400 SET flag = TRUE, d = 1h30m, f = $$raw$$ WHERE k = ?",
+                "UPDATE t USING TTL ? SET flag = ?, d = ?, f = ? WHERE k = ?",
+            ),
+            (
+                "SELECT v1, col_2 FROM t -- 'comment'\nWHERE k IN (1, 2) /* k = 3 */",
+                "SELECT v1, col_2 FROM t \nWHERE k IN (?, ?) ",
+            ),
+            (
+                "UPDATE t SET a = PT3H, b = P1Y2M, c = p2w, d = P0001-02-03T04:05:06 WHERE pt = ?",
+                "UPDATE t SET a = ?, b = ?, c = ?, d = ? WHERE pt = ?",
+            ),
+            (
+                "SELECT * FROM t WHERE k = :key AND c = 'unterminated",
+                "SELECT * FROM t WHERE k = :key AND c = ?",
+            ),
+        ];
+        for (statement, expected) in cases {
+            assert_eq!(redact_statement(statement), expected);
+        }
+    }
+
+    #[test]
+    fn bind_markers_are_counted() {
+        assert_eq!(count_bind_markers("SELECT * FROM t"), 0);
+        assert_eq!(
+            count_bind_markers("INSERT INTO t (a, b) VALUES (?, '?') USING TTL ?"),
+            2
+        );
+        assert_eq!(
+            count_bind_markers("SELECT * FROM t WHERE a = :a AND b = :\"B\" AND c = ?"),
+            3
+        );
+        assert_eq!(
+            count_bind_markers("SELECT * FROM t WHERE a = :a_1 AND b = :b"),
+            2
+        );
+        assert_eq!(
+            count_bind_markers("SELECT * FROM t WHERE a = ? -- AND b = ?\n"),
+            1
+        );
+    }
+}
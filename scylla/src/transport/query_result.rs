@@ -1,8 +1,14 @@
 use crate::frame::response::cql_to_rust::{FromRow, FromRowError};
 use crate::frame::response::result::ColumnSpec;
+use crate::frame::response::result::CqlValue;
 use crate::frame::response::result::Row;
-use crate::transport::session::{IntoTypedRows, TypedRowIter};
+use crate::tracing::TracingInfo;
+use crate::transport::errors::QueryError;
+use crate::transport::execution_info::ExecutionInfo;
+use crate::transport::session::{IntoTypedRows, Session, TypedRowIter};
 use bytes::Bytes;
+use std::collections::HashMap;
+use std::sync::Arc;
 use thiserror::Error;
 use uuid::Uuid;
 
@@ -25,6 +31,11 @@ pub struct QueryResult {
     pub col_specs: Vec<ColumnSpec>,
     /// The original size of the serialized rows in request
     pub serialized_size: usize,
+    /// Custom payload returned from the server, if the server attached one to the response
+    pub custom_payload: Option<HashMap<String, Vec<u8>>>,
+    /// Attempts, retries and speculative executions of the request - can only be Some if
+    /// [collecting execution info](crate::query::Query::set_collect_execution_info) is enabled
+    pub execution_info: Option<ExecutionInfo>,
 }
 
 impl QueryResult {
@@ -54,6 +65,18 @@ impl QueryResult {
         Ok(self.rows()?.into_typed())
     }
 
+    /// Returns the received rows as [`DynamicRow`]s, which allow to access the values
+    /// by column name, together with the column specifications.\
+    /// Fails when the query isn't of a type that could return rows, same as [`rows()`](QueryResult::rows).
+    pub fn rows_dynamic(self) -> Result<Vec<DynamicRow>, RowsExpectedError> {
+        let rows = self.rows.ok_or(RowsExpectedError)?;
+        let col_specs: Arc<[ColumnSpec]> = self.col_specs.into();
+        Ok(rows
+            .into_iter()
+            .map(|row| DynamicRow::new(col_specs.clone(), row))
+            .collect())
+    }
+
     /// Returns `Ok` for a result of a query that shouldn't contain any rows.\
     /// Will return `Ok` for `INSERT` result, but a `SELECT` result, even an empty one, will cause an error.\
     /// Opposite of [`rows()`](QueryResult::rows).
@@ -134,6 +157,126 @@ impl QueryResult {
             .enumerate()
             .find(|(_id, spec)| spec.name == name)
     }
+
+    /// Fetches [`TracingInfo`] of the query that produced this result.\
+    /// Returns `Ok(None)` if tracing was not enabled for the query.
+    ///
+    /// Tracing information is written to `system_traces` asynchronously by the server,
+    /// so the driver retries the fetch as configured by
+    /// [`SessionBuilder::tracing_info_fetch_attempts`](crate::transport::session_builder::GenericSessionBuilder::tracing_info_fetch_attempts),
+    /// [`tracing_info_fetch_interval`](crate::transport::session_builder::GenericSessionBuilder::tracing_info_fetch_interval)
+    /// and [`tracing_info_fetch_consistency`](crate::transport::session_builder::GenericSessionBuilder::tracing_info_fetch_consistency).
+    /// See [`Session::get_tracing_info`].
+    pub async fn fetch_tracing_info(
+        &self,
+        session: &Session,
+    ) -> Result<Option<TracingInfo>, QueryError> {
+        match self.tracing_id {
+            Some(tracing_id) => session.get_tracing_info(&tracing_id).await.map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// A row whose values can be accessed by column name, for rows of a schema which isn't known
+/// at compile time.\
+/// Returned by [`QueryResult::rows_dynamic`] and
+/// [`DynamicRowIterator`](crate::transport::iterator::DynamicRowIterator).
+///
+/// The column specifications are shared by all rows of a result.
+///
+/// # Example
+/// ```rust
+/// # use scylla::Session;
+/// # use std::error::Error;
+/// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+/// let rows = session
+///     .query("SELECT * FROM ks.tab", &[])
+///     .await?
+///     .rows_dynamic()?;
+///
+/// for row in rows {
+///     for (spec, value) in row.iter() {
+///         println!("{} ({:?}): {:?}", spec.name, spec.typ, value);
+///     }
+///     let id = row.get("id");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct DynamicRow {
+    col_specs: Arc<[ColumnSpec]>,
+    columns: Vec<Option<CqlValue>>,
+}
+
+impl DynamicRow {
+    // The row must have exactly one value per column specification,
+    // which the server guarantees for the rows of a result.
+    pub(crate) fn new(col_specs: Arc<[ColumnSpec]>, row: Row) -> Self {
+        debug_assert_eq!(col_specs.len(), row.columns.len());
+        Self {
+            col_specs,
+            columns: row.columns,
+        }
+    }
+
+    /// Returns the value of the column with given name.\
+    /// Returns `None` if the value is null, or if there is no such column.
+    pub fn get(&self, name: &str) -> Option<&CqlValue> {
+        self.get_column(name).and_then(|(_spec, value)| value)
+    }
+
+    /// Returns the specification and the value of the column with given name,
+    /// or `None` if there is no such column. The value is `None` if it is null.
+    pub fn get_column(&self, name: &str) -> Option<(&ColumnSpec, Option<&CqlValue>)> {
+        self.iter().find(|(spec, _value)| spec.name == name)
+    }
+
+    /// Returns specification of row columns
+    pub fn get_column_specs(&self) -> &[ColumnSpec] {
+        &self.col_specs
+    }
+
+    /// Returns the values of the row, in the order of the columns.
+    pub fn values(&self) -> &[Option<CqlValue>] {
+        &self.columns
+    }
+
+    /// Returns an iterator over the column specifications with their values, in the order
+    /// of the columns.
+    pub fn iter(&self) -> impl Iterator<Item = (&ColumnSpec, Option<&CqlValue>)> {
+        self.col_specs
+            .iter()
+            .zip(self.columns.iter().map(Option::as_ref))
+    }
+
+    /// Returns the number of columns.
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// Checks if the row has no columns.
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    /// Converts the row into a map from column names to values.
+    /// Null values are kept as `None`.
+    pub fn into_map(self) -> HashMap<String, Option<CqlValue>> {
+        self.col_specs
+            .iter()
+            .map(|spec| spec.name.clone())
+            .zip(self.columns)
+            .collect()
+    }
+
+    /// Converts the row into a plain [`Row`], dropping the column specifications.
+    pub fn into_row(self) -> Row {
+        Row {
+            columns: self.columns,
+        }
+    }
 }
 
 /// [`QueryResult::rows()`](QueryResult::rows) or a similar function called on a bad QueryResult.\
@@ -310,6 +453,8 @@ mod tests {
             paging_state: None,
             col_specs: vec![column_spec],
             serialized_size: 0,
+            custom_payload: None,
+            execution_info: None,
         }
     }
 
@@ -377,6 +522,47 @@ mod tests {
         assert_eq!(rows2, vec![(0,), (1,)]);
     }
 
+    #[test]
+    fn rows_dynamic_test() {
+        setup_tracing();
+        assert_eq!(
+            make_not_rows_query_result().rows_dynamic(),
+            Err(RowsExpectedError)
+        );
+        assert_eq!(make_rows_query_result(0).rows_dynamic(), Ok(vec![]));
+
+        let rows = make_rows_query_result(2).rows_dynamic().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].len(), 1);
+        assert_eq!(rows[1].get("column0"), Some(&CqlValue::Int(1)));
+        assert_eq!(rows[1].get("column1"), None);
+
+        let (spec, value) = rows[0].get_column("column0").unwrap();
+        assert_eq!(spec.typ, ColumnType::Int);
+        assert_eq!(value, Some(&CqlValue::Int(0)));
+        assert_eq!(rows[0].get_column_specs()[0].name, "column0");
+
+        let map = rows[1].clone().into_map();
+        assert_eq!(map.len(), 1);
+        assert_eq!(map["column0"], Some(CqlValue::Int(1)));
+        assert_eq!(rows[1].clone().into_row(), make_rows(2)[1]);
+    }
+
+    #[test]
+    fn dynamic_row_null_test() {
+        setup_tracing();
+        let mut result = make_not_rows_query_result();
+        result.rows = Some(vec![Row {
+            columns: vec![None],
+        }]);
+
+        let row = result.rows_dynamic().unwrap().remove(0);
+        assert_eq!(row.get("column0"), None);
+        assert_matches!(row.get_column("column0"), Some((_, None)));
+        assert_eq!(row.iter().count(), 1);
+        assert_eq!(row.into_map().get("column0"), Some(&None));
+    }
+
     #[test]
     fn result_not_rows_test() {
         setup_tracing();
@@ -2,8 +2,12 @@
 //! To decide when to retry a query the `Session` can use any object which implements
 //! the `RetryPolicy` trait
 
+use std::time::Duration;
+
+use rand::Rng;
+
 use crate::frame::types::Consistency;
-use crate::transport::errors::{DbError, QueryError, WriteType};
+use crate::transport::errors::{ConnectionError, DbError, QueryError, WriteType};
 
 /// Information about a failed query
 pub struct QueryInfo<'a> {
@@ -46,6 +50,13 @@ pub trait RetrySession: Send + Sync {
     /// Called after the query failed - decide what to do next
     fn decide_should_retry(&mut self, query_info: QueryInfo) -> RetryDecision;
 
+    /// Returns how long to wait before the retry decided by the last call to
+    /// [`decide_should_retry`](Self::decide_should_retry). Not called after other decisions.
+    /// By default retries are made immediately.
+    fn retry_delay(&self) -> Duration {
+        Duration::ZERO
+    }
+
     /// Reset before using for a new query
     fn reset(&mut self);
 }
@@ -142,10 +153,22 @@ impl RetrySession for DefaultRetrySession {
         match query_info.error {
             // Basic errors - there are some problems on this node
             // Retry on a different one if possible
-            QueryError::IoError(_)
-            | QueryError::DbError(DbError::Overloaded, _)
-            | QueryError::DbError(DbError::ServerError, _)
-            | QueryError::DbError(DbError::TruncateError, _) => {
+            QueryError::ConnectionError {
+                error: ConnectionError::IoError(_),
+                ..
+            }
+            | QueryError::DbError {
+                error: DbError::Overloaded,
+                ..
+            }
+            | QueryError::DbError {
+                error: DbError::ServerError,
+                ..
+            }
+            | QueryError::DbError {
+                error: DbError::TruncateError,
+                ..
+            } => {
                 if query_info.is_idempotent {
                     RetryDecision::RetryNextNode(None)
                 } else {
@@ -157,7 +180,10 @@ impl RetrySession for DefaultRetrySession {
             // Maybe this node has network problems - try a different one.
             // Perform at most one retry - it's unlikely that two nodes
             // have network problems at the same time
-            QueryError::DbError(DbError::Unavailable { .. }, _) => {
+            QueryError::DbError {
+                error: DbError::Unavailable { .. },
+                ..
+            } => {
                 if !self.was_unavailable_retry {
                     self.was_unavailable_retry = true;
                     RetryDecision::RetryNextNode(None)
@@ -171,15 +197,16 @@ impl RetrySession for DefaultRetrySession {
             // This happens when the coordinator picked replicas that were overloaded/dying.
             // Retried request should have some useful response because the node will detect
             // that these replicas are dead.
-            QueryError::DbError(
-                DbError::ReadTimeout {
-                    received,
-                    required,
-                    data_present,
-                    ..
-                },
-                _,
-            ) => {
+            QueryError::DbError {
+                error:
+                    DbError::ReadTimeout {
+                        received,
+                        required,
+                        data_present,
+                        ..
+                    },
+                ..
+            } => {
                 if !self.was_read_timeout_retry && received >= required && !*data_present {
                     self.was_read_timeout_retry = true;
                     RetryDecision::RetrySameNode(None)
@@ -191,7 +218,10 @@ impl RetrySession for DefaultRetrySession {
             // Retry at most once and only for BatchLog write.
             // Coordinator probably didn't detect the nodes as dead.
             // By the time we retry they should be detected as dead.
-            QueryError::DbError(DbError::WriteTimeout { write_type, .. }, _) => {
+            QueryError::DbError {
+                error: DbError::WriteTimeout { write_type, .. },
+                ..
+            } => {
                 if !self.was_write_timeout_retry
                     && query_info.is_idempotent
                     && *write_type == WriteType::BatchLog
@@ -203,9 +233,15 @@ impl RetrySession for DefaultRetrySession {
                 }
             }
             // The node is still bootstrapping it can't execute the query, we should try another one
-            QueryError::DbError(DbError::IsBootstrapping, _) => RetryDecision::RetryNextNode(None),
+            QueryError::DbError {
+                error: DbError::IsBootstrapping,
+                ..
+            } => RetryDecision::RetryNextNode(None),
             // Connection to the contacted node is overloaded, try another one
-            QueryError::UnableToAllocStreamId => RetryDecision::RetryNextNode(None),
+            QueryError::ConnectionError {
+                error: ConnectionError::UnableToAllocStreamId,
+                ..
+            } => RetryDecision::RetryNextNode(None),
             // In all other cases propagate the error to the user
             _ => RetryDecision::DontRetry,
         }
@@ -216,15 +252,234 @@ impl RetrySession for DefaultRetrySession {
     }
 }
 
+/// Retries with exponentially growing delays between the attempts, so that retries
+/// don't add much load to an already overloaded cluster.
+///
+/// Retries the errors after which a retry has a chance to succeed: on the next node after
+/// connection errors, overloaded or bootstrapping nodes and unavailable replicas, and on the
+/// same node after read and write timeouts. Like with [`DefaultRetryPolicy`], errors which
+/// might have happened after the query was applied are retried only for idempotent queries,
+/// and queries with serial consistency are never retried.
+///
+/// The delay before the `n`-th retry (counting from 0) is `base_delay * multiplier^n`, capped
+/// at `max_delay`. With jitter enabled (the default), the actual delay is uniformly random
+/// between zero and that value, which spreads the retries of many clients over time.
+///
+/// # Example
+/// ```
+/// # use scylla::transport::ExecutionProfile;
+/// # use scylla::transport::retry_policy::BackoffRetryPolicy;
+/// # use std::time::Duration;
+/// let policy = BackoffRetryPolicy::new()
+///     .with_base_delay(Duration::from_millis(50))
+///     .with_max_delay(Duration::from_secs(2))
+///     .with_max_attempts(5);
+///
+/// let handle = ExecutionProfile::builder()
+///     .retry_policy(Box::new(policy))
+///     .build()
+///     .into_handle();
+/// ```
+#[derive(Debug, Clone)]
+pub struct BackoffRetryPolicy {
+    base_delay: Duration,
+    multiplier: f64,
+    max_delay: Duration,
+    max_attempts: usize,
+    jitter: bool,
+}
+
+impl BackoffRetryPolicy {
+    /// Creates a policy with a base delay of 100 milliseconds, multiplier of 2, maximal delay
+    /// of 10 seconds, at most 3 attempts (the original one and 2 retries) and jitter enabled.
+    pub fn new() -> BackoffRetryPolicy {
+        BackoffRetryPolicy {
+            base_delay: Duration::from_millis(100),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(10),
+            max_attempts: 3,
+            jitter: true,
+        }
+    }
+
+    /// Sets the delay before the first retry.
+    pub fn with_base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// Sets the factor by which the delay grows with each retry.
+    ///
+    /// A multiplier lower than 1 or NaN is clamped to 1, which keeps the delay constant.
+    /// An infinite multiplier makes every retry after the first one wait `max_delay`.
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        // `f64::max` returns the other operand if one of them is NaN
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// Sets the maximal delay between the attempts.
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Sets the maximal number of attempts of a query, including the original one.
+    /// With 1, queries are never retried.
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Enables or disables full jitter - randomizing each delay between zero and its computed value.
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    // The delay before the retry with the given index, before applying the jitter
+    fn backoff(&self, retry: usize) -> Duration {
+        if self.base_delay.is_zero() {
+            return Duration::ZERO;
+        }
+        let exponent = i32::try_from(retry).unwrap_or(i32::MAX);
+        let delay = self.base_delay.as_secs_f64() * self.multiplier.powi(exponent);
+        // Converted before clamping, as `max_delay` may not survive a round trip through f64
+        Duration::try_from_secs_f64(delay).map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
+}
+
+impl Default for BackoffRetryPolicy {
+    fn default() -> BackoffRetryPolicy {
+        BackoffRetryPolicy::new()
+    }
+}
+
+impl RetryPolicy for BackoffRetryPolicy {
+    fn new_session(&self) -> Box<dyn RetrySession> {
+        Box::new(BackoffRetrySession::new(self.clone()))
+    }
+
+    fn clone_boxed(&self) -> Box<dyn RetryPolicy> {
+        Box::new(self.clone())
+    }
+}
+
+pub struct BackoffRetrySession {
+    policy: BackoffRetryPolicy,
+    retries: usize,
+    delay: Duration,
+}
+
+impl BackoffRetrySession {
+    pub fn new(policy: BackoffRetryPolicy) -> BackoffRetrySession {
+        BackoffRetrySession {
+            policy,
+            retries: 0,
+            delay: Duration::ZERO,
+        }
+    }
+
+    fn decide(query_info: &QueryInfo) -> RetryDecision {
+        if query_info.consistency.is_serial() {
+            return RetryDecision::DontRetry;
+        }
+        match query_info.error {
+            // The query might have been applied, retry only idempotent ones
+            QueryError::ConnectionError {
+                error: ConnectionError::IoError(_),
+                ..
+            }
+            | QueryError::DbError {
+                error: DbError::Overloaded,
+                ..
+            }
+            | QueryError::DbError {
+                error: DbError::ServerError,
+                ..
+            }
+            | QueryError::DbError {
+                error: DbError::TruncateError,
+                ..
+            } => {
+                if query_info.is_idempotent {
+                    RetryDecision::RetryNextNode(None)
+                } else {
+                    RetryDecision::DontRetry
+                }
+            }
+            QueryError::DbError {
+                error: DbError::ReadTimeout { .. },
+                ..
+            }
+            | QueryError::DbError {
+                error: DbError::WriteTimeout { .. },
+                ..
+            } => {
+                if query_info.is_idempotent {
+                    RetryDecision::RetrySameNode(None)
+                } else {
+                    RetryDecision::DontRetry
+                }
+            }
+            // The query was not applied
+            QueryError::DbError {
+                error: DbError::Unavailable { .. },
+                ..
+            }
+            | QueryError::DbError {
+                error: DbError::IsBootstrapping,
+                ..
+            }
+            | QueryError::ConnectionError {
+                error: ConnectionError::UnableToAllocStreamId,
+                ..
+            } => RetryDecision::RetryNextNode(None),
+            _ => RetryDecision::DontRetry,
+        }
+    }
+}
+
+impl RetrySession for BackoffRetrySession {
+    fn decide_should_retry(&mut self, query_info: QueryInfo) -> RetryDecision {
+        if self.retries + 1 >= self.policy.max_attempts {
+            return RetryDecision::DontRetry;
+        }
+        let decision = Self::decide(&query_info);
+        if decision != RetryDecision::DontRetry {
+            let backoff = self.policy.backoff(self.retries);
+            self.delay = if self.policy.jitter {
+                Duration::try_from_secs_f64(backoff.as_secs_f64() * rand::thread_rng().gen::<f64>())
+                    .map_or(backoff, |delay| delay.min(backoff))
+            } else {
+                backoff
+            };
+            self.retries += 1;
+        }
+        decision
+    }
+
+    fn retry_delay(&self) -> Duration {
+        self.delay
+    }
+
+    fn reset(&mut self) {
+        self.retries = 0;
+        self.delay = Duration::ZERO;
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{DefaultRetryPolicy, QueryInfo, RetryDecision, RetryPolicy};
+    use super::{BackoffRetryPolicy, DefaultRetryPolicy, QueryInfo, RetryDecision, RetryPolicy};
     use crate::statement::Consistency;
     use crate::test_utils::setup_tracing;
-    use crate::transport::errors::{BadQuery, DbError, QueryError, WriteType};
+    use crate::transport::errors::{
+        BadQuery, ConnectionError, DbError, OperationType, QueryError, WriteType,
+    };
     use bytes::Bytes;
     use std::io::ErrorKind;
-    use std::sync::Arc;
+    use std::time::Duration;
 
     fn make_query_info(error: &QueryError, is_idempotent: bool) -> QueryInfo<'_> {
         QueryInfo {
@@ -272,6 +527,7 @@ mod tests {
                 received: 2,
                 required: 1,
                 numfailures: 1,
+                failure_reasons: None,
                 data_present: false,
             },
             DbError::WriteFailure {
@@ -279,17 +535,26 @@ mod tests {
                 received: 1,
                 required: 2,
                 numfailures: 1,
+                failure_reasons: None,
                 write_type: WriteType::BatchLog,
             },
             DbError::Unprepared {
                 statement_id: Bytes::from_static(b"deadbeef"),
             },
             DbError::ProtocolError,
+            DbError::RateLimitReached {
+                op_type: OperationType::Write,
+                rejected_by_coordinator: true,
+            },
             DbError::Other(0x124816),
         ];
 
         for dberror in never_retried_dberrors {
-            default_policy_assert_never_retries(QueryError::DbError(dberror, String::new()));
+            default_policy_assert_never_retries(QueryError::DbError {
+                error: dberror,
+                message: String::new(),
+                node: None,
+            });
         }
 
         default_policy_assert_never_retries(QueryError::BadQuery(BadQuery::Other(
@@ -319,10 +584,22 @@ mod tests {
     fn default_idempotent_next_retries() {
         setup_tracing();
         let idempotent_next_errors = vec![
-            QueryError::DbError(DbError::Overloaded, String::new()),
-            QueryError::DbError(DbError::TruncateError, String::new()),
-            QueryError::DbError(DbError::ServerError, String::new()),
-            QueryError::IoError(Arc::new(std::io::Error::new(ErrorKind::Other, "test"))),
+            QueryError::DbError {
+                error: DbError::Overloaded,
+                message: String::new(),
+                node: None,
+            },
+            QueryError::DbError {
+                error: DbError::TruncateError,
+                message: String::new(),
+                node: None,
+            },
+            QueryError::DbError {
+                error: DbError::ServerError,
+                message: String::new(),
+                node: None,
+            },
+            QueryError::from(std::io::Error::new(ErrorKind::Other, "test")),
         ];
 
         for error in idempotent_next_errors {
@@ -334,7 +611,11 @@ mod tests {
     #[test]
     fn default_bootstrapping() {
         setup_tracing();
-        let error = QueryError::DbError(DbError::IsBootstrapping, String::new());
+        let error = QueryError::DbError {
+            error: DbError::IsBootstrapping,
+            message: String::new(),
+            node: None,
+        };
 
         let mut policy = DefaultRetryPolicy::new().new_session();
         assert_eq!(
@@ -353,14 +634,15 @@ mod tests {
     #[test]
     fn default_unavailable() {
         setup_tracing();
-        let error = QueryError::DbError(
-            DbError::Unavailable {
+        let error = QueryError::DbError {
+            error: DbError::Unavailable {
                 consistency: Consistency::Two,
                 required: 2,
                 alive: 1,
             },
-            String::new(),
-        );
+            message: String::new(),
+            node: None,
+        };
 
         let mut policy_not_idempotent = DefaultRetryPolicy::new().new_session();
         assert_eq!(
@@ -388,15 +670,16 @@ mod tests {
     fn default_read_timeout() {
         setup_tracing();
         // Enough responses and data_present == false - coordinator received only checksums
-        let enough_responses_no_data = QueryError::DbError(
-            DbError::ReadTimeout {
+        let enough_responses_no_data = QueryError::DbError {
+            error: DbError::ReadTimeout {
                 consistency: Consistency::Two,
                 received: 2,
                 required: 2,
                 data_present: false,
             },
-            String::new(),
-        );
+            message: String::new(),
+            node: None,
+        };
 
         // Not idempotent
         let mut policy = DefaultRetryPolicy::new().new_session();
@@ -422,15 +705,16 @@ mod tests {
 
         // Enough responses but data_present == true - coordinator probably timed out
         // waiting for read-repair acknowledgement.
-        let enough_responses_with_data = QueryError::DbError(
-            DbError::ReadTimeout {
+        let enough_responses_with_data = QueryError::DbError {
+            error: DbError::ReadTimeout {
                 consistency: Consistency::Two,
                 received: 2,
                 required: 2,
                 data_present: true,
             },
-            String::new(),
-        );
+            message: String::new(),
+            node: None,
+        };
 
         // Not idempotent
         let mut policy = DefaultRetryPolicy::new().new_session();
@@ -447,15 +731,16 @@ mod tests {
         );
 
         // Not enough responses, data_present == true
-        let not_enough_responses_with_data = QueryError::DbError(
-            DbError::ReadTimeout {
+        let not_enough_responses_with_data = QueryError::DbError {
+            error: DbError::ReadTimeout {
                 consistency: Consistency::Two,
                 received: 1,
                 required: 2,
                 data_present: true,
             },
-            String::new(),
-        );
+            message: String::new(),
+            node: None,
+        };
 
         // Not idempotent
         let mut policy = DefaultRetryPolicy::new().new_session();
@@ -477,15 +762,16 @@ mod tests {
     fn default_write_timeout() {
         setup_tracing();
         // WriteType == BatchLog
-        let good_write_type = QueryError::DbError(
-            DbError::WriteTimeout {
+        let good_write_type = QueryError::DbError {
+            error: DbError::WriteTimeout {
                 consistency: Consistency::Two,
                 received: 1,
                 required: 2,
                 write_type: WriteType::BatchLog,
             },
-            String::new(),
-        );
+            message: String::new(),
+            node: None,
+        };
 
         // Not idempotent
         let mut policy = DefaultRetryPolicy::new().new_session();
@@ -506,15 +792,16 @@ mod tests {
         );
 
         // WriteType != BatchLog
-        let bad_write_type = QueryError::DbError(
-            DbError::WriteTimeout {
+        let bad_write_type = QueryError::DbError {
+            error: DbError::WriteTimeout {
                 consistency: Consistency::Two,
                 received: 4,
                 required: 2,
                 write_type: WriteType::Simple,
             },
-            String::new(),
-        );
+            message: String::new(),
+            node: None,
+        };
 
         // Not idempotent
         let mut policy = DefaultRetryPolicy::new().new_session();
@@ -530,4 +817,183 @@ mod tests {
             RetryDecision::DontRetry
         );
     }
+
+    #[test]
+    fn backoff_delays_grow_up_to_max() {
+        setup_tracing();
+        let policy = BackoffRetryPolicy::new()
+            .with_base_delay(Duration::from_millis(10))
+            .with_multiplier(3.0)
+            .with_max_delay(Duration::from_millis(200))
+            .with_max_attempts(6)
+            .with_jitter(false);
+        let error = QueryError::DbError {
+            error: DbError::Overloaded,
+            message: String::new(),
+            node: None,
+        };
+
+        let mut session = policy.new_session();
+        let mut delays = Vec::new();
+        while session.decide_should_retry(make_query_info(&error, true))
+            == RetryDecision::RetryNextNode(None)
+        {
+            delays.push(session.retry_delay().as_millis());
+        }
+        assert_eq!(delays, [10, 30, 90, 200, 200]);
+
+        // Starts over after a reset
+        session.reset();
+        assert_eq!(
+            session.decide_should_retry(make_query_info(&error, true)),
+            RetryDecision::RetryNextNode(None)
+        );
+        assert_eq!(session.retry_delay(), Duration::from_millis(10));
+    }
+
+    #[test]
+    fn backoff_delays_saturate_at_max_duration() {
+        setup_tracing();
+        let policy = BackoffRetryPolicy::new()
+            .with_multiplier(1000.0)
+            .with_max_delay(Duration::MAX)
+            .with_max_attempts(20);
+        let error = QueryError::DbError {
+            error: DbError::Overloaded,
+            message: String::new(),
+            node: None,
+        };
+
+        for policy in [policy.clone(), policy.with_jitter(false)] {
+            let mut session = policy.new_session();
+            let mut delays = Vec::new();
+            while session.decide_should_retry(make_query_info(&error, true))
+                == RetryDecision::RetryNextNode(None)
+            {
+                delays.push(session.retry_delay());
+            }
+            assert_eq!(delays.len(), 19);
+            if !policy.jitter {
+                assert_eq!(delays.last(), Some(&Duration::MAX));
+            }
+        }
+    }
+
+    #[test]
+    fn backoff_multiplier_is_clamped() {
+        setup_tracing();
+        let error = QueryError::DbError {
+            error: DbError::Overloaded,
+            message: String::new(),
+            node: None,
+        };
+        let delays = |multiplier: f64| {
+            let policy = BackoffRetryPolicy::new()
+                .with_base_delay(Duration::from_millis(10))
+                .with_multiplier(multiplier)
+                .with_max_delay(Duration::from_secs(1))
+                .with_max_attempts(4)
+                .with_jitter(false);
+            let mut session = policy.new_session();
+            let mut delays = Vec::new();
+            while session.decide_should_retry(make_query_info(&error, true))
+                == RetryDecision::RetryNextNode(None)
+            {
+                delays.push(session.retry_delay().as_millis());
+            }
+            delays
+        };
+
+        assert_eq!(delays(0.5), [10, 10, 10]);
+        assert_eq!(delays(-2.0), [10, 10, 10]);
+        assert_eq!(delays(f64::NAN), [10, 10, 10]);
+        assert_eq!(delays(f64::INFINITY), [10, 1000, 1000]);
+    }
+
+    #[test]
+    fn backoff_jitter_stays_within_delay() {
+        setup_tracing();
+        let policy = BackoffRetryPolicy::new()
+            .with_base_delay(Duration::from_millis(100))
+            .with_max_attempts(2);
+        let error = QueryError::from(ConnectionError::UnableToAllocStreamId);
+
+        for _ in 0..100 {
+            let mut session = policy.new_session();
+            assert_eq!(
+                session.decide_should_retry(make_query_info(&error, false)),
+                RetryDecision::RetryNextNode(None)
+            );
+            assert!(session.retry_delay() <= Duration::from_millis(100));
+            assert_eq!(
+                session.decide_should_retry(make_query_info(&error, false)),
+                RetryDecision::DontRetry
+            );
+        }
+    }
+
+    #[test]
+    fn backoff_retries_only_safe_errors() {
+        setup_tracing();
+        let decide = |error: QueryError, is_idempotent: bool| {
+            BackoffRetryPolicy::new()
+                .new_session()
+                .decide_should_retry(make_query_info(&error, is_idempotent))
+        };
+        let write_timeout = || QueryError::DbError {
+            error: DbError::WriteTimeout {
+                consistency: Consistency::Quorum,
+                received: 1,
+                required: 2,
+                write_type: WriteType::Simple,
+            },
+            message: String::new(),
+            node: None,
+        };
+        let unavailable = || QueryError::DbError {
+            error: DbError::Unavailable {
+                consistency: Consistency::Quorum,
+                required: 2,
+                alive: 1,
+            },
+            message: String::new(),
+            node: None,
+        };
+
+        assert_eq!(
+            decide(write_timeout(), true),
+            RetryDecision::RetrySameNode(None)
+        );
+        assert_eq!(decide(write_timeout(), false), RetryDecision::DontRetry);
+        assert_eq!(
+            decide(unavailable(), false),
+            RetryDecision::RetryNextNode(None)
+        );
+        assert_eq!(
+            decide(
+                QueryError::DbError {
+                    error: DbError::SyntaxError,
+                    message: String::new(),
+                    node: None
+                },
+                true
+            ),
+            RetryDecision::DontRetry
+        );
+
+        let serial = BackoffRetryPolicy::new()
+            .new_session()
+            .decide_should_retry(QueryInfo {
+                error: &unavailable(),
+                is_idempotent: true,
+                consistency: Consistency::Serial,
+            });
+        assert_eq!(serial, RetryDecision::DontRetry);
+
+        let never = BackoffRetryPolicy::new()
+            .with_max_attempts(1)
+            .new_session()
+            .decide_should_retry(make_query_info(&unavailable(), true));
+        assert_eq!(never, RetryDecision::DontRetry);
+    }
 }
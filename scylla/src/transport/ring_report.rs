@@ -0,0 +1,353 @@
+//! Structured report of the token ring, as seen by the driver.
+//!
+//! [`ClusterData::ring_report`] exports the nodes with the token ranges they own,
+//! the datacenter and rack layout, and the replica sets the driver computes for each
+//! keyspace. Unlike querying `system.peers`, the report describes exactly the metadata
+//! which the driver uses for routing requests.
+//!
+//! Keyspaces using tablets are marked, and have no replica sets: the replicas of their
+//! tables are taken from tablets, and not from the token ring.
+//!
+//! With the `serde` feature enabled, all report types implement `serde::Serialize`,
+//! so the report can be exported e.g. to JSON.
+//!
+//! # Example
+//! ```rust
+//! # use scylla::Session;
+//! # fn check_only_compiles(session: &Session) {
+//! let report = session.get_cluster_data().ring_report();
+//! for node in &report.nodes {
+//!     println!(
+//!         "{} ({:?}/{:?}): {} token ranges",
+//!         node.address,
+//!         node.datacenter,
+//!         node.rack,
+//!         node.token_ranges.len()
+//!     );
+//! }
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+
+use itertools::Itertools;
+use scylla_cql::frame::response::result::TableSpec;
+use uuid::Uuid;
+
+use crate::transport::topology::Strategy;
+use crate::transport::ClusterData;
+
+/// Report of the token ring, created by [`ClusterData::ring_report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct RingReport {
+    /// All nodes known to the driver, ordered by their addresses.
+    pub nodes: Vec<NodeReport>,
+    /// Datacenters of the nodes, ordered by their names.
+    pub datacenters: Vec<DatacenterReport>,
+    /// Keyspaces known to the driver, ordered by their names.
+    pub keyspaces: Vec<KeyspaceReport>,
+}
+
+/// A node in the [`RingReport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct NodeReport {
+    pub host_id: Uuid,
+    pub address: SocketAddr,
+    pub datacenter: Option<String>,
+    pub rack: Option<String>,
+    /// Number of shards of the node, known once the driver has connected to it.
+    pub shard_count: Option<u16>,
+    /// Token ranges which end at the tokens of the node, in the order of the ring.
+    pub token_ranges: Vec<TokenRange>,
+}
+
+/// A range of tokens, from `start` (exclusive) to `end` (inclusive).
+///
+/// The range wraps around the ring if `start` is not less than `end`.
+/// In particular, if the ring has a single token, its only range covers the whole ring.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TokenRange {
+    pub start: i64,
+    pub end: i64,
+}
+
+/// A datacenter in the [`RingReport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct DatacenterReport {
+    pub name: String,
+    /// Racks of the datacenter, ordered by their names.
+    pub racks: Vec<RackReport>,
+}
+
+/// A rack in the [`RingReport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct RackReport {
+    /// Name of the rack, `None` if the nodes did not report it.
+    pub name: Option<String>,
+    /// Addresses of the nodes in the rack, in ascending order.
+    pub nodes: Vec<SocketAddr>,
+}
+
+/// A keyspace in the [`RingReport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct KeyspaceReport {
+    pub name: String,
+    pub strategy: Strategy,
+    /// Whether the keyspace uses tablets. The driver learns about tablets from
+    /// the responses to requests, so this is only known after a table of the keyspace
+    /// has been queried.
+    pub uses_tablets: bool,
+    /// Replicas of each token range of the ring, in the order of the ring,
+    /// computed from the replication strategy of the keyspace.
+    ///
+    /// Empty if the keyspace uses tablets.
+    pub replica_sets: Vec<ReplicaSetReport>,
+}
+
+/// Replicas of a token range of a keyspace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct ReplicaSetReport {
+    pub range: TokenRange,
+    /// Addresses of the replicas, in the order in which the driver computes them.
+    pub replicas: Vec<SocketAddr>,
+}
+
+impl RingReport {
+    pub(crate) fn new(cluster_data: &ClusterData) -> Self {
+        let ring = cluster_data.replica_locator().ring();
+        let tokens = ring
+            .iter()
+            .map(|(token, _)| token.value())
+            .collect::<Vec<_>>();
+        let range_ending_at = |index: usize| TokenRange {
+            start: tokens[(index + tokens.len() - 1) % tokens.len()],
+            end: tokens[index],
+        };
+
+        let mut token_ranges: BTreeMap<Uuid, Vec<TokenRange>> = BTreeMap::new();
+        for (index, (_, node)) in ring.iter().enumerate() {
+            token_ranges
+                .entry(node.host_id)
+                .or_default()
+                .push(range_ending_at(index));
+        }
+
+        let nodes = cluster_data
+            .known_peers
+            .values()
+            .map(|node| NodeReport {
+                host_id: node.host_id,
+                address: node.address.into_inner(),
+                datacenter: node.datacenter.clone(),
+                rack: node.rack.clone(),
+                shard_count: node.sharder().map(|sharder| sharder.nr_shards.get()),
+                token_ranges: token_ranges.remove(&node.host_id).unwrap_or_default(),
+            })
+            .sorted_by_key(|node| node.address)
+            .collect::<Vec<_>>();
+
+        let mut racks: BTreeMap<&str, BTreeMap<Option<&str>, Vec<SocketAddr>>> = BTreeMap::new();
+        for node in &nodes {
+            if let Some(datacenter) = &node.datacenter {
+                racks
+                    .entry(datacenter)
+                    .or_default()
+                    .entry(node.rack.as_deref())
+                    .or_default()
+                    .push(node.address);
+            }
+        }
+        let datacenters = racks
+            .into_iter()
+            .map(|(name, racks)| DatacenterReport {
+                name: name.to_owned(),
+                racks: racks
+                    .into_iter()
+                    .map(|(name, nodes)| RackReport {
+                        name: name.map(ToOwned::to_owned),
+                        nodes,
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let keyspaces = cluster_data
+            .keyspaces
+            .iter()
+            .sorted_by_key(|(name, _)| name.as_str())
+            .map(|(name, keyspace)| {
+                let uses_tablets = cluster_data
+                    .replica_locator()
+                    .tablets
+                    .has_tablets_in_keyspace(name);
+                // No table has an empty name, so the replicas are never taken from tablets
+                let table_spec = TableSpec::borrowed(name, "");
+                let replica_sets = if uses_tablets {
+                    Vec::new()
+                } else {
+                    ring.iter()
+                        .enumerate()
+                        .map(|(index, (token, _))| ReplicaSetReport {
+                            range: range_ending_at(index),
+                            replicas: cluster_data
+                                .replica_locator()
+                                .replicas_for_token(*token, &keyspace.strategy, None, &table_spec)
+                                .into_iter()
+                                .map(|(node, _)| node.address.into_inner())
+                                .collect(),
+                        })
+                        .collect()
+                };
+
+                KeyspaceReport {
+                    name: name.clone(),
+                    strategy: keyspace.strategy.clone(),
+                    uses_tablets,
+                    replica_sets,
+                }
+            })
+            .collect();
+
+        Self {
+            nodes,
+            datacenters,
+            keyspaces,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use scylla_cql::frame::response::result::TableSpec;
+
+    use super::TokenRange;
+    use crate::transport::locator::tablets::{Tablet, TabletsInfo};
+    use crate::transport::locator::test::{
+        id_to_invalid_addr, mock_metadata_for_token_aware_tests, KEYSPACE_NTS_RF_2,
+        KEYSPACE_NTS_RF_3, KEYSPACE_SS_RF_2,
+    };
+    use crate::transport::ClusterData;
+
+    #[tokio::test]
+    async fn ring_report_of_mock_cluster() {
+        let mut cluster_data = ClusterData::new(
+            mock_metadata_for_token_aware_tests(),
+            &Default::default(),
+            &HashMap::new(),
+            &None,
+            None,
+            TabletsInfo::new(),
+        )
+        .await
+        .unwrap();
+        cluster_data.locator.tablets.add_tablet(
+            TableSpec::borrowed(KEYSPACE_NTS_RF_3, "table").to_owned(),
+            Tablet::new_for_test(0, Vec::new(), None),
+        );
+        let report = cluster_data.ring_report();
+        let addr = |id| id_to_invalid_addr(id).into_inner();
+
+        assert_eq!(
+            report
+                .nodes
+                .iter()
+                .map(|node| node.address)
+                .collect::<Vec<_>>(),
+            (1..=7).map(addr).collect::<Vec<_>>()
+        );
+        // A owns tokens 50, 250 and 400, the ring ends at 900 (B)
+        let a = &report.nodes[0];
+        assert_eq!(a.datacenter.as_deref(), Some("eu"));
+        assert_eq!(a.shard_count, None);
+        assert_eq!(
+            a.token_ranges,
+            [(900, 50), (200, 250), (350, 400)]
+                .map(|(start, end)| TokenRange { start, end })
+                .to_vec()
+        );
+        let ranges_num: usize = report.nodes.iter().map(|n| n.token_ranges.len()).sum();
+        assert_eq!(ranges_num, 17);
+
+        assert_eq!(
+            report
+                .datacenters
+                .iter()
+                .map(|dc| (
+                    dc.name.as_str(),
+                    dc.racks
+                        .iter()
+                        .map(|rack| (rack.name.as_deref(), rack.nodes.clone()))
+                        .collect::<Vec<_>>()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "eu",
+                    vec![
+                        (Some("r1"), vec![addr(1), addr(2), addr(3)]),
+                        (Some("r2"), vec![addr(7)])
+                    ]
+                ),
+                (
+                    "us",
+                    vec![
+                        (Some("r1"), vec![addr(4), addr(5)]),
+                        (Some("r2"), vec![addr(6)])
+                    ]
+                ),
+            ]
+        );
+
+        let simple = report
+            .keyspaces
+            .iter()
+            .find(|ks| ks.name == KEYSPACE_SS_RF_2)
+            .unwrap();
+        assert_eq!(simple.replica_sets.len(), 17);
+        // The range ending at 50 is replicated to A and the next node, B
+        assert_eq!(
+            simple.replica_sets[0].range,
+            TokenRange {
+                start: 900,
+                end: 50
+            }
+        );
+        assert_eq!(simple.replica_sets[0].replicas, vec![addr(1), addr(2)]);
+
+        let nts = report
+            .keyspaces
+            .iter()
+            .find(|ks| ks.name == KEYSPACE_NTS_RF_2)
+            .unwrap();
+        assert!(!nts.uses_tablets);
+        assert!(nts
+            .replica_sets
+            .iter()
+            .all(|replica_set| replica_set.replicas.len() == 4));
+
+        // The token ring does not determine the replicas of tablet keyspaces
+        let tablet_keyspace = report
+            .keyspaces
+            .iter()
+            .find(|ks| ks.name == KEYSPACE_NTS_RF_3)
+            .unwrap();
+        assert!(tablet_keyspace.uses_tablets);
+        assert!(tablet_keyspace.replica_sets.is_empty());
+    }
+}
@@ -0,0 +1,142 @@
+//! Abstraction over the async runtime used by the driver.
+//!
+//! The driver runs background tasks (e.g. the ones which read from connections,
+//! refill connection pools or refresh cluster metadata) and waits on timers.
+//! It does so through a [`Runtime`], which is [`TokioRuntime`] by default.
+//! To use the driver in a program which does not run the Tokio runtime,
+//! implement [`Runtime`] for the executor of your choice and pass it to
+//! [`SessionBuilder::runtime`](crate::transport::session_builder::GenericSessionBuilder::runtime).
+//!
+//! Note that the driver opens TCP connections with Tokio. When using another runtime,
+//! also provide a [`Connector`](crate::transport::connector::Connector) which opens connections
+//! with that runtime, e.g. by wrapping its TCP streams with a compatibility layer.
+//! The driver's channels and locks don't depend on a particular runtime.
+//!
+//! With the `smol-2` feature, the driver provides such a runtime and connector for smol
+//! (and other executors built on `async-io`, like `async-std`) in `transport::smol`.
+
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A future which can be run by a [`Runtime`].
+pub type BoxedFuture<T> = Pin<Box<dyn Future<Output = T> + Send + 'static>>;
+
+/// Runs the driver's background tasks and timers.
+pub trait Runtime: fmt::Debug + Send + Sync {
+    /// Spawns a task which runs in the background until it completes.
+    /// The driver doesn't wait for the task, nor does it need a handle to it.
+    fn spawn(&self, task: BoxedFuture<()>);
+
+    /// Runs blocking or CPU-intensive code without stalling other tasks,
+    /// e.g. on a dedicated thread pool.
+    fn spawn_blocking(&self, job: Box<dyn FnOnce() + Send + 'static>);
+
+    /// Returns a future which completes after the given duration.
+    fn sleep(&self, duration: Duration) -> BoxedFuture<()>;
+}
+
+/// Runs the driver on the Tokio runtime the session was created in.
+///
+/// This is the default runtime.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioRuntime;
+
+impl Runtime for TokioRuntime {
+    fn spawn(&self, task: BoxedFuture<()>) {
+        tokio::task::spawn(task);
+    }
+
+    fn spawn_blocking(&self, job: Box<dyn FnOnce() + Send + 'static>) {
+        tokio::task::spawn_blocking(job);
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxedFuture<()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+pub(crate) fn default_runtime() -> Arc<dyn Runtime> {
+    Arc::new(TokioRuntime)
+}
+
+/// Returned by [`timeout`] if the future didn't complete in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Elapsed;
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "deadline has elapsed")
+    }
+}
+
+/// Awaits the future, unless it takes longer than the given duration.
+pub(crate) async fn timeout<F: Future>(
+    runtime: &dyn Runtime,
+    duration: Duration,
+    future: F,
+) -> Result<F::Output, Elapsed> {
+    tokio::select! {
+        biased;
+        output = future => Ok(output),
+        _ = runtime.sleep(duration) => Err(Elapsed),
+    }
+}
+
+/// Runs the closure with [`Runtime::spawn_blocking`] and returns its result.
+///
+/// Fails if the closure panicked, or the runtime dropped it without running it.
+pub(crate) async fn spawn_blocking<T: Send + 'static>(
+    runtime: &dyn Runtime,
+    job: impl FnOnce() -> T + Send + 'static,
+) -> io::Result<T> {
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    runtime.spawn_blocking(Box::new(move || {
+        let _ = sender.send(job());
+    }));
+    receiver.await.map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::Other,
+            "Blocking job panicked or was not run by the runtime",
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{spawn_blocking, timeout, Elapsed, TokioRuntime};
+
+    #[tokio::test(start_paused = true)]
+    async fn timeout_elapses() {
+        let runtime = TokioRuntime;
+        assert_eq!(
+            timeout(&runtime, Duration::from_secs(1), async { 42 }).await,
+            Ok(42)
+        );
+        assert_eq!(
+            timeout(
+                &runtime,
+                Duration::from_secs(1),
+                tokio::time::sleep(Duration::from_secs(2))
+            )
+            .await,
+            Err(Elapsed)
+        );
+    }
+
+    #[tokio::test]
+    async fn blocking_job_result_is_returned() {
+        assert_eq!(spawn_blocking(&TokioRuntime, || 2 + 2).await.unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn blocking_job_panic_is_returned_as_error() {
+        let result = spawn_blocking(&TokioRuntime, || -> i32 { panic!("job failed") }).await;
+        assert!(result.is_err());
+    }
+}
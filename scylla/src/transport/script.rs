@@ -0,0 +1,213 @@
+//! Execution of multi-statement CQL scripts.
+//!
+//! [`Session::execute_script`](crate::Session::execute_script) splits a script into statements
+//! with [`split_statements`] and executes them one by one, in order. Statements are separated
+//! by semicolons - except for the semicolons inside string literals, quoted identifiers,
+//! comments and `BEGIN BATCH ... APPLY BATCH` blocks, which are executed as a single statement.
+
+use scylla_cql::cql_lexer::{next_token, TokenKind};
+use thiserror::Error;
+
+use crate::transport::errors::QueryError;
+
+/// An error of executing a CQL script.
+#[derive(Error, Debug, Clone)]
+#[non_exhaustive]
+pub enum ScriptError {
+    /// The script could not be split into statements, nothing was executed.
+    #[error("Invalid CQL script: {0}")]
+    Parse(#[from] ScriptParseError),
+
+    /// Executing a statement of the script failed.
+    /// The statements preceding it were executed, and the ones following it were not.
+    #[error("Statement {index} of the script ({statement}) failed: {error}")]
+    StatementFailed {
+        /// Index of the failed statement among the statements of the script, starting at 0.
+        index: usize,
+        statement: String,
+        error: QueryError,
+    },
+}
+
+/// An error of splitting a CQL script into statements.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ScriptParseError {
+    /// A string literal, a quoted identifier or a comment is not terminated.
+    /// `position` is the byte offset of its start in the script.
+    #[error("Unterminated {kind} starting at byte {position}")]
+    Unterminated { kind: &'static str, position: usize },
+
+    /// A `BEGIN BATCH` block is not closed with `APPLY BATCH`.
+    /// `position` is the byte offset of the batch in the script.
+    #[error("Batch starting at byte {position} is not terminated with APPLY BATCH")]
+    UnterminatedBatch { position: usize },
+}
+
+/// Splits a CQL script into statements.
+///
+/// The statements are returned without the separating semicolons and surrounding whitespace.
+/// Comments (`-- ...`, `// ...` and `/* ... */`) between the statements are skipped,
+/// while the ones inside statements are kept. Empty statements are skipped.
+///
+/// # Example
+/// ```rust
+/// use scylla::transport::script::split_statements;
+///
+/// let script = "
+///     -- Fixture
+///     INSERT INTO ks.t (a, b) VALUES (1, 'a;b');
+///     BEGIN BATCH
+///         INSERT INTO ks.t (a, b) VALUES (2, 'c');
+///         INSERT INTO ks.t (a, b) VALUES (3, 'd');
+///     APPLY BATCH;
+/// ";
+/// let statements = split_statements(script).unwrap();
+/// assert_eq!(statements.len(), 2);
+/// assert_eq!(statements[0], "INSERT INTO ks.t (a, b) VALUES (1, 'a;b')");
+/// assert!(statements[1].starts_with("BEGIN BATCH") && statements[1].ends_with("APPLY BATCH"));
+/// ```
+pub fn split_statements(script: &str) -> Result<Vec<&str>, ScriptParseError> {
+    let mut statements = Vec::new();
+
+    // Start of the current statement, if any of its content was encountered
+    let mut statement_start: Option<usize> = None;
+    let mut in_batch = false;
+    // The last two words of the current statement, `None` standing for anything else than a word
+    let mut last_words: [Option<&str>; 2] = [None, None];
+
+    let mut pos = 0;
+    while let Some(token) = next_token(&script[pos..]) {
+        if !token.terminated {
+            return Err(ScriptParseError::Unterminated {
+                kind: match token.kind {
+                    TokenKind::String => "string literal",
+                    TokenKind::QuotedIdentifier => "quoted identifier",
+                    _ => "comment",
+                },
+                position: pos,
+            });
+        }
+        let text = &script[pos..pos + token.len];
+
+        match token.kind {
+            TokenKind::Whitespace | TokenKind::Comment => {}
+            TokenKind::Symbol if text == ";" => {
+                if !in_batch || ends_with_apply_batch(last_words) {
+                    if let Some(start) = statement_start.take() {
+                        statements.push(script[start..pos].trim_end());
+                    }
+                    in_batch = false;
+                    last_words = [None, None];
+                } else {
+                    last_words = [last_words[1], None];
+                }
+            }
+            TokenKind::Word => {
+                let start = *statement_start.get_or_insert(pos);
+                if start == pos && text.eq_ignore_ascii_case("begin") {
+                    in_batch = true;
+                }
+                last_words = [last_words[1], Some(text)];
+            }
+            _ => {
+                statement_start.get_or_insert(pos);
+                last_words = [last_words[1], None];
+            }
+        }
+        pos += token.len;
+    }
+
+    if let Some(start) = statement_start {
+        if in_batch && !ends_with_apply_batch(last_words) {
+            return Err(ScriptParseError::UnterminatedBatch { position: start });
+        }
+        statements.push(script[start..].trim_end());
+    }
+
+    Ok(statements)
+}
+
+fn ends_with_apply_batch(last_words: [Option<&str>; 2]) -> bool {
+    matches!(
+        last_words,
+        [Some(apply), Some(batch)]
+            if apply.eq_ignore_ascii_case("apply") && batch.eq_ignore_ascii_case("batch")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{split_statements, ScriptParseError};
+
+    #[test]
+    fn splits_statements_outside_of_literals() {
+        let script = "CREATE KEYSPACE ks WITH replication = {'class': 'SimpleStrategy', 'replication_factor': 1};\n\
+            INSERT INTO ks.t (a, \"weird;name\") VALUES ('it''s; fine', $$ body; $$);;\n\
+            SELECT * FROM ks.t WHERE a = 'x' -- trailing; comment\n\
+            ;/* block; comment */ UPDATE ks.t SET b = 1 WHERE a = 'zażółć;'";
+        assert_eq!(
+            split_statements(script).unwrap(),
+            vec![
+                "CREATE KEYSPACE ks WITH replication = {'class': 'SimpleStrategy', 'replication_factor': 1}",
+                "INSERT INTO ks.t (a, \"weird;name\") VALUES ('it''s; fine', $$ body; $$)",
+                "SELECT * FROM ks.t WHERE a = 'x' -- trailing; comment",
+                "UPDATE ks.t SET b = 1 WHERE a = 'zażółć;'",
+            ]
+        );
+
+        assert_eq!(split_statements("").unwrap(), Vec::<&str>::new());
+        assert_eq!(
+            split_statements("  -- only a comment\n ; ").unwrap(),
+            Vec::<&str>::new()
+        );
+    }
+
+    #[test]
+    fn keeps_batches_together() {
+        let script = "begin unlogged batch\n\
+            INSERT INTO t (a) VALUES (1);\n\
+            INSERT INTO t (a) VALUES ('apply batch;');\n\
+            apply batch;\n\
+            BEGIN BATCH USING TIMESTAMP 1 INSERT INTO t (a) VALUES (2); APPLY BATCH\n";
+        assert_eq!(
+            split_statements(script).unwrap(),
+            vec![
+                "begin unlogged batch\n\
+                INSERT INTO t (a) VALUES (1);\n\
+                INSERT INTO t (a) VALUES ('apply batch;');\n\
+                apply batch",
+                "BEGIN BATCH USING TIMESTAMP 1 INSERT INTO t (a) VALUES (2); APPLY BATCH",
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unterminated_constructs() {
+        assert_eq!(
+            split_statements("SELECT 1; SELECT 'abc"),
+            Err(ScriptParseError::Unterminated {
+                kind: "string literal",
+                position: 17
+            })
+        );
+        assert_eq!(
+            split_statements("SELECT \"a"),
+            Err(ScriptParseError::Unterminated {
+                kind: "quoted identifier",
+                position: 7
+            })
+        );
+        assert_eq!(
+            split_statements("SELECT 1; /* abc"),
+            Err(ScriptParseError::Unterminated {
+                kind: "comment",
+                position: 10
+            })
+        );
+        assert_eq!(
+            split_statements("SELECT 1; BEGIN BATCH INSERT INTO t (a) VALUES (1);"),
+            Err(ScriptParseError::UnterminatedBatch { position: 10 })
+        );
+    }
+}
@@ -15,37 +15,40 @@ use futures::future::join_all;
 use futures::future::try_join_all;
 use itertools::{Either, Itertools};
 pub use scylla_cql::errors::TranslationError;
-use scylla_cql::frame::response::result::{deser_cql_value, ColumnSpec, Rows};
+use scylla_cql::frame::request::options;
+use scylla_cql::frame::response::result::{deser_cql_value, ColumnSpec, RawRows, TableSpec};
 use scylla_cql::frame::response::NonErrorResponse;
+use scylla_cql::types::column_encryption::ColumnEncryptionPolicy;
 use scylla_cql::types::serialize::batch::BatchValues;
 use scylla_cql::types::serialize::row::SerializeRow;
 use std::borrow::Borrow;
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::future::Future;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::num::NonZeroU32;
 use std::str::FromStr;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::time::timeout;
+use std::sync::Mutex as StdMutex;
+use std::time::{Duration, Instant};
 use tracing::{debug, trace, trace_span, Instrument};
 use uuid::Uuid;
 
+use super::cancellation;
 use super::connection::NonErrorQueryResponse;
 use super::connection::QueryResponse;
 #[cfg(feature = "ssl")]
 use super::connection::SslConfig;
-use super::errors::{NewSessionError, QueryError};
+use super::errors::{DbError, NewSessionError, QueryError, TimeoutError};
 use super::execution_profile::{ExecutionProfile, ExecutionProfileHandle, ExecutionProfileInner};
 #[cfg(feature = "cloud")]
 use super::node::CloudEndpoint;
 use super::node::KnownNode;
 use super::partitioner::PartitionerName;
 use super::query_result::MaybeFirstRowTypedError;
-use super::topology::UntranslatedPeer;
+use super::topology::{CqlType, NativeType, UntranslatedPeer};
 use super::{NodeRef, SelfIdentity};
 use crate::cql_to_rust::FromRow;
 use crate::frame::response::cql_to_rust::FromRowError;
@@ -56,23 +59,38 @@ use crate::routing::{Shard, Token};
 use crate::statement::Consistency;
 use crate::tracing::{TracingEvent, TracingInfo};
 use crate::transport::cluster::{Cluster, ClusterData, ClusterNeatDebug};
-use crate::transport::connection::{Connection, ConnectionConfig, VerifiedKeyspaceName};
+use crate::transport::connection::{
+    Connection, ConnectionConfig, ConnectionInfo, KeepaliveRequest, RowsFormat,
+    VerifiedKeyspaceName,
+};
 use crate::transport::connection_pool::PoolConfig;
+use crate::transport::connector::Connector;
+use crate::transport::control_connection::{ControlConnectionConfig, ControlConnectionListener};
+use crate::transport::execution_info::ExecutionInfoRecorder;
+use crate::transport::frame_capture::FrameCapture;
 use crate::transport::host_filter::HostFilter;
 use crate::transport::iterator::{PreparedIteratorConfig, RowIterator};
 use crate::transport::load_balancing::{self, RoutingInfo};
+use crate::transport::load_shedding::LoadSheddingPolicy;
 use crate::transport::metrics::Metrics;
 use crate::transport::node::Node;
+use crate::transport::pipeline::ConnectionHandle;
+use crate::transport::query_log::{LoggedRequest, QueryLog};
 use crate::transport::query_result::QueryResult;
 use crate::transport::retry_policy::{QueryInfo, RetryDecision, RetrySession};
+use crate::transport::runtime::{self, default_runtime, Runtime};
+use crate::transport::script::{self, ScriptError};
 use crate::transport::speculative_execution;
+use crate::transport::write_defaults::{self, WriteDefaultsPolicy};
 use crate::transport::Compression;
 use crate::{
     batch::{Batch, BatchStatement},
     statement::StatementConfig,
 };
 
-pub use crate::transport::connection_pool::PoolSize;
+pub use crate::transport::connection_pool::{
+    PoolSize, PoolState, ShardAwarenessInactiveReason, ShardAwarenessStatus,
+};
 
 use crate::authentication::AuthenticatorProvider;
 #[cfg(feature = "ssl")]
@@ -81,6 +99,10 @@ use scylla_cql::errors::BadQuery;
 
 pub(crate) const TABLET_CHANNEL_SIZE: usize = 8192;
 
+// Minimal time between metadata refreshes done by `use_keyspace` for keyspaces missing from the metadata.
+// Within it, the last refreshed metadata is trusted.
+const KEYSPACE_VERIFICATION_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
 /// Translates IP addresses received from ScyllaDB nodes into locally reachable addresses.
 ///
 /// The driver auto-detects new ScyllaDB nodes added to the cluster through server side pushed
@@ -148,9 +170,21 @@ pub struct Session {
     schema_agreement_automatic_waiting: bool,
     refresh_metadata_on_auto_schema_agreement: bool,
     keyspace_name: ArcSwapOption<String>,
+    // The name of the keyspace of the session as the database sees it, i.e. lowercased
+    // unless it is case sensitive.
+    effective_keyspace_name: ArcSwapOption<String>,
+    // Serializes `use_keyspace` calls, so that all connections end up in the same keyspace.
+    // Holds the time of the last metadata refresh done to verify that a keyspace exists.
+    use_keyspace_lock: tokio::sync::Mutex<Option<Instant>>,
+    // Keyspaces whose metadata is fetched, all of them if empty.
+    keyspaces_to_fetch: Vec<String>,
     tracing_info_fetch_attempts: NonZeroU32,
     tracing_info_fetch_interval: Duration,
     tracing_info_fetch_consistency: Consistency,
+    runtime: Arc<dyn Runtime>,
+    query_log: Option<Arc<QueryLog>>,
+    load_shedding_policy: Option<Arc<LoadSheddingPolicy>>,
+    write_defaults_policy: Option<Arc<dyn WriteDefaultsPolicy>>,
 }
 
 /// This implementation deliberately omits some details from Cluster in order
@@ -187,9 +221,32 @@ pub struct SessionConfig {
     /// Preferred compression algorithm to use on connections.
     /// If it's not supported by database server Session will fall back to no compression.
     pub compression: Option<Compression>,
+
+    /// Minimal size in bytes of a request body for it to be compressed.
+    /// Smaller frames are sent uncompressed, even if compression was negotiated.
+    pub compression_threshold: usize,
+
     pub tcp_nodelay: bool,
     pub tcp_keepalive_interval: Option<Duration>,
 
+    /// Time between TCP keepalive probes, sent once the connection has been idle
+    /// for `tcp_keepalive_interval`. Takes effect only if `tcp_keepalive_interval` is set.
+    pub tcp_keepalive_probe_interval: Duration,
+
+    /// Number of unacknowledged TCP keepalive probes after which the connection is considered dead.
+    /// Takes effect only if `tcp_keepalive_interval` is set.
+    pub tcp_keepalive_retries: u32,
+
+    /// Size of the socket send buffer (`SO_SNDBUF`). If not set, the OS default is used.
+    pub tcp_send_buffer_size: Option<u32>,
+
+    /// Size of the socket receive buffer (`SO_RCVBUF`). If not set, the OS default is used.
+    pub tcp_recv_buffer_size: Option<u32>,
+
+    /// Local IP address that sockets are bound to before connecting.
+    /// If not set, the OS chooses it.
+    pub local_ip_address: Option<IpAddr>,
+
     pub default_execution_profile_handle: ExecutionProfileHandle,
 
     pub used_keyspace: Option<String>,
@@ -211,6 +268,13 @@ pub struct SessionConfig {
     /// Generally, this options is best left as default (false).
     pub disallow_shard_aware_port: bool,
 
+    /// If set, after a failed attempt to connect to the shard-aware port of a node, the driver
+    /// stops using that port for the given time and opens connections to the regular port.
+    /// Once the time passes, shard-aware connections are attempted again, and if they succeed,
+    /// connections opened to the regular port are replaced with shard-aware ones.
+    /// If not set, the shard-aware port is attempted on each pool refill.
+    pub shard_aware_port_retry_interval: Option<Duration>,
+
     /// If empty, fetch all keyspaces
     pub keyspaces_to_fetch: Vec<String>,
 
@@ -225,6 +289,13 @@ pub struct SessionConfig {
     /// If `None`, connections are never closed due to lack of response to a keepalive message.
     pub keepalive_timeout: Option<Duration>,
 
+    /// Number of consecutive keepalives which may fail or time out before a connection is closed.
+    /// Default is 1, i.e. the first missed keepalive closes the connection.
+    pub keepalive_max_missed: NonZeroU32,
+
+    /// The request sent as a keepalive. Default is [`KeepaliveRequest::Options`].
+    pub keepalive_request: KeepaliveRequest,
+
     /// How often the driver should ask if schema is in agreement.
     pub schema_agreement_interval: Duration,
 
@@ -247,6 +318,46 @@ pub struct SessionConfig {
     /// between the nodes and the driver.
     pub address_translator: Option<Arc<dyn AddressTranslator>>,
 
+    /// If provided, the driver opens connections with the given connector
+    /// instead of connecting over TCP.
+    pub connector: Option<Arc<dyn Connector>>,
+
+    /// If provided, values of the columns configured in the policy are encrypted
+    /// before being sent to the database, and decrypted when received in query results.
+    /// Only values bound to prepared statements are encrypted.
+    pub column_encryption_policy: Option<Arc<ColumnEncryptionPolicy>>,
+
+    /// If provided, the frames sent and received by all connections are recorded in the capture.
+    /// See the [frame_capture](crate::transport::frame_capture) module for details.
+    pub frame_capture: Option<Arc<FrameCapture>>,
+
+    /// The runtime which runs the driver's background tasks and timers.
+    /// See the [runtime](crate::transport::runtime) module for details.
+    /// Default is [`TokioRuntime`](crate::transport::runtime::TokioRuntime).
+    pub runtime: Arc<dyn Runtime>,
+
+    /// If provided, every attempt of executing a statement is passed to the log's logger.
+    /// See the [query_log](crate::transport::query_log) module for details.
+    pub query_log: Option<Arc<QueryLog>>,
+
+    /// If provided, limits the number of requests in flight, shedding the requests
+    /// of lower [priorities](crate::statement::Priority) first.
+    /// See the [load_shedding](crate::transport::load_shedding) module for details.
+    pub load_shedding_policy: Option<Arc<LoadSheddingPolicy>>,
+
+    /// If provided, supplies the default TTL and timestamp of writes based on their table.
+    /// See the [write_defaults](crate::transport::write_defaults) module for details.
+    pub write_defaults_policy: Option<Arc<dyn WriteDefaultsPolicy>>,
+
+    /// Whether the metrics of the session also track the requests to each table,
+    /// see [`Metrics::get_table_metrics`]. Enabled by default.
+    pub table_metrics: bool,
+
+    /// Maximal number of tables whose requests are tracked separately by [`Self::table_metrics`].
+    /// The requests to the tables above the limit are tracked together, see
+    /// [`Metrics::get_untracked_table_metrics`]. Default is 100.
+    pub max_tracked_tables: usize,
+
     /// The host filter decides whether any connections should be opened
     /// to the node or not. The driver will also avoid filtered out nodes when
     /// re-establishing the control connection.
@@ -268,6 +379,34 @@ pub struct SessionConfig {
     /// this option.
     pub enable_write_coalescing: bool,
 
+    /// Size of the per-connection buffer in which outgoing requests are collected
+    /// before being written to the socket. Requests written to it in one go,
+    /// e.g. thanks to write coalescing, are sent with a single syscall as long as they fit.
+    pub write_buffer_size: usize,
+
+    /// If set, responses whose frame is larger than this number of bytes are discarded
+    /// without being buffered and the request fails with [`QueryError::ResponseTooLarge`].
+    /// Does not apply to the control connection.
+    pub max_response_frame_size: Option<usize>,
+
+    /// If set, result pages containing more rows than this value are rejected
+    /// and the request fails with [`QueryError::TooManyRows`].
+    /// Does not apply to the control connection.
+    pub max_rows_per_page: Option<usize>,
+
+    /// Number of requests abandoned by the caller for longer than `orphan_age_threshold`
+    /// after which a connection is closed and replaced with a new one.
+    pub orphan_count_threshold: usize,
+
+    /// Time after which a request abandoned by the caller, whose response has not arrived yet,
+    /// is counted towards `orphan_count_threshold`. Must be greater than zero,
+    /// otherwise [`Session::connect`] fails with [`NewSessionError::InvalidConfiguration`].
+    pub orphan_age_threshold: Duration,
+
+    /// If set, a connection with fewer free stream ids than this value is proactively closed
+    /// and replaced with a new one, so that e.g. a stuck shard does not starve the connection.
+    pub min_free_stream_ids: Option<usize>,
+
     /// Number of attempts to fetch [`TracingInfo`]
     /// in [`Session::get_tracing_info`]. Tracing info
     /// might not be available immediately on queried node - that's why
@@ -290,9 +429,39 @@ pub struct SessionConfig {
     /// or they expect the topology to change frequently.
     pub cluster_metadata_refresh_interval: Duration,
 
+    /// If provided, the control connection is only opened to nodes accepted by this filter
+    /// (and by [`Self::host_filter`]), unless it rejects all of them. Initial contact points
+    /// are used for the first control connection regardless of the filter.
+    /// See the [control_connection](crate::transport::control_connection) module for details.
+    pub control_connection_host_filter: Option<Arc<dyn HostFilter>>,
+
+    /// Delay before the first attempt to re-establish a broken control connection.
+    /// The delay doubles with each failed attempt, up to
+    /// [`Self::control_connection_max_reconnect_interval`]. Must be greater than zero
+    /// and not greater than the maximal delay, otherwise [`Session::connect`] fails with
+    /// [`NewSessionError::InvalidConfiguration`].
+    pub control_connection_min_reconnect_interval: Duration,
+
+    /// Maximal delay between the attempts to re-establish a broken control connection.
+    pub control_connection_max_reconnect_interval: Duration,
+
+    /// If set, fetching the cluster metadata on the control connection fails
+    /// after this time, and the fetch is attempted on another node.
+    /// If not set, metadata fetches are not timed out.
+    pub metadata_request_timeout: Option<Duration>,
+
+    /// If provided, the listener is notified when the control connection changes hosts.
+    pub control_connection_listener: Option<Arc<dyn ControlConnectionListener>>,
+
     /// Driver and application self-identifying information,
     /// to be sent to server in STARTUP message.
     pub identity: SelfIdentity<'static>,
+
+    /// Custom options to be sent to server in STARTUP message, e.g. for a CQL-aware proxy.
+    /// Options reserved for the driver (see
+    /// [`is_reserved_startup_option`](scylla_cql::frame::request::options::is_reserved_startup_option))
+    /// are rejected when creating the session.
+    pub custom_startup_options: HashMap<String, String>,
 }
 
 impl SessionConfig {
@@ -310,8 +479,14 @@ impl SessionConfig {
         SessionConfig {
             known_nodes: Vec::new(),
             compression: None,
+            compression_threshold: 0,
             tcp_nodelay: true,
             tcp_keepalive_interval: None,
+            tcp_keepalive_probe_interval: Duration::from_secs(1),
+            tcp_keepalive_retries: 10,
+            tcp_send_buffer_size: None,
+            tcp_recv_buffer_size: None,
+            local_ip_address: None,
             schema_agreement_interval: Duration::from_millis(200),
             default_execution_profile_handle: ExecutionProfile::new_from_inner(Default::default())
                 .into_handle(),
@@ -323,23 +498,47 @@ impl SessionConfig {
             connect_timeout: Duration::from_secs(5),
             connection_pool_size: Default::default(),
             disallow_shard_aware_port: false,
+            shard_aware_port_retry_interval: None,
             keyspaces_to_fetch: Vec::new(),
             fetch_schema_metadata: true,
             keepalive_interval: Some(Duration::from_secs(30)),
             keepalive_timeout: Some(Duration::from_secs(30)),
+            keepalive_max_missed: NonZeroU32::new(1).unwrap(),
+            keepalive_request: KeepaliveRequest::Options,
             schema_agreement_timeout: Duration::from_secs(60),
             schema_agreement_automatic_waiting: true,
             address_translator: None,
+            connector: None,
+            column_encryption_policy: None,
+            frame_capture: None,
+            runtime: default_runtime(),
+            query_log: None,
+            load_shedding_policy: None,
+            write_defaults_policy: None,
+            table_metrics: true,
+            max_tracked_tables: 100,
             host_filter: None,
             refresh_metadata_on_auto_schema_agreement: true,
             #[cfg(feature = "cloud")]
             cloud_config: None,
             enable_write_coalescing: true,
+            write_buffer_size: 8192,
+            max_response_frame_size: None,
+            max_rows_per_page: None,
+            orphan_count_threshold: 1024,
+            orphan_age_threshold: Duration::from_secs(1),
+            min_free_stream_ids: None,
             tracing_info_fetch_attempts: NonZeroU32::new(10).unwrap(),
             tracing_info_fetch_interval: Duration::from_millis(3),
             tracing_info_fetch_consistency: Consistency::One,
             cluster_metadata_refresh_interval: Duration::from_secs(60),
+            control_connection_host_filter: None,
+            control_connection_min_reconnect_interval: Duration::from_secs(1),
+            control_connection_max_reconnect_interval: Duration::from_secs(1),
+            metadata_request_timeout: None,
+            control_connection_listener: None,
             identity: SelfIdentity::default(),
+            custom_startup_options: HashMap::new(),
         }
     }
 
@@ -501,12 +700,51 @@ impl Session {
             return Err(NewSessionError::EmptyKnownNodesList);
         }
 
+        // A zero period would make the connections check their orphaned requests in a busy loop
+        if config.orphan_age_threshold.is_zero() {
+            return Err(NewSessionError::InvalidConfiguration(
+                "orphan age threshold must be greater than zero".to_owned(),
+            ));
+        }
+        // A zero interval would make the control connection reconnect in a busy loop
+        if config.control_connection_min_reconnect_interval.is_zero()
+            || config.control_connection_min_reconnect_interval
+                > config.control_connection_max_reconnect_interval
+        {
+            return Err(NewSessionError::InvalidConfiguration(
+                "minimal reconnect interval of the control connection must be greater than zero \
+                 and not greater than the maximal one"
+                    .to_owned(),
+            ));
+        }
+        if let Some(key) = config
+            .custom_startup_options
+            .keys()
+            .find(|key| options::is_reserved_startup_option(key))
+        {
+            return Err(NewSessionError::InvalidConfiguration(format!(
+                "STARTUP option {key} is reserved for the driver"
+            )));
+        }
+
         let (tablet_sender, tablet_receiver) = tokio::sync::mpsc::channel(TABLET_CHANNEL_SIZE);
 
+        let metrics = Arc::new(if config.table_metrics {
+            Metrics::with_table_metrics(config.max_tracked_tables)
+        } else {
+            Metrics::new()
+        });
+
         let connection_config = ConnectionConfig {
             compression: config.compression,
+            compression_threshold: config.compression_threshold,
             tcp_nodelay: config.tcp_nodelay,
             tcp_keepalive_interval: config.tcp_keepalive_interval,
+            tcp_keepalive_probe_interval: config.tcp_keepalive_probe_interval,
+            tcp_keepalive_retries: config.tcp_keepalive_retries,
+            tcp_send_buffer_size: config.tcp_send_buffer_size,
+            tcp_recv_buffer_size: config.tcp_recv_buffer_size,
+            local_ip_address: config.local_ip_address,
             #[cfg(feature = "ssl")]
             ssl_config: config.ssl_context.map(SslConfig::new_with_global_context),
             authenticator: config.authenticator.clone(),
@@ -514,29 +752,54 @@ impl Session {
             event_sender: None,
             default_consistency: Default::default(),
             address_translator: config.address_translator,
+            connector: config.connector,
+            column_encryption_policy: config.column_encryption_policy,
+            frame_capture: config.frame_capture,
+            runtime: config.runtime.clone(),
             #[cfg(feature = "cloud")]
             cloud_config: config.cloud_config,
             enable_write_coalescing: config.enable_write_coalescing,
+            write_buffer_size: config.write_buffer_size,
+            max_response_frame_size: config.max_response_frame_size,
+            max_rows_per_page: config.max_rows_per_page,
+            orphan_count_threshold: config.orphan_count_threshold,
+            orphan_age_threshold: config.orphan_age_threshold,
+            min_free_stream_ids: config.min_free_stream_ids,
             keepalive_interval: config.keepalive_interval,
             keepalive_timeout: config.keepalive_timeout,
+            keepalive_max_missed: config.keepalive_max_missed,
+            keepalive_request: config.keepalive_request,
+            keepalive_failures: None,
             tablet_sender: Some(tablet_sender),
             identity: config.identity,
+            custom_startup_options: Arc::new(config.custom_startup_options),
+            metrics: Some(metrics.clone()),
         };
 
         let pool_config = PoolConfig {
             connection_config,
             pool_size: config.connection_pool_size,
             can_use_shard_aware_port: !config.disallow_shard_aware_port,
+            shard_aware_port_retry_interval: config.shard_aware_port_retry_interval,
             keepalive_interval: config.keepalive_interval,
         };
 
+        let control_connection_config = ControlConnectionConfig {
+            host_filter: config.control_connection_host_filter,
+            metadata_request_timeout: config.metadata_request_timeout,
+            min_reconnect_interval: config.control_connection_min_reconnect_interval,
+            max_reconnect_interval: config.control_connection_max_reconnect_interval,
+            listener: config.control_connection_listener,
+        };
+
         let cluster = Cluster::new(
             known_nodes,
             pool_config,
-            config.keyspaces_to_fetch,
+            config.keyspaces_to_fetch.clone(),
             config.fetch_schema_metadata,
             config.host_filter,
             config.cluster_metadata_refresh_interval,
+            control_connection_config,
             tablet_receiver,
         )
         .await?;
@@ -547,15 +810,22 @@ impl Session {
             cluster,
             default_execution_profile_handle,
             schema_agreement_interval: config.schema_agreement_interval,
-            metrics: Arc::new(Metrics::new()),
+            metrics,
             schema_agreement_timeout: config.schema_agreement_timeout,
             schema_agreement_automatic_waiting: config.schema_agreement_automatic_waiting,
             refresh_metadata_on_auto_schema_agreement: config
                 .refresh_metadata_on_auto_schema_agreement,
             keyspace_name: ArcSwapOption::default(), // will be set by use_keyspace
+            effective_keyspace_name: ArcSwapOption::default(),
+            use_keyspace_lock: tokio::sync::Mutex::new(None),
+            keyspaces_to_fetch: config.keyspaces_to_fetch,
             tracing_info_fetch_attempts: config.tracing_info_fetch_attempts,
             tracing_info_fetch_interval: config.tracing_info_fetch_interval,
             tracing_info_fetch_consistency: config.tracing_info_fetch_consistency,
+            runtime: config.runtime,
+            query_log: config.query_log,
+            load_shedding_policy: config.load_shedding_policy,
+            write_defaults_policy: config.write_defaults_policy,
         };
 
         if let Some(keyspace_name) = config.used_keyspace {
@@ -643,6 +913,8 @@ impl Session {
         paging_state: Option<Bytes>,
     ) -> Result<QueryResult, QueryError> {
         let query: Query = query.into();
+        // Computed once, so that all attempts of the request use the same timestamp
+        let default_timestamp = self.query_default_timestamp(&query);
 
         let execution_profile = query
             .get_execution_profile_handle()
@@ -658,11 +930,18 @@ impl Session {
                 .config
                 .serial_consistency
                 .unwrap_or(execution_profile.serial_consistency),
+            token: query.get_routing_token(),
+            table: query.get_routing_table(),
             ..Default::default()
         };
 
         let span = RequestSpan::new_query(&query.contents);
         let span_ref = &span;
+        let logged_request = self
+            .query_log
+            .as_deref()
+            .map(|log| log.unprepared(&query.contents));
+        let execution_info = ExecutionInfoRecorder::for_statement(&query.config);
         let run_query_result = self
             .run_query(
                 statement_info,
@@ -688,6 +967,8 @@ impl Session {
                                     consistency,
                                     serial_consistency,
                                     paging_state_ref.clone(),
+                                    default_timestamp,
+                                    RowsFormat::Deserialized,
                                 )
                                 .await
                                 .and_then(QueryResponse::into_non_error_query_response)
@@ -702,6 +983,8 @@ impl Session {
                                     consistency,
                                     serial_consistency,
                                     paging_state_ref.clone(),
+                                    default_timestamp,
+                                    RowsFormat::Deserialized,
                                 )
                                 .await
                                 .and_then(QueryResponse::into_non_error_query_response)
@@ -709,15 +992,20 @@ impl Session {
                     }
                 },
                 &span,
+                logged_request.as_ref(),
+                execution_info.as_ref(),
             )
             .instrument(span.span().clone())
-            .await?;
+            .await;
+        let execution_info = ExecutionInfoRecorder::finish(execution_info, &query.config);
+        let run_query_result = run_query_result?;
 
         let response = match run_query_result {
             RunQueryResult::IgnoredWriteError => NonErrorQueryResponse {
                 response: NonErrorResponse::Result(result::Result::Void),
                 tracing_id: None,
                 warnings: Vec::new(),
+                custom_payload: None,
             },
             RunQueryResult::Completed(response) => response,
         };
@@ -725,7 +1013,8 @@ impl Session {
         self.handle_set_keyspace_response(&response).await?;
         self.handle_auto_await_schema_agreement(&response).await?;
 
-        let result = response.into_query_result()?;
+        let mut result = response.into_query_result()?;
+        result.execution_info = execution_info;
         span.record_result_fields(&result);
         Ok(result)
     }
@@ -807,7 +1096,10 @@ impl Session {
         query: impl Into<Query>,
         values: impl SerializeRow,
     ) -> Result<RowIterator, QueryError> {
-        let query: Query = query.into();
+        let mut query: Query = query.into();
+        if let Some(timestamp) = self.query_default_timestamp(&query) {
+            query.set_timestamp(Some(timestamp));
+        }
 
         let execution_profile = query
             .get_execution_profile_handle()
@@ -820,6 +1112,9 @@ impl Session {
                 execution_profile,
                 self.cluster.get_data(),
                 self.metrics.clone(),
+                self.runtime.clone(),
+                self.query_log.clone(),
+                self.load_shedding_policy.clone(),
             )
             .await
         } else {
@@ -834,6 +1129,9 @@ impl Session {
                 execution_profile,
                 cluster_data: self.cluster.get_data(),
                 metrics: self.metrics.clone(),
+                runtime: self.runtime.clone(),
+                query_log: self.query_log.clone(),
+                load_shedding_policy: self.load_shedding_policy.clone(),
             })
             .await
         }
@@ -852,7 +1150,6 @@ impl Session {
     /// > (see [performance section](https://rust-driver.docs.scylladb.com/stable/queries/prepared.html#performance))
     ///
     /// See [the book](https://rust-driver.docs.scylladb.com/stable/queries/prepared.html) for more information.
-    /// See the documentation of [`PreparedStatement`].
     ///
     /// # Arguments
     /// * `query` - query to prepare, can be just a `&str` or the [Query] struct.
@@ -918,6 +1215,148 @@ impl Session {
         Ok(prepared)
     }
 
+    /// Prepares a statement like [`Session::prepare`], with the default TTL supplied for its table
+    /// by the [write defaults policy](crate::transport::write_defaults) of the session.
+    ///
+    /// The TTL is added to the text of an `INSERT` or `UPDATE` statement which does not specify
+    /// its TTL, so [`PreparedStatement::get_statement`] returns the statement with the added
+    /// `USING TTL` clause. The table is taken from the text of the statement, with the keyspace
+    /// of the session for an unqualified table name. Other statements, and writes to counter
+    /// tables, which can't have a TTL, are prepared unchanged.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use scylla::Session;
+    /// # use std::error::Error;
+    /// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+    /// let insert = session
+    ///     .prepare_with_default_ttl("INSERT INTO ks.events (id, body) VALUES (?, ?)")
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn prepare_with_default_ttl(
+        &self,
+        query: impl Into<Query>,
+    ) -> Result<PreparedStatement, QueryError> {
+        let query = query.into();
+        let query_with_ttl = self
+            .written_table(&query.contents)
+            .filter(|table| !self.is_counter_table(table))
+            .and_then(|table| self.query_with_default_ttl(&query, &table));
+        let Some(query_with_ttl) = query_with_ttl else {
+            return self.prepare(query).await;
+        };
+
+        match self.prepare(query_with_ttl).await {
+            // Counter tables reject TTLs. If their schema is not known,
+            // they can be told only by the statement prepared without the TTL.
+            Err(
+                err @ QueryError::DbError {
+                    error: DbError::Invalid,
+                    ..
+                },
+            ) => {
+                let prepared = self.prepare(query).await?;
+                if !write_defaults::is_counter_write(prepared.get_variable_col_specs()) {
+                    return Err(err);
+                }
+                Ok(prepared)
+            }
+            result => result,
+        }
+    }
+
+    // Returns the table written by the statement, judging by its text, if a write defaults
+    // policy is set. Unqualified table names are taken to be in the keyspace of the session.
+    fn written_table(&self, statement: &str) -> Option<TableSpec<'static>> {
+        self.write_defaults_policy.as_ref()?;
+        let (keyspace, table) = write_defaults::write_target(statement)?;
+        let keyspace = match keyspace {
+            Some(keyspace) => keyspace,
+            None => self.effective_keyspace_name.load_full()?.to_string(),
+        };
+        Some(TableSpec::owned(keyspace, table))
+    }
+
+    // Returns the query with the TTL supplied by the write defaults policy for the table,
+    // if the query is a write which does not specify its TTL.
+    fn query_with_default_ttl(&self, query: &Query, table: &TableSpec<'_>) -> Option<Query> {
+        let policy = self.write_defaults_policy.as_deref()?;
+        let ttl = policy.default_ttl(table)?;
+        let mut query_with_ttl = query.clone();
+        query_with_ttl.contents = write_defaults::with_default_ttl(&query.contents, ttl)?;
+        Some(query_with_ttl)
+    }
+
+    // Tells if the table has counter columns, according to the schema metadata.
+    // Returns false if the schema of the table was not fetched.
+    fn is_counter_table(&self, table: &TableSpec<'_>) -> bool {
+        let cluster_data = self.get_cluster_data();
+        let table = cluster_data
+            .keyspaces
+            .get(table.ks_name())
+            .and_then(|keyspace| keyspace.tables.get(table.table_name()));
+        matches!(table, Some(table) if table.columns.values().any(|column| {
+            column.type_ == CqlType::Native(NativeType::Counter)
+        }))
+    }
+
+    // The following return the timestamp supplied by the write defaults policy for
+    // an execution of a statement, if it is an unconditional write without a timestamp set.
+
+    fn query_default_timestamp(&self, query: &Query) -> Option<i64> {
+        let policy = self.write_defaults_policy.as_deref()?;
+        if query.get_timestamp().is_some()
+            || !write_defaults::accepts_default_timestamp(&query.contents)
+        {
+            return None;
+        }
+        policy.default_timestamp(&self.written_table(&query.contents)?)
+    }
+
+    fn prepared_default_timestamp(&self, prepared: &PreparedStatement) -> Option<i64> {
+        let policy = self.write_defaults_policy.as_deref()?;
+        if prepared.get_timestamp().is_some()
+            || !write_defaults::accepts_default_timestamp(prepared.get_statement())
+        {
+            return None;
+        }
+        // Statements without bind markers have no table in their metadata
+        match prepared.get_table_spec() {
+            Some(table) => policy.default_timestamp(table),
+            None => policy.default_timestamp(&self.written_table(prepared.get_statement())?),
+        }
+    }
+
+    // A batch gets the timestamp of the table of its first statement,
+    // if all of its statements accept one.
+    fn batch_default_timestamp(&self, batch: &Batch) -> Option<i64> {
+        let policy = self.write_defaults_policy.as_deref()?;
+        if batch.get_timestamp().is_some() {
+            return None;
+        }
+        fn statement(statement: &BatchStatement) -> &str {
+            match statement {
+                BatchStatement::Query(query) => &query.contents,
+                BatchStatement::PreparedStatement(prepared) => prepared.get_statement(),
+            }
+        }
+        if !batch
+            .statements
+            .iter()
+            .all(|s| write_defaults::accepts_default_timestamp(statement(s)))
+        {
+            return None;
+        }
+        match batch.statements.first()? {
+            BatchStatement::PreparedStatement(prepared) if prepared.get_table_spec().is_some() => {
+                policy.default_timestamp(prepared.get_table_spec()?)
+            }
+            first => policy.default_timestamp(&self.written_table(statement(first))?),
+        }
+    }
+
     fn extract_partitioner_name<'a>(
         &self,
         prepared: &PreparedStatement,
@@ -990,13 +1429,21 @@ impl Session {
         values: impl SerializeRow,
         paging_state: Option<Bytes>,
     ) -> Result<QueryResult, QueryError> {
+        // Computed once, so that all attempts of the request use the same timestamp
+        let default_timestamp = self.prepared_default_timestamp(prepared);
         let serialized_values = prepared.serialize_values(&values)?;
         let values_ref = &serialized_values;
         let paging_state_ref = &paging_state;
 
-        let (partition_key, token) = prepared
-            .extract_partition_key_and_calculate_token(prepared.get_partitioner_name(), values_ref)?
-            .unzip();
+        let (partition_key, token) = match prepared.calculate_routing_key_token() {
+            Some(token) => (None, Some(token)),
+            None => prepared
+                .extract_partition_key_and_calculate_token(
+                    prepared.get_partitioner_name(),
+                    values_ref,
+                )?
+                .unzip(),
+        };
 
         let execution_profile = prepared
             .get_execution_profile_handle()
@@ -1035,7 +1482,13 @@ impl Session {
             }
         }
 
-        let run_query_result: RunQueryResult<NonErrorQueryResponse> = self
+        let logged_request = self
+            .query_log
+            .as_deref()
+            .map(|log| log.prepared(prepared, values_ref));
+
+        let execution_info = ExecutionInfoRecorder::for_statement(&prepared.config);
+        let run_query_result = self
             .run_query(
                 statement_info,
                 &prepared.config,
@@ -1055,21 +1508,28 @@ impl Session {
                                 consistency,
                                 serial_consistency,
                                 paging_state_ref.clone(),
+                                default_timestamp,
+                                RowsFormat::Deserialized,
                             )
                             .await
                             .and_then(QueryResponse::into_non_error_query_response)
                     }
                 },
                 &span,
+                logged_request.as_ref(),
+                execution_info.as_ref(),
             )
             .instrument(span.span().clone())
-            .await?;
+            .await;
+        let execution_info = ExecutionInfoRecorder::finish(execution_info, &prepared.config);
+        let run_query_result: RunQueryResult<NonErrorQueryResponse> = run_query_result?;
 
         let response = match run_query_result {
             RunQueryResult::IgnoredWriteError => NonErrorQueryResponse {
                 response: NonErrorResponse::Result(result::Result::Void),
                 tracing_id: None,
                 warnings: Vec::new(),
+                custom_payload: None,
             },
             RunQueryResult::Completed(response) => response,
         };
@@ -1077,7 +1537,8 @@ impl Session {
         self.handle_set_keyspace_response(&response).await?;
         self.handle_auto_await_schema_agreement(&response).await?;
 
-        let result = response.into_query_result()?;
+        let mut result = response.into_query_result()?;
+        result.execution_info = execution_info;
         span.record_result_fields(&result);
         Ok(result)
     }
@@ -1128,7 +1589,10 @@ impl Session {
         prepared: impl Into<PreparedStatement>,
         values: impl SerializeRow,
     ) -> Result<RowIterator, QueryError> {
-        let prepared = prepared.into();
+        let mut prepared = prepared.into();
+        if let Some(timestamp) = self.prepared_default_timestamp(&prepared) {
+            prepared.set_timestamp(Some(timestamp));
+        }
         let serialized_values = prepared.serialize_values(&values)?;
 
         let execution_profile = prepared
@@ -1142,6 +1606,9 @@ impl Session {
             execution_profile,
             cluster_data: self.cluster.get_data(),
             metrics: self.metrics.clone(),
+            runtime: self.runtime.clone(),
+            query_log: self.query_log.clone(),
+            load_shedding_policy: self.load_shedding_policy.clone(),
         })
         .await
     }
@@ -1226,12 +1693,11 @@ impl Session {
             batch_values::peek_first_token(values, batch.statements.first())?;
         let values_ref = &values;
 
-        let table_spec =
-            if let Some(BatchStatement::PreparedStatement(ps)) = batch.statements.first() {
-                ps.get_table_spec()
-            } else {
-                None
-            };
+        let table_spec = match batch.statements.first() {
+            Some(BatchStatement::PreparedStatement(ps)) => ps.get_table_spec(),
+            Some(BatchStatement::Query(q)) => q.get_routing_table(),
+            None => None,
+        };
 
         let statement_info = RoutingInfo {
             consistency,
@@ -1241,8 +1707,13 @@ impl Session {
             is_confirmed_lwt: false,
         };
 
+        // Computed once, so that all attempts of the batch use the same timestamp
+        let default_timestamp = self.batch_default_timestamp(batch);
+
         let span = RequestSpan::new_batch();
+        let logged_request = self.query_log.as_deref().map(|log| log.batch(batch));
 
+        let execution_info = ExecutionInfoRecorder::for_statement(&batch.config);
         let run_query_result = self
             .run_query(
                 statement_info,
@@ -1262,25 +1733,237 @@ impl Session {
                                 values_ref,
                                 consistency,
                                 serial_consistency,
+                                default_timestamp,
                             )
                             .await
                     }
                 },
                 &span,
+                logged_request.as_ref(),
+                execution_info.as_ref(),
             )
             .instrument(span.span().clone())
-            .await?;
+            .await;
+        let execution_info = ExecutionInfoRecorder::finish(execution_info, &batch.config);
+        let run_query_result = run_query_result?;
 
-        let result = match run_query_result {
+        let mut result = match run_query_result {
             RunQueryResult::IgnoredWriteError => QueryResult::default(),
             RunQueryResult::Completed(response) => response,
         };
+        result.execution_info = execution_info;
         span.record_result_fields(&result);
         Ok(result)
     }
 
+    /// Executes a multi-statement CQL script.
+    ///
+    /// The script is split into statements separated by semicolons, respecting string literals,
+    /// quoted identifiers, comments and `BEGIN BATCH ... APPLY BATCH` blocks (see
+    /// [`split_statements`](crate::transport::script::split_statements)). The statements are
+    /// executed one by one, in order, as unprepared queries with the default execution profile.
+    /// `USE` statements change the keyspace of the session, like with [`Session::query`].
+    ///
+    /// If [automatic waiting for schema agreement](crate::transport::session_builder::GenericSessionBuilder::auto_await_schema_agreement)
+    /// is enabled (which is the default), each statement changing the schema is followed by waiting
+    /// for schema agreement, so that the following statements can use the created objects.
+    ///
+    /// Execution stops at the first failed statement, which is reported in the returned
+    /// [`ScriptError::StatementFailed`]. If the script cannot be split into statements,
+    /// none of them are executed.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use scylla::Session;
+    /// # use std::error::Error;
+    /// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+    /// session
+    ///     .execute_script(
+    ///         "CREATE KEYSPACE IF NOT EXISTS ks
+    ///              WITH replication = {'class': 'NetworkTopologyStrategy', 'replication_factor': 3};
+    ///          CREATE TABLE IF NOT EXISTS ks.tab (a int PRIMARY KEY, b text);
+    ///          INSERT INTO ks.tab (a, b) VALUES (1, 'first; and only');",
+    ///     )
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn execute_script(&self, script: &str) -> Result<(), ScriptError> {
+        let statements = script::split_statements(script)?;
+        for (index, statement) in statements.into_iter().enumerate() {
+            self.query(statement, &[])
+                .await
+                .map_err(|error| ScriptError::StatementFailed {
+                    index,
+                    statement: statement.to_owned(),
+                    error,
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns a handle to a single connection of the session, over which statements
+    /// can be [pipelined](crate::transport::pipeline) with results in submission order.
+    ///
+    /// The connection leads to the node and shard which the load balancing policy of the
+    /// statement's execution profile picks first for the statement with the given values,
+    /// usually a replica owning their partition. The statement itself is not executed.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use scylla::Session;
+    /// # use std::error::Error;
+    /// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+    /// let update = session
+    ///     .prepare("UPDATE ks.tab SET b = ? WHERE a = ?")
+    ///     .await?;
+    ///
+    /// let handle = session.connection_for_statement(&update, (0_i32, 1_i32)).await?;
+    /// let mut pipeline = handle.pipeline();
+    /// pipeline
+    ///     .set_ordered_timestamps(true)
+    ///     .append_prepared(&update, (1_i32, 1_i32))?
+    ///     .append_prepared(&update, (2_i32, 1_i32))?;
+    ///
+    /// // b = 2, even if the server applied the updates in a different order
+    /// let results = pipeline.run().await;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn connection_for_statement(
+        &self,
+        prepared: &PreparedStatement,
+        values: impl SerializeRow,
+    ) -> Result<ConnectionHandle, QueryError> {
+        let serialized_values = prepared.serialize_values(&values)?;
+        let token = match prepared.calculate_routing_key_token() {
+            Some(token) => Some(token),
+            None => prepared
+                .extract_partition_key_and_calculate_token(
+                    prepared.get_partitioner_name(),
+                    &serialized_values,
+                )?
+                .map(|(_, token)| token),
+        };
+
+        let execution_profile = prepared
+            .get_execution_profile_handle()
+            .unwrap_or_else(|| self.get_default_execution_profile_handle())
+            .access();
+
+        let statement_info = RoutingInfo {
+            consistency: prepared
+                .config
+                .consistency
+                .unwrap_or(execution_profile.consistency),
+            serial_consistency: prepared
+                .config
+                .serial_consistency
+                .unwrap_or(execution_profile.serial_consistency),
+            token,
+            table: prepared.get_table_spec(),
+            is_confirmed_lwt: prepared.is_confirmed_lwt(),
+        };
+
+        let cluster_data = self.get_cluster_data();
+        let (node, shard) = load_balancing::Plan::new(
+            execution_profile.load_balancing_policy.as_ref(),
+            &statement_info,
+            &cluster_data,
+        )
+        .next()
+        .ok_or_else(|| {
+            QueryError::from(std::io::Error::new(
+                std::io::ErrorKind::NotConnected,
+                "No node to connect to: the load balancing policy returned an empty plan",
+            ))
+        })?;
+        let connection = node.connection_for_shard(shard).await?;
+
+        Ok(ConnectionHandle::new(
+            connection,
+            node.clone(),
+            self.get_default_execution_profile_handle().clone(),
+            self.runtime.clone(),
+        ))
+    }
+
+    /// Prepares many statements at once.
+    ///
+    /// All statements are prepared concurrently, each of them on all connections of the pool,
+    /// like with [`Session::prepare`]. Statements with identical texts are prepared only once.
+    /// The prepared statements are returned in the order of `queries`, and each of them keeps
+    /// the settings (consistency, page size, etc.) of the query it was prepared from.
+    ///
+    /// Fails with the first error encountered while preparing any of the statements.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use scylla::Session;
+    /// # use std::error::Error;
+    /// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+    /// use scylla::prepared_statement::PreparedStatement;
+    ///
+    /// let prepared: Vec<PreparedStatement> = session
+    ///     .prepare_all([
+    ///         "INSERT INTO ks.tab (a, b) VALUES(?, ?)",
+    ///         "SELECT b FROM ks.tab WHERE a = ?",
+    ///         "DELETE FROM ks.tab WHERE a = ?",
+    ///     ])
+    ///     .await?;
+    ///
+    /// session.execute(&prepared[0], (1_i32, 2_i32)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn prepare_all(
+        &self,
+        queries: impl IntoIterator<Item = impl Into<Query>>,
+    ) -> Result<Vec<PreparedStatement>, QueryError> {
+        let queries: Vec<Query> = queries.into_iter().map(Into::into).collect();
+
+        // For each query, the index of the first query with the same text,
+        // which is the one that actually gets prepared.
+        let mut first_with_text: HashMap<&str, usize> = HashMap::new();
+        let originals: Vec<usize> = queries
+            .iter()
+            .enumerate()
+            .map(|(idx, query)| {
+                *first_with_text
+                    .entry(query.contents.as_str())
+                    .or_insert(idx)
+            })
+            .collect();
+
+        let mut prepared = try_join_all(
+            queries
+                .iter()
+                .zip(&originals)
+                .enumerate()
+                .filter(|(idx, (_, original))| idx == *original)
+                .map(|(_, (query, _))| self.prepare(query.clone())),
+        )
+        .await?
+        .into_iter();
+
+        let mut statements: Vec<PreparedStatement> = Vec::with_capacity(queries.len());
+        for (query, original) in queries.iter().zip(originals) {
+            let statement = if original == statements.len() {
+                // Unique statements were prepared in the order of their first occurrence
+                prepared.next().unwrap()
+            } else {
+                statements[original].with_settings_of(query)
+            };
+            statements.push(statement);
+        }
+
+        Ok(statements)
+    }
+
     /// Prepares all statements within the batch and returns a new batch where every
     /// statement is prepared.
+    /// Statements with identical texts are prepared only once, see [`Session::prepare_all`].
     /// /// # Example
     /// ```rust
     /// # extern crate scylla;
@@ -1309,19 +1992,21 @@ impl Session {
     pub async fn prepare_batch(&self, batch: &Batch) -> Result<Batch, QueryError> {
         let mut prepared_batch = batch.clone();
 
-        try_join_all(
-            prepared_batch
-                .statements
-                .iter_mut()
-                .map(|statement| async move {
-                    if let BatchStatement::Query(query) = statement {
-                        let prepared = self.prepare(query.clone()).await?;
-                        *statement = BatchStatement::PreparedStatement(prepared);
-                    }
-                    Ok::<(), QueryError>(())
-                }),
-        )
-        .await?;
+        let queries: Vec<Query> = batch
+            .statements
+            .iter()
+            .filter_map(|statement| match statement {
+                BatchStatement::Query(query) => Some(query.clone()),
+                BatchStatement::PreparedStatement(_) => None,
+            })
+            .collect();
+        let mut prepared = self.prepare_all(queries).await?.into_iter();
+
+        for statement in prepared_batch.statements.iter_mut() {
+            if let BatchStatement::Query(_) = statement {
+                *statement = BatchStatement::PreparedStatement(prepared.next().unwrap());
+            }
+        }
 
         Ok(prepared_batch)
     }
@@ -1329,12 +2014,21 @@ impl Session {
     /// Sends `USE <keyspace_name>` request on all connections\
     /// This allows to write `SELECT * FROM table` instead of `SELECT * FROM keyspace.table`\
     ///
+    /// If the cluster metadata is fetched for the keyspace (see [`SessionBuilder::keyspaces_to_fetch`](crate::SessionBuilder::keyspaces_to_fetch)),
+    /// the keyspace is first checked to exist, refreshing the metadata once if needed.
+    /// A keyspace missing from the metadata fails with [`BadQuery::KeyspaceNotFound`].
+    /// Such refreshes are done at most once every few seconds; if the metadata was refreshed
+    /// recently, it is checked without refreshing it again. If the refresh fails,
+    /// the keyspace is left for the database to reject.
+    ///
     /// Note that even failed `use_keyspace` can change currently used keyspace - the request is sent on all connections and
     /// can overwrite previously used keyspace.
     ///
-    /// Call only one `use_keyspace` at a time.\
-    /// Trying to do two `use_keyspace` requests simultaneously with different names
-    /// can end with some connections using one keyspace and the rest using the other.
+    /// Concurrent calls are executed one after another, so that all connections end up using the same keyspace.
+    /// Connections opened later, e.g. after a reconnect, are switched to the keyspace before being used;
+    /// a new connection which fails to switch is not used until switching, retried with a backoff, succeeds.
+    ///
+    /// The keyspace currently in use can be read with [`Session::get_keyspace`].
     ///
     /// See [the book](https://rust-driver.docs.scylladb.com/stable/queries/usekeyspace.html) for more information
     ///
@@ -1342,7 +2036,8 @@ impl Session {
     ///
     /// * `keyspace_name` - keyspace name to use,
     ///   keyspace names can have up to 48 alphanumeric characters and contain underscores
-    /// * `case_sensitive` - if set to true the generated query will put keyspace name in quotes
+    /// * `case_sensitive` - if set to true the generated query will put keyspace name in quotes.
+    ///   A name already enclosed in double quotes is always treated as case sensitive.
     /// # Example
     /// ```rust
     /// # use scylla::{Session, SessionBuilder};
@@ -1368,19 +2063,78 @@ impl Session {
         case_sensitive: bool,
     ) -> Result<(), QueryError> {
         let keyspace_name = keyspace_name.into();
-        self.keyspace_name
-            .store(Some(Arc::new(keyspace_name.clone())));
 
         // Trying to pass keyspace as bound value in "USE ?" doesn't work
         // So we have to create a string for query: "USE " + new_keyspace
         // To avoid any possible CQL injections it's good to verify that the name is valid
-        let verified_ks_name = VerifiedKeyspaceName::new(keyspace_name, case_sensitive)?;
+        let verified_ks_name = VerifiedKeyspaceName::new(keyspace_name.clone(), case_sensitive)?;
+
+        let mut last_refresh = self.use_keyspace_lock.lock().await;
+
+        let effective_name = verified_ks_name.effective_name();
+        self.verify_keyspace_exists(&effective_name, &mut last_refresh)
+            .await?;
 
         self.cluster.use_keyspace(verified_ks_name).await?;
 
+        self.keyspace_name.store(Some(Arc::new(keyspace_name)));
+        self.effective_keyspace_name
+            .store(Some(Arc::new(effective_name)));
+
         Ok(())
     }
 
+    // Checks the keyspace against the cluster metadata, if the metadata of the keyspace is fetched.
+    // The metadata may be stale, e.g. if the keyspace has just been created,
+    // so it is refreshed once before giving up. To avoid refreshing the metadata of
+    // the whole cluster on each call for a missing keyspace, the refresh is done at most once
+    // per `KEYSPACE_VERIFICATION_REFRESH_INTERVAL`; within it, the metadata fetched by
+    // the last refresh is recent enough to reject the keyspace.
+    async fn verify_keyspace_exists(
+        &self,
+        keyspace_name: &str,
+        last_refresh: &mut Option<Instant>,
+    ) -> Result<(), QueryError> {
+        if !self.keyspaces_to_fetch.is_empty()
+            && !self.keyspaces_to_fetch.iter().any(|ks| ks == keyspace_name)
+        {
+            return Ok(());
+        }
+
+        let is_known = |session: &Session| {
+            session
+                .get_cluster_data()
+                .get_keyspace_info()
+                .contains_key(keyspace_name)
+        };
+        if is_known(self) {
+            return Ok(());
+        }
+
+        let refreshed_recently = matches!(
+            *last_refresh,
+            Some(time) if time.elapsed() < KEYSPACE_VERIFICATION_REFRESH_INTERVAL
+        );
+        if !refreshed_recently {
+            if let Err(err) = self.refresh_metadata().await {
+                // Let the database validate the keyspace.
+                debug!(
+                    "Failed to refresh metadata while verifying keyspace {}: {}",
+                    keyspace_name, err
+                );
+                return Ok(());
+            }
+            *last_refresh = Some(Instant::now());
+            if is_known(self) {
+                return Ok(());
+            }
+        }
+
+        Err(QueryError::BadQuery(BadQuery::KeyspaceNotFound(
+            keyspace_name.to_owned(),
+        )))
+    }
+
     /// Manually trigger a metadata refresh\
     /// The driver will fetch current nodes in the cluster and update its metadata
     ///
@@ -1404,6 +2158,97 @@ impl Session {
         self.cluster.get_data()
     }
 
+    /// Returns information about all working connections of the session,
+    /// as negotiated with the nodes: protocol version, compression,
+    /// shard-aware port usage and Scylla protocol extensions.
+    ///
+    /// To get the information for a single node, see [`Node::get_connections_info`].
+    pub fn connection_info(&self) -> Vec<ConnectionInfo> {
+        self.get_cluster_data()
+            .get_nodes_info()
+            .iter()
+            .flat_map(|node| node.get_connections_info())
+            .collect()
+    }
+
+    /// Computes the query plan, i.e. the nodes and shards in the order in which
+    /// they would be tried, for executing the prepared statement with the given values.
+    ///
+    /// The plan is computed by the load balancing policy of the statement's execution
+    /// profile, against the current cluster metadata. This is meant for debugging and tests.
+    /// Unless the policy is deterministic (see [`DefaultPolicyBuilder::fixed_seed`](crate::load_balancing::DefaultPolicyBuilder::fixed_seed)),
+    /// the plan used when the statement is actually executed may differ.
+    pub fn get_query_plan_for_prepared(
+        &self,
+        prepared: &PreparedStatement,
+        values: impl SerializeRow,
+    ) -> Result<Vec<(Arc<Node>, Shard)>, QueryError> {
+        let serialized_values = prepared.serialize_values(&values)?;
+        let token = prepared.calculate_token_untyped(&serialized_values)?;
+
+        let execution_profile = prepared
+            .get_execution_profile_handle()
+            .unwrap_or_else(|| self.get_default_execution_profile_handle())
+            .access();
+
+        let routing_info = RoutingInfo {
+            consistency: prepared
+                .config
+                .consistency
+                .unwrap_or(execution_profile.consistency),
+            serial_consistency: prepared
+                .config
+                .serial_consistency
+                .unwrap_or(execution_profile.serial_consistency),
+            token,
+            table: prepared.get_table_spec(),
+            is_confirmed_lwt: prepared.is_confirmed_lwt(),
+        };
+
+        Ok(self.collect_query_plan(&routing_info, &execution_profile))
+    }
+
+    /// Computes the query plan for executing the unprepared query.
+    ///
+    /// See [`Session::get_query_plan_for_prepared`] for details.
+    pub fn get_query_plan_for_query(&self, query: &Query) -> Vec<(Arc<Node>, Shard)> {
+        let execution_profile = query
+            .get_execution_profile_handle()
+            .unwrap_or_else(|| self.get_default_execution_profile_handle())
+            .access();
+
+        let routing_info = RoutingInfo {
+            consistency: query
+                .config
+                .consistency
+                .unwrap_or(execution_profile.consistency),
+            serial_consistency: query
+                .config
+                .serial_consistency
+                .unwrap_or(execution_profile.serial_consistency),
+            token: query.get_routing_token(),
+            table: query.get_routing_table(),
+            ..Default::default()
+        };
+
+        self.collect_query_plan(&routing_info, &execution_profile)
+    }
+
+    fn collect_query_plan(
+        &self,
+        routing_info: &RoutingInfo,
+        execution_profile: &ExecutionProfileInner,
+    ) -> Vec<(Arc<Node>, Shard)> {
+        let cluster_data = self.get_cluster_data();
+        load_balancing::Plan::new(
+            execution_profile.load_balancing_policy.as_ref(),
+            routing_info,
+            &cluster_data,
+        )
+        .map(|(node, shard)| (node.clone(), shard))
+        .collect()
+    }
+
     /// Get [`TracingInfo`] of a traced query performed earlier
     ///
     /// See [the book](https://rust-driver.docs.scylladb.com/stable/tracing/tracing.html)
@@ -1417,7 +2262,7 @@ impl Session {
 
             match current_try {
                 Some(tracing_info) => return Ok(tracing_info),
-                None => tokio::time::sleep(self.tracing_info_fetch_interval).await,
+                None => self.runtime.sleep(self.tracing_info_fetch_interval).await,
             };
         }
 
@@ -1436,9 +2281,11 @@ impl Session {
     /// in the session configuration, but calling `use_keyspace` will update
     /// it.
     ///
-    /// Note: the return value might be wrong if `use_keyspace` was called
-    /// concurrently or it previously failed. It is also unspecified
-    /// if `get_keyspace` is called concurrently with `use_keyspace`.
+    /// The name is returned as it was passed to `use_keyspace`, e.g. with
+    /// the double quotes of a quoted name.
+    ///
+    /// Note: the return value might be wrong if `use_keyspace` previously failed.
+    /// It is also unspecified if `get_keyspace` is called concurrently with `use_keyspace`.
     #[inline]
     pub fn get_keyspace(&self) -> Option<Arc<String>> {
         self.keyspace_name.load_full()
@@ -1515,6 +2362,7 @@ impl Session {
     // On success this query's result is returned
     // I tried to make this closures take a reference instead of an Arc but failed
     // maybe once async closures get stabilized this can be fixed
+    #[allow(clippy::too_many_arguments)]
     async fn run_query<'a, QueryFut, ResT>(
         &'a self,
         statement_info: RoutingInfo<'a>,
@@ -1522,6 +2370,8 @@ impl Session {
         execution_profile: Arc<ExecutionProfileInner>,
         do_query: impl Fn(Arc<Connection>, Consistency, &ExecutionProfileInner) -> QueryFut,
         request_span: &'a RequestSpan,
+        logged_request: Option<&'a LoggedRequest<'a>>,
+        execution_info: Option<&'a ExecutionInfoRecorder>,
     ) -> Result<RunQueryResult<ResT>, QueryError>
     where
         QueryFut: Future<Output = Result<ResT, QueryError>>,
@@ -1534,8 +2384,27 @@ impl Session {
                 .map(|hl| (&**hl, hl.log_query_start()));
 
         let load_balancer = &execution_profile.load_balancing_policy;
+        let table = statement_info.table;
+        let last_attempt_node: StdMutex<Option<SocketAddr>> = StdMutex::new(None);
+        let attempts_in_flight = AtomicUsize::new(0);
 
         let runner = async {
+            // Held until the request completes, so that the limit
+            // also covers its retries and speculative executions
+            let _in_flight_permit = match &self.load_shedding_policy {
+                Some(policy) => match policy
+                    .acquire(statement_config.priority, &*self.runtime)
+                    .await
+                {
+                    Ok(permit) => Some(permit),
+                    Err(err) => {
+                        self.metrics.inc_shed_requests();
+                        return Err(err);
+                    }
+                },
+                None => None,
+            };
+
             let cluster_data = self.cluster.get_data();
             let query_plan =
                 load_balancing::Plan::new(load_balancer.as_ref(), &statement_info, &cluster_data);
@@ -1593,6 +2462,9 @@ impl Session {
 
                         if is_speculative {
                             request_span.inc_speculative_executions();
+                            if let Some(execution_info) = execution_info {
+                                execution_info.record_speculative_execution();
+                            }
                         }
 
                         self.execute_query(
@@ -1606,6 +2478,11 @@ impl Session {
                                 history_data,
                                 query_info: &statement_info,
                                 request_span,
+                                logged_request,
+                                execution_info,
+                                is_speculative,
+                                last_attempt_node: &last_attempt_node,
+                                attempts_in_flight: &attempts_in_flight,
                             },
                         )
                     };
@@ -1617,6 +2494,7 @@ impl Session {
                     speculative_execution::execute(
                         speculative.as_ref(),
                         &context,
+                        &*self.runtime,
                         execute_query_generator,
                     )
                     .await
@@ -1641,6 +2519,11 @@ impl Session {
                             history_data,
                             query_info: &statement_info,
                             request_span,
+                            logged_request,
+                            execution_info,
+                            is_speculative: false,
+                            last_attempt_node: &last_attempt_node,
+                            attempts_in_flight: &attempts_in_flight,
                         },
                     )
                     .await
@@ -1651,22 +2534,54 @@ impl Session {
             }
         };
 
+        // Dropping the runner stops retries and speculative executions of the request.
+        let runner = async {
+            tokio::select! {
+                biased;
+                _ = cancellation::cancelled(statement_config.cancel_handle.as_ref()) => {
+                    Err(QueryError::RequestCancelled)
+                }
+                result = runner => result,
+            }
+        };
+
+        // If the future of this function is dropped before the request completes,
+        // the guard counts the request as cancelled.
+        let mut cancellation_guard = CancellationGuard {
+            metrics: &self.metrics,
+            completed: false,
+        };
+
         let effective_timeout = statement_config
             .request_timeout
             .or(execution_profile.request_timeout);
         let result = match effective_timeout {
-            Some(timeout) => tokio::time::timeout(timeout, runner)
+            Some(timeout) => runtime::timeout(&*self.runtime, timeout, runner)
                 .await
                 .unwrap_or_else(|e| {
-                    Err(QueryError::RequestTimeout(format!(
+                    let error = QueryError::from(TimeoutError::Request(format!(
                         "Request took longer than {}ms: {}",
                         timeout.as_millis(),
                         e
-                    )))
+                    )));
+                    let error = match *last_attempt_node.lock().unwrap() {
+                        Some(node) => error.with_node(node),
+                        None => error,
+                    };
+                    // The attempts in flight were dropped before they could log themselves.
+                    // If the request timed out before sending any, there is nothing to log.
+                    if let Some(table) = table {
+                        for _ in 0..attempts_in_flight.load(Ordering::Relaxed) {
+                            self.metrics.log_table_request(table, timeout, Some(&error));
+                        }
+                    }
+                    Err(error)
                 }),
             None => runner.await,
         };
 
+        cancellation_guard.completed = !matches!(result, Err(QueryError::RequestCancelled));
+
         if let Some((history_listener, query_id)) = history_listener_and_id {
             match &result {
                 Ok(_) => history_listener.log_query_success(query_id),
@@ -1677,6 +2592,13 @@ impl Session {
         result
     }
 
+    async fn wait_before_retry(&self, retry_session: &dyn RetrySession) {
+        let delay = retry_session.retry_delay();
+        if !delay.is_zero() {
+            self.runtime.sleep(delay).await;
+        }
+    }
+
     async fn execute_query<'a, QueryFut, ResT>(
         &'a self,
         query_plan: impl Iterator<Item = (NodeRef<'a>, Shard)>,
@@ -1705,7 +2627,7 @@ impl Session {
                             error = %e,
                             "Choosing connection failed"
                         );
-                        last_error = Some(e);
+                        last_error = Some(e.with_node(node.address.into_inner()));
                         // Broken connection doesn't count as a failed query, don't log in metrics
                         continue 'nodes_in_plan;
                     }
@@ -1720,19 +2642,61 @@ impl Session {
                     connection = %connection.get_connect_address(),
                     "Sending"
                 );
+                let connect_address = connection.get_connect_address();
+                let connection_shard: Option<Shard> = connection
+                    .get_shard_info()
+                    .as_ref()
+                    .map(|info| info.shard.into());
                 let attempt_id: Option<history::AttemptId> =
-                    context.log_attempt_start(connection.get_connect_address());
+                    context.log_attempt_start(connect_address);
+                *context.last_attempt_node.lock().unwrap() = Some(connect_address);
+                let attempt_index = context.execution_info.map(|execution_info| {
+                    execution_info.record_attempt_start(
+                        connect_address,
+                        connection_shard,
+                        context.is_speculative,
+                    )
+                });
+                context.attempts_in_flight.fetch_add(1, Ordering::Relaxed);
                 let query_result: Result<ResT, QueryError> =
                     do_query(connection, current_consistency, execution_profile)
                         .instrument(span.clone())
-                        .await;
+                        .await
+                        .map_err(|error| error.with_node(connect_address));
+                context.attempts_in_flight.fetch_sub(1, Ordering::Relaxed);
 
                 let elapsed = query_start.elapsed();
+                if let Some(table) = context.query_info.table {
+                    self.metrics
+                        .log_table_request(table, elapsed, query_result.as_ref().err());
+                }
+                if let Some(logged_request) = context.logged_request {
+                    logged_request.log_attempt(
+                        current_consistency,
+                        connect_address,
+                        elapsed,
+                        query_result.as_ref().map(|_| ()),
+                    );
+                }
+                if let (Some(execution_info), Some(index)) = (context.execution_info, attempt_index)
+                {
+                    execution_info.record_attempt_end(
+                        index,
+                        elapsed,
+                        query_result.as_ref().map(|_| ()),
+                    );
+                }
                 last_error = match query_result {
                     Ok(response) => {
                         trace!(parent: &span, "Query succeeded");
                         let _ = self.metrics.log_query_latency(elapsed.as_millis() as u64);
                         context.log_attempt_success(&attempt_id);
+                        if let (Some(execution_info), Some(index)) =
+                            (context.execution_info, attempt_index)
+                        {
+                            execution_info.record_winning_attempt(index);
+                        }
+                        node.record_successful_request();
                         execution_profile.load_balancing_policy.on_query_success(
                             context.query_info,
                             elapsed,
@@ -1776,12 +2740,16 @@ impl Session {
                 match retry_decision {
                     RetryDecision::RetrySameNode(new_cl) => {
                         self.metrics.inc_retries_num();
+                        context.record_retry();
                         current_consistency = new_cl.unwrap_or(current_consistency);
+                        self.wait_before_retry(&*context.retry_session).await;
                         continue 'same_node_retries;
                     }
                     RetryDecision::RetryNextNode(new_cl) => {
                         self.metrics.inc_retries_num();
+                        context.record_retry();
                         current_consistency = new_cl.unwrap_or(current_consistency);
+                        self.wait_before_retry(&*context.retry_session).await;
                         continue 'nodes_in_plan;
                     }
                     RetryDecision::DontRetry => break 'nodes_in_plan,
@@ -1798,7 +2766,7 @@ impl Session {
 
     async fn await_schema_agreement_indefinitely(&self) -> Result<Uuid, QueryError> {
         loop {
-            tokio::time::sleep(self.schema_agreement_interval).await;
+            self.runtime.sleep(self.schema_agreement_interval).await;
             if let Some(agreed_version) = self.check_schema_agreement().await? {
                 return Ok(agreed_version);
             }
@@ -1806,14 +2774,16 @@ impl Session {
     }
 
     pub async fn await_schema_agreement(&self) -> Result<Uuid, QueryError> {
-        timeout(
+        runtime::timeout(
+            &*self.runtime,
             self.schema_agreement_timeout,
             self.await_schema_agreement_indefinitely(),
         )
         .await
-        .unwrap_or(Err(QueryError::RequestTimeout(
+        .unwrap_or(Err(TimeoutError::Request(
             "schema agreement not reached in time".to_owned(),
-        )))
+        )
+        .into()))
     }
 
     pub async fn check_schema_agreement(&self) -> Result<Option<Uuid>, QueryError> {
@@ -1835,6 +2805,19 @@ impl Session {
     }
 }
 
+struct CancellationGuard<'a> {
+    metrics: &'a Metrics,
+    completed: bool,
+}
+
+impl Drop for CancellationGuard<'_> {
+    fn drop(&mut self) {
+        if !self.completed {
+            self.metrics.inc_cancelled_requests();
+        }
+    }
+}
+
 // run_query, execute_query, etc have a template type called ResT.
 // There was a bug where ResT was set to QueryResponse, which could
 // be an error response. This was not caught by retry policy which
@@ -1857,6 +2840,15 @@ struct ExecuteQueryContext<'a> {
     history_data: Option<HistoryData<'a>>,
     query_info: &'a load_balancing::RoutingInfo<'a>,
     request_span: &'a RequestSpan,
+    logged_request: Option<&'a LoggedRequest<'a>>,
+    execution_info: Option<&'a ExecutionInfoRecorder>,
+    is_speculative: bool,
+    // The node of the most recently started attempt of the request, shared by all
+    // its speculative executions. A request timeout is reported for this node.
+    last_attempt_node: &'a StdMutex<Option<SocketAddr>>,
+    // The number of attempts of the request which were sent and did not complete yet,
+    // shared by all its speculative executions
+    attempts_in_flight: &'a AtomicUsize,
 }
 
 struct HistoryData<'a> {
@@ -1866,6 +2858,12 @@ struct HistoryData<'a> {
 }
 
 impl<'a> ExecuteQueryContext<'a> {
+    fn record_retry(&self) {
+        if let Some(execution_info) = self.execution_info {
+            execution_info.record_retry();
+        }
+    }
+
     fn log_attempt_start(&self, node_addr: SocketAddr) -> Option<history::AttemptId> {
         self.history_data.as_ref().map(|hd| {
             hd.listener
@@ -2010,9 +3008,9 @@ impl RequestSpan {
         }
     }
 
-    pub(crate) fn record_rows_fields(&self, rows: &Rows) {
-        self.span.record("result_size", rows.serialized_size);
-        self.span.record("result_rows", rows.rows.len());
+    pub(crate) fn record_rows_fields(&self, rows: &RawRows) {
+        self.span.record("result_size", rows.raw_rows.len());
+        self.span.record("result_rows", rows.rows_count);
     }
 
     pub(crate) fn record_replicas<'a>(&'a self, replicas: &'a [(impl Borrow<Arc<Node>>, Shard)]) {
@@ -1,6 +1,7 @@
 //! SessionBuilder provides an easy way to create new Sessions
 
-use super::connection::SelfIdentity;
+use super::config_loader::{ConfigLoadError, DriverConfig};
+use super::connection::{KeepaliveRequest, SelfIdentity};
 use super::errors::NewSessionError;
 use super::execution_profile::ExecutionProfileHandle;
 use super::session::{AddressTranslator, Session, SessionConfig};
@@ -13,17 +14,28 @@ use crate::ExecutionProfile;
 
 use crate::statement::Consistency;
 use crate::transport::connection_pool::PoolSize;
+use crate::transport::connector::Connector;
+use crate::transport::control_connection::ControlConnectionListener;
+use crate::transport::frame_capture::FrameCapture;
 use crate::transport::host_filter::HostFilter;
+use crate::transport::load_shedding::LoadSheddingPolicy;
+use crate::transport::query_log::QueryLog;
+use crate::transport::runtime::Runtime;
+use crate::transport::write_defaults::WriteDefaultsPolicy;
+use scylla_cql::types::column_encryption::ColumnEncryptionPolicy;
 use std::borrow::Borrow;
 use std::marker::PhantomData;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::num::NonZeroU32;
-#[cfg(feature = "cloud")]
+#[cfg(any(feature = "cloud", feature = "config-file"))]
 use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
 
-use crate::authentication::{AuthenticatorProvider, PlainTextAuthenticator};
+use crate::authentication::{
+    AuthenticatorProvider, CredentialsProvider, PlainTextAuthenticator,
+    RotatingPlainTextAuthenticator,
+};
 #[cfg(feature = "ssl")]
 use openssl::ssl::SslContext;
 use tracing::warn;
@@ -87,7 +99,70 @@ impl GenericSessionBuilder<DefaultMode> {
         }
     }
 
+    /// Creates new SessionBuilder configured with the options read from a TOML or YAML file.
+    /// The format is chosen based on the file extension: `.toml`, `.yaml` or `.yml`.
+    ///
+    /// Options which are not present in the file keep their default values.
+    /// Methods called on the returned builder are applied on top of the loaded configuration,
+    /// so they can be used to override it. Note that `known_node` family of methods
+    /// adds contact points to the ones from the file.
+    ///
+    /// See [`config_loader`](crate::transport::config_loader) for the list of supported options.
+    /// Requires the `config-file` feature.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// // scylla.toml:
+    /// // known_nodes = ["127.0.0.1:9042"]
+    /// // compression = "lz4"
+    /// let session: Session = SessionBuilder::from_config_file("scylla.toml")?
+    ///     .connection_timeout(Duration::from_secs(3))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "config-file")]
+    pub fn from_config_file(path: impl AsRef<Path>) -> Result<Self, ConfigLoadError> {
+        DriverConfig::read_from_file(path.as_ref())?.apply(Self::new())
+    }
+
+    /// Creates new SessionBuilder configured with the options read from
+    /// `SCYLLA_*` environment variables, e.g. `SCYLLA_KNOWN_NODES=10.0.0.1:9042,10.0.0.2:9042`.
+    ///
+    /// Options which are not set keep their default values.
+    /// Methods called on the returned builder are applied on top of the loaded configuration,
+    /// so they can be used to override it.
+    ///
+    /// See [`config_loader`](crate::transport::config_loader) for the list of supported options.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::from_env()?
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_env() -> Result<Self, ConfigLoadError> {
+        DriverConfig::read_from_env()?.apply(Self::new())
+    }
+
     /// Add a known node with a hostname
+    ///
+    /// The hostname is resolved when the session is created. It is resolved again later
+    /// if the driver is unable to reach any of the nodes it knows about, so that the session
+    /// can recover after the IPs of all nodes have changed.
+    ///
+    /// If the hostname resolves to multiple addresses, connections are attempted as described
+    /// in RFC 8305 (Happy Eyeballs): IPv6 addresses are tried first, alternating with IPv4 ones,
+    /// a next attempt is started if the previous one doesn't finish within 250ms, and the first
+    /// established connection is used.
     /// # Examples
     /// ```
     /// # use scylla::{Session, SessionBuilder};
@@ -193,6 +268,47 @@ impl GenericSessionBuilder<DefaultMode> {
         self
     }
 
+    /// Set a provider of username and password for plain text authentication.\
+    /// Unlike [`user`](Self::user), the credentials are fetched from the provider each time
+    /// a new connection authenticates, so they can be rotated without rebuilding the session.
+    /// Already established connections are not affected.
+    ///
+    /// # Example
+    /// ```
+    /// # use std::sync::Arc;
+    /// use async_trait::async_trait;
+    /// use scylla::{Session, SessionBuilder};
+    /// use scylla::authentication::{AuthError, CredentialsProvider};
+    ///
+    /// struct VaultCredentials;
+    ///
+    /// #[async_trait]
+    /// impl CredentialsProvider for VaultCredentials {
+    ///     async fn credentials(&self) -> Result<(String, String), AuthError> {
+    ///         // Fetch a fresh, possibly short-lived password here.
+    ///         Ok(("cassandra".to_string(), "cassandra".to_string()))
+    ///     }
+    /// }
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .user_credentials_provider(Arc::new(VaultCredentials))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn user_credentials_provider(
+        mut self,
+        credentials_provider: Arc<dyn CredentialsProvider>,
+    ) -> Self {
+        self.config.authenticator = Some(Arc::new(RotatingPlainTextAuthenticator::new(
+            credentials_provider,
+        )));
+        self
+    }
+
     /// Set custom authenticator provider to create an authenticator instance during a session creation.
     ///
     /// # Example
@@ -304,6 +420,50 @@ impl GenericSessionBuilder<DefaultMode> {
         self
     }
 
+    /// Uses a custom connector to open connections to the nodes, instead of connecting over TCP.
+    /// It allows connecting e.g. over Unix domain sockets, through proxies,
+    /// or to an in-process transport in tests.
+    ///
+    /// TLS, if configured, is established on top of the streams returned by the connector,
+    /// but TCP-specific options (`tcp_nodelay`, `tcp_keepalive_interval`) are not applied.
+    ///
+    /// # Example
+    /// ```
+    /// # use std::net::SocketAddr;
+    /// # use std::sync::Arc;
+    /// use async_trait::async_trait;
+    /// use scylla::{Session, SessionBuilder};
+    /// use scylla::transport::connector::{ConnectionStream, Connector};
+    ///
+    /// struct UnixSocketConnector;
+    ///
+    /// #[async_trait]
+    /// impl Connector for UnixSocketConnector {
+    ///     async fn connect(
+    ///         &self,
+    ///         _address: SocketAddr,
+    ///         _source_port: Option<u16>,
+    ///     ) -> std::io::Result<Box<dyn ConnectionStream>> {
+    ///         let stream = tokio::net::UnixStream::connect("/var/lib/scylla/cql.sock").await?;
+    ///         Ok(Box::new(stream))
+    ///     }
+    /// }
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .connector(Arc::new(UnixSocketConnector))
+    ///     .disallow_shard_aware_port(true)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn connector(mut self, connector: Arc<dyn Connector>) -> Self {
+        self.config.connector = Some(connector);
+        self
+    }
+
     /// ssl feature
     /// Provide SessionBuilder with SslContext from openssl crate that will be
     /// used to create an ssl connection to the database.
@@ -389,6 +549,32 @@ impl<K: SessionBuilderKind> GenericSessionBuilder<K> {
         self
     }
 
+    /// Set the minimal size in bytes of a request body for it to be compressed.
+    /// Requests with smaller bodies are sent uncompressed, which avoids spending CPU
+    /// on frames that would not get noticeably smaller.
+    /// The default is 0, which means that all requests are compressed.
+    ///
+    /// Has an effect only if compression was negotiated, see [`compression`](Self::compression).
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::transport::Compression;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .compression(Some(Compression::Lz4))
+    ///     .compression_threshold(512)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn compression_threshold(mut self, compression_threshold: usize) -> Self {
+        self.config.compression_threshold = compression_threshold;
+        self
+    }
+
     /// Set the delay for schema agreement check. How often driver should ask if schema is in agreement
     /// The default is 200 milliseconds.
     ///
@@ -487,6 +673,123 @@ impl<K: SessionBuilderKind> GenericSessionBuilder<K> {
         self
     }
 
+    /// Set the time between TCP keepalive probes, sent after the connection
+    /// has been idle for [`tcp_keepalive_interval`](Self::tcp_keepalive_interval).
+    /// The default is 1 second.
+    /// Takes effect only if the TCP keepalive interval is set, and only on platforms
+    /// which support configuring it.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .tcp_keepalive_interval(Duration::from_secs(30))
+    ///     .tcp_keepalive_probe_interval(Duration::from_secs(5))
+    ///     .tcp_keepalive_retries(3)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tcp_keepalive_probe_interval(mut self, interval: Duration) -> Self {
+        self.config.tcp_keepalive_probe_interval = interval;
+        self
+    }
+
+    /// Set the number of unacknowledged TCP keepalive probes after which
+    /// the connection is considered dead and closed by the OS.
+    /// The default is 10.
+    /// Takes effect only if the TCP keepalive interval is set, and only on platforms
+    /// which support configuring it.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .tcp_keepalive_interval(Duration::from_secs(30))
+    ///     .tcp_keepalive_retries(3)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tcp_keepalive_retries(mut self, retries: u32) -> Self {
+        self.config.tcp_keepalive_retries = retries;
+        self
+    }
+
+    /// Set the size of the socket send buffer (`SO_SNDBUF`) of each connection.
+    /// The default is `None`, which means that the OS default is used.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .tcp_send_buffer_size(256 * 1024)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tcp_send_buffer_size(mut self, size: u32) -> Self {
+        self.config.tcp_send_buffer_size = Some(size);
+        self
+    }
+
+    /// Set the size of the socket receive buffer (`SO_RCVBUF`) of each connection.
+    /// The default is `None`, which means that the OS default is used.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .tcp_recv_buffer_size(256 * 1024)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tcp_recv_buffer_size(mut self, size: u32) -> Self {
+        self.config.tcp_recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Set the local IP address which connections are bound to before connecting,
+    /// e.g. to choose the network interface used to reach the cluster.
+    /// The default is `None`, which means that the OS chooses the address.
+    ///
+    /// Only the node addresses of the same family (IPv4 or IPv6) as the local address
+    /// can be connected to. If a hostname resolves to addresses of both families,
+    /// the addresses of the other family are skipped.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use std::net::{IpAddr, Ipv4Addr};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .local_ip_address(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn local_ip_address(mut self, address: IpAddr) -> Self {
+        self.config.local_ip_address = Some(address);
+        self
+    }
+
     /// Set keyspace to be used on all connections.\
     /// Each connection will send `"USE <keyspace_name>"` before sending any requests.\
     /// This can be later changed with [`Session::use_keyspace`]
@@ -615,6 +918,38 @@ impl<K: SessionBuilderKind> GenericSessionBuilder<K> {
         self
     }
 
+    /// Sets for how long the driver should stop using the shard-aware port of a node
+    /// after it failed to connect through it (e.g. the port is blocked by a firewall,
+    /// no free source port was available or a NAT changed the source port).
+    /// In the meantime, connections are opened to the regular port and shards are assigned
+    /// by the node. When the interval passes, the driver tries to replace one of those
+    /// connections with a connection opened to the shard-aware port, and once that succeeds,
+    /// the remaining connections opened to the regular port are migrated as well.
+    ///
+    /// By default, it is not set and the shard-aware port is attempted on each pool refill,
+    /// which may slow down refilling if connection attempts to the port time out.
+    ///
+    /// Current state of each node can be checked with
+    /// [`Node::shard_awareness_status`](crate::transport::Node::shard_awareness_status).
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .shard_aware_port_retry_interval(Duration::from_secs(60))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn shard_aware_port_retry_interval(mut self, interval: Duration) -> Self {
+        self.config.shard_aware_port_retry_interval = Some(interval);
+        self
+    }
+
     /// Set the keyspaces to be fetched, to retrieve their strategy, and schema metadata if enabled
     /// No keyspaces, the default value, means all the keyspaces will be fetched.
     ///
@@ -718,6 +1053,57 @@ impl<K: SessionBuilderKind> GenericSessionBuilder<K> {
         self
     }
 
+    /// Set the number of consecutive keepalives which may fail or time out
+    /// before the connection is closed.
+    /// The default is 1, which means that the first missed keepalive closes the connection.
+    ///
+    /// Regardless of this setting, after the last keepalive is missed on at least half
+    /// of the node's connections, the node is suspected to be down
+    /// (see [`Node::is_down_suspected`](crate::transport::Node::is_down_suspected))
+    /// until keepalives get through again or a request sent to it succeeds, and the default
+    /// load balancing policy stops picking it as the first node of query plans.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use std::num::NonZeroU32;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .keepalive_max_missed(NonZeroU32::new(3).unwrap())
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn keepalive_max_missed(mut self, max_missed: NonZeroU32) -> Self {
+        self.config.keepalive_max_missed = max_missed;
+        self
+    }
+
+    /// Set the request sent as a keepalive.
+    /// The default is [`KeepaliveRequest::Options`], which is answered without
+    /// touching the storage layer; [`KeepaliveRequest::LocalQuery`] additionally
+    /// checks that the node is able to execute queries.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::transport::KeepaliveRequest;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .keepalive_request(KeepaliveRequest::LocalQuery)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn keepalive_request(mut self, request: KeepaliveRequest) -> Self {
+        self.config.keepalive_request = request;
+        self
+    }
+
     /// Sets the timeout for waiting for schema agreement.
     /// By default, the timeout is 60 seconds.
     ///
@@ -920,42 +1306,318 @@ impl<K: SessionBuilderKind> GenericSessionBuilder<K> {
         self
     }
 
-    /// Set the interval at which the driver refreshes the cluster metadata which contains information
-    /// about the cluster topology as well as the cluster schema.
+    /// Set the size of the per-connection buffer in which outgoing requests
+    /// are collected before being written to the socket.
     ///
-    /// The default is 60 seconds.
+    /// All requests collected in the buffer (see [`write_coalescing`](Self::write_coalescing))
+    /// are flushed with a single syscall. A request which does not fit in the buffer
+    /// causes an additional write, so increasing the size can reduce the number of syscalls
+    /// under high request rates or with large requests, at the cost of memory used
+    /// by each connection.
+    ///
+    /// The default is 8 KiB.
     ///
-    /// In the given example, we have set the duration value to 20 seconds, which
-    /// means that the metadata is refreshed every 20 seconds.
     /// # Example
     /// ```
     /// # use scylla::{Session, SessionBuilder};
     /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
-    ///     let session: Session = SessionBuilder::new()
-    ///         .known_node("127.0.0.1:9042")
-    ///         .cluster_metadata_refresh_interval(std::time::Duration::from_secs(20))
-    ///         .build()
-    ///         .await?;
-    /// #   Ok(())
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .write_buffer_size(64 * 1024)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
     /// # }
     /// ```
-    pub fn cluster_metadata_refresh_interval(mut self, interval: Duration) -> Self {
-        self.config.cluster_metadata_refresh_interval = interval;
+    pub fn write_buffer_size(mut self, size: usize) -> Self {
+        self.config.write_buffer_size = size;
         self
     }
 
-    /// Set the custom identity of the driver/application/instance,
-    /// to be sent as options in STARTUP message.
+    /// Set the maximum size of a response frame, in bytes.
     ///
-    /// By default driver name and version are sent;
-    /// application name and version and client id are not sent.
+    /// A larger response is consumed from the socket without being buffered,
+    /// and its request fails with [`QueryError::ResponseTooLarge`](crate::transport::errors::QueryError::ResponseTooLarge).
+    /// This protects the application from running out of memory because of
+    /// an unexpectedly large result. The limit applies to the frame as sent by
+    /// the database, i.e. before decompression. Cluster metadata fetches are not limited.
+    ///
+    /// By default, the size of responses is not limited.
     ///
     /// # Example
     /// ```
     /// # use scylla::{Session, SessionBuilder};
-    /// # use scylla::transport::SelfIdentity;
     /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
-    ///     let (app_major, app_minor, app_patch) = (2, 1, 3);
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .max_response_frame_size(16 * 1024 * 1024)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn max_response_frame_size(mut self, size: usize) -> Self {
+        self.config.max_response_frame_size = Some(size);
+        self
+    }
+
+    /// Set the maximum number of rows in a single result page.
+    ///
+    /// A page containing more rows is rejected before its rows are deserialized,
+    /// and its request fails with [`QueryError::TooManyRows`](crate::transport::errors::QueryError::TooManyRows).
+    /// This guards against statements executed without paging, or with
+    /// a page size bigger than intended. Cluster metadata fetches are not limited.
+    ///
+    /// By default, the number of rows is not limited.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .max_rows_per_page(10_000)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn max_rows_per_page(mut self, rows: usize) -> Self {
+        self.config.max_rows_per_page = Some(rows);
+        self
+    }
+
+    /// Set the number of orphaned requests after which a connection is closed
+    /// and replaced with a new one.
+    ///
+    /// A request becomes orphaned when its caller stops waiting for the response
+    /// (e.g. the request future is dropped). Its stream id stays occupied until
+    /// the response arrives. Only requests orphaned for longer than
+    /// [`orphan_age_threshold`](Self::orphan_age_threshold) are counted.
+    ///
+    /// The default is 1024.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .orphan_count_threshold(256)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn orphan_count_threshold(mut self, threshold: usize) -> Self {
+        self.config.orphan_count_threshold = threshold;
+        self
+    }
+
+    /// Set the time after which an orphaned request is counted towards
+    /// [`orphan_count_threshold`](Self::orphan_count_threshold).
+    /// The connections check the number of such requests with this period.
+    ///
+    /// The default is 1 second. The threshold must be greater than zero,
+    /// otherwise building the session fails.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .orphan_age_threshold(Duration::from_secs(5))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn orphan_age_threshold(mut self, threshold: Duration) -> Self {
+        self.config.orphan_age_threshold = threshold;
+        self
+    }
+
+    /// Set the minimal number of free stream ids of a connection. A connection
+    /// having fewer free stream ids is closed and replaced with a new one.
+    /// Pending requests on such connection fail with [`ConnectionError::UnableToAllocStreamId`](crate::transport::errors::ConnectionError::UnableToAllocStreamId).
+    ///
+    /// This prevents a connection from being starved by requests which never get
+    /// a response, e.g. because a shard got stuck. The number of free stream ids
+    /// is checked with the period of [`orphan_age_threshold`](Self::orphan_age_threshold).
+    /// A connection has 32768 stream ids in total.
+    ///
+    /// By default, connections are not replaced based on the number of free stream ids.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .min_free_stream_ids(1024)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn min_free_stream_ids(mut self, min_free_stream_ids: usize) -> Self {
+        self.config.min_free_stream_ids = Some(min_free_stream_ids);
+        self
+    }
+
+    /// Set the interval at which the driver refreshes the cluster metadata which contains information
+    /// about the cluster topology as well as the cluster schema.
+    ///
+    /// The default is 60 seconds.
+    ///
+    /// In the given example, we have set the duration value to 20 seconds, which
+    /// means that the metadata is refreshed every 20 seconds.
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let session: Session = SessionBuilder::new()
+    ///         .known_node("127.0.0.1:9042")
+    ///         .cluster_metadata_refresh_interval(std::time::Duration::from_secs(20))
+    ///         .build()
+    ///         .await?;
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn cluster_metadata_refresh_interval(mut self, interval: Duration) -> Self {
+        self.config.cluster_metadata_refresh_interval = interval;
+        self
+    }
+
+    /// Sets the filter deciding which nodes may host the control connection,
+    /// which is used to fetch the cluster metadata. It is applied on top of
+    /// [`host_filter`](Self::host_filter), and ignored if it rejects all nodes.
+    /// The initial contact points are used for the first control connection regardless of the filter.
+    ///
+    /// See the [control_connection](crate::transport::control_connection) module for details.
+    ///
+    /// # Example
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::transport::host_filter::DcHostFilter;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// // Metadata will be fetched from nodes of "my-local-dc"
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .control_connection_host_filter(Arc::new(DcHostFilter::new("my-local-dc".to_string())))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn control_connection_host_filter(mut self, filter: Arc<dyn HostFilter>) -> Self {
+        self.config.control_connection_host_filter = Some(filter);
+        self
+    }
+
+    /// Sets the delays between the attempts to re-establish a broken control connection.
+    /// The first attempt is made immediately, the next one after `min_interval`,
+    /// and the delay doubles with each failed attempt, up to `max_interval`.
+    ///
+    /// The default is 1 second for both intervals. `min_interval` must be greater than zero
+    /// and not greater than `max_interval`, otherwise building the session fails.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .control_connection_reconnect_backoff(Duration::from_millis(100), Duration::from_secs(10))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn control_connection_reconnect_backoff(
+        mut self,
+        min_interval: Duration,
+        max_interval: Duration,
+    ) -> Self {
+        self.config.control_connection_min_reconnect_interval = min_interval;
+        self.config.control_connection_max_reconnect_interval = max_interval;
+        self
+    }
+
+    /// Sets the timeout of fetching the cluster metadata on the control connection.
+    /// When it elapses, the fetch is retried on another node, so that an overloaded
+    /// node does not stall the topology updates.
+    ///
+    /// The default is no timeout.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .metadata_request_timeout(Duration::from_secs(5))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn metadata_request_timeout(mut self, timeout: Duration) -> Self {
+        self.config.metadata_request_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the listener which is notified when the control connection changes hosts.
+    ///
+    /// # Example
+    /// ```
+    /// # use std::net::SocketAddr;
+    /// # use std::sync::Arc;
+    /// # use scylla::{Session, SessionBuilder};
+    /// use scylla::transport::control_connection::ControlConnectionListener;
+    ///
+    /// struct PrintingListener;
+    ///
+    /// impl ControlConnectionListener for PrintingListener {
+    ///     fn on_control_connection_change(&self, previous: SocketAddr, current: SocketAddr) {
+    ///         println!("Control connection moved from {} to {}", previous, current);
+    ///     }
+    /// }
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .control_connection_listener(Arc::new(PrintingListener))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn control_connection_listener(
+        mut self,
+        listener: Arc<dyn ControlConnectionListener>,
+    ) -> Self {
+        self.config.control_connection_listener = Some(listener);
+        self
+    }
+
+    /// Set the custom identity of the driver/application/instance,
+    /// to be sent as options in STARTUP message.
+    ///
+    /// By default driver name and version are sent;
+    /// application name and version and client id are not sent.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::transport::SelfIdentity;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let (app_major, app_minor, app_patch) = (2, 1, 3);
     ///     let app_version = format!("{app_major}.{app_minor}.{app_patch}");
     ///
     ///     let session: Session = SessionBuilder::new()
@@ -975,6 +1637,261 @@ impl<K: SessionBuilderKind> GenericSessionBuilder<K> {
         self.config.identity = identity;
         self
     }
+
+    /// Adds a custom option to be sent in STARTUP message, e.g. an identifier
+    /// consumed by a CQL-aware proxy. The options received from the nodes in SUPPORTED
+    /// message are available in [`ConnectionInfo::supported_options`](crate::transport::ConnectionInfo::supported_options).
+    ///
+    /// The option must not be reserved for the driver, see
+    /// [`is_reserved_startup_option`](scylla_cql::frame::request::options::is_reserved_startup_option),
+    /// otherwise building the session fails. The identity of the driver and application can be set with [`Self::custom_identity`].
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .custom_startup_option("TENANT_ID", "tenant-17")
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn custom_startup_option(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.config
+            .custom_startup_options
+            .insert(key.into(), value.into());
+        self
+    }
+
+    /// Sets the policy for client-side encryption of column values.
+    ///
+    /// Values of the encrypted columns bound to prepared statements (including
+    /// prepared statements in batches) are serialized with their cleartext types,
+    /// encrypted with the policy's [`ColumnCryptoProvider`](crate::column_encryption::ColumnCryptoProvider)
+    /// and sent as blobs. Values of those columns in query results are decrypted
+    /// and returned as values of the cleartext types.
+    ///
+    /// Encrypted columns can't be bound to values in unprepared statements, and
+    /// must not be aliased in `SELECT` clauses, as the results wouldn't be decrypted.
+    /// Using an encrypted column in a `WHERE` clause requires deterministic encryption.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::column_encryption::{ColumnCryptoProvider, ColumnEncryptionPolicy};
+    /// # use scylla::frame::response::result::ColumnType;
+    /// # use std::sync::Arc;
+    /// # async fn example(provider: Arc<dyn ColumnCryptoProvider>) -> Result<(), Box<dyn std::error::Error>> {
+    /// let policy = ColumnEncryptionPolicy::new(provider)
+    ///     .with_encrypted_column("ks", "users", "ssn", ColumnType::Text);
+    ///
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .column_encryption_policy(Arc::new(policy))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn column_encryption_policy(mut self, policy: Arc<ColumnEncryptionPolicy>) -> Self {
+        self.config.column_encryption_policy = Some(policy);
+        self
+    }
+
+    /// Records the frames sent and received by all connections of the session in the capture.
+    ///
+    /// See the [`frame_capture`](crate::transport::frame_capture) module for how to read captures.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::transport::frame_capture::FrameCapture;
+    /// # use std::sync::Arc;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .frame_capture(Arc::new(FrameCapture::create("frames.cqlcap")?))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn frame_capture(mut self, capture: Arc<FrameCapture>) -> Self {
+        self.config.frame_capture = Some(capture);
+        self
+    }
+
+    /// Sets the runtime which runs the driver's background tasks and timers.
+    ///
+    /// The default is [`TokioRuntime`](crate::transport::runtime::TokioRuntime).
+    /// See the [`runtime`](crate::transport::runtime) module for what is needed
+    /// to use the driver without Tokio.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::transport::runtime::TokioRuntime;
+    /// # use std::sync::Arc;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .runtime(Arc::new(TokioRuntime))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn runtime(mut self, runtime: Arc<dyn Runtime>) -> Self {
+        self.config.runtime = runtime;
+        self
+    }
+
+    /// Passes every attempt of executing a statement to the log's logger.
+    ///
+    /// Literals in statement texts are redacted and bound values are not logged
+    /// unless configured otherwise, see the [`query_log`](crate::transport::query_log) module.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::transport::query_log::{QueryLog, QueryLogEntry, QueryLogger};
+    /// # use std::sync::Arc;
+    /// # #[derive(Debug)]
+    /// # struct MyLogger;
+    /// # impl QueryLogger for MyLogger {
+    /// #     fn log_query(&self, _entry: &QueryLogEntry<'_>) {}
+    /// # }
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .query_log(QueryLog::new(Arc::new(MyLogger)).with_value_logging_for_table("ks", "t"))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn query_log(mut self, log: QueryLog) -> Self {
+        self.config.query_log = Some(Arc::new(log));
+        self
+    }
+
+    /// Limits the number of requests in flight, shedding the requests
+    /// of lower [priorities](crate::statement::Priority) first when the session is saturated.
+    /// See the [`load_shedding`](crate::transport::load_shedding) module for details.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::transport::load_shedding::LoadSheddingPolicy;
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .load_shedding_policy(
+    ///         LoadSheddingPolicy::new(1024).with_max_queue_time(Duration::from_millis(5)),
+    ///     )
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_shedding_policy(mut self, policy: LoadSheddingPolicy) -> Self {
+        self.config.load_shedding_policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Sets the policy which supplies the default TTL and timestamp of writes
+    /// based on the table they target.
+    /// See the [`write_defaults`](crate::transport::write_defaults) module for details.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::frame::response::result::TableSpec;
+    /// # use scylla::transport::write_defaults::WriteDefaultsPolicy;
+    /// # use std::sync::Arc;
+    /// #[derive(Debug)]
+    /// struct OneDayTtl;
+    ///
+    /// impl WriteDefaultsPolicy for OneDayTtl {
+    ///     fn default_ttl(&self, _table: &TableSpec<'_>) -> Option<u32> {
+    ///         Some(24 * 60 * 60)
+    ///     }
+    /// }
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .write_defaults_policy(Arc::new(OneDayTtl))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_defaults_policy(mut self, policy: Arc<dyn WriteDefaultsPolicy>) -> Self {
+        self.config.write_defaults_policy = Some(policy);
+        self
+    }
+
+    /// Enables or disables tracking of the requests to each table in the metrics of the session,
+    /// see [`Metrics::get_table_metrics`](crate::Metrics::get_table_metrics).
+    /// Enabled by default.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .table_metrics(false)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn table_metrics(mut self, enabled: bool) -> Self {
+        self.config.table_metrics = enabled;
+        self
+    }
+
+    /// Sets the maximal number of tables whose requests are tracked separately in the metrics
+    /// of the session. The requests to the tables above the limit are tracked together, see
+    /// [`Metrics::get_untracked_table_metrics`](crate::Metrics::get_untracked_table_metrics).
+    /// Default is 100.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .max_tracked_tables(1000)
+    ///     .build()
+    ///     .await?;
+    ///
+    /// let metrics = session.get_metrics();
+    /// for (keyspace, table, table_metrics) in metrics.get_all_table_metrics() {
+    ///     println!(
+    ///         "{}.{}: {} requests, {} timeouts",
+    ///         keyspace,
+    ///         table,
+    ///         table_metrics.get_requests_num(),
+    ///         table_metrics.get_timeouts_num()
+    ///     );
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn max_tracked_tables(mut self, max_tables: usize) -> Self {
+        self.config.max_tracked_tables = max_tables;
+        self
+    }
 }
 
 /// Creates a [`SessionBuilder`] with default configuration, same as [`SessionBuilder::new`]
@@ -986,15 +1903,32 @@ impl Default for SessionBuilder {
 
 #[cfg(test)]
 mod tests {
+    use assert_matches::assert_matches;
+    use scylla_cql::frame::response::result::{ColumnType, TableSpec};
     use scylla_cql::frame::types::SerialConsistency;
+    use scylla_cql::types::column_encryption::{
+        ColumnCryptoProvider, ColumnEncryptionPolicy, EncryptedColumn,
+    };
     use scylla_cql::Consistency;
 
     use super::SessionBuilder;
     use crate::test_utils::setup_tracing;
+    use crate::transport::control_connection::ControlConnectionListener;
+    use crate::transport::errors::NewSessionError;
     use crate::transport::execution_profile::{defaults, ExecutionProfile};
+    use crate::transport::frame_capture::FrameCapture;
+    use crate::transport::host_filter::AcceptAllHostFilter;
+    use crate::transport::load_shedding::LoadSheddingPolicy;
     use crate::transport::node::KnownNode;
+    use crate::transport::query_log::{QueryLog, QueryLogEntry, QueryLogger};
+    use crate::transport::runtime::{Runtime, TokioRuntime};
+    use crate::transport::write_defaults::WriteDefaultsPolicy;
     use crate::transport::Compression;
+    use crate::transport::KeepaliveRequest;
+    use std::collections::HashMap;
     use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::num::NonZeroU32;
+    use std::sync::Arc;
     use std::time::Duration;
 
     #[test]
@@ -1082,6 +2016,16 @@ mod tests {
         assert_eq!(builder.config.compression, None);
     }
 
+    #[test]
+    fn compression_threshold() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert_eq!(builder.config.compression_threshold, 0);
+
+        builder = builder.compression_threshold(512);
+        assert_eq!(builder.config.compression_threshold, 512);
+    }
+
     #[test]
     fn tcp_nodelay() {
         setup_tracing();
@@ -1095,6 +2039,296 @@ mod tests {
         assert!(builder.config.tcp_nodelay);
     }
 
+    #[test]
+    fn tcp_socket_options() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert_eq!(
+            builder.config.tcp_keepalive_probe_interval,
+            Duration::from_secs(1)
+        );
+        assert_eq!(builder.config.tcp_keepalive_retries, 10);
+        assert_eq!(builder.config.tcp_send_buffer_size, None);
+        assert_eq!(builder.config.tcp_recv_buffer_size, None);
+        assert_eq!(builder.config.local_ip_address, None);
+
+        let local_ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        builder = builder
+            .tcp_keepalive_probe_interval(Duration::from_secs(5))
+            .tcp_keepalive_retries(3)
+            .tcp_send_buffer_size(1024)
+            .tcp_recv_buffer_size(2048)
+            .local_ip_address(local_ip);
+
+        assert_eq!(
+            builder.config.tcp_keepalive_probe_interval,
+            Duration::from_secs(5)
+        );
+        assert_eq!(builder.config.tcp_keepalive_retries, 3);
+        assert_eq!(builder.config.tcp_send_buffer_size, Some(1024));
+        assert_eq!(builder.config.tcp_recv_buffer_size, Some(2048));
+        assert_eq!(builder.config.local_ip_address, Some(local_ip));
+    }
+
+    #[test]
+    fn write_buffer_size() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert!(builder.config.enable_write_coalescing);
+        assert_eq!(builder.config.write_buffer_size, 8192);
+
+        builder = builder.write_coalescing(false).write_buffer_size(65536);
+        assert!(!builder.config.enable_write_coalescing);
+        assert_eq!(builder.config.write_buffer_size, 65536);
+    }
+
+    #[test]
+    fn response_limits() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert_eq!(builder.config.max_response_frame_size, None);
+        assert_eq!(builder.config.max_rows_per_page, None);
+
+        builder = builder
+            .max_response_frame_size(1024 * 1024)
+            .max_rows_per_page(100);
+
+        assert_eq!(builder.config.max_response_frame_size, Some(1024 * 1024));
+        assert_eq!(builder.config.max_rows_per_page, Some(100));
+    }
+
+    #[test]
+    fn orphan_handling() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert_eq!(builder.config.orphan_count_threshold, 1024);
+        assert_eq!(builder.config.orphan_age_threshold, Duration::from_secs(1));
+        assert_eq!(builder.config.min_free_stream_ids, None);
+
+        builder = builder
+            .orphan_count_threshold(16)
+            .orphan_age_threshold(Duration::from_millis(500))
+            .min_free_stream_ids(128);
+
+        assert_eq!(builder.config.orphan_count_threshold, 16);
+        assert_eq!(
+            builder.config.orphan_age_threshold,
+            Duration::from_millis(500)
+        );
+        assert_eq!(builder.config.min_free_stream_ids, Some(128));
+    }
+
+    #[test]
+    fn column_encryption_policy() {
+        struct NoopProvider;
+
+        impl ColumnCryptoProvider for NoopProvider {
+            fn encrypt(
+                &self,
+                _column: &EncryptedColumn,
+                plaintext: &[u8],
+            ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+                Ok(plaintext.to_vec())
+            }
+
+            fn decrypt(
+                &self,
+                _column: &EncryptedColumn,
+                ciphertext: &[u8],
+            ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+                Ok(ciphertext.to_vec())
+            }
+        }
+
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert!(builder.config.column_encryption_policy.is_none());
+
+        let policy = ColumnEncryptionPolicy::new(Arc::new(NoopProvider)).with_encrypted_column(
+            "ks",
+            "t",
+            "c",
+            ColumnType::Int,
+        );
+        builder = builder.column_encryption_policy(Arc::new(policy));
+        let policy = builder.config.column_encryption_policy.as_ref().unwrap();
+        assert_eq!(
+            policy
+                .encrypted_column("ks", "t", "c")
+                .unwrap()
+                .cleartext_type,
+            ColumnType::Int
+        );
+        assert!(policy.encrypted_column("ks", "t", "d").is_none());
+    }
+
+    #[test]
+    fn frame_capture() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert!(builder.config.frame_capture.is_none());
+
+        let capture = Arc::new(FrameCapture::new(std::io::sink()).unwrap());
+        builder = builder.frame_capture(capture.clone());
+        assert!(Arc::ptr_eq(
+            builder.config.frame_capture.as_ref().unwrap(),
+            &capture
+        ));
+    }
+
+    #[test]
+    fn runtime() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+
+        let runtime: Arc<dyn Runtime> = Arc::new(TokioRuntime);
+        assert!(!Arc::ptr_eq(&builder.config.runtime, &runtime));
+
+        builder = builder.runtime(runtime.clone());
+        assert!(Arc::ptr_eq(&builder.config.runtime, &runtime));
+    }
+
+    #[test]
+    fn keepalive_settings() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert_eq!(builder.config.keepalive_max_missed.get(), 1);
+        assert_eq!(builder.config.keepalive_request, KeepaliveRequest::Options);
+
+        builder = builder
+            .keepalive_max_missed(NonZeroU32::new(3).unwrap())
+            .keepalive_request(KeepaliveRequest::LocalQuery);
+        assert_eq!(builder.config.keepalive_max_missed.get(), 3);
+        assert_eq!(
+            builder.config.keepalive_request,
+            KeepaliveRequest::LocalQuery
+        );
+    }
+
+    #[test]
+    fn query_log() {
+        #[derive(Debug)]
+        struct NoopLogger;
+
+        impl QueryLogger for NoopLogger {
+            fn log_query(&self, _entry: &QueryLogEntry<'_>) {}
+        }
+
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert!(builder.config.query_log.is_none());
+
+        builder = builder.query_log(QueryLog::new(Arc::new(NoopLogger)));
+        assert!(builder.config.query_log.is_some());
+    }
+
+    #[test]
+    fn load_shedding_policy() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert!(builder.config.load_shedding_policy.is_none());
+
+        builder = builder.load_shedding_policy(LoadSheddingPolicy::new(16));
+        assert_eq!(
+            builder
+                .config
+                .load_shedding_policy
+                .as_ref()
+                .map(|policy| policy.get_in_flight()),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn write_defaults_policy() {
+        #[derive(Debug)]
+        struct FixedTtl;
+
+        impl WriteDefaultsPolicy for FixedTtl {
+            fn default_ttl(&self, _table: &TableSpec<'_>) -> Option<u32> {
+                Some(60)
+            }
+        }
+
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert!(builder.config.write_defaults_policy.is_none());
+
+        builder = builder.write_defaults_policy(Arc::new(FixedTtl));
+        let table = TableSpec::borrowed("ks", "t");
+        assert_eq!(
+            builder
+                .config
+                .write_defaults_policy
+                .as_ref()
+                .map(|policy| (policy.default_ttl(&table), policy.default_timestamp(&table))),
+            Some((Some(60), None))
+        );
+    }
+
+    #[test]
+    fn custom_startup_options() {
+        let builder = SessionBuilder::new()
+            .custom_startup_option("TENANT_ID", "a")
+            .custom_startup_option("TENANT_ID", "b")
+            .custom_startup_option(String::from("REGION"), String::from("eu"));
+        assert_eq!(
+            builder.config.custom_startup_options,
+            HashMap::from([
+                ("TENANT_ID".to_owned(), "b".to_owned()),
+                ("REGION".to_owned(), "eu".to_owned()),
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn zero_orphan_age_threshold() {
+        setup_tracing();
+        let result = SessionBuilder::new()
+            .known_node("127.0.0.1:9042")
+            .orphan_age_threshold(Duration::ZERO)
+            .build()
+            .await;
+        assert_matches!(result, Err(NewSessionError::InvalidConfiguration(_)));
+    }
+
+    #[tokio::test]
+    async fn invalid_control_connection_reconnect_backoff() {
+        setup_tracing();
+        for (min_interval, max_interval) in [
+            (Duration::ZERO, Duration::from_secs(1)),
+            (Duration::from_secs(2), Duration::from_secs(1)),
+        ] {
+            let result = SessionBuilder::new()
+                .known_node("127.0.0.1:9042")
+                .control_connection_reconnect_backoff(min_interval, max_interval)
+                .build()
+                .await;
+            assert_matches!(result, Err(NewSessionError::InvalidConfiguration(_)));
+        }
+    }
+
+    #[tokio::test]
+    async fn reserved_startup_option() {
+        let result = SessionBuilder::new()
+            .known_node("127.0.0.1:9042")
+            .custom_startup_option("SCYLLA_RATE_LIMIT_ERROR", "")
+            .build()
+            .await;
+        assert_matches!(result, Err(NewSessionError::InvalidConfiguration(_)));
+    }
+
+    #[test]
+    fn table_metrics() {
+        let mut builder = SessionBuilder::new();
+        assert!(builder.config.table_metrics);
+        assert_eq!(builder.config.max_tracked_tables, 100);
+
+        builder = builder.table_metrics(false).max_tracked_tables(10);
+        assert!(!builder.config.table_metrics);
+        assert_eq!(builder.config.max_tracked_tables, 10);
+    }
+
     #[test]
     fn use_keyspace() {
         setup_tracing();
@@ -1211,6 +2445,52 @@ mod tests {
         );
     }
 
+    #[test]
+    fn control_connection_settings() {
+        struct NoopListener;
+
+        impl ControlConnectionListener for NoopListener {
+            fn on_control_connection_change(&self, _previous: SocketAddr, _current: SocketAddr) {}
+        }
+
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert!(builder.config.control_connection_host_filter.is_none());
+        assert_eq!(
+            builder.config.control_connection_min_reconnect_interval,
+            Duration::from_secs(1)
+        );
+        assert_eq!(
+            builder.config.control_connection_max_reconnect_interval,
+            Duration::from_secs(1)
+        );
+        assert_eq!(builder.config.metadata_request_timeout, None);
+        assert!(builder.config.control_connection_listener.is_none());
+
+        builder = builder
+            .control_connection_host_filter(Arc::new(AcceptAllHostFilter))
+            .control_connection_reconnect_backoff(
+                Duration::from_millis(100),
+                Duration::from_secs(10),
+            )
+            .metadata_request_timeout(Duration::from_secs(5))
+            .control_connection_listener(Arc::new(NoopListener));
+        assert!(builder.config.control_connection_host_filter.is_some());
+        assert_eq!(
+            builder.config.control_connection_min_reconnect_interval,
+            Duration::from_millis(100)
+        );
+        assert_eq!(
+            builder.config.control_connection_max_reconnect_interval,
+            Duration::from_secs(10)
+        );
+        assert_eq!(
+            builder.config.metadata_request_timeout,
+            Some(Duration::from_secs(5))
+        );
+        assert!(builder.config.control_connection_listener.is_some());
+    }
+
     #[test]
     fn all_features() {
         setup_tracing();
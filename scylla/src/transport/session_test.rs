@@ -1,5 +1,6 @@
 use crate as scylla;
 use crate::batch::{Batch, BatchStatement};
+use crate::benchmarks::{self, MockConfig, MockServer};
 use crate::frame::response::result::Row;
 use crate::prepared_statement::PreparedStatement;
 use crate::query::Query;
@@ -9,14 +10,18 @@ use crate::statement::Consistency;
 use crate::test_utils::{scylla_supports_tablets, setup_tracing};
 use crate::tracing::TracingInfo;
 use crate::transport::cluster::Datacenter;
-use crate::transport::errors::{BadKeyspaceName, BadQuery, DbError, QueryError};
+use crate::transport::errors::{BadKeyspaceName, BadQuery, DbError, QueryError, TimeoutError};
+use crate::transport::execution_info::{AttemptOutcome, ExecutionInfoCollector};
+use crate::transport::load_shedding::LoadSheddingPolicy;
 use crate::transport::partitioner::{
     calculate_token_for_partition_key, Murmur3Partitioner, Partitioner, PartitionerName,
 };
+use crate::transport::script::ScriptError;
 use crate::transport::topology::Strategy::NetworkTopologyStrategy;
 use crate::transport::topology::{
     CollectionType, ColumnKind, CqlType, NativeType, UserDefinedType,
 };
+use crate::transport::write_defaults::WriteDefaultsPolicy;
 use crate::utils::test_utils::{
     create_new_session_builder, supports_feature, unique_keyspace_name,
 };
@@ -28,13 +33,14 @@ use assert_matches::assert_matches;
 use bytes::Bytes;
 use futures::{FutureExt, StreamExt, TryStreamExt};
 use itertools::Itertools;
-use scylla_cql::frame::response::result::ColumnType;
+use scylla_cql::frame::response::result::{ColumnType, TableSpec};
 use scylla_cql::types::serialize::row::{SerializeRow, SerializedValues};
 use scylla_cql::types::serialize::value::SerializeValue;
 use std::collections::BTreeSet;
 use std::collections::{BTreeMap, HashMap};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::TcpListener;
 use uuid::Uuid;
 
@@ -63,6 +69,35 @@ async fn test_connection_failure() {
     }
 }
 
+#[tokio::test]
+async fn request_timed_out_before_any_attempt_is_not_counted_in_table_metrics() {
+    setup_tracing();
+    let server = MockServer::start(MockConfig::default()).unwrap();
+    // Admits no requests, so each one waits for a slot until its request timeout
+    let policy = LoadSheddingPolicy::new(0).with_max_queue_time(Duration::from_secs(10));
+    let session = server
+        .session_builder()
+        .load_shedding_policy(policy)
+        .build()
+        .await
+        .unwrap();
+    let mut prepared = session.prepare(benchmarks::SELECT).await.unwrap();
+    prepared.set_request_timeout(Some(Duration::from_millis(50)));
+
+    let result = session.execute(&prepared, (1,)).await;
+    assert_matches!(
+        result,
+        Err(QueryError::TimeoutError {
+            error: TimeoutError::Request(_),
+            node: None,
+        })
+    );
+    assert!(session
+        .get_metrics()
+        .get_table_metrics("bench", "kv")
+        .is_none());
+}
+
 #[tokio::test]
 async fn test_unprepared_statement() {
     setup_tracing();
@@ -663,11 +698,18 @@ async fn test_use_keyspace() {
 
     assert_eq!(rows, vec!["test1".to_string(), "test2".to_string()]);
 
-    // Test that trying to use nonexisting keyspace fails
-    assert!(session
-        .use_keyspace("this_keyspace_does_not_exist_at_all", false)
-        .await
-        .is_err());
+    // Test that trying to use nonexisting keyspace fails, both when the metadata
+    // is refreshed to verify it and when it has just been refreshed
+    for _ in 0..2 {
+        assert_matches!(
+            session
+                .use_keyspace("this_keyspace_does_not_exist_at_all", false)
+                .await,
+            Err(QueryError::BadQuery(BadQuery::KeyspaceNotFound(_)))
+        );
+    }
+    // A failed `use_keyspace` does not change the keyspace
+    assert_eq!(*session.get_keyspace().unwrap(), ks);
 
     // Test that invalid keyspaces get rejected
     assert!(matches!(
@@ -772,7 +814,26 @@ async fn test_use_keyspace_case_sensitivity() {
 
     // Use uppercase keyspace with case sensitivity
     // Should select the uppercase one
-    session.use_keyspace(ks_upper, true).await.unwrap();
+    session.use_keyspace(ks_upper.clone(), true).await.unwrap();
+
+    let rows: Vec<String> = session
+        .query("SELECT * from tab", &[])
+        .await
+        .unwrap()
+        .rows_typed::<(String,)>()
+        .unwrap()
+        .map(|row| row.unwrap().0)
+        .collect();
+
+    assert_eq!(rows, vec!["uppercase".to_string()]);
+
+    // A quoted name is case sensitive and is returned from `get_keyspace` as passed
+    let ks_quoted = format!("\"{}\"", ks_upper);
+    session
+        .use_keyspace(ks_quoted.clone(), false)
+        .await
+        .unwrap();
+    assert_eq!(*session.get_keyspace().unwrap(), ks_quoted);
 
     let rows: Vec<String> = session
         .query("SELECT * from tab", &[])
@@ -858,18 +919,28 @@ async fn test_db_errors() {
     let session = create_new_session_builder().build().await.unwrap();
     let ks = unique_keyspace_name();
 
-    // SyntaxError on bad query
+    // SyntaxError on bad query, returned by one of the known nodes
+    let syntax_error = session.query("gibberish", &[]).await.unwrap_err();
     assert!(matches!(
-        session.query("gibberish", &[]).await,
-        Err(QueryError::DbError(DbError::SyntaxError, _))
+        syntax_error,
+        QueryError::DbError {
+            error: DbError::SyntaxError,
+            ..
+        }
     ));
+    let node = syntax_error.node().unwrap();
+    assert!(session
+        .get_cluster_data()
+        .get_nodes_info()
+        .iter()
+        .any(|known_node| known_node.address.into_inner().ip() == node.ip()));
 
     // AlreadyExists when creating a keyspace for the second time
     session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
 
     let create_keyspace_res = session.query(format!("CREATE KEYSPACE {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks), &[]).await;
     let keyspace_exists_error: DbError = match create_keyspace_res {
-        Err(QueryError::DbError(e, _)) => e,
+        Err(QueryError::DbError { error: e, .. }) => e,
         _ => panic!("Second CREATE KEYSPACE didn't return an error!"),
     };
 
@@ -894,7 +965,7 @@ async fn test_db_errors() {
         .query(format!("CREATE TABLE {}.tab (a text primary key)", ks), &[])
         .await;
     let create_tab_error: DbError = match create_table_res {
-        Err(QueryError::DbError(e, _)) => e,
+        Err(QueryError::DbError { error: e, .. }) => e,
         _ => panic!("Second CREATE TABLE didn't return an error!"),
     };
 
@@ -1268,7 +1339,6 @@ async fn test_timestamp() {
 #[tokio::test]
 async fn test_request_timeout() {
     setup_tracing();
-    use std::time::Duration;
 
     let fast_timeouting_profile_handle = ExecutionProfile::builder()
         .request_timeout(Some(Duration::from_millis(1)))
@@ -1282,7 +1352,13 @@ async fn test_request_timeout() {
         query.set_request_timeout(Some(Duration::from_millis(1)));
         match session.query(query, &[]).await {
             Ok(_) => panic!("the query should have failed due to a client-side timeout"),
-            Err(e) => assert_matches!(e, QueryError::RequestTimeout(_)),
+            Err(e) => assert_matches!(
+                e,
+                QueryError::TimeoutError {
+                    error: TimeoutError::Request(_),
+                    ..
+                }
+            ),
         }
 
         let mut prepared = session
@@ -1293,7 +1369,13 @@ async fn test_request_timeout() {
         prepared.set_request_timeout(Some(Duration::from_millis(1)));
         match session.execute(&prepared, &[]).await {
             Ok(_) => panic!("the prepared query should have failed due to a client-side timeout"),
-            Err(e) => assert_matches!(e, QueryError::RequestTimeout(_)),
+            Err(e) => assert_matches!(
+                e,
+                QueryError::TimeoutError {
+                    error: TimeoutError::Request(_),
+                    ..
+                }
+            ),
         };
     }
     {
@@ -1307,7 +1389,13 @@ async fn test_request_timeout() {
 
         match timeouting_session.query(query.clone(), &[]).await {
             Ok(_) => panic!("the query should have failed due to a client-side timeout"),
-            Err(e) => assert_matches!(e, QueryError::RequestTimeout(_)),
+            Err(e) => assert_matches!(
+                e,
+                QueryError::TimeoutError {
+                    error: TimeoutError::Request(_),
+                    ..
+                }
+            ),
         };
 
         query.set_request_timeout(Some(Duration::from_secs(10000)));
@@ -1323,7 +1411,13 @@ async fn test_request_timeout() {
 
         match timeouting_session.execute(&prepared, &[]).await {
             Ok(_) => panic!("the prepared query should have failed due to a client-side timeout"),
-            Err(e) => assert_matches!(e, QueryError::RequestTimeout(_)),
+            Err(e) => assert_matches!(
+                e,
+                QueryError::TimeoutError {
+                    error: TimeoutError::Request(_),
+                    ..
+                }
+            ),
         };
 
         prepared.set_request_timeout(Some(Duration::from_secs(10000)));
@@ -2462,7 +2556,10 @@ async fn test_rate_limit_exceeded_exception() {
     use scylla_cql::errors::OperationType;
 
     match maybe_err.expect("Rate limit error didn't occur") {
-        QueryError::DbError(DbError::RateLimitReached { op_type, .. }, _) => {
+        QueryError::DbError {
+            error: DbError::RateLimitReached { op_type, .. },
+            ..
+        } => {
             assert_eq!(op_type, OperationType::Write);
         }
         err => panic!("Unexpected error type received: {:?}", err),
@@ -2936,3 +3033,1080 @@ async fn test_manual_primary_key_computation() {
         .await;
     }
 }
+
+#[tokio::test]
+async fn test_column_encryption() {
+    use crate::column_encryption::{ColumnCryptoProvider, ColumnEncryptionPolicy, EncryptedColumn};
+
+    struct XorProvider;
+
+    impl ColumnCryptoProvider for XorProvider {
+        fn encrypt(
+            &self,
+            _column: &EncryptedColumn,
+            plaintext: &[u8],
+        ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(plaintext.iter().map(|b| b ^ 0x5a).collect())
+        }
+
+        fn decrypt(
+            &self,
+            column: &EncryptedColumn,
+            ciphertext: &[u8],
+        ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+            self.encrypt(column, ciphertext)
+        }
+    }
+
+    setup_tracing();
+    let ks = unique_keyspace_name();
+    let policy = ColumnEncryptionPolicy::new(Arc::new(XorProvider)).with_encrypted_column(
+        &ks,
+        "t",
+        "secret",
+        ColumnType::Text,
+    );
+    let session = create_new_session_builder()
+        .column_encryption_policy(Arc::new(policy))
+        .build()
+        .await
+        .unwrap();
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session.use_keyspace(&ks, false).await.unwrap();
+    session
+        .query("CREATE TABLE t (a int primary key, secret blob)", &[])
+        .await
+        .unwrap();
+
+    let insert = session
+        .prepare("INSERT INTO t (a, secret) VALUES (?, ?)")
+        .await
+        .unwrap();
+    session.execute(&insert, (1, "abc")).await.unwrap();
+
+    let mut batch = Batch::default();
+    batch.append_statement(insert);
+    session.batch(&batch, ((2, "def"),)).await.unwrap();
+
+    // Results of unprepared statements are decrypted, too.
+    let mut raw_rows = session
+        .query("SELECT a, secret FROM t", &[])
+        .await
+        .unwrap()
+        .rows_typed::<(i32, Option<String>)>()
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect::<Vec<_>>();
+    raw_rows.sort();
+    assert_eq!(
+        raw_rows,
+        vec![(1, Some("abc".to_owned())), (2, Some("def".to_owned()))]
+    );
+
+    let select = session
+        .prepare("SELECT secret FROM t WHERE a = ?")
+        .await
+        .unwrap();
+    let (secret,) = session
+        .execute(&select, (1,))
+        .await
+        .unwrap()
+        .single_row_typed::<(String,)>()
+        .unwrap();
+    assert_eq!(secret, "abc");
+
+    // The values are stored encrypted.
+    let plain_session = create_new_session_builder().build().await.unwrap();
+    let (stored,) = plain_session
+        .query(format!("SELECT secret FROM {}.t WHERE a = 1", ks), &[])
+        .await
+        .unwrap()
+        .single_row_typed::<(Vec<u8>,)>()
+        .unwrap();
+    assert_eq!(stored, b"abc".iter().map(|b| b ^ 0x5a).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn test_cancel_handle() {
+    use crate::transport::cancellation::CancelHandle;
+
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    let metrics = session.get_metrics();
+
+    let handle = CancelHandle::new();
+    let mut query = Query::new("SELECT host_id FROM system.local");
+    query.set_cancel_handle(Some(handle.clone()));
+    session.query(query.clone(), &[]).await.unwrap();
+    assert_eq!(metrics.get_cancelled_requests_num(), 0);
+
+    handle.cancel();
+    assert_matches!(
+        session.query(query.clone(), &[]).await,
+        Err(QueryError::RequestCancelled)
+    );
+    assert_matches!(
+        session.query_iter(query, &[]).await.err(),
+        Some(QueryError::RequestCancelled)
+    );
+    assert_eq!(metrics.get_cancelled_requests_num(), 2);
+
+    let mut prepared = session
+        .prepare("SELECT host_id FROM system.local")
+        .await
+        .unwrap();
+    prepared.set_cancel_handle(Some(handle));
+    assert_matches!(
+        session.execute(&prepared, &[]).await,
+        Err(QueryError::RequestCancelled)
+    );
+    assert_eq!(metrics.get_cancelled_requests_num(), 3);
+}
+
+#[tokio::test]
+async fn test_frame_capture() {
+    use crate::frame::request::Request;
+    use crate::frame::response::Response;
+    use crate::transport::frame_capture::{CaptureReader, FrameCapture, FrameDirection};
+    use scylla_cql::frame::protocol_features::ProtocolFeatures;
+    use std::io::Write;
+    use std::sync::Mutex;
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    setup_tracing();
+    let buffer = SharedBuffer::default();
+    let session = create_new_session_builder()
+        .frame_capture(Arc::new(FrameCapture::new(buffer.clone()).unwrap()))
+        .build()
+        .await
+        .unwrap();
+
+    let statement = "SELECT host_id FROM system.local WHERE key = 'local'";
+    session.query(statement, &[]).await.unwrap();
+
+    let data = buffer.0.lock().unwrap().clone();
+    let frames = CaptureReader::new(&data[..])
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    let request = frames
+        .iter()
+        .find(|frame| {
+            matches!(
+                frame.decode_request().unwrap(),
+                Some(Request::Query(q)) if q.contents == statement
+            )
+        })
+        .expect("the query was not captured");
+    let response = frames
+        .iter()
+        .find(|frame| {
+            frame.direction == FrameDirection::Response
+                && frame.address == request.address
+                && frame.params.stream == request.params.stream
+                && frame.timestamp >= request.timestamp
+        })
+        .expect("the response was not captured");
+    assert_matches!(
+        response.decode_response(&ProtocolFeatures::default()),
+        Ok(Some(Response::Result(_)))
+    );
+}
+
+#[tokio::test]
+async fn test_query_log() {
+    use crate::frame::response::result::CqlValue;
+    use crate::transport::query_log::{
+        LoggedValue, QueryLog, QueryLogEntry, QueryLogger, StatementKind,
+    };
+    use std::sync::Mutex;
+
+    type Entry = (StatementKind, String, usize, Option<Vec<LoggedValue>>);
+
+    #[derive(Debug, Default)]
+    struct CollectingLogger(Mutex<Vec<Entry>>);
+
+    impl QueryLogger for CollectingLogger {
+        fn log_query(&self, entry: &QueryLogEntry<'_>) {
+            self.0.lock().unwrap().push((
+                entry.kind,
+                entry.statement.to_owned(),
+                entry.bound_values_count,
+                entry.values.map(|values| values.to_vec()),
+            ));
+        }
+    }
+
+    setup_tracing();
+    let ks = unique_keyspace_name();
+    let logger = Arc::new(CollectingLogger::default());
+    let session = create_new_session_builder()
+        .query_log(QueryLog::new(logger.clone()).with_value_logging_for_table(ks.clone(), "t"))
+        .build()
+        .await
+        .unwrap();
+
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    for table in ["t", "secret"] {
+        session
+            .query(
+                format!(
+                    "CREATE TABLE IF NOT EXISTS {}.{} (a int primary key, b text)",
+                    ks, table
+                ),
+                &[],
+            )
+            .await
+            .unwrap();
+    }
+    session.await_schema_agreement().await.unwrap();
+    logger.0.lock().unwrap().clear();
+
+    session
+        .query(
+            format!("INSERT INTO {}.secret (a, b) VALUES (1, 'password')", ks),
+            &[],
+        )
+        .await
+        .unwrap();
+    let insert_logged = session
+        .prepare(format!("INSERT INTO {}.t (a, b) VALUES (?, ?)", ks))
+        .await
+        .unwrap();
+    session
+        .execute(&insert_logged, (2, "visible"))
+        .await
+        .unwrap();
+    let insert_secret = session
+        .prepare(format!("INSERT INTO {}.secret (a, b) VALUES (?, ?)", ks))
+        .await
+        .unwrap();
+    session
+        .execute(&insert_secret, (3, "password"))
+        .await
+        .unwrap();
+
+    let entries = logger.0.lock().unwrap().clone();
+    assert_eq!(
+        entries,
+        vec![
+            (
+                StatementKind::Unprepared,
+                format!("INSERT INTO {}.secret (a, b) VALUES (?, ?)", ks),
+                0,
+                None
+            ),
+            (
+                StatementKind::Prepared,
+                format!("INSERT INTO {}.t (a, b) VALUES (?, ?)", ks),
+                2,
+                Some(vec![
+                    LoggedValue::Value(CqlValue::Int(2)),
+                    LoggedValue::Value(CqlValue::Text("visible".to_owned()))
+                ])
+            ),
+            (
+                StatementKind::Prepared,
+                format!("INSERT INTO {}.secret (a, b) VALUES (?, ?)", ks),
+                2,
+                None
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_node_health() {
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    session
+        .query("SELECT host_id FROM system.local WHERE key = 'local'", &[])
+        .await
+        .unwrap();
+
+    let cluster_data = session.get_cluster_data();
+    let healths: Vec<_> = cluster_data
+        .get_nodes_info()
+        .iter()
+        .map(|node| node.health())
+        .collect();
+    assert!(!healths.is_empty());
+    for health in &healths {
+        assert!(health.is_up);
+        let pool = health.pool.as_ref().unwrap();
+        assert!(pool.open_connections() > 0 || pool.connecting_connections > 0);
+    }
+    assert!(healths.iter().any(|health| health
+        .pool
+        .as_ref()
+        .unwrap()
+        .time_since_last_successful_request
+        .is_some()));
+}
+
+#[tokio::test]
+async fn test_prepare_all() {
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session
+        .query(
+            format!(
+                "CREATE TABLE IF NOT EXISTS {}.t (a int PRIMARY KEY, b int)",
+                ks
+            ),
+            &[],
+        )
+        .await
+        .unwrap();
+
+    let insert = format!("INSERT INTO {}.t (a, b) VALUES (?, ?)", ks);
+    let select = format!("SELECT b FROM {}.t WHERE a = ?", ks);
+    let mut insert_with_consistency = Query::new(insert.clone());
+    insert_with_consistency.set_consistency(Consistency::One);
+
+    let prepared = session
+        .prepare_all([
+            Query::new(insert.clone()),
+            Query::new(select.clone()),
+            insert_with_consistency,
+        ])
+        .await
+        .unwrap();
+
+    assert_eq!(prepared.len(), 3);
+    assert_eq!(prepared[0].get_statement(), insert);
+    assert_eq!(prepared[1].get_statement(), select);
+    assert_eq!(prepared[2].get_statement(), insert);
+    assert_eq!(prepared[0].get_id(), prepared[2].get_id());
+    assert_eq!(prepared[0].get_consistency(), None);
+    assert_eq!(prepared[2].get_consistency(), Some(Consistency::One));
+
+    session.execute(&prepared[2], (1_i32, 2_i32)).await.unwrap();
+    let (b,) = session
+        .execute(&prepared[1], (1_i32,))
+        .await
+        .unwrap()
+        .single_row_typed::<(i32,)>()
+        .unwrap();
+    assert_eq!(b, 2);
+
+    let mut batch = Batch::default();
+    batch.append_statement(insert.as_str());
+    batch.append_statement(prepared[0].clone());
+    batch.append_statement(insert.as_str());
+    let prepared_batch = session.prepare_batch(&batch).await.unwrap();
+    for statement in &prepared_batch.statements {
+        match statement {
+            BatchStatement::PreparedStatement(p) => assert_eq!(p.get_id(), prepared[0].get_id()),
+            BatchStatement::Query(_) => panic!("Batch statement was not prepared"),
+        }
+    }
+    session
+        .batch(
+            &prepared_batch,
+            ((3_i32, 4_i32), (5_i32, 6_i32), (7_i32, 8_i32)),
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_iterator_pages_info() {
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session
+        .query(
+            format!(
+                "CREATE TABLE IF NOT EXISTS {}.t (a int, b int, PRIMARY KEY (a, b))",
+                ks
+            ),
+            &[],
+        )
+        .await
+        .unwrap();
+
+    let insert = session
+        .prepare(format!("INSERT INTO {}.t (a, b) VALUES (0, ?)", ks))
+        .await
+        .unwrap();
+    for b in 0..25_i32 {
+        session.execute(&insert, (b,)).await.unwrap();
+    }
+
+    let mut query = Query::new(format!("SELECT b FROM {}.t WHERE a = 0", ks));
+    query.set_page_size(10);
+    let mut iter = session.query_iter(query, &[]).await.unwrap();
+    let mut rows = 0;
+    while iter.next().await.transpose().unwrap().is_some() {
+        rows += 1;
+    }
+    assert_eq!(rows, 25);
+
+    let pages = iter.get_pages_info();
+    assert!(pages.len() >= 3);
+    assert_eq!(iter.get_fetched_pages_count(), pages.len());
+    assert_eq!(pages.iter().map(|page| page.rows).sum::<usize>(), 25);
+    assert_eq!(iter.get_fetched_rows_count(), 25);
+    assert!(iter.get_fetched_bytes() > 0);
+    assert_eq!(iter.get_retries_count(), 0);
+
+    let node_addresses: Vec<_> = session
+        .get_cluster_data()
+        .get_nodes_info()
+        .iter()
+        .map(|node| node.address.into_inner())
+        .collect();
+    for page in pages {
+        assert!(page.rows <= 10);
+        assert!(node_addresses.contains(&page.node));
+    }
+}
+
+#[tokio::test]
+async fn test_execute_script() {
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    let script = format!(
+        "CREATE KEYSPACE IF NOT EXISTS {ks} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}};
+        -- The table is used right after creating it
+        CREATE TABLE IF NOT EXISTS {ks}.t (a int PRIMARY KEY, b text);
+        USE {ks};
+        INSERT INTO t (a, b) VALUES (1, 'one; two');
+        BEGIN BATCH
+            INSERT INTO t (a, b) VALUES (2, 'two');
+            INSERT INTO t (a, b) VALUES (3, 'three');
+        APPLY BATCH;"
+    );
+    session.execute_script(&script).await.unwrap();
+    assert_eq!(session.get_keyspace().as_deref(), Some(&ks));
+
+    let mut rows: Vec<(i32, String)> = session
+        .query("SELECT a, b FROM t", &[])
+        .await
+        .unwrap()
+        .rows_typed::<(i32, String)>()
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    rows.sort();
+    assert_eq!(
+        rows,
+        vec![
+            (1, "one; two".to_owned()),
+            (2, "two".to_owned()),
+            (3, "three".to_owned())
+        ]
+    );
+
+    let error = session
+        .execute_script("INSERT INTO t (a, b) VALUES (4, 'four'); SELECT * FROM missing; INSERT INTO t (a, b) VALUES (5, 'five')")
+        .await
+        .unwrap_err();
+    assert_matches!(
+        error,
+        ScriptError::StatementFailed { index: 1, ref statement, error: QueryError::DbError { .. } }
+            if statement == "SELECT * FROM missing"
+    );
+    let count: i64 = session
+        .query("SELECT COUNT(*) FROM t", &[])
+        .await
+        .unwrap()
+        .single_row_typed::<(i64,)>()
+        .unwrap()
+        .0;
+    assert_eq!(count, 4);
+
+    assert_matches!(
+        session.execute_script("SELECT 'unterminated").await,
+        Err(ScriptError::Parse(_))
+    );
+}
+
+#[tokio::test]
+async fn test_execution_info() {
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session
+        .query(
+            format!("CREATE TABLE IF NOT EXISTS {}.t (a int PRIMARY KEY)", ks),
+            &[],
+        )
+        .await
+        .unwrap();
+
+    let mut query = Query::new(format!("SELECT a FROM {}.t", ks));
+    assert!(session
+        .query(query.clone(), &[])
+        .await
+        .unwrap()
+        .execution_info
+        .is_none());
+
+    query.set_collect_execution_info(true);
+    let node_addresses: Vec<_> = session
+        .get_cluster_data()
+        .get_nodes_info()
+        .iter()
+        .map(|node| node.address.into_inner())
+        .collect();
+    let check_info = |result: QueryResult| {
+        let info = result.execution_info.unwrap();
+        assert_eq!(info.attempts.len(), 1);
+        assert_eq!(info.retries, 0);
+        assert_eq!(info.speculative_executions, 0);
+        assert!(!info.is_won_by_speculative_execution());
+        let winner = info.get_winning_attempt().unwrap();
+        assert_matches!(winner.outcome, AttemptOutcome::Success);
+        assert!(node_addresses.contains(&winner.node));
+    };
+    check_info(session.query(query.clone(), &[]).await.unwrap());
+
+    let prepared = session.prepare(query.clone()).await.unwrap();
+    assert!(prepared.get_collect_execution_info());
+    check_info(session.execute(&prepared, &[]).await.unwrap());
+
+    let mut batch = Batch::default();
+    batch.append_statement(format!("INSERT INTO {}.t (a) VALUES (1)", ks).as_str());
+    batch.set_collect_execution_info(true);
+    check_info(session.batch(&batch, ((),)).await.unwrap());
+
+    let collector = ExecutionInfoCollector::new();
+    let mut failing = Query::new(format!("SELECT a FROM {}.missing", ks));
+    failing.set_execution_info_collector(Some(collector.clone()));
+    session.query(failing, &[]).await.unwrap_err();
+    let info = collector.take_last().unwrap();
+    assert_eq!(info.attempts.len(), 1);
+    assert!(info.get_winning_attempt().is_none());
+    assert_matches!(info.attempts[0].outcome, AttemptOutcome::Error(_));
+    assert!(node_addresses.contains(&info.attempts[0].node));
+    assert!(collector.take_last().is_none());
+}
+
+#[tokio::test]
+async fn test_write_defaults_policy() {
+    #[derive(Debug)]
+    struct ExpiringEvents;
+
+    impl WriteDefaultsPolicy for ExpiringEvents {
+        fn default_ttl(&self, table: &TableSpec<'_>) -> Option<u32> {
+            (table.table_name() == "events").then_some(3600)
+        }
+
+        fn default_timestamp(&self, table: &TableSpec<'_>) -> Option<i64> {
+            (table.table_name() == "events").then_some(42)
+        }
+    }
+
+    setup_tracing();
+    let session = create_new_session_builder()
+        .write_defaults_policy(Arc::new(ExpiringEvents))
+        .build()
+        .await
+        .unwrap();
+    let ks = unique_keyspace_name();
+
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    for table in ["events", "other"] {
+        session
+            .query(
+                format!(
+                    "CREATE TABLE IF NOT EXISTS {}.{} (a int PRIMARY KEY, b int)",
+                    ks, table
+                ),
+                &[],
+            )
+            .await
+            .unwrap();
+    }
+
+    let statement = format!("INSERT INTO {}.events (a, b) VALUES (?, ?)", ks);
+    let plain = session.prepare(statement.as_str()).await.unwrap();
+    assert_eq!(plain.get_statement(), statement);
+    let insert = session
+        .prepare_with_default_ttl(statement.as_str())
+        .await
+        .unwrap();
+    assert_eq!(
+        insert.get_statement(),
+        format!("{} USING TTL 3600", statement)
+    );
+    session.execute(&insert, (1, 1)).await.unwrap();
+
+    let update = session
+        .prepare_with_default_ttl(format!(
+            "UPDATE {}.events USING TTL 60 SET b = ? WHERE a = ?",
+            ks
+        ))
+        .await
+        .unwrap();
+    session.execute(&update, (2, 2)).await.unwrap();
+
+    // Unprepared writes get the default timestamp too
+    session
+        .query(
+            format!("INSERT INTO {}.events (a, b) VALUES (3, 3)", ks),
+            &[],
+        )
+        .await
+        .unwrap();
+    // Conditional writes don't accept client-side timestamps
+    session
+        .query(
+            format!(
+                "INSERT INTO {}.events (a, b) VALUES (4, 4) IF NOT EXISTS",
+                ks
+            ),
+            &[],
+        )
+        .await
+        .unwrap();
+
+    let other = session
+        .prepare_with_default_ttl(format!("INSERT INTO {}.other (a, b) VALUES (?, ?)", ks))
+        .await
+        .unwrap();
+    session.execute(&other, (1, 1)).await.unwrap();
+
+    // The table of an unqualified name is in the keyspace of the session
+    session.use_keyspace(&ks, false).await.unwrap();
+    let unqualified = session
+        .prepare_with_default_ttl("INSERT INTO events (a, b) VALUES (?, ?)")
+        .await
+        .unwrap();
+    assert_eq!(
+        unqualified.get_statement(),
+        "INSERT INTO events (a, b) VALUES (?, ?) USING TTL 3600"
+    );
+
+    let ttl_and_timestamp = |table: &'static str, a: i32| {
+        let session = &session;
+        let ks = &ks;
+        async move {
+            session
+                .query(
+                    format!(
+                        "SELECT TTL(b), WRITETIME(b) FROM {}.{} WHERE a = ?",
+                        ks, table
+                    ),
+                    (a,),
+                )
+                .await
+                .unwrap()
+                .single_row_typed::<(Option<i32>, i64)>()
+                .unwrap()
+        }
+    };
+    let (ttl, timestamp) = ttl_and_timestamp("events", 1).await;
+    assert!(matches!(ttl, Some(ttl) if ttl > 3500));
+    assert_eq!(timestamp, 42);
+    let (ttl, _) = ttl_and_timestamp("events", 2).await;
+    assert!(matches!(ttl, Some(ttl) if ttl <= 60));
+    let (ttl, timestamp) = ttl_and_timestamp("events", 3).await;
+    assert_eq!(ttl, None);
+    assert_eq!(timestamp, 42);
+    let (_, timestamp) = ttl_and_timestamp("events", 4).await;
+    assert_ne!(timestamp, 42);
+    let (ttl, timestamp) = ttl_and_timestamp("other", 1).await;
+    assert_eq!(ttl, None);
+    assert_ne!(timestamp, 42);
+}
+
+#[tokio::test]
+async fn test_write_defaults_policy_skips_counter_tables() {
+    use crate::frame::value::Counter;
+
+    #[derive(Debug)]
+    struct FixedTtl;
+
+    impl WriteDefaultsPolicy for FixedTtl {
+        fn default_ttl(&self, _table: &TableSpec<'_>) -> Option<u32> {
+            Some(3600)
+        }
+    }
+
+    setup_tracing();
+    // Without the schema, counter tables are told by the metadata of the prepared statements
+    let session = create_new_session_builder()
+        .write_defaults_policy(Arc::new(FixedTtl))
+        .fetch_schema_metadata(false)
+        .build()
+        .await
+        .unwrap();
+    let ks = unique_keyspace_name();
+
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session
+        .query(
+            format!(
+                "CREATE TABLE IF NOT EXISTS {}.counts (a int PRIMARY KEY, c counter)",
+                ks
+            ),
+            &[],
+        )
+        .await
+        .unwrap();
+
+    let statement = format!("UPDATE {}.counts SET c = c + ? WHERE a = ?", ks);
+    let qualified = session
+        .prepare_with_default_ttl(statement.as_str())
+        .await
+        .unwrap();
+    assert_eq!(qualified.get_statement(), statement);
+    session.execute(&qualified, (Counter(1), 1)).await.unwrap();
+
+    session.use_keyspace(&ks, false).await.unwrap();
+    let unqualified = session
+        .prepare_with_default_ttl("UPDATE counts SET c = c + ? WHERE a = ?")
+        .await
+        .unwrap();
+    assert_eq!(
+        unqualified.get_statement(),
+        "UPDATE counts SET c = c + ? WHERE a = ?"
+    );
+    session
+        .execute(&unqualified, (Counter(1), 1))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_connection_pipeline() {
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session
+        .query(
+            format!(
+                "CREATE TABLE IF NOT EXISTS {}.t (a int, b int, c int, PRIMARY KEY (a, b))",
+                ks
+            ),
+            &[],
+        )
+        .await
+        .unwrap();
+
+    let insert = session
+        .prepare(format!("INSERT INTO {}.t (a, b, c) VALUES (?, ?, ?)", ks))
+        .await
+        .unwrap();
+    let handle = session
+        .connection_for_statement(&insert, (1, 0, 0))
+        .await
+        .unwrap();
+
+    // Overwrites of the same row are applied in submission order
+    let mut pipeline = handle.pipeline();
+    pipeline.set_ordered_timestamps(true);
+    for c in 0..100 {
+        pipeline.append_prepared(&insert, (1, 0, c)).unwrap();
+    }
+    pipeline
+        .append_prepared(&insert, (1, 1, 1))
+        .unwrap()
+        .append_query(format!("SELECT c FROM {}.t WHERE a = 1 AND b = 1000", ks));
+    assert_eq!(pipeline.len(), 102);
+
+    let results = pipeline.run().await;
+    assert_eq!(results.len(), 102);
+    for result in &results[..101] {
+        assert!(result.as_ref().unwrap().rows.is_none());
+    }
+    assert_eq!(
+        results[101].as_ref().unwrap().rows.as_ref().map(Vec::len),
+        Some(0)
+    );
+
+    let (c,) = session
+        .query(format!("SELECT c FROM {}.t WHERE a = 1 AND b = 0", ks), &[])
+        .await
+        .unwrap()
+        .single_row_typed::<(i32,)>()
+        .unwrap();
+    assert_eq!(c, 99);
+
+    // Errors are reported in place, without failing the other statements
+    let mut pipeline = handle.pipeline();
+    pipeline
+        .append_query(format!("SELECT c FROM {}.missing", ks))
+        .append_prepared(&insert, (1, 2, 2))
+        .unwrap();
+    let results = pipeline.run().await;
+    assert_matches!(
+        results[0],
+        Err(QueryError::DbError {
+            error: DbError::Invalid,
+            ..
+        })
+    );
+    assert!(results[1].is_ok());
+}
+
+#[tokio::test]
+async fn test_table_metrics() {
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session
+        .query(
+            format!(
+                "CREATE TABLE IF NOT EXISTS {}.t (a int PRIMARY KEY, b int)",
+                ks
+            ),
+            &[],
+        )
+        .await
+        .unwrap();
+
+    let insert = session
+        .prepare(format!("INSERT INTO {}.t (a, b) VALUES (?, ?)", ks))
+        .await
+        .unwrap();
+    for a in 0..5 {
+        session.execute(&insert, (a, a)).await.unwrap();
+    }
+    let select = session
+        .prepare(format!("SELECT b FROM {}.t", ks))
+        .await
+        .unwrap();
+    session.execute_iter(select, &[]).await.unwrap();
+    // Unprepared queries are not attributed to tables
+    session
+        .query(format!("SELECT b FROM {}.t", ks), &[])
+        .await
+        .unwrap();
+
+    let metrics = session.get_metrics();
+    let table_metrics = metrics.get_table_metrics(&ks, "t").unwrap();
+    assert!(table_metrics.get_requests_num() >= 5);
+    assert_eq!(table_metrics.get_errors_num(), 0);
+    assert!(metrics
+        .get_all_table_metrics()
+        .iter()
+        .any(|(keyspace, table, _)| keyspace == &ks && table == "t"));
+
+    let session = create_new_session_builder()
+        .table_metrics(false)
+        .build()
+        .await
+        .unwrap();
+    session.execute(&insert, (0, 0)).await.unwrap();
+    assert!(session.get_metrics().get_all_table_metrics().is_empty());
+}
+
+#[tokio::test]
+async fn test_dynamic_rows() {
+    use crate::frame::response::result::CqlValue;
+
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session
+        .query(
+            format!(
+                "CREATE TABLE IF NOT EXISTS {}.t (a int PRIMARY KEY, b text)",
+                ks
+            ),
+            &[],
+        )
+        .await
+        .unwrap();
+    for a in 0..10 {
+        let b = (a % 2 == 0).then(|| format!("b{}", a));
+        session
+            .query(format!("INSERT INTO {}.t (a, b) VALUES (?, ?)", ks), (a, b))
+            .await
+            .unwrap();
+    }
+
+    let rows = session
+        .query(format!("SELECT a, b FROM {}.t WHERE a = 2", ks), &[])
+        .await
+        .unwrap()
+        .rows_dynamic()
+        .unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].get("a"), Some(&CqlValue::Int(2)));
+    assert_eq!(rows[0].get("b"), Some(&CqlValue::Text("b2".to_string())));
+    let (spec, _) = rows[0].get_column("b").unwrap();
+    assert_eq!(spec.typ, ColumnType::Text);
+
+    let mut query = Query::new(format!("SELECT a, b FROM {}.t", ks));
+    query.set_page_size(3);
+    let rows: Vec<_> = session
+        .query_iter(query, &[])
+        .await
+        .unwrap()
+        .into_dynamic()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 10);
+    for row in rows {
+        assert_eq!(row.get_column_specs().len(), 2);
+        let map = row.into_map();
+        let Some(CqlValue::Int(a)) = map["a"] else {
+            panic!("Unexpected value of a: {:?}", map["a"]);
+        };
+        assert_eq!(map["b"].is_some(), a % 2 == 0);
+    }
+}
+
+#[crate::macros::prepared(
+    crate = crate,
+    schema = "src/transport/session_test_schema.cql",
+    statement = "INSERT INTO typed_statements (a, b, c, d, \"type\") VALUES (?, ?, ?, ?, ?) USING TTL ?"
+)]
+struct TypedInsert;
+
+#[crate::macros::prepared(
+    crate = crate,
+    schema = "src/transport/session_test_schema.cql",
+    statement = "UPDATE typed_statements SET d[?] = ? WHERE a = ? AND b = ?"
+)]
+struct TypedUpdate;
+
+#[crate::macros::prepared(
+    crate = crate,
+    schema = "src/transport/session_test_schema.cql",
+    statement = "SELECT b, c, d, \"type\" FROM typed_statements WHERE a = ? AND b IN ?",
+    row = TypedRow
+)]
+struct TypedSelect;
+
+#[tokio::test]
+async fn test_typed_statements() {
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session.use_keyspace(&ks, false).await.unwrap();
+    session
+        .query(include_str!("session_test_schema.cql"), &[])
+        .await
+        .unwrap();
+
+    let insert = TypedInsert::prepare(&session).await.unwrap();
+    let update = TypedUpdate::prepare(&session).await.unwrap();
+    let select = TypedSelect::prepare(&session).await.unwrap();
+
+    insert
+        .execute(&session, (1, "x", Some(&[1, 2][..]), None, None, 3600))
+        .await
+        .unwrap();
+    insert
+        .execute(&session, (1, "y", None, None, Some(&[7_u8][..]), 3600))
+        .await
+        .unwrap();
+    update.execute(&session, ("k", 17, 1, "y")).await.unwrap();
+
+    let keys = ["x".to_string(), "y".to_string()];
+    let rows = select
+        .execute(&session, (1, &keys[..]))
+        .await
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let expected = vec![
+        TypedRow {
+            b: "x".to_string(),
+            c: Some(vec![1, 2]),
+            d: None,
+            r#type: None,
+        },
+        TypedRow {
+            b: "y".to_string(),
+            c: None,
+            d: Some(HashMap::from([("k".to_string(), 17)])),
+            r#type: Some(vec![7]),
+        },
+    ];
+    assert_eq!(rows, expected);
+
+    let rows: Vec<TypedRow> = select
+        .execute_iter(&session, (1, &keys[..]))
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(rows, expected);
+
+    // The schema of the cluster changed since the schema dump was taken
+    session
+        .query("DROP TABLE typed_statements", &[])
+        .await
+        .unwrap();
+    session
+        .query(
+            "CREATE TABLE typed_statements (a int, b text, c list<int>, d map<text, int>, \"type\" blob, PRIMARY KEY (a, b))",
+            &[],
+        )
+        .await
+        .unwrap();
+    assert_matches!(
+        TypedInsert::prepare(&session).await,
+        Err(QueryError::BadQuery(BadQuery::Other(_)))
+    );
+}
+
+#[tokio::test]
+async fn test_paged_queries_are_shed() {
+    setup_tracing();
+    // No statement is ever admitted
+    let session = create_new_session_builder()
+        .load_shedding_policy(crate::transport::load_shedding::LoadSheddingPolicy::new(0))
+        .build()
+        .await
+        .unwrap();
+
+    let err = session
+        .query_iter("SELECT host_id FROM system.local", &[])
+        .await
+        .map(|_| ())
+        .unwrap_err();
+    assert_matches!(err, QueryError::RequestShed);
+
+    let prepared = session
+        .prepare("SELECT host_id FROM system.local")
+        .await
+        .unwrap();
+    let err = session
+        .execute_iter(prepared, &[])
+        .await
+        .map(|_| ())
+        .unwrap_err();
+    assert_matches!(err, QueryError::RequestShed);
+
+    assert_eq!(session.get_metrics().get_shed_requests_num(), 2);
+}
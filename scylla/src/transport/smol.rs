@@ -0,0 +1,241 @@
+//! Running the driver on the [smol](https://docs.rs/smol) runtime.
+//!
+//! Enabled with the `smol-2` feature. [`SmolRuntime`] runs the driver's background tasks,
+//! timers and blocking jobs (like hostname lookups) on smol's global executor, and
+//! [`SmolConnector`] opens TCP connections with smol's reactor, so that a session can be used
+//! in a program which doesn't run Tokio. Both have to be set on the session.
+//! Other executors built on `async-io`, like `async-std`, can use them as well.
+//!
+//! # Example
+//! ```rust,no_run
+//! # use scylla::{Session, SessionBuilder};
+//! # use std::error::Error;
+//! # fn example() -> Result<(), Box<dyn Error>> {
+//! use scylla::transport::smol::{SmolConnector, SmolRuntime};
+//! use std::sync::Arc;
+//!
+//! smol::block_on(async {
+//!     let session: Session = SessionBuilder::new()
+//!         .known_node("127.0.0.1:9042")
+//!         .runtime(Arc::new(SmolRuntime))
+//!         .connector(Arc::new(SmolConnector::new()))
+//!         .build()
+//!         .await?;
+//!     session.query("SELECT host_id FROM system.local", &[]).await?;
+//!     Ok(())
+//! })
+//! # }
+//! ```
+
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::ready;
+use smol::Async;
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use super::connector::{ConnectionStream, Connector};
+use super::runtime::{BoxedFuture, Runtime};
+
+/// Runs the driver's background tasks on smol's global executor.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SmolRuntime;
+
+impl Runtime for SmolRuntime {
+    fn spawn(&self, task: BoxedFuture<()>) {
+        smol::spawn(task).detach();
+    }
+
+    fn spawn_blocking(&self, job: Box<dyn FnOnce() + Send + 'static>) {
+        smol::unblock(job).detach();
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxedFuture<()> {
+        Box::pin(async move {
+            smol::Timer::after(duration).await;
+        })
+    }
+}
+
+/// Opens TCP connections with smol.
+///
+/// Like the driver's default connections, they have `TCP_NODELAY` set unless disabled with
+/// [`SmolConnector::tcp_nodelay`]. Other TCP options of the session, as well as its local IP
+/// address, don't apply to connections opened by a connector.
+#[derive(Debug, Clone)]
+pub struct SmolConnector {
+    tcp_nodelay: bool,
+}
+
+impl SmolConnector {
+    /// Creates a connector which sets `TCP_NODELAY` on its connections.
+    pub fn new() -> Self {
+        Self { tcp_nodelay: true }
+    }
+
+    /// Sets whether `TCP_NODELAY` is set on the connections.
+    pub fn tcp_nodelay(mut self, nodelay: bool) -> Self {
+        self.tcp_nodelay = nodelay;
+        self
+    }
+}
+
+impl Default for SmolConnector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Connector for SmolConnector {
+    async fn connect(
+        &self,
+        address: SocketAddr,
+        source_port: Option<u16>,
+    ) -> io::Result<Box<dyn ConnectionStream>> {
+        let stream = match source_port {
+            None => Async::<TcpStream>::connect(address).await?,
+            // Connecting from a chosen port needs binding the socket first, which smol
+            // doesn't support, so the socket is connected on a blocking thread.
+            Some(port) => {
+                let stream = smol::unblock(move || connect_from_port(address, port)).await?;
+                Async::new(stream)?
+            }
+        };
+        stream.get_ref().set_nodelay(self.tcp_nodelay)?;
+        Ok(Box::new(SmolStream(stream)))
+    }
+}
+
+fn connect_from_port(address: SocketAddr, port: u16) -> io::Result<TcpStream> {
+    let socket = Socket::new(
+        Domain::for_address(address),
+        Type::STREAM,
+        Some(Protocol::TCP),
+    )?;
+    let unspecified_ip: IpAddr = match address {
+        SocketAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+        SocketAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+    };
+    socket.bind(&SocketAddr::new(unspecified_ip, port).into())?;
+    socket.connect(&address.into())?;
+    Ok(socket.into())
+}
+
+// Adapts the `futures` I/O traits implemented by smol's streams to the Tokio ones used by the driver.
+struct SmolStream(Async<TcpStream>);
+
+impl AsyncRead for SmolStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let read = ready!(futures::io::AsyncRead::poll_read(
+            Pin::new(&mut self.0),
+            cx,
+            buf.initialize_unfilled()
+        ))?;
+        buf.advance(read);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for SmolStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        futures::io::AsyncWrite::poll_write(Pin::new(&mut self.0), cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        futures::io::AsyncWrite::poll_flush(Pin::new(&mut self.0), cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        futures::io::AsyncWrite::poll_close(Pin::new(&mut self.0), cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpListener};
+    use std::time::Duration;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::{SmolConnector, SmolRuntime};
+    use crate::transport::connector::Connector;
+    use crate::transport::runtime::{self, Runtime};
+
+    #[test]
+    fn connector_streams_work_without_tokio() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        // Echoes the first 4 bytes back
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 4];
+            stream.read_exact(&mut buf).unwrap();
+            stream.write_all(&buf).unwrap();
+        });
+
+        smol::block_on(async {
+            let mut stream = SmolConnector::new().connect(address, None).await.unwrap();
+            stream.write_all(b"ping").await.unwrap();
+            let mut buf = [0; 4];
+            stream.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"ping");
+        });
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn connector_uses_source_port() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        // Find a free port for the client side
+        let source_port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let _stream =
+            smol::block_on(SmolConnector::new().connect(address, Some(source_port))).unwrap();
+        let (_, peer): (_, SocketAddr) = listener.accept().unwrap();
+        assert_eq!(peer.port(), source_port);
+    }
+
+    #[test]
+    fn runtime_runs_tasks_timers_and_blocking_jobs() {
+        let runtime = SmolRuntime;
+        smol::block_on(async {
+            let (sender, receiver) = futures::channel::oneshot::channel();
+            runtime.spawn(Box::pin(async move {
+                let _ = sender.send(());
+            }));
+            receiver.await.unwrap();
+
+            assert_eq!(
+                runtime::spawn_blocking(&runtime, || 2 + 2).await.unwrap(),
+                4
+            );
+            assert!(runtime::timeout(
+                &runtime,
+                Duration::from_millis(10),
+                futures::future::pending::<()>()
+            )
+            .await
+            .is_err());
+        });
+    }
+}
@@ -5,7 +5,11 @@ use futures::{
 use std::{future::Future, sync::Arc, time::Duration};
 use tracing::{trace_span, warn, Instrument};
 
-use super::{errors::QueryError, metrics::Metrics};
+use super::{
+    errors::{ConnectionError, QueryError, TimeoutError},
+    metrics::Metrics,
+    runtime::Runtime,
+};
 
 /// Context is passed as an argument to `SpeculativeExecutionPolicy` methods
 pub struct Context {
@@ -83,8 +87,14 @@ impl SpeculativeExecutionPolicy for PercentileSpeculativeExecutionPolicy {
 fn can_be_ignored<ResT>(result: &Result<ResT, QueryError>) -> bool {
     match result {
         Ok(_) => false,
-        Err(QueryError::IoError(_)) => true,
-        Err(QueryError::TimeoutError) => true,
+        Err(QueryError::ConnectionError {
+            error: ConnectionError::IoError(_),
+            ..
+        }) => true,
+        Err(QueryError::TimeoutError {
+            error: TimeoutError::Connect,
+            ..
+        }) => true,
         _ => false,
     }
 }
@@ -94,6 +104,7 @@ const EMPTY_PLAN_ERROR: QueryError = QueryError::ProtocolError("Empty query plan
 pub(crate) async fn execute<QueryFut, ResT>(
     policy: &dyn SpeculativeExecutionPolicy,
     context: &Context,
+    runtime: &dyn Runtime,
     query_runner_generator: impl Fn(bool) -> QueryFut,
 ) -> Result<ResT, QueryError>
 where
@@ -108,8 +119,7 @@ where
             .instrument(trace_span!("Speculative execution: original query")),
     );
 
-    let sleep = tokio::time::sleep(retry_interval).fuse();
-    tokio::pin!(sleep);
+    let mut sleep = runtime.sleep(retry_interval).fuse();
 
     let mut last_error = None;
     loop {
@@ -120,7 +130,7 @@ where
                     retries_remaining -= 1;
 
                     // reset the timeout
-                    sleep.set(tokio::time::sleep(retry_interval).fuse());
+                    sleep = runtime.sleep(retry_interval).fuse();
                 }
             }
             res = async_tasks.select_next_some() => {
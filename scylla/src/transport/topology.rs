@@ -3,9 +3,11 @@ use crate::routing::Token;
 use crate::statement::query::Query;
 use crate::transport::connection::{Connection, ConnectionConfig};
 use crate::transport::connection_pool::{NodeConnectionPool, PoolConfig, PoolSize};
-use crate::transport::errors::{DbError, QueryError};
+use crate::transport::control_connection::ControlConnectionConfig;
+use crate::transport::errors::{DbError, QueryError, TimeoutError};
 use crate::transport::host_filter::HostFilter;
-use crate::transport::node::resolve_contact_points;
+use crate::transport::node::{resolve_contact_points, HostnameResolver};
+use crate::transport::runtime;
 use crate::utils::parse::{ParseErrorCause, ParseResult, ParserState};
 
 use futures::future::{self, FutureExt};
@@ -18,7 +20,7 @@ use scylla_cql::frame::response::result::Row;
 use scylla_macros::FromRow;
 use std::borrow::BorrowMut;
 use std::cell::Cell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fmt::Formatter;
 use std::net::{IpAddr, SocketAddr};
@@ -42,17 +44,22 @@ pub(crate) struct MetadataReader {
 
     // when control connection fails, MetadataReader tries to connect to one of known_peers
     known_peers: Vec<UntranslatedEndpoint>,
+    // known_peers which are also accepted by the control connection host filter
+    control_connection_peers: Vec<UntranslatedEndpoint>,
     keyspaces_to_fetch: Vec<String>,
     fetch_schema: bool,
     host_filter: Option<Arc<dyn HostFilter>>,
 
-    // When no known peer is reachable, initial known nodes are resolved once again as a fallback
+    // Whenever no known peer is reachable, initial known nodes are resolved once again as a fallback
     // and establishing control connection to them is attempted.
     initial_known_nodes: Vec<KnownNode>,
+    hostname_resolver: Arc<dyn HostnameResolver>,
 
     // When a control connection breaks, the PoolRefiller of its pool uses the requester
     // to signal ClusterWorker that an immediate metadata refresh is advisable.
     control_connection_repair_requester: broadcast::Sender<()>,
+
+    control_connection_config: ControlConnectionConfig,
 }
 
 /// Describes all metadata retrieved from the cluster
@@ -374,6 +381,7 @@ impl std::str::FromStr for ColumnKind {
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(tag = "class"))]
 #[allow(clippy::enum_variant_names)]
 pub enum Strategy {
     SimpleStrategy {
@@ -449,6 +457,7 @@ impl MetadataReader {
     #[allow(clippy::too_many_arguments)]
     pub(crate) async fn new(
         initial_known_nodes: Vec<KnownNode>,
+        hostname_resolver: Arc<dyn HostnameResolver>,
         control_connection_repair_requester: broadcast::Sender<()>,
         mut connection_config: ConnectionConfig,
         keepalive_interval: Option<Duration>,
@@ -456,9 +465,10 @@ impl MetadataReader {
         keyspaces_to_fetch: Vec<String>,
         fetch_schema: bool,
         host_filter: &Option<Arc<dyn HostFilter>>,
+        control_connection_config: ControlConnectionConfig,
     ) -> Result<Self, NewSessionError> {
         let (initial_peers, resolved_hostnames) =
-            resolve_contact_points(&initial_known_nodes).await;
+            resolve_contact_points(&*hostname_resolver, &initial_known_nodes).await;
         // Ensure there is at least one resolved node
         if initial_peers.is_empty() {
             return Err(NewSessionError::FailedToResolveAnyHostname(
@@ -478,6 +488,11 @@ impl MetadataReader {
         // - send received events via server_event_sender
         connection_config.event_sender = Some(server_event_sender);
 
+        // Response size limits are meant to protect the application from unexpectedly
+        // large user query results. Metadata fetches, e.g. of a big schema, must not fail because of them.
+        connection_config.max_response_frame_size = None;
+        connection_config.max_rows_per_page = None;
+
         let control_connection = Self::make_control_connection_pool(
             control_connection_endpoint.clone(),
             connection_config.clone(),
@@ -485,20 +500,27 @@ impl MetadataReader {
             control_connection_repair_requester.clone(),
         );
 
+        // The control connection host filter can't be applied to the contact points,
+        // as there is no metadata about them yet
+        let known_peers: Vec<_> = initial_peers
+            .into_iter()
+            .map(UntranslatedEndpoint::ContactPoint)
+            .collect();
+
         Ok(MetadataReader {
             control_connection_endpoint,
             control_connection,
             keepalive_interval,
             connection_config,
-            known_peers: initial_peers
-                .into_iter()
-                .map(UntranslatedEndpoint::ContactPoint)
-                .collect(),
+            control_connection_peers: known_peers.clone(),
+            known_peers,
             keyspaces_to_fetch,
             fetch_schema,
             host_filter: host_filter.clone(),
             initial_known_nodes,
+            hostname_resolver,
             control_connection_repair_requester,
+            control_connection_config,
         })
     }
 
@@ -520,11 +542,11 @@ impl MetadataReader {
         // At this point, we known that fetching metadata on currect control connection failed.
         // Therefore, we try to fetch metadata from other known peers, in order.
 
-        // shuffle known_peers to iterate through them in random order later
-        self.known_peers.shuffle(&mut thread_rng());
+        // shuffle control_connection_peers to iterate through them in random order later
+        self.control_connection_peers.shuffle(&mut thread_rng());
         debug!(
             "Known peers: {}",
-            self.known_peers
+            self.control_connection_peers
                 .iter()
                 .map(|endpoint| format!("{:?}", endpoint))
                 .collect::<Vec<String>>()
@@ -533,7 +555,7 @@ impl MetadataReader {
 
         let address_of_failed_control_connection = self.control_connection_endpoint.address();
         let filtered_known_peers = self
-            .known_peers
+            .control_connection_peers
             .clone()
             .into_iter()
             .filter(|peer| peer.address() != address_of_failed_control_connection);
@@ -548,13 +570,31 @@ impl MetadataReader {
             if !initial {
                 // If no known peer is reachable, try falling back to initial contact points, in hope that
                 // there are some hostnames there which will resolve to reachable new addresses.
+                // The hostnames are resolved again here, so that the driver can recover even if
+                // all IPs resolved earlier are gone (e.g. when nodes are rescheduled in Kubernetes).
                 warn!("Failed to establish control connection and fetch metadata on all known peers. Falling back to initial contact points.");
+                let tried_addresses: HashSet<SocketAddr> = self
+                    .control_connection_peers
+                    .iter()
+                    .map(|peer| peer.address().into_inner())
+                    .chain(std::iter::once(
+                        address_of_failed_control_connection.into_inner(),
+                    ))
+                    .collect();
                 let (initial_peers, _hostnames) =
-                    resolve_contact_points(&self.initial_known_nodes).await;
+                    resolve_contact_points(&*self.hostname_resolver, &self.initial_known_nodes)
+                        .await;
+                let untried_initial_peers: Vec<_> = initial_peers
+                    .into_iter()
+                    .filter(|peer| !tried_addresses.contains(&peer.address))
+                    .collect();
+                if untried_initial_peers.is_empty() {
+                    debug!("Initial contact points did not resolve to any address which was not already tried");
+                }
                 result = self
                     .retry_fetch_metadata_on_nodes(
                         initial,
-                        initial_peers
+                        untried_initial_peers
                             .into_iter()
                             .map(UntranslatedEndpoint::ContactPoint),
                         prev_err,
@@ -604,13 +644,7 @@ impl MetadataReader {
                 "Failed to fetch metadata using current control connection"
             );
 
-            self.control_connection_endpoint = peer.clone();
-            self.control_connection = Self::make_control_connection_pool(
-                self.control_connection_endpoint.clone(),
-                self.connection_config.clone(),
-                self.keepalive_interval,
-                self.control_connection_repair_requester.clone(),
-            );
+            self.switch_control_connection(peer);
 
             debug!(
                 "Retrying to establish the control connection on {}",
@@ -622,17 +656,40 @@ impl MetadataReader {
     }
 
     async fn fetch_metadata(&self, initial: bool) -> Result<Metadata, QueryError> {
-        // TODO: Timeouts?
-        self.control_connection.wait_until_initialized().await;
-        let conn = &self.control_connection.random_connection()?;
-
-        let res = query_metadata(
-            conn,
-            self.control_connection_endpoint.address().port(),
-            &self.keyspaces_to_fetch,
-            self.fetch_schema,
-        )
-        .await;
+        let with_node = |err: QueryError| {
+            err.with_node(self.control_connection_endpoint.address().into_inner())
+        };
+
+        // Failing to obtain a control connection is returned right away,
+        // only the failures of the metadata queries themselves may fall back to dummy metadata
+        let fetch = async {
+            self.control_connection.wait_until_initialized().await;
+            let conn = &self.control_connection.random_connection()?;
+
+            Ok(query_metadata(
+                conn,
+                self.control_connection_endpoint.address().port(),
+                &self.keyspaces_to_fetch,
+                self.fetch_schema,
+            )
+            .await)
+        };
+
+        let res = match self.control_connection_config.metadata_request_timeout {
+            Some(timeout) => runtime::timeout(&*self.connection_config.runtime, timeout, fetch)
+                .await
+                .unwrap_or_else(|e| {
+                    Ok(Err(TimeoutError::Request(format!(
+                        "Metadata fetch took longer than {}ms: {}",
+                        timeout.as_millis(),
+                        e
+                    ))
+                    .into()))
+                }),
+            None => fetch.await,
+        }
+        .map_err(with_node)?
+        .map_err(with_node);
 
         if initial {
             if let Err(err) = res {
@@ -652,13 +709,38 @@ impl MetadataReader {
 
     fn update_known_peers(&mut self, metadata: &Metadata) {
         let host_filter = self.host_filter.as_ref();
+        let is_accepted = |peer: &&Peer| host_filter.map_or(true, |f| f.accept(peer));
         self.known_peers = metadata
             .peers
             .iter()
-            .filter(|peer| host_filter.map_or(true, |f| f.accept(peer)))
+            .filter(is_accepted)
             .map(|peer| UntranslatedEndpoint::Peer(peer.to_peer_endpoint()))
             .collect();
 
+        let control_connection_host_filter = self.control_connection_config.host_filter.as_ref();
+        self.control_connection_peers = match control_connection_host_filter {
+            Some(control_connection_host_filter) => metadata
+                .peers
+                .iter()
+                .filter(is_accepted)
+                .filter(|peer| control_connection_host_filter.accept(peer))
+                .map(|peer| UntranslatedEndpoint::Peer(peer.to_peer_endpoint()))
+                .collect(),
+            None => self.known_peers.clone(),
+        };
+
+        // The control connection is better placed on a node not accepted
+        // by the control connection host filter than not placed at all
+        if self.control_connection_peers.is_empty() && !self.known_peers.is_empty() {
+            if control_connection_host_filter.is_some() {
+                warn!(
+                    "The control connection host filter rejected all nodes in the cluster. \
+                    The control connection may be established to any node."
+                );
+            }
+            self.control_connection_peers = self.known_peers.clone();
+        }
+
         // Check if the host filter isn't accidentally too restrictive,
         // and print an error message about this fact
         if !metadata.peers.is_empty() && self.known_peers.is_empty() {
@@ -697,25 +779,61 @@ impl MetadataReader {
                     control connection to a different node."
                 );
 
-                // Assuming here that known_peers are up-to-date
-                if !self.known_peers.is_empty() {
-                    self.control_connection_endpoint = self
-                        .known_peers
+                // Assuming here that control_connection_peers are up-to-date
+                if let Some(peer) = self.control_connection_peers.choose(&mut thread_rng()) {
+                    self.switch_control_connection(peer.clone());
+                }
+                return;
+            }
+        }
+
+        if let Some(control_connection_host_filter) = &self.control_connection_config.host_filter {
+            let control_connection_address = self.control_connection_endpoint.address();
+            let control_connection_peer = metadata
+                .peers
+                .iter()
+                .find(|peer| peer.address == control_connection_address);
+            if let Some(peer) = control_connection_peer {
+                if !control_connection_host_filter.accept(peer) {
+                    let accepted_peer = self
+                        .control_connection_peers
+                        .iter()
+                        .filter(|endpoint| endpoint.address() != control_connection_address)
+                        .collect::<Vec<_>>()
                         .choose(&mut thread_rng())
-                        .expect("known_peers is empty - should be impossible")
-                        .clone();
-
-                    self.control_connection = Self::make_control_connection_pool(
-                        self.control_connection_endpoint.clone(),
-                        self.connection_config.clone(),
-                        self.keepalive_interval,
-                        self.control_connection_repair_requester.clone(),
-                    );
+                        .map(|endpoint| (*endpoint).clone());
+                    if let Some(accepted_peer) = accepted_peer {
+                        debug!(
+                            control_connection_address = ?control_connection_address,
+                            "The node that the control connection is established to \
+                            is not accepted by the control connection host filter. \
+                            Moving the control connection to a different node."
+                        );
+                        self.switch_control_connection(accepted_peer);
+                    }
                 }
             }
         }
     }
 
+    fn switch_control_connection(&mut self, endpoint: UntranslatedEndpoint) {
+        let previous_address = self.control_connection_endpoint.address();
+        self.control_connection_endpoint = endpoint;
+        self.control_connection = Self::make_control_connection_pool(
+            self.control_connection_endpoint.clone(),
+            self.connection_config.clone(),
+            self.keepalive_interval,
+            self.control_connection_repair_requester.clone(),
+        );
+
+        if let Some(listener) = &self.control_connection_config.listener {
+            listener.on_control_connection_change(
+                previous_address.into_inner(),
+                self.control_connection_endpoint.address().into_inner(),
+            );
+        }
+    }
+
     fn make_control_connection_pool(
         endpoint: UntranslatedEndpoint,
         connection_config: ConnectionConfig,
@@ -732,6 +850,7 @@ impl MetadataReader {
             // The shard-aware port won't be used with PerHost pool size anyway,
             // so explicitly disable it here
             can_use_shard_aware_port: false,
+            shard_aware_port_retry_interval: None,
         };
 
         NodeConnectionPool::new(endpoint, pool_config, None, refresh_requester)
@@ -1682,7 +1801,10 @@ async fn query_table_partitioners(
         // that we are only interested in the ones resulting from non-existent table
         // system_schema.scylla_tables.
         // For more information please refer to https://github.com/scylladb/scylla-rust-driver/pull/349#discussion_r762050262
-        Err(QueryError::DbError(DbError::Invalid, _)) => Ok(HashMap::new()),
+        Err(QueryError::DbError {
+            error: DbError::Invalid,
+            ..
+        }) => Ok(HashMap::new()),
         result => result,
     }
 }
@@ -1908,4 +2030,78 @@ mod tests {
             assert_eq!(parsed, expected);
         }
     }
+
+    // Resolves every hostname to the address set by the test and counts the lookups.
+    struct StubResolver {
+        address: std::sync::Mutex<SocketAddr>,
+        lookups: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl HostnameResolver for StubResolver {
+        async fn lookup_host(
+            &self,
+            _host: String,
+            _port: Option<u16>,
+        ) -> std::io::Result<Vec<SocketAddr>> {
+            self.lookups
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            Ok(vec![*self.address.lock().unwrap()])
+        }
+    }
+
+    // Returns a local address nothing listens on.
+    fn closed_port_address() -> SocketAddr {
+        std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn unreachable_hostname_contact_point_is_resolved_again_on_each_refresh() {
+        setup_tracing();
+        let resolver = Arc::new(StubResolver {
+            address: std::sync::Mutex::new(closed_port_address()),
+            lookups: 0.into(),
+        });
+        let lookups = || resolver.lookups.load(std::sync::atomic::Ordering::Relaxed);
+
+        let mut reader = MetadataReader::new(
+            vec![KnownNode::Hostname("scylla.test".to_owned())],
+            resolver.clone(),
+            broadcast::channel(1).0,
+            ConnectionConfig::default(),
+            None,
+            mpsc::channel(1).0,
+            Vec::new(),
+            false,
+            &None,
+            ControlConnectionConfig {
+                host_filter: None,
+                metadata_request_timeout: Some(Duration::from_secs(5)),
+                min_reconnect_interval: Duration::from_millis(100),
+                max_reconnect_interval: Duration::from_secs(1),
+                listener: None,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(lookups(), 1);
+        assert!(reader.read_metadata(true).await.is_err());
+
+        for _ in 0..3 {
+            // The node was rescheduled and the hostname points to its new address
+            let new_address = closed_port_address();
+            *resolver.address.lock().unwrap() = new_address;
+            let lookups_before = lookups();
+
+            assert!(reader.read_metadata(false).await.is_err());
+            assert_eq!(lookups(), lookups_before + 1);
+            assert_eq!(
+                reader.control_connection_endpoint.address().into_inner(),
+                new_address
+            );
+        }
+    }
 }
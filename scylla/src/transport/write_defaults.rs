@@ -0,0 +1,387 @@
+//! Defaults of the TTL and timestamp of writes, decided centrally per table.
+//!
+//! A [`WriteDefaultsPolicy`], set with
+//! [`SessionBuilder::write_defaults_policy`](crate::transport::session_builder::GenericSessionBuilder::write_defaults_policy),
+//! supplies the TTL and timestamp of writes based on the table they target, so that
+//! e.g. data retention rules are enforced in one place instead of in every statement.
+//!
+//! The default timestamp is sent with each execution of an `INSERT`, `UPDATE` or `DELETE`
+//! statement, prepared or not, and of a batch of such statements, unless a timestamp is set
+//! on the statement with `set_timestamp`. Reads and conditional writes, which don't accept
+//! a client-side timestamp, are sent without it. The table of a prepared statement is taken
+//! from its metadata; otherwise it is taken from the text of the statement, with the keyspace
+//! of the session for unqualified table names. A batch gets the timestamp of the table of its
+//! first statement. Statements sent in a [`Pipeline`](crate::transport::pipeline::Pipeline) don't
+//! get the default timestamp.
+//!
+//! The native protocol has no request parameter for the TTL, so the default TTL is applied
+//! only to statements prepared with
+//! [`Session::prepare_with_default_ttl`](crate::Session::prepare_with_default_ttl), which adds
+//! it to the text of `INSERT` and `UPDATE` statements with a `USING TTL` clause, or to their
+//! existing `USING` clause. Statements which already specify their TTL are left unchanged,
+//! as are writes to counter tables, which can't have a TTL.
+//! [`Session::prepare`](crate::Session::prepare) never changes the text of statements.
+//!
+//! # Example
+//! ```rust
+//! # use scylla::{Session, SessionBuilder};
+//! # use std::error::Error;
+//! # async fn check_only_compiles() -> Result<(), Box<dyn Error>> {
+//! use scylla::frame::response::result::TableSpec;
+//! use scylla::transport::write_defaults::WriteDefaultsPolicy;
+//! use std::sync::Arc;
+//!
+//! // Events expire after 30 days, other data is kept forever
+//! #[derive(Debug)]
+//! struct Retention;
+//!
+//! impl WriteDefaultsPolicy for Retention {
+//!     fn default_ttl(&self, table: &TableSpec<'_>) -> Option<u32> {
+//!         (table.table_name() == "events").then_some(30 * 24 * 60 * 60)
+//!     }
+//! }
+//!
+//! let session: Session = SessionBuilder::new()
+//!     .known_node("127.0.0.1:9042")
+//!     .write_defaults_policy(Arc::new(Retention))
+//!     .build()
+//!     .await?;
+//!
+//! // Prepared as "INSERT INTO ks.events (id, body) VALUES (?, ?) USING TTL 2592000"
+//! let insert = session
+//!     .prepare_with_default_ttl("INSERT INTO ks.events (id, body) VALUES (?, ?)")
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt::Debug;
+
+use scylla_cql::cql_lexer::{next_token, unquote, TokenKind};
+use scylla_cql::frame::response::result::{ColumnSpec, ColumnType, TableSpec};
+
+/// Supplies the default TTL and timestamp of statements targeting a table.
+///
+/// See the [module documentation](self) for details.
+pub trait WriteDefaultsPolicy: Debug + Send + Sync {
+    /// Returns the TTL, in seconds, of the data written by the statements targeting the table,
+    /// which do not specify their TTL. Called once per statement prepared with
+    /// [`Session::prepare_with_default_ttl`](crate::Session::prepare_with_default_ttl).
+    fn default_ttl(&self, _table: &TableSpec<'_>) -> Option<u32> {
+        None
+    }
+
+    /// Returns the timestamp, in microseconds since the Unix epoch, of a single execution
+    /// of an unconditional write to the table, which does not have a timestamp set.
+    /// If `None` is returned, the timestamp is assigned by the server.
+    fn default_timestamp(&self, _table: &TableSpec<'_>) -> Option<i64> {
+        None
+    }
+}
+
+/// Adds a `USING TTL` clause with the given TTL to an `INSERT` or `UPDATE` statement.
+///
+/// Returns `None` if the statement is of a different kind, already specifies its TTL,
+/// or can't be parsed.
+pub(crate) fn with_default_ttl(statement: &str, ttl: u32) -> Option<String> {
+    let (words, end) = top_level_words(statement)?;
+    let position = |words: &[(usize, &str)], keyword: &str| {
+        words
+            .iter()
+            .position(|(_, word)| word.eq_ignore_ascii_case(keyword))
+    };
+    let has_ttl = |parameters: &[(usize, &str)]| position(parameters, "ttl").is_some();
+
+    let (_, kind) = words.first()?;
+    // The clause which follows USING, if there is none yet
+    let (clause_end, new_using) = if kind.eq_ignore_ascii_case("insert") {
+        // The USING clause ends the statement
+        (words.len(), (end, format!(" USING TTL {ttl}")))
+    } else if kind.eq_ignore_ascii_case("update") {
+        // The USING clause precedes the assignments
+        let set = position(&words, "set")?;
+        (set, (words[set].0, format!("USING TTL {ttl} ")))
+    } else {
+        return None;
+    };
+    // An existing USING clause, e.g. with a timestamp, is extended with the TTL
+    let (offset, clause) = match position(&words[..clause_end], "using") {
+        Some(using) if has_ttl(&words[using + 1..clause_end]) => return None,
+        Some(using) => {
+            let (using_offset, using_word) = words[using];
+            (using_offset + using_word.len(), format!(" TTL {ttl} AND"))
+        }
+        None => new_using,
+    };
+
+    Some(format!(
+        "{}{}{}",
+        &statement[..offset],
+        clause,
+        &statement[offset..]
+    ))
+}
+
+/// Tells if a statement writes to a counter table, judging by the types of its bind markers.
+///
+/// Writes to counter tables can't specify a TTL.
+pub(crate) fn is_counter_write(variable_col_specs: &[ColumnSpec]) -> bool {
+    variable_col_specs
+        .iter()
+        .any(|spec| spec.typ == ColumnType::Counter)
+}
+
+/// Tells if a statement is an `INSERT`, `UPDATE` or `DELETE` without conditions,
+/// which accepts a client-side timestamp.
+///
+/// Returns false for other statements, including batches, and for statements
+/// which can't be parsed.
+pub(crate) fn accepts_default_timestamp(statement: &str) -> bool {
+    let Some((words, _)) = top_level_words(statement) else {
+        return false;
+    };
+    let is_write = matches!(words.first(), Some((_, kind)) if ["insert", "update", "delete"]
+        .iter()
+        .any(|write| kind.eq_ignore_ascii_case(write)));
+    // IF is a reserved keyword, so it can't be the name of a column or a table
+    is_write
+        && !words
+            .iter()
+            .any(|(_, word)| word.eq_ignore_ascii_case("if"))
+}
+
+/// Returns the keyspace, if the table name is qualified with it, and the table targeted
+/// by an `INSERT`, `UPDATE` or `DELETE` statement, with the case of unquoted names folded
+/// like the database does.
+///
+/// Returns `None` if the statement is of a different kind or it can't be parsed.
+pub(crate) fn write_target(statement: &str) -> Option<(Option<String>, String)> {
+    let mut tokens = significant_tokens(statement).peekable();
+    let kind = tokens.next()?;
+    if kind.eq_ignore_ascii_case("insert") {
+        if !tokens.next()?.eq_ignore_ascii_case("into") {
+            return None;
+        }
+    } else if kind.eq_ignore_ascii_case("delete") {
+        // The deleted columns precede FROM, which is a reserved keyword
+        tokens.find(|token| token.eq_ignore_ascii_case("from"))?;
+    } else if !kind.eq_ignore_ascii_case("update") {
+        return None;
+    }
+
+    let name = identifier(tokens.next()?)?;
+    if tokens.next_if_eq(&".").is_none() {
+        return Some((None, name));
+    }
+    let table = identifier(tokens.next()?)?;
+    Some((Some(name), table))
+}
+
+// Returns the text of the tokens of the statement other than whitespace and comments,
+// ending at the first one which is not terminated.
+fn significant_tokens(statement: &str) -> impl Iterator<Item = &str> {
+    let mut pos = 0;
+    std::iter::from_fn(move || loop {
+        let token = next_token(&statement[pos..]).filter(|token| token.terminated)?;
+        let text = &statement[pos..pos + token.len];
+        pos += token.len;
+        if !matches!(token.kind, TokenKind::Whitespace | TokenKind::Comment) {
+            return Some(text);
+        }
+    })
+}
+
+// Returns the name of an identifier token, lowercased unless it is quoted.
+fn identifier(token: &str) -> Option<String> {
+    match next_token(token)?.kind {
+        TokenKind::Word => Some(token.to_lowercase()),
+        TokenKind::QuotedIdentifier => Some(unquote(token)),
+        _ => None,
+    }
+}
+
+// Returns the words of the statement which are not nested in parentheses, brackets or braces,
+// with their byte offsets, and the end offset of the last token of the statement other
+// than a semicolon. Returns `None` if a literal or a comment is not terminated.
+fn top_level_words(statement: &str) -> Option<(Vec<(usize, &str)>, usize)> {
+    let mut words = Vec::new();
+    let mut depth: usize = 0;
+    let mut end = 0;
+
+    let mut pos = 0;
+    while let Some(token) = next_token(&statement[pos..]) {
+        if !token.terminated {
+            return None;
+        }
+        let text = &statement[pos..pos + token.len];
+        pos += token.len;
+
+        match token.kind {
+            TokenKind::Whitespace | TokenKind::Comment => continue,
+            TokenKind::Symbol => match text {
+                ";" => continue,
+                "(" | "[" | "{" => depth += 1,
+                ")" | "]" | "}" => depth = depth.saturating_sub(1),
+                _ => {}
+            },
+            TokenKind::Word if depth == 0 => words.push((pos - token.len, text)),
+            _ => {}
+        }
+        end = pos;
+    }
+
+    Some((words, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use scylla_cql::frame::response::result::{ColumnSpec, ColumnType, TableSpec};
+
+    use super::{accepts_default_timestamp, is_counter_write, with_default_ttl, write_target};
+
+    #[test]
+    fn adds_ttl_to_inserts() {
+        assert_eq!(
+            with_default_ttl("INSERT INTO ks.t (a, ttl) VALUES (?, ?)", 60).as_deref(),
+            Some("INSERT INTO ks.t (a, ttl) VALUES (?, ?) USING TTL 60")
+        );
+        assert_eq!(
+            with_default_ttl(
+                "insert into t (a) values (?) if not exists using timestamp ?; -- using",
+                60
+            )
+            .as_deref(),
+            Some(
+                "insert into t (a) values (?) if not exists using TTL 60 AND timestamp ?; -- using"
+            )
+        );
+        assert_eq!(
+            with_default_ttl("INSERT INTO t (a) VALUES (?) USING TIMESTAMP ?", 60).as_deref(),
+            Some("INSERT INTO t (a) VALUES (?) USING TTL 60 AND TIMESTAMP ?")
+        );
+        assert_eq!(
+            with_default_ttl("INSERT INTO t JSON '{\"a\": 1, \"b\": \"using\"}'", 5).as_deref(),
+            Some("INSERT INTO t JSON '{\"a\": 1, \"b\": \"using\"}' USING TTL 5")
+        );
+        assert_eq!(
+            with_default_ttl(
+                "INSERT INTO t (a) VALUES (?) USING TIMESTAMP 1 AND TTL ?",
+                60
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn adds_ttl_to_updates() {
+        assert_eq!(
+            with_default_ttl("UPDATE ks.t SET ttl = ? WHERE a = ?", 60).as_deref(),
+            Some("UPDATE ks.t USING TTL 60 SET ttl = ? WHERE a = ?")
+        );
+        assert_eq!(
+            with_default_ttl(
+                "UPDATE t USING TIMESTAMP ? SET b = {'set': 1} WHERE a = ?",
+                60
+            )
+            .as_deref(),
+            Some("UPDATE t USING TTL 60 AND TIMESTAMP ? SET b = {'set': 1} WHERE a = ?")
+        );
+        assert_eq!(
+            with_default_ttl("UPDATE t USING ttl 1 SET b = ? WHERE a = ?", 60),
+            None
+        );
+    }
+
+    #[test]
+    fn ignores_other_statements() {
+        assert_eq!(
+            with_default_ttl("SELECT ttl(b) FROM t WHERE a = ?", 60),
+            None
+        );
+        assert_eq!(with_default_ttl("DELETE FROM t WHERE a = ?", 60), None);
+        assert_eq!(
+            with_default_ttl("BEGIN BATCH INSERT INTO t (a) VALUES (?); APPLY BATCH", 60),
+            None
+        );
+        assert_eq!(with_default_ttl("INSERT INTO t (a) VALUES ('abc", 60), None);
+        assert_eq!(with_default_ttl("", 60), None);
+    }
+
+    #[test]
+    fn detects_counter_writes() {
+        let spec = |name: &str, typ: ColumnType| ColumnSpec {
+            table_spec: TableSpec::borrowed("ks", "t").into_owned(),
+            name: name.to_owned(),
+            typ,
+        };
+        assert!(is_counter_write(&[
+            spec("c", ColumnType::Counter),
+            spec("a", ColumnType::Int)
+        ]));
+        assert!(!is_counter_write(&[
+            spec("b", ColumnType::BigInt),
+            spec("a", ColumnType::Int)
+        ]));
+        assert!(!is_counter_write(&[]));
+    }
+
+    #[test]
+    fn finds_targets_of_writes() {
+        let target =
+            |ks: Option<&str>, table: &str| Some((ks.map(str::to_owned), table.to_owned()));
+        assert_eq!(
+            write_target("INSERT INTO Ks.Events (a) VALUES (?)"),
+            target(Some("ks"), "events")
+        );
+        assert_eq!(
+            write_target("update /* c */ \"Ks\" . \"My\"\"Table\" SET b = ? WHERE a = ?"),
+            target(Some("Ks"), "My\"Table")
+        );
+        assert_eq!(
+            write_target("DELETE b, m['from'] FROM ks.events WHERE a = ?"),
+            target(Some("ks"), "events")
+        );
+        assert_eq!(
+            write_target("INSERT INTO events (a) VALUES (?)"),
+            target(None, "events")
+        );
+        assert_eq!(
+            write_target("UPDATE Events USING TTL 5 SET b = ? WHERE a = ?"),
+            target(None, "events")
+        );
+        assert_eq!(
+            write_target("delete from events where a = ?"),
+            target(None, "events")
+        );
+        assert_eq!(write_target("SELECT a FROM ks.events"), None);
+        assert_eq!(write_target("DELETE a WHERE a = ?"), None);
+        assert_eq!(write_target("INSERT INTO \"ks.events"), None);
+    }
+
+    #[test]
+    fn detects_unconditional_writes() {
+        assert!(accepts_default_timestamp(
+            "INSERT INTO t (a, b) VALUES (?, ?)"
+        ));
+        assert!(accepts_default_timestamp(
+            "update t set b = ? where a = ? -- if"
+        ));
+        assert!(accepts_default_timestamp(
+            "DELETE m['if'] FROM t USING TIMESTAMP 5 WHERE a = ?"
+        ));
+        assert!(!accepts_default_timestamp(
+            "INSERT INTO t (a) VALUES (?) IF NOT EXISTS"
+        ));
+        assert!(!accepts_default_timestamp(
+            "UPDATE t SET b = ? WHERE a = ? if b = 1"
+        ));
+        assert!(!accepts_default_timestamp(
+            "DELETE FROM t WHERE a = ? IF EXISTS"
+        ));
+        assert!(!accepts_default_timestamp("SELECT a FROM t"));
+        assert!(!accepts_default_timestamp(
+            "BEGIN BATCH INSERT INTO t (a) VALUES (?); APPLY BATCH"
+        ));
+        assert!(!accepts_default_timestamp("INSERT INTO t (a) VALUES ('abc"));
+    }
+}
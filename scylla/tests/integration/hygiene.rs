@@ -75,6 +75,14 @@ macro_rules! test_crate {
 
 mod scylla_hygiene {
     test_crate!(scylla);
+
+    #[allow(unused)]
+    #[_scylla::macros::prepared(
+        crate = _scylla,
+        schema = "src/transport/session_test_schema.cql",
+        statement = "SELECT b, c, d, \"type\" FROM typed_statements WHERE a = ?"
+    )]
+    struct TestPrepared;
 }
 mod scylla_cql_hygiene {
     test_crate!(scylla_cql);
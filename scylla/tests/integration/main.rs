@@ -3,10 +3,12 @@ mod execution_profiles;
 mod hygiene;
 mod lwt_optimisation;
 mod new_session;
+mod pipeline;
 mod retries;
 mod self_identity;
 mod shards;
 mod silent_prepare_query;
 mod skip_metadata_optimization;
+mod table_metrics;
 mod tablets;
 pub(crate) mod utils;
@@ -0,0 +1,75 @@
+use crate::utils::{setup_tracing, test_with_3_node_cluster};
+use assert_matches::assert_matches;
+use scylla::query::Query;
+use scylla::transport::errors::{QueryError, TimeoutError};
+use scylla::transport::session::Session;
+use scylla::SessionBuilder;
+use scylla_proxy::{
+    Condition, ProxyError, Reaction, RequestOpcode, RequestReaction, RequestRule, ShardAwareness,
+    WorkerError,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+#[ntest::timeout(30000)]
+#[cfg(not(scylla_cloud_tests))]
+async fn request_timeout_applies_to_pipelined_statements() {
+    setup_tracing();
+    let res = test_with_3_node_cluster(
+        ShardAwareness::QueryNode,
+        |proxy_uris, translation_map, mut running_proxy| async move {
+            let session: Session = SessionBuilder::new()
+                .known_node(proxy_uris[0].as_str())
+                .address_translator(Arc::new(translation_map))
+                .build()
+                .await
+                .unwrap();
+
+            let prepared = session
+                .prepare("SELECT host_id FROM system.local WHERE key = ?")
+                .await
+                .unwrap();
+            let handle = session
+                .connection_for_statement(&prepared, ("local",))
+                .await
+                .unwrap();
+
+            let drop_frame_rule = RequestRule(
+                Condition::RequestOpcode(RequestOpcode::Query)
+                    .and(Condition::BodyContainsCaseSensitive(Box::new(*b"dropped"))),
+                RequestReaction::drop_frame(),
+            );
+            for node in running_proxy.running_nodes.iter_mut() {
+                node.change_request_rules(Some(vec![drop_frame_rule.clone()]));
+            }
+
+            // The statement whose request is dropped times out without delaying the next one
+            let mut dropped = Query::new("SELECT host_id FROM system.local WHERE key = 'dropped'");
+            dropped.set_request_timeout(Some(Duration::from_millis(200)));
+            let mut pipeline = handle.pipeline();
+            pipeline
+                .append_query(dropped)
+                .append_prepared(&prepared, ("local",))
+                .unwrap();
+            let results = pipeline.run().await;
+            assert_matches!(
+                results[0],
+                Err(QueryError::TimeoutError {
+                    error: TimeoutError::Request(_),
+                    ..
+                })
+            );
+            assert!(results[1].is_ok());
+
+            running_proxy
+        },
+    )
+    .await;
+
+    match res {
+        Ok(()) => (),
+        Err(ProxyError::Worker(WorkerError::DriverDisconnected(_))) => (),
+        Err(err) => panic!("{}", err),
+    }
+}
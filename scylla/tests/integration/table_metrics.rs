@@ -0,0 +1,70 @@
+use crate::utils::{setup_tracing, test_with_3_node_cluster};
+use assert_matches::assert_matches;
+use scylla::test_utils::unique_keyspace_name;
+use scylla::transport::errors::{QueryError, TimeoutError};
+use scylla::transport::session::Session;
+use scylla::SessionBuilder;
+use scylla_proxy::{
+    Condition, ProxyError, Reaction, RequestOpcode, RequestReaction, RequestRule, ShardAwareness,
+    WorkerError,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+#[ntest::timeout(30000)]
+#[cfg(not(scylla_cloud_tests))]
+async fn client_side_timeouts_are_counted_in_table_metrics() {
+    setup_tracing();
+    let res = test_with_3_node_cluster(
+        ShardAwareness::QueryNode,
+        |proxy_uris, translation_map, mut running_proxy| async move {
+            let session: Session = SessionBuilder::new()
+                .known_node(proxy_uris[0].as_str())
+                .address_translator(Arc::new(translation_map))
+                .build()
+                .await
+                .unwrap();
+
+            let ks = unique_keyspace_name();
+            session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 3}}", ks), &[]).await.unwrap();
+            session
+                .query(
+                    format!("CREATE TABLE IF NOT EXISTS {}.t (a int PRIMARY KEY)", ks),
+                    &[],
+                )
+                .await
+                .unwrap();
+            let mut prepared = session
+                .prepare(format!("SELECT a FROM {}.t WHERE a = ?", ks))
+                .await
+                .unwrap();
+            prepared.set_request_timeout(Some(Duration::from_millis(200)));
+
+            let drop_frame_rule = RequestRule(
+                Condition::RequestOpcode(RequestOpcode::Execute),
+                RequestReaction::drop_frame(),
+            );
+            for node in running_proxy.running_nodes.iter_mut() {
+                node.change_request_rules(Some(vec![drop_frame_rule.clone()]));
+            }
+
+            let result = session.execute(&prepared, (1,)).await;
+            assert_matches!(result, Err(QueryError::TimeoutError { error: TimeoutError::Request(_), .. }));
+
+            let table_metrics = session.get_metrics().get_table_metrics(&ks, "t").unwrap();
+            assert_eq!(table_metrics.get_requests_num(), 1);
+            assert_eq!(table_metrics.get_errors_num(), 1);
+            assert_eq!(table_metrics.get_timeouts_num(), 1);
+
+            running_proxy
+        },
+    )
+    .await;
+
+    match res {
+        Ok(()) => (),
+        Err(ProxyError::Worker(WorkerError::DriverDisconnected(_))) => (),
+        Err(err) => panic!("{}", err),
+    }
+}